@@ -0,0 +1,12 @@
+-- Multi-domain support: several short domains served from one instance
+
+CREATE TABLE IF NOT EXISTS domains (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    hostname    TEXT     NOT NULL UNIQUE,
+    base_url    TEXT     NOT NULL,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+-- Links without a domain belong to the primary domain (BASE_URL)
+ALTER TABLE links ADD COLUMN domain_id INTEGER REFERENCES domains(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS idx_links_domain_id ON links(domain_id);
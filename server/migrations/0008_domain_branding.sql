@@ -0,0 +1,7 @@
+-- Per-domain root redirect, 404 handling, and branding.
+-- NULL falls back to the instance-wide defaults.
+
+ALTER TABLE domains ADD COLUMN root_redirect_url TEXT;
+ALTER TABLE domains ADD COLUMN not_found_url     TEXT;
+ALTER TABLE domains ADD COLUMN logo_url          TEXT;
+ALTER TABLE domains ADD COLUMN brand_color       TEXT;
@@ -0,0 +1,4 @@
+-- Optional share token for the public per-link stats page (/stats/:code?token=…)
+
+ALTER TABLE links ADD COLUMN stats_token TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_links_stats_token ON links(stats_token);
@@ -0,0 +1,13 @@
+-- Personal API tokens for the JSON API (Authorization: Bearer <token>).
+-- Only a SHA-256 hash of each token is stored.
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id            INTEGER  PRIMARY KEY AUTOINCREMENT,
+    user_id       INTEGER  NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name          TEXT     NOT NULL,
+    token_hash    TEXT     NOT NULL UNIQUE,
+    created_at    TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    last_used_at  TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user_id ON api_tokens(user_id);
@@ -0,0 +1,12 @@
+-- REST hook subscriptions: callback URLs notified when events happen
+-- (link.created, click.recorded, link.expired).
+
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    user_id     INTEGER  NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event       TEXT     NOT NULL,
+    target_url  TEXT     NOT NULL,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_subscriptions_user_id ON webhook_subscriptions(user_id);
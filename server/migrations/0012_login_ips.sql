@@ -0,0 +1,10 @@
+-- IP addresses each user has signed in from, so a login from a new address
+-- can trigger an email notice.
+
+CREATE TABLE IF NOT EXISTS user_login_ips (
+    user_id        INTEGER  NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    ip             TEXT     NOT NULL,
+    first_seen_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    last_seen_at   TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    PRIMARY KEY (user_id, ip)
+);
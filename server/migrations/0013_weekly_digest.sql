@@ -0,0 +1,12 @@
+-- Weekly analytics digest: recipients who unsubscribed, and which weeks have
+-- already been sent (so a restart never sends the same digest twice).
+
+CREATE TABLE IF NOT EXISTS digest_opt_outs (
+    email          TEXT  PRIMARY KEY,
+    opted_out_at   TEXT  NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS digest_runs (
+    week_start  TEXT  PRIMARY KEY,
+    sent_at     TEXT  NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
@@ -0,0 +1,11 @@
+-- Per-link click thresholds. When a link's click count reaches a threshold,
+-- chat webhooks and the link's owner are alerted once and the row is marked
+-- triggered.
+
+CREATE TABLE IF NOT EXISTS link_click_alerts (
+    link_id       INTEGER  NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+    threshold     INTEGER  NOT NULL,
+    created_at    TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    triggered_at  TEXT,
+    PRIMARY KEY (link_id, threshold)
+);
@@ -0,0 +1,26 @@
+-- Extra short codes for a link. An alias redirects to its link's destination
+-- on the link's domain, and its clicks are recorded against the link.
+
+CREATE TABLE IF NOT EXISTS link_aliases (
+    short_code  TEXT     PRIMARY KEY,
+    link_id     INTEGER  NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_link_aliases_link_id ON link_aliases(link_id);
+
+-- Short codes and aliases share one namespace. These raise the same UNIQUE
+-- error a duplicate short code does, so callers need no special handling.
+CREATE TRIGGER IF NOT EXISTS link_aliases_unique_code
+BEFORE INSERT ON link_aliases
+WHEN EXISTS (SELECT 1 FROM links WHERE short_code = NEW.short_code)
+BEGIN
+    SELECT RAISE(ABORT, 'UNIQUE constraint failed: links.short_code');
+END;
+
+CREATE TRIGGER IF NOT EXISTS links_unique_code
+BEFORE INSERT ON links
+WHEN EXISTS (SELECT 1 FROM link_aliases WHERE short_code = NEW.short_code)
+BEGIN
+    SELECT RAISE(ABORT, 'UNIQUE constraint failed: link_aliases.short_code');
+END;
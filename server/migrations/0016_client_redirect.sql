@@ -0,0 +1,9 @@
+-- Optional client-side redirect: instead of a 302, serve a small page that
+-- reports screen size, language, and time zone before sending the visitor on.
+
+ALTER TABLE links ADD COLUMN client_redirect INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE clicks ADD COLUMN screen_width INTEGER;
+ALTER TABLE clicks ADD COLUMN screen_height INTEGER;
+ALTER TABLE clicks ADD COLUMN language TEXT;
+ALTER TABLE clicks ADD COLUMN timezone TEXT;
@@ -0,0 +1,6 @@
+-- Tracking-pixel loads (e.g. email opens) are logged alongside clicks.
+-- Click counts and breakdowns only include 'click' rows.
+
+ALTER TABLE clicks ADD COLUMN event_type TEXT NOT NULL DEFAULT 'click';
+
+CREATE INDEX IF NOT EXISTS idx_clicks_link_event ON clicks(link_id, event_type);
@@ -0,0 +1,16 @@
+-- Click IDs and conversions. Every redirect gets a random click ID, which the
+-- destination can receive via the {{click_id}} URL macro and report back
+-- through POST /api/v1/conversions.
+
+ALTER TABLE clicks ADD COLUMN click_id TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_clicks_click_id ON clicks(click_id);
+
+CREATE TABLE IF NOT EXISTS conversions (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    click_id    TEXT     NOT NULL REFERENCES clicks(click_id) ON DELETE CASCADE,
+    name        TEXT     NOT NULL,
+    value       REAL,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversions_click_id ON conversions(click_id);
@@ -0,0 +1,5 @@
+-- Per-link country allow/deny lists, stored as normalised comma-separated
+-- ISO 3166-1 alpha-2 codes (e.g. "US,CA"). NULL means no list.
+
+ALTER TABLE links ADD COLUMN allow_countries TEXT;
+ALTER TABLE links ADD COLUMN deny_countries TEXT;
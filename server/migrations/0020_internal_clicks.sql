@@ -0,0 +1,4 @@
+-- Clicks from INTERNAL_IPS (e.g. the office network) are kept but flagged,
+-- so testing doesn't pollute campaign numbers.
+
+ALTER TABLE clicks ADD COLUMN internal INTEGER NOT NULL DEFAULT 0;
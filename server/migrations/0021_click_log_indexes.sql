@@ -0,0 +1,7 @@
+-- Indexes for browsing a link's individual clicks (/admin/links/:id/clicks)
+-- by country, browser, or date. SQLite appends the rowid to every index, so
+-- pages filtered by country or browser come back in click order unsorted.
+
+CREATE INDEX IF NOT EXISTS idx_clicks_link_country ON clicks(link_id, country);
+CREATE INDEX IF NOT EXISTS idx_clicks_link_browser ON clicks(link_id, browser);
+CREATE INDEX IF NOT EXISTS idx_clicks_link_time    ON clicks(link_id, clicked_at);
@@ -0,0 +1,5 @@
+-- ISO country code and continent code of each click, so geo breakdowns
+-- group on stable codes rather than free-form country names.
+
+ALTER TABLE clicks ADD COLUMN country_code TEXT;
+ALTER TABLE clicks ADD COLUMN continent TEXT;
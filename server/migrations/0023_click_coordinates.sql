@@ -0,0 +1,4 @@
+-- Approximate visitor position from the geo lookup, for the click map.
+
+ALTER TABLE clicks ADD COLUMN latitude REAL;
+ALTER TABLE clicks ADD COLUMN longitude REAL;
@@ -0,0 +1,7 @@
+-- Network a click came from, per the geo lookup. Clicks from hosting and
+-- datacenter networks are mostly scanners and bots, so they are flagged and
+-- can be left out of analytics.
+
+ALTER TABLE clicks ADD COLUMN asn INTEGER;
+ALTER TABLE clicks ADD COLUMN isp TEXT;
+ALTER TABLE clicks ADD COLUMN datacenter INTEGER NOT NULL DEFAULT 0;
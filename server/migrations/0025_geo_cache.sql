@@ -0,0 +1,22 @@
+-- Resolved IP geolocation results, so a restart doesn't throw away lookups
+-- and re-hit the rate-limited geolocation service. `found = 0` records an IP
+-- the provider had no data for. Rows older than GEO_CACHE_TTL_DAYS are
+-- deleted at startup.
+
+CREATE TABLE IF NOT EXISTS geo_cache (
+    ip              TEXT     PRIMARY KEY,
+    found           INTEGER  NOT NULL DEFAULT 1,
+    country         TEXT     NOT NULL DEFAULT '',
+    country_code    TEXT     NOT NULL DEFAULT '',
+    continent_code  TEXT     NOT NULL DEFAULT '',
+    region          TEXT     NOT NULL DEFAULT '',
+    city            TEXT     NOT NULL DEFAULT '',
+    latitude        REAL,
+    longitude       REAL,
+    asn             INTEGER,
+    isp             TEXT     NOT NULL DEFAULT '',
+    datacenter      INTEGER  NOT NULL DEFAULT 0,
+    resolved_at     TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_geo_cache_resolved_at ON geo_cache(resolved_at);
@@ -0,0 +1,27 @@
+-- Workspaces: separate tenants (e.g. sister companies) sharing one instance.
+-- Each has its own users, links, and links pages; API tokens and REST hooks
+-- follow their user.
+
+CREATE TABLE IF NOT EXISTS workspaces (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    name        TEXT     NOT NULL UNIQUE,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+-- Everything created before workspaces belongs to the first one
+INSERT OR IGNORE INTO workspaces (id, name) VALUES (1, 'Default');
+
+-- SQLite can't add a REFERENCES column with a non-NULL default, so these are
+-- plain columns; a workspace can only be deleted once nothing points at it
+ALTER TABLE users ADD COLUMN workspace_id INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX IF NOT EXISTS idx_users_workspace_id ON users(workspace_id);
+
+ALTER TABLE links ADD COLUMN workspace_id INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX IF NOT EXISTS idx_links_workspace_id ON links(workspace_id);
+
+ALTER TABLE bio_pages ADD COLUMN workspace_id INTEGER NOT NULL DEFAULT 1;
+CREATE INDEX IF NOT EXISTS idx_bio_pages_workspace_id ON bio_pages(workspace_id);
+
+-- A domain owned by a workspace only serves that workspace's links, and
+-- registrations on it join the workspace. Unowned domains are shared.
+ALTER TABLE domains ADD COLUMN workspace_id INTEGER REFERENCES workspaces(id) ON DELETE SET NULL;
@@ -0,0 +1,5 @@
+-- Per-user quota overrides. NULL falls back to QUOTA_MAX_LINKS /
+-- QUOTA_API_REQUESTS_PER_HOUR; 0 means unlimited.
+
+ALTER TABLE users ADD COLUMN max_links INTEGER;
+ALTER TABLE users ADD COLUMN api_requests_per_hour INTEGER;
@@ -0,0 +1,7 @@
+-- Per-link social preview overrides, served to link unfurlers (Slack,
+-- Twitter/X, Facebook, ...) instead of the destination's own metadata.
+-- NULL falls back to the link's title and description.
+
+ALTER TABLE links ADD COLUMN social_title TEXT;
+ALTER TABLE links ADD COLUMN social_description TEXT;
+ALTER TABLE links ADD COLUMN social_image_url TEXT;
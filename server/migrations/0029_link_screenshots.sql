@@ -0,0 +1,9 @@
+-- Thumbnail of each link's destination from the SCREENSHOT_URL service,
+-- shown in the link list, the dashboard and on the link's analytics page.
+
+CREATE TABLE IF NOT EXISTS link_screenshots (
+    link_id       INTEGER  PRIMARY KEY REFERENCES links(id) ON DELETE CASCADE,
+    content_type  TEXT     NOT NULL,
+    image         BLOB     NOT NULL,
+    captured_at   TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
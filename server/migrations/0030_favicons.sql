@@ -0,0 +1,10 @@
+-- Favicons of destination hosts, shown next to links in the admin panel.
+-- `image` is NULL when the host has none, so it isn't asked again until
+-- the entry is refreshed.
+
+CREATE TABLE IF NOT EXISTS favicons (
+    host          TEXT     PRIMARY KEY,
+    content_type  TEXT,
+    image         BLOB,
+    fetched_at    TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
@@ -0,0 +1,4 @@
+-- Archived links are hidden from the default short-links list but keep
+-- resolving (unless ARCHIVED_LINKS_REDIRECT=false). NULL = not archived.
+
+ALTER TABLE links ADD COLUMN archived_at TEXT;
@@ -0,0 +1,5 @@
+-- Deleting a link moves it to the trash: it stops resolving and leaves the
+-- lists, and is purged for good after 30 days unless restored first.
+-- NULL = not deleted.
+
+ALTER TABLE links ADD COLUMN deleted_at TEXT;
@@ -0,0 +1,10 @@
+-- When each link was last clicked, kept up to date by the click logger so
+-- the short links list can sort by it without scanning clicks. Internal
+-- clicks and impressions don't count. NULL = never clicked.
+
+ALTER TABLE links ADD COLUMN last_clicked_at TEXT;
+
+UPDATE links SET last_clicked_at = (
+    SELECT MAX(clicked_at) FROM clicks
+    WHERE clicks.link_id = links.id AND event_type = 'click' AND internal = 0
+);
@@ -0,0 +1,10 @@
+-- When each link was first clicked, set once by the click logger alongside
+-- last_clicked_at. Internal clicks and impressions don't count. NULL = never
+-- clicked.
+
+ALTER TABLE links ADD COLUMN first_clicked_at TEXT;
+
+UPDATE links SET first_clicked_at = (
+    SELECT MIN(clicked_at) FROM clicks
+    WHERE clicks.link_id = links.id AND event_type = 'click' AND internal = 0
+);
@@ -0,0 +1,5 @@
+-- Clicks a link had collected in the service it was imported from (Bitly,
+-- YOURLS, ...), counted in its totals alongside the clicks recorded here.
+-- 0 for links created in Linkly.
+
+ALTER TABLE links ADD COLUMN imported_clicks INTEGER NOT NULL DEFAULT 0;
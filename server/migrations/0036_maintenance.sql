@@ -0,0 +1,12 @@
+-- Maintenance mode, switched on by an instance admin: the row exists while
+-- it's on. Writes by anyone but instance admins are refused, and short
+-- links either serve a maintenance page ('page') or keep redirecting
+-- without recording clicks ('redirect').
+
+CREATE TABLE IF NOT EXISTS maintenance (
+    id          INTEGER  PRIMARY KEY CHECK (id = 1),
+    mode        TEXT     NOT NULL CHECK (mode IN ('page', 'redirect')),
+    message     TEXT,
+    started_by  INTEGER  REFERENCES users(id) ON DELETE SET NULL,
+    started_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
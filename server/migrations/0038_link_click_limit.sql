@@ -0,0 +1,6 @@
+-- Optional cap on a link's clicks per minute, to spare a fragile
+-- destination. NULL means no cap. Visitors over the cap get a 429, or with
+-- click_limit_page set a page that waits and retries on its own.
+
+ALTER TABLE links ADD COLUMN click_limit_per_minute INTEGER;
+ALTER TABLE links ADD COLUMN click_limit_page INTEGER NOT NULL DEFAULT 0;
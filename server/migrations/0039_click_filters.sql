@@ -0,0 +1,13 @@
+-- User-agent patterns whose requests redirect as usual but are never
+-- recorded: uptime monitors, mail security scanners and the like. Edited by
+-- instance admins at /admin/settings/filters.
+
+CREATE TABLE IF NOT EXISTS click_filters (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    pattern     TEXT     NOT NULL,
+    -- 1 when `pattern` is a regular expression, 0 for a plain substring
+    is_regex    INTEGER  NOT NULL DEFAULT 0,
+    created_by  INTEGER  REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    UNIQUE (pattern, is_regex)
+);
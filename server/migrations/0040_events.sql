@@ -0,0 +1,9 @@
+-- Clicks, tracking-pixel impressions, and link preview page views are all
+-- rows of one table, told apart by event_type, so every kind of event shares
+-- the same logging, geolocation, and analytics queries. Renaming keeps the
+-- rows, indexes (under their old names), and the conversions reference.
+--
+-- event_type is one of models::EVENT_*: 'click', 'impression', 'preview'.
+-- clicked_at is when the event happened, whatever its type.
+
+ALTER TABLE clicks RENAME TO events;
@@ -0,0 +1,5 @@
+-- How a visitor reached a link, when its URL says so: 'qr' for the
+-- `?src=qr` that the link's QR code adds. NULL otherwise; analytics then
+-- tells social, other referring sites, and direct visits apart by referer.
+
+ALTER TABLE events ADD COLUMN source TEXT;
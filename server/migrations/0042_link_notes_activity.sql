@@ -0,0 +1,18 @@
+-- Free-form notes on a link for its team, and a timeline of changes made to
+-- it, shown on the link's page at /admin/links/:id. Creation and click
+-- milestones come from the link and its events rather than this table.
+
+ALTER TABLE links ADD COLUMN notes TEXT;
+
+CREATE TABLE IF NOT EXISTS link_activity (
+    id          INTEGER  PRIMARY KEY AUTOINCREMENT,
+    link_id     INTEGER  NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+    -- What happened: edited, activated, deactivated, archived, ...
+    kind        TEXT     NOT NULL,
+    -- Specifics, e.g. which fields were edited
+    detail      TEXT,
+    user_id     INTEGER  REFERENCES users(id) ON DELETE SET NULL,
+    created_at  TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_link_activity_link ON link_activity(link_id, created_at);
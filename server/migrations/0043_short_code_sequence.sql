@@ -0,0 +1,9 @@
+-- Counter behind SHORT_CODE_STRATEGY=sequential: the number the next
+-- generated code is made from. Always exactly one row.
+
+CREATE TABLE IF NOT EXISTS short_code_sequence (
+    id         INTEGER PRIMARY KEY CHECK (id = 1),
+    next_value INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO short_code_sequence (id, next_value) VALUES (1, 0);
@@ -0,0 +1,7 @@
+-- Case-insensitive fallback lookups for CASE_INSENSITIVE_CODES.
+
+CREATE INDEX IF NOT EXISTS idx_links_short_code_nocase
+    ON links (short_code COLLATE NOCASE);
+
+CREATE INDEX IF NOT EXISTS idx_link_aliases_short_code_nocase
+    ON link_aliases (short_code COLLATE NOCASE);
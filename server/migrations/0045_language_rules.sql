@@ -0,0 +1,9 @@
+-- Per-link destinations by the visitor's preferred language, one rule per
+-- line as "<language tag> <url>", e.g. "de https://example.com/de/". Tried
+-- against the Accept-Language header before the link's own destination.
+
+ALTER TABLE links ADD COLUMN language_rules TEXT;
+
+-- The language of the rule a click was sent by; NULL when it went to the
+-- link's own destination.
+ALTER TABLE events ADD COLUMN matched_language TEXT;
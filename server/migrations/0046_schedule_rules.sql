@@ -0,0 +1,6 @@
+-- Per-link destinations by local time (DISPLAY_TIMEZONE), one rule per line
+-- as "<days> <start>-<end> <url>", e.g. "mon-fri 9-17 https://example.com/book".
+-- The first rule covering the moment of a visit wins, before language rules
+-- and the link's own destination.
+
+ALTER TABLE links ADD COLUMN schedule_rules TEXT;
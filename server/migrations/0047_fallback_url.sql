@@ -0,0 +1,6 @@
+-- Where a link sends visitors while its destination is down, and since when
+-- the health checker (FALLBACK_CHECK_MINUTES) has found it down. NULL
+-- `destination_down_since` means the destination is up, or unchecked.
+
+ALTER TABLE links ADD COLUMN fallback_url TEXT;
+ALTER TABLE links ADD COLUMN destination_down_since TEXT;
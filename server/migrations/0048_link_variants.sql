@@ -0,0 +1,10 @@
+-- Destinations a link's visitors are split between for A/B tests, one per
+-- line as "<weight> <url>", e.g. "70 https://example.com/a". With
+-- `sticky_variants`, a visitor's variant comes from a hash of their IP and
+-- User-Agent instead of a random draw, so they keep getting the same one.
+
+ALTER TABLE links ADD COLUMN variants TEXT;
+ALTER TABLE links ADD COLUMN sticky_variants INTEGER NOT NULL DEFAULT 0;
+
+-- The variant URL a click was sent to; NULL when the link had none.
+ALTER TABLE events ADD COLUMN variant TEXT;
@@ -0,0 +1,26 @@
+-- A keyed hash of the visitor's IP and User-Agent, the same on every link,
+-- so a visitor's clicks can be followed from one link to another without
+-- comparing raw IPs. NULL for anonymous visitors and clicks from before it.
+ALTER TABLE events ADD COLUMN visitor_hash TEXT;
+
+-- Funnels: visitors who click the first step's link and then each next
+-- step's link within `window_hours` of the step before.
+CREATE TABLE IF NOT EXISTS funnels (
+    id           INTEGER  PRIMARY KEY AUTOINCREMENT,
+    workspace_id INTEGER  NOT NULL DEFAULT 1,
+    user_id      INTEGER  REFERENCES users(id) ON DELETE SET NULL,
+    name         TEXT     NOT NULL,
+    window_hours INTEGER  NOT NULL,
+    created_at   TEXT     NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS funnel_steps (
+    funnel_id INTEGER NOT NULL REFERENCES funnels(id) ON DELETE CASCADE,
+    -- From 0, in the order visitors are expected to click
+    position  INTEGER NOT NULL,
+    link_id   INTEGER NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+    PRIMARY KEY (funnel_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_funnels_workspace_id ON funnels(workspace_id);
+CREATE INDEX IF NOT EXISTS idx_events_link_visitor ON events(link_id, visitor_hash);
@@ -0,0 +1,30 @@
+-- Daily totals of events pruned under CLICK_RETENTION_DAYS, so counts, daily
+-- charts and unique-visitor estimates outlive the raw rows. A link and day
+-- may have several rows for the same kind of event (e.g. after links are
+-- merged); readers add them up.
+CREATE TABLE IF NOT EXISTS event_rollups (
+    link_id    INTEGER NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+    -- UTC day, YYYY-MM-DD
+    day        TEXT    NOT NULL,
+    event_type TEXT    NOT NULL,
+    internal   INTEGER NOT NULL,
+    datacenter INTEGER NOT NULL,
+    events     INTEGER NOT NULL,
+    -- HyperLogLog sketch of the events' IP addresses (see sketch.rs)
+    visitors   BLOB    NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_rollups_link_day ON event_rollups(link_id, day);
+
+-- Pruned clicks (not internal) by country, referrer, browser, and the other
+-- attributes reports break clicks down by. `value` is NULL where unknown.
+CREATE TABLE IF NOT EXISTS click_rollup_values (
+    link_id   INTEGER NOT NULL REFERENCES links(id) ON DELETE CASCADE,
+    day       TEXT    NOT NULL,
+    dimension TEXT    NOT NULL,
+    value     TEXT,
+    clicks    INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_click_rollup_values_link_dim
+    ON click_rollup_values(link_id, dimension, day);
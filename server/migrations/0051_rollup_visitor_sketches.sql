@@ -0,0 +1,11 @@
+-- Visitor sketches of each finished UTC day's clicks, kept in event_rollups
+-- as click rows with no events of their own (events = 0), so unique visitors
+-- of large links can be estimated without scanning every click. Days are
+-- sketched oldest first, before any are pruned: every day up to the latest
+-- one with click rows has its sketches. From here on `visitors` sketches
+-- events' visitor hashes rather than their IP addresses.
+CREATE INDEX IF NOT EXISTS idx_event_rollups_type_day ON event_rollups(event_type, day);
+
+-- Clicks from before visitor hashes, hashed in batches by the sketching job
+CREATE INDEX IF NOT EXISTS idx_events_unhashed ON events(id)
+    WHERE visitor_hash IS NULL AND ip_address IS NOT NULL;
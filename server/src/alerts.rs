@@ -0,0 +1,144 @@
+use crate::{db_alerts, db_users, hooks, models::Link, notify, AppState};
+use askama::Template;
+use dashmap::DashMap;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "email/click_alert.txt")]
+struct ClickAlertEmail<'a> {
+    app_title: &'a str,
+    base_url: &'a str,
+    display_name: &'a str,
+    label: &'a str,
+    short_url: &'a str,
+    threshold: i64,
+    link_id: i64,
+}
+
+/// Per-link click thresholds that have not been reached yet.
+///
+/// Kept in memory so the click worker only counts a link's clicks when it
+/// has a pending threshold; links without alerts cost a single map lookup.
+#[derive(Clone, Debug, Default)]
+pub struct ClickAlerts {
+    pending: Arc<DashMap<i64, Vec<i64>>>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl ClickAlerts {
+    pub async fn load(db: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let alerts = Self::default();
+        for (link_id, threshold) in db_alerts::get_pending_alerts(db).await? {
+            alerts.add(link_id, threshold);
+        }
+        Ok(alerts)
+    }
+
+    /// Whether `link_id` has any threshold still to reach.
+    pub fn has_pending(&self, link_id: i64) -> bool {
+        self.pending.contains_key(&link_id)
+    }
+
+    pub fn add(&self, link_id: i64, threshold: i64) {
+        let mut thresholds = self.pending.entry(link_id).or_default();
+        if !thresholds.contains(&threshold) {
+            thresholds.push(threshold);
+        }
+    }
+
+    pub fn remove(&self, link_id: i64, threshold: i64) {
+        self.pending.remove_if_mut(&link_id, |_, thresholds| {
+            thresholds.retain(|t| *t != threshold);
+            thresholds.is_empty()
+        });
+    }
+
+    /// Drop every pending threshold of a link that no longer exists.
+    pub fn forget(&self, link_id: i64) {
+        self.pending.remove(&link_id);
+    }
+
+    /// Fire every pending threshold `link` has reached now that it has
+    /// `clicks` clicks. Called from the click-logging worker.
+    pub async fn check(&self, state: &AppState, link: &Link, clicks: i64) {
+        let reached: Vec<i64> = match self.pending.get(&link.id) {
+            Some(thresholds) => thresholds
+                .iter()
+                .copied()
+                .filter(|t| *t <= clicks)
+                .collect(),
+            None => return,
+        };
+
+        for threshold in reached {
+            // Drop it from memory first so concurrent clicks don't also fire it.
+            self.remove(link.id, threshold);
+            match db_alerts::mark_triggered(&state.db, link.id, threshold).await {
+                Ok(true) => alert(state, link, threshold).await,
+                Ok(false) => {}
+                Err(e) => tracing::error!(
+                    "Failed to mark click alert {} on link {}: {:?}",
+                    threshold,
+                    link.id,
+                    e
+                ),
+            }
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Tell chat webhooks, REST hook subscribers, and the link's owner that
+/// `threshold` was reached.
+async fn alert(state: &AppState, link: &Link, threshold: i64) {
+    let short_url = state.short_url(link.domain_id, &link.short_code);
+    state.hooks.emit(
+        hooks::CLICK_THRESHOLD,
+        link,
+        serde_json::json!({
+            "link": hooks::link_data(link, &short_url),
+            "threshold": threshold,
+        }),
+    );
+    state.notifier.send(
+        notify::Event::ClickMilestone,
+        notify::LinkVars {
+            code: link.short_code.clone(),
+            short_url: short_url.clone(),
+            url: link.original_url.clone(),
+            title: link.title.clone(),
+            clicks: threshold,
+        },
+    );
+
+    let Some(owner_id) = link.user_id else {
+        return;
+    };
+    let owner = match db_users::get_user_by_id(&state.db, owner_id).await {
+        Ok(Some(u)) => u,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::error!("Failed to load owner of link {}: {:?}", link.id, e);
+            return;
+        }
+    };
+    let label = link.title.as_deref().unwrap_or(&link.short_code);
+    state.mailer.send(
+        &owner.email,
+        &format!("{label} reached {threshold} clicks"),
+        &ClickAlertEmail {
+            app_title: &state.config.app_title,
+            base_url: &state.config.base_url,
+            display_name: &owner.display_name,
+            label,
+            short_url: &short_url,
+            threshold,
+            link_id: link.id,
+        },
+    );
+}
@@ -0,0 +1,131 @@
+use crate::config::{AnalyticsProvider, AppConfig};
+use std::sync::Arc;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// One short-link click, as forwarded to the analytics service.
+#[derive(Debug, Clone)]
+pub struct Pageview {
+    /// Full short URL that was visited, e.g. "https://go.example.com/abc".
+    pub url: String,
+    pub referrer: Option<String>,
+    /// The visitor's User-Agent and IP, which Plausible and Umami use to
+    /// derive browser, device, location, and unique-visitor counts.
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+/// Forwards clicks as pageviews to a Plausible or Umami instance.
+///
+/// Cheap to clone; a forwarder without `ANALYTICS_PROVIDER` does nothing.
+#[derive(Clone)]
+pub struct Forwarder {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    provider: Option<AnalyticsProvider>,
+    endpoint: String,
+    site_id: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Forwarder {
+    pub fn new(config: &AppConfig, client: reqwest::Client) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                provider: config.analytics_provider,
+                endpoint: config.analytics_endpoint.clone(),
+                site_id: config.analytics_site_id.clone(),
+            }),
+        }
+    }
+
+    /// Whether an analytics provider is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.provider.is_some()
+    }
+
+    /// Forward a pageview in the background. Best effort: failures are
+    /// logged and never retried, since the click is already in our database.
+    pub fn send(&self, pageview: Pageview) {
+        if !self.is_enabled() {
+            return;
+        }
+        let forwarder = self.clone();
+        tokio::spawn(async move { forwarder.deliver(pageview).await });
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl Forwarder {
+    async fn deliver(&self, pageview: Pageview) {
+        let Some(provider) = self.inner.provider else {
+            return;
+        };
+
+        let (url, body) = match provider {
+            // https://plausible.io/docs/events-api
+            AnalyticsProvider::Plausible => (
+                format!("{}/api/event", self.inner.endpoint),
+                serde_json::json!({
+                    "name": "pageview",
+                    "domain": self.inner.site_id,
+                    "url": pageview.url,
+                    "referrer": pageview.referrer,
+                }),
+            ),
+            // https://umami.is/docs/api/sending-stats
+            AnalyticsProvider::Umami => {
+                let (hostname, path) = split_url(&pageview.url);
+                (
+                    format!("{}/api/send", self.inner.endpoint),
+                    serde_json::json!({
+                        "type": "event",
+                        "payload": {
+                            "website": self.inner.site_id,
+                            "hostname": hostname,
+                            "url": path,
+                            "referrer": pageview.referrer.as_deref().unwrap_or(""),
+                            "language": "",
+                            "screen": "",
+                            "title": "",
+                        },
+                    }),
+                )
+            }
+        };
+
+        let mut req = self.inner.client.post(&url).json(&body);
+        if let Some(ua) = &pageview.user_agent {
+            req = req.header(reqwest::header::USER_AGENT, ua);
+        }
+        if let Some(ip) = &pageview.ip {
+            req = req.header("x-forwarded-for", ip);
+        }
+
+        match req.send().await {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => tracing::warn!(
+                "{} rejected pageview for {} with {}",
+                provider.as_str(),
+                pageview.url,
+                resp.status()
+            ),
+            Err(e) => tracing::warn!("Failed to forward pageview to {}: {}", provider.as_str(), e),
+        }
+    }
+}
+
+/// Split "https://host/path" into ("host", "/path").
+fn split_url(url: &str) -> (&str, &str) {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    }
+}
@@ -1,4 +1,11 @@
-use crate::AppState;
+use crate::{
+    db::Scope,
+    db_users,
+    error::AppError,
+    models::{Domain, User, DEFAULT_WORKSPACE},
+    quota, AppState,
+};
+use anyhow::Context;
 use async_trait::async_trait;
 use axum::{
     extract::{FromRef, FromRequestParts},
@@ -8,6 +15,7 @@ use axum::{
 use axum_extra::extract::CookieJar;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::sync::Arc;
 
 // ── JWT Claims ────────────────────────────────────────────────────────────
@@ -22,6 +30,12 @@ pub struct Claims {
     pub iat: usize,   // issued at
     #[serde(default)] // backward compat with tokens issued before this field existed
     pub fpc: bool, // force password change
+    #[serde(default = "default_workspace")]
+    pub ws: i64, // workspace ID
+}
+
+fn default_workspace() -> i64 {
+    DEFAULT_WORKSPACE
 }
 
 /// Create a signed JWT for the given user.
@@ -32,6 +46,7 @@ pub fn create_jwt(
     secret: &str,
     duration_hours: u64,
     force_password_change: bool,
+    workspace_id: i64,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let now = chrono::Utc::now();
     let exp = (now + chrono::Duration::hours(duration_hours as i64)).timestamp() as usize;
@@ -42,6 +57,7 @@ pub fn create_jwt(
         exp,
         iat: now.timestamp() as usize,
         fpc: force_password_change,
+        ws: workspace_id,
     };
     encode(
         &Header::default(),
@@ -70,12 +86,37 @@ pub struct AuthUser {
     pub email: String,
     pub role: String,
     pub force_password_change: bool,
+    pub workspace_id: i64,
 }
 
 impl AuthUser {
     pub fn is_admin(&self) -> bool {
         self.role == "admin"
     }
+
+    /// Admins of the default workspace manage the whole instance.
+    pub fn is_instance_admin(&self) -> bool {
+        self.is_admin() && self.workspace_id == DEFAULT_WORKSPACE
+    }
+
+    /// Rows this user sees in listings: their workspace's for admins, their
+    /// own otherwise.
+    pub fn scope(&self) -> Scope {
+        Scope {
+            workspace_id: Some(self.workspace_id),
+            user_id: (!self.is_admin()).then_some(self.user_id),
+        }
+    }
+
+    /// Whether this user may manage a row in `workspace_id` owned by `owner`.
+    pub fn can_manage(&self, workspace_id: i64, owner: Option<i64>) -> bool {
+        workspace_id == self.workspace_id && (self.is_admin() || owner == Some(self.user_id))
+    }
+
+    /// Whether links in this user's workspace may be served from `domain`.
+    pub fn can_use(&self, domain: &Domain) -> bool {
+        domain.serves(self.workspace_id)
+    }
 }
 
 #[async_trait]
@@ -108,6 +149,7 @@ where
                     email: c.email,
                     role: c.role,
                     force_password_change: c.fpc,
+                    workspace_id: c.ws,
                 })
             }
             None => Err(Redirect::to("/admin/login")),
@@ -122,6 +164,25 @@ where
 pub struct AdminUser {
     pub user_id: i64,
     pub email: String,
+    pub workspace_id: i64,
+}
+
+impl AdminUser {
+    /// Admins of the default workspace manage the whole instance.
+    pub fn is_instance_admin(&self) -> bool {
+        self.workspace_id == DEFAULT_WORKSPACE
+    }
+
+    /// The workspace whose users this admin manages; `None` for instance
+    /// admins, who manage every workspace's users.
+    pub fn user_scope(&self) -> Option<i64> {
+        (!self.is_instance_admin()).then_some(self.workspace_id)
+    }
+
+    /// Whether this admin manages `user`'s account.
+    pub fn manages(&self, user: &User) -> bool {
+        self.is_instance_admin() || user.workspace_id == self.workspace_id
+    }
 }
 
 #[async_trait]
@@ -138,9 +199,220 @@ where
             Ok(AdminUser {
                 user_id: auth.user_id,
                 email: auth.email,
+                workspace_id: auth.workspace_id,
             })
         } else {
             Err(Redirect::to("/admin/dashboard"))
         }
     }
 }
+
+// ── InstanceAdmin extractor ──────────────────────────────────────────────
+
+/// Extractor for instance-wide settings (workspaces and domains): requires an
+/// admin of the default workspace. Redirects anyone else to dashboard.
+#[allow(dead_code)]
+pub struct InstanceAdmin(pub AdminUser);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for InstanceAdmin
+where
+    S: Send + Sync,
+    Arc<AppState>: FromRef<S>,
+{
+    type Rejection = Redirect;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let admin = AdminUser::from_request_parts(parts, state).await?;
+        if admin.is_instance_admin() {
+            Ok(InstanceAdmin(admin))
+        } else {
+            Err(Redirect::to("/admin/dashboard"))
+        }
+    }
+}
+
+// ── API tokens ───────────────────────────────────────────────────────────
+
+/// Prefix that makes Linkly tokens easy to spot in configs and secret scanners.
+const API_TOKEN_PREFIX: &str = "lk_";
+
+/// Generate a new random API token. Shown to the user once; only its hash is stored.
+pub fn generate_api_token() -> String {
+    use rand::Rng;
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let mut rng = rand::thread_rng();
+    let secret: String = (0..40)
+        .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char)
+        .collect();
+    format!("{API_TOKEN_PREFIX}{secret}")
+}
+
+/// SHA-256 hex digest used to store and look up API tokens.
+pub fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// ── ApiUser extractor ────────────────────────────────────────────────────
+
+/// Extractor for JSON API routes: authenticates `Authorization: Bearer <token>`
+/// against the user's API tokens and rejects with a JSON 401 instead of a
+/// login redirect.
+///
+/// A `?key=<token>` query parameter is accepted as a fallback for clients
+/// that cannot set headers, such as bookmarklets.
+#[derive(Clone)]
+pub struct ApiUser {
+    pub user_id: i64,
+    pub email: String,
+    pub role: String,
+    pub workspace_id: i64,
+}
+
+impl ApiUser {
+    pub fn is_admin(&self) -> bool {
+        self.role == "admin"
+    }
+
+    /// Admins of the default workspace manage the whole instance.
+    pub fn is_instance_admin(&self) -> bool {
+        self.is_admin() && self.workspace_id == DEFAULT_WORKSPACE
+    }
+
+    /// Rows this user sees in listings, as for [`AuthUser::scope`].
+    pub fn scope(&self) -> Scope {
+        Scope {
+            workspace_id: Some(self.workspace_id),
+            user_id: (!self.is_admin()).then_some(self.user_id),
+        }
+    }
+
+    /// Whether this user may read a row in `workspace_id` owned by `owner`.
+    pub fn can_manage(&self, workspace_id: i64, owner: Option<i64>) -> bool {
+        workspace_id == self.workspace_id && (self.is_admin() || owner == Some(self.user_id))
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ApiUser
+where
+    S: Send + Sync,
+    Arc<AppState>: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = Arc::<AppState>::from_ref(state);
+        let token = parts
+            .headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::to_owned)
+            .or_else(|| query_key(parts.uri.query()?))
+            .filter(|t| !t.trim().is_empty())
+            .ok_or_else(|| AppError::Unauthorized("Missing API token".into()))?;
+
+        let (user, token_id) =
+            db_users::get_user_by_api_token(&state.db, &hash_api_token(token.trim()))
+                .await
+                .context("DB error checking API token")?
+                .ok_or_else(|| AppError::Unauthorized("Invalid API token".into()))?;
+        let user_limit = quota::api_limit(&state, user.api_requests_per_hour);
+        let key_limit = state.tunables().quota_api_key_requests_per_hour;
+        if let Err(reached) = state
+            .api_usage
+            .record(user.id, user_limit, token_id, key_limit)
+        {
+            let msg = format!(
+                "API request limit reached ({} per hour for this {}); try again in {} seconds",
+                reached.limit,
+                if reached.per_key { "token" } else { "account" },
+                reached.retry_after
+            );
+            return Err(AppError::RateLimited(msg, Some(reached.retry_after)));
+        }
+        Ok(ApiUser {
+            user_id: user.id,
+            email: user.email,
+            role: user.role,
+            workspace_id: user.workspace_id,
+        })
+    }
+}
+
+/// Extractor for the API routes the admin panel calls from its pages: an
+/// [`ApiUser`] from an API token as usual, or from the session cookie when
+/// the request carries no token.
+///
+/// Only for JSON routes that change state with PATCH and the like. Other sites
+/// can't send those with the cookie: it is `SameSite=Lax`, and a cross-origin
+/// JSON request needs a CORS preflight this server doesn't answer.
+pub struct SessionOrApiUser(pub ApiUser);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for SessionOrApiUser
+where
+    S: Send + Sync,
+    Arc<AppState>: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let has_token = parts.headers.contains_key("authorization")
+            || parts.uri.query().and_then(query_key).is_some();
+        if has_token {
+            return ApiUser::from_request_parts(parts, state).await.map(Self);
+        }
+
+        let app = Arc::<AppState>::from_ref(state);
+        let claims = CookieJar::from_headers(&parts.headers)
+            .get("auth_token")
+            .and_then(|cookie| verify_jwt(cookie.value(), &app.config.jwt_secret))
+            .filter(|c| !c.fpc);
+        match claims {
+            Some(c) => Ok(Self(ApiUser {
+                user_id: c.sub,
+                email: c.email,
+                role: c.role,
+                workspace_id: c.ws,
+            })),
+            None => Err(AppError::Unauthorized("Missing API token".into())),
+        }
+    }
+}
+
+/// Whether the request comes from an instance admin, signed in or with an
+/// API token. Unlike the extractors it never rejects, and an API token check
+/// doesn't count toward the token's quota.
+pub async fn is_instance_admin(state: &AppState, parts: &Parts) -> bool {
+    let claims = CookieJar::from_headers(&parts.headers)
+        .get("auth_token")
+        .and_then(|cookie| verify_jwt(cookie.value(), &state.config.jwt_secret));
+    if let Some(c) = claims {
+        return c.role == "admin" && c.ws == DEFAULT_WORKSPACE;
+    }
+
+    let token = parts
+        .headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_owned)
+        .or_else(|| query_key(parts.uri.query()?));
+    let Some(token) = token.filter(|t| !t.trim().is_empty()) else {
+        return false;
+    };
+    matches!(
+        db_users::get_user_by_api_token(&state.db, &hash_api_token(token.trim())).await,
+        Ok(Some((user, _))) if user.role == "admin" && user.workspace_id == DEFAULT_WORKSPACE
+    )
+}
+
+/// The decoded `key` parameter of a query string.
+fn query_key(query: &str) -> Option<String> {
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .ok()?
+        .into_iter()
+        .find_map(|(k, v)| (k == "key").then_some(v))
+}
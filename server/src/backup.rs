@@ -0,0 +1,236 @@
+use crate::{s3 as s3_util, AppState};
+use anyhow::Context;
+use axum::body::{Body, Bytes};
+use chrono::Utc;
+use s3::Bucket;
+use sqlx::SqlitePool;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{io::AsyncReadExt, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+/// Backups are named `linkly-<UTC timestamp>.db`, so names sort by age.
+const FILE_PREFIX: &str = "linkly-";
+const FILE_SUFFIX: &str = ".db";
+
+/// Folder backups are uploaded to in the S3 bucket.
+const S3_PREFIX: &str = "backups/";
+
+/// Media type of a SQLite database file.
+pub const CONTENT_TYPE: &str = "application/vnd.sqlite3";
+
+/// Bytes read per chunk when a backup is downloaded.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A copy of the database on disk. Copies that weren't kept in `BACKUP_DIR`
+/// are deleted when dropped.
+pub struct Backup {
+    /// File name, e.g. `linkly-20240131T020000Z.db`.
+    pub name: String,
+    path: PathBuf,
+    temporary: bool,
+}
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Back up the database every `BACKUP_INTERVAL_HOURS`, the first one an
+/// interval after startup. Spawned from `main` when `BACKUP_DIR` or
+/// `BACKUP_S3` is set.
+pub async fn run(state: Arc<AppState>) {
+    tracing::info!(
+        "Database backups scheduled every {} hour(s), keeping the last {}",
+        state.config.backup_interval_hours,
+        state.tunables().backup_keep
+    );
+
+    let interval = Duration::from_secs(state.config.backup_interval_hours * 3600);
+    loop {
+        tokio::time::sleep(interval).await;
+        match create(&state).await {
+            Ok(backup) => tracing::info!("Backed up the database to {}", backup.name),
+            Err(e) => tracing::error!("Failed to back up the database: {:?}", e),
+        }
+    }
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// Copy the database with `VACUUM INTO`, which writes a consistent,
+/// compacted file while the server keeps running. The copy is kept in
+/// `BACKUP_DIR` and uploaded with `BACKUP_S3`, and backups beyond the last
+/// `BACKUP_KEEP` are removed from both.
+pub async fn create(state: &AppState) -> anyhow::Result<Backup> {
+    let config = &state.config;
+    let name = file_name();
+    let backup = match &config.backup_dir {
+        Some(dir) => {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .with_context(|| format!("Failed to create BACKUP_DIR {dir}"))?;
+            Backup {
+                path: Path::new(dir).join(&name),
+                name,
+                temporary: false,
+            }
+        }
+        None => Backup::temporary(name),
+    };
+    vacuum_into(&state.db, &backup).await?;
+
+    if config.backup_s3 {
+        let bucket = s3_util::get_bucket(config).context("Failed to initialize S3")?;
+        let mut file = tokio::fs::File::open(&backup.path).await?;
+        let resp = bucket
+            .put_object_stream_with_content_type(
+                &mut file,
+                format!("{S3_PREFIX}{}", backup.name),
+                CONTENT_TYPE,
+            )
+            .await
+            .context("Failed to upload the backup to S3")?;
+        if !(200..300).contains(&resp.status_code()) {
+            anyhow::bail!("S3 upload failed with status {}", resp.status_code());
+        }
+        if let Err(e) = prune_s3(&bucket, state.tunables().backup_keep).await {
+            tracing::warn!("Failed to remove old backups from S3: {:?}", e);
+        }
+    }
+    if let Some(dir) = &config.backup_dir {
+        if let Err(e) = prune_dir(Path::new(dir), state.tunables().backup_keep).await {
+            tracing::warn!("Failed to remove old backups from {}: {:?}", dir, e);
+        }
+    }
+
+    Ok(backup)
+}
+
+/// A consistent copy of the database that is neither kept nor uploaded,
+/// for streaming to a client.
+pub async fn snapshot(db: &SqlitePool) -> anyhow::Result<Backup> {
+    let backup = Backup::temporary(file_name());
+    vacuum_into(db, &backup).await?;
+    Ok(backup)
+}
+
+impl Backup {
+    /// Stream the file as a response body, a chunk at a time.
+    pub fn into_body(self) -> Body {
+        let (tx, rx) = mpsc::channel::<std::io::Result<Bytes>>(4);
+        tokio::spawn(async move {
+            // `self` moves in here, so a temporary copy outlives the download
+            let mut file = match tokio::fs::File::open(&self.path).await {
+                Ok(file) => file,
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+            };
+            loop {
+                let mut chunk = vec![0; CHUNK_SIZE];
+                let sent = match file.read(&mut chunk).await {
+                    Ok(0) => return,
+                    Ok(n) => {
+                        chunk.truncate(n);
+                        tx.send(Ok(chunk.into())).await
+                    }
+                    Err(e) => tx.send(Err(e)).await,
+                };
+                if sent.is_err() {
+                    return;
+                }
+            }
+        });
+        Body::from_stream(ReceiverStream::new(rx))
+    }
+}
+
+impl Drop for Backup {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl Backup {
+    /// A copy in the temp directory, under a unique name so concurrent
+    /// downloads don't collide.
+    fn temporary(name: String) -> Self {
+        Self {
+            path: std::env::temp_dir().join(format!("{}-{name}", Uuid::new_v4())),
+            name,
+            temporary: true,
+        }
+    }
+}
+
+fn file_name() -> String {
+    format!(
+        "{FILE_PREFIX}{}{FILE_SUFFIX}",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+async fn vacuum_into(db: &SqlitePool, backup: &Backup) -> anyhow::Result<()> {
+    let path = backup
+        .path
+        .to_str()
+        .context("Backup path is not valid UTF-8")?;
+    sqlx::query("VACUUM INTO ?1")
+        .bind(path)
+        .execute(db)
+        .await
+        .with_context(|| format!("Failed to write {path}"))?;
+    Ok(())
+}
+
+// ── Retention ──────────────────────────────────────────────────────────────
+
+fn is_backup(name: &str) -> bool {
+    name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX)
+}
+
+/// The backups in `names` beyond the newest `keep`.
+fn expired(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    names.truncate(names.len().saturating_sub(keep));
+    names
+}
+
+async fn prune_dir(dir: &Path, keep: usize) -> std::io::Result<()> {
+    let mut names = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        if let Some(name) = name.to_str().filter(|n| is_backup(n)) {
+            names.push(name.to_owned());
+        }
+    }
+    for name in expired(names, keep) {
+        tokio::fs::remove_file(dir.join(name)).await?;
+    }
+    Ok(())
+}
+
+async fn prune_s3(bucket: &Bucket, keep: usize) -> anyhow::Result<()> {
+    let names = bucket
+        .list(S3_PREFIX.to_owned(), None)
+        .await?
+        .into_iter()
+        .flat_map(|page| page.contents)
+        .filter_map(|object| object.key.strip_prefix(S3_PREFIX).map(str::to_owned))
+        .filter(|name| is_backup(name))
+        .collect();
+    for name in expired(names, keep) {
+        bucket.delete_object(format!("{S3_PREFIX}{name}")).await?;
+    }
+    Ok(())
+}
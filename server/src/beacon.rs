@@ -0,0 +1,83 @@
+use dashmap::DashMap;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::oneshot;
+
+/// How long a click waits for its page's beacon before it is recorded
+/// without client details (JavaScript disabled, or the visitor left first).
+const WAIT: Duration = Duration::from_secs(5);
+
+/// Longest language or time zone value stored.
+const MAX_TEXT_LEN: usize = 64;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Details reported by the browser on a client-side redirect page.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub screen_width: Option<i64>,
+    pub screen_height: Option<i64>,
+    pub language: Option<String>,
+    pub timezone: Option<String>,
+}
+
+/// Clicks on client-redirect links waiting for their page's beacon, keyed by
+/// a random one-time token embedded in the page.
+///
+/// The click is still logged by the redirect handler's background task; the
+/// beacon only hands it the extra fields, so each visit is one `events` row.
+#[derive(Clone, Default)]
+pub struct Beacons {
+    waiting: Arc<DashMap<String, oneshot::Sender<ClientInfo>>>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Beacons {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start waiting for a beacon. Returns the token to embed in the page and
+    /// the receiver to pass to [`Beacons::wait`].
+    pub fn expect(&self) -> (String, oneshot::Receiver<ClientInfo>) {
+        let token = hex::encode(rand::random::<[u8; 16]>());
+        let (tx, rx) = oneshot::channel();
+        self.waiting.insert(token.clone(), tx);
+        (token, rx)
+    }
+
+    /// Hand a beacon to the click waiting for it. Unknown or expired tokens
+    /// are ignored.
+    pub fn deliver(&self, token: &str, info: ClientInfo) {
+        if let Some((_, tx)) = self.waiting.remove(token) {
+            let _ = tx.send(sanitize(info));
+        }
+    }
+
+    /// Wait up to [`WAIT`] for the beacon for `token`.
+    pub async fn wait(&self, token: &str, rx: oneshot::Receiver<ClientInfo>) -> ClientInfo {
+        let info = tokio::time::timeout(WAIT, rx).await;
+        self.waiting.remove(token);
+        match info {
+            Ok(Ok(info)) => info,
+            _ => ClientInfo::default(),
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// The beacon is unauthenticated, so drop implausible values.
+fn sanitize(info: ClientInfo) -> ClientInfo {
+    let dimension = |v: Option<i64>| v.filter(|v| (1..=100_000).contains(v));
+    let text = |v: Option<String>| {
+        v.map(|s| s.trim().chars().take(MAX_TEXT_LEN).collect::<String>())
+            .filter(|s| !s.is_empty())
+    };
+    ClientInfo {
+        screen_width: dimension(info.screen_width),
+        screen_height: dimension(info.screen_height),
+        language: text(info.language),
+        timezone: text(info.timezone),
+    }
+}
@@ -1,51 +1,407 @@
+use crate::click_limit::ClickLimit;
+use crate::geo::CountryRules;
+use crate::language::LanguageRules;
+use crate::models::{Domain, Link};
+use crate::schedule::ScheduleRules;
+use crate::variants::Variants;
+use crate::AppState;
 use dashmap::DashMap;
-use std::sync::Arc;
+use moka::{ops::compute::Op, sync::Cache};
+use sqlx::SqlitePool;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
 
-/// Thread-safe in-memory cache mapping short_code -> original_url.
+/// Cache key: the link's domain (`None` for the primary domain) and its short code.
+pub type LinkKey = (Option<i64>, String);
+
+/// What the redirect handler needs to know about a cached short code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedLink {
+    /// The link's ID, which clicks are recorded against.
+    pub id: i64,
+    /// The link's own code; differs from the cache key for an alias.
+    pub short_code: String,
+    pub domain_id: Option<i64>,
+    pub workspace_id: i64,
+    pub user_id: Option<i64>,
+    pub original_url: String,
+    /// Where visitors go instead of `original_url` while the health checker
+    /// has it marked down; `None` otherwise.
+    pub fallback_url: Option<String>,
+    pub client_redirect: bool,
+    /// The link's own country lists; `None` when it has none.
+    pub country_rules: Option<Arc<CountryRules>>,
+    /// Destinations by visitor language; `None` when the link has none.
+    pub language_rules: Option<Arc<LanguageRules>>,
+    /// Destinations by local time; `None` when the link has none.
+    pub schedule_rules: Option<Arc<ScheduleRules>>,
+    /// Destinations visitors are split between; `None` when the link has
+    /// none.
+    pub variants: Option<Arc<Variants>>,
+    pub sticky_variants: bool,
+    /// What link unfurlers are shown instead of being redirected; `None`
+    /// when the link has no social preview overrides.
+    pub social: Option<Arc<SocialPreview>>,
+    pub click_limit: Option<ClickLimit>,
+}
+
+/// A link's social preview, with fields it doesn't override filled in from
+/// the link itself.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SocialPreview {
+    pub title: String,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+}
+
+impl SocialPreview {
+    fn of(link: &Link) -> Self {
+        let title = link
+            .social_title
+            .clone()
+            .or_else(|| link.title.clone())
+            .or_else(|| {
+                reqwest::Url::parse(&link.original_url)
+                    .ok()?
+                    .host_str()
+                    .map(str::to_owned)
+            })
+            .unwrap_or_else(|| link.short_code.clone());
+        Self {
+            title,
+            description: link
+                .social_description
+                .clone()
+                .or_else(|| link.description.clone()),
+            image_url: link.social_image_url.clone(),
+        }
+    }
+}
+
+impl From<&Link> for CachedLink {
+    fn from(link: &Link) -> Self {
+        // Stored lists were validated when saved, so a parse error can only
+        // come from a hand-edited database; treat it as no restriction.
+        let rules = CountryRules::parse(
+            link.allow_countries.as_deref().unwrap_or_default(),
+            link.deny_countries.as_deref().unwrap_or_default(),
+        )
+        .unwrap_or_default();
+        let languages = LanguageRules::parse(link.language_rules.as_deref().unwrap_or_default())
+            .unwrap_or_default();
+        let schedule = ScheduleRules::parse(link.schedule_rules.as_deref().unwrap_or_default())
+            .unwrap_or_default();
+        let variants =
+            Variants::parse(link.variants.as_deref().unwrap_or_default()).unwrap_or_default();
+        Self {
+            id: link.id,
+            short_code: link.short_code.clone(),
+            domain_id: link.domain_id,
+            workspace_id: link.workspace_id,
+            user_id: link.user_id,
+            original_url: link.original_url.clone(),
+            fallback_url: link
+                .fallback_url
+                .clone()
+                .filter(|_| link.destination_down_since.is_some()),
+            client_redirect: link.client_redirect,
+            country_rules: (!rules.is_empty()).then(|| Arc::new(rules)),
+            language_rules: (!languages.is_empty()).then(|| Arc::new(languages)),
+            schedule_rules: (!schedule.is_empty()).then(|| Arc::new(schedule)),
+            variants: (!variants.is_empty()).then(|| Arc::new(variants)),
+            sticky_variants: link.sticky_variants,
+            social: link
+                .has_social_preview()
+                .then(|| Arc::new(SocialPreview::of(link))),
+            click_limit: ClickLimit::of(link.click_limit_per_minute, link.click_limit_page),
+        }
+    }
+}
+
+/// Thread-safe in-memory cache mapping (domain, short_code) -> destination.
+///
+/// Bounded to `LINK_CACHE_MAX_ENTRIES`, evicting the least used entries
+/// first, so an instance with many links keeps only its busy ones in memory.
+/// The cache is warmed on startup with the most recently clicked links,
+/// filled from the database on a miss, and kept in sync via explicit
+/// insert/remove calls from the handlers after every write operation. A
+/// periodic re-sync (see [`crate::db::resync_cache`]) drops entries a missed
+/// call left stale.
 ///
-/// Backed by a DashMap so reads are concurrent and lock-free for most cases.
-/// The cache is warmed on startup by loading all active links from the
-/// database, then kept in sync via explicit insert/remove calls from the
-/// handlers after every write operation.
-#[derive(Clone, Debug)]
+/// Entries older than `LINK_CACHE_TTL_MINUTES` are stale but still served:
+/// the lookup that finds one queues it for [`revalidate`], so a redirect
+/// never waits on the database for a code it has cached.
+///
+/// With `CACHE_BUS_URL` set, handler writes are also announced to the other
+/// instances (see [`crate::cache_bus`]), which drop their copy.
+#[derive(Clone)]
 pub struct LinkCache {
-    inner: Arc<DashMap<String, String>>,
+    inner: Cache<LinkKey, Entry>,
+    /// Age after which an entry is refreshed; `None` = never.
+    ttl: Option<Duration>,
+    /// Set once the startup warm-up has loaded the links it keeps.
+    warmed: Arc<AtomicBool>,
+    /// Where changed keys are sent for other instances; unset without a bus.
+    changes: Arc<OnceLock<mpsc::UnboundedSender<LinkKey>>>,
+    /// Where stale keys are sent to be refreshed; unset without a TTL.
+    stale: Arc<OnceLock<mpsc::UnboundedSender<LinkKey>>>,
+    /// Stale keys sent and not yet refreshed, so each is queued once.
+    refreshing: Arc<DashMap<LinkKey, ()>>,
+}
+
+#[derive(Clone)]
+struct Entry {
+    link: CachedLink,
+    cached_at: Instant,
 }
 
 impl LinkCache {
-    pub fn new() -> Self {
+    /// A cache holding up to `max_entries` short codes, each refreshed once
+    /// older than `ttl` (`None` = never).
+    pub fn new(max_entries: u64, ttl: Option<Duration>) -> Self {
         Self {
-            inner: Arc::new(DashMap::new()),
+            inner: Cache::new(max_entries),
+            ttl,
+            warmed: Arc::new(AtomicBool::new(false)),
+            changes: Arc::new(OnceLock::new()),
+            stale: Arc::new(OnceLock::new()),
+            refreshing: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Send every key changed through [`set`](Self::set) and
+    /// [`remove`](Self::remove) to `changes` from now on.
+    pub fn announce_to(&self, changes: mpsc::UnboundedSender<LinkKey>) {
+        let _ = self.changes.set(changes);
+    }
+
+    /// Send keys found stale to `stale` from now on, once each until
+    /// [`refreshed`](Self::refreshed).
+    pub fn revalidate_with(&self, stale: mpsc::UnboundedSender<LinkKey>) {
+        let _ = self.stale.set(stale);
+    }
+
+    /// Record that the startup warm-up has completed.
+    pub fn mark_warm(&self) {
+        self.warmed.store(true, Ordering::Release);
+    }
+
+    /// Whether the startup warm-up has completed.
+    pub fn is_warm(&self) -> bool {
+        self.warmed.load(Ordering::Acquire)
+    }
+
+    /// Insert or update a mapping after the link changed, telling other
+    /// instances.
+    pub fn set(&self, domain_id: Option<i64>, short_code: impl Into<String>, entry: CachedLink) {
+        let key = (domain_id, short_code.into());
+        self.insert(key.clone(), entry);
+        self.announce(key);
+    }
+
+    /// Insert a mapping read from the database as it is, e.g. on a miss.
+    /// Other instances aren't told, since nothing changed.
+    pub fn fill(&self, domain_id: Option<i64>, short_code: impl Into<String>, entry: CachedLink) {
+        self.insert((domain_id, short_code.into()), entry);
+    }
+
+    /// Look up a short code on a domain. Returns a clone of the entry if
+    /// present, even a stale one, which is queued to be refreshed.
+    pub fn get(&self, domain_id: Option<i64>, short_code: &str) -> Option<CachedLink> {
+        let key = (domain_id, short_code.to_owned());
+        let entry = self.inner.get(&key)?;
+        if self.ttl.is_some_and(|ttl| entry.cached_at.elapsed() >= ttl) {
+            self.queue_refresh(key);
         }
+        Some(entry.link)
     }
 
-    /// Insert or update a mapping.
-    pub fn set(&self, short_code: impl Into<String>, original_url: impl Into<String>) {
-        self.inner.insert(short_code.into(), original_url.into());
+    /// Remove a mapping (e.g. when a link is deleted or deactivated),
+    /// telling other instances.
+    pub fn remove(&self, domain_id: Option<i64>, short_code: &str) {
+        let key = (domain_id, short_code.to_owned());
+        self.inner.invalidate(&key);
+        self.announce(key);
     }
 
-    /// Look up a short code. Returns a clone of the original URL if present.
-    pub fn get(&self, short_code: &str) -> Option<String> {
-        self.inner.get(short_code).map(|v| v.clone())
+    /// Drop a mapping from this instance only, e.g. one another instance
+    /// announced a change to.
+    pub fn evict(&self, domain_id: Option<i64>, short_code: &str) {
+        self.inner.invalidate(&(domain_id, short_code.to_owned()));
     }
 
-    /// Remove a mapping (e.g. when a link is deleted or deactivated).
-    pub fn remove(&self, short_code: &str) {
-        self.inner.remove(short_code);
+    /// Replace a stale entry with `link` as read from the database at
+    /// `read_at`, or drop it if `None`. An entry set or removed since then is
+    /// newer, and kept.
+    pub fn refresh(&self, key: LinkKey, link: Option<CachedLink>, read_at: Instant) {
+        self.inner
+            .entry(key)
+            .and_compute_with(|current| match current {
+                Some(current) if current.value().cached_at < read_at => match link {
+                    Some(link) => Op::Put(Entry {
+                        link,
+                        cached_at: Instant::now(),
+                    }),
+                    None => Op::Remove,
+                },
+                _ => Op::Nop,
+            });
+    }
+
+    /// Mark a stale key as dealt with, whether or not its refresh worked;
+    /// one that failed is queued again the next time it's looked up.
+    pub fn refreshed(&self, key: &LinkKey) {
+        self.refreshing.remove(key);
+    }
+
+    /// Move every entry of a deleted domain onto the primary domain, mirroring
+    /// the `ON DELETE SET NULL` on `links.domain_id`.
+    pub fn reassign_to_primary(&self, domain_id: i64) {
+        for (key, mut entry) in self.entries() {
+            if key.0 == Some(domain_id) {
+                self.inner.invalidate(&key);
+                self.announce(key.clone());
+                entry.domain_id = None;
+                self.insert((None, key.1), entry);
+            }
+        }
+    }
+
+    /// A snapshot of every entry, keyed by domain and short code.
+    pub fn entries(&self) -> Vec<(LinkKey, CachedLink)> {
+        self.inner
+            .iter()
+            .map(|(key, entry)| ((*key).clone(), entry.link))
+            .collect()
     }
 
     /// Number of entries currently cached.
     pub fn len(&self) -> usize {
-        self.inner.len()
+        // Counts lag behind inserts and evictions until these are applied
+        self.inner.run_pending_tasks();
+        self.inner.entry_count() as usize
     }
 
     pub fn is_empty(&self) -> bool {
-        self.inner.is_empty()
+        self.len() == 0
+    }
+
+    fn insert(&self, key: LinkKey, link: CachedLink) {
+        let entry = Entry {
+            link,
+            cached_at: Instant::now(),
+        };
+        self.inner.insert(key, entry);
+    }
+
+    fn announce(&self, key: LinkKey) {
+        if let Some(changes) = self.changes.get() {
+            // Only fails once the bus has shut down
+            let _ = changes.send(key);
+        }
+    }
+
+    fn queue_refresh(&self, key: LinkKey) {
+        let Some(stale) = self.stale.get() else {
+            return;
+        };
+        if self.refreshing.insert(key.clone(), ()).is_none() && stale.send(key.clone()).is_err() {
+            self.refreshing.remove(&key);
+        }
+    }
+}
+
+/// Refresh the stale entries [`LinkCache::get`] queues, one at a time, from
+/// the database: a link that still resolves is cached again, anything else
+/// dropped. Spawned from `main` unless `LINK_CACHE_TTL_MINUTES` is 0.
+pub async fn revalidate(state: Arc<AppState>, mut stale: mpsc::UnboundedReceiver<LinkKey>) {
+    while let Some(key) = stale.recv().await {
+        let read_at = Instant::now();
+        match crate::db::get_link_by_code(&state.db, &key.1).await {
+            Ok(link) => {
+                let link = link
+                    .filter(|link| link.domain_id == key.0 && state.link_resolves(link))
+                    .map(|link| CachedLink::from(&link));
+                state.cache.refresh(key.clone(), link, read_at);
+            }
+            // Served stale until the next lookup queues it again
+            Err(e) => tracing::warn!("Failed to refresh cached short code '{}': {:?}", key.1, e),
+        }
+        state.cache.refreshed(&key);
     }
 }
 
-impl Default for LinkCache {
-    fn default() -> Self {
-        Self::new()
+/// Check the cached links against the database every `interval` (see
+/// [`crate::db::resync_cache`]). Spawned from `main` unless
+/// `LINK_CACHE_RESYNC_MINUTES` is 0.
+pub async fn resync(
+    pool: SqlitePool,
+    cache: LinkCache,
+    include_archived: bool,
+    interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+        match crate::db::resync_cache(&pool, &cache, include_archived).await {
+            Ok(0) => {}
+            Ok(dropped) => tracing::info!("Dropped {} stale cached short code(s)", dropped),
+            Err(e) => tracing::error!("Failed to re-sync the link cache: {:?}", e),
+        }
+    }
+}
+
+/// Host name -> domain lookup table for the redirect handler.
+///
+/// Loaded at startup and refreshed whenever a domain is added or removed, so
+/// resolving the request host never touches the database.
+#[derive(Clone, Debug, Default)]
+pub struct DomainCache {
+    inner: Arc<DashMap<String, Domain>>,
+}
+
+impl DomainCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the whole table with `domains`. Existing entries are updated
+    /// in place, so concurrent lookups never see an empty table.
+    pub fn reload(&self, domains: Vec<Domain>) {
+        self.inner
+            .retain(|hostname, _| domains.iter().any(|d| &d.hostname == hostname));
+        for domain in domains {
+            self.inner.insert(domain.hostname.clone(), domain);
+        }
+    }
+
+    /// Find a domain by ID.
+    pub fn get(&self, id: i64) -> Option<Domain> {
+        self.inner
+            .iter()
+            .find(|e| e.value().id == id)
+            .map(|e| e.value().clone())
+    }
+
+    /// The domain a workspace's links use unless another is picked: the
+    /// first domain reserved for it, alphabetically.
+    pub fn default_for(&self, workspace_id: i64) -> Option<Domain> {
+        self.inner
+            .iter()
+            .filter(|e| e.value().workspace_id == Some(workspace_id))
+            .min_by(|a, b| a.key().cmp(b.key()))
+            .map(|e| e.value().clone())
+    }
+
+    /// Find the domain serving `host` (a `Host` header value, port allowed).
+    /// Returns `None` for the primary domain or any unknown host.
+    pub fn resolve(&self, host: &str) -> Option<Domain> {
+        let hostname = host.split(':').next().unwrap_or(host).to_ascii_lowercase();
+        self.inner.get(&hostname).map(|d| d.clone())
     }
 }
@@ -0,0 +1,111 @@
+use crate::{
+    cache::{LinkCache, LinkKey},
+    config::AppConfig,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+/// Redis channel link changes are announced on.
+const CHANNEL: &str = "linkly:link-cache";
+
+/// Wait before reconnecting after the connection to Redis drops.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// One changed cache key, as published. `origin` identifies the instance,
+/// so it can skip its own announcements.
+#[derive(Serialize, Deserialize)]
+struct Change {
+    origin: String,
+    domain_id: Option<i64>,
+    short_code: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// Keep `cache` consistent with other instances sharing `CACHE_BUS_URL`:
+/// short codes changed here are published over Redis pub/sub, and other
+/// instances drop their copy so the next redirect reads the link again.
+/// Does nothing unless `CACHE_BUS_URL` is set.
+///
+/// Announcements sent while an instance is disconnected are lost; the
+/// periodic re-sync (`LINK_CACHE_RESYNC_MINUTES`) catches what they missed.
+pub fn start(config: &AppConfig, cache: &LinkCache) -> anyhow::Result<()> {
+    let Some(url) = &config.cache_bus_url else {
+        return Ok(());
+    };
+    let client = redis::Client::open(url.as_str())?;
+    let origin = Uuid::new_v4().simple().to_string();
+    let (tx, rx) = mpsc::unbounded_channel();
+    cache.announce_to(tx);
+    tokio::spawn(publish(client.clone(), origin.clone(), rx));
+    tokio::spawn(subscribe(client, origin, cache.clone()));
+    tracing::info!("Sharing link cache changes over CACHE_BUS_URL");
+    Ok(())
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Publish each key the cache hands over, reconnecting as needed. A key
+/// that fails to send is dropped rather than retried.
+async fn publish(client: redis::Client, origin: String, mut rx: mpsc::UnboundedReceiver<LinkKey>) {
+    let mut connection = None;
+    while let Some((domain_id, short_code)) = rx.recv().await {
+        if connection.is_none() {
+            match client.get_multiplexed_async_connection().await {
+                Ok(c) => connection = Some(c),
+                Err(e) => {
+                    tracing::warn!("Cache bus: failed to connect to publish: {}", e);
+                    continue;
+                }
+            }
+        }
+        let Some(conn) = connection.as_mut() else {
+            continue;
+        };
+        let change = Change {
+            origin: origin.clone(),
+            domain_id,
+            short_code,
+        };
+        let payload = serde_json::to_string(&change).unwrap_or_default();
+        if let Err(e) = conn.publish::<_, _, ()>(CHANNEL, payload).await {
+            tracing::warn!("Cache bus: failed to publish a link change: {}", e);
+            connection = None;
+        }
+    }
+}
+
+/// Drop the keys other instances announce, resubscribing whenever the
+/// connection drops.
+async fn subscribe(client: redis::Client, origin: String, cache: LinkCache) {
+    loop {
+        match listen(&client, &origin, &cache).await {
+            Ok(()) => tracing::warn!("Cache bus: subscription closed; reconnecting"),
+            Err(e) => tracing::warn!("Cache bus: subscription failed: {}; reconnecting", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn listen(client: &redis::Client, origin: &str, cache: &LinkCache) -> redis::RedisResult<()> {
+    let mut pubsub = client.get_async_pubsub().await?;
+    pubsub.subscribe(CHANNEL).await?;
+    let mut messages = pubsub.on_message();
+    while let Some(message) = messages.next().await {
+        let payload: String = message.get_payload()?;
+        match serde_json::from_str::<Change>(&payload) {
+            Ok(change) if change.origin != origin => {
+                cache.evict(change.domain_id, &change.short_code);
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Cache bus: ignoring a malformed message: {}", e),
+        }
+    }
+    Ok(())
+}
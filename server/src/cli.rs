@@ -0,0 +1,306 @@
+use crate::{
+    cache::DomainCache,
+    config::AppConfig,
+    db, db_domains, db_users,
+    handlers::admin::count_field,
+    hooks::{self, Hooks},
+    http,
+    links::{self, DomainChoice, NewLink, Owner, Prepared},
+    models::Link,
+    notify::{self, Notifier},
+    screenshot::Screenshots,
+};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+
+// ── Argument types ────────────────────────────────────────────────────────
+
+/// Self-hosted URL shortener and link-in-bio page builder.
+///
+/// Running without a subcommand starts the web server, same as `linkly serve`.
+/// Every other subcommand works directly against the configured database.
+#[derive(Parser)]
+#[command(name = "linkly", version)]
+pub struct Cli {
+    /// TOML config file (defaults to ./linkly.toml when present)
+    #[arg(long, global = true, env = "LINKLY_CONFIG", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Validate the configuration, print the effective values, and exit
+    #[arg(long, global = true)]
+    pub check_config: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP server
+    Serve,
+    /// Manage short links
+    #[command(subcommand)]
+    Link(LinkCommand),
+    /// Write every short link with its click count to stdout as JSON
+    Export,
+    /// Print click statistics for a short link
+    Stats {
+        /// Short code of the link
+        code: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LinkCommand {
+    /// Create a short link
+    Add {
+        /// Destination URL (must start with http:// or https://)
+        url: String,
+        /// Custom short code (random if omitted)
+        #[arg(long)]
+        code: Option<String>,
+        /// Optional title
+        #[arg(long)]
+        title: Option<String>,
+        /// Email of the owning user (defaults to the first admin)
+        #[arg(long, value_name = "EMAIL")]
+        owner: Option<String>,
+        /// Host name of the short domain to serve the link from (defaults to the primary domain)
+        #[arg(long, value_name = "HOST")]
+        domain: Option<String>,
+    },
+    /// List all short links, newest first
+    List,
+}
+
+// ── Dispatch ──────────────────────────────────────────────────────────────
+
+/// Run a non-server subcommand.
+pub async fn run(command: Command, config: &AppConfig, pool: &SqlitePool) -> Result<()> {
+    match command {
+        Command::Serve => unreachable!("serve is handled by main"),
+        Command::Link(LinkCommand::Add {
+            url,
+            code,
+            title,
+            owner,
+            domain,
+        }) => link_add(config, pool, &url, code, title, owner, domain).await,
+        Command::Link(LinkCommand::List) => link_list(pool).await,
+        Command::Export => export(config, pool).await,
+        Command::Stats { code } => stats(config, pool, &code).await,
+    }
+}
+
+// ── Subcommands ───────────────────────────────────────────────────────────
+
+/// `linkly link add <url> [--code] [--title] [--owner] [--domain]`
+async fn link_add(
+    config: &AppConfig,
+    pool: &SqlitePool,
+    url: &str,
+    code: Option<String>,
+    title: Option<String>,
+    owner: Option<String>,
+    domain: Option<String>,
+) -> Result<()> {
+    let owner = match owner {
+        Some(email) => db_users::get_user_by_email(pool, &email.trim().to_lowercase())
+            .await?
+            .with_context(|| format!("No user with email '{email}'"))?,
+        None => db_users::get_first_admin(pool)
+            .await?
+            .context("No admin user exists; create one first or pass --owner")?,
+    };
+    let link_owner = Owner {
+        user_id: owner.id,
+        workspace_id: owner.workspace_id,
+    };
+
+    let domains = DomainCache::new();
+    domains.reload(db_domains::get_all_domains(pool).await?);
+    let short_url = |link: &Link| {
+        let base_url = link
+            .domain_id
+            .and_then(|id| domains.get(id))
+            .map_or_else(|| config.base_url.clone(), |d| d.base_url);
+        format!("{}/{}", base_url, link.short_code)
+    };
+
+    // As `links::create`, but without the link limit, which doesn't bind
+    // whoever runs the server
+    let request = NewLink {
+        url,
+        code: code.as_deref(),
+        title: title.as_deref(),
+        description: None,
+        domain: domain
+            .as_deref()
+            .map_or(DomainChoice::Default, DomainChoice::Host),
+    };
+    let probe_http = http::probe_client(config)?;
+    let link =
+        match links::prepare(pool, config, &probe_http, &domains, link_owner, request).await? {
+            Prepared::Existing(link) => {
+                eprintln!("{} already has a link to that URL.", owner.email);
+                println!("{}", short_url(&link));
+                return Ok(());
+            }
+            Prepared::New(row) => links::insert(pool, link_owner, &row).await?,
+        };
+    let short_url = short_url(&link);
+
+    // Awaited (not spawned) so the process doesn't exit before delivery.
+    let http = http::client(config)?;
+    Notifier::new(config, http.clone())
+        .deliver(
+            notify::Event::LinkCreated,
+            notify::LinkVars {
+                code: link.short_code.clone(),
+                short_url: short_url.clone(),
+                url: link.original_url.clone(),
+                title: link.title.clone(),
+                clicks: 0,
+            },
+        )
+        .await;
+    Hooks::load(pool.clone(), http.clone())
+        .await?
+        .deliver(
+            hooks::LINK_CREATED,
+            link.workspace_id,
+            link.user_id,
+            serde_json::json!({ "link": hooks::link_data(&link, &short_url) }),
+        )
+        .await;
+    let screenshots = Screenshots::new(config, http, pool.clone());
+    if screenshots.is_enabled() {
+        if let Err(e) = screenshots.capture_now(&link).await {
+            eprintln!("Couldn't capture a screenshot: {e}");
+        }
+    }
+
+    println!("{short_url}");
+    Ok(())
+}
+
+/// `linkly link list`
+async fn link_list(pool: &SqlitePool) -> Result<()> {
+    let links =
+        db::get_all_links_with_stats(pool, db::Scope::default(), db::LinkSort::default()).await?;
+    if links.is_empty() {
+        println!("No links yet.");
+        return Ok(());
+    }
+
+    let code_width = links
+        .iter()
+        .map(|l| l.short_code.len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+    println!(
+        "{:<code_width$}  {:>7}  {:<8}  {:<10}  DESTINATION",
+        "CODE", "CLICKS", "STATUS", "CREATED"
+    );
+    for link in &links {
+        println!(
+            "{:<code_width$}  {:>7}  {:<8}  {:<10}  {}",
+            link.short_code,
+            link.click_count,
+            if link.is_active { "active" } else { "inactive" },
+            link.created_at.format("%Y-%m-%d"),
+            link.original_url,
+        );
+    }
+    Ok(())
+}
+
+/// `linkly export`
+async fn export(config: &AppConfig, pool: &SqlitePool) -> Result<()> {
+    let links =
+        db::get_all_links_with_stats(pool, db::Scope::default(), db::LinkSort::default()).await?;
+    let rows: Vec<serde_json::Value> = links
+        .iter()
+        .map(|l| {
+            serde_json::json!({
+                "id": l.id,
+                "short_code": l.short_code,
+                "short_url": l.short_url(&config.base_url),
+                "original_url": l.original_url,
+                "title": l.title,
+                "description": l.description,
+                "created_at": l.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                "is_active": l.is_active,
+                "click_count": l.click_count,
+                "user_id": l.user_id,
+            })
+        })
+        .collect();
+
+    println!("{}", serde_json::to_string_pretty(&rows)?);
+    Ok(())
+}
+
+/// `linkly stats <code>`
+async fn stats(config: &AppConfig, pool: &SqlitePool, code: &str) -> Result<()> {
+    let link = db::find_link_by_code(pool, code)
+        .await?
+        .with_context(|| format!("No link with code '{code}'"))?;
+    let summary = db::get_analytics(pool, link.id, db::TrafficFilter::default())
+        .await?
+        .with_context(|| format!("No link with code '{code}'"))?;
+
+    let base_url = match summary.link.domain_id {
+        Some(id) => db_domains::get_domain_by_id(pool, id)
+            .await?
+            .map(|d| d.base_url)
+            .unwrap_or_else(|| config.base_url.clone()),
+        None => config.base_url.clone(),
+    };
+    println!("{}/{}", base_url, summary.link.short_code);
+    println!("  → {}", summary.link.original_url);
+    println!(
+        "  status: {}   created: {}",
+        if summary.link.is_active {
+            "active"
+        } else {
+            "inactive"
+        },
+        summary.link.created_at.format("%Y-%m-%d"),
+    );
+    println!("  total clicks: {}", summary.total_clicks);
+    println!("  visitors:     {}", summary.unique_visitors);
+
+    let breakdowns = [
+        (
+            "Countries",
+            count_field(summary.clicks.iter().map(|c| c.country.as_deref())),
+        ),
+        (
+            "Browsers",
+            count_field(summary.clicks.iter().map(|c| c.browser.as_deref())),
+        ),
+        (
+            "Devices",
+            count_field(summary.clicks.iter().map(|c| c.device_type.as_deref())),
+        ),
+        (
+            "Referrers",
+            count_field(summary.clicks.iter().map(|c| c.referer.as_deref())),
+        ),
+    ];
+    for (heading, rows) in breakdowns {
+        if rows.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{heading}:");
+        for (name, count) in rows {
+            println!("  {count:>7}  {name}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::{db_click_filters, models::ClickFilter};
+use regex::RegexSet;
+use sqlx::SqlitePool;
+use std::sync::{Arc, RwLock};
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// User agents whose visits aren't recorded, mirrored from the
+/// `click_filters` table so redirects don't query it.
+#[derive(Default)]
+pub struct ClickFilters {
+    current: RwLock<Arc<Matcher>>,
+}
+
+#[derive(Default)]
+struct Matcher {
+    /// Lowercased, for case-insensitive matching.
+    substrings: Vec<String>,
+    regexes: Option<RegexSet>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl ClickFilters {
+    pub async fn load(db: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let filters = Self::default();
+        filters.reload(db).await?;
+        Ok(filters)
+    }
+
+    /// Re-read the filters after they changed in the database.
+    pub async fn reload(&self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let filters = db_click_filters::get_click_filters(db).await?;
+        *self.current.write().unwrap() = Arc::new(Matcher::new(&filters));
+        Ok(())
+    }
+
+    /// Whether visits from `user_agent` go unrecorded.
+    pub fn matches(&self, user_agent: &str) -> bool {
+        let matcher = self.current.read().unwrap().clone();
+        if !matcher.substrings.is_empty() {
+            let lower = user_agent.to_lowercase();
+            if matcher.substrings.iter().any(|s| lower.contains(s)) {
+                return true;
+            }
+        }
+        matcher
+            .regexes
+            .as_ref()
+            .is_some_and(|set| set.is_match(user_agent))
+    }
+}
+
+/// Check a regular expression before it's saved as a filter.
+pub fn validate_regex(pattern: &str) -> Result<(), String> {
+    regex::Regex::new(pattern)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid regular expression: {e}"))
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+impl Matcher {
+    fn new(filters: &[ClickFilter]) -> Self {
+        let substrings = filters
+            .iter()
+            .filter(|f| !f.is_regex)
+            .map(|f| f.pattern.to_lowercase())
+            .collect();
+        // Filters are checked when added, so one that no longer compiles
+        // (e.g. edited in the database) is skipped rather than dropping all.
+        let patterns: Vec<&str> = filters
+            .iter()
+            .filter(|f| f.is_regex)
+            .map(|f| f.pattern.as_str())
+            .filter(|p| match regex::Regex::new(p) {
+                Ok(_) => true,
+                Err(e) => {
+                    tracing::warn!("Skipping click filter /{}/: {}", p, e);
+                    false
+                }
+            })
+            .collect();
+        let regexes = (!patterns.is_empty())
+            .then(|| RegexSet::new(&patterns))
+            .and_then(Result::ok);
+        Self {
+            substrings,
+            regexes,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::Utc;
+use dashmap::DashMap;
+use std::sync::Arc;
+
+/// Links remembered before counters from earlier minutes are dropped.
+const MAX_TRACKED_LINKS: usize = 10_000;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A link's cap on clicks per minute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClickLimit {
+    pub per_minute: u64,
+    /// Visitors over the cap get a page that retries by itself rather than
+    /// a plain 429.
+    pub page: bool,
+}
+
+/// Clicks counted per link over the current clock minute, for links with a
+/// [`ClickLimit`].
+///
+/// Kept in memory and per instance: a restart resets the counters, and
+/// several instances each allow the full rate.
+#[derive(Clone, Debug, Default)]
+pub struct ClickLimiter {
+    links: Arc<DashMap<i64, Window>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Window {
+    /// Minutes since the Unix epoch.
+    minute: i64,
+    clicks: u64,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl ClickLimit {
+    /// The limit stored on a link, if it has one.
+    pub fn of(per_minute: Option<i64>, page: bool) -> Option<Self> {
+        let per_minute = u64::try_from(per_minute?).ok().filter(|n| *n > 0)?;
+        Some(Self { per_minute, page })
+    }
+}
+
+impl ClickLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one click on `link_id` unless it is already at `limit` this
+    /// minute. Returns the seconds until the counter resets when refused;
+    /// refused clicks aren't counted.
+    pub fn record(&self, link_id: i64, limit: &ClickLimit) -> Result<(), u64> {
+        let now = Utc::now().timestamp();
+        let minute = now.div_euclid(60);
+        if self.links.len() > MAX_TRACKED_LINKS {
+            self.links.retain(|_, w| w.minute == minute);
+        }
+
+        let mut window = self.links.entry(link_id).or_default();
+        if window.minute != minute {
+            *window = Window { minute, clicks: 0 };
+        }
+        if window.clicks >= limit.per_minute {
+            return Err((60 - now.rem_euclid(60)) as u64);
+        }
+        window.clicks += 1;
+        Ok(())
+    }
+}
@@ -1,4 +1,158 @@
+use crate::{error_report::Dsn, geo::CountryRules};
 use anyhow::{Context, Result};
+use std::{
+    collections::{BTreeMap, HashSet},
+    env::VarError,
+    net::IpAddr,
+    path::Path,
+    sync::OnceLock,
+};
+
+/// Config file loaded when neither `--config` nor `LINKLY_CONFIG` is given.
+pub const DEFAULT_CONFIG_FILE: &str = "linkly.toml";
+
+/// Log filter used when `RUST_LOG` is unset.
+pub const DEFAULT_LOG_FILTER: &str = "linkly=info,tower_http=info";
+
+/// Shortest `CLICK_RETENTION_DAYS` allowed, so digests, funnels and the
+/// recent-clicks views always have raw clicks to work from.
+const MIN_CLICK_RETENTION_DAYS: u64 = 30;
+
+/// Every setting name, as read from the environment or the config file.
+/// Keys in the file that aren't here (usually typos) are reported, even for
+/// settings only read in some configurations.
+const SETTINGS: &[&str] = &[
+    "ACCESS_ALLOW_COUNTRIES",
+    "ACCESS_BLOCKED_MESSAGE",
+    "ACCESS_DENY_COUNTRIES",
+    "ADMIN_PASSWORD",
+    "ANALYTICS_ENABLED",
+    "ANALYTICS_ENDPOINT",
+    "ANALYTICS_PROVIDER",
+    "ANALYTICS_SITE_ID",
+    "APP_TITLE",
+    "ARCHIVED_LINKS_REDIRECT",
+    "BACKUP_DIR",
+    "BACKUP_INTERVAL_HOURS",
+    "BACKUP_KEEP",
+    "BACKUP_S3",
+    "BASE_URL",
+    "CACHE_BUS_URL",
+    "CASE_INSENSITIVE_CODES",
+    "CLICK_RETENTION_DAYS",
+    "DATABASE_URL",
+    "DIGEST_HOUR",
+    "DIGEST_RECIPIENTS",
+    "DIGEST_WEEKDAY",
+    "DISPLAY_TIMEZONE",
+    "EMOJI_SHORT_CODES",
+    "ERROR_WEBHOOK_URL",
+    "FALLBACK_CHECK_MINUTES",
+    "FAVICONS",
+    "FORM_BODY_LIMIT_KB",
+    "GA_API_SECRET",
+    "GA_MEASUREMENT_ID",
+    "GEO_CACHE_TTL_DAYS",
+    "GEO_ENABLED",
+    "GEO_IPDATA_API_KEY",
+    "GEO_IPINFO_TOKEN",
+    "GEO_IP_API_KEY",
+    "GEO_MAXMIND_ASN_DB",
+    "GEO_MAXMIND_CITY_DB",
+    "GEO_PROVIDER",
+    "GEO_REQUESTS_PER_MINUTE",
+    "GEO_TIMEOUT_SECS",
+    "GRAPHQL_ENABLED",
+    "HONOR_DNT",
+    "HOST",
+    "INTERNAL_IPS",
+    "JWT_SECRET",
+    "LINK_CACHE_MAX_ENTRIES",
+    "LINK_CACHE_RESYNC_MINUTES",
+    "LINK_CACHE_TTL_MINUTES",
+    "NOTIFY_CLICK_MILESTONES",
+    "NOTIFY_TEMPLATE_CLICK_MILESTONE",
+    "NOTIFY_TEMPLATE_LINK_CREATED",
+    "NOTIFY_TEMPLATE_LINK_DELETED",
+    "NOTIFY_WEBHOOK_URLS",
+    "NOT_FOUND_SEARCH_URL",
+    "OIDC_ADMIN_DOMAINS",
+    "OIDC_ADMIN_EMAILS",
+    "OIDC_CLIENT_ID",
+    "OIDC_CLIENT_SECRET",
+    "OIDC_ISSUER",
+    "OIDC_PROVIDER_NAME",
+    "OIDC_USER_DOMAINS",
+    "OIDC_USER_EMAILS",
+    "OUTBOUND_PROXY",
+    "OUTBOUND_TIMEOUT_SECS",
+    "OUTBOUND_USER_AGENT",
+    "PEXELS_API_KEY",
+    "PORT",
+    "PUBLIC_SHORTEN",
+    "PUBLIC_SHORTEN_OWNER",
+    "PUBLIC_SHORTEN_PER_HOUR",
+    "QUOTA_API_KEY_REQUESTS_PER_HOUR",
+    "QUOTA_API_REQUESTS_PER_HOUR",
+    "QUOTA_MAX_LINKS",
+    "REDIRECT_LOOKUP_BUDGET_MS",
+    "REQUEST_TIMEOUT_SECS",
+    "ROOT_REDIRECT_URL",
+    "RUST_LOG",
+    "S3_ACCESS_KEY",
+    "S3_BUCKET",
+    "S3_ENDPOINT",
+    "S3_REGION",
+    "S3_SECRET_KEY",
+    "SAFE_BROWSING_API_KEY",
+    "SCANNER_BAN_MINUTES",
+    "SCANNER_MISS_LIMIT",
+    "SCREENSHOT_URL",
+    "SEED_ADMIN_EMAIL",
+    "SEED_ADMIN_PASSWORD",
+    "SENTRY_DSN",
+    "SENTRY_ENVIRONMENT",
+    "SESSION_DURATION_HOURS",
+    "SHORT_CODE_ALPHABET",
+    "SHORT_CODE_BLOCKED_WORDS",
+    "SHORT_CODE_LENGTH",
+    "SHORT_CODE_STRATEGY",
+    "SHORT_CODE_WORD_FILTER",
+    "SLACK_LINK_OWNER",
+    "SLACK_SIGNING_SECRET",
+    "SMTP_FROM",
+    "SMTP_HOST",
+    "SMTP_PASSWORD",
+    "SMTP_PORT",
+    "SMTP_SECURITY",
+    "SMTP_USERNAME",
+    "STRIP_TRACKING_PARAMS",
+    "TELEGRAM_ALLOWED_CHAT_IDS",
+    "TELEGRAM_BOT_TOKEN",
+    "TELEGRAM_LINK_OWNER",
+    "TRUSTED_PROXIES",
+    "TURNSTILE_SECRET_KEY",
+    "TURNSTILE_SITE_KEY",
+    "UNICODE_SHORT_CODES",
+    "UNSPLASH_ACCESS_KEY",
+    "UPLOAD_LIMIT_MB",
+    "WAL_CHECKPOINT_MINUTES",
+];
+
+/// Names of the variables set in the process environment before `.env` was
+/// loaded. They keep winning over `.env` when the config is reloaded.
+static PROCESS_ENV: OnceLock<HashSet<String>> = OnceLock::new();
+
+/// Load `.env` into the environment (if there is one) without overriding
+/// variables that are already set.
+pub fn load_dotenv() {
+    PROCESS_ENV.get_or_init(|| {
+        std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .collect()
+    });
+    dotenvy::dotenv().ok();
+}
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
@@ -28,6 +182,55 @@ pub struct AppConfig {
     /// URL to redirect visitors to when they hit the root path ("/").
     pub root_redirect_url: String,
 
+    /// Where unknown codes redirect, with `{code}` replaced by the code
+    /// (`NOT_FOUND_SEARCH_URL`), e.g. a site search. A domain's own
+    /// not-found URL takes precedence. Unset shows the 404 page.
+    pub not_found_search_url: Option<String>,
+
+    /// Time zone that link schedules are read in (`DISPLAY_TIMEZONE`), e.g.
+    /// "Europe/Berlin". UTC by default.
+    pub display_timezone: chrono_tz::Tz,
+
+    /// Minutes between health checks of links with a fallback URL
+    /// (`FALLBACK_CHECK_MINUTES`); 0 turns the checks off.
+    pub fallback_check_minutes: u64,
+
+    /// Drop `utm_*`, `fbclid`, `gclid` and similar parameters from new
+    /// destinations (`STRIP_TRACKING_PARAMS`). Off by default.
+    pub strip_tracking_params: bool,
+
+    /// Allow non-ASCII letters and digits in custom short codes, aliases
+    /// and links page slugs (`UNICODE_SHORT_CODES`). Off by default.
+    pub unicode_short_codes: bool,
+
+    /// Allow custom short codes, aliases and links page slugs made only of
+    /// emoji (`EMOJI_SHORT_CODES`). Off by default.
+    pub emoji_short_codes: bool,
+
+    /// How codes are generated for links created without one
+    /// (`SHORT_CODE_STRATEGY`).
+    pub short_code_strategy: CodeStrategy,
+
+    /// When a code matches no link, look for one whose code differs only in
+    /// case (`CASE_INSENSITIVE_CODES`). Off by default.
+    pub case_insensitive_codes: bool,
+
+    /// Characters generated short codes are drawn from
+    /// (`SHORT_CODE_ALPHABET`). Defaults to ASCII letters and digits.
+    pub short_code_alphabet: Vec<char>,
+
+    /// Length of generated short codes (`SHORT_CODE_LENGTH`); the shortest
+    /// length for sequential ones. Defaults to 7, or 4 for sequential codes.
+    pub short_code_length: usize,
+
+    /// Never generate a code containing a word from the built-in offensive
+    /// word list (`SHORT_CODE_WORD_FILTER`). On by default.
+    pub short_code_word_filter: bool,
+
+    /// Further words, in lower case, generated codes must not contain
+    /// (`SHORT_CODE_BLOCKED_WORDS`).
+    pub short_code_blocked_words: Vec<String>,
+
     /// S3 configuration (all optional — if any are missing, uploads are disabled)
     pub s3_bucket: Option<String>,
     pub s3_region: Option<String>,
@@ -43,68 +246,1199 @@ pub struct AppConfig {
 
     /// Application title shown in nav, page titles, and footer. Defaults to "Linkly".
     pub app_title: String,
+
+    /// Slack app signing secret (optional — if missing, `/integrations/slack` is disabled)
+    pub slack_signing_secret: Option<String>,
+
+    /// Email of the user who owns links created from Slack. Defaults to the first admin.
+    pub slack_link_owner: Option<String>,
+
+    /// Discord / Mattermost / Slack incoming-webhook URLs that receive link
+    /// notifications (comma-separated in `NOTIFY_WEBHOOK_URLS`). Empty disables them.
+    pub notify_webhook_urls: Vec<String>,
+
+    /// Click counts that trigger a milestone notification, ascending.
+    pub notify_click_milestones: Vec<i64>,
+
+    /// Optional message templates overriding the built-in wording.
+    pub notify_template_link_created: Option<String>,
+    pub notify_template_link_deleted: Option<String>,
+    pub notify_template_click_milestone: Option<String>,
+
+    /// Telegram bot token (optional — if missing, the bot is not started)
+    pub telegram_bot_token: Option<String>,
+
+    /// Chat IDs allowed to use the Telegram bot. Messages from others are ignored.
+    pub telegram_allowed_chat_ids: Vec<i64>,
+
+    /// Email of the user who owns links created from Telegram. Defaults to the first admin.
+    pub telegram_link_owner: Option<String>,
+
+    /// Web analytics service that receives a pageview for every short-link
+    /// click (optional). Set together with `analytics_endpoint` and
+    /// `analytics_site_id`.
+    pub analytics_provider: Option<AnalyticsProvider>,
+
+    /// Base URL of the analytics instance, e.g. "https://plausible.io".
+    pub analytics_endpoint: String,
+
+    /// Plausible site domain or Umami website ID.
+    pub analytics_site_id: String,
+
+    /// GA4 measurement ID and Measurement Protocol API secret (optional —
+    /// both must be set to export clicks to Google Analytics).
+    pub ga_measurement_id: Option<String>,
+    pub ga_api_secret: Option<String>,
+
+    /// Google Safe Browsing API key (optional). When set, on-demand link
+    /// checks also look destinations up in Safe Browsing.
+    pub safe_browsing_api_key: Option<String>,
+
+    /// Where errors and panics are reported (optional): a Sentry project's
+    /// DSN, the environment its events are tagged with, and/or a URL that
+    /// gets each report as JSON.
+    pub sentry_dsn: Option<String>,
+    pub sentry_environment: Option<String>,
+    pub error_webhook_url: Option<String>,
+
+    /// SMTP server for alert emails (optional — if missing, no mail is sent).
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_security: SmtpSecurity,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+
+    /// Sender address, e.g. "Linkly <linkly@example.com>". Required with `smtp_host`.
+    pub smtp_from: Option<String>,
+
+    /// Addresses that receive the weekly analytics digest (comma-separated in
+    /// `DIGEST_RECIPIENTS`). Empty disables the digest.
+    pub digest_recipients: Vec<String>,
+
+    /// When the digest goes out: day of week and hour (UTC).
+    pub digest_weekday: chrono::Weekday,
+    pub digest_hour: u32,
+
+    /// Serve the read-only GraphQL API at `/api/graphql`. Off by default.
+    pub graphql_enabled: bool,
+
+    /// Countries allowed or denied for every link (`ACCESS_ALLOW_COUNTRIES`,
+    /// `ACCESS_DENY_COUNTRIES`), checked along with each link's own lists.
+    pub country_rules: CountryRules,
+    /// Shown to visitors a country rule turns away.
+    pub access_blocked_message: String,
+
+    /// OpenID Connect provider for single sign-on (optional — set together
+    /// with the client ID and secret to show an SSO button on the login page).
+    pub oidc_issuer: Option<String>,
+    pub oidc_client_id: Option<String>,
+    pub oidc_client_secret: Option<String>,
+
+    /// Provider name on the login button, e.g. "Google". Defaults to "SSO".
+    pub oidc_provider_name: String,
+
+    /// Who may sign in with SSO, and with which role: exact addresses or
+    /// whole email domains (comma-separated). Admin entries win.
+    pub oidc_admin_emails: Vec<String>,
+    pub oidc_admin_domains: Vec<String>,
+    pub oidc_user_emails: Vec<String>,
+    pub oidc_user_domains: Vec<String>,
+
+    /// Record clicks, impressions, preview views, and bio page views. When
+    /// off, Linkly only redirects: nothing is written per visit and nothing
+    /// that depends on clicks (forwarding, hooks, alerts, live updates) runs.
+    /// Always off in builds without the `analytics` feature.
+    pub analytics_enabled: bool,
+
+    /// Look up where visitors are. When off, no IP ever leaves the server or
+    /// is looked up locally, so clicks have no location, `{country}` expands
+    /// to nothing, and country allow lists turn everyone away. Always off in
+    /// builds without the `geo` feature.
+    pub geo_enabled: bool,
+
+    /// Count clicks from visitors sending `DNT: 1` or `Sec-GPC: 1` without
+    /// their IP, user agent, or location (`HONOR_DNT`). Off by default.
+    pub honor_dnt: bool,
+
+    /// Days raw clicks, impressions and preview views are kept
+    /// (`CLICK_RETENTION_DAYS`); older ones are folded into daily totals.
+    /// 0 keeps them forever.
+    pub click_retention_days: u64,
+
+    /// Addresses and CIDR ranges (comma-separated in `INTERNAL_IPS`) whose
+    /// clicks are flagged internal and left out of analytics.
+    pub internal_ips: Vec<ipnet::IpNet>,
+
+    /// Reverse proxies (comma-separated in `TRUSTED_PROXIES`) whose
+    /// `X-Forwarded-For` is believed when limiting or banning clients by IP,
+    /// and whose `X-Forwarded-Host` picks the domain links resolve on.
+    pub trusted_proxies: Vec<ipnet::IpNet>,
+
+    /// IP geolocation service for click analytics. Defaults to ip-api.com's
+    /// free tier.
+    pub geo_provider: GeoProviderKind,
+
+    /// Timeout for one geolocation request.
+    pub geo_timeout_secs: u64,
+
+    /// Overrides the provider's default request rate (`GEO_REQUESTS_PER_MINUTE`).
+    pub geo_requests_per_minute: Option<f64>,
+
+    /// Provider credentials. The ip-api.com key switches to its Pro
+    /// endpoint; the ipinfo.io token is optional; ipdata needs its key.
+    pub geo_ip_api_key: Option<String>,
+    pub geo_ipinfo_token: Option<String>,
+    pub geo_ipdata_api_key: Option<String>,
+
+    /// MaxMind GeoIP2 / GeoLite2 database files. The City database is
+    /// required with `geo_provider = maxmind`; the ASN one adds networks.
+    pub geo_maxmind_city_db: Option<String>,
+    pub geo_maxmind_asn_db: Option<String>,
+
+    /// How long resolved IPs are kept in the `geo_cache` table before being
+    /// looked up again.
+    pub geo_cache_ttl_days: u64,
+
+    /// Shared client for outbound HTTP requests (geolocation, webhooks,
+    /// analytics, SSO, Telegram, image search): default timeout, User-Agent
+    /// header, and an optional proxy URL for every request.
+    pub outbound_timeout_secs: u64,
+    pub outbound_user_agent: String,
+    pub outbound_proxy: Option<String>,
+
+    /// Limits on incoming requests: seconds a request may take before the
+    /// client gets a 408 (`REQUEST_TIMEOUT_SECS`, 0 = none), and the largest
+    /// admin form and file upload (the CSV import and bio images) accepted.
+    pub request_timeout_secs: u64,
+    pub form_body_limit_kb: usize,
+    pub upload_limit_mb: usize,
+
+    /// Milliseconds a redirect waits on each database lookup
+    /// (`REDIRECT_LOOKUP_BUDGET_MS`, 0 = as long as it takes). A short code
+    /// missing from the cache that takes longer gets a retry page.
+    pub redirect_lookup_budget_ms: u64,
+    /// Unknown short codes one client IP may request within a minute before
+    /// it is treated as a scanner (`SCANNER_MISS_LIMIT`, 0 = never), and how
+    /// long it then stays banned (`SCANNER_BAN_MINUTES`).
+    pub scanner_miss_limit: u64,
+    pub scanner_ban_minutes: u64,
+
+    /// Quotas; 0 is unlimited. Links a user may own (`QUOTA_MAX_LINKS`) and
+    /// API requests per hour per user and per API token. Users can be given
+    /// their own link and per-user limits from the admin panel.
+    pub quota_max_links: u64,
+    pub quota_api_requests_per_hour: u64,
+    pub quota_api_key_requests_per_hour: u64,
+
+    /// Serve the anonymous `/shorten` page (`PUBLIC_SHORTEN`). Off by default.
+    pub public_shorten: bool,
+    /// Email of the user who owns publicly created links. Defaults to the first admin.
+    pub public_shorten_owner: Option<String>,
+    /// Links one client IP may create per hour on `/shorten` (0 = unlimited).
+    pub public_shorten_per_hour: u64,
+    /// Cloudflare Turnstile keys; when both are set `/shorten` requires the
+    /// captcha.
+    pub turnstile_site_key: Option<String>,
+    pub turnstile_secret_key: Option<String>,
+
+    /// Screenshot service for link thumbnails (`SCREENSHOT_URL`): a GET URL
+    /// with a `{url}` placeholder for the destination, returning an image.
+    /// `None` disables thumbnails.
+    pub screenshot_url: Option<String>,
+
+    /// Show destination favicons next to links in the admin panel, fetched
+    /// from each destination site (`FAVICONS`). On by default.
+    pub favicons: bool,
+
+    /// Whether archived links keep redirecting (`ARCHIVED_LINKS_REDIRECT`).
+    /// On by default; off, they answer like deleted links until restored.
+    pub archived_links_redirect: bool,
+
+    /// Scheduled database backups: a directory to keep them in
+    /// (`BACKUP_DIR`) and/or an upload to the S3 bucket (`BACKUP_S3`). With
+    /// neither, no backups are scheduled.
+    pub backup_dir: Option<String>,
+    pub backup_s3: bool,
+    /// Hours between scheduled backups, and how many are kept in each place.
+    pub backup_interval_hours: u64,
+    pub backup_keep: usize,
+
+    /// Minutes between explicit `wal_checkpoint(TRUNCATE)`s
+    /// (`WAL_CHECKPOINT_MINUTES`); 0 leaves checkpoints to SQLite.
+    pub wal_checkpoint_minutes: u64,
+
+    /// Short codes kept in the in-memory link cache (`LINK_CACHE_MAX_ENTRIES`),
+    /// minutes after which one is refreshed in the background, still being
+    /// served meanwhile (`LINK_CACHE_TTL_MINUTES`, 0 = never), and minutes
+    /// between checks of cached links against
+    /// the database (`LINK_CACHE_RESYNC_MINUTES`, 0 = off).
+    pub link_cache_max_entries: u64,
+    pub link_cache_ttl_minutes: u64,
+    pub link_cache_resync_minutes: u64,
+
+    /// Redis URL whose pub/sub carries link cache changes between instances
+    /// (`CACHE_BUS_URL`); `None` for a single instance.
+    pub cache_bus_url: Option<String>,
+
+    /// `tracing` filter directives (`RUST_LOG`).
+    pub log_filter: String,
+}
+
+/// The settings a config reload (SIGHUP or `POST /admin/reload-config`)
+/// applies without a restart. Code that runs after startup reads them from
+/// `AppState::tunables`, since the same fields on `AppConfig` keep their
+/// startup values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tunables {
+    pub root_redirect_url: String,
+    pub quota_max_links: u64,
+    pub quota_api_requests_per_hour: u64,
+    pub quota_api_key_requests_per_hour: u64,
+    pub public_shorten_per_hour: u64,
+    pub backup_keep: usize,
+    pub log_filter: String,
+}
+
+impl Tunables {
+    /// Environment variable names of every tunable setting.
+    pub const NAMES: &'static [&'static str] = &[
+        "ROOT_REDIRECT_URL",
+        "QUOTA_MAX_LINKS",
+        "QUOTA_API_REQUESTS_PER_HOUR",
+        "QUOTA_API_KEY_REQUESTS_PER_HOUR",
+        "PUBLIC_SHORTEN_PER_HOUR",
+        "BACKUP_KEEP",
+        "RUST_LOG",
+    ];
+}
+
+/// How the SMTP connection is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (port 587).
+    StartTls,
+    /// TLS from the first byte (port 465).
+    Tls,
+    /// Unencrypted; only for a relay on localhost or a trusted network.
+    None,
+}
+
+impl SmtpSecurity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::StartTls => "starttls",
+            Self::Tls => "tls",
+            Self::None => "none",
+        }
+    }
+
+    fn default_port(self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Tls => 465,
+            Self::None => 25,
+        }
+    }
+}
+
+/// How short codes are generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeStrategy {
+    /// Random characters, retried when the code is taken.
+    Random,
+    /// A counter, scrambled with a key derived from `JWT_SECRET` so codes
+    /// don't look consecutive. Never collides, and stays short longer.
+    Sequential,
+}
+
+impl CodeStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Random => "random",
+            Self::Sequential => "sequential",
+        }
+    }
+}
+
+/// Supported click-forwarding targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalyticsProvider {
+    Plausible,
+    Umami,
+}
+
+impl AnalyticsProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Plausible => "plausible",
+            Self::Umami => "umami",
+        }
+    }
+}
+
+/// Supported IP geolocation services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoProviderKind {
+    IpApi,
+    IpInfo,
+    IpData,
+    MaxMind,
+}
+
+impl GeoProviderKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::IpApi => "ip-api",
+            Self::IpInfo => "ipinfo",
+            Self::IpData => "ipdata",
+            Self::MaxMind => "maxmind",
+        }
+    }
 }
 
 impl AppConfig {
-    /// Load configuration from environment variables (populated by dotenvy before this is called).
-    pub fn from_env() -> Result<Self> {
-        let jwt_secret = std::env::var("JWT_SECRET")
+    /// Load configuration from environment variables (populated by dotenvy
+    /// before this is called), falling back to values from the TOML file at
+    /// `path` when one is given. Environment variables always win.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let source = match path {
+            Some(p) => ConfigSource::from_file(p)?,
+            None => ConfigSource::default(),
+        };
+        let config = Self::from_source(&source)?;
+        source.check_unknown_keys()?;
+        Ok(config)
+    }
+
+    /// Load the configuration again while the server runs: like [`load`],
+    /// but `.env` is read afresh, so edits to it count as much as edits to
+    /// the config file. Variables from the real process environment still
+    /// win.
+    ///
+    /// [`load`]: Self::load
+    pub fn reload(path: Option<&Path>) -> Result<Self> {
+        let mut source = match path {
+            Some(p) => ConfigSource::from_file(p)?,
+            None => ConfigSource::default(),
+        };
+        source.dotenv = Some(read_dotenv()?);
+        let config = Self::from_source(&source)?;
+        source.check_unknown_keys()?;
+        Ok(config)
+    }
+
+    /// The settings a reload can change, as currently configured.
+    pub fn tunables(&self) -> Tunables {
+        Tunables {
+            root_redirect_url: self.root_redirect_url.clone(),
+            quota_max_links: self.quota_max_links,
+            quota_api_requests_per_hour: self.quota_api_requests_per_hour,
+            quota_api_key_requests_per_hour: self.quota_api_key_requests_per_hour,
+            public_shorten_per_hour: self.public_shorten_per_hour,
+            backup_keep: self.backup_keep,
+            log_filter: self.log_filter.clone(),
+        }
+    }
+
+    fn from_source(source: &ConfigSource) -> Result<Self> {
+        let jwt_secret = source
+            .var("JWT_SECRET")
             .context("JWT_SECRET must be set in the environment or .env file")?;
 
         if jwt_secret.trim().is_empty() {
             anyhow::bail!("JWT_SECRET must not be empty");
         }
 
-        let port = std::env::var("PORT")
+        let port = source
+            .var("PORT")
             .unwrap_or_else(|_| "3000".into())
             .parse::<u16>()
             .context("PORT must be a valid port number (1–65535)")?;
 
-        let session_duration_hours = std::env::var("SESSION_DURATION_HOURS")
+        let session_duration_hours = source
+            .var("SESSION_DURATION_HOURS")
             .unwrap_or_else(|_| "24".into())
             .parse::<u64>()
             .unwrap_or(24);
 
-        let base_url = std::env::var("BASE_URL")
+        let base_url = source
+            .var("BASE_URL")
             .unwrap_or_else(|_| format!("http://localhost:{port}"))
             .trim_end_matches('/')
             .to_owned();
 
-        let root_redirect_url = std::env::var("ROOT_REDIRECT_URL")
+        let root_redirect_url = source
+            .var("ROOT_REDIRECT_URL")
             .unwrap_or_else(|_| "https://secedastudios.com".into())
             .trim_end_matches('/')
             .to_owned();
 
-        let seed_admin_email = std::env::var("SEED_ADMIN_EMAIL")
+        let not_found_search_url = source
+            .var("NOT_FOUND_SEARCH_URL")
             .ok()
+            .map(|s| s.trim().to_owned())
             .filter(|s| !s.is_empty());
-        let seed_admin_password = std::env::var("SEED_ADMIN_PASSWORD")
-            .or_else(|_| std::env::var("ADMIN_PASSWORD")) // backward compat
+        if let Some(url) = &not_found_search_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("NOT_FOUND_SEARCH_URL must start with http:// or https://");
+            }
+            if !url.contains("{code}") {
+                anyhow::bail!("NOT_FOUND_SEARCH_URL must contain a {{code}} placeholder");
+            }
+        }
+
+        let display_timezone = source
+            .var("DISPLAY_TIMEZONE")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "UTC".into())
+            .trim()
+            .parse::<chrono_tz::Tz>()
+            .ok()
+            .context("DISPLAY_TIMEZONE must be an IANA time zone, e.g. 'Europe/Berlin'")?;
+
+        let fallback_check_minutes = source
+            .var("FALLBACK_CHECK_MINUTES")
+            .unwrap_or_else(|_| "5".into())
+            .parse::<u64>()
+            .context("FALLBACK_CHECK_MINUTES must be a number of minutes (0 = off)")?;
+
+        let strip_tracking_params = source.flag("STRIP_TRACKING_PARAMS", false)?;
+
+        let unicode_short_codes = source.flag("UNICODE_SHORT_CODES", false)?;
+
+        let emoji_short_codes = source.flag("EMOJI_SHORT_CODES", false)?;
+
+        let short_code_strategy = match source
+            .var("SHORT_CODE_STRATEGY")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "random" => CodeStrategy::Random,
+            "sequential" => CodeStrategy::Sequential,
+            other => {
+                anyhow::bail!("SHORT_CODE_STRATEGY '{other}' must be 'random' or 'sequential'")
+            }
+        };
+
+        let case_insensitive_codes = source.flag("CASE_INSENSITIVE_CODES", false)?;
+
+        let mut short_code_alphabet = Vec::new();
+        for c in source
+            .var("SHORT_CODE_ALPHABET")
+            .unwrap_or_else(|_| {
+                "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789".into()
+            })
+            .trim()
+            .chars()
+        {
+            if !(c == '-'
+                || c.is_ascii_alphanumeric()
+                || (unicode_short_codes && c.is_alphanumeric()))
+            {
+                anyhow::bail!(
+                    "SHORT_CODE_ALPHABET may only contain characters allowed in short codes, got '{c}'"
+                );
+            }
+            if !short_code_alphabet.contains(&c) {
+                short_code_alphabet.push(c);
+            }
+        }
+        if short_code_alphabet.len() < 2 {
+            anyhow::bail!("SHORT_CODE_ALPHABET must contain at least 2 different characters");
+        }
+
+        let short_code_length: usize = source
+            .var("SHORT_CODE_LENGTH")
+            .unwrap_or_else(|_| match short_code_strategy {
+                CodeStrategy::Random => "7".into(),
+                CodeStrategy::Sequential => "4".into(),
+            })
+            .trim()
+            .parse()
+            .ok()
+            .filter(|n| (3..=32).contains(n))
+            .context("SHORT_CODE_LENGTH must be a number from 3 to 32")?;
+
+        let short_code_word_filter = source.flag("SHORT_CODE_WORD_FILTER", true)?;
+
+        let mut short_code_blocked_words: Vec<String> = source
+            .var("SHORT_CODE_BLOCKED_WORDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        short_code_blocked_words.sort_unstable();
+        short_code_blocked_words.dedup();
+
+        let notify_webhook_urls: Vec<String> = source
+            .var("NOTIFY_WEBHOOK_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned)
+            .collect();
+        if let Some(bad) = notify_webhook_urls
+            .iter()
+            .find(|u| !u.starts_with("http://") && !u.starts_with("https://"))
+        {
+            anyhow::bail!("NOTIFY_WEBHOOK_URLS entry '{bad}' must start with http:// or https://");
+        }
+
+        let mut notify_click_milestones = source
+            .var("NOTIFY_CLICK_MILESTONES")
+            .unwrap_or_else(|_| "100,1000,10000".into())
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<i64>().ok().filter(|n| *n > 0))
+            .collect::<Option<Vec<i64>>>()
+            .context(
+                "NOTIFY_CLICK_MILESTONES must be a comma-separated list of positive numbers",
+            )?;
+        notify_click_milestones.sort_unstable();
+        notify_click_milestones.dedup();
+
+        let telegram_allowed_chat_ids = source
+            .var("TELEGRAM_ALLOWED_CHAT_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<i64>().ok())
+            .collect::<Option<Vec<i64>>>()
+            .context("TELEGRAM_ALLOWED_CHAT_IDS must be a comma-separated list of chat IDs")?;
+
+        let analytics_provider = match source
+            .var("ANALYTICS_PROVIDER")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" => None,
+            "plausible" => Some(AnalyticsProvider::Plausible),
+            "umami" => Some(AnalyticsProvider::Umami),
+            other => {
+                anyhow::bail!("ANALYTICS_PROVIDER '{other}' must be 'plausible' or 'umami'")
+            }
+        };
+        let analytics_endpoint = source
+            .var("ANALYTICS_ENDPOINT")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .or_else(|| {
+                (analytics_provider == Some(AnalyticsProvider::Plausible))
+                    .then(|| "https://plausible.io".into())
+            })
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_owned();
+        let analytics_site_id = source
+            .var("ANALYTICS_SITE_ID")
+            .unwrap_or_default()
+            .trim()
+            .to_owned();
+        if analytics_provider.is_some() {
+            if analytics_site_id.is_empty() {
+                anyhow::bail!("ANALYTICS_SITE_ID must be set when ANALYTICS_PROVIDER is");
+            }
+            if !analytics_endpoint.starts_with("http://")
+                && !analytics_endpoint.starts_with("https://")
+            {
+                anyhow::bail!("ANALYTICS_ENDPOINT must be set to an http:// or https:// URL");
+            }
+        }
+
+        let ga_measurement_id = source
+            .var("GA_MEASUREMENT_ID")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let ga_api_secret = source.var("GA_API_SECRET").ok().filter(|s| !s.is_empty());
+        if ga_measurement_id.is_some() != ga_api_secret.is_some() {
+            anyhow::bail!("GA_MEASUREMENT_ID and GA_API_SECRET must be set together");
+        }
+        let safe_browsing_api_key = source
+            .var("SAFE_BROWSING_API_KEY")
+            .ok()
+            .filter(|s| !s.is_empty());
+
+        let sentry_dsn = source.var("SENTRY_DSN").ok().filter(|s| !s.is_empty());
+        if let Some(dsn) = &sentry_dsn {
+            Dsn::parse(dsn)
+                .context("SENTRY_DSN must look like https://<key>@<host>/<project id>")?;
+        }
+        let error_webhook_url = source
+            .var("ERROR_WEBHOOK_URL")
+            .ok()
+            .filter(|s| !s.is_empty());
+        if let Some(url) = &error_webhook_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("ERROR_WEBHOOK_URL must start with http:// or https://");
+            }
+        }
+
+        let smtp_security = match source
+            .var("SMTP_SECURITY")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "starttls" => SmtpSecurity::StartTls,
+            "tls" => SmtpSecurity::Tls,
+            "none" => SmtpSecurity::None,
+            other => anyhow::bail!("SMTP_SECURITY '{other}' must be 'starttls', 'tls', or 'none'"),
+        };
+        let smtp_port = match source.var("SMTP_PORT") {
+            Ok(p) => p
+                .parse::<u16>()
+                .context("SMTP_PORT must be a valid port number (1–65535)")?,
+            Err(_) => smtp_security.default_port(),
+        };
+        let smtp_host = source.var("SMTP_HOST").ok().filter(|s| !s.is_empty());
+        let smtp_from = source.var("SMTP_FROM").ok().filter(|s| !s.is_empty());
+        if smtp_host.is_some() {
+            match &smtp_from {
+                Some(from) if from.parse::<lettre::message::Mailbox>().is_err() => {
+                    anyhow::bail!("SMTP_FROM '{from}' is not a valid email address")
+                }
+                Some(_) => {}
+                None => anyhow::bail!("SMTP_FROM must be set when SMTP_HOST is"),
+            }
+        }
+
+        let digest_recipients: Vec<String> = source
+            .var("DIGEST_RECIPIENTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect();
+        if let Some(bad) = digest_recipients
+            .iter()
+            .find(|r| r.parse::<lettre::Address>().is_err())
+        {
+            anyhow::bail!("DIGEST_RECIPIENTS entry '{bad}' is not a valid email address");
+        }
+        let digest_weekday = source
+            .var("DIGEST_WEEKDAY")
+            .unwrap_or_else(|_| "monday".into())
+            .trim()
+            .parse::<chrono::Weekday>()
+            .ok()
+            .context("DIGEST_WEEKDAY must be a day of the week, e.g. 'monday'")?;
+        let digest_hour = source
+            .var("DIGEST_HOUR")
+            .unwrap_or_else(|_| "8".into())
+            .parse::<u32>()
+            .ok()
+            .filter(|h| *h < 24)
+            .context("DIGEST_HOUR must be an hour of the day (0–23)")?;
+
+        let graphql_enabled = source.flag("GRAPHQL_ENABLED", false)?;
+
+        let public_shorten = source.flag("PUBLIC_SHORTEN", false)?;
+        let public_shorten_per_hour = source
+            .var("PUBLIC_SHORTEN_PER_HOUR")
+            .unwrap_or_else(|_| "10".into())
+            .trim()
+            .parse::<u64>()
+            .context("PUBLIC_SHORTEN_PER_HOUR must be a number (0 = unlimited)")?;
+        let turnstile_site_key = source
+            .var("TURNSTILE_SITE_KEY")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let turnstile_secret_key = source
+            .var("TURNSTILE_SECRET_KEY")
+            .ok()
+            .filter(|s| !s.is_empty());
+        if turnstile_site_key.is_some() != turnstile_secret_key.is_some() {
+            anyhow::bail!("TURNSTILE_SITE_KEY and TURNSTILE_SECRET_KEY must be set together");
+        }
+
+        let favicons = source.flag("FAVICONS", true)?;
+
+        let archived_links_redirect = source.flag("ARCHIVED_LINKS_REDIRECT", true)?;
+
+        let backup_dir = source
+            .var("BACKUP_DIR")
+            .ok()
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty());
+        let backup_s3 = source.flag("BACKUP_S3", false)?;
+        let backup_interval_hours = source
+            .var("BACKUP_INTERVAL_HOURS")
+            .unwrap_or_else(|_| "24".into())
+            .parse::<u64>()
+            .ok()
+            .filter(|h| *h > 0)
+            .context("BACKUP_INTERVAL_HOURS must be a positive number of hours")?;
+        let backup_keep = source
+            .var("BACKUP_KEEP")
+            .unwrap_or_else(|_| "7".into())
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .context("BACKUP_KEEP must be a positive number of backups")?;
+        let request_timeout_secs = source
+            .var("REQUEST_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "30".into())
+            .parse::<u64>()
+            .context("REQUEST_TIMEOUT_SECS must be a number of seconds (0 = no timeout)")?;
+        let form_body_limit_kb = source
+            .var("FORM_BODY_LIMIT_KB")
+            .unwrap_or_else(|_| "1024".into())
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .context("FORM_BODY_LIMIT_KB must be a positive number of kilobytes")?;
+        let upload_limit_mb = source
+            .var("UPLOAD_LIMIT_MB")
+            .unwrap_or_else(|_| "10".into())
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .context("UPLOAD_LIMIT_MB must be a positive number of megabytes")?;
+        let redirect_lookup_budget_ms = source
+            .var("REDIRECT_LOOKUP_BUDGET_MS")
+            .unwrap_or_else(|_| "2000".into())
+            .parse::<u64>()
+            .context(
+                "REDIRECT_LOOKUP_BUDGET_MS must be a number of milliseconds (0 = no budget)",
+            )?;
+        let scanner_miss_limit = source
+            .var("SCANNER_MISS_LIMIT")
+            .unwrap_or_else(|_| "30".into())
+            .parse::<u64>()
+            .context("SCANNER_MISS_LIMIT must be a number (0 = never ban)")?;
+        let scanner_ban_minutes = source
+            .var("SCANNER_BAN_MINUTES")
+            .unwrap_or_else(|_| "60".into())
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .context("SCANNER_BAN_MINUTES must be a positive number of minutes")?;
+        let wal_checkpoint_minutes = source
+            .var("WAL_CHECKPOINT_MINUTES")
+            .unwrap_or_else(|_| "5".into())
+            .parse::<u64>()
+            .context("WAL_CHECKPOINT_MINUTES must be a number of minutes (0 = off)")?;
+        let link_cache_max_entries = source
+            .var("LINK_CACHE_MAX_ENTRIES")
+            .unwrap_or_else(|_| "100000".into())
+            .parse::<u64>()
+            .ok()
+            .filter(|n| *n > 0)
+            .context("LINK_CACHE_MAX_ENTRIES must be a positive number of links")?;
+        let link_cache_ttl_minutes = source
+            .var("LINK_CACHE_TTL_MINUTES")
+            .unwrap_or_else(|_| "60".into())
+            .parse::<u64>()
+            .context("LINK_CACHE_TTL_MINUTES must be a number of minutes (0 = never refresh)")?;
+        let link_cache_resync_minutes = source
+            .var("LINK_CACHE_RESYNC_MINUTES")
+            .unwrap_or_else(|_| "5".into())
+            .parse::<u64>()
+            .context("LINK_CACHE_RESYNC_MINUTES must be a number of minutes (0 = off)")?;
+        let cache_bus_url = source.var("CACHE_BUS_URL").ok().filter(|s| !s.is_empty());
+        if let Some(url) = &cache_bus_url {
+            redis::Client::open(url.as_str())
+                .context("CACHE_BUS_URL must be a redis:// or rediss:// URL")?;
+        }
+
+        let screenshot_url = source
+            .var("SCREENSHOT_URL")
+            .ok()
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty());
+        if let Some(url) = &screenshot_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("SCREENSHOT_URL must start with http:// or https://");
+            }
+            if !url.contains("{url}") {
+                anyhow::bail!("SCREENSHOT_URL must contain a {{url}} placeholder");
+            }
+        }
+
+        let country_rules = match CountryRules::parse(
+            &source.var("ACCESS_ALLOW_COUNTRIES").unwrap_or_default(),
+            &source.var("ACCESS_DENY_COUNTRIES").unwrap_or_default(),
+        ) {
+            Ok(rules) => rules,
+            Err(e) => anyhow::bail!("ACCESS_ALLOW_COUNTRIES / ACCESS_DENY_COUNTRIES: {e}"),
+        };
+
+        let oidc_issuer = source
+            .var("OIDC_ISSUER")
+            .ok()
+            .map(|s| s.trim().trim_end_matches('/').to_owned())
+            .filter(|s| !s.is_empty());
+        let oidc_client_id = source.var("OIDC_CLIENT_ID").ok().filter(|s| !s.is_empty());
+        let oidc_client_secret = source
+            .var("OIDC_CLIENT_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let oidc_list = |name: &str| -> Vec<String> {
+            source
+                .var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().trim_start_matches('@').to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        let oidc_admin_emails = oidc_list("OIDC_ADMIN_EMAILS");
+        let oidc_admin_domains = oidc_list("OIDC_ADMIN_DOMAINS");
+        let oidc_user_emails = oidc_list("OIDC_USER_EMAILS");
+        let oidc_user_domains = oidc_list("OIDC_USER_DOMAINS");
+        if let Some(issuer) = &oidc_issuer {
+            if !issuer.starts_with("https://") && !issuer.starts_with("http://") {
+                anyhow::bail!("OIDC_ISSUER must be an http:// or https:// URL");
+            }
+            if oidc_client_id.is_none() || oidc_client_secret.is_none() {
+                anyhow::bail!("OIDC_CLIENT_ID and OIDC_CLIENT_SECRET must be set with OIDC_ISSUER");
+            }
+            if oidc_admin_emails.is_empty()
+                && oidc_admin_domains.is_empty()
+                && oidc_user_emails.is_empty()
+                && oidc_user_domains.is_empty()
+            {
+                anyhow::bail!(
+                    "OIDC_ISSUER needs at least one of OIDC_ADMIN_EMAILS, OIDC_ADMIN_DOMAINS, \
+                     OIDC_USER_EMAILS, or OIDC_USER_DOMAINS, or nobody can sign in"
+                );
+            }
+        }
+
+        let internal_ips = source.ip_list("INTERNAL_IPS")?;
+        let trusted_proxies = source.ip_list("TRUSTED_PROXIES")?;
+
+        // Only on by default when built in, so `true` means it was asked for
+        let analytics_enabled = source.flag("ANALYTICS_ENABLED", cfg!(feature = "analytics"))?;
+        if analytics_enabled && !cfg!(feature = "analytics") {
+            anyhow::bail!(
+                "ANALYTICS_ENABLED is set, but Linkly was built without the analytics feature"
+            );
+        }
+        let geo_enabled = source.flag("GEO_ENABLED", cfg!(feature = "geo"))?;
+        if geo_enabled && !cfg!(feature = "geo") {
+            anyhow::bail!("GEO_ENABLED is set, but Linkly was built without the geo feature");
+        }
+
+        let honor_dnt = source.flag("HONOR_DNT", false)?;
+
+        let click_retention_days = source
+            .var("CLICK_RETENTION_DAYS")
+            .unwrap_or_else(|_| "0".into())
+            .parse::<u64>()
+            .context("CLICK_RETENTION_DAYS must be a number of days (0 = keep forever)")?;
+        if click_retention_days > 0 && click_retention_days < MIN_CLICK_RETENTION_DAYS {
+            anyhow::bail!(
+                "CLICK_RETENTION_DAYS must be 0 (keep forever) or at least {MIN_CLICK_RETENTION_DAYS}"
+            );
+        }
+
+        let geo_provider = match source
+            .var("GEO_PROVIDER")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "ip-api" | "ipapi" => GeoProviderKind::IpApi,
+            "ipinfo" => GeoProviderKind::IpInfo,
+            "ipdata" => GeoProviderKind::IpData,
+            "maxmind" => GeoProviderKind::MaxMind,
+            other => anyhow::bail!(
+                "GEO_PROVIDER '{other}' must be 'ip-api', 'ipinfo', 'ipdata', or 'maxmind'"
+            ),
+        };
+        let geo_timeout_secs = source
+            .var("GEO_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "10".into())
+            .parse::<u64>()
+            .ok()
+            .filter(|s| *s > 0)
+            .context("GEO_TIMEOUT_SECS must be a positive number of seconds")?;
+        let geo_requests_per_minute = match source.var("GEO_REQUESTS_PER_MINUTE") {
+            Ok(n) if !n.trim().is_empty() => Some(
+                n.trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|n| n.is_finite() && *n > 0.0)
+                    .context("GEO_REQUESTS_PER_MINUTE must be a positive number")?,
+            ),
+            _ => None,
+        };
+        let geo_ip_api_key = source.var("GEO_IP_API_KEY").ok().filter(|s| !s.is_empty());
+        let geo_ipinfo_token = source
+            .var("GEO_IPINFO_TOKEN")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let geo_ipdata_api_key = source
+            .var("GEO_IPDATA_API_KEY")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let geo_maxmind_city_db = source
+            .var("GEO_MAXMIND_CITY_DB")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let geo_maxmind_asn_db = source
+            .var("GEO_MAXMIND_ASN_DB")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let geo_cache_ttl_days = source
+            .var("GEO_CACHE_TTL_DAYS")
+            .unwrap_or_else(|_| "30".into())
+            .parse::<u64>()
+            .context("GEO_CACHE_TTL_DAYS must be a number of days")?;
+        let outbound_timeout_secs = source
+            .var("OUTBOUND_TIMEOUT_SECS")
+            .unwrap_or_else(|_| "10".into())
+            .parse::<u64>()
+            .ok()
+            .filter(|s| *s > 0)
+            .context("OUTBOUND_TIMEOUT_SECS must be a positive number of seconds")?;
+        let outbound_proxy = source
+            .var("OUTBOUND_PROXY")
+            .ok()
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty());
+        if let Some(proxy) = &outbound_proxy {
+            if reqwest::Proxy::all(proxy).is_err() {
+                anyhow::bail!(
+                    "OUTBOUND_PROXY '{proxy}' must be a proxy URL, e.g. http://proxy:3128"
+                );
+            }
+        }
+        let quota = |key: &str| -> Result<u64> {
+            match source.var(key) {
+                Ok(n) if !n.trim().is_empty() => n
+                    .trim()
+                    .parse::<u64>()
+                    .with_context(|| format!("{key} must be a number (0 = unlimited)")),
+                _ => Ok(0),
+            }
+        };
+        let quota_max_links = quota("QUOTA_MAX_LINKS")?;
+        let quota_api_requests_per_hour = quota("QUOTA_API_REQUESTS_PER_HOUR")?;
+        let quota_api_key_requests_per_hour = quota("QUOTA_API_KEY_REQUESTS_PER_HOUR")?;
+        match geo_provider {
+            GeoProviderKind::IpData if geo_ipdata_api_key.is_none() => {
+                anyhow::bail!("GEO_IPDATA_API_KEY must be set when GEO_PROVIDER is ipdata")
+            }
+            GeoProviderKind::MaxMind if geo_maxmind_city_db.is_none() => {
+                anyhow::bail!("GEO_MAXMIND_CITY_DB must be set when GEO_PROVIDER is maxmind")
+            }
+            _ => {}
+        }
+
+        let log_filter = source
+            .var("RUST_LOG")
+            .ok()
+            .map(|s| s.trim().to_owned())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| DEFAULT_LOG_FILTER.into());
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&log_filter) {
+            anyhow::bail!("RUST_LOG '{log_filter}' is not a valid log filter: {e}");
+        }
+
+        let seed_admin_email = source
+            .var("SEED_ADMIN_EMAIL")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let seed_admin_password = source
+            .var("SEED_ADMIN_PASSWORD")
+            .or_else(|_| source.var("ADMIN_PASSWORD")) // backward compat
             .ok()
             .filter(|s| !s.is_empty());
 
         Ok(Self {
-            database_url: std::env::var("DATABASE_URL")
+            database_url: source
+                .var("DATABASE_URL")
                 .unwrap_or_else(|_| "sqlite:./linkly.db".into()),
             jwt_secret,
             seed_admin_email,
             seed_admin_password,
-            host: std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
+            host: source.var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
             port,
             base_url,
             session_duration_hours,
             root_redirect_url,
-            s3_bucket: std::env::var("S3_BUCKET").ok(),
-            s3_region: std::env::var("S3_REGION").ok(),
-            s3_endpoint: std::env::var("S3_ENDPOINT").ok(),
-            s3_access_key: std::env::var("S3_ACCESS_KEY").ok(),
-            s3_secret_key: std::env::var("S3_SECRET_KEY").ok(),
-            unsplash_access_key: std::env::var("UNSPLASH_ACCESS_KEY").ok(),
-            pexels_api_key: std::env::var("PEXELS_API_KEY").ok(),
-            app_title: std::env::var("APP_TITLE").unwrap_or_else(|_| "Linkly".into()),
+            not_found_search_url,
+            display_timezone,
+            fallback_check_minutes,
+            strip_tracking_params,
+            unicode_short_codes,
+            emoji_short_codes,
+            short_code_strategy,
+            case_insensitive_codes,
+            short_code_alphabet,
+            short_code_length,
+            short_code_word_filter,
+            short_code_blocked_words,
+            s3_bucket: source.var("S3_BUCKET").ok(),
+            s3_region: source.var("S3_REGION").ok(),
+            s3_endpoint: source.var("S3_ENDPOINT").ok(),
+            s3_access_key: source.var("S3_ACCESS_KEY").ok(),
+            s3_secret_key: source.var("S3_SECRET_KEY").ok(),
+            unsplash_access_key: source.var("UNSPLASH_ACCESS_KEY").ok(),
+            pexels_api_key: source.var("PEXELS_API_KEY").ok(),
+            app_title: source.var("APP_TITLE").unwrap_or_else(|_| "Linkly".into()),
+            slack_signing_secret: source
+                .var("SLACK_SIGNING_SECRET")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            slack_link_owner: source
+                .var("SLACK_LINK_OWNER")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            notify_webhook_urls,
+            notify_click_milestones,
+            notify_template_link_created: source
+                .var("NOTIFY_TEMPLATE_LINK_CREATED")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            notify_template_link_deleted: source
+                .var("NOTIFY_TEMPLATE_LINK_DELETED")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            notify_template_click_milestone: source
+                .var("NOTIFY_TEMPLATE_CLICK_MILESTONE")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            telegram_bot_token: source
+                .var("TELEGRAM_BOT_TOKEN")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            telegram_allowed_chat_ids,
+            telegram_link_owner: source
+                .var("TELEGRAM_LINK_OWNER")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            analytics_provider,
+            analytics_endpoint,
+            analytics_site_id,
+            ga_measurement_id,
+            ga_api_secret,
+            safe_browsing_api_key,
+            sentry_dsn,
+            sentry_environment: source
+                .var("SENTRY_ENVIRONMENT")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            error_webhook_url,
+            smtp_host,
+            smtp_port,
+            smtp_security,
+            smtp_username: source.var("SMTP_USERNAME").ok().filter(|s| !s.is_empty()),
+            smtp_password: source.var("SMTP_PASSWORD").ok().filter(|s| !s.is_empty()),
+            smtp_from,
+            digest_recipients,
+            digest_weekday,
+            digest_hour,
+            graphql_enabled,
+            country_rules,
+            access_blocked_message: source
+                .var("ACCESS_BLOCKED_MESSAGE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "This link isn't available in your region.".into()),
+            oidc_issuer,
+            oidc_client_id,
+            oidc_client_secret,
+            oidc_provider_name: source
+                .var("OIDC_PROVIDER_NAME")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "SSO".into()),
+            oidc_admin_emails,
+            oidc_admin_domains,
+            oidc_user_emails,
+            oidc_user_domains,
+            analytics_enabled,
+            geo_enabled,
+            honor_dnt,
+            click_retention_days,
+            internal_ips,
+            trusted_proxies,
+            geo_provider,
+            geo_timeout_secs,
+            geo_requests_per_minute,
+            geo_ip_api_key,
+            geo_ipinfo_token,
+            geo_ipdata_api_key,
+            geo_maxmind_city_db,
+            geo_maxmind_asn_db,
+            geo_cache_ttl_days,
+            outbound_timeout_secs,
+            outbound_user_agent: source
+                .var("OUTBOUND_USER_AGENT")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| format!("Linkly/{}", env!("CARGO_PKG_VERSION"))),
+            outbound_proxy,
+            request_timeout_secs,
+            form_body_limit_kb,
+            upload_limit_mb,
+            redirect_lookup_budget_ms,
+            scanner_miss_limit,
+            scanner_ban_minutes,
+            quota_max_links,
+            quota_api_requests_per_hour,
+            quota_api_key_requests_per_hour,
+            public_shorten,
+            public_shorten_owner: source
+                .var("PUBLIC_SHORTEN_OWNER")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            public_shorten_per_hour,
+            turnstile_site_key,
+            turnstile_secret_key,
+            screenshot_url,
+            favicons,
+            archived_links_redirect,
+            backup_dir,
+            backup_s3,
+            backup_interval_hours,
+            backup_keep,
+            log_filter,
+            wal_checkpoint_minutes,
+            link_cache_max_entries,
+            link_cache_ttl_minutes,
+            link_cache_resync_minutes,
+            cache_bus_url,
         })
     }
 
+    /// Whether clicks from `ip` are internal (see `INTERNAL_IPS`).
+    pub fn is_internal_ip(&self, ip: &str) -> bool {
+        if self.internal_ips.is_empty() {
+            return false;
+        }
+        match ip.parse::<IpAddr>() {
+            // IPv4 clients on a dual-stack socket show up as ::ffff:a.b.c.d
+            Ok(ip) => self
+                .internal_ips
+                .iter()
+                .any(|net| net.contains(&ip.to_canonical())),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether `ip` is one of `TRUSTED_PROXIES`.
+    pub fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|net| net.contains(&ip.to_canonical()))
+    }
+
     /// Returns true if all required S3 credentials are configured.
     pub fn s3_configured(&self) -> bool {
         self.s3_bucket.is_some()
@@ -122,4 +1456,475 @@ impl AppConfig {
     pub fn image_search_configured(&self) -> bool {
         self.unsplash_access_key.is_some() || self.pexels_api_key.is_some()
     }
+
+    /// Render the effective configuration as `KEY = value` lines for
+    /// `--check-config`. Secrets are redacted.
+    pub fn describe(&self) -> String {
+        let rows = self.rows();
+        let width = rows.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+        rows.into_iter()
+            .map(|(k, v)| format!("{k:<width$} = {v}\n"))
+            .collect()
+    }
+
+    /// Environment variable names of the settings that differ from `other`,
+    /// as far as [`describe`](Self::describe) shows them (secrets only
+    /// count when set or unset).
+    pub fn changes(&self, other: &Self) -> Vec<&'static str> {
+        self.rows()
+            .into_iter()
+            .zip(other.rows())
+            .filter(|((_, a), (_, b))| a != b)
+            .map(|((name, _), _)| name)
+            .collect()
+    }
+
+    /// `(name, value)` for every setting, secrets redacted.
+    fn rows(&self) -> Vec<(&'static str, String)> {
+        fn opt(v: &Option<String>) -> String {
+            v.clone().unwrap_or_else(|| "(unset)".into())
+        }
+        fn secret(v: Option<&str>) -> String {
+            match v {
+                Some(_) => "(set)".into(),
+                None => "(unset)".into(),
+            }
+        }
+        fn unlimited(n: u64) -> String {
+            match n {
+                0 => "(unlimited)".into(),
+                n => n.to_string(),
+            }
+        }
+
+        vec![
+            ("APP_TITLE", self.app_title.clone()),
+            ("DATABASE_URL", self.database_url.clone()),
+            ("HOST", self.host.clone()),
+            ("PORT", self.port.to_string()),
+            ("BASE_URL", self.base_url.clone()),
+            ("ROOT_REDIRECT_URL", self.root_redirect_url.clone()),
+            ("NOT_FOUND_SEARCH_URL", opt(&self.not_found_search_url)),
+            ("DISPLAY_TIMEZONE", self.display_timezone.name().to_owned()),
+            (
+                "FALLBACK_CHECK_MINUTES",
+                self.fallback_check_minutes.to_string(),
+            ),
+            (
+                "STRIP_TRACKING_PARAMS",
+                self.strip_tracking_params.to_string(),
+            ),
+            ("UNICODE_SHORT_CODES", self.unicode_short_codes.to_string()),
+            ("EMOJI_SHORT_CODES", self.emoji_short_codes.to_string()),
+            (
+                "CASE_INSENSITIVE_CODES",
+                self.case_insensitive_codes.to_string(),
+            ),
+            (
+                "SHORT_CODE_STRATEGY",
+                self.short_code_strategy.as_str().into(),
+            ),
+            (
+                "SHORT_CODE_ALPHABET",
+                self.short_code_alphabet.iter().collect(),
+            ),
+            ("SHORT_CODE_LENGTH", self.short_code_length.to_string()),
+            (
+                "SHORT_CODE_WORD_FILTER",
+                self.short_code_word_filter.to_string(),
+            ),
+            (
+                "SHORT_CODE_BLOCKED_WORDS",
+                self.short_code_blocked_words.join(","),
+            ),
+            ("JWT_SECRET", secret(Some(&self.jwt_secret))),
+            (
+                "SESSION_DURATION_HOURS",
+                self.session_duration_hours.to_string(),
+            ),
+            ("SEED_ADMIN_EMAIL", opt(&self.seed_admin_email)),
+            (
+                "SEED_ADMIN_PASSWORD",
+                secret(self.seed_admin_password.as_deref()),
+            ),
+            ("S3_BUCKET", opt(&self.s3_bucket)),
+            ("S3_REGION", opt(&self.s3_region)),
+            ("S3_ENDPOINT", opt(&self.s3_endpoint)),
+            ("S3_ACCESS_KEY", secret(self.s3_access_key.as_deref())),
+            ("S3_SECRET_KEY", secret(self.s3_secret_key.as_deref())),
+            (
+                "UNSPLASH_ACCESS_KEY",
+                secret(self.unsplash_access_key.as_deref()),
+            ),
+            ("PEXELS_API_KEY", secret(self.pexels_api_key.as_deref())),
+            (
+                "SLACK_SIGNING_SECRET",
+                secret(self.slack_signing_secret.as_deref()),
+            ),
+            ("SLACK_LINK_OWNER", opt(&self.slack_link_owner)),
+            // Webhook URLs embed their credentials, so only the count is shown.
+            (
+                "NOTIFY_WEBHOOK_URLS",
+                format!("({} configured)", self.notify_webhook_urls.len()),
+            ),
+            (
+                "NOTIFY_CLICK_MILESTONES",
+                self.notify_click_milestones
+                    .iter()
+                    .map(i64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            (
+                "NOTIFY_TEMPLATE_LINK_CREATED",
+                opt(&self.notify_template_link_created),
+            ),
+            (
+                "NOTIFY_TEMPLATE_LINK_DELETED",
+                opt(&self.notify_template_link_deleted),
+            ),
+            (
+                "NOTIFY_TEMPLATE_CLICK_MILESTONE",
+                opt(&self.notify_template_click_milestone),
+            ),
+            (
+                "TELEGRAM_BOT_TOKEN",
+                secret(self.telegram_bot_token.as_deref()),
+            ),
+            (
+                "TELEGRAM_ALLOWED_CHAT_IDS",
+                self.telegram_allowed_chat_ids
+                    .iter()
+                    .map(i64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ("TELEGRAM_LINK_OWNER", opt(&self.telegram_link_owner)),
+            (
+                "ANALYTICS_PROVIDER",
+                self.analytics_provider
+                    .map_or_else(|| "(unset)".into(), |p| p.as_str().into()),
+            ),
+            ("ANALYTICS_ENDPOINT", self.analytics_endpoint.clone()),
+            ("ANALYTICS_SITE_ID", self.analytics_site_id.clone()),
+            ("GA_MEASUREMENT_ID", opt(&self.ga_measurement_id)),
+            ("GA_API_SECRET", secret(self.ga_api_secret.as_deref())),
+            (
+                "SAFE_BROWSING_API_KEY",
+                secret(self.safe_browsing_api_key.as_deref()),
+            ),
+            ("SENTRY_DSN", secret(self.sentry_dsn.as_deref())),
+            ("SENTRY_ENVIRONMENT", opt(&self.sentry_environment)),
+            // Webhook URLs often embed a token
+            (
+                "ERROR_WEBHOOK_URL",
+                secret(self.error_webhook_url.as_deref()),
+            ),
+            ("SMTP_HOST", opt(&self.smtp_host)),
+            ("SMTP_PORT", self.smtp_port.to_string()),
+            ("SMTP_SECURITY", self.smtp_security.as_str().into()),
+            ("SMTP_USERNAME", opt(&self.smtp_username)),
+            ("SMTP_PASSWORD", secret(self.smtp_password.as_deref())),
+            ("SMTP_FROM", opt(&self.smtp_from)),
+            ("DIGEST_RECIPIENTS", self.digest_recipients.join(",")),
+            ("DIGEST_WEEKDAY", self.digest_weekday.to_string()),
+            ("DIGEST_HOUR", self.digest_hour.to_string()),
+            ("GRAPHQL_ENABLED", self.graphql_enabled.to_string()),
+            ("ACCESS_ALLOW_COUNTRIES", self.country_rules.allow.join(",")),
+            ("ACCESS_DENY_COUNTRIES", self.country_rules.deny.join(",")),
+            (
+                "ACCESS_BLOCKED_MESSAGE",
+                self.access_blocked_message.clone(),
+            ),
+            ("OIDC_ISSUER", opt(&self.oidc_issuer)),
+            ("OIDC_CLIENT_ID", opt(&self.oidc_client_id)),
+            (
+                "OIDC_CLIENT_SECRET",
+                secret(self.oidc_client_secret.as_deref()),
+            ),
+            ("OIDC_PROVIDER_NAME", self.oidc_provider_name.clone()),
+            ("OIDC_ADMIN_EMAILS", self.oidc_admin_emails.join(",")),
+            ("OIDC_ADMIN_DOMAINS", self.oidc_admin_domains.join(",")),
+            ("OIDC_USER_EMAILS", self.oidc_user_emails.join(",")),
+            ("OIDC_USER_DOMAINS", self.oidc_user_domains.join(",")),
+            ("ANALYTICS_ENABLED", self.analytics_enabled.to_string()),
+            ("GEO_ENABLED", self.geo_enabled.to_string()),
+            ("HONOR_DNT", self.honor_dnt.to_string()),
+            (
+                "CLICK_RETENTION_DAYS",
+                self.click_retention_days.to_string(),
+            ),
+            (
+                "INTERNAL_IPS",
+                self.internal_ips
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            (
+                "TRUSTED_PROXIES",
+                self.trusted_proxies
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ("GEO_PROVIDER", self.geo_provider.as_str().into()),
+            ("GEO_TIMEOUT_SECS", self.geo_timeout_secs.to_string()),
+            (
+                "GEO_REQUESTS_PER_MINUTE",
+                self.geo_requests_per_minute
+                    .map_or_else(|| "(provider default)".into(), |n| n.to_string()),
+            ),
+            ("GEO_IP_API_KEY", secret(self.geo_ip_api_key.as_deref())),
+            ("GEO_IPINFO_TOKEN", secret(self.geo_ipinfo_token.as_deref())),
+            (
+                "GEO_IPDATA_API_KEY",
+                secret(self.geo_ipdata_api_key.as_deref()),
+            ),
+            ("GEO_MAXMIND_CITY_DB", opt(&self.geo_maxmind_city_db)),
+            ("GEO_MAXMIND_ASN_DB", opt(&self.geo_maxmind_asn_db)),
+            ("GEO_CACHE_TTL_DAYS", self.geo_cache_ttl_days.to_string()),
+            (
+                "OUTBOUND_TIMEOUT_SECS",
+                self.outbound_timeout_secs.to_string(),
+            ),
+            ("OUTBOUND_USER_AGENT", self.outbound_user_agent.clone()),
+            // Proxy URLs may embed credentials
+            ("OUTBOUND_PROXY", secret(self.outbound_proxy.as_deref())),
+            (
+                "REQUEST_TIMEOUT_SECS",
+                self.request_timeout_secs.to_string(),
+            ),
+            ("FORM_BODY_LIMIT_KB", self.form_body_limit_kb.to_string()),
+            ("UPLOAD_LIMIT_MB", self.upload_limit_mb.to_string()),
+            (
+                "REDIRECT_LOOKUP_BUDGET_MS",
+                self.redirect_lookup_budget_ms.to_string(),
+            ),
+            ("SCANNER_MISS_LIMIT", self.scanner_miss_limit.to_string()),
+            ("SCANNER_BAN_MINUTES", self.scanner_ban_minutes.to_string()),
+            ("QUOTA_MAX_LINKS", unlimited(self.quota_max_links)),
+            (
+                "QUOTA_API_REQUESTS_PER_HOUR",
+                unlimited(self.quota_api_requests_per_hour),
+            ),
+            (
+                "QUOTA_API_KEY_REQUESTS_PER_HOUR",
+                unlimited(self.quota_api_key_requests_per_hour),
+            ),
+            ("PUBLIC_SHORTEN", self.public_shorten.to_string()),
+            ("PUBLIC_SHORTEN_OWNER", opt(&self.public_shorten_owner)),
+            (
+                "PUBLIC_SHORTEN_PER_HOUR",
+                unlimited(self.public_shorten_per_hour),
+            ),
+            ("TURNSTILE_SITE_KEY", opt(&self.turnstile_site_key)),
+            (
+                "TURNSTILE_SECRET_KEY",
+                secret(self.turnstile_secret_key.as_deref()),
+            ),
+            // Services usually take their API key in the URL
+            ("SCREENSHOT_URL", secret(self.screenshot_url.as_deref())),
+            ("FAVICONS", self.favicons.to_string()),
+            (
+                "ARCHIVED_LINKS_REDIRECT",
+                self.archived_links_redirect.to_string(),
+            ),
+            ("BACKUP_DIR", opt(&self.backup_dir)),
+            ("BACKUP_S3", self.backup_s3.to_string()),
+            (
+                "BACKUP_INTERVAL_HOURS",
+                self.backup_interval_hours.to_string(),
+            ),
+            ("BACKUP_KEEP", self.backup_keep.to_string()),
+            (
+                "WAL_CHECKPOINT_MINUTES",
+                self.wal_checkpoint_minutes.to_string(),
+            ),
+            (
+                "LINK_CACHE_MAX_ENTRIES",
+                self.link_cache_max_entries.to_string(),
+            ),
+            (
+                "LINK_CACHE_TTL_MINUTES",
+                self.link_cache_ttl_minutes.to_string(),
+            ),
+            (
+                "LINK_CACHE_RESYNC_MINUTES",
+                self.link_cache_resync_minutes.to_string(),
+            ),
+            // Redis URLs may embed a password
+            ("CACHE_BUS_URL", secret(self.cache_bus_url.as_deref())),
+            ("RUST_LOG", self.log_filter.clone()),
+        ]
+    }
+}
+
+// ── Config sources ────────────────────────────────────────────────────────
+
+#[cfg(test)]
+impl AppConfig {
+    /// A configuration with `settings` as if read from a config file (the
+    /// environment still wins), for tests.
+    pub fn from_settings(settings: &[(&str, &str)]) -> Self {
+        let source = ConfigSource {
+            file: settings
+                .iter()
+                .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+                .collect(),
+            ..ConfigSource::default()
+        };
+        Self::from_source(&source).expect("test settings are valid")
+    }
+}
+
+/// Raw configuration values keyed by environment-variable name.
+///
+/// Lookups check the process environment first and then the config file, so
+/// any file value can be overridden per-deployment with an env var.
+#[derive(Default)]
+struct ConfigSource {
+    file: BTreeMap<String, String>,
+    /// `.env` as re-read by [`AppConfig::reload`]; `None` when `.env` was
+    /// loaded into the environment at startup instead.
+    dotenv: Option<BTreeMap<String, String>>,
+}
+
+impl ConfigSource {
+    /// Parse a TOML file. Top-level keys map to the upper-cased env var name
+    /// (`base_url` → `BASE_URL`); keys inside a table are prefixed with the
+    /// table name (`[s3] bucket` → `S3_BUCKET`).
+    fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let table: toml::Table = text
+            .parse()
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+        let mut file = BTreeMap::new();
+        flatten_toml("", &table, &mut file)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+        Ok(Self {
+            file,
+            ..Self::default()
+        })
+    }
+
+    /// Same contract as `std::env::var`, with the config file as a fallback.
+    fn var(&self, name: &str) -> Result<String, VarError> {
+        debug_assert!(SETTINGS.contains(&name), "{name} is missing from SETTINGS");
+        // On reload, only the process's own variables come from the
+        // environment; the rest were copied from the old `.env`
+        if let Some(dotenv) = &self.dotenv {
+            if !PROCESS_ENV.get().is_some_and(|names| names.contains(name)) {
+                return dotenv
+                    .get(name)
+                    .or_else(|| self.file.get(name))
+                    .cloned()
+                    .ok_or(VarError::NotPresent);
+            }
+        }
+        match std::env::var(name) {
+            Err(VarError::NotPresent) => self.file.get(name).cloned().ok_or(VarError::NotPresent),
+            other => other,
+        }
+    }
+
+    /// A true/false setting: `true`, `1`, `yes` or `on`, or `false`, `0`,
+    /// `no` or `off`, in any case. Unset or empty is `default`.
+    fn flag(&self, name: &str, default: bool) -> Result<bool> {
+        match self
+            .var(name)
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" => Ok(default),
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            other => anyhow::bail!("{name} must be true or false, got '{other}'"),
+        }
+    }
+
+    /// A comma-separated list of IP addresses and CIDR ranges in `name`.
+    fn ip_list(&self, name: &str) -> Result<Vec<ipnet::IpNet>> {
+        let mut nets = Vec::new();
+        for entry in self.var(name).unwrap_or_default().split(',') {
+            let entry = entry.trim();
+            if entry.is_empty() {
+                continue;
+            }
+            let net = match (entry.parse::<ipnet::IpNet>(), entry.parse::<IpAddr>()) {
+                (Ok(net), _) => net.trunc(),
+                (_, Ok(ip)) => ip.into(),
+                _ => anyhow::bail!(
+                    "{name} entry '{entry}' must be an IP address or CIDR range, \
+                     e.g. 203.0.113.7 or 10.0.0.0/8"
+                ),
+            };
+            nets.push(net);
+        }
+        Ok(nets)
+    }
+
+    /// Fail if the config file contains keys that aren't settings.
+    fn check_unknown_keys(&self) -> Result<()> {
+        let unknown: Vec<String> = self
+            .file
+            .keys()
+            .filter(|k| !SETTINGS.contains(&k.as_str()))
+            .map(|k| k.to_lowercase())
+            .collect();
+        if !unknown.is_empty() {
+            anyhow::bail!("Unknown config file key(s): {}", unknown.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// The variables in `.env`, or none when there is no `.env`.
+fn read_dotenv() -> Result<BTreeMap<String, String>> {
+    match dotenvy::dotenv_iter() {
+        Ok(iter) => iter
+            .collect::<Result<_, _>>()
+            .context("Failed to read .env"),
+        Err(e) if e.not_found() => Ok(BTreeMap::new()),
+        Err(e) => Err(e).context("Failed to read .env"),
+    }
+}
+
+fn flatten_toml(
+    prefix: &str,
+    table: &toml::Table,
+    out: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    for (key, value) in table {
+        let name = if prefix.is_empty() {
+            key.to_uppercase()
+        } else {
+            format!("{prefix}_{}", key.to_uppercase())
+        };
+        let value = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            toml::Value::Table(t) => {
+                flatten_toml(&name, t, out)?;
+                continue;
+            }
+            toml::Value::Array(_) | toml::Value::Datetime(_) => {
+                anyhow::bail!(
+                    "`{}` must be a string, number, or boolean",
+                    name.to_lowercase()
+                )
+            }
+        };
+        out.insert(name, value);
+    }
+    Ok(())
 }
@@ -1,9 +1,14 @@
 use crate::{
-    cache::LinkCache,
-    models::{AnalyticsSummary, Click, Link, LinkWithStats},
+    beacon::ClientInfo,
+    cache::{CachedLink, LinkCache},
+    db_rollups,
+    geo::GeoInfo,
+    import::ImportedLink,
+    models::{AnalyticsSummary, Click, ComparisonTotals, Link, LinkWithStats, EVENT_CLICK},
 };
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
 
 type LinkStatsRow = (
     i64,
@@ -15,6 +20,11 @@ type LinkStatsRow = (
     bool,
     i64,
     Option<i64>,
+    Option<i64>,
+    Option<String>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
+    Option<NaiveDateTime>,
 );
 
 type ClickActivityRow = (
@@ -26,31 +36,200 @@ type ClickActivityRow = (
     Option<String>,
 );
 
-const LINK_COLUMNS: &str =
-    "id, short_code, original_url, title, description, created_at, is_active, user_id";
+const LINK_COLUMNS: &str = "id, short_code, original_url, title, description, created_at, \
+                            is_active, user_id, domain_id, stats_token, client_redirect, \
+                            allow_countries, deny_countries, workspace_id, social_title, \
+                            social_description, social_image_url, archived_at, deleted_at, \
+                            imported_clicks, click_limit_per_minute, click_limit_page, notes, \
+                            language_rules, schedule_rules, fallback_url, \
+                            destination_down_since, variants, sticky_variants";
+
+/// Clicks on link `l` that were pruned into `event_rollups`, not counting
+/// internal ones.
+const ROLLED_UP_CLICKS_SQL: &str = "(SELECT COALESCE(SUM(r.events), 0) FROM event_rollups r
+     WHERE r.link_id = l.id AND r.event_type = 'click' AND r.internal = 0)";
+
+/// Order of the short links list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkSort {
+    /// Newest first.
+    #[default]
+    Created,
+    /// Most clicked first.
+    Clicks,
+    /// Most recently clicked first; never-clicked links last.
+    LastClicked,
+    /// Alphabetical by short code.
+    Code,
+}
+
+impl LinkSort {
+    /// The order named by a `sort` query parameter.
+    pub fn from_param(value: &str) -> Option<Self> {
+        match value {
+            "created" => Some(Self::Created),
+            "clicks" => Some(Self::Clicks),
+            "last_clicked" => Some(Self::LastClicked),
+            "code" => Some(Self::Code),
+            _ => None,
+        }
+    }
+
+    pub fn as_param(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Clicks => "clicks",
+            Self::LastClicked => "last_clicked",
+            Self::Code => "code",
+        }
+    }
+
+    fn order_by(self) -> &'static str {
+        match self {
+            Self::Created => "l.created_at DESC, l.id DESC",
+            Self::Clicks => "click_count DESC, l.created_at DESC",
+            Self::LastClicked => {
+                "l.last_clicked_at IS NULL, l.last_clicked_at DESC, l.created_at DESC"
+            }
+            Self::Code => "l.short_code ASC",
+        }
+    }
+}
+
+/// The rows a listing or total covers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scope {
+    /// Restrict to one workspace (`None` = every workspace, for the CLI).
+    pub workspace_id: Option<i64>,
+    /// Restrict to one user's rows (`None` = the whole workspace, for admins).
+    pub user_id: Option<i64>,
+}
+
+/// A link for [`create_links`] to insert.
+#[derive(Debug, Clone)]
+pub struct NewLinkRow {
+    pub short_code: String,
+    pub original_url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub domain_id: Option<i64>,
+}
 
 // ── Warm-up ────────────────────────────────────────────────────────────────
 
-/// Load every active link into the in-memory cache at startup.
-pub async fn warm_cache(pool: &SqlitePool, cache: &LinkCache) -> anyhow::Result<()> {
+/// Load active links into the in-memory cache at startup, archived ones only
+/// if `include_archived`: up to `limit` of them, most recently clicked first.
+pub async fn warm_cache(
+    pool: &SqlitePool,
+    cache: &LinkCache,
+    include_archived: bool,
+    limit: u64,
+) -> anyhow::Result<()> {
     let links: Vec<Link> = sqlx::query_as(&format!(
-        "SELECT {LINK_COLUMNS} FROM links WHERE is_active = 1"
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE is_active = 1 AND deleted_at IS NULL AND (?1 OR archived_at IS NULL)
+         ORDER BY COALESCE(last_clicked_at, created_at) DESC
+         LIMIT ?2"
     ))
+    .bind(include_archived)
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await?;
+
+    let aliases: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT a.short_code, a.link_id
+         FROM link_aliases a
+         JOIN links l ON l.id = a.link_id
+         WHERE l.is_active = 1 AND l.deleted_at IS NULL AND (?1 OR l.archived_at IS NULL)",
+    )
+    .bind(include_archived)
     .fetch_all(pool)
     .await?;
 
     let count = links.len();
-    for link in links {
-        cache.set(link.short_code, link.original_url);
+    let by_id: HashMap<i64, &Link> = links.iter().map(|l| (l.id, l)).collect();
+    for (alias, link_id) in aliases {
+        if let Some(link) = by_id.get(&link_id) {
+            cache.fill(link.domain_id, alias, CachedLink::from(*link));
+        }
     }
+    for link in &links {
+        cache.fill(link.domain_id, &link.short_code, CachedLink::from(link));
+    }
+    cache.mark_warm();
 
     tracing::info!("Cache warmed with {} active link(s)", count);
     Ok(())
 }
 
+/// Drop cached entries that no longer match the database: links deactivated,
+/// deleted, archived (unless `include_archived`), moved, or edited without
+/// the cache being told, and aliases since removed. Current entries are left
+/// alone, so they still expire as usual. Returns how many were dropped.
+pub async fn resync_cache(
+    pool: &SqlitePool,
+    cache: &LinkCache,
+    include_archived: bool,
+) -> anyhow::Result<usize> {
+    let entries = cache.entries();
+    let ids: HashSet<i64> = entries.iter().map(|(_, entry)| entry.id).collect();
+    let ids = serde_json::to_string(&ids)?;
+
+    let links: Vec<Link> = sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE id IN (SELECT value FROM json_each(?1))
+           AND is_active = 1 AND deleted_at IS NULL AND (?2 OR archived_at IS NULL)"
+    ))
+    .bind(&ids)
+    .bind(include_archived)
+    .fetch_all(pool)
+    .await?;
+    let aliases: HashSet<(String, i64)> = sqlx::query_as(
+        "SELECT short_code, link_id FROM link_aliases
+         WHERE link_id IN (SELECT value FROM json_each(?1))",
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let by_id: HashMap<i64, &Link> = links.iter().map(|l| (l.id, l)).collect();
+    let mut dropped = 0;
+    for ((domain_id, code), entry) in entries {
+        let current = by_id
+            .get(&entry.id)
+            .filter(|link| {
+                link.domain_id == domain_id
+                    && (link.short_code == code || aliases.contains(&(code.clone(), link.id)))
+            })
+            .map(|link| CachedLink::from(*link));
+        if current.as_ref() != Some(&entry) {
+            cache.evict(domain_id, &code);
+            dropped += 1;
+        }
+    }
+    Ok(dropped)
+}
+
+/// Cheap connectivity probe used by the readiness check.
+/// Whether `e` is a UNIQUE constraint failing, e.g. a short code that's
+/// already taken.
+pub fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .is_some_and(|e| e.is_unique_violation())
+}
+
+pub async fn ping(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
+
 // ── Links ──────────────────────────────────────────────────────────────────
 
-/// Insert a new link and return the newly created row.
+/// Insert a new link in its owner's workspace and return the newly created
+/// row.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_link(
     pool: &SqlitePool,
     short_code: &str,
@@ -58,16 +237,19 @@ pub async fn create_link(
     title: Option<&str>,
     description: Option<&str>,
     user_id: i64,
+    domain_id: Option<i64>,
 ) -> Result<Link, sqlx::Error> {
     let id = sqlx::query(
-        "INSERT INTO links (short_code, original_url, title, description, user_id)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT INTO links (short_code, original_url, title, description, user_id, domain_id,
+                            workspace_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT workspace_id FROM users WHERE id = ?5))",
     )
     .bind(short_code)
     .bind(original_url)
     .bind(title)
     .bind(description)
     .bind(user_id)
+    .bind(domain_id)
     .execute(pool)
     .await?
     .last_insert_rowid();
@@ -80,46 +262,259 @@ pub async fn create_link(
     Ok(link)
 }
 
-/// Fetch a single active link by its short code (for public redirect, no user scoping).
+/// Create `links` for `user_id` in one transaction, returning each link in
+/// order. A link whose short code is taken fails on its own; any other error
+/// rolls back the lot.
+pub async fn create_links(
+    pool: &SqlitePool,
+    user_id: i64,
+    links: &[NewLinkRow],
+) -> Result<Vec<Result<Link, sqlx::Error>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut created = Vec::with_capacity(links.len());
+    for link in links {
+        let inserted = sqlx::query(
+            "INSERT INTO links (short_code, original_url, title, description, user_id, domain_id,
+                                workspace_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT workspace_id FROM users WHERE id = ?5))",
+        )
+        .bind(&link.short_code)
+        .bind(&link.original_url)
+        .bind(&link.title)
+        .bind(&link.description)
+        .bind(user_id)
+        .bind(link.domain_id)
+        .execute(&mut *tx)
+        .await;
+        let id = match inserted {
+            Ok(done) => done.last_insert_rowid(),
+            Err(e) if is_unique_violation(&e) => {
+                created.push(Err(e));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let link: Link = sqlx::query_as(&format!("SELECT {LINK_COLUMNS} FROM links WHERE id = ?1"))
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+        created.push(Ok(link));
+    }
+    tx.commit().await?;
+    Ok(created)
+}
+
+/// Create a link from another shortener's export (see `import`), keeping
+/// its creation time and seeding `imported_clicks` with its click count.
+pub async fn create_imported_link(
+    pool: &SqlitePool,
+    short_code: &str,
+    link: &ImportedLink,
+    user_id: i64,
+    domain_id: Option<i64>,
+) -> Result<Link, sqlx::Error> {
+    let id = sqlx::query(
+        "INSERT INTO links (short_code, original_url, title, user_id, domain_id, workspace_id,
+                            created_at, imported_clicks)
+         VALUES (?1, ?2, ?3, ?4, ?5, (SELECT workspace_id FROM users WHERE id = ?4),
+                 COALESCE(?6, strftime('%Y-%m-%dT%H:%M:%SZ', 'now')), ?7)",
+    )
+    .bind(short_code)
+    .bind(&link.url)
+    .bind(&link.title)
+    .bind(user_id)
+    .bind(domain_id)
+    .bind(
+        link.created_at
+            .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+    )
+    .bind(link.clicks)
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    sqlx::query_as(&format!("SELECT {LINK_COLUMNS} FROM links WHERE id = ?1"))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+/// Whether `short_code` belongs to any link, including ones in the trash,
+/// or alias.
+pub async fn short_code_taken(pool: &SqlitePool, short_code: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM links WHERE short_code = ?1)
+             OR EXISTS (SELECT 1 FROM link_aliases WHERE short_code = ?1)",
+    )
+    .bind(short_code)
+    .fetch_one(pool)
+    .await
+}
+
+/// Copy link `from` to a new link with `short_code`, owned by `user_id`: its
+/// destination, title, description, domain, redirect mode, country, language
+/// and schedule rules, fallback URL, variants, social preview and click limit. Clicks, aliases and sharing aren't copied.
+pub async fn duplicate_link(
+    pool: &SqlitePool,
+    from: i64,
+    short_code: &str,
+    user_id: i64,
+) -> Result<Link, sqlx::Error> {
+    let id = sqlx::query(
+        "INSERT INTO links (short_code, original_url, title, description, user_id, domain_id,
+                            workspace_id, client_redirect, allow_countries, deny_countries,
+                            social_title, social_description, social_image_url,
+                            click_limit_per_minute, click_limit_page, language_rules,
+                            schedule_rules, fallback_url, variants, sticky_variants)
+         SELECT ?2, original_url, title, description, ?3, domain_id,
+                (SELECT workspace_id FROM users WHERE id = ?3), client_redirect,
+                allow_countries, deny_countries, social_title, social_description,
+                social_image_url, click_limit_per_minute, click_limit_page, language_rules,
+                schedule_rules, fallback_url, variants, sticky_variants
+         FROM links WHERE id = ?1",
+    )
+    .bind(from)
+    .bind(short_code)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    sqlx::query_as(&format!("SELECT {LINK_COLUMNS} FROM links WHERE id = ?1"))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+/// A user's existing active link to `original_url` on `domain_id`, returned
+/// instead of creating a duplicate when no custom code is asked for. Links in
+/// the trash don't count.
+pub async fn find_duplicate_link(
+    pool: &SqlitePool,
+    user_id: i64,
+    domain_id: Option<i64>,
+    original_url: &str,
+) -> Result<Option<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE user_id = ?1 AND domain_id IS ?2 AND original_url = ?3 AND is_active = 1
+           AND deleted_at IS NULL
+         ORDER BY id ASC LIMIT 1"
+    ))
+    .bind(user_id)
+    .bind(domain_id)
+    .bind(original_url)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Take the next number from the sequential code counter.
+pub async fn next_code_number(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE short_code_sequence SET next_value = next_value + 1
+         WHERE id = 1
+         RETURNING next_value - 1",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Fetch a single active link by its short code or one of its aliases (for
+/// public redirect, no user scoping). Links in the trash are included, as
+/// their codes stay taken until they are purged.
 pub async fn get_link_by_code(
     pool: &SqlitePool,
     short_code: &str,
 ) -> Result<Option<Link>, sqlx::Error> {
     sqlx::query_as(&format!(
-        "SELECT {LINK_COLUMNS} FROM links WHERE short_code = ?1 AND is_active = 1"
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE is_active = 1
+           AND (short_code = ?1
+                OR id = (SELECT link_id FROM link_aliases WHERE short_code = ?1))"
     ))
     .bind(short_code)
     .fetch_optional(pool)
     .await
 }
 
-/// Return all links joined with their total click counts, newest first.
-/// When `user_id_filter` is Some, only return links owned by that user.
-/// When None (admin), return all links.
+/// The code or alias that matches `short_code` ignoring ASCII case, if
+/// exactly one does (for `CASE_INSENSITIVE_CODES`).
+pub async fn find_code_ignoring_case(
+    pool: &SqlitePool,
+    short_code: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let codes: Vec<String> = sqlx::query_scalar(
+        "SELECT short_code FROM links WHERE short_code = ?1 COLLATE NOCASE
+         UNION
+         SELECT short_code FROM link_aliases WHERE short_code = ?1 COLLATE NOCASE
+         LIMIT 2",
+    )
+    .bind(short_code)
+    .fetch_all(pool)
+    .await?;
+    Ok(match <[String; 1]>::try_from(codes) {
+        Ok([code]) => Some(code),
+        Err(_) => None,
+    })
+}
+
+/// Fetch a link by its short code or one of its aliases regardless of
+/// status, unless it is in the trash (for `/api/v1/expand`).
+pub async fn find_link_by_code_or_alias(
+    pool: &SqlitePool,
+    short_code: &str,
+) -> Result<Option<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE deleted_at IS NULL
+           AND (short_code = ?1
+                OR id = (SELECT link_id FROM link_aliases WHERE short_code = ?1))"
+    ))
+    .bind(short_code)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Fetch a link by its short code regardless of status, unless it is in the
+/// trash (for CLI/admin lookups).
+pub async fn find_link_by_code(
+    pool: &SqlitePool,
+    short_code: &str,
+) -> Result<Option<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links WHERE short_code = ?1 AND deleted_at IS NULL"
+    ))
+    .bind(short_code)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Return the links in `scope` joined with their total click counts
+/// (including imported ones), in `sort` order.
 pub async fn get_all_links_with_stats(
     pool: &SqlitePool,
-    user_id_filter: Option<i64>,
+    scope: Scope,
+    sort: LinkSort,
 ) -> Result<Vec<LinkWithStats>, sqlx::Error> {
-    let (where_clause, bind_val) = match user_id_filter {
-        Some(uid) => ("WHERE l.user_id = ?1", Some(uid)),
-        None => ("", None),
-    };
-
-    let sql = format!(
+    let rows: Vec<LinkStatsRow> = sqlx::query_as(&format!(
         "SELECT l.id, l.short_code, l.original_url, l.title, l.description,
-                l.created_at, l.is_active, COUNT(c.id) as click_count, l.user_id
+                l.created_at, l.is_active,
+                COUNT(c.id) + l.imported_clicks + {ROLLED_UP_CLICKS_SQL} as click_count,
+                l.user_id, l.domain_id, d.base_url, l.archived_at, l.first_clicked_at,
+                l.last_clicked_at
          FROM links l
-         LEFT JOIN clicks c ON c.link_id = l.id
-         {where_clause}
+         LEFT JOIN events c ON c.link_id = l.id AND c.event_type = 'click' AND c.internal = 0
+         LEFT JOIN domains d ON d.id = l.domain_id
+         WHERE (?1 IS NULL OR l.workspace_id = ?1) AND (?2 IS NULL OR l.user_id = ?2)
+           AND l.deleted_at IS NULL
          GROUP BY l.id
-         ORDER BY l.created_at DESC"
-    );
-
-    let rows: Vec<LinkStatsRow> = if let Some(uid) = bind_val {
-        sqlx::query_as(&sql).bind(uid).fetch_all(pool).await?
-    } else {
-        sqlx::query_as(&sql).fetch_all(pool).await?
-    };
+         ORDER BY {}",
+        sort.order_by()
+    ))
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(rows
         .into_iter()
@@ -134,6 +529,11 @@ pub async fn get_all_links_with_stats(
                 is_active,
                 click_count,
                 user_id,
+                domain_id,
+                domain_base_url,
+                archived_at,
+                first_clicked_at,
+                last_clicked_at,
             )| {
                 LinkWithStats {
                     id,
@@ -145,24 +545,221 @@ pub async fn get_all_links_with_stats(
                     is_active,
                     click_count,
                     user_id,
+                    domain_id,
+                    domain_base_url,
+                    archived_at,
+                    first_clicked_at,
+                    last_clicked_at,
                 }
             },
         )
         .collect())
 }
 
-/// Fetch a single link by its primary key (any status).
+/// Fetch a single link by its primary key (any status, but not from the
+/// trash).
 pub async fn get_link_by_id(pool: &SqlitePool, id: i64) -> Result<Option<Link>, sqlx::Error> {
-    sqlx::query_as(&format!("SELECT {LINK_COLUMNS} FROM links WHERE id = ?1"))
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links WHERE id = ?1 AND deleted_at IS NULL"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Fetch a link in the trash by its primary key.
+pub async fn get_trashed_link(pool: &SqlitePool, id: i64) -> Result<Option<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links WHERE id = ?1 AND deleted_at IS NOT NULL"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// The `limit` most recently created links in `scope`, newest first.
+pub async fn recent_links(
+    pool: &SqlitePool,
+    scope: Scope,
+    limit: i64,
+) -> Result<Vec<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE deleted_at IS NULL
+           AND (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+         ORDER BY created_at DESC, id DESC
+         LIMIT ?3"
+    ))
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// The links in `scope` that are in the trash, most recently deleted first.
+pub async fn get_trashed_links(pool: &SqlitePool, scope: Scope) -> Result<Vec<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE deleted_at IS NOT NULL
+           AND (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+         ORDER BY deleted_at DESC"
+    ))
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Set or clear (`None`) the public stats share token for a link.
+pub async fn set_stats_token(
+    pool: &SqlitePool,
+    id: i64,
+    token: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("UPDATE links SET stats_token = ?2 WHERE id = ?1")
         .bind(id)
-        .fetch_optional(pool)
-        .await
+        .bind(token)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(affected > 0)
 }
 
-/// Permanently delete a link (cascades to clicks via FK).
-pub async fn delete_link(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
-    let affected = sqlx::query("DELETE FROM links WHERE id = ?1")
+/// Replace a link's title and description (`None` clears them) and turn it
+/// on or off.
+pub async fn update_link_details(
+    pool: &SqlitePool,
+    id: i64,
+    title: Option<&str>,
+    description: Option<&str>,
+    is_active: bool,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE links SET title = ?2, description = ?3, is_active = ?4
+         WHERE id = ?1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .bind(title)
+    .bind(description)
+    .bind(is_active)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Replace a link's notes (`None` clears them).
+pub async fn set_link_notes(
+    pool: &SqlitePool,
+    id: i64,
+    notes: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("UPDATE links SET notes = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(notes)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Switch a link between a 302 and a client-side redirect.
+pub async fn set_client_redirect(
+    pool: &SqlitePool,
+    id: i64,
+    enabled: bool,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("UPDATE links SET client_redirect = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(enabled)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Archive a link, or restore it to the default list (`false`).
+pub async fn set_archived(pool: &SqlitePool, id: i64, archived: bool) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE links
+         SET archived_at = CASE WHEN ?2 THEN strftime('%Y-%m-%dT%H:%M:%SZ', 'now') END
+         WHERE id = ?1",
+    )
+    .bind(id)
+    .bind(archived)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Move a link to another short domain (`None` for the primary domain).
+/// Its aliases follow, since they resolve through the link.
+pub async fn set_link_domain(
+    pool: &SqlitePool,
+    id: i64,
+    domain_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("UPDATE links SET domain_id = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(domain_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Replace a link's country allow and deny lists (`None` clears a list).
+pub async fn set_country_rules(
+    pool: &SqlitePool,
+    id: i64,
+    allow: Option<&str>,
+    deny: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected =
+        sqlx::query("UPDATE links SET allow_countries = ?2, deny_countries = ?3 WHERE id = ?1")
+            .bind(id)
+            .bind(allow)
+            .bind(deny)
+            .execute(pool)
+            .await?
+            .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Replace a link's language rules (`None` clears them).
+pub async fn set_language_rules(
+    pool: &SqlitePool,
+    id: i64,
+    rules: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("UPDATE links SET language_rules = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(rules)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Replace a link's schedule rules (`None` clears them).
+pub async fn set_schedule_rules(
+    pool: &SqlitePool,
+    id: i64,
+    rules: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("UPDATE links SET schedule_rules = ?2 WHERE id = ?1")
         .bind(id)
+        .bind(rules)
         .execute(pool)
         .await?
         .rows_affected();
@@ -170,11 +767,249 @@ pub async fn delete_link(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error
     Ok(affected > 0)
 }
 
-// ── Clicks ─────────────────────────────────────────────────────────────────
+/// Set where a link sends visitors while its destination is down. Removing
+/// it (`None`) also forgets that the destination was down, since only links
+/// with a fallback are checked.
+pub async fn set_fallback_url(
+    pool: &SqlitePool,
+    id: i64,
+    url: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE links SET fallback_url = ?2,
+             destination_down_since = CASE WHEN ?2 IS NULL THEN NULL ELSE destination_down_since END
+         WHERE id = ?1",
+    )
+    .bind(id)
+    .bind(url)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Links the health checker watches: those with a fallback URL that are
+/// active and not in the trash.
+pub async fn get_links_with_fallback(pool: &SqlitePool) -> Result<Vec<Link>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {LINK_COLUMNS} FROM links
+         WHERE fallback_url IS NOT NULL AND is_active = 1 AND deleted_at IS NULL
+         ORDER BY id"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// Record that a link's destination went down (now) or came back up.
+pub async fn set_destination_down(
+    pool: &SqlitePool,
+    id: i64,
+    down: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE links SET destination_down_since =
+             CASE WHEN ?2 THEN strftime('%Y-%m-%dT%H:%M:%SZ', 'now') END
+         WHERE id = ?1",
+    )
+    .bind(id)
+    .bind(down)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Replace the destinations a link's visitors are split between (`None`
+/// removes them) and whether each visitor keeps getting the same one.
+pub async fn set_variants(
+    pool: &SqlitePool,
+    id: i64,
+    variants: Option<&str>,
+    sticky: bool,
+) -> Result<bool, sqlx::Error> {
+    let affected =
+        sqlx::query("UPDATE links SET variants = ?2, sticky_variants = ?3 WHERE id = ?1")
+            .bind(id)
+            .bind(variants)
+            .bind(sticky)
+            .execute(pool)
+            .await?
+            .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Set a link's cap on clicks per minute (`None` removes it) and whether
+/// visitors over it get the waiting page.
+pub async fn set_click_limit(
+    pool: &SqlitePool,
+    id: i64,
+    per_minute: Option<i64>,
+    page: bool,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE links SET click_limit_per_minute = ?2, click_limit_page = ?3 WHERE id = ?1",
+    )
+    .bind(id)
+    .bind(per_minute)
+    .bind(page)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Replace a link's social preview overrides (`None` clears a field).
+pub async fn set_social_preview(
+    pool: &SqlitePool,
+    id: i64,
+    title: Option<&str>,
+    description: Option<&str>,
+    image_url: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE links SET social_title = ?2, social_description = ?3, social_image_url = ?4
+         WHERE id = ?1",
+    )
+    .bind(id)
+    .bind(title)
+    .bind(description)
+    .bind(image_url)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Move a link to the trash. It keeps its clicks and aliases until it is
+/// purged.
+pub async fn delete_link(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE links SET deleted_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+         WHERE id = ?1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Take a link back out of the trash.
+pub async fn restore_link(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let affected =
+        sqlx::query("UPDATE links SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL")
+            .bind(id)
+            .execute(pool)
+            .await?
+            .rows_affected();
+
+    Ok(affected > 0)
+}
+
+/// Permanently delete the links moved to the trash before `before` (cascades
+/// to clicks and aliases via FK). Returns how many were deleted.
+pub async fn purge_deleted_links(
+    pool: &SqlitePool,
+    before: NaiveDateTime,
+) -> Result<u64, sqlx::Error> {
+    let affected = sqlx::query("DELETE FROM links WHERE deleted_at < ?1")
+        .bind(before.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(affected)
+}
+
+/// Merge link `from` into link `into_id`: move its clicks and aliases over,
+/// delete it, and if `keep_code` add its short code as an alias of
+/// `into_id`. Returns the codes that now resolve to `into_id` as a result.
+pub async fn merge_links(
+    pool: &SqlitePool,
+    into_id: i64,
+    from: &Link,
+    keep_code: bool,
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE events SET link_id = ?1 WHERE link_id = ?2")
+        .bind(into_id)
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
 
-/// Record a click event.
+    // Pruned clicks are gone from `events`, so the links' own first and last
+    // click times count too
+    sqlx::query(
+        "UPDATE links SET (first_clicked_at, last_clicked_at) = (
+             SELECT MIN(t), MAX(t) FROM (
+                 SELECT clicked_at AS t FROM events
+                 WHERE link_id = ?1 AND event_type = 'click' AND internal = 0
+                 UNION ALL SELECT first_clicked_at FROM links WHERE id IN (?1, ?3)
+                 UNION ALL SELECT last_clicked_at FROM links WHERE id IN (?1, ?3)
+             )
+         ), imported_clicks = imported_clicks + ?2
+         WHERE id = ?1",
+    )
+    .bind(into_id)
+    .bind(from.imported_clicks)
+    .bind(from.id)
+    .execute(&mut *tx)
+    .await?;
+
+    let mut codes: Vec<String> = sqlx::query_scalar(
+        "UPDATE link_aliases SET link_id = ?1 WHERE link_id = ?2 RETURNING short_code",
+    )
+    .bind(into_id)
+    .bind(from.id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for table in ["event_rollups", "click_rollup_values"] {
+        sqlx::query(&format!(
+            "UPDATE {table} SET link_id = ?1 WHERE link_id = ?2"
+        ))
+        .bind(into_id)
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // Funnels keep their step; its clicks are `into_id`'s now
+    sqlx::query("UPDATE funnel_steps SET link_id = ?1 WHERE link_id = ?2")
+        .bind(into_id)
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("DELETE FROM links WHERE id = ?1")
+        .bind(from.id)
+        .execute(&mut *tx)
+        .await?;
+
+    if keep_code {
+        sqlx::query("INSERT INTO link_aliases (short_code, link_id) VALUES (?1, ?2)")
+            .bind(&from.short_code)
+            .bind(into_id)
+            .execute(&mut *tx)
+            .await?;
+        codes.push(from.short_code.clone());
+    }
+
+    tx.commit().await?;
+    Ok(codes)
+}
+
+// ── Events ─────────────────────────────────────────────────────────────────
+
+/// Record an event of `event_type` (`models::EVENT_*`) on a link. Only
+/// clicks move its first and last click times.
 #[allow(clippy::too_many_arguments)]
-pub async fn log_click(
+pub async fn log_event(
     pool: &SqlitePool,
     link_id: i64,
     ip_address: Option<&str>,
@@ -183,15 +1018,25 @@ pub async fn log_click(
     browser: Option<&str>,
     os: Option<&str>,
     device_type: Option<&str>,
-    country: Option<&str>,
-    region: Option<&str>,
-    city: Option<&str>,
+    geo: Option<&GeoInfo>,
+    client: &ClientInfo,
+    event_type: &str,
+    click_id: Option<&str>,
+    internal: bool,
+    source: Option<&str>,
+    matched_language: Option<&str>,
+    variant: Option<&str>,
+    visitor_hash: Option<&str>,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO clicks
+        "INSERT INTO events
              (link_id, ip_address, user_agent, referer, browser, os, device_type,
-              country, region, city)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+              country, country_code, continent, region, city, latitude, longitude,
+              asn, isp, datacenter, screen_width, screen_height, language, timezone,
+              event_type, click_id, internal, source, matched_language, variant,
+              visitor_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                 ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28)",
     )
     .bind(link_id)
     .bind(ip_address)
@@ -200,92 +1045,139 @@ pub async fn log_click(
     .bind(browser)
     .bind(os)
     .bind(device_type)
-    .bind(country)
-    .bind(region)
-    .bind(city)
+    .bind(geo.map(|g| g.country.as_str()))
+    .bind(
+        geo.map(|g| g.country_code.as_str())
+            .filter(|c| !c.is_empty()),
+    )
+    .bind(
+        geo.map(|g| g.continent_code.as_str())
+            .filter(|c| !c.is_empty()),
+    )
+    .bind(geo.map(|g| g.region.as_str()))
+    .bind(geo.map(|g| g.city.as_str()))
+    .bind(geo.and_then(|g| g.latitude))
+    .bind(geo.and_then(|g| g.longitude))
+    .bind(geo.and_then(|g| g.asn))
+    .bind(geo.map(|g| g.isp.as_str()).filter(|i| !i.is_empty()))
+    .bind(geo.is_some_and(|g| g.datacenter))
+    .bind(client.screen_width)
+    .bind(client.screen_height)
+    .bind(&client.language)
+    .bind(&client.timezone)
+    .bind(event_type)
+    .bind(click_id)
+    .bind(internal)
+    .bind(source)
+    .bind(matched_language)
+    .bind(variant)
+    .bind(visitor_hash)
     .execute(pool)
     .await?;
 
+    if event_type == EVENT_CLICK && !internal {
+        sqlx::query(
+            "UPDATE links SET last_clicked_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now'),
+                 first_clicked_at = COALESCE(first_clicked_at, strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+             WHERE id = ?1",
+        )
+        .bind(link_id)
+        .execute(pool)
+        .await?;
+    }
+
     Ok(())
 }
 
-/// Count total short links, optionally filtered by user.
-pub async fn count_links(
-    pool: &SqlitePool,
-    user_id_filter: Option<i64>,
-) -> Result<i64, sqlx::Error> {
-    match user_id_filter {
-        Some(uid) => {
-            let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM links WHERE user_id = ?1")
-                .bind(uid)
-                .fetch_one(pool)
-                .await?;
-            Ok(count)
-        }
-        None => {
-            let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM links")
-                .fetch_one(pool)
-                .await?;
-            Ok(count)
-        }
-    }
+/// Total clicks recorded for one link, including pruned ones.
+pub async fn count_link_clicks(pool: &SqlitePool, link_id: i64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM events
+                 WHERE link_id = ?1 AND event_type = 'click' AND internal = 0)
+              + (SELECT COALESCE(SUM(events), 0) FROM event_rollups
+                 WHERE link_id = ?1 AND event_type = 'click' AND internal = 0)",
+    )
+    .bind(link_id)
+    .fetch_one(pool)
+    .await
 }
 
-/// Count total short link clicks, optionally filtered by user.
-pub async fn count_total_clicks(
-    pool: &SqlitePool,
-    user_id_filter: Option<i64>,
-) -> Result<i64, sqlx::Error> {
-    match user_id_filter {
-        Some(uid) => {
-            let (count,): (i64,) = sqlx::query_as(
-                "SELECT COUNT(*) FROM clicks c JOIN links l ON l.id = c.link_id WHERE l.user_id = ?1",
-            )
-            .bind(uid)
-            .fetch_one(pool)
-            .await?;
-            Ok(count)
-        }
-        None => {
-            let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM clicks")
-                .fetch_one(pool)
-                .await?;
-            Ok(count)
-        }
-    }
+/// Count the short links in `scope`.
+pub async fn count_links(pool: &SqlitePool, scope: Scope) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM links
+         WHERE (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+           AND deleted_at IS NULL",
+    )
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_one(pool)
+    .await
 }
 
-/// Top short links by click count, optionally filtered by user.
+/// Count the clicks on short links in `scope`, including imported and
+/// pruned ones.
+pub async fn count_total_clicks(pool: &SqlitePool, scope: Scope) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM events c JOIN links l ON l.id = c.link_id
+                 WHERE (?1 IS NULL OR l.workspace_id = ?1) AND (?2 IS NULL OR l.user_id = ?2)
+                   AND l.deleted_at IS NULL AND c.event_type = 'click' AND c.internal = 0)
+              + (SELECT COALESCE(SUM(r.events), 0) FROM event_rollups r
+                 JOIN links l ON l.id = r.link_id
+                 WHERE (?1 IS NULL OR l.workspace_id = ?1) AND (?2 IS NULL OR l.user_id = ?2)
+                   AND l.deleted_at IS NULL AND r.event_type = 'click' AND r.internal = 0)
+              + (SELECT COALESCE(SUM(imported_clicks), 0) FROM links
+                 WHERE (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+                   AND deleted_at IS NULL)",
+    )
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Top short links in `scope` by click count, counting only clicks from
+/// `since` on when given; all-time counts include imported clicks. Pruned
+/// clicks count from the start of their day.
 pub async fn top_links_by_clicks(
     pool: &SqlitePool,
     limit: i64,
-    user_id_filter: Option<i64>,
+    since: Option<NaiveDateTime>,
+    scope: Scope,
 ) -> Result<Vec<LinkWithStats>, sqlx::Error> {
-    let (where_clause, bind_uid) = match user_id_filter {
-        Some(uid) => ("WHERE l.user_id = ?2", Some(uid)),
-        None => ("", None),
+    // Spelled out rather than `?4 IS NULL OR ...` so SQLite can seek to the
+    // period's first click instead of reading all of them.
+    let period = if since.is_some() {
+        "AND c.clicked_at >= ?4"
+    } else {
+        ""
     };
-
-    let sql = format!(
+    let rows: Vec<LinkStatsRow> = sqlx::query_as(&format!(
         "SELECT l.id, l.short_code, l.original_url, l.title, l.description,
-                l.created_at, l.is_active, COUNT(c.id) as click_count, l.user_id
+                l.created_at, l.is_active,
+                COUNT(c.id) + CASE WHEN ?4 IS NULL THEN l.imported_clicks ELSE 0 END
+                    + (SELECT COALESCE(SUM(r.events), 0) FROM event_rollups r
+                       WHERE r.link_id = l.id AND r.event_type = 'click' AND r.internal = 0
+                         AND (?4 IS NULL OR r.day >= substr(?4, 1, 10)))
+                    as click_count,
+                l.user_id, l.domain_id, d.base_url, l.archived_at, l.first_clicked_at,
+                l.last_clicked_at
          FROM links l
-         LEFT JOIN clicks c ON c.link_id = l.id
-         {where_clause}
+         LEFT JOIN events c ON c.link_id = l.id AND c.event_type = 'click' AND c.internal = 0
+           {period}
+         LEFT JOIN domains d ON d.id = l.domain_id
+         WHERE (?2 IS NULL OR l.workspace_id = ?2) AND (?3 IS NULL OR l.user_id = ?3)
+           AND l.deleted_at IS NULL
          GROUP BY l.id
          ORDER BY click_count DESC
          LIMIT ?1"
-    );
-
-    let rows: Vec<LinkStatsRow> = if let Some(uid) = bind_uid {
-        sqlx::query_as(&sql)
-            .bind(limit)
-            .bind(uid)
-            .fetch_all(pool)
-            .await?
-    } else {
-        sqlx::query_as(&sql).bind(limit).fetch_all(pool).await?
-    };
+    ))
+    .bind(limit)
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .bind(since.map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string()))
+    .fetch_all(pool)
+    .await?;
 
     Ok(rows
         .into_iter()
@@ -300,6 +1192,11 @@ pub async fn top_links_by_clicks(
                 is_active,
                 click_count,
                 user_id,
+                domain_id,
+                domain_base_url,
+                archived_at,
+                first_clicked_at,
+                last_clicked_at,
             )| {
                 LinkWithStats {
                     id,
@@ -311,17 +1208,51 @@ pub async fn top_links_by_clicks(
                     is_active,
                     click_count,
                     user_id,
+                    domain_id,
+                    domain_base_url,
+                    archived_at,
+                    first_clicked_at,
+                    last_clicked_at,
                 }
             },
         )
         .collect())
 }
 
-/// Recent short link clicks with labels for the dashboard.
+/// Active, unarchived links in `scope` that have gone longest without a
+/// click, counting never-clicked links from when they were created.
+pub async fn quiet_links(
+    pool: &SqlitePool,
+    limit: i64,
+    scope: Scope,
+) -> Result<Vec<LinkWithStats>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT l.id, l.short_code, l.original_url, l.title, l.description,
+                l.created_at, l.is_active, COUNT(c.id) + {ROLLED_UP_CLICKS_SQL} AS click_count,
+                l.user_id,
+                l.domain_id, d.base_url AS domain_base_url, l.archived_at,
+                l.first_clicked_at, l.last_clicked_at
+         FROM links l
+         LEFT JOIN events c ON c.link_id = l.id AND c.event_type = 'click' AND c.internal = 0
+         LEFT JOIN domains d ON d.id = l.domain_id
+         WHERE (?2 IS NULL OR l.workspace_id = ?2) AND (?3 IS NULL OR l.user_id = ?3)
+           AND l.is_active = 1 AND l.archived_at IS NULL AND l.deleted_at IS NULL
+         GROUP BY l.id
+         ORDER BY COALESCE(l.last_clicked_at, l.created_at) ASC
+         LIMIT ?1"
+    ))
+    .bind(limit)
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Recent clicks on short links in `scope`, with labels for the dashboard.
 pub async fn recent_clicks_with_labels(
     pool: &SqlitePool,
     limit: i64,
-    user_id_filter: Option<i64>,
+    scope: Scope,
 ) -> Result<
     Vec<(
         String,
@@ -332,29 +1263,19 @@ pub async fn recent_clicks_with_labels(
     )>,
     sqlx::Error,
 > {
-    let (where_clause, bind_uid) = match user_id_filter {
-        Some(uid) => ("WHERE l.user_id = ?2", Some(uid)),
-        None => ("", None),
-    };
-
-    let sql = format!(
+    let rows: Vec<ClickActivityRow> = sqlx::query_as(
         "SELECT l.title, l.short_code, c.clicked_at, c.country, c.browser, c.referer
-         FROM clicks c
-         JOIN links l ON l.id = c.link_id
-         {where_clause}
+         FROM events c
+         JOIN links l ON l.id = c.link_id AND c.event_type = 'click' AND c.internal = 0
+         WHERE (?2 IS NULL OR l.workspace_id = ?2) AND (?3 IS NULL OR l.user_id = ?3)
          ORDER BY c.clicked_at DESC
-         LIMIT ?1"
-    );
-
-    let rows: Vec<ClickActivityRow> = if let Some(uid) = bind_uid {
-        sqlx::query_as(&sql)
-            .bind(limit)
-            .bind(uid)
-            .fetch_all(pool)
-            .await?
-    } else {
-        sqlx::query_as(&sql).bind(limit).fetch_all(pool).await?
-    };
+         LIMIT ?1",
+    )
+    .bind(limit)
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(rows
         .into_iter()
@@ -365,45 +1286,338 @@ pub async fn recent_clicks_with_labels(
         .collect())
 }
 
-/// Fetch full analytics for one link.
+/// Which clicks analytics count, beyond the link and event type.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrafficFilter {
+    /// Count clicks from `INTERNAL_IPS`, which are left out by default.
+    pub include_internal: bool,
+    /// Leave out clicks from hosting and datacenter networks.
+    pub exclude_datacenter: bool,
+}
+
+/// Matches clicks allowed by a [`TrafficFilter`] bound as `?2` and `?3`, in
+/// `events` or `event_rollups`.
+pub const TRAFFIC_FILTER_SQL: &str = "(?2 OR internal = 0) AND (?3 = 0 OR datacenter = 0)";
+
+/// Fetch full analytics for one link, counting the clicks `filter` allows.
 pub async fn get_analytics(
     pool: &SqlitePool,
     link_id: i64,
+    filter: TrafficFilter,
 ) -> Result<Option<AnalyticsSummary>, sqlx::Error> {
     let link = match get_link_by_id(pool, link_id).await? {
         Some(l) => l,
         None => return Ok(None),
     };
 
-    let total_clicks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clicks WHERE link_id = ?1")
-        .bind(link_id)
-        .fetch_one(pool)
-        .await?;
+    let total_clicks: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND {TRAFFIC_FILTER_SQL}"
+    ))
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_one(pool)
+    .await?;
 
-    let unique_ips: i64 = sqlx::query_scalar(
-        "SELECT COUNT(DISTINCT ip_address) FROM clicks
-         WHERE link_id = ?1 AND ip_address IS NOT NULL",
+    let internal_clicks: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM events WHERE link_id = ?1 AND event_type = 'click' AND internal = 1",
     )
     .bind(link_id)
     .fetch_one(pool)
     .await?;
 
-    let clicks: Vec<Click> = sqlx::query_as(
+    let datacenter_clicks: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND (?2 OR internal = 0)
+           AND datacenter = 1",
+    )
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .fetch_one(pool)
+    .await?;
+
+    let (unique_visitors, unique_visitors_estimated) =
+        count_link_visitors(pool, link_id, total_clicks, filter).await?;
+
+    let (impressions, previews, api_expansions): (i64, i64, i64) = sqlx::query_as(&format!(
+        "SELECT COALESCE(SUM(event_type = 'impression'), 0),
+                COALESCE(SUM(event_type = 'preview'), 0),
+                COALESCE(SUM(event_type = 'api_expand'), 0)
+         FROM events
+         WHERE link_id = ?1 AND event_type IN ('impression', 'preview', 'api_expand')
+           AND {TRAFFIC_FILTER_SQL}"
+    ))
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_one(pool)
+    .await?;
+
+    let rolled_up: (i64, i64, i64, i64, i64, i64) = sqlx::query_as(&format!(
+        "SELECT COALESCE(SUM(CASE WHEN event_type = 'click' AND {TRAFFIC_FILTER_SQL}
+                              THEN events END), 0),
+                COALESCE(SUM(CASE WHEN event_type = 'click' AND internal = 1
+                              THEN events END), 0),
+                COALESCE(SUM(CASE WHEN event_type = 'click' AND (?2 OR internal = 0)
+                                   AND datacenter = 1 THEN events END), 0),
+                COALESCE(SUM(CASE WHEN event_type = 'impression' AND {TRAFFIC_FILTER_SQL}
+                              THEN events END), 0),
+                COALESCE(SUM(CASE WHEN event_type = 'preview' AND {TRAFFIC_FILTER_SQL}
+                              THEN events END), 0),
+                COALESCE(SUM(CASE WHEN event_type = 'api_expand' AND {TRAFFIC_FILTER_SQL}
+                              THEN events END), 0)
+         FROM event_rollups
+         WHERE link_id = ?1"
+    ))
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_one(pool)
+    .await?;
+
+    let clicks: Vec<Click> = sqlx::query_as(&format!(
         "SELECT id, link_id, clicked_at, ip_address, user_agent,
-                referer, browser, os, device_type, country, region, city
-         FROM clicks
-         WHERE link_id = ?1
+                referer, browser, os, device_type, country, country_code, continent,
+                region, city, asn, isp, screen_width, screen_height, language, timezone,
+                internal, datacenter, source, matched_language, variant
+         FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND {TRAFFIC_FILTER_SQL}
          ORDER BY clicked_at DESC
-         LIMIT 500",
-    )
+         LIMIT 500"
+    ))
     .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
     .fetch_all(pool)
     .await?;
 
     Ok(Some(AnalyticsSummary {
         link,
-        total_clicks,
-        unique_ips,
+        total_clicks: total_clicks + rolled_up.0,
+        unique_visitors,
+        unique_visitors_estimated,
+        impressions: impressions + rolled_up.3,
+        previews: previews + rolled_up.4,
+        api_expansions: api_expansions + rolled_up.5,
+        internal_clicks: internal_clicks + rolled_up.1,
+        datacenter_clicks: datacenter_clicks + rolled_up.2,
         clicks,
     }))
 }
+
+/// Distinct visitors among the clicks on `link_id` that `filter` allows, and
+/// whether that's an estimate. Pruned days only have visitor sketches, and
+/// once `raw_clicks` of the link's raw clicks reach
+/// [`db_rollups::EXACT_VISITORS_BELOW`], sketched days are counted from
+/// their sketches too, leaving only the clicks since to scan.
+async fn count_link_visitors(
+    pool: &SqlitePool,
+    link_id: i64,
+    raw_clicks: i64,
+    filter: TrafficFilter,
+) -> Result<(i64, bool), sqlx::Error> {
+    let mut sketches = db_rollups::click_sketches(pool, &[link_id], filter).await?;
+    let mut since = None;
+    if raw_clicks >= db_rollups::EXACT_VISITORS_BELOW {
+        if let Some(days) = db_rollups::sketched_days(pool, None, None).await? {
+            sketches.extend(db_rollups::day_sketches(pool, &[link_id], days, filter).await?);
+            since = Some(days.end());
+        }
+    }
+
+    if sketches.is_empty() {
+        let unique_visitors = sqlx::query_scalar(&format!(
+            "SELECT COUNT(DISTINCT visitor_hash) FROM events
+             WHERE link_id = ?1 AND event_type = 'click' AND {TRAFFIC_FILTER_SQL}"
+        ))
+        .bind(link_id)
+        .bind(filter.include_internal)
+        .bind(filter.exclude_datacenter)
+        .fetch_one(pool)
+        .await?;
+        return Ok((unique_visitors, false));
+    }
+    let visitors: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT DISTINCT visitor_hash FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND {TRAFFIC_FILTER_SQL}
+           AND visitor_hash IS NOT NULL AND clicked_at >= ?4"
+    ))
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .bind(since.unwrap_or_default())
+    .fetch_all(pool)
+    .await?;
+    let unique_visitors =
+        db_rollups::estimate_visitors(sketches.iter().map(|(_, s)| s.as_slice()), &visitors);
+    Ok((unique_visitors, true))
+}
+
+/// A link's clicks with a known position, grouped into grid cells `cell`
+/// degrees across, as (latitude, longitude, clicks). Each cluster sits at
+/// the average position of its clicks.
+pub async fn click_locations(
+    pool: &SqlitePool,
+    link_id: i64,
+    filter: TrafficFilter,
+    cell: f64,
+) -> Result<Vec<(f64, f64, i64)>, sqlx::Error> {
+    // Offsetting to non-negative values makes the integer cast a floor.
+    sqlx::query_as(&format!(
+        "SELECT AVG(latitude), AVG(longitude), COUNT(*) AS clicks
+         FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND {TRAFFIC_FILTER_SQL}
+           AND latitude IS NOT NULL AND longitude IS NOT NULL
+         GROUP BY CAST((latitude + 90) / ?4 AS INTEGER),
+                  CAST((longitude + 180) / ?4 AS INTEGER)
+         ORDER BY clicks DESC"
+    ))
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .bind(cell)
+    .fetch_all(pool)
+    .await
+}
+
+/// Days of the week, in the row order of [`clicks_by_weekday_hour`].
+pub const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// A link's clicks counted by UTC day of the week (row 0 is Monday) and
+/// hour of the day.
+pub async fn clicks_by_weekday_hour(
+    pool: &SqlitePool,
+    link_id: i64,
+    filter: TrafficFilter,
+) -> Result<[[i64; 24]; 7], sqlx::Error> {
+    // strftime('%w') counts from Sunday; shift it so weeks start on Monday.
+    let rows: Vec<(i64, i64, i64)> = sqlx::query_as(&format!(
+        "SELECT (CAST(strftime('%w', clicked_at) AS INTEGER) + 6) % 7 AS day,
+                CAST(strftime('%H', clicked_at) AS INTEGER) AS hour,
+                COUNT(*)
+         FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND {TRAFFIC_FILTER_SQL}
+         GROUP BY day, hour"
+    ))
+    .bind(link_id)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_all(pool)
+    .await?;
+
+    let mut matrix = [[0; 24]; 7];
+    for (day, hour, count) in rows {
+        if let (Ok(day @ 0..=6), Ok(hour @ 0..=23)) = (usize::try_from(day), usize::try_from(hour))
+        {
+            matrix[day][hour] = count;
+        }
+    }
+    Ok(matrix)
+}
+
+/// Click totals for each of `link_ids` that has any clicks or impressions,
+/// in one pass over their clicks for the comparison page. Pruned events are
+/// added from their rollups, and unique visitors counted as on the analytics
+/// page, estimated for links that have pruned days or many clicks.
+pub async fn comparison_totals(
+    pool: &SqlitePool,
+    link_ids: &[i64],
+    filter: TrafficFilter,
+) -> Result<Vec<ComparisonTotals>, sqlx::Error> {
+    let ids = serde_json::to_string(link_ids).unwrap_or_default();
+    let mut totals: Vec<ComparisonTotals> = sqlx::query_as(&format!(
+        "SELECT link_id,
+                SUM(event_type = 'click') AS clicks,
+                0 AS unique_visitors,
+                SUM(event_type = 'impression') AS impressions,
+                SUM(event_type = 'click' AND EXISTS (
+                    SELECT 1 FROM conversions v WHERE v.click_id = events.click_id
+                )) AS converted_clicks
+         FROM events
+         WHERE link_id IN (SELECT value FROM json_each(?1)) AND {TRAFFIC_FILTER_SQL}
+         GROUP BY link_id"
+    ))
+    .bind(&ids)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_all(pool)
+    .await?;
+    for total in &mut totals {
+        total.unique_visitors = count_link_visitors(pool, total.link_id, total.clicks, filter)
+            .await?
+            .0;
+    }
+
+    let rolled_up: Vec<(i64, i64, i64)> = sqlx::query_as(&format!(
+        "SELECT link_id, SUM(CASE WHEN event_type = 'click' THEN events ELSE 0 END),
+                SUM(CASE WHEN event_type = 'impression' THEN events ELSE 0 END)
+         FROM event_rollups
+         WHERE link_id IN (SELECT value FROM json_each(?1)) AND events > 0
+           AND {TRAFFIC_FILTER_SQL}
+         GROUP BY link_id"
+    ))
+    .bind(&ids)
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_all(pool)
+    .await?;
+    for (link_id, clicks, impressions) in rolled_up {
+        match totals.iter_mut().find(|t| t.link_id == link_id) {
+            Some(total) => {
+                total.clicks += clicks;
+                total.impressions += impressions;
+            }
+            // Only pruned events left
+            None => totals.push(ComparisonTotals {
+                link_id,
+                clicks,
+                unique_visitors: count_link_visitors(pool, link_id, 0, filter).await?.0,
+                impressions,
+                ..Default::default()
+            }),
+        }
+    }
+    Ok(totals)
+}
+
+/// Clicks per UTC day (`YYYY-MM-DD`) on each of `link_ids` since `since`,
+/// as (link id, day, clicks), from raw clicks or, for pruned days, their
+/// rollups. Days without clicks are omitted.
+pub async fn daily_clicks(
+    pool: &SqlitePool,
+    link_ids: &[i64],
+    since: NaiveDate,
+    filter: TrafficFilter,
+) -> Result<Vec<(i64, String, i64)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT link_id, day, SUM(clicks) FROM (
+             SELECT link_id, substr(clicked_at, 1, 10) AS day, COUNT(*) AS clicks
+             FROM events
+             WHERE link_id IN (SELECT value FROM json_each(?1)) AND event_type = 'click'
+               AND {TRAFFIC_FILTER_SQL} AND clicked_at >= ?4
+             GROUP BY link_id, day
+             UNION ALL
+             SELECT link_id, day, SUM(events)
+             FROM event_rollups
+             WHERE link_id IN (SELECT value FROM json_each(?1)) AND event_type = 'click'
+               AND events > 0 AND {TRAFFIC_FILTER_SQL} AND day >= ?4
+             GROUP BY link_id, day
+         )
+         GROUP BY link_id, day"
+    ))
+    .bind(serde_json::to_string(link_ids).unwrap_or_default())
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .bind(since.format("%Y-%m-%d").to_string())
+    .fetch_all(pool)
+    .await
+}
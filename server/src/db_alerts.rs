@@ -0,0 +1,74 @@
+use crate::models::ClickAlert;
+use sqlx::SqlitePool;
+
+const ALERT_COLUMNS: &str = "link_id, threshold, created_at, triggered_at";
+
+/// Every threshold not yet reached, as `(link_id, threshold)`.
+pub async fn get_pending_alerts(pool: &SqlitePool) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    sqlx::query_as("SELECT link_id, threshold FROM link_click_alerts WHERE triggered_at IS NULL")
+        .fetch_all(pool)
+        .await
+}
+
+/// A link's alerts, lowest threshold first.
+pub async fn get_alerts_for_link(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Vec<ClickAlert>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {ALERT_COLUMNS} FROM link_click_alerts WHERE link_id = ?1 ORDER BY threshold ASC"
+    ))
+    .bind(link_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Add a threshold. Returns `false` if the link already has it.
+pub async fn create_alert(
+    pool: &SqlitePool,
+    link_id: i64,
+    threshold: i64,
+) -> Result<bool, sqlx::Error> {
+    let affected =
+        sqlx::query("INSERT OR IGNORE INTO link_click_alerts (link_id, threshold) VALUES (?1, ?2)")
+            .bind(link_id)
+            .bind(threshold)
+            .execute(pool)
+            .await?
+            .rows_affected();
+    Ok(affected > 0)
+}
+
+pub async fn delete_alert(
+    pool: &SqlitePool,
+    link_id: i64,
+    threshold: i64,
+) -> Result<bool, sqlx::Error> {
+    let affected =
+        sqlx::query("DELETE FROM link_click_alerts WHERE link_id = ?1 AND threshold = ?2")
+            .bind(link_id)
+            .bind(threshold)
+            .execute(pool)
+            .await?
+            .rows_affected();
+    Ok(affected > 0)
+}
+
+/// Mark a threshold as reached. Returns `false` if another click already
+/// did, so each alert fires exactly once.
+pub async fn mark_triggered(
+    pool: &SqlitePool,
+    link_id: i64,
+    threshold: i64,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE link_click_alerts SET triggered_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+         WHERE link_id = ?1 AND threshold = ?2 AND triggered_at IS NULL",
+    )
+    .bind(link_id)
+    .bind(threshold)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(affected > 0)
+}
@@ -0,0 +1,46 @@
+use crate::models::LinkAlias;
+use sqlx::SqlitePool;
+
+/// A link's aliases, oldest first.
+pub async fn get_aliases_for_link(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Vec<LinkAlias>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT short_code, link_id, created_at FROM link_aliases
+         WHERE link_id = ?1 ORDER BY created_at ASC, short_code ASC",
+    )
+    .bind(link_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Add an alias. Fails with a UNIQUE error if the code is already a short
+/// code or alias.
+pub async fn create_alias(
+    pool: &SqlitePool,
+    link_id: i64,
+    short_code: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO link_aliases (short_code, link_id) VALUES (?1, ?2)")
+        .bind(short_code)
+        .bind(link_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Remove one of a link's aliases. Returns `false` if it had no such alias.
+pub async fn delete_alias(
+    pool: &SqlitePool,
+    link_id: i64,
+    short_code: &str,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("DELETE FROM link_aliases WHERE link_id = ?1 AND short_code = ?2")
+        .bind(link_id)
+        .bind(short_code)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(affected > 0)
+}
@@ -1,3 +1,4 @@
+use crate::db::Scope;
 use crate::models::{
     BioLink, BioLinkClick, BioLinkClickCount, BioLinkClickDetail, BioPage, BioPageAnalytics,
     BioPageFull, BioPageView, BioPageWithClicks, BioSocialLink,
@@ -17,32 +18,23 @@ type BioClickRow = (
 
 const BIO_PAGE_COLUMNS: &str =
     "id, slug, display_name, bio, profile_image_url, background_type, background_value,
-     template_name, custom_css, email_address, is_published, created_at, updated_at, user_id";
+     template_name, custom_css, email_address, is_published, created_at, updated_at, user_id,
+     workspace_id";
 
-/// Fetch all bio pages, newest first.
-/// When `user_id_filter` is Some, only return pages owned by that user.
-/// When None (admin), return all pages.
+/// Fetch the bio pages in `scope`, newest first.
 pub async fn get_all_bio_pages(
     pool: &SqlitePool,
-    user_id_filter: Option<i64>,
+    scope: Scope,
 ) -> Result<Vec<BioPage>, sqlx::Error> {
-    match user_id_filter {
-        Some(uid) => {
-            sqlx::query_as(&format!(
-            "SELECT {BIO_PAGE_COLUMNS} FROM bio_pages WHERE user_id = ?1 ORDER BY created_at DESC"
-        ))
-            .bind(uid)
-            .fetch_all(pool)
-            .await
-        }
-        None => {
-            sqlx::query_as(&format!(
-                "SELECT {BIO_PAGE_COLUMNS} FROM bio_pages ORDER BY created_at DESC"
-            ))
-            .fetch_all(pool)
-            .await
-        }
-    }
+    sqlx::query_as(&format!(
+        "SELECT {BIO_PAGE_COLUMNS} FROM bio_pages
+         WHERE (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+         ORDER BY created_at DESC"
+    ))
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await
 }
 
 /// Fetch a single bio page by ID.
@@ -156,7 +148,7 @@ pub async fn bio_slug_exists(pool: &SqlitePool, slug: &str) -> Result<bool, sqlx
     Ok(row.0 > 0)
 }
 
-/// Create a new bio page. Returns the created row.
+/// Create a new bio page in its owner's workspace. Returns the created row.
 pub async fn create_bio_page(
     pool: &SqlitePool,
     slug: &str,
@@ -166,8 +158,8 @@ pub async fn create_bio_page(
     user_id: i64,
 ) -> Result<BioPage, sqlx::Error> {
     let id = sqlx::query(
-        "INSERT INTO bio_pages (slug, display_name, bio, template_name, user_id)
-         VALUES (?1, ?2, ?3, ?4, ?5)",
+        "INSERT INTO bio_pages (slug, display_name, bio, template_name, user_id, workspace_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, (SELECT workspace_id FROM users WHERE id = ?5))",
     )
     .bind(slug)
     .bind(display_name)
@@ -341,80 +333,54 @@ pub async fn log_bio_link_click(
     Ok(())
 }
 
-/// Count total bio link clicks, optionally filtered by user.
+/// Count the clicks on bio pages in `scope`.
 pub async fn count_total_bio_link_clicks(
     pool: &SqlitePool,
-    user_id_filter: Option<i64>,
+    scope: Scope,
 ) -> Result<i64, sqlx::Error> {
-    match user_id_filter {
-        Some(uid) => {
-            sqlx::query_scalar(
-                "SELECT COUNT(*) FROM bio_link_clicks blc
-                 JOIN bio_pages bp ON bp.id = blc.page_id
-                 WHERE bp.user_id = ?1",
-            )
-            .bind(uid)
-            .fetch_one(pool)
-            .await
-        }
-        None => {
-            sqlx::query_scalar("SELECT COUNT(*) FROM bio_link_clicks")
-                .fetch_one(pool)
-                .await
-        }
-    }
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM bio_link_clicks blc
+         JOIN bio_pages bp ON bp.id = blc.page_id
+         WHERE (?1 IS NULL OR bp.workspace_id = ?1) AND (?2 IS NULL OR bp.user_id = ?2)",
+    )
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_one(pool)
+    .await
 }
 
-/// Count total bio pages, optionally filtered by user.
-pub async fn count_bio_pages(
-    pool: &SqlitePool,
-    user_id_filter: Option<i64>,
-) -> Result<i64, sqlx::Error> {
-    match user_id_filter {
-        Some(uid) => {
-            sqlx::query_scalar("SELECT COUNT(*) FROM bio_pages WHERE user_id = ?1")
-                .bind(uid)
-                .fetch_one(pool)
-                .await
-        }
-        None => {
-            sqlx::query_scalar("SELECT COUNT(*) FROM bio_pages")
-                .fetch_one(pool)
-                .await
-        }
-    }
+/// Count the bio pages in `scope`.
+pub async fn count_bio_pages(pool: &SqlitePool, scope: Scope) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM bio_pages
+         WHERE (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)",
+    )
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_one(pool)
+    .await
 }
 
-/// Top bio pages by click count, optionally filtered by user.
+/// Top bio pages in `scope` by click count.
 pub async fn top_bio_pages_by_clicks(
     pool: &SqlitePool,
     limit: i64,
-    user_id_filter: Option<i64>,
+    scope: Scope,
 ) -> Result<Vec<BioPageWithClicks>, sqlx::Error> {
-    let (where_clause, bind_uid) = match user_id_filter {
-        Some(uid) => ("WHERE bp.user_id = ?2", Some(uid)),
-        None => ("", None),
-    };
-
-    let sql = format!(
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(
         "SELECT bp.slug, bp.display_name, COUNT(blc.id) as click_count
          FROM bio_pages bp
          LEFT JOIN bio_link_clicks blc ON blc.page_id = bp.id
-         {where_clause}
+         WHERE (?2 IS NULL OR bp.workspace_id = ?2) AND (?3 IS NULL OR bp.user_id = ?3)
          GROUP BY bp.id
          ORDER BY click_count DESC
-         LIMIT ?1"
-    );
-
-    let rows: Vec<(String, String, i64)> = if let Some(uid) = bind_uid {
-        sqlx::query_as(&sql)
-            .bind(limit)
-            .bind(uid)
-            .fetch_all(pool)
-            .await?
-    } else {
-        sqlx::query_as(&sql).bind(limit).fetch_all(pool).await?
-    };
+         LIMIT ?1",
+    )
+    .bind(limit)
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(rows
         .into_iter()
@@ -426,36 +392,26 @@ pub async fn top_bio_pages_by_clicks(
         .collect())
 }
 
-/// Recent bio link clicks with details for the dashboard, optionally filtered by user.
+/// Recent clicks on bio pages in `scope`, with details for the dashboard.
 pub async fn recent_bio_link_clicks(
     pool: &SqlitePool,
     limit: i64,
-    user_id_filter: Option<i64>,
+    scope: Scope,
 ) -> Result<Vec<BioLinkClickDetail>, sqlx::Error> {
-    let (where_clause, bind_uid) = match user_id_filter {
-        Some(uid) => ("WHERE bp.user_id = ?2", Some(uid)),
-        None => ("", None),
-    };
-
-    let sql = format!(
+    let rows: Vec<BioClickRow> = sqlx::query_as(
         "SELECT bl.title, bp.slug, blc.clicked_at, blc.country, blc.referer, blc.browser
          FROM bio_link_clicks blc
          JOIN bio_links bl ON bl.id = blc.bio_link_id
          JOIN bio_pages bp ON bp.id = blc.page_id
-         {where_clause}
+         WHERE (?2 IS NULL OR bp.workspace_id = ?2) AND (?3 IS NULL OR bp.user_id = ?3)
          ORDER BY blc.clicked_at DESC
-         LIMIT ?1"
-    );
-
-    let rows: Vec<BioClickRow> = if let Some(uid) = bind_uid {
-        sqlx::query_as(&sql)
-            .bind(limit)
-            .bind(uid)
-            .fetch_all(pool)
-            .await?
-    } else {
-        sqlx::query_as(&sql).bind(limit).fetch_all(pool).await?
-    };
+         LIMIT ?1",
+    )
+    .bind(limit)
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await?;
 
     Ok(rows
         .into_iter()
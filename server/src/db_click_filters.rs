@@ -0,0 +1,38 @@
+use crate::models::ClickFilter;
+use sqlx::SqlitePool;
+
+/// Every click filter, oldest first.
+pub async fn get_click_filters(pool: &SqlitePool) -> Result<Vec<ClickFilter>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT f.id, f.pattern, f.is_regex, u.email AS created_by, f.created_at
+         FROM click_filters f
+         LEFT JOIN users u ON u.id = f.created_by
+         ORDER BY f.id ASC",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn create_click_filter(
+    pool: &SqlitePool,
+    pattern: &str,
+    is_regex: bool,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO click_filters (pattern, is_regex, created_by) VALUES (?1, ?2, ?3)")
+        .bind(pattern)
+        .bind(is_regex)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Returns whether the filter existed.
+pub async fn delete_click_filter(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM click_filters WHERE id = ?1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
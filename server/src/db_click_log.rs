@@ -0,0 +1,146 @@
+use crate::models::Click;
+use chrono::NaiveDate;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+/// Device type woothee assigns to crawlers and other bots.
+pub const BOT_DEVICE_TYPE: &str = "crawler";
+
+/// Filters for browsing one link's individual clicks.
+#[derive(Debug, Clone, Default)]
+pub struct ClickLogFilter {
+    /// First and last day (UTC) to include.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub country: Option<String>,
+    pub browser: Option<String>,
+    /// `Some(true)` for bots only, `Some(false)` to hide them.
+    pub bots: Option<bool>,
+    /// Case-insensitive substring of the referer.
+    pub referer: Option<String>,
+}
+
+/// Which page of clicks to fetch. Pages are keyed by click id, newest first,
+/// so paging stays on the `(link_id, …)` indexes however old the clicks are.
+#[derive(Debug, Clone, Copy)]
+pub enum Page {
+    Newest,
+    /// Clicks older than this id.
+    Before(i64),
+    /// Clicks newer than this id.
+    After(i64),
+}
+
+/// A click attribute offered as a filter choice.
+#[derive(Debug, Clone, Copy)]
+pub enum Facet {
+    Country,
+    Browser,
+}
+
+fn push_filters(qb: &mut QueryBuilder<'_, Sqlite>, link_id: i64, f: &ClickLogFilter) {
+    qb.push(" FROM events c WHERE c.link_id = ")
+        .push_bind(link_id)
+        .push(" AND c.event_type = 'click'");
+    // Timestamps are stored as ISO-8601 text, so ranges compare as strings.
+    if let Some(from) = f.from {
+        qb.push(" AND c.clicked_at >= ")
+            .push_bind(from.format("%Y-%m-%d").to_string());
+    }
+    if let Some(to) = f.to.and_then(|d| d.succ_opt()) {
+        qb.push(" AND c.clicked_at < ")
+            .push_bind(to.format("%Y-%m-%d").to_string());
+    }
+    if let Some(country) = &f.country {
+        qb.push(" AND c.country = ").push_bind(country.clone());
+    }
+    if let Some(browser) = &f.browser {
+        qb.push(" AND c.browser = ").push_bind(browser.clone());
+    }
+    match f.bots {
+        Some(true) => {
+            qb.push(" AND c.device_type = ").push_bind(BOT_DEVICE_TYPE);
+        }
+        Some(false) => {
+            qb.push(" AND c.device_type IS NOT ")
+                .push_bind(BOT_DEVICE_TYPE);
+        }
+        None => {}
+    }
+    if let Some(referer) = &f.referer {
+        qb.push(" AND instr(lower(c.referer), ")
+            .push_bind(referer.to_lowercase())
+            .push(") > 0");
+    }
+}
+
+/// Up to `limit` clicks matching `filter`, newest first.
+pub async fn list_clicks(
+    pool: &SqlitePool,
+    link_id: i64,
+    filter: &ClickLogFilter,
+    page: Page,
+    limit: i64,
+) -> Result<Vec<Click>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT c.id, c.link_id, c.clicked_at, c.ip_address, c.user_agent, c.referer,
+                c.browser, c.os, c.device_type, c.country, c.country_code, c.continent,
+                c.region, c.city, c.asn, c.isp, c.screen_width, c.screen_height,
+                c.language, c.timezone, c.internal, c.datacenter, c.source,
+                c.matched_language, c.variant",
+    );
+    push_filters(&mut qb, link_id, filter);
+    match page {
+        Page::Newest => {
+            qb.push(" ORDER BY c.id DESC");
+        }
+        Page::Before(id) => {
+            qb.push(" AND c.id < ")
+                .push_bind(id)
+                .push(" ORDER BY c.id DESC");
+        }
+        // Take the oldest clicks after `id`, then flip them below.
+        Page::After(id) => {
+            qb.push(" AND c.id > ")
+                .push_bind(id)
+                .push(" ORDER BY c.id ASC");
+        }
+    }
+    qb.push(" LIMIT ").push_bind(limit);
+
+    let mut clicks: Vec<Click> = qb.build_query_as().fetch_all(pool).await?;
+    if let Page::After(_) = page {
+        clicks.reverse();
+    }
+    Ok(clicks)
+}
+
+/// Number of clicks matching `filter`.
+pub async fn count_clicks(
+    pool: &SqlitePool,
+    link_id: i64,
+    filter: &ClickLogFilter,
+) -> Result<i64, sqlx::Error> {
+    let mut qb = QueryBuilder::new("SELECT COUNT(*)");
+    push_filters(&mut qb, link_id, filter);
+    qb.build_query_scalar().fetch_one(pool).await
+}
+
+/// Values of `facet` seen on the link's clicks, alphabetically.
+pub async fn facet_values(
+    pool: &SqlitePool,
+    link_id: i64,
+    facet: Facet,
+) -> Result<Vec<String>, sqlx::Error> {
+    let column = match facet {
+        Facet::Country => "country",
+        Facet::Browser => "browser",
+    };
+    sqlx::query_scalar(&format!(
+        "SELECT DISTINCT {column} FROM events
+         WHERE link_id = ?1 AND event_type = 'click' AND {column} IS NOT NULL
+         ORDER BY {column}"
+    ))
+    .bind(link_id)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,54 @@
+use crate::models::{Conversion, ConversionTotal};
+use sqlx::SqlitePool;
+
+/// The link, link workspace, and link owner of a click, by click ID.
+pub async fn find_click(
+    pool: &SqlitePool,
+    click_id: &str,
+) -> Result<Option<(i64, i64, Option<i64>)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT l.id, l.workspace_id, l.user_id FROM events c
+         JOIN links l ON l.id = c.link_id
+         WHERE c.click_id = ?1",
+    )
+    .bind(click_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Record a conversion and return it.
+pub async fn create_conversion(
+    pool: &SqlitePool,
+    click_id: &str,
+    name: &str,
+    value: Option<f64>,
+) -> Result<Conversion, sqlx::Error> {
+    sqlx::query_as(
+        "INSERT INTO conversions (click_id, name, value) VALUES (?1, ?2, ?3)
+         RETURNING id, click_id, name, value, created_at",
+    )
+    .bind(click_id)
+    .bind(name)
+    .bind(value)
+    .fetch_one(pool)
+    .await
+}
+
+/// A link's conversions grouped by name, most frequent first.
+pub async fn totals_for_link(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Vec<ConversionTotal>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT v.name, COUNT(*) AS conversions, COUNT(DISTINCT v.click_id) AS clicks,
+                SUM(v.value) AS value
+         FROM conversions v
+         JOIN events c ON c.click_id = v.click_id
+         WHERE c.link_id = ?1 AND c.internal = 0
+         GROUP BY v.name
+         ORDER BY conversions DESC, v.name ASC",
+    )
+    .bind(link_id)
+    .fetch_all(pool)
+    .await
+}
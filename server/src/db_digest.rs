@@ -0,0 +1,101 @@
+use crate::models::DigestLink;
+use chrono::NaiveDateTime;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// Timestamps are stored as ISO-8601 text, so ranges compare as strings.
+fn ts(t: NaiveDateTime) -> String {
+    t.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+// ── Report data ───────────────────────────────────────────────────────────
+
+/// Short link clicks in `[from, to)`.
+pub async fn count_clicks_between(
+    pool: &SqlitePool,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM events
+         WHERE event_type = 'click' AND internal = 0 AND clicked_at >= ?1 AND clicked_at < ?2",
+    )
+    .bind(ts(from))
+    .bind(ts(to))
+    .fetch_one(pool)
+    .await
+}
+
+/// Short links created in `[from, to)`.
+pub async fn count_links_created_between(
+    pool: &SqlitePool,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM links WHERE created_at >= ?1 AND created_at < ?2")
+        .bind(ts(from))
+        .bind(ts(to))
+        .fetch_one(pool)
+        .await
+}
+
+/// The most-clicked links in `[week_start, week_end)`, with each link's
+/// clicks from the seven days before for comparison.
+pub async fn top_links_for_week(
+    pool: &SqlitePool,
+    week_start: NaiveDateTime,
+    week_end: NaiveDateTime,
+    limit: i64,
+) -> Result<Vec<DigestLink>, sqlx::Error> {
+    let prev_start = week_start - chrono::Duration::days(7);
+    sqlx::query_as(
+        "SELECT l.short_code, l.title, l.domain_id,
+                SUM(c.clicked_at >= ?2) AS clicks,
+                SUM(c.clicked_at < ?2)  AS prev_clicks
+         FROM events c
+         JOIN links l ON l.id = c.link_id
+         WHERE c.event_type = 'click' AND c.internal = 0 AND c.clicked_at >= ?1 AND c.clicked_at < ?3
+         GROUP BY l.id
+         HAVING clicks > 0
+         ORDER BY clicks DESC, l.short_code ASC
+         LIMIT ?4",
+    )
+    .bind(ts(prev_start))
+    .bind(ts(week_start))
+    .bind(ts(week_end))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+// ── Opt-outs ──────────────────────────────────────────────────────────────
+
+/// Every address that has unsubscribed from the digest.
+pub async fn get_opt_outs(pool: &SqlitePool) -> Result<HashSet<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT email FROM digest_opt_outs")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(e,)| e).collect())
+}
+
+/// Unsubscribe `email` from the digest. Idempotent.
+pub async fn opt_out(pool: &SqlitePool, email: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO digest_opt_outs (email) VALUES (?1)")
+        .bind(email)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// ── Runs ──────────────────────────────────────────────────────────────────
+
+/// Mark the digest for the week starting at `week_start` as sent. Returns
+/// `false` if it already was, so each week goes out at most once.
+pub async fn claim_week(pool: &SqlitePool, week_start: NaiveDateTime) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("INSERT OR IGNORE INTO digest_runs (week_start) VALUES (?1)")
+        .bind(ts(week_start))
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(affected > 0)
+}
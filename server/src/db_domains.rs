@@ -0,0 +1,103 @@
+use crate::models::Domain;
+use sqlx::SqlitePool;
+
+const DOMAIN_COLUMNS: &str =
+    "id, hostname, base_url, created_at, root_redirect_url, not_found_url, logo_url, brand_color,
+     workspace_id";
+
+/// List all configured domains, alphabetically.
+pub async fn get_all_domains(pool: &SqlitePool) -> Result<Vec<Domain>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {DOMAIN_COLUMNS} FROM domains ORDER BY hostname ASC"
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// Find a domain by ID.
+pub async fn get_domain_by_id(pool: &SqlitePool, id: i64) -> Result<Option<Domain>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {DOMAIN_COLUMNS} FROM domains WHERE id = ?1"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Add a domain. Returns the created row.
+pub async fn create_domain(
+    pool: &SqlitePool,
+    hostname: &str,
+    base_url: &str,
+) -> Result<Domain, sqlx::Error> {
+    let id = sqlx::query("INSERT INTO domains (hostname, base_url) VALUES (?1, ?2)")
+        .bind(hostname)
+        .bind(base_url)
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+
+    get_domain_by_id(pool, id)
+        .await
+        .map(|opt| opt.expect("just-inserted domain must exist"))
+}
+
+/// Update a domain's base URL, redirects, and branding.
+pub async fn update_domain(
+    pool: &SqlitePool,
+    id: i64,
+    base_url: &str,
+    root_redirect_url: Option<&str>,
+    not_found_url: Option<&str>,
+    logo_url: Option<&str>,
+    brand_color: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE domains
+         SET base_url = ?2, root_redirect_url = ?3, not_found_url = ?4,
+             logo_url = ?5, brand_color = ?6
+         WHERE id = ?1",
+    )
+    .bind(id)
+    .bind(base_url)
+    .bind(root_redirect_url)
+    .bind(not_found_url)
+    .bind(logo_url)
+    .bind(brand_color)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(affected > 0)
+}
+
+/// Reserve a domain for one workspace, or share it again with `None`.
+/// Returns `false` (and changes nothing) while links from other workspaces
+/// are still served from it.
+pub async fn set_domain_workspace(
+    pool: &SqlitePool,
+    id: i64,
+    workspace_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "UPDATE domains SET workspace_id = ?2
+         WHERE id = ?1
+           AND (?2 IS NULL
+                OR NOT EXISTS (SELECT 1 FROM links WHERE domain_id = ?1 AND workspace_id != ?2))",
+    )
+    .bind(id)
+    .bind(workspace_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(affected > 0)
+}
+
+/// Delete a domain. Its links fall back to the primary domain (ON DELETE SET NULL).
+pub async fn delete_domain(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("DELETE FROM domains WHERE id = ?1")
+        .bind(id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(affected > 0)
+}
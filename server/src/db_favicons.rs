@@ -0,0 +1,39 @@
+use chrono::NaiveDateTime;
+use sqlx::SqlitePool;
+
+/// A host's stored favicon: `(content type, image)` when it has one, and
+/// when it was fetched.
+pub type StoredFavicon = (Option<(String, Vec<u8>)>, NaiveDateTime);
+
+pub async fn get_favicon(
+    pool: &SqlitePool,
+    host: &str,
+) -> Result<Option<StoredFavicon>, sqlx::Error> {
+    let row: Option<(Option<String>, Option<Vec<u8>>, NaiveDateTime)> =
+        sqlx::query_as("SELECT content_type, image, fetched_at FROM favicons WHERE host = ?1")
+            .bind(host)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|(content_type, image, fetched_at)| (content_type.zip(image), fetched_at)))
+}
+
+/// Store what was found for `host` (`None` when it has no favicon).
+pub async fn save_favicon(
+    pool: &SqlitePool,
+    host: &str,
+    icon: Option<(&str, &[u8])>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO favicons (host, content_type, image) VALUES (?1, ?2, ?3)
+         ON CONFLICT(host) DO UPDATE SET
+             content_type = excluded.content_type,
+             image = excluded.image,
+             fetched_at = excluded.fetched_at",
+    )
+    .bind(host)
+    .bind(icon.map(|(content_type, _)| content_type))
+    .bind(icon.map(|(_, image)| image))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use crate::db::Scope;
+use crate::models::{Funnel, FunnelStep};
+use chrono::NaiveDateTime;
+use sqlx::SqlitePool;
+
+const FUNNEL_COLUMNS: &str = "id, workspace_id, user_id, name, window_hours, created_at";
+
+/// The funnels in `scope`, newest first.
+pub async fn get_funnels(pool: &SqlitePool, scope: Scope) -> Result<Vec<Funnel>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {FUNNEL_COLUMNS} FROM funnels
+         WHERE (?1 IS NULL OR workspace_id = ?1) AND (?2 IS NULL OR user_id = ?2)
+         ORDER BY created_at DESC, id DESC"
+    ))
+    .bind(scope.workspace_id)
+    .bind(scope.user_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_funnel(pool: &SqlitePool, id: i64) -> Result<Option<Funnel>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {FUNNEL_COLUMNS} FROM funnels WHERE id = ?1"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// A funnel's steps, first step first.
+pub async fn get_funnel_steps(
+    pool: &SqlitePool,
+    funnel_id: i64,
+) -> Result<Vec<FunnelStep>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT s.link_id, l.short_code, l.title
+         FROM funnel_steps s
+         JOIN links l ON l.id = s.link_id
+         WHERE s.funnel_id = ?1
+         ORDER BY s.position ASC",
+    )
+    .bind(funnel_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Create a funnel owned by `user_id` in their workspace, with `link_ids` as
+/// its steps in order. Returns its ID.
+pub async fn create_funnel(
+    pool: &SqlitePool,
+    user_id: i64,
+    name: &str,
+    window_hours: i64,
+    link_ids: &[i64],
+) -> Result<i64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let id = sqlx::query(
+        "INSERT INTO funnels (workspace_id, user_id, name, window_hours)
+         VALUES ((SELECT workspace_id FROM users WHERE id = ?1), ?1, ?2, ?3)",
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(window_hours)
+    .execute(&mut *tx)
+    .await?
+    .last_insert_rowid();
+
+    for (position, link_id) in link_ids.iter().enumerate() {
+        sqlx::query("INSERT INTO funnel_steps (funnel_id, position, link_id) VALUES (?1, ?2, ?3)")
+            .bind(id)
+            .bind(position as i64)
+            .bind(link_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Returns whether the funnel existed. Its steps go with it.
+pub async fn delete_funnel(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM funnels WHERE id = ?1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Real visitors' clicks on `link_ids` since `since`, as
+/// `(visitor_hash, link_id, clicked_at)`, grouped by visitor and oldest first
+/// within each. Clicks without a visitor hash can't be followed, so they're
+/// left out.
+pub async fn get_funnel_clicks(
+    pool: &SqlitePool,
+    link_ids: &[i64],
+    since: NaiveDateTime,
+) -> Result<Vec<(String, i64, NaiveDateTime)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT visitor_hash, link_id, clicked_at FROM events
+         WHERE link_id IN (SELECT value FROM json_each(?1))
+           AND event_type = 'click' AND internal = 0
+           AND visitor_hash IS NOT NULL AND clicked_at >= ?2
+         ORDER BY visitor_hash, clicked_at, id",
+    )
+    .bind(serde_json::to_string(link_ids).unwrap_or_default())
+    .bind(since.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,87 @@
+use crate::geo::GeoInfo;
+use sqlx::SqlitePool;
+
+/// Distinct IPs of clicks, bio link clicks, and bio page views recorded
+/// without a location, in order, starting after `after`.
+pub async fn ips_missing_geo(
+    pool: &SqlitePool,
+    after: &str,
+    limit: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT ip_address FROM events
+         WHERE country IS NULL AND ip_address IS NOT NULL AND ip_address > ?1
+         UNION
+         SELECT ip_address FROM bio_link_clicks
+         WHERE country IS NULL AND ip_address IS NOT NULL AND ip_address > ?1
+         UNION
+         SELECT ip_address FROM bio_page_views
+         WHERE country IS NULL AND ip_address IS NOT NULL AND ip_address > ?1
+         ORDER BY 1
+         LIMIT ?2",
+    )
+    .bind(after)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Rows (across all three tables) with an IP but no location.
+pub async fn count_missing_geo(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT
+             (SELECT COUNT(*) FROM events
+              WHERE country IS NULL AND ip_address IS NOT NULL)
+           + (SELECT COUNT(*) FROM bio_link_clicks
+              WHERE country IS NULL AND ip_address IS NOT NULL)
+           + (SELECT COUNT(*) FROM bio_page_views
+              WHERE country IS NULL AND ip_address IS NOT NULL)",
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Fill in the location of every row from `ip` that has none. Returns the
+/// number of rows updated.
+pub async fn fill_geo(pool: &SqlitePool, ip: &str, geo: &GeoInfo) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let clicks = sqlx::query(
+        "UPDATE events
+         SET country = ?2, country_code = ?3, continent = ?4, region = ?5, city = ?6,
+             latitude = ?7, longitude = ?8, asn = ?9, isp = ?10, datacenter = ?11
+         WHERE ip_address = ?1 AND country IS NULL",
+    )
+    .bind(ip)
+    .bind(&geo.country)
+    .bind(Some(geo.country_code.as_str()).filter(|c| !c.is_empty()))
+    .bind(Some(geo.continent_code.as_str()).filter(|c| !c.is_empty()))
+    .bind(&geo.region)
+    .bind(&geo.city)
+    .bind(geo.latitude)
+    .bind(geo.longitude)
+    .bind(geo.asn)
+    .bind(Some(geo.isp.as_str()).filter(|i| !i.is_empty()))
+    .bind(geo.datacenter)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    let mut bio = 0;
+    for table in ["bio_link_clicks", "bio_page_views"] {
+        bio += sqlx::query(&format!(
+            "UPDATE {table} SET country = ?2, region = ?3, city = ?4
+             WHERE ip_address = ?1 AND country IS NULL"
+        ))
+        .bind(ip)
+        .bind(&geo.country)
+        .bind(&geo.region)
+        .bind(&geo.city)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(clicks + bio)
+}
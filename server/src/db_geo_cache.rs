@@ -0,0 +1,95 @@
+use crate::geo::GeoInfo;
+use sqlx::SqlitePool;
+
+#[derive(sqlx::FromRow)]
+struct GeoCacheRow {
+    ip: String,
+    found: bool,
+    country: String,
+    country_code: String,
+    continent_code: String,
+    region: String,
+    city: String,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    asn: Option<i64>,
+    isp: String,
+    datacenter: bool,
+}
+
+impl GeoCacheRow {
+    fn into_entry(self) -> (String, Option<GeoInfo>) {
+        if !self.found {
+            return (self.ip, None);
+        }
+        let info = GeoInfo {
+            country: self.country,
+            country_code: self.country_code,
+            continent_code: self.continent_code,
+            region: self.region,
+            city: self.city,
+            latitude: self.latitude,
+            longitude: self.longitude,
+            asn: self.asn,
+            isp: self.isp,
+            datacenter: self.datacenter,
+        };
+        (self.ip, Some(info))
+    }
+}
+
+/// Delete entries resolved more than `ttl_days` ago and return the rest,
+/// with `None` for IPs the provider had no data for.
+pub async fn load_geo_cache(
+    pool: &SqlitePool,
+    ttl_days: u64,
+) -> Result<Vec<(String, Option<GeoInfo>)>, sqlx::Error> {
+    let cutoff = format!("-{ttl_days} days");
+    sqlx::query(
+        "DELETE FROM geo_cache WHERE resolved_at < strftime('%Y-%m-%dT%H:%M:%SZ', 'now', ?1)",
+    )
+    .bind(&cutoff)
+    .execute(pool)
+    .await?;
+
+    let rows: Vec<GeoCacheRow> = sqlx::query_as(
+        "SELECT ip, found, country, country_code, continent_code, region, city,
+                latitude, longitude, asn, isp, datacenter
+         FROM geo_cache",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(GeoCacheRow::into_entry).collect())
+}
+
+/// Store one batch of lookup results, replacing older entries for the same IPs.
+pub async fn save_geo_cache(
+    pool: &SqlitePool,
+    entries: &[(String, Option<GeoInfo>)],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for (ip, info) in entries {
+        let empty = String::new();
+        sqlx::query(
+            "INSERT OR REPLACE INTO geo_cache
+                 (ip, found, country, country_code, continent_code, region, city,
+                  latitude, longitude, asn, isp, datacenter)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )
+        .bind(ip)
+        .bind(info.is_some())
+        .bind(info.as_ref().map_or(&empty, |g| &g.country))
+        .bind(info.as_ref().map_or(&empty, |g| &g.country_code))
+        .bind(info.as_ref().map_or(&empty, |g| &g.continent_code))
+        .bind(info.as_ref().map_or(&empty, |g| &g.region))
+        .bind(info.as_ref().map_or(&empty, |g| &g.city))
+        .bind(info.as_ref().and_then(|g| g.latitude))
+        .bind(info.as_ref().and_then(|g| g.longitude))
+        .bind(info.as_ref().and_then(|g| g.asn))
+        .bind(info.as_ref().map_or(&empty, |g| &g.isp))
+        .bind(info.as_ref().is_some_and(|g| g.datacenter))
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
@@ -0,0 +1,332 @@
+use crate::db_rollups;
+use crate::models::{Click, LinkWithStats};
+use chrono::NaiveDateTime;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
+
+/// Which links a GraphQL query may return.
+#[derive(Debug, Clone, Default)]
+pub struct LinkQuery {
+    /// Restrict to one workspace's links.
+    pub workspace_id: Option<i64>,
+    /// Restrict to one user's links (`None` = all links, for admins).
+    pub owner_id: Option<i64>,
+    pub id: Option<i64>,
+    pub short_code: Option<String>,
+    /// Substring of the short code, destination URL, or title.
+    pub search: Option<String>,
+    pub domain_id: Option<i64>,
+    pub is_active: Option<bool>,
+    pub created_after: Option<NaiveDateTime>,
+    pub created_before: Option<NaiveDateTime>,
+}
+
+/// Which clicks to list or aggregate.
+#[derive(Debug, Clone, Default)]
+pub struct ClickScope {
+    /// Restrict to clicks on one workspace's links.
+    pub workspace_id: Option<i64>,
+    /// Restrict to clicks on one user's links (`None` = all links).
+    pub owner_id: Option<i64>,
+    pub link_id: Option<i64>,
+    /// Inclusive lower and exclusive upper bound on `clicked_at`.
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+/// A click attribute to group by.
+#[derive(Debug, Clone, Copy)]
+pub enum Dimension {
+    Country,
+    CountryCode,
+    Continent,
+    Referer,
+    Browser,
+    Os,
+    DeviceType,
+}
+
+impl Dimension {
+    fn column(self) -> &'static str {
+        match self {
+            Self::Country => "c.country",
+            Self::CountryCode => "c.country_code",
+            Self::Continent => "c.continent",
+            Self::Referer => "c.referer",
+            Self::Browser => "c.browser",
+            Self::Os => "c.os",
+            Self::DeviceType => "c.device_type",
+        }
+    }
+
+    /// Its `click_rollup_values.dimension`.
+    fn rollup_name(self) -> &'static str {
+        self.column().trim_start_matches("c.")
+    }
+}
+
+/// Timestamps are stored as ISO-8601 text, so ranges compare as strings.
+fn ts(t: NaiveDateTime) -> String {
+    t.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+// ── Links ──────────────────────────────────────────────────────────────────
+
+fn push_link_filters(qb: &mut QueryBuilder<'_, Sqlite>, q: &LinkQuery) {
+    qb.push(" WHERE l.deleted_at IS NULL");
+    if let Some(ws) = q.workspace_id {
+        qb.push(" AND l.workspace_id = ").push_bind(ws);
+    }
+    if let Some(uid) = q.owner_id {
+        qb.push(" AND l.user_id = ").push_bind(uid);
+    }
+    if let Some(id) = q.id {
+        qb.push(" AND l.id = ").push_bind(id);
+    }
+    if let Some(code) = &q.short_code {
+        qb.push(" AND l.short_code = ").push_bind(code.clone());
+    }
+    if let Some(search) = &q.search {
+        let pattern = format!(
+            "%{}%",
+            search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        qb.push(" AND (l.short_code LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR l.original_url LIKE ")
+            .push_bind(pattern.clone())
+            .push(" ESCAPE '\\' OR l.title LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\')");
+    }
+    if let Some(domain_id) = q.domain_id {
+        qb.push(" AND l.domain_id = ").push_bind(domain_id);
+    }
+    if let Some(active) = q.is_active {
+        qb.push(" AND l.is_active = ").push_bind(active);
+    }
+    if let Some(after) = q.created_after {
+        qb.push(" AND l.created_at >= ").push_bind(ts(after));
+    }
+    if let Some(before) = q.created_before {
+        qb.push(" AND l.created_at < ").push_bind(ts(before));
+    }
+}
+
+/// Links matching `q` with their click counts, newest first. Pages by id:
+/// only links with an id below `before_id` are returned.
+pub async fn list_links(
+    pool: &SqlitePool,
+    q: &LinkQuery,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<LinkWithStats>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT l.id, l.short_code, l.original_url, l.title, l.description,
+                l.created_at, l.is_active,
+                (SELECT COUNT(*) FROM events c
+                 WHERE c.link_id = l.id AND c.event_type = 'click' AND c.internal = 0)
+                + (SELECT COALESCE(SUM(r.events), 0) FROM event_rollups r
+                   WHERE r.link_id = l.id AND r.event_type = 'click' AND r.internal = 0)
+                    AS click_count,
+                l.user_id, l.domain_id, d.base_url AS domain_base_url, l.archived_at,
+                l.first_clicked_at, l.last_clicked_at
+         FROM links l
+         LEFT JOIN domains d ON d.id = l.domain_id",
+    );
+    push_link_filters(&mut qb, q);
+    if let Some(id) = before_id {
+        qb.push(" AND l.id < ").push_bind(id);
+    }
+    qb.push(" ORDER BY l.id DESC LIMIT ").push_bind(limit);
+    qb.build_query_as().fetch_all(pool).await
+}
+
+pub async fn count_links(pool: &SqlitePool, q: &LinkQuery) -> Result<i64, sqlx::Error> {
+    let mut qb = QueryBuilder::new("SELECT COUNT(*) FROM links l");
+    push_link_filters(&mut qb, q);
+    qb.build_query_scalar().fetch_one(pool).await
+}
+
+// ── Clicks ─────────────────────────────────────────────────────────────────
+
+fn push_click_filters(qb: &mut QueryBuilder<'_, Sqlite>, scope: &ClickScope) {
+    qb.push(" FROM events c JOIN links l ON l.id = c.link_id WHERE c.event_type = 'click' AND c.internal = 0 AND l.deleted_at IS NULL");
+    if let Some(ws) = scope.workspace_id {
+        qb.push(" AND l.workspace_id = ").push_bind(ws);
+    }
+    if let Some(uid) = scope.owner_id {
+        qb.push(" AND l.user_id = ").push_bind(uid);
+    }
+    if let Some(link_id) = scope.link_id {
+        qb.push(" AND c.link_id = ").push_bind(link_id);
+    }
+    if let Some(from) = scope.from {
+        qb.push(" AND c.clicked_at >= ").push_bind(ts(from));
+    }
+    if let Some(to) = scope.to {
+        qb.push(" AND c.clicked_at < ").push_bind(ts(to));
+    }
+}
+
+/// Like [`push_click_filters`] for the daily rollups of pruned clicks in
+/// `table`, aliased `r`. A rolled-up day is in `scope` if its first moment
+/// is.
+fn push_rollup_filters(qb: &mut QueryBuilder<'_, Sqlite>, scope: &ClickScope, table: &str) {
+    qb.push(format!(
+        " FROM {table} r JOIN links l ON l.id = r.link_id WHERE l.deleted_at IS NULL"
+    ));
+    if let Some(ws) = scope.workspace_id {
+        qb.push(" AND l.workspace_id = ").push_bind(ws);
+    }
+    if let Some(uid) = scope.owner_id {
+        qb.push(" AND l.user_id = ").push_bind(uid);
+    }
+    if let Some(link_id) = scope.link_id {
+        qb.push(" AND r.link_id = ").push_bind(link_id);
+    }
+    if let Some(from) = scope.from {
+        qb.push(" AND r.day || 'T00:00:00Z' >= ")
+            .push_bind(ts(from));
+    }
+    if let Some(to) = scope.to {
+        qb.push(" AND r.day || 'T00:00:00Z' < ").push_bind(ts(to));
+    }
+}
+
+/// Matches the rolled-up clicks [`push_click_filters`] would have matched.
+/// Rows of visitor sketches alone have no events, so aren't included.
+const ROLLED_UP_CLICKS: &str = " AND r.event_type = 'click' AND r.internal = 0 AND r.events > 0";
+
+/// Clicks in `scope`, newest first, paged by id like [`list_links`]. Pruned
+/// clicks are only counted, so they aren't listed.
+pub async fn list_clicks(
+    pool: &SqlitePool,
+    scope: &ClickScope,
+    before_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<Click>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT c.id, c.link_id, c.clicked_at, c.ip_address, c.user_agent, c.referer,
+                c.browser, c.os, c.device_type, c.country, c.country_code, c.continent,
+                c.region, c.city, c.asn, c.isp, c.screen_width, c.screen_height,
+                c.language, c.timezone, c.internal, c.datacenter, c.source,
+                c.matched_language, c.variant",
+    );
+    push_click_filters(&mut qb, scope);
+    if let Some(id) = before_id {
+        qb.push(" AND c.id < ").push_bind(id);
+    }
+    qb.push(" ORDER BY c.id DESC LIMIT ").push_bind(limit);
+    qb.build_query_as().fetch_all(pool).await
+}
+
+pub async fn count_clicks(pool: &SqlitePool, scope: &ClickScope) -> Result<i64, sqlx::Error> {
+    let mut qb = QueryBuilder::new("SELECT (SELECT COUNT(*)");
+    push_click_filters(&mut qb, scope);
+    qb.push(") + (SELECT COALESCE(SUM(r.events), 0)");
+    push_rollup_filters(&mut qb, scope, "event_rollups");
+    qb.push(ROLLED_UP_CLICKS).push(")");
+    qb.build_query_scalar().fetch_one(pool).await
+}
+
+/// Distinct visitors in `scope`: exact, or estimated when the scope
+/// covers pruned days or has [`db_rollups::EXACT_VISITORS_BELOW`] raw clicks
+/// or more. Then whole days in range with visitor sketches are counted from
+/// those, and only the clicks around them scanned.
+pub async fn count_unique_visitors(
+    pool: &SqlitePool,
+    scope: &ClickScope,
+) -> Result<i64, sqlx::Error> {
+    let mut qb = QueryBuilder::new("SELECT r.visitors");
+    push_rollup_filters(&mut qb, scope, "event_rollups");
+    qb.push(ROLLED_UP_CLICKS);
+    let mut sketches: Vec<Vec<u8>> = qb.build_query_scalar().fetch_all(pool).await?;
+
+    let mut qb = QueryBuilder::new("SELECT COUNT(*)");
+    push_click_filters(&mut qb, scope);
+    let raw_clicks: i64 = qb.build_query_scalar().fetch_one(pool).await?;
+    let mut sketched = None;
+    if raw_clicks >= db_rollups::EXACT_VISITORS_BELOW {
+        sketched = db_rollups::sketched_days(pool, scope.from, scope.to).await?;
+    }
+    if let Some(days) = sketched {
+        let mut qb = QueryBuilder::new("SELECT r.visitors");
+        push_rollup_filters(&mut qb, scope, "event_rollups");
+        qb.push(" AND r.event_type = 'click' AND r.internal = 0 AND r.day <= ")
+            .push_bind(days.last.format("%Y-%m-%d").to_string());
+        if let Some(first) = days.first {
+            qb.push(" AND r.day >= ")
+                .push_bind(first.format("%Y-%m-%d").to_string());
+        }
+        sketches.extend(qb.build_query_scalar::<Vec<u8>>().fetch_all(pool).await?);
+    }
+
+    if sketches.is_empty() {
+        let mut qb = QueryBuilder::new("SELECT COUNT(DISTINCT c.visitor_hash)");
+        push_click_filters(&mut qb, scope);
+        return qb.build_query_scalar().fetch_one(pool).await;
+    }
+    let mut qb = QueryBuilder::new("SELECT DISTINCT c.visitor_hash");
+    push_click_filters(&mut qb, scope);
+    qb.push(" AND c.visitor_hash IS NOT NULL");
+    if let Some(days) = sketched {
+        match days.start() {
+            Some(start) => qb
+                .push(" AND (c.clicked_at < ")
+                .push_bind(start)
+                .push(" OR c.clicked_at >= ")
+                .push_bind(days.end())
+                .push(")"),
+            None => qb.push(" AND c.clicked_at >= ").push_bind(days.end()),
+        };
+    }
+    let visitors: Vec<String> = qb.build_query_scalar().fetch_all(pool).await?;
+    Ok(db_rollups::estimate_visitors(
+        sketches.iter().map(Vec::as_slice),
+        &visitors,
+    ))
+}
+
+/// Click counts per value of `dim`, most clicks first. `None` collects
+/// clicks where the value is unknown.
+pub async fn clicks_by(
+    pool: &SqlitePool,
+    scope: &ClickScope,
+    dim: Dimension,
+    limit: i64,
+) -> Result<Vec<(Option<String>, i64)>, sqlx::Error> {
+    let column = dim.column();
+    let mut qb = QueryBuilder::new(format!(
+        "SELECT value, SUM(n) AS total FROM (SELECT {column} AS value, COUNT(*) AS n"
+    ));
+    push_click_filters(&mut qb, scope);
+    qb.push(format!(
+        " GROUP BY {column} UNION ALL SELECT r.value, SUM(r.clicks)"
+    ));
+    push_rollup_filters(&mut qb, scope, "click_rollup_values");
+    qb.push(" AND r.dimension = ")
+        .push_bind(dim.rollup_name())
+        .push(" GROUP BY r.value) GROUP BY value ORDER BY total DESC, value ASC LIMIT ")
+        .push_bind(limit);
+    qb.build_query_as().fetch_all(pool).await
+}
+
+/// Click counts per UTC day (`YYYY-MM-DD`), oldest first, from raw clicks
+/// or, for pruned days, their rollups. Days without clicks are omitted.
+pub async fn clicks_by_day(
+    pool: &SqlitePool,
+    scope: &ClickScope,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    let mut qb = QueryBuilder::new(
+        "SELECT day, SUM(n) FROM (SELECT substr(c.clicked_at, 1, 10) AS day, COUNT(*) AS n",
+    );
+    push_click_filters(&mut qb, scope);
+    qb.push(" GROUP BY day UNION ALL SELECT r.day, SUM(r.events)");
+    push_rollup_filters(&mut qb, scope, "event_rollups");
+    qb.push(ROLLED_UP_CLICKS)
+        .push(" GROUP BY r.day) GROUP BY day ORDER BY day ASC");
+    qb.build_query_as().fetch_all(pool).await
+}
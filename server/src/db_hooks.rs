@@ -0,0 +1,100 @@
+use crate::models::WebhookSubscription;
+use sqlx::SqlitePool;
+
+const SUBSCRIPTION_COLUMNS: &str = "id, user_id, event, target_url, created_at";
+
+/// Every subscription, with the workspace of its owner when they are an
+/// admin (admins receive events for all links in their workspace, everyone
+/// else only for their own).
+pub async fn get_all_subscriptions(
+    pool: &SqlitePool,
+) -> Result<Vec<(WebhookSubscription, Option<i64>)>, sqlx::Error> {
+    let rows: Vec<(i64, i64, String, String, chrono::NaiveDateTime, Option<i64>)> = sqlx::query_as(
+        "SELECT s.id, s.user_id, s.event, s.target_url, s.created_at,
+                    CASE WHEN u.role = 'admin' THEN u.workspace_id END
+             FROM webhook_subscriptions s
+             JOIN users u ON u.id = s.user_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, user_id, event, target_url, created_at, admin_of)| {
+            (
+                WebhookSubscription {
+                    id,
+                    user_id,
+                    event,
+                    target_url,
+                    created_at,
+                },
+                admin_of,
+            )
+        })
+        .collect())
+}
+
+/// A user's subscriptions, oldest first.
+pub async fn get_subscriptions_for_user(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Vec<WebhookSubscription>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {SUBSCRIPTION_COLUMNS} FROM webhook_subscriptions WHERE user_id = ?1 ORDER BY id ASC"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Register a callback URL for an event. Returns the created row.
+pub async fn create_subscription(
+    pool: &SqlitePool,
+    user_id: i64,
+    event: &str,
+    target_url: &str,
+) -> Result<WebhookSubscription, sqlx::Error> {
+    let id = sqlx::query(
+        "INSERT INTO webhook_subscriptions (user_id, event, target_url) VALUES (?1, ?2, ?3)",
+    )
+    .bind(user_id)
+    .bind(event)
+    .bind(target_url)
+    .execute(pool)
+    .await?
+    .last_insert_rowid();
+
+    sqlx::query_as(&format!(
+        "SELECT {SUBSCRIPTION_COLUMNS} FROM webhook_subscriptions WHERE id = ?1"
+    ))
+    .bind(id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Remove a subscription. `user_id` scopes the delete to its owner; `None`
+/// deletes regardless (used when a target answers 410 Gone).
+pub async fn delete_subscription(
+    pool: &SqlitePool,
+    id: i64,
+    user_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let affected = match user_id {
+        Some(uid) => {
+            sqlx::query("DELETE FROM webhook_subscriptions WHERE id = ?1 AND user_id = ?2")
+                .bind(id)
+                .bind(uid)
+                .execute(pool)
+                .await?
+        }
+        None => {
+            sqlx::query("DELETE FROM webhook_subscriptions WHERE id = ?1")
+                .bind(id)
+                .execute(pool)
+                .await?
+        }
+    }
+    .rows_affected();
+    Ok(affected > 0)
+}
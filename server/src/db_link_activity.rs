@@ -0,0 +1,86 @@
+use crate::db_rollups;
+use crate::models::LinkActivity;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use sqlx::SqlitePool;
+
+/// Most changes shown on a link's timeline.
+const MAX_ACTIVITY: i64 = 200;
+
+/// Note a change to `link_id` made by `user_id` (`None` for the system).
+pub async fn record(
+    pool: &SqlitePool,
+    link_id: i64,
+    user_id: Option<i64>,
+    kind: &str,
+    detail: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO link_activity (link_id, kind, detail, user_id) VALUES (?1, ?2, ?3, ?4)",
+    )
+    .bind(link_id)
+    .bind(kind)
+    .bind(detail)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// A link's latest changes, newest first.
+pub async fn get_activity(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Vec<LinkActivity>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT a.kind, a.detail, u.email AS user_email, a.created_at
+         FROM link_activity a
+         LEFT JOIN users u ON u.id = a.user_id
+         WHERE a.link_id = ?1
+         ORDER BY a.created_at DESC, a.id DESC
+         LIMIT ?2",
+    )
+    .bind(link_id)
+    .bind(MAX_ACTIVITY)
+    .fetch_all(pool)
+    .await
+}
+
+/// When a link got its 1st, 10th, 100th, … click, as (clicks, time), up to
+/// the most it has reached. Clicks from internal IPs aren't counted. A
+/// milestone among pruned clicks is dated to the start of its day.
+pub async fn click_milestones(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Vec<(i64, NaiveDateTime)>, sqlx::Error> {
+    let mut reached = Vec::new();
+    let mut clicks = 1;
+
+    let mut pruned = 0;
+    for (day, count) in db_rollups::daily_rolled_up_clicks(pool, link_id).await? {
+        pruned += count;
+        while clicks <= pruned {
+            if let Ok(day) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") {
+                reached.push((clicks, day.and_time(NaiveTime::MIN)));
+            }
+            clicks *= 10;
+        }
+    }
+
+    loop {
+        let at: Option<NaiveDateTime> = sqlx::query_scalar(
+            "SELECT clicked_at FROM events
+             WHERE link_id = ?1 AND event_type = 'click' AND internal = 0
+             ORDER BY clicked_at ASC
+             LIMIT 1 OFFSET ?2",
+        )
+        .bind(link_id)
+        .bind(clicks - 1 - pruned)
+        .fetch_optional(pool)
+        .await?;
+        let Some(at) = at else {
+            return Ok(reached);
+        };
+        reached.push((clicks, at));
+        clicks *= 10;
+    }
+}
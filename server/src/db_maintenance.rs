@@ -0,0 +1,38 @@
+use crate::models::Maintenance;
+use sqlx::SqlitePool;
+
+/// Maintenance mode, or `None` when it's off.
+pub async fn get_maintenance(pool: &SqlitePool) -> Result<Option<Maintenance>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT m.mode, m.message, u.email AS started_by, m.started_at
+         FROM maintenance m
+         LEFT JOIN users u ON u.id = m.started_by
+         WHERE m.id = 1",
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Switch maintenance mode on, or change its mode and message if it already is.
+pub async fn start_maintenance(
+    pool: &SqlitePool,
+    mode: &str,
+    message: Option<&str>,
+    user_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO maintenance (id, mode, message, started_by) VALUES (1, ?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET mode = excluded.mode, message = excluded.message",
+    )
+    .bind(mode)
+    .bind(message)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn end_maintenance(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM maintenance").execute(pool).await?;
+    Ok(())
+}
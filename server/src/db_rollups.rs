@@ -0,0 +1,374 @@
+use crate::db::{TrafficFilter, TRAFFIC_FILTER_SQL};
+use crate::sketch::VisitorSketch;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Click attributes kept per day in `click_rollup_values`, as (dimension,
+/// `events` column). The dimensions match `db_graphql::Dimension`.
+pub const ROLLUP_DIMENSIONS: [(&str, &str); 7] = [
+    ("country", "country"),
+    ("country_code", "country_code"),
+    ("continent", "continent"),
+    ("referer", "referer"),
+    ("browser", "browser"),
+    ("os", "os"),
+    ("device_type", "device_type"),
+];
+
+/// Matches events that may be pruned. Clicks with conversions are kept, as
+/// their conversions would go with them.
+const PRUNABLE_SQL: &str =
+    "NOT EXISTS (SELECT 1 FROM conversions v WHERE v.click_id = events.click_id)";
+
+/// Raw clicks from which unique visitors are estimated from the days'
+/// visitor sketches instead of counted exactly; fewer are cheap to count.
+pub const EXACT_VISITORS_BELOW: i64 = 10_000;
+
+type RollupKey = (i64, String, bool, bool);
+type SketchKey = (i64, bool, bool);
+
+/// A run of whole UTC days whose clicks all have visitor sketches, from
+/// `first` (or the first day with clicks) through `last`.
+#[derive(Debug, Clone, Copy)]
+pub struct SketchedDays {
+    pub first: Option<NaiveDate>,
+    pub last: NaiveDate,
+}
+
+impl SketchedDays {
+    /// The first moment of the run, as `events.clicked_at` is stored.
+    pub fn start(&self) -> Option<String> {
+        self.first.map(day_start)
+    }
+
+    /// The first moment after the run.
+    pub fn end(&self) -> String {
+        day_start(self.last + Duration::days(1))
+    }
+}
+
+// ── Pruning ────────────────────────────────────────────────────────────────
+
+/// The oldest UTC day before `before` with events that can be pruned.
+pub async fn oldest_prunable_day(
+    pool: &SqlitePool,
+    before: NaiveDate,
+) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let day: Option<String> = sqlx::query_scalar(&format!(
+        "SELECT substr(MIN(clicked_at), 1, 10) FROM events
+         WHERE clicked_at < ?1 AND {PRUNABLE_SQL}"
+    ))
+    .bind(before.format("%Y-%m-%d").to_string())
+    .fetch_one(pool)
+    .await?;
+    Ok(day.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()))
+}
+
+/// Fold one UTC day of events into `event_rollups` and
+/// `click_rollup_values`, then delete them, all in one transaction. Returns
+/// how many events were deleted.
+pub async fn roll_up_day(pool: &SqlitePool, day: NaiveDate) -> Result<u64, sqlx::Error> {
+    let from = day_start(day);
+    let to = day_start(day + Duration::days(1));
+    let day = day.format("%Y-%m-%d").to_string();
+    let mut tx = pool.begin().await?;
+
+    let events: Vec<(i64, String, bool, bool, Option<String>)> = sqlx::query_as(&format!(
+        "SELECT link_id, event_type, internal, datacenter, visitor_hash FROM events
+         WHERE clicked_at >= ?1 AND clicked_at < ?2 AND {PRUNABLE_SQL}"
+    ))
+    .bind(&from)
+    .bind(&to)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut rollups: HashMap<RollupKey, (i64, VisitorSketch)> = HashMap::new();
+    for (link_id, event_type, internal, datacenter, visitor) in events {
+        let (count, sketch) = rollups
+            .entry((link_id, event_type, internal, datacenter))
+            .or_default();
+        *count += 1;
+        if let Some(visitor) = visitor {
+            sketch.insert(&visitor);
+        }
+    }
+    for ((link_id, event_type, internal, datacenter), (count, sketch)) in &rollups {
+        sqlx::query(
+            "INSERT INTO event_rollups
+                 (link_id, day, event_type, internal, datacenter, events, visitors)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(link_id)
+        .bind(&day)
+        .bind(event_type)
+        .bind(internal)
+        .bind(datacenter)
+        .bind(count)
+        .bind(sketch.to_bytes())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for (dimension, column) in ROLLUP_DIMENSIONS {
+        sqlx::query(&format!(
+            "INSERT INTO click_rollup_values (link_id, day, dimension, value, clicks)
+             SELECT link_id, ?3, ?4, {column}, COUNT(*) FROM events
+             WHERE clicked_at >= ?1 AND clicked_at < ?2 AND {PRUNABLE_SQL}
+               AND event_type = 'click' AND internal = 0
+             GROUP BY link_id, {column}"
+        ))
+        .bind(&from)
+        .bind(&to)
+        .bind(&day)
+        .bind(dimension)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    let deleted = sqlx::query(&format!(
+        "DELETE FROM events WHERE clicked_at >= ?1 AND clicked_at < ?2 AND {PRUNABLE_SQL}"
+    ))
+    .bind(&from)
+    .bind(&to)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+    Ok(deleted)
+}
+
+// ── Sketching ──────────────────────────────────────────────────────────────
+
+/// Up to `limit` events with an IP address but no visitor hash (clicks from
+/// before hashes were recorded), as (id, IP, user agent).
+pub async fn unhashed_events(
+    pool: &SqlitePool,
+    limit: i64,
+) -> Result<Vec<(i64, String, Option<String>)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, ip_address, user_agent FROM events
+         WHERE visitor_hash IS NULL AND ip_address IS NOT NULL
+         ORDER BY id
+         LIMIT ?1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Store visitor hashes computed for `unhashed_events`, as (id, hash).
+pub async fn set_visitor_hashes(
+    pool: &SqlitePool,
+    hashes: &[(i64, String)],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for (id, hash) in hashes {
+        sqlx::query("UPDATE events SET visitor_hash = ?1 WHERE id = ?2")
+            .bind(hash)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+/// The first UTC day before `before` with clicks and no visitor sketches
+/// yet, after the last day that has them.
+pub async fn next_unsketched_day(
+    pool: &SqlitePool,
+    before: NaiveDate,
+) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let after = match last_sketched_day(pool).await? {
+        Some(last) => day_start(last + Duration::days(1)),
+        None => String::new(),
+    };
+    let day: Option<String> = sqlx::query_scalar(
+        "SELECT substr(MIN(clicked_at), 1, 10) FROM events
+         WHERE clicked_at >= ?1 AND clicked_at < ?2 AND event_type = 'click'",
+    )
+    .bind(after)
+    .bind(day_start(before))
+    .fetch_one(pool)
+    .await?;
+    Ok(day.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()))
+}
+
+/// Store visitor sketches of one UTC day's clicks for every link clicked
+/// that day, as click rollups with no events. Returns how many links that
+/// was.
+pub async fn sketch_day(pool: &SqlitePool, day: NaiveDate) -> Result<usize, sqlx::Error> {
+    let clicks: Vec<(i64, bool, bool, Option<String>)> = sqlx::query_as(
+        "SELECT link_id, internal, datacenter, visitor_hash FROM events
+         WHERE clicked_at >= ?1 AND clicked_at < ?2 AND event_type = 'click'",
+    )
+    .bind(day_start(day))
+    .bind(day_start(day + Duration::days(1)))
+    .fetch_all(pool)
+    .await?;
+
+    // Anonymous clicks still get an (empty) sketch, so the day counts as
+    // sketched
+    let mut sketches: HashMap<SketchKey, VisitorSketch> = HashMap::new();
+    for (link_id, internal, datacenter, visitor) in clicks {
+        let sketch = sketches.entry((link_id, internal, datacenter)).or_default();
+        if let Some(visitor) = visitor {
+            sketch.insert(&visitor);
+        }
+    }
+
+    let day = day.format("%Y-%m-%d").to_string();
+    let mut tx = pool.begin().await?;
+    for ((link_id, internal, datacenter), sketch) in &sketches {
+        sqlx::query(
+            "INSERT INTO event_rollups
+                 (link_id, day, event_type, internal, datacenter, events, visitors)
+             VALUES (?1, ?2, 'click', ?3, ?4, 0, ?5)",
+        )
+        .bind(link_id)
+        .bind(&day)
+        .bind(internal)
+        .bind(datacenter)
+        .bind(sketch.to_bytes())
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    let mut links: Vec<i64> = sketches.keys().map(|(link_id, _, _)| *link_id).collect();
+    links.sort_unstable();
+    links.dedup();
+    Ok(links.len())
+}
+
+/// The last day with click rollups. Days are sketched before they're
+/// pruned, so every day with clicks up to it has visitor sketches.
+async fn last_sketched_day(pool: &SqlitePool) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let day: Option<String> =
+        sqlx::query_scalar("SELECT MAX(day) FROM event_rollups WHERE event_type = 'click'")
+            .fetch_one(pool)
+            .await?;
+    Ok(day.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()))
+}
+
+// ── Reading ────────────────────────────────────────────────────────────────
+
+/// The last day of a link's events that was rolled up, if any was. Rows of
+/// visitor sketches alone (no events) don't count.
+pub async fn last_rolled_up_day(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Option<NaiveDate>, sqlx::Error> {
+    let day: Option<String> =
+        sqlx::query_scalar("SELECT MAX(day) FROM event_rollups WHERE link_id = ?1 AND events > 0")
+            .bind(link_id)
+            .fetch_one(pool)
+            .await?;
+    Ok(day.and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()))
+}
+
+/// A link's rolled-up clicks per day, oldest first, as (day, clicks).
+/// Internal clicks aren't counted.
+pub async fn daily_rolled_up_clicks(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT day, SUM(events) FROM event_rollups
+         WHERE link_id = ?1 AND event_type = 'click' AND internal = 0 AND events > 0
+         GROUP BY day
+         ORDER BY day ASC",
+    )
+    .bind(link_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Visitor sketches of the rolled-up clicks on `link_ids` that `filter`
+/// allows, as (link id, sketch).
+pub async fn click_sketches(
+    pool: &SqlitePool,
+    link_ids: &[i64],
+    filter: TrafficFilter,
+) -> Result<Vec<(i64, Vec<u8>)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT link_id, visitors FROM event_rollups
+         WHERE link_id IN (SELECT value FROM json_each(?1)) AND event_type = 'click'
+           AND events > 0 AND {TRAFFIC_FILTER_SQL}"
+    ))
+    .bind(serde_json::to_string(link_ids).unwrap_or_default())
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .fetch_all(pool)
+    .await
+}
+
+/// The whole UTC days within `[from, to)` whose clicks have visitor
+/// sketches, if there are any. Days only partly in range aren't included.
+pub async fn sketched_days(
+    pool: &SqlitePool,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+) -> Result<Option<SketchedDays>, sqlx::Error> {
+    let Some(mut last) = last_sketched_day(pool).await? else {
+        return Ok(None);
+    };
+    if let Some(to) = to {
+        last = last.min(to.date() - Duration::days(1));
+    }
+    let first = from.map(|from| match from.time() {
+        NaiveTime::MIN => from.date(),
+        _ => from.date() + Duration::days(1),
+    });
+    Ok(match first {
+        Some(first) if first > last => None,
+        _ => Some(SketchedDays { first, last }),
+    })
+}
+
+/// Visitor sketches of all clicks on `link_ids` on `days` that `filter`
+/// allows, raw and rolled up, as (link id, sketch).
+pub async fn day_sketches(
+    pool: &SqlitePool,
+    link_ids: &[i64],
+    days: SketchedDays,
+    filter: TrafficFilter,
+) -> Result<Vec<(i64, Vec<u8>)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT link_id, visitors FROM event_rollups
+         WHERE link_id IN (SELECT value FROM json_each(?1)) AND event_type = 'click'
+           AND {TRAFFIC_FILTER_SQL} AND day >= ?4 AND day <= ?5"
+    ))
+    .bind(serde_json::to_string(link_ids).unwrap_or_default())
+    .bind(filter.include_internal)
+    .bind(filter.exclude_datacenter)
+    .bind(
+        days.first
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+    )
+    .bind(days.last.format("%Y-%m-%d").to_string())
+    .fetch_all(pool)
+    .await
+}
+
+/// Distinct visitors among the `raw` visitor hashes and in `sketches`,
+/// estimated: a visitor seen both before and after pruning counts once.
+pub fn estimate_visitors<'a>(sketches: impl IntoIterator<Item = &'a [u8]>, raw: &[String]) -> i64 {
+    let mut merged = VisitorSketch::default();
+    for bytes in sketches {
+        merged.merge(&VisitorSketch::from_bytes(bytes));
+    }
+    for visitor in raw {
+        merged.insert(visitor);
+    }
+    merged.estimate()
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+/// The first moment of `day`, as `events.clicked_at` is stored.
+fn day_start(day: NaiveDate) -> String {
+    format!("{}T00:00:00Z", day.format("%Y-%m-%d"))
+}
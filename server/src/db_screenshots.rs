@@ -0,0 +1,59 @@
+use crate::models::Screenshot;
+use chrono::NaiveDateTime;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// Store a link's thumbnail, replacing any earlier one.
+pub async fn save_screenshot(
+    pool: &SqlitePool,
+    link_id: i64,
+    content_type: &str,
+    image: &[u8],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO link_screenshots (link_id, content_type, image) VALUES (?1, ?2, ?3)
+         ON CONFLICT(link_id) DO UPDATE SET
+             content_type = excluded.content_type,
+             image = excluded.image,
+             captured_at = excluded.captured_at",
+    )
+    .bind(link_id)
+    .bind(content_type)
+    .bind(image)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_screenshot(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Option<Screenshot>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT content_type, image, captured_at FROM link_screenshots WHERE link_id = ?1",
+    )
+    .bind(link_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// When a link's thumbnail was captured, if it has one.
+pub async fn captured_at(
+    pool: &SqlitePool,
+    link_id: i64,
+) -> Result<Option<NaiveDateTime>, sqlx::Error> {
+    sqlx::query_scalar("SELECT captured_at FROM link_screenshots WHERE link_id = ?1")
+        .bind(link_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// When each link with a thumbnail was captured, keyed by link ID. The time
+/// goes in the image URL so browsers fetch a new capture.
+pub async fn captured_times(pool: &SqlitePool) -> Result<HashMap<i64, NaiveDateTime>, sqlx::Error> {
+    let rows: Vec<(i64, NaiveDateTime)> =
+        sqlx::query_as("SELECT link_id, captured_at FROM link_screenshots")
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().collect())
+}
@@ -1,8 +1,9 @@
-use crate::models::User;
+use crate::models::{ApiToken, User};
 use sqlx::SqlitePool;
 
 const USER_COLUMNS: &str =
-    "id, email, display_name, password_hash, role, is_approved, created_at, updated_at, force_password_change";
+    "id, email, display_name, password_hash, role, is_approved, created_at, updated_at, force_password_change,
+     workspace_id, max_links, api_requests_per_hour";
 
 /// Find a user by email (for login).
 pub async fn get_user_by_email(
@@ -25,7 +26,17 @@ pub async fn get_user_by_id(pool: &SqlitePool, id: i64) -> Result<Option<User>,
         .await
 }
 
-/// Create a new user. Returns the created row.
+/// Find the oldest admin account (default owner for CLI-created links).
+pub async fn get_first_admin(pool: &SqlitePool) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS} FROM users WHERE role = 'admin' ORDER BY id ASC LIMIT 1"
+    ))
+    .fetch_optional(pool)
+    .await
+}
+
+/// Create a new user in `workspace_id`. Returns the created row.
+#[allow(clippy::too_many_arguments)]
 pub async fn create_user(
     pool: &SqlitePool,
     email: &str,
@@ -34,10 +45,12 @@ pub async fn create_user(
     role: &str,
     is_approved: bool,
     force_password_change: bool,
+    workspace_id: i64,
 ) -> Result<User, sqlx::Error> {
     let id = sqlx::query(
-        "INSERT INTO users (email, display_name, password_hash, role, is_approved, force_password_change)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO users (email, display_name, password_hash, role, is_approved, force_password_change,
+                            workspace_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
     )
     .bind(email)
     .bind(display_name)
@@ -45,6 +58,7 @@ pub async fn create_user(
     .bind(role)
     .bind(is_approved)
     .bind(force_password_change)
+    .bind(workspace_id)
     .execute(pool)
     .await?
     .last_insert_rowid();
@@ -62,21 +76,36 @@ pub async fn count_users(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
     Ok(count)
 }
 
-/// List all users (for admin user management page).
-pub async fn get_all_users(pool: &SqlitePool) -> Result<Vec<User>, sqlx::Error> {
+/// List the users in `workspace_id` (for admin user management page).
+///
+/// The admin functions below all take the acting admin's workspace and only
+/// touch users in it; `None` (instance admins) reaches every workspace.
+pub async fn get_all_users(
+    pool: &SqlitePool,
+    workspace_id: Option<i64>,
+) -> Result<Vec<User>, sqlx::Error> {
     sqlx::query_as(&format!(
-        "SELECT {USER_COLUMNS} FROM users ORDER BY created_at DESC"
+        "SELECT {USER_COLUMNS} FROM users
+         WHERE ?1 IS NULL OR workspace_id = ?1
+         ORDER BY created_at DESC"
     ))
+    .bind(workspace_id)
     .fetch_all(pool)
     .await
 }
 
 /// Approve a user (admin action).
-pub async fn approve_user(pool: &SqlitePool, user_id: i64) -> Result<bool, sqlx::Error> {
+pub async fn approve_user(
+    pool: &SqlitePool,
+    workspace_id: Option<i64>,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
     let affected = sqlx::query(
-        "UPDATE users SET is_approved = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now') WHERE id = ?1",
+        "UPDATE users SET is_approved = 1, updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+         WHERE id = ?1 AND (?2 IS NULL OR workspace_id = ?2)",
     )
     .bind(user_id)
+    .bind(workspace_id)
     .execute(pool)
     .await?
     .rows_affected();
@@ -86,14 +115,17 @@ pub async fn approve_user(pool: &SqlitePool, user_id: i64) -> Result<bool, sqlx:
 /// Change a user's role (admin action).
 pub async fn set_user_role(
     pool: &SqlitePool,
+    workspace_id: Option<i64>,
     user_id: i64,
     role: &str,
 ) -> Result<bool, sqlx::Error> {
     let affected = sqlx::query(
-        "UPDATE users SET role = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now') WHERE id = ?2",
+        "UPDATE users SET role = ?1, updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+         WHERE id = ?2 AND (?3 IS NULL OR workspace_id = ?3)",
     )
     .bind(role)
     .bind(user_id)
+    .bind(workspace_id)
     .execute(pool)
     .await?
     .rows_affected();
@@ -101,12 +133,18 @@ pub async fn set_user_role(
 }
 
 /// Delete a user (admin action). Links/pages become unowned (user_id = NULL via ON DELETE SET NULL).
-pub async fn delete_user(pool: &SqlitePool, user_id: i64) -> Result<bool, sqlx::Error> {
-    let affected = sqlx::query("DELETE FROM users WHERE id = ?1")
-        .bind(user_id)
-        .execute(pool)
-        .await?
-        .rows_affected();
+pub async fn delete_user(
+    pool: &SqlitePool,
+    workspace_id: Option<i64>,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let affected =
+        sqlx::query("DELETE FROM users WHERE id = ?1 AND (?2 IS NULL OR workspace_id = ?2)")
+            .bind(user_id)
+            .bind(workspace_id)
+            .execute(pool)
+            .await?
+            .rows_affected();
     Ok(affected > 0)
 }
 
@@ -131,8 +169,10 @@ pub async fn update_user_profile(
 }
 
 /// Update all editable fields for a user (admin edit).
+#[allow(clippy::too_many_arguments)]
 pub async fn update_user_full(
     pool: &SqlitePool,
+    workspace_id: Option<i64>,
     user_id: i64,
     email: &str,
     display_name: &str,
@@ -143,7 +183,7 @@ pub async fn update_user_full(
     let affected = sqlx::query(
         "UPDATE users SET email = ?1, display_name = ?2, role = ?3, is_approved = ?4,
          force_password_change = ?5, updated_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
-         WHERE id = ?6",
+         WHERE id = ?6 AND (?7 IS NULL OR workspace_id = ?7)",
     )
     .bind(email)
     .bind(display_name)
@@ -151,6 +191,7 @@ pub async fn update_user_full(
     .bind(is_approved)
     .bind(force_password_change)
     .bind(user_id)
+    .bind(workspace_id)
     .execute(pool)
     .await?
     .rows_affected();
@@ -174,3 +215,155 @@ pub async fn update_user_password(
     .rows_affected();
     Ok(affected > 0)
 }
+
+// ── API tokens ────────────────────────────────────────────────────────────
+
+const API_TOKEN_COLUMNS: &str = "id, user_id, name, created_at, last_used_at";
+
+/// List a user's API tokens, newest first.
+pub async fn get_api_tokens(pool: &SqlitePool, user_id: i64) -> Result<Vec<ApiToken>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {API_TOKEN_COLUMNS} FROM api_tokens WHERE user_id = ?1 ORDER BY id DESC"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Store a new API token by its hash.
+pub async fn create_api_token(
+    pool: &SqlitePool,
+    user_id: i64,
+    name: &str,
+    token_hash: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO api_tokens (user_id, name, token_hash) VALUES (?1, ?2, ?3)")
+        .bind(user_id)
+        .bind(name)
+        .bind(token_hash)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Revoke one of a user's API tokens.
+pub async fn delete_api_token(
+    pool: &SqlitePool,
+    id: i64,
+    user_id: i64,
+) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query("DELETE FROM api_tokens WHERE id = ?1 AND user_id = ?2")
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(affected > 0)
+}
+
+/// Resolve an API token hash to its (approved) owner and the token's ID,
+/// and record the use.
+pub async fn get_user_by_api_token(
+    pool: &SqlitePool,
+    token_hash: &str,
+) -> Result<Option<(User, i64)>, sqlx::Error> {
+    let token: Option<(i64, i64)> =
+        sqlx::query_as("SELECT id, user_id FROM api_tokens WHERE token_hash = ?1")
+            .bind(token_hash)
+            .fetch_optional(pool)
+            .await?;
+    let Some((token_id, user_id)) = token else {
+        return Ok(None);
+    };
+
+    let user: Option<User> = sqlx::query_as(&format!(
+        "SELECT {USER_COLUMNS} FROM users WHERE id = ?1 AND is_approved = 1"
+    ))
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    if user.is_some() {
+        sqlx::query(
+            "UPDATE api_tokens SET last_used_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+             WHERE id = ?1",
+        )
+        .bind(token_id)
+        .execute(pool)
+        .await?;
+    }
+    Ok(user.map(|u| (u, token_id)))
+}
+
+// ── Quotas ────────────────────────────────────────────────────────────────
+
+/// A user's link count and their `max_links` override, if the user exists.
+pub async fn link_quota(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Option<(i64, Option<i64>)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT (SELECT COUNT(*) FROM links WHERE user_id = ?1 AND deleted_at IS NULL), max_links
+         FROM users WHERE id = ?1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Number of links owned by each user that owns any, as (user_id, count).
+pub async fn link_counts(pool: &SqlitePool) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT user_id, COUNT(*) FROM links
+         WHERE user_id IS NOT NULL AND deleted_at IS NULL
+         GROUP BY user_id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Set a user's quota overrides (`None` = use the instance default).
+pub async fn set_user_quotas(
+    pool: &SqlitePool,
+    user_id: i64,
+    max_links: Option<i64>,
+    api_requests_per_hour: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET max_links = ?1, api_requests_per_hour = ?2 WHERE id = ?3")
+        .bind(max_links)
+        .bind(api_requests_per_hour)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// ── Login IPs ─────────────────────────────────────────────────────────────
+
+/// Record a successful login from `ip`. Returns `true` when the user has
+/// signed in before but never from this address.
+pub async fn record_login_ip(
+    pool: &SqlitePool,
+    user_id: i64,
+    ip: &str,
+) -> Result<bool, sqlx::Error> {
+    let (known_ips, seen_this_ip): (i64, i64) = sqlx::query_as(
+        "SELECT COUNT(*), COALESCE(SUM(ip = ?2), 0) FROM user_login_ips WHERE user_id = ?1",
+    )
+    .bind(user_id)
+    .bind(ip)
+    .fetch_one(pool)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO user_login_ips (user_id, ip) VALUES (?1, ?2)
+         ON CONFLICT (user_id, ip)
+         DO UPDATE SET last_seen_at = strftime('%Y-%m-%dT%H:%M:%SZ', 'now')",
+    )
+    .bind(user_id)
+    .bind(ip)
+    .execute(pool)
+    .await?;
+
+    Ok(known_ips > 0 && seen_this_ip == 0)
+}
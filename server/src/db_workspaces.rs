@@ -0,0 +1,80 @@
+use crate::models::Workspace;
+use sqlx::SqlitePool;
+
+const WORKSPACE_COLUMNS: &str = "id, name, created_at";
+
+/// List all workspaces with their user and link counts, oldest first.
+pub async fn get_all_workspaces(
+    pool: &SqlitePool,
+) -> Result<Vec<(Workspace, i64, i64)>, sqlx::Error> {
+    let rows: Vec<(i64, String, chrono::NaiveDateTime, i64, i64)> = sqlx::query_as(
+        "SELECT w.id, w.name, w.created_at,
+                (SELECT COUNT(*) FROM users u WHERE u.workspace_id = w.id),
+                (SELECT COUNT(*) FROM links l
+                 WHERE l.workspace_id = w.id AND l.deleted_at IS NULL)
+         FROM workspaces w
+         ORDER BY w.id ASC",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, name, created_at, users, links)| {
+            (
+                Workspace {
+                    id,
+                    name,
+                    created_at,
+                },
+                users,
+                links,
+            )
+        })
+        .collect())
+}
+
+/// Find a workspace by ID.
+pub async fn get_workspace_by_id(
+    pool: &SqlitePool,
+    id: i64,
+) -> Result<Option<Workspace>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        "SELECT {WORKSPACE_COLUMNS} FROM workspaces WHERE id = ?1"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Add a workspace. Returns the created row.
+pub async fn create_workspace(pool: &SqlitePool, name: &str) -> Result<Workspace, sqlx::Error> {
+    let id = sqlx::query("INSERT INTO workspaces (name) VALUES (?1)")
+        .bind(name)
+        .execute(pool)
+        .await?
+        .last_insert_rowid();
+
+    get_workspace_by_id(pool, id)
+        .await
+        .map(|opt| opt.expect("just-inserted workspace must exist"))
+}
+
+/// Delete a workspace that has no users, links, or links pages left. The
+/// default workspace is never deleted. Domains reserved for it become
+/// shared (ON DELETE SET NULL).
+pub async fn delete_workspace(pool: &SqlitePool, id: i64) -> Result<bool, sqlx::Error> {
+    let affected = sqlx::query(
+        "DELETE FROM workspaces
+         WHERE id = ?1 AND id != ?2
+           AND NOT EXISTS (SELECT 1 FROM users WHERE workspace_id = ?1)
+           AND NOT EXISTS (SELECT 1 FROM links WHERE workspace_id = ?1)
+           AND NOT EXISTS (SELECT 1 FROM bio_pages WHERE workspace_id = ?1)",
+    )
+    .bind(id)
+    .bind(crate::models::DEFAULT_WORKSPACE)
+    .execute(pool)
+    .await?
+    .rows_affected();
+    Ok(affected > 0)
+}
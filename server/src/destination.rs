@@ -0,0 +1,298 @@
+use crate::{config::AppConfig, db_domains, geo};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::{header, StatusCode, Url};
+use sqlx::SqlitePool;
+use std::{borrow::Cow, collections::HashSet, time::Duration};
+
+/// Redirects followed when checking a new destination. A chain longer than
+/// this is treated as a loop.
+const MAX_HOPS: usize = 10;
+
+/// Time allowed for each hop, so a slow site doesn't hold up link creation.
+const HOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Query parameters dropped by [`normalize`] with `STRIP_TRACKING_PARAMS`,
+/// besides every `utm_*` one.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "msclkid",
+    "mc_cid",
+    "mc_eid",
+    "igshid",
+    "yclid",
+    "twclid",
+    "ttclid",
+    "li_fat_id",
+    "_ga",
+    "_gl",
+];
+
+/// Escaped by [`location`] when a URL can't be parsed: controls, spaces
+/// and (always, by `utf8_percent_encode`) non-ASCII bytes.
+const UNSAFE_IN_HEADER: &AsciiSet = &CONTROLS.add(b' ');
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Why a destination was refused.
+#[derive(Debug, thiserror::Error)]
+pub enum DestinationError {
+    #[error("The destination points back at this server; link to the final page instead.")]
+    SelfReference,
+    #[error("The destination redirects back to this server, which would loop.")]
+    RedirectsHere,
+    #[error("The destination's redirects go round in a circle.")]
+    Loop,
+    #[error("The destination redirects to a {0}: URL; links may only lead to http(s) pages.")]
+    UnsafeRedirect(String),
+    #[error(
+        "The destination redirects more than {} times, which looks like a loop.",
+        MAX_HOPS
+    )]
+    TooManyRedirects,
+    #[error("The URL leads to this server or a private network address; it must be public.")]
+    PrivateAddress,
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+}
+
+/// One request made while following a destination's redirects.
+#[derive(Debug, Clone)]
+pub struct Hop {
+    pub url: String,
+    /// `None` when the site couldn't be reached.
+    pub status: Option<u16>,
+}
+
+/// Where following a destination's redirects led: each request made, in
+/// order, and what is wrong with the chain, if anything.
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub hops: Vec<Hop>,
+    pub problem: Option<DestinationError>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// Refuse a new link's destination if it is on one of this instance's own
+/// hosts (the base URL or a short domain), or if following its redirects
+/// leads back to one, goes round in circles, or ends somewhere other than
+/// an http(s) page (such as a `javascript:` URL). `client` must not follow
+/// redirects itself (see `http::probe_client`).
+///
+/// Sites that can't be reached are accepted: the check is only about where
+/// redirects lead.
+pub async fn check(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(), DestinationError> {
+    match trace(pool, config, client, url).await?.problem {
+        Some(problem) => Err(problem),
+        None => Ok(()),
+    }
+}
+
+/// Refuse a URL this server sends requests to on a user's behalf, such as
+/// a REST hook's target, if it is on one of this instance's own hosts (as
+/// [`check`] refuses) or its host resolves to a loopback, private, or
+/// link-local address, so users can't reach internal services through it.
+/// Hosts that don't resolve are accepted; nothing can be sent to them.
+pub async fn check_callback(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    url: &str,
+) -> Result<(), DestinationError> {
+    let Ok(url) = Url::parse(url) else {
+        return Err(DestinationError::PrivateAddress);
+    };
+    if OwnHosts::load(pool, config).await?.contains(&url) {
+        return Err(DestinationError::PrivateAddress);
+    }
+    let Some(host) = url.host_str() else {
+        return Err(DestinationError::PrivateAddress);
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().unwrap_or(80);
+    let private = match tokio::net::lookup_host((host, port)).await {
+        Ok(mut addrs) => addrs.any(|addr| geo::is_private_ip(addr.ip())),
+        Err(_) => false,
+    };
+    if private {
+        return Err(DestinationError::PrivateAddress);
+    }
+    Ok(())
+}
+
+/// Follow `url`'s redirects as [`check`] does, noting the status of each
+/// request, for checking an existing link on demand.
+pub async fn trace(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<Trace, sqlx::Error> {
+    let mut trace = Trace::default();
+    let Ok(mut current) = Url::parse(url) else {
+        return Ok(trace);
+    };
+    let own = OwnHosts::load(pool, config).await?;
+    if own.contains(&current) {
+        trace.problem = Some(DestinationError::SelfReference);
+        return Ok(trace);
+    }
+
+    let mut seen = HashSet::from([current.to_string()]);
+    for _ in 0..MAX_HOPS {
+        let response = request(client, &current).await;
+        trace.hops.push(Hop {
+            url: current.to_string(),
+            status: response.as_ref().map(|(status, _)| status.as_u16()),
+        });
+        let Some((_, Some(next))) = response else {
+            return Ok(trace);
+        };
+        if !matches!(next.scheme(), "http" | "https") {
+            trace.problem = Some(DestinationError::UnsafeRedirect(next.scheme().to_owned()));
+            return Ok(trace);
+        }
+        if own.contains(&next) {
+            trace.problem = Some(DestinationError::RedirectsHere);
+            return Ok(trace);
+        }
+        if !seen.insert(next.to_string()) {
+            trace.problem = Some(DestinationError::Loop);
+            return Ok(trace);
+        }
+        current = next;
+    }
+    trace.problem = Some(DestinationError::TooManyRedirects);
+    Ok(trace)
+}
+
+/// Canonical form of a destination, stored in place of what was typed so
+/// the same page always compares equal: lower-case scheme and host, IDNs in
+/// punycode, default ports and `..` segments removed, an empty query
+/// dropped, and with `strip_tracking` the known tracking parameters too.
+/// `None` unless `url` is an absolute http(s) URL.
+///
+/// Destination placeholders (`{code}` etc.) are kept as typed.
+pub fn normalize(url: &str, strip_tracking: bool) -> Option<String> {
+    let mut parsed = Url::parse(url.trim()).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return None;
+    }
+
+    if strip_tracking {
+        if let Some(query) = parsed.query() {
+            // Filtered on the raw pairs so the rest of the query keeps its
+            // exact encoding, placeholders included
+            let kept = query
+                .split('&')
+                .filter(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    value.contains('{') || !is_tracking_param(key)
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            parsed.set_query(Some(&kept));
+        }
+    }
+    if parsed.query() == Some("") {
+        parsed.set_query(None);
+    }
+
+    let mut normalized = String::from(parsed);
+    // The parser percent-encodes braces in the path; put placeholders back
+    // unless the original spelled them encoded.
+    if !url.contains("%7B") && !url.contains("%7b") {
+        normalized = normalized.replace("%7B", "{").replace("%7D", "}");
+    }
+    Some(normalized)
+}
+
+/// `url` as it can be sent in a `Location` header, which only carries
+/// visible ASCII: Unicode hosts in punycode and everything else
+/// percent-encoded. Links saved before destinations were normalized, and
+/// placeholders filled in with non-ASCII values, can need this.
+pub fn location(url: &str) -> Cow<'_, str> {
+    if url.bytes().all(|b| b.is_ascii_graphic()) {
+        return Cow::Borrowed(url);
+    }
+    match Url::parse(url) {
+        Ok(parsed) => Cow::Owned(parsed.into()),
+        Err(_) => Cow::Owned(utf8_percent_encode(url, UNSAFE_IN_HEADER).to_string()),
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
+
+/// Host names (and the base URL's port) this instance answers on.
+struct OwnHosts {
+    base: Option<(String, Option<u16>)>,
+    domains: HashSet<String>,
+}
+
+impl OwnHosts {
+    async fn load(pool: &SqlitePool, config: &AppConfig) -> Result<Self, sqlx::Error> {
+        let base = Url::parse(&config.base_url).ok().and_then(|u| {
+            let host = u.host_str()?.to_ascii_lowercase();
+            Some((host, u.port_or_known_default()))
+        });
+        let domains = db_domains::get_all_domains(pool)
+            .await?
+            .into_iter()
+            .map(|d| d.hostname.to_ascii_lowercase())
+            .collect();
+        Ok(Self { base, domains })
+    }
+
+    fn contains(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let on_base = self
+            .base
+            .as_ref()
+            .is_some_and(|(h, port)| *h == host && *port == url.port_or_known_default());
+        on_base || self.domains.contains(&host)
+    }
+}
+
+/// The status `url` answers with and where it redirects to, if anywhere;
+/// `None` if it can't be reached.
+async fn request(client: &reqwest::Client, url: &Url) -> Option<(StatusCode, Option<Url>)> {
+    let mut resp = client
+        .head(url.clone())
+        .timeout(HOP_TIMEOUT)
+        .send()
+        .await
+        .ok()?;
+    // Some servers don't implement HEAD
+    if matches!(
+        resp.status(),
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    ) {
+        resp = client
+            .get(url.clone())
+            .timeout(HOP_TIMEOUT)
+            .send()
+            .await
+            .ok()?;
+    }
+    let status = resp.status();
+    let next = status
+        .is_redirection()
+        .then(|| resp.headers().get(header::LOCATION)?.to_str().ok())
+        .flatten()
+        .and_then(|location| url.join(location).ok());
+    Some((status, next))
+}
@@ -0,0 +1,186 @@
+use crate::{db_digest, AppState};
+use askama::Template;
+use chrono::{Datelike, Duration, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// Links listed in the digest.
+const TOP_LINKS: i64 = 10;
+
+/// A digest whose scheduled time passed while the server was down is still
+/// sent if the server comes back within this window.
+const CATCH_UP_HOURS: i64 = 24;
+
+// ── Templates ──────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "email/weekly_digest.txt")]
+struct DigestEmail<'a> {
+    app_title: &'a str,
+    base_url: &'a str,
+    period: &'a str,
+    total_clicks: i64,
+    change: &'a str,
+    new_links: i64,
+    top_links: &'a [TopLink],
+    unsubscribe_url: String,
+}
+
+struct TopLink {
+    label: String,
+    short_url: String,
+    clicks: i64,
+    change: String,
+}
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Send the digest every week at `DIGEST_WEEKDAY` `DIGEST_HOUR`:00 UTC.
+/// Spawned from `main` when SMTP and `DIGEST_RECIPIENTS` are configured.
+pub async fn run(state: Arc<AppState>) {
+    tracing::info!(
+        "Weekly digest scheduled for {} {:02}:00 UTC",
+        state.config.digest_weekday,
+        state.config.digest_hour
+    );
+
+    loop {
+        let now = Utc::now().naive_utc();
+        let due = most_recent_due(&state, now);
+        if now - due < Duration::hours(CATCH_UP_HOURS) {
+            if let Err(e) = send_week(&state, due).await {
+                tracing::error!("Failed to send weekly digest: {:?}", e);
+            }
+        }
+
+        let next = due + Duration::days(7);
+        let wait = (next - Utc::now().naive_utc()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// The latest scheduled send time at or before `now`.
+fn most_recent_due(state: &AppState, now: NaiveDateTime) -> NaiveDateTime {
+    let today = now.date();
+    let days_back = (today.weekday().num_days_from_monday() + 7
+        - state.config.digest_weekday.num_days_from_monday())
+        % 7;
+    let due = (today - Duration::days(i64::from(days_back)))
+        .and_hms_opt(state.config.digest_hour, 0, 0)
+        .unwrap_or_default();
+    if due > now {
+        due - Duration::days(7)
+    } else {
+        due
+    }
+}
+
+// ── Composition ────────────────────────────────────────────────────────────
+
+/// Email the digest for the seven days ending at `week_end`, unless it has
+/// already gone out.
+async fn send_week(state: &AppState, week_end: NaiveDateTime) -> Result<(), sqlx::Error> {
+    let db = &state.db;
+    let week_start = week_end - Duration::days(7);
+    let prev_start = week_start - Duration::days(7);
+
+    let total_clicks = db_digest::count_clicks_between(db, week_start, week_end).await?;
+    let prev_clicks = db_digest::count_clicks_between(db, prev_start, week_start).await?;
+    let new_links = db_digest::count_links_created_between(db, week_start, week_end).await?;
+    let top_links: Vec<TopLink> =
+        db_digest::top_links_for_week(db, week_start, week_end, TOP_LINKS)
+            .await?
+            .into_iter()
+            .map(|l| TopLink {
+                short_url: state.short_url(l.domain_id, &l.short_code),
+                change: pct_change(l.clicks, l.prev_clicks),
+                label: l.title.unwrap_or(l.short_code),
+                clicks: l.clicks,
+            })
+            .collect();
+    let opt_outs = db_digest::get_opt_outs(db).await?;
+
+    if !db_digest::claim_week(db, week_start).await? {
+        return Ok(());
+    }
+
+    let period = format!(
+        "{} – {}",
+        week_start.format("%b %-d"),
+        (week_end - Duration::days(1)).format("%b %-d, %Y")
+    );
+    let change = pct_change(total_clicks, prev_clicks);
+    let subject = format!("{} weekly summary: {}", state.config.app_title, period);
+
+    let mut sent = 0;
+    for recipient in &state.config.digest_recipients {
+        if opt_outs.contains(recipient) {
+            continue;
+        }
+        let email = DigestEmail {
+            app_title: &state.config.app_title,
+            base_url: &state.config.base_url,
+            period: &period,
+            total_clicks,
+            change: &change,
+            new_links,
+            top_links: &top_links,
+            unsubscribe_url: unsubscribe_url(state, recipient),
+        };
+        state.mailer.send(recipient, &subject, &email);
+        sent += 1;
+    }
+    tracing::info!(
+        "Queued weekly digest for {} to {} recipient(s)",
+        period,
+        sent
+    );
+    Ok(())
+}
+
+/// Week-over-week change, e.g. "+12%", "-5%", or "new".
+fn pct_change(current: i64, previous: i64) -> String {
+    match (current, previous) {
+        (0, 0) => "no change".into(),
+        (_, 0) => "new".into(),
+        _ => {
+            let pct = ((current - previous) as f64 / previous as f64 * 100.0).round() as i64;
+            format!("{pct:+}%")
+        }
+    }
+}
+
+// ── Unsubscribe tokens ─────────────────────────────────────────────────────
+
+fn unsubscribe_url(state: &AppState, email: &str) -> String {
+    let query = serde_urlencoded::to_string([
+        ("email", email),
+        ("token", &unsubscribe_token(&state.config.jwt_secret, email)),
+    ])
+    .unwrap_or_default();
+    format!("{}/digest/unsubscribe?{}", state.config.base_url, query)
+}
+
+/// Unsubscribe links are signed so nobody can opt out someone else.
+fn unsubscribe_mac(secret: &str, email: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"digest-unsubscribe:");
+    mac.update(email.as_bytes());
+    mac
+}
+
+fn unsubscribe_token(secret: &str, email: &str) -> String {
+    hex::encode(unsubscribe_mac(secret, email).finalize().into_bytes())
+}
+
+/// Check a token from an unsubscribe link in constant time.
+pub fn verify_unsubscribe_token(secret: &str, email: &str, token: &str) -> bool {
+    let Ok(expected) = hex::decode(token) else {
+        return false;
+    };
+    unsubscribe_mac(secret, email)
+        .verify_slice(&expected)
+        .is_ok()
+}
@@ -0,0 +1,208 @@
+use crate::{
+    handlers::redirect,
+    validate::{FieldError, Invalid},
+    AppState,
+};
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::{net::SocketAddr, sync::Arc};
+use utoipa::ToSchema;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// An error a handler or extractor answers with. [`render`] turns it into
+/// an HTML page for browsers, or problem details (RFC 9457) for API clients.
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    NotFound(String),
+    /// Something with the same name, code, or email already exists.
+    #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
+    PayloadTooLarge(String),
+    /// The request was understood, but its values are invalid.
+    #[error("{0}")]
+    Unprocessable(String),
+    /// Fields of a link are invalid; listed one by one in problem details.
+    #[error("{0}")]
+    Invalid(#[from] Invalid),
+    /// A rate limit or quota was reached; retry after the given seconds.
+    #[error("{0}")]
+    RateLimited(String, Option<u64>),
+    #[error("{0}")]
+    Unavailable(String),
+    /// Anything unexpected, such as a failed query. Logged in full; the
+    /// client only sees "Internal error".
+    #[error("Internal error")]
+    Internal(#[from] anyhow::Error),
+}
+
+/// Problem details as the API sends them. `error` repeats `detail` for
+/// clients written against the original `{"error": "..."}` responses.
+#[derive(Serialize, ToSchema)]
+pub struct Problem {
+    #[schema(example = "about:blank")]
+    r#type: &'static str,
+    #[schema(example = "Not Found")]
+    title: &'static str,
+    #[schema(example = 404)]
+    status: u16,
+    #[schema(example = "Link not found")]
+    detail: String,
+    #[schema(example = "Link not found")]
+    error: String,
+    /// For invalid input, what is wrong with each field.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+/// Marks a response as an [`AppError`] for [`render`], with its message and
+/// any field errors.
+#[derive(Clone)]
+struct ErrorMessage(String, Vec<FieldError>);
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::Unprocessable(_) | Self::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::RateLimited(..) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// An [`AppError::Internal`] for `e`, saying what was being done.
+    pub fn internal<E, C>(e: E, context: C) -> Self
+    where
+        E: Into<anyhow::Error>,
+        C: std::fmt::Display + Send + Sync + 'static,
+    {
+        Self::Internal(e.into().context(context))
+    }
+
+    /// The error as a line of plain text, for clients that asked for text
+    /// rather than problem details.
+    pub fn into_text_response(self) -> Response {
+        let mut response = self.into_response();
+        response.extensions_mut().remove::<ErrorMessage>();
+        response
+    }
+}
+
+/// A unique constraint failing becomes a generic [`AppError::Conflict`];
+/// handlers that can name what's taken check [`crate::db::is_unique_violation`]
+/// first.
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        if crate::db::is_unique_violation(&e) {
+            Self::Conflict("That already exists".into())
+        } else {
+            Self::Internal(e.into())
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        if let Self::Internal(e) = &self {
+            tracing::error!("{:#}", e);
+        }
+        let message = self.to_string();
+        let mut response = (self.status(), format!("{message}\n")).into_response();
+        let mut fields = Vec::new();
+        match self {
+            Self::RateLimited(_, Some(retry_after)) => {
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            }
+            Self::Invalid(Invalid(errors)) => fields = errors,
+            _ => {}
+        }
+        response
+            .extensions_mut()
+            .insert(ErrorMessage(message, fields));
+        response
+    }
+}
+
+// ── Middleware ─────────────────────────────────────────────────────────────
+
+/// Give [`AppError`] responses their body: problem details under `/api/`
+/// and for requests that carry a token or ask for JSON, otherwise an error
+/// page in the branding of the domain it was requested on. Other responses
+/// pass through.
+pub async fn render(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let json = request.uri().path().starts_with("/api/") || wants_json(request.headers());
+    let host = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .and_then(|ConnectInfo(addr)| {
+            redirect::request_host(&state.config, request.headers(), *addr)
+        })
+        .map(str::to_owned);
+
+    let response = next.run(request).await;
+    let Some(ErrorMessage(message, errors)) = response.extensions().get::<ErrorMessage>().cloned()
+    else {
+        return response;
+    };
+    let (mut parts, _) = response.into_parts();
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    if json {
+        let status = parts.status;
+        let problem = Problem {
+            r#type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail: message.clone(),
+            error: message,
+            errors,
+        };
+        parts.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        let body = serde_json::to_vec(&problem).unwrap_or_default();
+        Response::from_parts(parts, Body::from(body))
+    } else {
+        let domain = host.and_then(|h| state.domains.resolve(&h));
+        let page = redirect::error_page(&state, parts.status, &message, domain.as_ref());
+        let (page_parts, body) = page.into_parts();
+        parts.headers.extend(page_parts.headers);
+        Response::from_parts(parts, body)
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Whether the client is a program: it sent a token or asked for JSON.
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::AUTHORIZATION)
+        || headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("application/json"))
+}
@@ -0,0 +1,314 @@
+use crate::config::AppConfig;
+use anyhow::Context as _;
+use axum::{extract::Request, http::header, middleware::Next, response::Response};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Map, Value};
+use std::{backtrace::Backtrace, future::Future, sync::OnceLock};
+use tokio::sync::mpsc;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, Layer};
+use uuid::Uuid;
+
+/// Reports waiting to be sent. Beyond this they are dropped, so a burst of
+/// errors can't pile up in memory or slow down requests.
+const QUEUE_SIZE: usize = 100;
+
+/// `release` of every report, matching Sentry's `<project>@<version>`.
+const RELEASE: &str = concat!("linkly@", env!("CARGO_PKG_VERSION"));
+
+/// Queue of the running reporter; unset when reporting isn't configured.
+static QUEUE: OnceLock<mpsc::Sender<Report>> = OnceLock::new();
+
+tokio::task_local! {
+    /// The request being handled, for the reports it causes.
+    static REQUEST: RequestContext;
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A Sentry DSN, `https://<public key>@<host>/<project id>`.
+#[derive(Debug, Clone)]
+pub struct Dsn {
+    public_key: String,
+    envelope_url: String,
+}
+
+/// Tracing layer that reports `ERROR` events.
+pub struct ReportLayer;
+
+/// What is known about the request an error happened in. The query string
+/// is left out, since it may carry an API token.
+#[derive(Debug, Clone)]
+struct RequestContext {
+    method: String,
+    path: String,
+    user_agent: Option<String>,
+}
+
+/// One error or panic on its way out.
+struct Report {
+    level: &'static str,
+    message: String,
+    target: String,
+    fields: Map<String, Value>,
+    request: Option<RequestContext>,
+    at: DateTime<Utc>,
+}
+
+struct Reporter {
+    client: reqwest::Client,
+    sentry: Option<Dsn>,
+    environment: Option<String>,
+    webhook_url: Option<String>,
+    base_url: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Dsn {
+    pub fn parse(dsn: &str) -> anyhow::Result<Self> {
+        let url = reqwest::Url::parse(dsn)?;
+        let host = url.host_str().context("no host")?;
+        if url.username().is_empty() {
+            anyhow::bail!("no public key");
+        }
+        let (prefix, project) = url
+            .path()
+            .trim_end_matches('/')
+            .rsplit_once('/')
+            .filter(|(_, project)| !project.is_empty())
+            .context("no project id")?;
+        let port = url.port().map(|p| format!(":{p}")).unwrap_or_default();
+        Ok(Self {
+            public_key: url.username().to_owned(),
+            envelope_url: format!(
+                "{}://{host}{port}{prefix}/api/{project}/envelope/",
+                url.scheme()
+            ),
+        })
+    }
+}
+
+/// Start reporting errors and panics to Sentry and/or `ERROR_WEBHOOK_URL`.
+/// Does nothing unless one of them is configured.
+pub fn start(config: &AppConfig, client: reqwest::Client) {
+    let sentry = config
+        .sentry_dsn
+        .as_deref()
+        .and_then(|d| Dsn::parse(d).ok());
+    if sentry.is_none() && config.error_webhook_url.is_none() {
+        return;
+    }
+    let reporter = Reporter {
+        client,
+        sentry,
+        environment: config.sentry_environment.clone(),
+        webhook_url: config.error_webhook_url.clone(),
+        base_url: config.base_url.clone(),
+    };
+    let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+    if QUEUE.set(tx).is_err() {
+        return;
+    }
+    tokio::spawn(reporter.run(rx));
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let mut fields = Map::new();
+        if let Some(location) = info.location() {
+            fields.insert("location".into(), location.to_string().into());
+        }
+        fields.insert(
+            "backtrace".into(),
+            Backtrace::force_capture().to_string().into(),
+        );
+        queue(Report {
+            level: "fatal",
+            message: format!("panicked: {message}"),
+            target: "panic".into(),
+            fields,
+            request: REQUEST.try_with(Clone::clone).ok(),
+            at: Utc::now(),
+        });
+        previous(info);
+    }));
+    tracing::info!("Reporting errors and panics");
+}
+
+/// Middleware that remembers the request being handled, so errors logged
+/// while handling it are reported with its method, path, and user agent.
+pub async fn capture_request(request: Request, next: Next) -> Response {
+    if QUEUE.get().is_none() {
+        return next.run(request).await;
+    }
+    let context = RequestContext {
+        method: request.method().to_string(),
+        path: request.uri().path().to_owned(),
+        user_agent: request
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_owned),
+    };
+    REQUEST.scope(context, next.run(request)).await
+}
+
+/// Carry the current request's context into `future`, for work spawned
+/// from a handler such as recording a click.
+pub fn in_current_request<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let context = REQUEST.try_with(Clone::clone).ok();
+    async move {
+        match context {
+            Some(context) => REQUEST.scope(context, future).await,
+            None => future.await,
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for ReportLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() != Level::ERROR || QUEUE.get().is_none() {
+            return;
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        queue(Report {
+            level: "error",
+            message: fields.message.unwrap_or_else(|| metadata.name().to_owned()),
+            target: metadata.target().to_owned(),
+            fields: fields.other,
+            request: REQUEST.try_with(Clone::clone).ok(),
+            at: Utc::now(),
+        });
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+fn queue(report: Report) {
+    if let Some(queue) = QUEUE.get() {
+        // Full or shut down: the error is still in the log
+        let _ = queue.try_send(report);
+    }
+}
+
+/// An event's `message` and any other fields.
+#[derive(Default)]
+struct Fields {
+    message: Option<String>,
+    other: Map<String, Value>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = Some(value.to_owned()),
+            name => {
+                self.other.insert(name.to_owned(), value.into());
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+}
+
+impl Reporter {
+    async fn run(self, mut rx: mpsc::Receiver<Report>) {
+        while let Some(report) = rx.recv().await {
+            if let Some(dsn) = &self.sentry {
+                self.send_to_sentry(dsn, &report).await;
+            }
+            if let Some(url) = &self.webhook_url {
+                self.send_to_webhook(url, &report).await;
+            }
+        }
+    }
+
+    /// https://develop.sentry.dev/sdk/data-model/envelopes/
+    async fn send_to_sentry(&self, dsn: &Dsn, report: &Report) {
+        let event_id = Uuid::new_v4().simple().to_string();
+        let mut event = json!({
+            "event_id": event_id,
+            "timestamp": report.at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "platform": "native",
+            "level": report.level,
+            "logger": report.target,
+            "message": { "formatted": report.message },
+            "release": RELEASE,
+            "extra": report.fields,
+        });
+        if let Some(environment) = &self.environment {
+            event["environment"] = environment.as_str().into();
+        }
+        if let Some(request) = &report.request {
+            event["request"] = json!({
+                "method": request.method,
+                "url": format!("{}{}", self.base_url, request.path),
+                "headers": { "User-Agent": request.user_agent },
+            });
+        }
+        let body = format!(
+            "{}\n{}\n{}\n",
+            json!({ "event_id": event_id }),
+            json!({ "type": "event" }),
+            event
+        );
+
+        let auth = format!(
+            "Sentry sentry_version=7, sentry_key={}, sentry_client={RELEASE}",
+            dsn.public_key
+        );
+        let result = self
+            .client
+            .post(&dsn.envelope_url)
+            .header("X-Sentry-Auth", auth)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-sentry-envelope",
+            )
+            .body(body)
+            .send()
+            .await;
+        match result {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => tracing::warn!("Sentry rejected an error report with {}", resp.status()),
+            Err(e) => tracing::warn!("Failed to send an error report to Sentry: {}", e),
+        }
+    }
+
+    async fn send_to_webhook(&self, url: &str, report: &Report) {
+        let body = json!({
+            "level": report.level,
+            "message": report.message,
+            "target": report.target,
+            "timestamp": report.at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "release": RELEASE,
+            "environment": self.environment,
+            "request": report.request.as_ref().map(|request| json!({
+                "method": request.method,
+                "url": format!("{}{}", self.base_url, request.path),
+                "user_agent": request.user_agent,
+            })),
+            "fields": report.fields,
+        });
+        match self.client.post(url).json(&body).send().await {
+            Ok(resp) if resp.status().is_success() => {}
+            Ok(resp) => {
+                tracing::warn!("ERROR_WEBHOOK_URL rejected a report with {}", resp.status())
+            }
+            Err(e) => tracing::warn!("Failed to send an error report to ERROR_WEBHOOK_URL: {}", e),
+        }
+    }
+}
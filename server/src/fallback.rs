@@ -0,0 +1,112 @@
+use crate::{
+    db, db_link_activity, destination,
+    handlers::admin::recache_link,
+    models::{Link, ACTIVITY_DESTINATION_DOWN, ACTIVITY_DESTINATION_UP},
+    AppState,
+};
+use chrono::Utc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// Checks in a row a destination must fail before visitors are sent to the
+/// fallback, so a single slow response doesn't switch a link over.
+const FAILURES_BEFORE_DOWN: u32 = 2;
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Check the destination of every link with a fallback URL every
+/// `interval`, one link at a time. A destination that fails
+/// [`FAILURES_BEFORE_DOWN`] checks in a row is marked down, and the link
+/// redirects to its fallback until a check finds it up again. Spawned from
+/// `main` unless `FALLBACK_CHECK_MINUTES` is 0.
+pub async fn run(state: Arc<AppState>, interval: Duration) {
+    // Failed checks in a row, by link ID
+    let mut failures: HashMap<i64, u32> = HashMap::new();
+    loop {
+        tokio::time::sleep(interval).await;
+        let links = match db::get_links_with_fallback(&state.db).await {
+            Ok(links) => links,
+            Err(e) => {
+                tracing::error!("Failed to load links with a fallback: {:?}", e);
+                continue;
+            }
+        };
+        failures.retain(|id, _| links.iter().any(|link| link.id == *id));
+
+        for link in links {
+            let down = match is_down(&state, &link.original_url).await {
+                Ok(down) => down,
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to check the destination of link {}: {:?}",
+                        link.id,
+                        e
+                    );
+                    continue;
+                }
+            };
+            let was_down = link.destination_down_since.is_some();
+            if down {
+                let count = failures.entry(link.id).or_default();
+                *count += 1;
+                if *count >= FAILURES_BEFORE_DOWN && !was_down {
+                    mark(&state, link, true).await;
+                }
+            } else {
+                failures.remove(&link.id);
+                if was_down {
+                    mark(&state, link, false).await;
+                }
+            }
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Whether `url` is down: it can't be reached, or answers with a server
+/// error, after following its redirects. One the check can't follow (not a
+/// URL, or redirects that loop) doesn't count as down; the fallback is for
+/// outages, not misconfigured links.
+async fn is_down(state: &AppState, url: &str) -> Result<bool, sqlx::Error> {
+    let trace = destination::trace(&state.db, &state.config, &state.probe_http, url).await?;
+    if trace.problem.is_some() {
+        return Ok(false);
+    }
+    Ok(trace
+        .hops
+        .last()
+        .is_some_and(|hop| hop.status.is_none_or(|status| status >= 500)))
+}
+
+/// Switch `link` to its fallback (`down`) or back to its destination, and
+/// note it on the link's timeline.
+async fn mark(state: &AppState, mut link: Link, down: bool) {
+    if let Err(e) = db::set_destination_down(&state.db, link.id, down).await {
+        tracing::error!("Failed to update the status of link {}: {:?}", link.id, e);
+        return;
+    }
+    link.destination_down_since = down.then(|| Utc::now().naive_utc());
+    recache_link(state, &link).await;
+
+    let fallback = link.fallback_url.as_deref().unwrap_or_default();
+    let (kind, detail) = if down {
+        tracing::warn!(
+            "Destination of '{}' is down; sending visitors to {}",
+            link.short_code,
+            fallback
+        );
+        (
+            ACTIVITY_DESTINATION_DOWN,
+            format!("Sending visitors to {fallback}"),
+        )
+    } else {
+        tracing::info!("Destination of '{}' is back up", link.short_code);
+        (
+            ACTIVITY_DESTINATION_UP,
+            format!("Sending visitors to {}", link.original_url),
+        )
+    };
+    if let Err(e) = db_link_activity::record(&state.db, link.id, None, kind, Some(&detail)).await {
+        tracing::error!("Failed to record activity on link {}: {:?}", link.id, e);
+    }
+}
@@ -0,0 +1,263 @@
+use crate::db_favicons;
+use chrono::{Duration as ChronoDuration, Utc};
+use dashmap::DashMap;
+use reqwest::{header, Url};
+use sqlx::SqlitePool;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// Time allowed for each request to a destination site.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Only the start of a home page is read; icons are declared in `<head>`.
+const MAX_PAGE_BYTES: usize = 256 * 1024;
+
+/// Larger icons are ignored.
+const MAX_ICON_BYTES: usize = 128 * 1024;
+
+/// How long a found favicon, and a host found to have none, are kept before
+/// the host is asked again.
+const FOUND_TTL_DAYS: i64 = 30;
+const MISSING_TTL_DAYS: i64 = 7;
+
+/// Icon URLs tried per host, besides `/favicon.ico`.
+const MAX_CANDIDATES: usize = 3;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Destination favicons for the admin panel, fetched the first time a host
+/// is shown and kept in the `favicons` table.
+pub struct Favicons {
+    client: reqwest::Client,
+    db: SqlitePool,
+    /// One fetch per host at a time; the link list asks for many at once.
+    fetching: DashMap<String, Arc<Mutex<()>>>,
+}
+
+/// An icon and its content type.
+pub type Icon = (String, Vec<u8>);
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Favicons {
+    pub fn new(client: reqwest::Client, db: SqlitePool) -> Self {
+        Self {
+            client,
+            db,
+            fetching: DashMap::new(),
+        }
+    }
+
+    /// The favicon of `host`, fetched if it isn't stored or has expired.
+    /// `None` if the host has none.
+    pub async fn get(&self, host: &str) -> Result<Option<Icon>, sqlx::Error> {
+        if let Some((icon, fetched_at)) = db_favicons::get_favicon(&self.db, host).await? {
+            if !is_stale(icon.is_some(), fetched_at) {
+                return Ok(icon);
+            }
+        }
+
+        let lock = self.fetching.entry(host.to_owned()).or_default().clone();
+        let _guard = lock.lock().await;
+        // Another request may have fetched it while this one waited
+        let stored = db_favicons::get_favicon(&self.db, host).await?;
+        if let Some((icon, fetched_at)) = &stored {
+            if !is_stale(icon.is_some(), *fetched_at) {
+                return Ok(icon.clone());
+            }
+        }
+
+        // A site that can't be reached right now keeps its old icon
+        let icon = match (self.fetch(host).await, stored) {
+            (Some(icon), _) => Some(icon),
+            (None, Some((old, _))) => old,
+            (None, None) => None,
+        };
+        let saved = icon
+            .as_ref()
+            .map(|(content_type, image)| (content_type.as_str(), image.as_slice()));
+        db_favicons::save_favicon(&self.db, host, saved).await?;
+        self.fetching.remove(host);
+        Ok(icon)
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+fn is_stale(found: bool, fetched_at: chrono::NaiveDateTime) -> bool {
+    let ttl = if found {
+        FOUND_TTL_DAYS
+    } else {
+        MISSING_TTL_DAYS
+    };
+    Utc::now().naive_utc() - fetched_at > ChronoDuration::days(ttl)
+}
+
+impl Favicons {
+    /// Look for the icons the home page declares, then `/favicon.ico`.
+    async fn fetch(&self, host: &str) -> Option<Icon> {
+        let mut candidates = Vec::new();
+        for scheme in ["https", "http"] {
+            let Ok(home) = Url::parse(&format!("{scheme}://{host}/")) else {
+                return None;
+            };
+            if let Some((page_url, html)) = self.fetch_page(&home).await {
+                candidates.extend(
+                    icon_links(&html, &page_url)
+                        .into_iter()
+                        .take(MAX_CANDIDATES),
+                );
+                candidates.extend(page_url.join("/favicon.ico").ok());
+                break;
+            }
+        }
+
+        for url in candidates {
+            if let Some(icon) = self.fetch_icon(url).await {
+                return Some(icon);
+            }
+        }
+        None
+    }
+
+    /// The start of an HTML page and its final URL, after redirects.
+    async fn fetch_page(&self, url: &Url) -> Option<(Url, String)> {
+        let resp = self
+            .client
+            .get(url.clone())
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?;
+        let final_url = resp.url().clone();
+        let is_html = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.to_ascii_lowercase().contains("html"));
+        let (body, _) = read_capped(resp, MAX_PAGE_BYTES).await?;
+        let html = if is_html {
+            String::from_utf8_lossy(&body).into_owned()
+        } else {
+            String::new()
+        };
+        Some((final_url, html))
+    }
+
+    async fn fetch_icon(&self, url: Url) -> Option<Icon> {
+        let resp = self
+            .client
+            .get(url)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?;
+        let declared = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| v.starts_with("image/"));
+        let (image, complete) = read_capped(resp, MAX_ICON_BYTES).await?;
+        if !complete || image.is_empty() {
+            return None;
+        }
+        // Many servers send icons as application/octet-stream, and some
+        // answer every path with an HTML page
+        let content_type = declared.or_else(|| sniff(&image).map(str::to_owned))?;
+        Some((content_type, image))
+    }
+}
+
+/// Read a response body up to `max` bytes. The flag is false if the body
+/// was cut short.
+async fn read_capped(mut resp: reqwest::Response, max: usize) -> Option<(Vec<u8>, bool)> {
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.ok()? {
+        if body.len() + chunk.len() > max {
+            body.extend_from_slice(&chunk[..max - body.len()]);
+            return Some((body, false));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Some((body, true))
+}
+
+/// Content type of an image from its first bytes.
+fn sniff(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0, 0, 1, 0]) {
+        Some("image/x-icon")
+    } else if data.starts_with(b"\x89PNG") {
+        Some("image/png")
+    } else if data.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+/// Icon URLs declared by `<link rel="icon">` and similar tags, small icons
+/// before `apple-touch-icon`s.
+fn icon_links(html: &str, base: &Url) -> Vec<Url> {
+    // Lower-casing ASCII keeps byte offsets, so tags are found in `lower`
+    // and attribute values read from `html`
+    let lower = html.to_ascii_lowercase();
+    let mut icons = Vec::new();
+    let mut touch_icons = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<link") {
+        let start = pos + start;
+        let Some(len) = lower[start..].find('>') else {
+            break;
+        };
+        let end = start + len;
+        pos = end;
+
+        let Some(rel) = attribute(&lower[start..end], &lower[start..end], "rel") else {
+            continue;
+        };
+        let Some(href) = attribute(&lower[start..end], &html[start..end], "href") else {
+            continue;
+        };
+        let Ok(url) = base.join(&href.replace("&amp;", "&")) else {
+            continue;
+        };
+        let mut tokens = rel.split_ascii_whitespace();
+        if tokens.clone().any(|t| t == "icon") {
+            icons.push(url);
+        } else if tokens.any(|t| t.starts_with("apple-touch-icon")) {
+            touch_icons.push(url);
+        }
+    }
+    icons.extend(touch_icons);
+    icons
+}
+
+/// The value of attribute `name` in a tag, looked up in the lower-cased
+/// `lower` and returned from `tag`.
+fn attribute<'a>(lower: &str, tag: &'a str, name: &str) -> Option<&'a str> {
+    let mut pos = 0;
+    while let Some(found) = lower[pos..].find(name) {
+        let at = pos + found;
+        pos = at + name.len();
+        let preceded_by_space = lower[..at].ends_with(|c: char| c.is_ascii_whitespace());
+        let rest = lower[pos..].trim_start();
+        if !preceded_by_space || !rest.starts_with('=') {
+            continue;
+        }
+        let value_start = lower.len() - rest[1..].trim_start().len();
+        let value = &tag[value_start..];
+        return Some(match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+            _ => value.split_ascii_whitespace().next().unwrap_or_default(),
+        });
+    }
+    None
+}
@@ -0,0 +1,57 @@
+use chrono::{Duration, NaiveDateTime};
+
+/// Most steps a funnel may have.
+pub const MAX_STEPS: usize = 5;
+
+/// Longest time allowed between two steps, in hours (30 days).
+pub const MAX_WINDOW_HOURS: i64 = 30 * 24;
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// How many visitors reached each step of a funnel whose steps are
+/// `link_ids`, in order. A visitor reaches the first step by clicking its
+/// link, and each later step by clicking its link within `window` after
+/// reaching the step before.
+///
+/// `clicks` are `(visitor_hash, link_id, clicked_at)`, grouped by visitor and
+/// oldest first within each, as `db_funnels::get_funnel_clicks` returns them.
+pub fn count_steps(
+    link_ids: &[i64],
+    window: Duration,
+    clicks: &[(String, i64, NaiveDateTime)],
+) -> Vec<i64> {
+    let mut counts = vec![0; link_ids.len()];
+    for visitor in clicks.chunk_by(|a, b| a.0 == b.0) {
+        // When the visitor last reached each step
+        let mut reached: Vec<Option<NaiveDateTime>> = vec![None; link_ids.len()];
+        for (_, link_id, at) in visitor {
+            // Last step first, so one click can't advance two steps on the
+            // same link
+            for step in (0..link_ids.len()).rev() {
+                if link_ids[step] != *link_id {
+                    continue;
+                }
+                let follows =
+                    step == 0 || reached[step - 1].is_some_and(|before| *at - before <= window);
+                if follows {
+                    reached[step] = Some(*at);
+                }
+            }
+        }
+        for (count, step) in counts.iter_mut().zip(&reached) {
+            if step.is_some() {
+                *count += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// `part` as a percentage of `whole`, e.g. "42.5%", or "—" when `whole` is 0.
+pub fn rate(part: i64, whole: i64) -> String {
+    if whole > 0 {
+        format!("{:.1}%", part as f64 / whole as f64 * 100.0)
+    } else {
+        "—".to_owned()
+    }
+}
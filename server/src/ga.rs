@@ -0,0 +1,211 @@
+use crate::config::AppConfig;
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, time::Duration};
+use tokio::{sync::mpsc, time::Instant};
+
+const COLLECT_URL: &str = "https://www.google-analytics.com/mp/collect";
+
+/// GA4 accepts at most 25 events per Measurement Protocol request.
+const MAX_EVENTS_PER_REQUEST: usize = 25;
+
+/// Clicks are buffered for up to this long (or until `MAX_BATCH` is
+/// reached) before being sent.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+const MAX_BATCH: usize = 200;
+
+/// Clicks queued beyond this are dropped rather than growing memory without
+/// bound while GA is unreachable.
+const QUEUE_CAPACITY: usize = 10_000;
+
+/// GA4 truncates event parameter values longer than this.
+const MAX_PARAM_LEN: usize = 100;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A short-link click to export.
+#[derive(Debug, Clone)]
+pub struct Click {
+    pub short_url: String,
+    pub short_code: String,
+    pub referrer: Option<String>,
+    pub browser: Option<String>,
+    pub os: Option<String>,
+    pub device_type: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    /// Used only to derive GA's `client_id`; never sent.
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Exports clicks to Google Analytics 4 via the Measurement Protocol.
+///
+/// Clicks are queued and sent in batches by a background task started in
+/// [`GaExporter::start`]. Without `GA_MEASUREMENT_ID` and `GA_API_SECRET`
+/// this does nothing.
+#[derive(Clone)]
+pub struct GaExporter {
+    tx: Option<mpsc::Sender<Event>>,
+    /// Random per-process salt for `client_id`, so the hash cannot be
+    /// reversed by enumerating IP addresses.
+    salt: [u8; 16],
+}
+
+struct Event {
+    client_id: String,
+    timestamp_micros: i64,
+    params: serde_json::Value,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl GaExporter {
+    /// Spawn the batching worker when GA is configured.
+    pub fn start(config: &AppConfig, client: reqwest::Client) -> Self {
+        let (Some(measurement_id), Some(api_secret)) =
+            (&config.ga_measurement_id, &config.ga_api_secret)
+        else {
+            return Self {
+                tx: None,
+                salt: [0; 16],
+            };
+        };
+
+        let query = vec![
+            ("measurement_id", measurement_id.clone()),
+            ("api_secret", api_secret.clone()),
+        ];
+
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run(rx, client, query));
+        Self {
+            tx: Some(tx),
+            salt: rand::random(),
+        }
+    }
+
+    /// Queue a click for export. Never blocks the caller.
+    pub fn send(&self, click: Click) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+
+        let mut params = serde_json::json!({
+            "link_code": trunc(&click.short_code),
+            "page_location": click.short_url,
+            "page_referrer": click.referrer.as_deref().map(trunc),
+            "country": click.country.as_deref().map(trunc),
+            "region": click.region.as_deref().map(trunc),
+            "city": click.city.as_deref().map(trunc),
+            "device_category": click.device_type.as_deref().map(trunc),
+            "browser": click.browser.as_deref().map(trunc),
+            "operating_system": click.os.as_deref().map(trunc),
+            "engagement_time_msec": 1,
+        });
+        // GA rejects null parameter values; omit unknown fields instead.
+        if let Some(map) = params.as_object_mut() {
+            map.retain(|_, v| !v.is_null());
+        }
+
+        let event = Event {
+            client_id: self.client_id(click.ip.as_deref(), click.user_agent.as_deref()),
+            timestamp_micros: chrono::Utc::now().timestamp_micros(),
+            params,
+        };
+
+        if let Err(mpsc::error::TrySendError::Full(_)) = tx.try_send(event) {
+            tracing::warn!("GA export queue is full; dropping click");
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Collect clicks into batches and send them until the channel closes.
+async fn run(
+    mut rx: mpsc::Receiver<Event>,
+    client: reqwest::Client,
+    query: Vec<(&'static str, String)>,
+) {
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < MAX_BATCH {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+        flush(&client, &query, batch).await;
+    }
+}
+
+/// Send a batch. Each Measurement Protocol request carries a single
+/// `client_id`, so events are grouped per visitor first.
+async fn flush(client: &reqwest::Client, query: &[(&'static str, String)], batch: Vec<Event>) {
+    let mut by_client: BTreeMap<String, Vec<serde_json::Value>> = BTreeMap::new();
+    for event in batch {
+        by_client
+            .entry(event.client_id)
+            .or_default()
+            .push(serde_json::json!({
+                "name": "short_link_click",
+                "timestamp_micros": event.timestamp_micros,
+                "params": event.params,
+            }));
+    }
+
+    for (client_id, events) in by_client {
+        for chunk in events.chunks(MAX_EVENTS_PER_REQUEST) {
+            let body = serde_json::json!({ "client_id": client_id, "events": chunk });
+            match client
+                .post(COLLECT_URL)
+                .query(query)
+                .json(&body)
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => {}
+                Ok(resp) => tracing::warn!(
+                    "GA rejected {} click event(s) with {}",
+                    chunk.len(),
+                    resp.status()
+                ),
+                // The URL carries the API secret; keep it out of the logs.
+                Err(e) => tracing::warn!(
+                    "Failed to send {} click event(s) to GA: {}",
+                    chunk.len(),
+                    e.without_url()
+                ),
+            }
+        }
+    }
+}
+
+impl GaExporter {
+    /// A pseudonymous visitor ID: a salted hash of IP and User-Agent that
+    /// rotates daily, so GA can count unique visitors without receiving
+    /// either value.
+    fn client_id(&self, ip: Option<&str>, user_agent: Option<&str>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt);
+        hasher.update(chrono::Utc::now().date_naive().to_string());
+        hasher.update(ip.unwrap_or_default());
+        hasher.update([0]);
+        hasher.update(user_agent.unwrap_or_default());
+        let digest = hasher.finalize();
+        format!(
+            "{}.{}",
+            u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]),
+            u32::from_be_bytes([digest[4], digest[5], digest[6], digest[7]])
+        )
+    }
+}
+
+fn trunc(s: &str) -> &str {
+    match s.char_indices().nth(MAX_PARAM_LEN) {
+        Some((i, _)) => &s[..i],
+        None => s,
+    }
+}
@@ -1,9 +1,21 @@
+use crate::config::AppConfig;
+use crate::db_geo_cache;
+use crate::geo_provider::{self, GeoProvider};
 use dashmap::DashMap;
-use serde::Deserialize;
+use sqlx::SqlitePool;
 use std::net::IpAddr;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+/// How long the first queued IP waits for others to share its request.
+const BATCH_WINDOW: Duration = Duration::from_millis(250);
+
+/// How long a caller waits for its IP's result. A lookup still in the queue
+/// after this finishes in the background and lands in the cache.
+const LOOKUP_WAIT: Duration = Duration::from_secs(3);
 
 // ── Types ──────────────────────────────────────────────────────────────────
 
@@ -11,40 +23,126 @@ use std::time::Duration;
 #[derive(Debug, Clone)]
 pub struct GeoInfo {
     pub country: String,
+    /// ISO 3166-1 alpha-2 code, e.g. "US"; empty when unknown.
+    pub country_code: String,
+    /// Two-letter continent code, e.g. "EU"; empty when unknown. See
+    /// [`continent_name`].
+    pub continent_code: String,
     pub region: String,
     pub city: String,
+    /// Approximate position of the city (or region, or country).
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Autonomous system number of the address's network, e.g. 15169.
+    pub asn: Option<i64>,
+    /// Name of the network's operator; empty when unknown.
+    pub isp: String,
+    /// The network is a hosting provider or datacenter, so the visitor is
+    /// most likely a scanner or bot rather than a person.
+    pub datacenter: bool,
 }
 
 /// Thread-safe in-memory cache: IP string → Option<GeoInfo>.
 /// `None` means we already tried and the lookup failed/returned no data.
+///
+/// IPs not yet cached are queued for a background task that looks them up
+/// in batches through the configured [`GeoProvider`], within its rate limit.
+/// Results are also written to the `geo_cache` table and loaded back at
+/// startup, so a restart doesn't repeat lookups.
+///
+/// With `GEO_ENABLED` off the cache is empty and every lookup is a miss.
 #[derive(Clone, Debug)]
 pub struct GeoCache {
+    enabled: bool,
     inner: Arc<DashMap<String, Option<GeoInfo>>>,
+    /// Callers waiting on each queued or in-flight IP.
+    waiting: Arc<DashMap<String, Vec<oneshot::Sender<Option<GeoInfo>>>>>,
+    queue: mpsc::UnboundedSender<String>,
 }
 
 impl GeoCache {
-    pub fn new() -> Self {
-        Self {
-            inner: Arc::new(DashMap::new()),
+    /// Create the cache from the unexpired `geo_cache` rows and start its
+    /// batching task with the provider chosen by `GEO_PROVIDER`, sending its
+    /// requests through the shared `client`. Neither happens with
+    /// `GEO_ENABLED` off.
+    pub async fn load(
+        config: &AppConfig,
+        db: SqlitePool,
+        client: reqwest::Client,
+    ) -> anyhow::Result<Self> {
+        if !config.geo_enabled {
+            tracing::info!("IP geolocation is off (GEO_ENABLED=false)");
+            let (queue, _) = mpsc::unbounded_channel();
+            return Ok(Self {
+                enabled: false,
+                inner: Arc::new(DashMap::new()),
+                waiting: Arc::new(DashMap::new()),
+                queue,
+            });
         }
-    }
-}
 
-impl Default for GeoCache {
-    fn default() -> Self {
-        Self::new()
+        let provider = geo_provider::from_config(config)?;
+        let per_minute = config
+            .geo_requests_per_minute
+            .or_else(|| provider.requests_per_minute());
+
+        let entries = db_geo_cache::load_geo_cache(&db, config.geo_cache_ttl_days).await?;
+        tracing::info!(
+            "IP geolocation via {}; {} cached address(es) loaded",
+            provider.name(),
+            entries.len()
+        );
+
+        let (queue, rx) = mpsc::unbounded_channel();
+        let cache = Self {
+            enabled: true,
+            inner: Arc::new(entries.into_iter().collect()),
+            waiting: Arc::new(DashMap::new()),
+            queue,
+        };
+        tokio::spawn(run_batches(
+            cache.clone(),
+            db,
+            provider,
+            client,
+            RateLimiter::new(per_minute),
+            rx,
+        ));
+        Ok(cache)
     }
 }
 
-// ── ip-api.com response shape ──────────────────────────────────────────────
-
-#[derive(Deserialize)]
-struct IpApiResponse {
-    status: String,
-    country: Option<String>,
-    #[serde(rename = "regionName")]
-    region_name: Option<String>,
-    city: Option<String>,
+#[cfg(test)]
+impl GeoCache {
+    /// A cache that already knows the country of each (IP, country code)
+    /// in `countries` and looks nothing else up, for tests.
+    pub fn with_countries(countries: &[(&str, &str)]) -> Self {
+        let (queue, _) = mpsc::unbounded_channel();
+        let inner = countries
+            .iter()
+            .map(|(ip, code)| {
+                let info = GeoInfo {
+                    country: String::new(),
+                    country_code: (*code).to_owned(),
+                    continent_code: String::new(),
+                    region: String::new(),
+                    city: String::new(),
+                    latitude: None,
+                    longitude: None,
+                    asn: None,
+                    isp: String::new(),
+                    datacenter: false,
+                };
+                ((*ip).to_owned(), Some(info))
+            })
+            .collect();
+        Self {
+            enabled: true,
+            inner: Arc::new(inner),
+            waiting: Arc::new(DashMap::new()),
+            queue,
+        }
+    }
 }
 
 // ── Public API ─────────────────────────────────────────────────────────────
@@ -56,89 +154,293 @@ struct IpApiResponse {
 /// - private / loopback / link-local addresses
 /// - failed or rate-limited API responses
 /// - IPs that previously returned no useful data
+/// - lookups still queued after a few seconds, e.g. while rate-limited
+/// - every IP when `GEO_ENABLED` is off
 ///
-/// The lookup is performed with a 3-second timeout so it can never stall a
+/// Waits at most [`LOOKUP_WAIT`], so it can never stall a redirect or
 /// background task for long.
 pub async fn lookup(ip: &str, cache: &GeoCache) -> Option<GeoInfo> {
+    match enqueue(ip, cache) {
+        Lookup::Done(info) => info,
+        Lookup::Queued(rx) => tokio::time::timeout(LOOKUP_WAIT, rx)
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .flatten(),
+    }
+}
+
+/// Like [`lookup`], but waits for the IP's batch however long the rate
+/// limiter holds it back. For background jobs such as the geo backfill.
+pub async fn lookup_wait(ip: &str, cache: &GeoCache) -> Option<GeoInfo> {
+    match enqueue(ip, cache) {
+        Lookup::Done(info) => info,
+        Lookup::Queued(rx) => rx.await.ok().flatten(),
+    }
+}
+
+enum Lookup {
+    Done(Option<GeoInfo>),
+    Queued(oneshot::Receiver<Option<GeoInfo>>),
+}
+
+/// Answer from the cache, or queue `ip` (unless it already is) and return
+/// the receiver for its batch's result.
+fn enqueue(ip: &str, cache: &GeoCache) -> Lookup {
     // Skip addresses that can never be geolocated
-    if is_private(ip) {
-        return None;
+    if !cache.enabled || is_private(ip) {
+        return Lookup::Done(None);
     }
 
     // Check cache first (covers both successful hits and known misses)
     if let Some(entry) = cache.inner.get(ip) {
-        return entry.clone();
+        return Lookup::Done(entry.clone());
+    }
+
+    // Not cached — queue it, unless it already is, and wait for the batch
+    let (tx, rx) = oneshot::channel();
+    let cached = {
+        let mut waiters = cache.waiting.entry(ip.to_owned()).or_default();
+        // The batch may have finished since the cache check above
+        match cache.inner.get(ip) {
+            Some(entry) => Some(entry.clone()),
+            None => {
+                if waiters.is_empty() {
+                    let _ = cache.queue.send(ip.to_owned());
+                }
+                waiters.push(tx);
+                None
+            }
+        }
+    };
+    if let Some(info) = cached {
+        cache.waiting.remove_if(ip, |_, waiters| waiters.is_empty());
+        return Lookup::Done(info);
     }
+    Lookup::Queued(rx)
+}
 
-    // Not cached — ask ip-api.com
-    let result = fetch_geo(ip).await;
+// ── Batching ───────────────────────────────────────────────────────────────
 
-    // Store in cache regardless of outcome so we don't retry endlessly
-    cache.inner.insert(ip.to_owned(), result.clone());
+/// Drain the lookup queue: gather up to the provider's batch size, wait for
+/// the rate limiter, and resolve everyone waiting on the batch's IPs.
+async fn run_batches(
+    cache: GeoCache,
+    db: SqlitePool,
+    provider: Arc<dyn GeoProvider>,
+    client: reqwest::Client,
+    mut limiter: RateLimiter,
+    mut rx: mpsc::UnboundedReceiver<String>,
+) {
+    let batch_size = provider.batch_size().max(1);
 
-    result
-}
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + BATCH_WINDOW;
+        while batch.len() < batch_size {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(ip)) => batch.push(ip),
+                _ => break,
+            }
+        }
 
-// ── Internal helpers ───────────────────────────────────────────────────────
+        limiter.acquire().await;
+        // Take whatever queued up while we waited for the limiter
+        while batch.len() < batch_size {
+            match rx.try_recv() {
+                Ok(ip) => batch.push(ip),
+                Err(_) => break,
+            }
+        }
 
-async fn fetch_geo(ip: &str) -> Option<GeoInfo> {
-    // Build a lightweight client with a strict timeout
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(3))
-        .build()
-        .ok()?;
+        let fetched = provider.fetch(&client, &batch).await;
+        limiter.observe(fetched.remaining, fetched.reset_secs);
+        let results = fetched.results.unwrap_or_default();
+        for (ip, info) in &results {
+            // Store misses too so we don't retry endlessly
+            cache.inner.insert(ip.clone(), info.clone());
+            resolve(&cache, ip, info.clone());
+        }
+        // IPs missing from the response, or from a failed request, count as
+        // misses for now but aren't cached, so a later click retries them
+        for ip in &batch {
+            resolve(&cache, ip, None);
+        }
 
-    let url = format!(
-        "http://ip-api.com/json/{}?fields=status,country,regionName,city",
-        ip
-    );
+        if !results.is_empty() {
+            if let Err(e) = db_geo_cache::save_geo_cache(&db, &results).await {
+                tracing::warn!("Failed to persist geo lookups: {}", e);
+            }
+        }
+    }
+}
 
-    let resp = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| tracing::debug!("geo lookup network error for {}: {}", ip, e))
-        .ok()?;
+/// Hand a result to everyone waiting on `ip`.
+fn resolve(cache: &GeoCache, ip: &str, info: Option<GeoInfo>) {
+    if let Some((_, waiters)) = cache.waiting.remove(ip) {
+        for tx in waiters {
+            let _ = tx.send(info.clone());
+        }
+    }
+}
 
-    let body: IpApiResponse = resp
-        .json()
-        .await
-        .map_err(|e| tracing::debug!("geo lookup parse error for {}: {}", ip, e))
-        .ok()?;
+/// Token bucket for provider requests, refilled at the provider's
+/// requests-per-minute rate (unlimited when it has none) and corrected by
+/// the rate-limit counts a provider reports, e.g. ip-api.com's `X-Rl`
+/// (requests left in the current window) and `X-Ttl` (seconds until it
+/// resets) headers.
+struct RateLimiter {
+    per_minute: Option<f64>,
+    tokens: f64,
+    refilled_at: Instant,
+    /// Set when the server says the window is used up.
+    blocked_until: Option<Instant>,
+}
 
-    if body.status != "success" {
-        tracing::debug!("geo lookup returned non-success status for {}", ip);
-        return None;
+impl RateLimiter {
+    fn new(per_minute: Option<f64>) -> Self {
+        Self {
+            per_minute,
+            tokens: per_minute.unwrap_or(0.0),
+            refilled_at: Instant::now(),
+            blocked_until: None,
+        }
     }
 
-    let country = body.country.filter(|s| !s.is_empty()).unwrap_or_default();
-    let region = body
-        .region_name
-        .filter(|s| !s.is_empty())
-        .unwrap_or_default();
-    let city = body.city.filter(|s| !s.is_empty()).unwrap_or_default();
+    /// Wait until a request may be sent, and spend a token on it.
+    async fn acquire(&mut self) {
+        if let Some(until) = self.blocked_until.take() {
+            tokio::time::sleep_until(until).await;
+            self.tokens = self.per_minute.unwrap_or(0.0);
+            self.refilled_at = Instant::now();
+        }
+        let Some(per_minute) = self.per_minute else {
+            return;
+        };
+        loop {
+            let now = Instant::now();
+            let earned = now.duration_since(self.refilled_at).as_secs_f64() / 60.0 * per_minute;
+            self.tokens = (self.tokens + earned).min(per_minute);
+            self.refilled_at = now;
+            if self.tokens >= 1.0 {
+                self.tokens -= 1.0;
+                return;
+            }
+            let wait = (1.0 - self.tokens) * 60.0 / per_minute;
+            tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+        }
+    }
 
-    // Treat completely empty results as a miss
-    if country.is_empty() && region.is_empty() && city.is_empty() {
-        return None;
+    /// Adopt the server's count of requests left, so restarts and other
+    /// clients on the same IP are accounted for.
+    fn observe(&mut self, remaining: Option<u64>, ttl_secs: Option<u64>) {
+        let Some(remaining) = remaining else {
+            return;
+        };
+        self.tokens = self.tokens.min(remaining as f64);
+        if remaining == 0 {
+            // A second of slack for clock skew at the window edge
+            let ttl = Duration::from_secs(ttl_secs.unwrap_or(60) + 1);
+            self.blocked_until = Some(Instant::now() + ttl);
+        }
     }
+}
+
+// ── Display helpers ────────────────────────────────────────────────────────
 
-    Some(GeoInfo {
-        country,
-        region,
-        city,
+/// English name of a two-letter continent code.
+pub fn continent_name(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "AF" => "Africa",
+        "AN" => "Antarctica",
+        "AS" => "Asia",
+        "EU" => "Europe",
+        "NA" => "North America",
+        "OC" => "Oceania",
+        "SA" => "South America",
+        _ => return None,
     })
 }
 
+/// Flag emoji for an ISO 3166-1 alpha-2 country code, e.g. "🇩🇪" for "DE".
+/// Empty for anything that isn't two ASCII letters.
+pub fn flag(country_code: &str) -> String {
+    if country_code.len() != 2 || !country_code.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return String::new();
+    }
+    country_code
+        .bytes()
+        .filter_map(|b| char::from_u32(0x1F1E6 + u32::from(b.to_ascii_uppercase() - b'A')))
+        .collect()
+}
+
+// ── Country rules ──────────────────────────────────────────────────────────
+
+/// Country allow and deny lists for redirects, as ISO 3166-1 alpha-2 codes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CountryRules {
+    /// When non-empty, only these countries may follow the link.
+    pub allow: Vec<String>,
+    /// These countries may never follow the link.
+    pub deny: Vec<String>,
+}
+
+impl CountryRules {
+    /// Parse comma-separated code lists, e.g. `"US, ca"`.
+    pub fn parse(allow: &str, deny: &str) -> Result<Self, String> {
+        Ok(Self {
+            allow: parse_country_codes(allow)?,
+            deny: parse_country_codes(deny)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether a visitor from `country_code` may follow the link. A visitor
+    /// whose country is unknown passes deny lists but not allow lists.
+    pub fn permits(&self, country_code: Option<&str>) -> bool {
+        if let Some(code) = country_code {
+            if self.deny.iter().any(|c| c == code) {
+                return false;
+            }
+        }
+        self.allow.is_empty()
+            || country_code.is_some_and(|code| self.allow.iter().any(|c| c == code))
+    }
+}
+
+fn parse_country_codes(list: &str) -> Result<Vec<String>, String> {
+    let mut codes = Vec::new();
+    for code in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        if code.len() != 2 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(format!(
+                "'{code}' is not a two-letter country code (e.g. US, GB, DE)"
+            ));
+        }
+        let code = code.to_ascii_uppercase();
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    Ok(codes)
+}
+
 /// Return `true` for addresses that should never be sent to a public
 /// geolocation API: loopback, link-local, private ranges, and IPv6 special
 /// addresses.
 fn is_private(ip_str: &str) -> bool {
-    // Strip IPv6-mapped IPv4 prefix: "::ffff:1.2.3.4" → "1.2.3.4"
-    let ip_str = ip_str.strip_prefix("::ffff:").unwrap_or(ip_str);
+    // unparseable → treat as private / skip
+    IpAddr::from_str(ip_str).map_or(true, is_private_ip)
+}
 
-    match IpAddr::from_str(ip_str) {
-        Ok(IpAddr::V4(addr)) => {
+/// Whether `ip` is loopback, link-local, unspecified, broadcast, or in a
+/// private range, rather than a public address. IPv4-mapped IPv6 addresses
+/// count as their IPv4 address.
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(addr) => {
             let octets = addr.octets();
             addr.is_loopback()          // 127.x.x.x
             || addr.is_link_local()     // 169.254.x.x
@@ -151,7 +453,7 @@ fn is_private(ip_str: &str) -> bool {
             // 192.168.x.x
             || (octets[0] == 192 && octets[1] == 168)
         }
-        Ok(IpAddr::V6(addr)) => {
+        IpAddr::V6(addr) => {
             addr.is_loopback()       // ::1
             || addr.is_unspecified() // ::
             // fe80::/10  link-local
@@ -159,6 +461,5 @@ fn is_private(ip_str: &str) -> bool {
             // fc00::/7   unique-local
             || (addr.segments()[0] & 0xfe00) == 0xfc00
         }
-        Err(_) => true, // unparseable → treat as private / skip
     }
 }
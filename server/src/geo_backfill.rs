@@ -0,0 +1,117 @@
+use crate::{db_geo_backfill, geo, AppState};
+use chrono::{NaiveDateTime, Utc};
+use std::sync::{Arc, Mutex};
+
+/// IPs resolved together. The geo cache's batching task merges them into
+/// as few provider requests as its batch size allows, and live clicks queue
+/// between chunks instead of behind the whole backlog.
+const CHUNK: i64 = 100;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Admin-triggered job that re-resolves the location of clicks recorded
+/// without one (e.g. during a geolocation outage), through the regular geo
+/// cache and its rate limiter.
+#[derive(Clone, Default)]
+pub struct GeoBackfill {
+    progress: Arc<Mutex<Progress>>,
+}
+
+/// Snapshot of the job for the dashboard.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub running: bool,
+    /// IPs looked up so far in the current or last run.
+    pub ips_checked: u64,
+    /// Rows given a location so far in the current or last run.
+    pub rows_updated: u64,
+    pub finished_at: Option<NaiveDateTime>,
+    pub error: Option<String>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl GeoBackfill {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// Start the job in the background. Returns `false` if it is already
+    /// running.
+    pub fn start(&self, state: Arc<AppState>) -> bool {
+        {
+            let mut progress = self.progress.lock().unwrap();
+            if progress.running {
+                return false;
+            }
+            *progress = Progress {
+                running: true,
+                ..Progress::default()
+            };
+        }
+
+        let job = self.clone();
+        tokio::spawn(async move {
+            let result = job.run(&state).await;
+            let mut progress = job.progress.lock().unwrap();
+            progress.running = false;
+            progress.finished_at = Some(Utc::now().naive_utc());
+            match result {
+                Ok(()) => tracing::info!(
+                    "Geo backfill finished: {} IP(s) checked, {} row(s) updated",
+                    progress.ips_checked,
+                    progress.rows_updated
+                ),
+                Err(e) => {
+                    tracing::error!("Geo backfill failed: {:?}", e);
+                    progress.error = Some("Database error; see the server log.".into());
+                }
+            }
+        });
+        true
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl GeoBackfill {
+    async fn run(&self, state: &AppState) -> Result<(), sqlx::Error> {
+        tracing::info!("Geo backfill started");
+        // IPs are visited in order, so ones that still can't be located
+        // are passed over rather than retried forever
+        let mut after = String::new();
+        loop {
+            let ips = db_geo_backfill::ips_missing_geo(&state.db, &after, CHUNK).await?;
+            let Some(last) = ips.last() else {
+                return Ok(());
+            };
+            after = last.clone();
+
+            let mut lookups = tokio::task::JoinSet::new();
+            for ip in ips {
+                let cache = state.geo_cache.clone();
+                lookups.spawn(async move {
+                    let info = geo::lookup_wait(&ip, &cache).await;
+                    (ip, info)
+                });
+            }
+
+            while let Some(joined) = lookups.join_next().await {
+                let Ok((ip, info)) = joined else {
+                    continue;
+                };
+                let updated = match info {
+                    Some(info) => db_geo_backfill::fill_geo(&state.db, &ip, &info).await?,
+                    None => 0,
+                };
+                let mut progress = self.progress.lock().unwrap();
+                progress.ips_checked += 1;
+                progress.rows_updated += updated;
+            }
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use crate::config::{AppConfig, GeoProviderKind};
+use crate::geo::GeoInfo;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most IPs ip-api.com accepts in one batch request.
+const IP_API_BATCH_SIZE: usize = 100;
+
+/// Batch requests ip-api.com's free tier allows per minute.
+const IP_API_FREE_REQUESTS_PER_MINUTE: f64 = 15.0;
+
+/// Default pace for the keyed HTTP services, whose quotas are per day or
+/// month rather than per minute. Tune with `GEO_REQUESTS_PER_MINUTE`.
+const KEYED_REQUESTS_PER_MINUTE: f64 = 60.0;
+
+/// IPs looked up per pass over a local MaxMind database.
+#[cfg(feature = "geo")]
+const MAXMIND_BATCH_SIZE: usize = 100;
+
+// ── Trait ──────────────────────────────────────────────────────────────────
+
+/// A source of IP geolocation data. The geo cache's batching task calls
+/// [`fetch`](GeoProvider::fetch) with up to [`batch_size`](GeoProvider::batch_size)
+/// IPs at a time, paced to [`requests_per_minute`](GeoProvider::requests_per_minute).
+#[async_trait]
+pub trait GeoProvider: Send + Sync {
+    /// Short name for logs, e.g. "ip-api".
+    fn name(&self) -> &'static str;
+
+    /// Most IPs one call to `fetch` may be given.
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    /// Calls to `fetch` allowed per minute, or `None` for no limit.
+    fn requests_per_minute(&self) -> Option<f64>;
+
+    /// Look up `ips` in one request.
+    async fn fetch(&self, client: &reqwest::Client, ips: &[String]) -> Batch;
+}
+
+/// Outcome of one [`GeoProvider::fetch`] call.
+#[derive(Default)]
+pub struct Batch {
+    /// Result for each IP the provider answered, `None` for IPs it has no
+    /// data on. `None` overall when the request failed or was rate-limited,
+    /// so the IPs are retried on a later click.
+    pub results: Option<Vec<(String, Option<GeoInfo>)>>,
+    /// Requests left in the provider's current rate-limit window, and
+    /// seconds until it resets, when the provider reports them.
+    pub remaining: Option<u64>,
+    pub reset_secs: Option<u64>,
+}
+
+/// Build the provider selected by `GEO_PROVIDER`.
+pub fn from_config(config: &AppConfig) -> anyhow::Result<Arc<dyn GeoProvider>> {
+    let timeout = Duration::from_secs(config.geo_timeout_secs);
+    Ok(match config.geo_provider {
+        GeoProviderKind::IpApi => Arc::new(IpApi {
+            key: config.geo_ip_api_key.clone(),
+            timeout,
+        }),
+        GeoProviderKind::IpInfo => Arc::new(IpInfo {
+            token: config.geo_ipinfo_token.clone(),
+            timeout,
+        }),
+        GeoProviderKind::IpData => Arc::new(IpData {
+            key: config.geo_ipdata_api_key.clone().unwrap_or_default(),
+            timeout,
+        }),
+        #[cfg(feature = "geo")]
+        GeoProviderKind::MaxMind => {
+            use anyhow::Context;
+
+            let city_path = config
+                .geo_maxmind_city_db
+                .as_deref()
+                .context("GEO_MAXMIND_CITY_DB must be set when GEO_PROVIDER is maxmind")?;
+            let city = maxminddb::Reader::open_readfile(city_path)
+                .with_context(|| format!("Failed to open MaxMind database {city_path}"))?;
+            let asn = match config.geo_maxmind_asn_db.as_deref() {
+                Some(path) => Some(
+                    maxminddb::Reader::open_readfile(path)
+                        .with_context(|| format!("Failed to open MaxMind database {path}"))?,
+                ),
+                None => None,
+            };
+            Arc::new(MaxMind { city, asn })
+        }
+        #[cfg(not(feature = "geo"))]
+        GeoProviderKind::MaxMind => {
+            anyhow::bail!("GEO_PROVIDER maxmind needs a build with the geo feature")
+        }
+    })
+}
+
+// ── ip-api.com ─────────────────────────────────────────────────────────────
+
+/// ip-api.com's batch endpoint: the free tier over plain HTTP, or the Pro
+/// endpoint when a key is configured.
+struct IpApi {
+    key: Option<String>,
+    timeout: Duration,
+}
+
+/// Response fields requested from ip-api.com.
+const IP_API_FIELDS: &str =
+    "status,query,continentCode,country,countryCode,regionName,city,lat,lon,as,isp,hosting";
+
+#[derive(Deserialize)]
+struct IpApiResponse {
+    status: String,
+    /// The IP address looked up.
+    query: String,
+    country: Option<String>,
+    #[serde(rename = "countryCode")]
+    country_code: Option<String>,
+    #[serde(rename = "continentCode")]
+    continent_code: Option<String>,
+    #[serde(rename = "regionName")]
+    region_name: Option<String>,
+    city: Option<String>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    /// e.g. "AS15169 Google LLC".
+    #[serde(rename = "as")]
+    as_name: Option<String>,
+    isp: Option<String>,
+    hosting: Option<bool>,
+}
+
+#[async_trait]
+impl GeoProvider for IpApi {
+    fn name(&self) -> &'static str {
+        "ip-api"
+    }
+
+    fn batch_size(&self) -> usize {
+        IP_API_BATCH_SIZE
+    }
+
+    fn requests_per_minute(&self) -> Option<f64> {
+        // Pro keys aren't rate-limited
+        self.key
+            .is_none()
+            .then_some(IP_API_FREE_REQUESTS_PER_MINUTE)
+    }
+
+    async fn fetch(&self, client: &reqwest::Client, ips: &[String]) -> Batch {
+        let url = match &self.key {
+            Some(key) => format!(
+                "https://pro.ip-api.com/batch?fields={}&key={}",
+                IP_API_FIELDS, key
+            ),
+            None => format!("http://ip-api.com/batch?fields={}", IP_API_FIELDS),
+        };
+
+        let resp = match client
+            .post(&url)
+            .timeout(self.timeout)
+            .json(ips)
+            .send()
+            .await
+        {
+            Ok(resp) => resp,
+            Err(e) => {
+                tracing::debug!("geo batch lookup network error: {}", e);
+                return Batch::default();
+            }
+        };
+
+        let header = |name: &str| -> Option<u64> {
+            resp.headers().get(name)?.to_str().ok()?.trim().parse().ok()
+        };
+        let mut batch = Batch {
+            results: None,
+            remaining: header("X-Rl"),
+            reset_secs: header("X-Ttl"),
+        };
+
+        if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            tracing::warn!(
+                "ip-api.com rate limit hit; {} geo lookup(s) skipped",
+                ips.len()
+            );
+            return batch;
+        }
+
+        batch.results = resp
+            .json::<Vec<IpApiResponse>>()
+            .await
+            .map_err(|e| tracing::debug!("geo batch lookup parse error: {}", e))
+            .ok()
+            .map(|bodies| {
+                bodies
+                    .into_iter()
+                    .map(|body| (body.query.clone(), parse_ip_api(body)))
+                    .collect()
+            });
+        batch
+    }
+}
+
+/// Convert one ip-api.com result, treating failures as misses.
+fn parse_ip_api(body: IpApiResponse) -> Option<GeoInfo> {
+    if body.status != "success" {
+        tracing::debug!("geo lookup returned non-success status for {}", body.query);
+        return None;
+    }
+
+    let (asn, _) = split_as_name(body.as_name.as_deref().unwrap_or_default());
+    complete(GeoInfo {
+        country: body.country.unwrap_or_default(),
+        country_code: body.country_code.unwrap_or_default(),
+        continent_code: body.continent_code.unwrap_or_default(),
+        region: body.region_name.unwrap_or_default(),
+        city: body.city.unwrap_or_default(),
+        latitude: body.lat,
+        longitude: body.lon,
+        asn,
+        isp: body.isp.unwrap_or_default(),
+        datacenter: body.hosting.unwrap_or(false),
+    })
+}
+
+// ── ipinfo.io ──────────────────────────────────────────────────────────────
+
+/// ipinfo.io's single-IP endpoint. Works without a token at a low daily
+/// quota.
+struct IpInfo {
+    token: Option<String>,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct IpInfoResponse {
+    /// ISO country code; ipinfo.io doesn't return the name.
+    country: Option<String>,
+    region: Option<String>,
+    city: Option<String>,
+    /// "latitude,longitude".
+    loc: Option<String>,
+    /// e.g. "AS15169 Google LLC".
+    org: Option<String>,
+    /// Reserved or private address.
+    #[serde(default)]
+    bogon: bool,
+}
+
+#[async_trait]
+impl GeoProvider for IpInfo {
+    fn name(&self) -> &'static str {
+        "ipinfo"
+    }
+
+    fn requests_per_minute(&self) -> Option<f64> {
+        Some(KEYED_REQUESTS_PER_MINUTE)
+    }
+
+    async fn fetch(&self, client: &reqwest::Client, ips: &[String]) -> Batch {
+        let Some(ip) = ips.first() else {
+            return Batch::default();
+        };
+        let mut request = client
+            .get(format!("https://ipinfo.io/{ip}/json"))
+            .timeout(self.timeout);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        fetch_one(self.name(), ip, request, |body: IpInfoResponse| {
+            if body.bogon {
+                return None;
+            }
+            let (latitude, longitude) = body
+                .loc
+                .as_deref()
+                .and_then(|loc| loc.split_once(','))
+                .map_or((None, None), |(lat, lon)| {
+                    (lat.trim().parse().ok(), lon.trim().parse().ok())
+                });
+            let (asn, isp) = split_as_name(body.org.as_deref().unwrap_or_default());
+            let country_code = body.country.unwrap_or_default();
+            complete(GeoInfo {
+                country: country_code.clone(),
+                country_code,
+                continent_code: String::new(),
+                region: body.region.unwrap_or_default(),
+                city: body.city.unwrap_or_default(),
+                latitude,
+                longitude,
+                asn,
+                isp,
+                datacenter: false,
+            })
+        })
+        .await
+    }
+}
+
+// ── ipdata.co ──────────────────────────────────────────────────────────────
+
+/// ipdata.co's single-IP endpoint. Requires an API key.
+struct IpData {
+    key: String,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct IpDataResponse {
+    country_name: Option<String>,
+    country_code: Option<String>,
+    continent_code: Option<String>,
+    region: Option<String>,
+    city: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    asn: Option<IpDataAsn>,
+    threat: Option<IpDataThreat>,
+}
+
+#[derive(Deserialize)]
+struct IpDataAsn {
+    /// e.g. "AS15169".
+    asn: Option<String>,
+    name: Option<String>,
+    /// "hosting", "isp", "business", …
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IpDataThreat {
+    #[serde(default)]
+    is_datacenter: bool,
+}
+
+#[async_trait]
+impl GeoProvider for IpData {
+    fn name(&self) -> &'static str {
+        "ipdata"
+    }
+
+    fn requests_per_minute(&self) -> Option<f64> {
+        Some(KEYED_REQUESTS_PER_MINUTE)
+    }
+
+    async fn fetch(&self, client: &reqwest::Client, ips: &[String]) -> Batch {
+        let Some(ip) = ips.first() else {
+            return Batch::default();
+        };
+        let request = client
+            .get(format!("https://api.ipdata.co/{ip}"))
+            .timeout(self.timeout)
+            .query(&[("api-key", self.key.as_str())]);
+
+        fetch_one(self.name(), ip, request, |body: IpDataResponse| {
+            let asn = body.asn.as_ref();
+            complete(GeoInfo {
+                country: body.country_name.unwrap_or_default(),
+                country_code: body.country_code.unwrap_or_default(),
+                continent_code: body.continent_code.unwrap_or_default(),
+                region: body.region.unwrap_or_default(),
+                city: body.city.unwrap_or_default(),
+                latitude: body.latitude,
+                longitude: body.longitude,
+                asn: asn
+                    .and_then(|a| a.asn.as_deref())
+                    .and_then(|s| s.strip_prefix("AS"))
+                    .and_then(|n| n.parse().ok()),
+                isp: asn.and_then(|a| a.name.clone()).unwrap_or_default(),
+                datacenter: asn.is_some_and(|a| a.kind.as_deref() == Some("hosting"))
+                    || body.threat.is_some_and(|t| t.is_datacenter),
+            })
+        })
+        .await
+    }
+}
+
+// ── MaxMind ────────────────────────────────────────────────────────────────
+
+/// Local MaxMind GeoIP2 / GeoLite2 City database, plus the ASN database
+/// when configured. No network requests and no rate limit.
+#[cfg(feature = "geo")]
+struct MaxMind {
+    city: maxminddb::Reader<Vec<u8>>,
+    asn: Option<maxminddb::Reader<Vec<u8>>>,
+}
+
+#[cfg(feature = "geo")]
+#[async_trait]
+impl GeoProvider for MaxMind {
+    fn name(&self) -> &'static str {
+        "maxmind"
+    }
+
+    fn batch_size(&self) -> usize {
+        MAXMIND_BATCH_SIZE
+    }
+
+    fn requests_per_minute(&self) -> Option<f64> {
+        None
+    }
+
+    async fn fetch(&self, _client: &reqwest::Client, ips: &[String]) -> Batch {
+        let results = ips.iter().map(|ip| (ip.clone(), self.lookup(ip))).collect();
+        Batch {
+            results: Some(results),
+            ..Batch::default()
+        }
+    }
+}
+
+#[cfg(feature = "geo")]
+impl MaxMind {
+    fn lookup(&self, ip: &str) -> Option<GeoInfo> {
+        let addr = ip.parse::<std::net::IpAddr>().ok()?.to_canonical();
+        let city = self
+            .city
+            .lookup::<maxminddb::geoip2::City>(addr)
+            .map_err(|e| tracing::debug!("MaxMind lookup for {} failed: {}", ip, e))
+            .ok()?;
+        let asn = self
+            .asn
+            .as_ref()
+            .and_then(|db| db.lookup::<maxminddb::geoip2::Asn>(addr).ok());
+
+        let english = |names: Option<&std::collections::BTreeMap<&str, &str>>| {
+            names
+                .and_then(|n| n.get("en"))
+                .map(|s| s.to_string())
+                .unwrap_or_default()
+        };
+        let country = city.country.as_ref();
+        let location = city.location.as_ref();
+        complete(GeoInfo {
+            country: english(country.and_then(|c| c.names.as_ref())),
+            country_code: country
+                .and_then(|c| c.iso_code)
+                .unwrap_or_default()
+                .to_owned(),
+            continent_code: city
+                .continent
+                .as_ref()
+                .and_then(|c| c.code)
+                .unwrap_or_default()
+                .to_owned(),
+            region: english(
+                city.subdivisions
+                    .as_ref()
+                    .and_then(|s| s.first())
+                    .and_then(|s| s.names.as_ref()),
+            ),
+            city: english(city.city.as_ref().and_then(|c| c.names.as_ref())),
+            latitude: location.and_then(|l| l.latitude),
+            longitude: location.and_then(|l| l.longitude),
+            asn: asn
+                .as_ref()
+                .and_then(|a| a.autonomous_system_number)
+                .map(i64::from),
+            isp: asn
+                .as_ref()
+                .and_then(|a| a.autonomous_system_organization)
+                .unwrap_or_default()
+                .to_owned(),
+            datacenter: false,
+        })
+    }
+}
+
+// ── Shared helpers ─────────────────────────────────────────────────────────
+
+/// Networks of the big cloud and hosting providers. Providers' own hosting
+/// flags catch most others.
+const DATACENTER_ASNS: &[i64] = &[
+    396982, // Google Cloud
+    16509,  // Amazon AWS
+    14618,  // Amazon AWS
+    8075,   // Microsoft Azure
+    31898,  // Oracle Cloud
+    14061,  // DigitalOcean
+    63949,  // Akamai Connected Cloud (Linode)
+    20473,  // Vultr
+    16276,  // OVH
+    24940,  // Hetzner
+    12876,  // Scaleway
+    51167,  // Contabo
+    45102,  // Alibaba Cloud
+    132203, // Tencent Cloud
+];
+
+/// Normalise a provider's result: upper-case the codes, flag well-known
+/// datacenter networks, and treat completely empty results as a miss.
+fn complete(mut info: GeoInfo) -> Option<GeoInfo> {
+    info.country_code.make_ascii_uppercase();
+    info.continent_code.make_ascii_uppercase();
+    info.datacenter |= info.asn.is_some_and(|n| DATACENTER_ASNS.contains(&n));
+
+    if info.country.is_empty() && info.region.is_empty() && info.city.is_empty() {
+        return None;
+    }
+    Some(info)
+}
+
+/// Split "AS15169 Google LLC" into the AS number and the operator's name.
+fn split_as_name(s: &str) -> (Option<i64>, String) {
+    let (number, name) = s.split_once(' ').unwrap_or((s, ""));
+    let asn = number.strip_prefix("AS").and_then(|n| n.parse().ok());
+    (asn, name.trim().to_owned())
+}
+
+/// Send a single-IP lookup and convert the JSON response. Rate limiting,
+/// rejected credentials, and network or server errors leave the IP for a
+/// later retry; other client errors (e.g. an address the provider rejects)
+/// are cached as misses.
+async fn fetch_one<T: DeserializeOwned>(
+    provider: &str,
+    ip: &str,
+    request: reqwest::RequestBuilder,
+    convert: impl FnOnce(T) -> Option<GeoInfo>,
+) -> Batch {
+    let resp = match request.send().await {
+        Ok(resp) => resp,
+        Err(e) => {
+            tracing::debug!("{} lookup network error: {}", provider, e);
+            return Batch::default();
+        }
+    };
+
+    let status = resp.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+        || status.is_server_error()
+    {
+        tracing::warn!("{} lookup for {} skipped: HTTP {}", provider, ip, status);
+        return Batch::default();
+    }
+    if !status.is_success() {
+        tracing::debug!("{} has no data for {}: HTTP {}", provider, ip, status);
+        return Batch {
+            results: Some(vec![(ip.to_owned(), None)]),
+            ..Batch::default()
+        };
+    }
+
+    match resp.json::<T>().await {
+        Ok(body) => Batch {
+            results: Some(vec![(ip.to_owned(), convert(body))]),
+            ..Batch::default()
+        },
+        Err(e) => {
+            tracing::debug!("{} lookup parse error: {}", provider, e);
+            Batch::default()
+        }
+    }
+}
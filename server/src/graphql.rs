@@ -0,0 +1,468 @@
+use crate::{
+    db_graphql::{self, ClickScope, Dimension, LinkQuery},
+    models::{Click, LinkWithStats},
+    AppState,
+};
+use async_graphql::{
+    connection::{self, Connection, Edge},
+    ComplexObject, Context, EmptyMutation, EmptySubscription, InputObject, Object, Schema,
+    SimpleObject,
+};
+use chrono::NaiveDateTime;
+use std::sync::Arc;
+
+/// Page size when `first` is not given.
+const DEFAULT_PAGE_SIZE: usize = 50;
+/// Largest page a client may ask for.
+const MAX_PAGE_SIZE: usize = 500;
+/// Rows per breakdown when `limit` is not given.
+const DEFAULT_BREAKDOWN_LIMIT: i64 = 10;
+
+pub type LinklySchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// The read-only reporting schema served at `/api/graphql`.
+///
+/// Each request must carry the app state and a [`Viewer`] as request data;
+/// see `handlers::graphql`.
+pub fn schema() -> LinklySchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(10)
+        .limit_complexity(1_000)
+        .finish()
+}
+
+/// The API user a request runs as. Everyone only sees their workspace's
+/// links, and non-admins only their own.
+pub struct Viewer {
+    pub user_id: i64,
+    pub is_admin: bool,
+    pub workspace_id: i64,
+}
+
+impl Viewer {
+    fn owner_filter(&self) -> Option<i64> {
+        (!self.is_admin).then_some(self.user_id)
+    }
+}
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Filters for `links`. All are optional and combined with AND.
+#[derive(InputObject, Default)]
+pub struct LinkFilter {
+    /// Substring of the short code, destination URL, or title.
+    search: Option<String>,
+    domain_id: Option<i64>,
+    is_active: Option<bool>,
+    /// Created at or after this UTC time.
+    created_after: Option<NaiveDateTime>,
+    /// Created before this UTC time.
+    created_before: Option<NaiveDateTime>,
+}
+
+#[derive(SimpleObject)]
+pub struct TotalCount {
+    /// Matches across all pages.
+    total_count: i64,
+}
+
+/// A short link.
+#[derive(SimpleObject)]
+#[graphql(complex, name = "Link")]
+pub struct LinkNode {
+    id: i64,
+    short_code: String,
+    short_url: String,
+    original_url: String,
+    title: Option<String>,
+    description: Option<String>,
+    created_at: NaiveDateTime,
+    is_active: bool,
+    /// All-time clicks.
+    click_count: i64,
+}
+
+/// One click on a short link. Visitor IPs and user agents are not exposed.
+#[derive(SimpleObject)]
+#[graphql(name = "Click")]
+pub struct ClickNode {
+    id: i64,
+    link_id: i64,
+    clicked_at: NaiveDateTime,
+    referer: Option<String>,
+    browser: Option<String>,
+    os: Option<String>,
+    device_type: Option<String>,
+    country: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. `US`.
+    country_code: Option<String>,
+    /// Two-letter continent code: `AF`, `AN`, `AS`, `EU`, `NA`, `OC`, or `SA`.
+    continent: Option<String>,
+    region: Option<String>,
+    city: Option<String>,
+    /// Reported by the browser on client-side redirects only.
+    screen_width: Option<i64>,
+    screen_height: Option<i64>,
+    language: Option<String>,
+    timezone: Option<String>,
+}
+
+/// Aggregated click statistics over a set of links and a time range.
+pub struct Stats {
+    scope: ClickScope,
+}
+
+/// Clicks for one value of a breakdown; `value` is null when unknown.
+#[derive(SimpleObject)]
+pub struct BreakdownRow {
+    value: Option<String>,
+    clicks: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct DailyClicks {
+    /// UTC date, `YYYY-MM-DD`.
+    date: String,
+    clicks: i64,
+}
+
+type LinkConnection = Connection<i64, LinkNode, TotalCount>;
+type ClickConnection = Connection<i64, ClickNode, TotalCount>;
+
+// ── Query root ─────────────────────────────────────────────────────────────
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Links, newest first. Cursors are opaque; pass `endCursor` as `after`.
+    async fn links(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<LinkFilter>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<LinkConnection> {
+        let (state, viewer) = request_data(ctx)?;
+        let filter = filter.unwrap_or_default();
+        let q = LinkQuery {
+            workspace_id: Some(viewer.workspace_id),
+            owner_id: viewer.owner_filter(),
+            search: filter.search.filter(|s| !s.is_empty()),
+            domain_id: filter.domain_id,
+            is_active: filter.is_active,
+            created_after: filter.created_after,
+            created_before: filter.created_before,
+            ..Default::default()
+        };
+
+        connection::query(
+            after,
+            None,
+            first,
+            None,
+            |after: Option<i64>, _, first, _| async move {
+                let limit = page_size(first);
+                let total_count = db_graphql::count_links(&state.db, &q)
+                    .await
+                    .map_err(internal)?;
+                let mut rows = db_graphql::list_links(&state.db, &q, after, limit as i64 + 1)
+                    .await
+                    .map_err(internal)?;
+                let has_next = rows.len() > limit;
+                rows.truncate(limit);
+
+                let mut page = Connection::with_additional_fields(
+                    after.is_some(),
+                    has_next,
+                    TotalCount { total_count },
+                );
+                page.edges.extend(
+                    rows.into_iter()
+                        .map(|l| Edge::new(l.id, LinkNode::new(state, l))),
+                );
+                Ok::<_, async_graphql::Error>(page)
+            },
+        )
+        .await
+    }
+
+    /// One link by id or short code.
+    async fn link(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<i64>,
+        short_code: Option<String>,
+    ) -> async_graphql::Result<Option<LinkNode>> {
+        let (state, viewer) = request_data(ctx)?;
+        if id.is_none() && short_code.is_none() {
+            return Err("Pass id or shortCode".into());
+        }
+        let q = LinkQuery {
+            workspace_id: Some(viewer.workspace_id),
+            owner_id: viewer.owner_filter(),
+            id,
+            short_code,
+            ..Default::default()
+        };
+        let link = db_graphql::list_links(&state.db, &q, None, 1)
+            .await
+            .map_err(internal)?
+            .pop();
+        Ok(link.map(|l| LinkNode::new(state, l)))
+    }
+
+    /// Click statistics across all your links (or your workspace's, for admins),
+    /// optionally narrowed to one link and a `[from, to)` UTC range.
+    async fn stats(
+        &self,
+        ctx: &Context<'_>,
+        link_id: Option<i64>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<Stats> {
+        let (_, viewer) = request_data(ctx)?;
+        Ok(Stats {
+            scope: ClickScope {
+                workspace_id: Some(viewer.workspace_id),
+                owner_id: viewer.owner_filter(),
+                link_id,
+                from,
+                to,
+            },
+        })
+    }
+}
+
+// ── Links ──────────────────────────────────────────────────────────────────
+
+impl LinkNode {
+    fn new(state: &AppState, l: LinkWithStats) -> Self {
+        Self {
+            short_url: l.short_url(&state.config.base_url),
+            id: l.id,
+            short_code: l.short_code,
+            original_url: l.original_url,
+            title: l.title,
+            description: l.description,
+            created_at: l.created_at,
+            is_active: l.is_active,
+            click_count: l.click_count,
+        }
+    }
+}
+
+#[ComplexObject]
+impl LinkNode {
+    /// This link's clicks, newest first, optionally within `[from, to)`.
+    async fn clicks(
+        &self,
+        ctx: &Context<'_>,
+        first: Option<i32>,
+        after: Option<String>,
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<ClickConnection> {
+        let (state, _) = request_data(ctx)?;
+        let scope = ClickScope {
+            link_id: Some(self.id),
+            from,
+            to,
+            ..Default::default()
+        };
+
+        connection::query(
+            after,
+            None,
+            first,
+            None,
+            |after: Option<i64>, _, first, _| async move {
+                let limit = page_size(first);
+                let total_count = db_graphql::count_clicks(&state.db, &scope)
+                    .await
+                    .map_err(internal)?;
+                let mut rows = db_graphql::list_clicks(&state.db, &scope, after, limit as i64 + 1)
+                    .await
+                    .map_err(internal)?;
+                let has_next = rows.len() > limit;
+                rows.truncate(limit);
+
+                let mut page = Connection::with_additional_fields(
+                    after.is_some(),
+                    has_next,
+                    TotalCount { total_count },
+                );
+                page.edges.extend(
+                    rows.into_iter()
+                        .map(|c| Edge::new(c.id, ClickNode::from(c))),
+                );
+                Ok::<_, async_graphql::Error>(page)
+            },
+        )
+        .await
+    }
+
+    /// Statistics for this link, optionally within `[from, to)`.
+    async fn stats(&self, from: Option<NaiveDateTime>, to: Option<NaiveDateTime>) -> Stats {
+        Stats {
+            scope: ClickScope {
+                link_id: Some(self.id),
+                from,
+                to,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<Click> for ClickNode {
+    fn from(c: Click) -> Self {
+        Self {
+            id: c.id,
+            link_id: c.link_id,
+            clicked_at: c.clicked_at,
+            referer: c.referer,
+            browser: c.browser,
+            os: c.os,
+            device_type: c.device_type,
+            country: c.country,
+            country_code: c.country_code,
+            continent: c.continent,
+            region: c.region,
+            city: c.city,
+            screen_width: c.screen_width,
+            screen_height: c.screen_height,
+            language: c.language,
+            timezone: c.timezone,
+        }
+    }
+}
+
+// ── Stats ──────────────────────────────────────────────────────────────────
+
+#[Object]
+impl Stats {
+    async fn total_clicks(&self, ctx: &Context<'_>) -> async_graphql::Result<i64> {
+        let (state, _) = request_data(ctx)?;
+        db_graphql::count_clicks(&state.db, &self.scope)
+            .await
+            .map_err(internal)
+    }
+
+    /// Distinct visitors, told apart by IP address and user agent.
+    async fn unique_visitors(&self, ctx: &Context<'_>) -> async_graphql::Result<i64> {
+        let (state, _) = request_data(ctx)?;
+        db_graphql::count_unique_visitors(&state.db, &self.scope)
+            .await
+            .map_err(internal)
+    }
+
+    async fn countries(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::Country, limit).await
+    }
+
+    /// Clicks by ISO 3166-1 alpha-2 country code.
+    async fn country_codes(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::CountryCode, limit).await
+    }
+
+    /// Clicks by two-letter continent code.
+    async fn continents(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::Continent, limit).await
+    }
+
+    async fn referrers(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::Referer, limit).await
+    }
+
+    async fn browsers(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::Browser, limit).await
+    }
+
+    async fn operating_systems(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::Os, limit).await
+    }
+
+    async fn devices(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        self.breakdown(ctx, Dimension::DeviceType, limit).await
+    }
+
+    /// Clicks per UTC day, oldest first. Days without clicks are omitted.
+    async fn daily(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<DailyClicks>> {
+        let (state, _) = request_data(ctx)?;
+        let rows = db_graphql::clicks_by_day(&state.db, &self.scope)
+            .await
+            .map_err(internal)?;
+        Ok(rows
+            .into_iter()
+            .map(|(date, clicks)| DailyClicks { date, clicks })
+            .collect())
+    }
+}
+
+impl Stats {
+    async fn breakdown(
+        &self,
+        ctx: &Context<'_>,
+        dim: Dimension,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<Vec<BreakdownRow>> {
+        let (state, _) = request_data(ctx)?;
+        let limit = limit
+            .unwrap_or(DEFAULT_BREAKDOWN_LIMIT)
+            .clamp(1, MAX_PAGE_SIZE as i64);
+        let rows = db_graphql::clicks_by(&state.db, &self.scope, dim, limit)
+            .await
+            .map_err(internal)?;
+        Ok(rows
+            .into_iter()
+            .map(|(value, clicks)| BreakdownRow { value, clicks })
+            .collect())
+    }
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+fn request_data<'a>(ctx: &Context<'a>) -> async_graphql::Result<(&'a AppState, &'a Viewer)> {
+    let state = ctx.data::<Arc<AppState>>()?;
+    let viewer = ctx.data::<Viewer>()?;
+    Ok((state, viewer))
+}
+
+fn page_size(first: Option<usize>) -> usize {
+    first.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Log a database error and hide its details from the client.
+fn internal(e: sqlx::Error) -> async_graphql::Error {
+    tracing::error!("GraphQL query failed: {:?}", e);
+    "Internal error".into()
+}
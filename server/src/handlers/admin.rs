@@ -1,23 +1,53 @@
 use crate::{
-    auth::{self, AuthUser},
-    db, db_bio, db_users,
-    models::{AnalyticsSummary, BioPageWithClicks, LinkWithStats, User},
-    password, AppState,
+    auth::{self, AdminUser, ApiUser, AuthUser, InstanceAdmin},
+    backup,
+    cache::CachedLink,
+    config::{AppConfig, CodeStrategy},
+    db, db_alerts, db_aliases, db_bio,
+    db_click_log::{self, ClickLogFilter, Facet, Page},
+    db_conversions, db_domains, db_geo_backfill, db_graphql, db_link_activity, db_maintenance,
+    db_rollups, db_screenshots, db_users,
+    destination::{self, DestinationError},
+    error::AppError,
+    geo::{self, CountryRules},
+    geo_backfill,
+    handlers::redirect,
+    http_cache,
+    language::LanguageRules,
+    links::{self, CreateError, Created, DomainChoice, NewLink, Owner},
+    models::{
+        AnalyticsSummary, ApiToken, BioPageWithClicks, Click, ClickAlert, ComparisonTotals, Domain,
+        Link, LinkWithStats, Maintenance, User, ACTIVITY_ACTIVATED, ACTIVITY_ARCHIVED,
+        ACTIVITY_DEACTIVATED, ACTIVITY_DESTINATION_DOWN, ACTIVITY_DESTINATION_UP, ACTIVITY_EDITED,
+        ACTIVITY_RESTORED, ACTIVITY_SETTINGS, ACTIVITY_TRASHED, ACTIVITY_UNARCHIVED,
+        DEFAULT_WORKSPACE, MAINTENANCE_PAGE, MAINTENANCE_REDIRECT, SOURCE_QR,
+    },
+    notify, oidc, password, qr, quota, reload,
+    schedule::ScheduleRules,
+    screenshot::CaptureError,
+    short_code, trash,
+    validate::{LinkInput, MAX_CODE_LEN},
+    variants::Variants,
+    AppState,
 };
 use askama::Template;
 use axum::{
-    extract::{Form, Path, Query, State},
+    extract::{ConnectInfo, Form, Path, Query, State},
+    http::{header, HeaderMap},
     response::{
-        sse::{Event, Sse},
-        IntoResponse, Redirect, Response,
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Redirect, Response,
     },
 };
 use axum_extra::extract::{
     cookie::{Cookie, SameSite},
     CookieJar,
 };
-use serde::Deserialize;
-use std::sync::Arc;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
 
 // ── Template structs ───────────────────────────────────────────────────────
 
@@ -26,6 +56,8 @@ use std::sync::Arc;
 struct LoginTemplate {
     error: Option<String>,
     app_title: String,
+    /// Provider name for the single sign-on button, when SSO is configured.
+    sso_name: Option<String>,
 }
 
 #[derive(Template)]
@@ -50,10 +82,22 @@ struct DashboardTemplate {
     total_bio_pages: i64,
     total_bio_link_clicks: i64,
     top_short_links: Vec<LinkWithStats>,
+    thumbnails: Thumbnails,
+    /// Whether to show destination favicons (`FAVICONS`).
+    favicons: bool,
     max_short_link_clicks: i64,
+    /// Links that have gone longest without a click.
+    quiet_links: Vec<LinkWithStats>,
     top_bio_pages: Vec<BioPageWithClicks>,
     max_bio_page_clicks: i64,
     recent_activity: Vec<RecentActivityRow>,
+    /// Admins only: the location backfill job's progress.
+    geo_backfill: Option<geo_backfill::Progress>,
+    /// Clicks recorded with an IP but no location (admins only).
+    geo_missing: i64,
+    /// Show maintenance mode's switch (instance admins only).
+    show_maintenance: bool,
+    maintenance: Option<Arc<Maintenance>>,
     is_admin: bool,
     app_title: String,
 }
@@ -72,6 +116,21 @@ struct RecentActivityRow {
 #[template(path = "short_links.html")]
 struct ShortLinksTemplate {
     links: Vec<LinkWithStats>,
+    /// Listing archived links instead of the working set.
+    show_archived: bool,
+    sort: db::LinkSort,
+    /// Links in the list not shown.
+    hidden_count: usize,
+    /// Days deleted links can be restored for.
+    trash_days: i64,
+    thumbnails: Thumbnails,
+    /// Whether to show destination favicons (`FAVICONS`).
+    favicons: bool,
+    domains: Vec<Domain>,
+    /// Preselected in the create form: the workspace's own domain, if any.
+    default_domain_id: Option<i64>,
+    /// `pattern` for the custom code input.
+    code_pattern: &'static str,
     base_url: String,
     flash_success: Option<String>,
     flash_error: Option<String>,
@@ -79,17 +138,189 @@ struct ShortLinksTemplate {
     app_title: String,
 }
 
+impl ShortLinksTemplate {
+    /// Query string for this list in another order.
+    fn sort_query(&self, sort: &str) -> String {
+        if self.show_archived {
+            format!("?archived=true&sort={sort}")
+        } else {
+            format!("?sort={sort}")
+        }
+    }
+
+    fn sorted_by(&self, sort: &str) -> bool {
+        self.sort.as_param() == sort
+    }
+}
+
+#[derive(Template)]
+#[template(path = "trash.html")]
+struct TrashTemplate {
+    links: Vec<TrashedLink>,
+    retention_days: i64,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+#[derive(Template)]
+#[template(path = "compare.html")]
+struct CompareTemplate {
+    links: Vec<ComparedLink>,
+    /// Links that can still be added, as (id, label).
+    candidates: Vec<(i64, String)>,
+    /// The compared link ids, comma-separated.
+    links_param: String,
+    days: i64,
+    periods: [i64; 4],
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+    /// Most clicks any of the links got in one day: the top of the chart.
+    peak: i64,
+    is_admin: bool,
+    app_title: String,
+}
+
+#[derive(Template)]
+#[template(path = "report.html")]
+struct ReportTemplate {
+    link: Link,
+    short_url: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    clicks: i64,
+    unique_visitors: i64,
+    /// Clicks per day of the range, oldest first.
+    daily: Vec<(NaiveDate, i64)>,
+    /// Most clicks on one day, the top of the chart.
+    peak: i64,
+    // Breakdowns: (name, count, pct_of_total)
+    top_countries: Vec<(String, i64, i64)>,
+    top_referers: Vec<(String, i64, i64)>,
+    top_devices: Vec<(String, i64, i64)>,
+    top_browsers: Vec<(String, i64, i64)>,
+    generated_at: NaiveDateTime,
+    app_title: String,
+}
+
+#[derive(Template)]
+#[template(path = "link_detail.html")]
+struct LinkDetailTemplate {
+    link: Link,
+    short_url: String,
+    /// Short URL the link's QR code encodes.
+    qr_url: String,
+    totals: ComparisonTotals,
+    /// Clicks over the last 7 days, today included.
+    week_clicks: i64,
+    /// Whether the link redirects right now.
+    resolves: bool,
+    /// Newest first.
+    timeline: Vec<TimelineEntry>,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+/// One event on a link's timeline.
+struct TimelineEntry {
+    at: NaiveDateTime,
+    label: String,
+    detail: Option<String>,
+    /// Who did it, for changes made by someone.
+    by: Option<String>,
+}
+
 #[derive(Template)]
 #[template(path = "analytics.html")]
 struct AnalyticsTemplate {
     summary: AnalyticsSummary,
     short_url: String,
+    /// `pattern` for the alias input.
+    code_pattern: &'static str,
+    /// Tracking pixel that logs impressions of this link.
+    pixel_url: String,
+    /// Short URL the link's QR code encodes.
+    qr_url: String,
+    /// Public stats page URL, when sharing is enabled.
+    stats_url: Option<String>,
+    /// `DISPLAY_TIMEZONE`, which schedule rules are read in, and the time
+    /// there now.
+    schedule_timezone: &'static str,
+    schedule_now: String,
+    /// Whether destinations with a fallback are checked
+    /// (`FALLBACK_CHECK_MINUTES`).
+    fallback_checks: bool,
+    /// The last day of clicks folded into daily totals by
+    /// `CLICK_RETENTION_DAYS`, if any were.
+    rolled_up_until: Option<NaiveDate>,
+    /// Whether `SCREENSHOT_URL` is set, and the link's thumbnail if it has one.
+    screenshots_enabled: bool,
+    thumbnail_url: Option<String>,
+    /// Whether the figures include clicks from `INTERNAL_IPS`.
+    include_internal: bool,
+    /// Whether the figures leave out clicks from datacenter networks.
+    hide_datacenter: bool,
+    /// Query strings for this page with the current traffic filters, and
+    /// with each one flipped.
+    traffic_query: String,
+    internal_toggle_query: String,
+    datacenter_toggle_query: String,
     // Pre-computed breakdowns: (name, count, pct_of_total)
     top_browsers: Vec<(String, i64, i64)>,
     top_os: Vec<(String, i64, i64)>,
     top_devices: Vec<(String, i64, i64)>,
     top_referers: Vec<(String, i64, i64)>,
+    top_sources: Vec<(String, i64, i64)>,
+    /// Clicks by the language rule that sent them; empty unless the link
+    /// has or had language rules.
+    top_language_rules: Vec<(String, i64, i64)>,
+    /// Clicks by the variant they were sent to; empty unless the link has
+    /// or had variants.
+    top_variants: Vec<(String, i64, i64)>,
     top_countries: Vec<(String, i64, i64)>,
+    top_continents: Vec<(String, i64, i64)>,
+    top_networks: Vec<(String, i64, i64)>,
+    // Reported by client-redirect pages only
+    top_screens: Vec<(String, i64, i64)>,
+    top_languages: Vec<(String, i64, i64)>,
+    top_timezones: Vec<(String, i64, i64)>,
+    /// (weekday, [(clicks, cell opacity %); 24]), in UTC
+    heatmap: Vec<(&'static str, Vec<(i64, i64)>)>,
+    /// (name, conversions, conversion rate, total value)
+    conversions: Vec<(String, i64, String, String)>,
+    click_alerts: Vec<ClickAlert>,
+    /// (alias, short URL)
+    aliases: Vec<(String, String)>,
+    /// Short domains the link can be moved to.
+    domains: Vec<Domain>,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+#[derive(Template)]
+#[template(path = "click_log.html")]
+struct ClickLogTemplate {
+    link: Link,
+    short_url: String,
+    clicks: Vec<Click>,
+    /// Clicks matching the filters, across all pages.
+    matching: i64,
+    // Filter form values, as submitted
+    from: String,
+    to: String,
+    referer: String,
+    bots: String,
+    /// (value, selected)
+    countries: Vec<(String, bool)>,
+    browsers: Vec<(String, bool)>,
+    /// Links to the neighbouring pages, keeping the filters.
+    newer_url: Option<String>,
+    older_url: Option<String>,
     is_admin: bool,
     app_title: String,
 }
@@ -98,12 +329,33 @@ struct AnalyticsTemplate {
 #[template(path = "profile.html")]
 struct ProfileTemplate {
     user: User,
+    /// (token, API requests made with it this hour)
+    api_tokens: Vec<(ApiToken, u64)>,
+    /// This account's API requests this hour and the hourly limits for the
+    /// account and for each token (0 = unlimited).
+    api_requests: u64,
+    api_limit: u64,
+    key_limit: u64,
+    /// Shown exactly once, right after the token is created.
+    new_api_token: Option<String>,
     flash_success: Option<String>,
     flash_error: Option<String>,
     is_admin: bool,
     app_title: String,
 }
 
+/// Emailed when an account signs in from an IP address it hasn't used before.
+#[derive(Template)]
+#[template(path = "email/new_login.txt")]
+struct NewLoginEmail<'a> {
+    app_title: &'a str,
+    base_url: &'a str,
+    display_name: &'a str,
+    ip: &'a str,
+    user_agent: &'a str,
+    time: String,
+}
+
 // ── Form types ─────────────────────────────────────────────────────────────
 
 #[derive(Deserialize)]
@@ -126,6 +378,11 @@ pub struct ChangePasswordForm {
     new_password_confirm: String,
 }
 
+#[derive(Deserialize)]
+pub struct ApiTokenForm {
+    name: String,
+}
+
 #[derive(Deserialize)]
 pub struct ProfileForm {
     email: String,
@@ -141,14 +398,27 @@ pub struct CreateLinkForm {
     title: Option<String>,
     description: Option<String>,
     custom_code: Option<String>,
+    domain_id: Option<String>,
 }
 
 // ── Handlers ───────────────────────────────────────────────────────────────
 
 /// GET /
-/// Redirect root visitors to the configured ROOT_REDIRECT_URL.
-pub async fn index(State(state): State<Arc<AppState>>) -> Redirect {
-    Redirect::to(&state.config.root_redirect_url)
+/// Redirect root visitors to the domain's root redirect, falling back to the
+/// configured ROOT_REDIRECT_URL.
+pub async fn index(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Redirect {
+    let domain_url = super::redirect::request_host(&state.config, &headers, addr)
+        .and_then(|host| state.domains.resolve(host))
+        .and_then(|d| d.root_redirect_url);
+    Redirect::to(
+        domain_url
+            .as_deref()
+            .unwrap_or(&state.tunables().root_redirect_url),
+    )
 }
 
 /// GET /admin
@@ -167,16 +437,14 @@ pub async fn login_page(jar: CookieJar, State(state): State<Arc<AppState>>) -> R
             return Redirect::to("/admin/dashboard").into_response();
         }
     }
-    LoginTemplate {
-        error: None,
-        app_title: state.config.app_title.clone(),
-    }
-    .into_response()
+    login_form(&state, None)
 }
 
 /// POST /admin/login
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     jar: CookieJar,
     Form(form): Form<LoginForm>,
 ) -> Response {
@@ -187,11 +455,7 @@ pub async fn login(
         Ok(Some(u)) => u,
         _ => {
             tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-            return LoginTemplate {
-                error: Some("Invalid email or password.".into()),
-                app_title: state.config.app_title.clone(),
-            }
-            .into_response();
+            return login_form(&state, Some("Invalid email or password."));
         }
     };
 
@@ -204,23 +468,29 @@ pub async fn login(
 
     if !valid {
         tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-        return LoginTemplate {
-            error: Some("Invalid email or password.".into()),
-            app_title: state.config.app_title.clone(),
-        }
-        .into_response();
+        return login_form(&state, Some("Invalid email or password."));
     }
 
     // Check approval
     if !user.is_approved {
-        return LoginTemplate {
-            error: Some("Your account is pending approval by an admin.".into()),
-            app_title: state.config.app_title.clone(),
-        }
-        .into_response();
+        return login_form(
+            &state,
+            Some("Your account is pending approval by an admin."),
+        );
     }
 
-    // Issue JWT
+    start_session(&state, jar, &headers, addr, &user).await
+}
+
+/// Sign `user` in: set the session cookie, note a new sign-in IP (emailing
+/// the user about it), and go to the dashboard.
+async fn start_session(
+    state: &AppState,
+    jar: CookieJar,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+    user: &User,
+) -> Response {
     let token = match auth::create_jwt(
         user.id,
         &user.email,
@@ -228,15 +498,12 @@ pub async fn login(
         &state.config.jwt_secret,
         state.config.session_duration_hours,
         user.force_password_change,
+        user.workspace_id,
     ) {
         Ok(t) => t,
         Err(e) => {
             tracing::error!("Failed to create JWT: {:?}", e);
-            return LoginTemplate {
-                error: Some("Internal error. Please try again.".into()),
-                app_title: state.config.app_title.clone(),
-            }
-            .into_response();
+            return login_form(state, Some("Internal error. Please try again."));
         }
     };
 
@@ -249,9 +516,206 @@ pub async fn login(
         ))
         .build();
 
+    // A spoofed address could pass for a known one and hide the sign-in
+    let ip = redirect::client_ip(&state.config, headers, addr);
+    match db_users::record_login_ip(&state.db, user.id, &ip).await {
+        Ok(true) => {
+            let user_agent = headers
+                .get("user-agent")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("unknown");
+            state.mailer.send(
+                &user.email,
+                &format!("New sign-in to your {} account", state.config.app_title),
+                &NewLoginEmail {
+                    app_title: &state.config.app_title,
+                    base_url: &state.config.base_url,
+                    display_name: &user.display_name,
+                    ip: &ip,
+                    user_agent,
+                    time: chrono::Utc::now().format("%Y-%m-%d %H:%M").to_string(),
+                },
+            );
+        }
+        Ok(false) => {}
+        Err(e) => tracing::error!("Failed to record login IP for user {}: {:?}", user.id, e),
+    }
+
     (jar.add(cookie), Redirect::to("/admin/dashboard")).into_response()
 }
 
+// ── Single sign-on ─────────────────────────────────────────────────────────
+
+/// Cookie holding the `state` and `nonce` of a sign-in in progress.
+const SSO_COOKIE: &str = "sso_state";
+
+#[derive(Deserialize)]
+pub struct SsoCallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+/// GET /admin/login/sso
+///
+/// Send the visitor to the OpenID Connect provider to sign in.
+pub async fn sso_login(State(state): State<Arc<AppState>>, jar: CookieJar) -> Response {
+    let Some(oidc) = &state.oidc else {
+        return Redirect::to("/admin/login").into_response();
+    };
+
+    let csrf = hex::encode(rand::random::<[u8; 16]>());
+    let nonce = hex::encode(rand::random::<[u8; 16]>());
+    let url = match oidc.authorize_url(&csrf, &nonce).await {
+        Ok(url) => url,
+        Err(e) => {
+            tracing::error!("SSO sign-in could not start: {:#}", e);
+            return login_form(
+                &state,
+                Some("Single sign-on is unavailable. Please try again later."),
+            );
+        }
+    };
+
+    let cookie = Cookie::build((SSO_COOKIE, format!("{csrf}.{nonce}")))
+        .path("/admin/login/sso")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(10))
+        .build();
+    (jar.add(cookie), Redirect::to(&url)).into_response()
+}
+
+/// GET /admin/login/sso/callback
+///
+/// Where the provider sends the visitor back. Accounts are created on first
+/// sign-in; the role comes from the `OIDC_*_EMAILS` / `OIDC_*_DOMAINS`
+/// settings and is re-applied on every sign-in.
+pub async fn sso_callback(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    Query(query): Query<SsoCallbackQuery>,
+) -> Response {
+    let Some(oidc) = &state.oidc else {
+        return Redirect::to("/admin/login").into_response();
+    };
+
+    let expected = jar.get(SSO_COOKIE).map(|c| c.value().to_owned());
+    let jar = jar.remove(Cookie::build(SSO_COOKIE).path("/admin/login/sso"));
+
+    if let Some(error) = query.error {
+        tracing::warn!("SSO provider returned an error: {}", error);
+        return (
+            jar,
+            login_form(
+                &state,
+                Some("Sign-in was cancelled or refused by the provider."),
+            ),
+        )
+            .into_response();
+    }
+    let (Some((csrf, nonce)), Some(code), Some(returned)) = (
+        expected.as_deref().and_then(|v| v.split_once('.')),
+        query.code,
+        query.state,
+    ) else {
+        return (
+            jar,
+            login_form(&state, Some("Sign-in expired. Please try again.")),
+        )
+            .into_response();
+    };
+    if returned != csrf {
+        return (
+            jar,
+            login_form(&state, Some("Sign-in expired. Please try again.")),
+        )
+            .into_response();
+    }
+
+    let identity = match oidc.exchange(&code, nonce).await {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::error!("SSO sign-in failed: {:#}", e);
+            return (
+                jar,
+                login_form(&state, Some("Single sign-on failed. Please try again.")),
+            )
+                .into_response();
+        }
+    };
+
+    let Some(role) = oidc.role_for(&identity.email) else {
+        tracing::warn!("SSO sign-in refused for {}", identity.email);
+        let msg = format!("{} isn't allowed to sign in here.", identity.email);
+        return (jar, login_form(&state, Some(&msg))).into_response();
+    };
+
+    let user = match sso_user(&state, &identity, role).await {
+        Ok(user) => user,
+        Err(e) => {
+            tracing::error!("Failed to set up SSO user {}: {:?}", identity.email, e);
+            return (
+                jar,
+                login_form(&state, Some("Internal error. Please try again.")),
+            )
+                .into_response();
+        }
+    };
+
+    start_session(&state, jar, &headers, addr, &user).await
+}
+
+/// Find or create the account for an SSO identity and bring its role and
+/// approval in line with the SSO settings.
+async fn sso_user(state: &AppState, identity: &oidc::Identity, role: &str) -> anyhow::Result<User> {
+    if let Some(user) = db_users::get_user_by_email(&state.db, &identity.email).await? {
+        if user.role != role {
+            db_users::set_user_role(&state.db, None, user.id, role).await?;
+        }
+        if !user.is_approved {
+            db_users::approve_user(&state.db, None, user.id).await?;
+        }
+        return Ok(User {
+            role: role.to_owned(),
+            is_approved: true,
+            ..user
+        });
+    }
+
+    // SSO accounts sign in through the provider, so the password is a random
+    // value nobody knows. An admin can still set one from the users page.
+    // The callback is always on the primary domain, so new accounts join the
+    // default workspace.
+    let unusable = hex::encode(rand::random::<[u8; 32]>());
+    let hash = tokio::task::spawn_blocking(move || password::hash_password(&unusable))
+        .await?
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {e}"))?;
+    let display_name = identity.name.clone().unwrap_or_else(|| {
+        identity
+            .email
+            .split('@')
+            .next()
+            .unwrap_or_default()
+            .to_owned()
+    });
+    let user = db_users::create_user(
+        &state.db,
+        &identity.email,
+        &display_name,
+        &hash,
+        role,
+        true,
+        false,
+        DEFAULT_WORKSPACE,
+    )
+    .await?;
+    tracing::info!("Created {} account for {} via SSO", role, identity.email);
+    Ok(user)
+}
+
 /// GET /admin/logout
 pub async fn logout(jar: CookieJar) -> Response {
     let removal = Cookie::build(("auth_token", ""))
@@ -330,6 +794,7 @@ pub async fn change_password(
         &state.config.jwt_secret,
         state.config.session_duration_hours,
         false,
+        auth.workspace_id,
     ) {
         Ok(t) => t,
         Err(e) => {
@@ -363,6 +828,16 @@ pub async fn profile_page(
     auth: AuthUser,
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+) -> Response {
+    render_profile(&auth, &state, jar, None).await
+}
+
+/// Render the profile page, optionally revealing a freshly created API token.
+async fn render_profile(
+    auth: &AuthUser,
+    state: &AppState,
+    jar: CookieJar,
+    new_api_token: Option<String>,
 ) -> Response {
     let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
     let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
@@ -381,8 +856,26 @@ pub async fn profile_page(
         _ => return Redirect::to("/admin/dashboard").into_response(),
     };
 
+    let api_tokens = db_users::get_api_tokens(&state.db, auth.user_id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load API tokens: {:?}", e);
+            vec![]
+        })
+        .into_iter()
+        .map(|t| {
+            let requests = state.api_usage.key_requests(t.id);
+            (t, requests)
+        })
+        .collect();
+
     let tmpl = ProfileTemplate {
+        api_requests: state.api_usage.user_requests(user.id),
+        api_limit: quota::api_limit(state, user.api_requests_per_hour),
+        key_limit: state.tunables().quota_api_key_requests_per_hour,
         user,
+        api_tokens,
+        new_api_token,
         flash_success,
         flash_error,
         is_admin: auth.is_admin(),
@@ -531,7 +1024,7 @@ pub async fn update_profile(
         db_users::update_user_profile(&state.db, auth.user_id, &email, &display_name).await
     {
         tracing::error!("Failed to update profile: {:?}", e);
-        let msg = if e.to_string().contains("UNIQUE") {
+        let msg = if db::is_unique_violation(&e) {
             "That email is already in use by another account."
         } else {
             "Failed to update profile."
@@ -548,6 +1041,7 @@ pub async fn update_profile(
             &state.config.jwt_secret,
             state.config.session_duration_hours,
             false,
+            auth.workspace_id,
         ) {
             Ok(t) => t,
             Err(e) => {
@@ -599,6 +1093,8 @@ pub async fn register_page(jar: CookieJar, State(state): State<Arc<AppState>>) -
 /// POST /admin/register
 pub async fn register(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     jar: CookieJar,
     Form(form): Form<RegisterForm>,
 ) -> Response {
@@ -676,6 +1172,12 @@ pub async fn register(
         ("user", false)
     };
 
+    // Registering on a domain reserved for a workspace joins that workspace
+    let workspace_id = super::redirect::request_host(&state.config, &headers, addr)
+        .and_then(|host| state.domains.resolve(host))
+        .and_then(|d| d.workspace_id)
+        .unwrap_or(DEFAULT_WORKSPACE);
+
     match db_users::create_user(
         &state.db,
         &email,
@@ -684,6 +1186,7 @@ pub async fn register(
         role,
         is_approved,
         false,
+        workspace_id,
     )
     .await
     {
@@ -697,6 +1200,7 @@ pub async fn register(
                     &state.config.jwt_secret,
                     state.config.session_duration_hours,
                     false,
+                    user.workspace_id,
                 ) {
                     let cookie = Cookie::build(("auth_token", token))
                         .path("/")
@@ -710,18 +1214,14 @@ pub async fn register(
                 }
             }
             // Normal user — show success message on login page
-            LoginTemplate {
-                error: Some(
-                    "Account created! An admin must approve your account before you can log in."
-                        .into(),
-                ),
-                app_title: state.config.app_title.clone(),
-            }
-            .into_response()
+            login_form(
+                &state,
+                Some("Account created! An admin must approve your account before you can log in."),
+            )
         }
         Err(e) => {
             tracing::error!("Failed to create user: {:?}", e);
-            let msg = if e.to_string().contains("UNIQUE") {
+            let msg = if db::is_unique_violation(&e) {
                 "An account with that email already exists."
             } else {
                 "Failed to create account. Please try again."
@@ -739,34 +1239,29 @@ pub async fn register(
 
 /// GET /admin/dashboard
 pub async fn dashboard(auth: AuthUser, State(state): State<Arc<AppState>>) -> Response {
-    let user_filter = if auth.is_admin() {
-        None
-    } else {
-        Some(auth.user_id)
-    };
+    let scope = auth.scope();
 
-    let total_short_links = db::count_links(&state.db, user_filter).await.unwrap_or(0);
-    let total_short_link_clicks = db::count_total_clicks(&state.db, user_filter)
-        .await
-        .unwrap_or(0);
-    let total_bio_pages = db_bio::count_bio_pages(&state.db, user_filter)
-        .await
-        .unwrap_or(0);
-    let total_bio_link_clicks = db_bio::count_total_bio_link_clicks(&state.db, user_filter)
+    let total_short_links = db::count_links(&state.db, scope).await.unwrap_or(0);
+    let total_short_link_clicks = db::count_total_clicks(&state.db, scope).await.unwrap_or(0);
+    let total_bio_pages = db_bio::count_bio_pages(&state.db, scope).await.unwrap_or(0);
+    let total_bio_link_clicks = db_bio::count_total_bio_link_clicks(&state.db, scope)
         .await
         .unwrap_or(0);
 
-    let top_short_links = db::top_links_by_clicks(&state.db, 10, user_filter)
+    let top_short_links = db::top_links_by_clicks(&state.db, 10, None, scope)
+        .await
+        .unwrap_or_default();
+    let quiet_links = db::quiet_links(&state.db, 10, scope)
         .await
         .unwrap_or_default();
-    let top_bio_pages = db_bio::top_bio_pages_by_clicks(&state.db, 10, user_filter)
+    let top_bio_pages = db_bio::top_bio_pages_by_clicks(&state.db, 10, scope)
         .await
         .unwrap_or_default();
 
-    let recent_short = db::recent_clicks_with_labels(&state.db, 20, user_filter)
+    let recent_short = db::recent_clicks_with_labels(&state.db, 20, scope)
         .await
         .unwrap_or_default();
-    let recent_bio = db_bio::recent_bio_link_clicks(&state.db, 20, user_filter)
+    let recent_bio = db_bio::recent_bio_link_clicks(&state.db, 20, scope)
         .await
         .unwrap_or_default();
 
@@ -802,29 +1297,221 @@ pub async fn dashboard(auth: AuthUser, State(state): State<Arc<AppState>>) -> Re
     let max_short_link_clicks = top_short_links.first().map(|l| l.click_count).unwrap_or(0);
     let max_bio_page_clicks = top_bio_pages.first().map(|p| p.click_count).unwrap_or(0);
 
+    let (geo_backfill, geo_missing) = if auth.is_instance_admin() && state.config.geo_enabled {
+        let missing = db_geo_backfill::count_missing_geo(&state.db)
+            .await
+            .unwrap_or(0);
+        (Some(state.geo_backfill.progress()), missing)
+    } else {
+        (None, 0)
+    };
+
     DashboardTemplate {
         total_short_links,
         total_short_link_clicks,
         total_bio_pages,
         total_bio_link_clicks,
         top_short_links,
+        thumbnails: Thumbnails::load(&state).await,
+        favicons: state.favicons.is_some(),
         max_short_link_clicks,
+        quiet_links,
         top_bio_pages,
         max_bio_page_clicks,
         recent_activity,
+        geo_backfill,
+        geo_missing,
+        show_maintenance: auth.is_instance_admin(),
+        maintenance: state.maintenance.get(),
         is_admin: auth.is_admin(),
         app_title: state.config.app_title.clone(),
     }
     .into_response()
 }
 
+/// Shortest time between two live updates of one dashboard, so a burst of
+/// clicks during a launch is one recount rather than one per click.
+const LIVE_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// GET /admin/dashboard/live — Datastar SSE stream that patches the
+/// dashboard's click total whenever a click in the user's scope is recorded.
+pub async fn dashboard_live(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>> {
+    let scope = auth.scope();
+    let mut clicks = state.live_clicks.subscribe();
+    let (tx, rx) = mpsc::channel(1);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                click = clicks.recv() => match click {
+                    Ok(click) if !click.in_scope(scope) => continue,
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                },
+                // The dashboard was closed
+                _ = tx.closed() => return,
+            }
+            tokio::time::sleep(LIVE_UPDATE_INTERVAL).await;
+            clicks = clicks.resubscribe();
+
+            let total = match db::count_total_clicks(&state.db, scope).await {
+                Ok(total) => total,
+                Err(e) => {
+                    tracing::error!("Failed to count clicks for the live dashboard: {:?}", e);
+                    continue;
+                }
+            };
+            let fragment =
+                format!(r#"<div class="stat-value" id="total-short-link-clicks">{total}</div>"#);
+            let event = Event::default()
+                .event("datastar-patch-elements")
+                .data(format!("elements {fragment}"));
+            if tx.send(Ok(event)).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default())
+}
+
+/// POST /admin/geo-backfill — start re-resolving the location of clicks
+/// recorded without one. Progress shows on the dashboard.
+pub async fn start_geo_backfill(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    if !state.config.geo_enabled {
+        tracing::debug!("Geo backfill requested with GEO_ENABLED off");
+    } else if !state.geo_backfill.start(state.clone()) {
+        tracing::debug!("Geo backfill already running");
+    }
+    Redirect::to("/admin/dashboard").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct MaintenanceForm {
+    /// `page` or `redirect` to switch maintenance mode on (or change it),
+    /// anything else to switch it off.
+    mode: String,
+    message: Option<String>,
+}
+
+/// POST /admin/maintenance — switch maintenance mode on or off.
+pub async fn set_maintenance(
+    admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<MaintenanceForm>,
+) -> Response {
+    let message = form
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    let result = match form.mode.as_str() {
+        mode @ (MAINTENANCE_PAGE | MAINTENANCE_REDIRECT) => {
+            tracing::warn!(
+                "Maintenance mode ({}) switched on by {}",
+                mode,
+                admin.0.email
+            );
+            db_maintenance::start_maintenance(&state.db, mode, message, admin.0.user_id).await
+        }
+        _ => {
+            tracing::warn!("Maintenance mode switched off by {}", admin.0.email);
+            db_maintenance::end_maintenance(&state.db).await
+        }
+    };
+    if let Err(e) = result {
+        tracing::error!("Failed to switch maintenance mode: {:?}", e);
+    } else if let Err(e) = state.maintenance.reload(&state.db).await {
+        tracing::error!("Failed to reload maintenance mode: {:?}", e);
+    }
+    Redirect::to("/admin/dashboard").into_response()
+}
+
+/// GET /admin/backup — back up the database now and download the copy. It
+/// also goes wherever scheduled backups go and counts toward `BACKUP_KEEP`.
+pub async fn download_backup(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+) -> Response {
+    match backup::create(&state).await {
+        Ok(backup) => {
+            let disposition = format!("attachment; filename=\"{}\"", backup.name);
+            (
+                [
+                    (header::CONTENT_TYPE, backup::CONTENT_TYPE.to_owned()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                backup.into_body(),
+            )
+                .into_response()
+        }
+        Err(e) => AppError::internal(e, "Failed to back up the database").into_response(),
+    }
+}
+
+/// POST /admin/reload-config — reload the configuration like SIGHUP does,
+/// and report what changed. Takes an instance admin's API token.
+pub async fn reload_config(user: ApiUser, State(state): State<Arc<AppState>>) -> Response {
+    if !user.is_instance_admin() {
+        return AppError::Forbidden("Reloading needs an instance admin's API token".into())
+            .into_response();
+    }
+    let result = state.reloader.reload();
+    reload::log(&result);
+    match result {
+        Ok(reloaded) => Json(reloaded).into_response(),
+        Err(e) => AppError::Unprocessable(format!("{e:#}")).into_response(),
+    }
+}
+
+/// GET /admin/db-snapshot — stream a consistent copy of the whole database,
+/// for replication or off-site backups from a script. Takes an instance
+/// admin's API token, as a Bearer header or `?key=`.
+pub async fn db_snapshot(user: ApiUser, State(state): State<Arc<AppState>>) -> Response {
+    if !user.is_instance_admin() {
+        return AppError::Forbidden("Snapshots need an instance admin's API token".into())
+            .into_response();
+    }
+    match backup::snapshot(&state.db).await {
+        Ok(snapshot) => {
+            let disposition = format!("attachment; filename=\"{}\"", snapshot.name);
+            (
+                [
+                    (header::CONTENT_TYPE, backup::CONTENT_TYPE.to_owned()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                    (header::CACHE_CONTROL, "no-store".to_owned()),
+                ],
+                snapshot.into_body(),
+            )
+                .into_response()
+        }
+        Err(e) => AppError::internal(e, "Failed to snapshot the database").into_response(),
+    }
+}
+
 // ── Short Links ───────────────────────────────────────────────────────────
 
+#[derive(Deserialize)]
+pub struct ShortLinksQuery {
+    /// List archived links, which are hidden by default.
+    #[serde(default)]
+    archived: bool,
+    /// `created` (default), `clicks`, `last_clicked`, or `code`.
+    sort: Option<String>,
+}
+
 /// GET /admin/short-links
 pub async fn short_links(
     auth: AuthUser,
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
+    Query(query): Query<ShortLinksQuery>,
 ) -> Response {
     let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
     let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
@@ -838,26 +1525,44 @@ pub async fn short_links(
         .max_age(time::Duration::seconds(0))
         .build();
 
-    let user_filter = if auth.is_admin() {
-        None
-    } else {
-        Some(auth.user_id)
+    let sort = query
+        .sort
+        .as_deref()
+        .and_then(db::LinkSort::from_param)
+        .unwrap_or_default();
+    let links = match db::get_all_links_with_stats(&state.db, auth.scope(), sort).await {
+        Ok(l) => l,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load links").into_response();
+        }
     };
+    let (links, hidden): (Vec<_>, Vec<_>) = links
+        .into_iter()
+        .partition(|l| l.archived_at.is_some() == query.archived);
 
-    let links = match db::get_all_links_with_stats(&state.db, user_filter).await {
-        Ok(l) => l,
+    let mut domains = match db_domains::get_all_domains(&state.db).await {
+        Ok(d) => d,
         Err(e) => {
-            tracing::error!("Failed to load links: {:?}", e);
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to load links",
-            )
-                .into_response();
+            tracing::error!("Failed to load domains: {:?}", e);
+            Vec::new()
         }
     };
+    domains.retain(|d| auth.can_use(d));
 
     let tmpl = ShortLinksTemplate {
         links,
+        show_archived: query.archived,
+        sort,
+        hidden_count: hidden.len(),
+        trash_days: trash::RETENTION_DAYS,
+        thumbnails: Thumbnails::load(&state).await,
+        favicons: state.favicons.is_some(),
+        domains,
+        default_domain_id: state.domains.default_for(auth.workspace_id).map(|d| d.id),
+        code_pattern: short_code::input_pattern(
+            state.config.unicode_short_codes,
+            state.config.emoji_short_codes,
+        ),
         base_url: state.config.base_url.clone(),
         flash_success,
         flash_error,
@@ -877,128 +1582,2490 @@ pub async fn create_link(
     jar: CookieJar,
     Form(form): Form<CreateLinkForm>,
 ) -> Response {
-    // Basic URL validation
-    let url = form.url.trim().to_owned();
-    if url.is_empty() {
-        return set_flash_and_redirect(
-            jar,
-            None,
-            Some("URL must not be empty."),
-            "/admin/short-links",
-        );
-    }
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return set_flash_and_redirect(
+    // "" means the workspace's default domain
+    let domain = match form.domain_id.as_deref().filter(|s| !s.is_empty()) {
+        None => DomainChoice::Default,
+        Some(id) => match id.parse() {
+            Ok(id) => DomainChoice::Id(id),
+            Err(_) => {
+                return set_flash_and_redirect(
+                    jar,
+                    None,
+                    Some("Unknown domain."),
+                    "/admin/short-links",
+                );
+            }
+        },
+    };
+    let owner = Owner {
+        user_id: auth.user_id,
+        workspace_id: auth.workspace_id,
+    };
+    let request = NewLink {
+        url: &form.url,
+        code: form.custom_code.as_deref(),
+        title: form.title.as_deref(),
+        description: form.description.as_deref(),
+        domain,
+    };
+
+    let (success, error) = match links::create(&state, owner, request).await {
+        Ok(Created::New(link)) => (
+            Some(format!(
+                "Link created: {}",
+                state.short_url(link.domain_id, &link.short_code)
+            )),
+            None,
+        ),
+        Ok(Created::Existing(link)) => (
+            Some(format!(
+                "You already have a link to that URL: {}",
+                state.short_url(link.domain_id, &link.short_code)
+            )),
+            None,
+        ),
+        Err(CreateError::Db(e)) => {
+            tracing::error!("Failed to create link: {:?}", e);
+            (None, Some("Internal error. Please try again.".to_owned()))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    };
+    set_flash_and_redirect(
+        jar,
+        success.as_deref(),
+        error.as_deref(),
+        "/admin/short-links",
+    )
+}
+
+// ── Delete link ────────────────────────────────────────────────────────────
+
+/// POST /admin/links/:id/delete
+pub async fn delete_link(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    // Fetch the link first so we can check ownership and evict from cache
+    let link = match db::get_link_by_id(&state.db, id).await {
+        Ok(Some(l)) => l,
+        Ok(None) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Link not found."),
+                "/admin/short-links",
+            );
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch link {}: {:?}", id, e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Database error while looking up link."),
+                "/admin/short-links",
+            );
+        }
+    };
+
+    // Ownership check: non-admins can only delete their own links
+    if !auth.can_manage(link.workspace_id, link.user_id) {
+        return set_flash_and_redirect(jar, None, Some("Access denied."), "/admin/short-links");
+    }
+
+    // Aliases stay with the link in the trash but stop resolving
+    let aliases = db_aliases::get_aliases_for_link(&state.db, id)
+        .await
+        .unwrap_or_default();
+
+    let clicks = if state.notifier.is_enabled() {
+        db::count_link_clicks(&state.db, id).await.unwrap_or(0)
+    } else {
+        0
+    };
+
+    match db::delete_link(&state.db, id).await {
+        Ok(true) => {
+            state.cache.remove(link.domain_id, &link.short_code);
+            for alias in &aliases {
+                state.cache.remove(link.domain_id, &alias.short_code);
+            }
+            log_activity(&state, id, auth.user_id, ACTIVITY_TRASHED, None).await;
+            state.notifier.send(
+                notify::Event::LinkDeleted,
+                notify::LinkVars {
+                    code: link.short_code.clone(),
+                    short_url: state.short_url(link.domain_id, &link.short_code),
+                    url: link.original_url.clone(),
+                    title: link.title.clone(),
+                    clicks,
+                },
+            );
+            set_flash_and_redirect(
+                jar,
+                Some(&format!(
+                    "Link '{}' moved to the trash. It can be restored for {} days.",
+                    link.short_code,
+                    trash::RETENTION_DAYS
+                )),
+                None,
+                "/admin/short-links",
+            )
+        }
+        Ok(false) => {
+            set_flash_and_redirect(jar, None, Some("Link not found."), "/admin/short-links")
+        }
+        Err(e) => {
+            tracing::error!("Failed to delete link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to delete link."),
+                "/admin/short-links",
+            )
+        }
+    }
+}
+
+// ── Trash ──────────────────────────────────────────────────────────────────
+
+/// A deleted link as listed in the trash.
+struct TrashedLink {
+    link: Link,
+    short_url: String,
+    /// When the link will be purged for good.
+    purge_at: NaiveDateTime,
+}
+
+/// GET /admin/short-links/trash
+pub async fn trash(auth: AuthUser, State(state): State<Arc<AppState>>, jar: CookieJar) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let links = match db::get_trashed_links(&state.db, auth.scope()).await {
+        Ok(l) => l,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load trashed links").into_response();
+        }
+    };
+    let links = links
+        .into_iter()
+        .map(|link| TrashedLink {
+            short_url: state.short_url(link.domain_id, &link.short_code),
+            purge_at: link.deleted_at.unwrap_or_default()
+                + chrono::Duration::days(trash::RETENTION_DAYS),
+            link,
+        })
+        .collect();
+
+    let tmpl = TrashTemplate {
+        links,
+        retention_days: trash::RETENTION_DAYS,
+        flash_success,
+        flash_error,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/links/:id/restore — take a link back out of the trash.
+pub async fn restore_link(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    const TRASH_URL: &str = "/admin/short-links/trash";
+
+    let mut link = match db::get_trashed_link(&state.db, id).await {
+        Ok(Some(l)) if auth.can_manage(l.workspace_id, l.user_id) => l,
+        Ok(Some(_)) => return set_flash_and_redirect(jar, None, Some("Access denied."), TRASH_URL),
+        Ok(None) => return set_flash_and_redirect(jar, None, Some("Link not found."), TRASH_URL),
+        Err(e) => {
+            tracing::error!("Failed to fetch link {}: {:?}", id, e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Database error while looking up link."),
+                TRASH_URL,
+            );
+        }
+    };
+
+    // Trashed links don't count toward the owner's quota
+    if let Some(owner) = link.user_id {
+        match quota::links_exhausted(&state, owner).await {
+            Ok(Some(limit)) => {
+                return set_flash_and_redirect(
+                    jar,
+                    None,
+                    Some(&format!(
+                        "Link limit reached: the link's owner may have at most {limit} links."
+                    )),
+                    TRASH_URL,
+                );
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("Failed to check link quota: {:?}", e);
+                return set_flash_and_redirect(
+                    jar,
+                    None,
+                    Some("Failed to restore link."),
+                    TRASH_URL,
+                );
+            }
+        }
+    }
+
+    match db::restore_link(&state.db, id).await {
+        Ok(_) => {
+            link.deleted_at = None;
+            recache_link(&state, &link).await;
+            log_activity(&state, id, auth.user_id, ACTIVITY_RESTORED, None).await;
+            set_flash_and_redirect(
+                jar,
+                Some(&format!("Link '{}' restored.", link.short_code)),
+                None,
+                TRASH_URL,
+            )
+        }
+        Err(e) => {
+            tracing::error!("Failed to restore link {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to restore link."), TRASH_URL)
+        }
+    }
+}
+
+// ── Analytics ──────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct AnalyticsQuery {
+    /// Include clicks from `INTERNAL_IPS`, which are hidden by default.
+    #[serde(default)]
+    internal: bool,
+    /// Leave out clicks from hosting and datacenter networks.
+    #[serde(default)]
+    hide_datacenter: bool,
+}
+
+impl AnalyticsQuery {
+    fn filter(&self) -> db::TrafficFilter {
+        db::TrafficFilter {
+            include_internal: self.internal,
+            exclude_datacenter: self.hide_datacenter,
+        }
+    }
+
+    /// `?internal=true&hide_datacenter=true`, or whichever parts are set.
+    fn query_string(internal: bool, hide_datacenter: bool) -> String {
+        let params: Vec<&str> = [
+            internal.then_some("internal=true"),
+            hide_datacenter.then_some("hide_datacenter=true"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if params.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", params.join("&"))
+        }
+    }
+}
+
+/// GET /admin/links/:id/analytics
+pub async fn analytics(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<AnalyticsQuery>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let summary = match db::get_analytics(&state.db, id, query.filter()).await {
+        Ok(Some(s)) => s,
+        Ok(None) => {
+            return AppError::NotFound("Link not found.".into()).into_response();
+        }
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load analytics for link {id}"))
+                .into_response();
+        }
+    };
+
+    // Ownership check
+    if !auth.can_manage(summary.link.workspace_id, summary.link.user_id) {
+        return AppError::Forbidden("Access denied.".into()).into_response();
+    }
+
+    let base_url = match summary.link.domain_id {
+        Some(domain_id) => match db_domains::get_domain_by_id(&state.db, domain_id).await {
+            Ok(Some(d)) => d.base_url,
+            _ => state.config.base_url.clone(),
+        },
+        None => state.config.base_url.clone(),
+    };
+    let short_url = format!("{}/{}", base_url, summary.link.short_code);
+    let pixel_url = format!("{}/p/{}.gif", base_url, summary.link.short_code);
+    let qr_url = qr::url(&state, summary.link.domain_id, &summary.link.short_code);
+    let stats_url = summary.link.stats_token.as_ref().map(|token| {
+        format!(
+            "{}/stats/{}?token={}",
+            base_url, summary.link.short_code, token
+        )
+    });
+
+    let total = summary.total_clicks;
+    let top_browsers = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.browser.as_deref())),
+        total,
+    );
+    let top_os = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.os.as_deref())),
+        total,
+    );
+    let top_devices = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.device_type.as_deref())),
+        total,
+    );
+    let top_referers = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.referer.as_deref())),
+        total,
+    );
+    let top_sources = with_pct(count_sources(&summary.clicks), total);
+    let top_variants =
+        if summary.link.variants.is_some() || summary.clicks.iter().any(|c| c.variant.is_some()) {
+            let variants = summary
+                .clicks
+                .iter()
+                .map(|c| Some(c.variant.as_deref().unwrap_or("No variant")));
+            with_pct(count_field(variants), total)
+        } else {
+            Vec::new()
+        };
+    let top_language_rules = if summary.link.language_rules.is_some()
+        || summary.clicks.iter().any(|c| c.matched_language.is_some())
+    {
+        let languages = summary.clicks.iter().map(|c| {
+            Some(
+                c.matched_language
+                    .as_deref()
+                    .unwrap_or("Default destination"),
+            )
+        });
+        with_pct(count_field(languages), total)
+    } else {
+        Vec::new()
+    };
+    let top_countries = with_pct(count_countries(&summary.clicks), total);
+    let top_continents = with_pct(
+        count_field(
+            summary
+                .clicks
+                .iter()
+                .map(|c| c.continent.as_deref().and_then(geo::continent_name)),
+        ),
+        total,
+    );
+    let top_networks = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.isp.as_deref())),
+        total,
+    );
+    let screens: Vec<Option<String>> = summary
+        .clicks
+        .iter()
+        .map(|c| match (c.screen_width, c.screen_height) {
+            (Some(w), Some(h)) => Some(format!("{w}×{h}")),
+            _ => None,
+        })
+        .collect();
+    let top_screens = with_pct(count_field(screens.iter().map(Option::as_deref)), total);
+    let top_languages = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.language.as_deref())),
+        total,
+    );
+    let top_timezones = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.timezone.as_deref())),
+        total,
+    );
+
+    let heatmap = db::clicks_by_weekday_hour(&state.db, id, query.filter())
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load click heatmap for link {}: {:?}", id, e);
+            [[0; 24]; 7]
+        });
+    let busiest = heatmap.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let heatmap = db::WEEKDAYS
+        .into_iter()
+        .zip(heatmap)
+        .map(|(day, hours)| {
+            // Faint for empty hours, then scaled against the busiest hour.
+            let cells = hours
+                .iter()
+                .map(|&n| (n, if n == 0 { 6 } else { 15 + n * 85 / busiest }))
+                .collect();
+            (day, cells)
+        })
+        .collect();
+
+    let conversions = db_conversions::totals_for_link(&state.db, id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load conversions for link {}: {:?}", id, e);
+            Vec::new()
+        })
+        .into_iter()
+        .map(|c| {
+            let rate = if total > 0 {
+                format!("{:.1}%", c.clicks as f64 / total as f64 * 100.0)
+            } else {
+                "—".to_owned()
+            };
+            let value = c
+                .value
+                .map_or_else(|| "—".to_owned(), |v| format!("{v:.2}"));
+            (c.name, c.conversions, rate, value)
+        })
+        .collect();
+
+    let click_alerts = db_alerts::get_alerts_for_link(&state.db, id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load click alerts for link {}: {:?}", id, e);
+            Vec::new()
+        });
+
+    let aliases = db_aliases::get_aliases_for_link(&state.db, id)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load aliases for link {}: {:?}", id, e);
+            Vec::new()
+        })
+        .into_iter()
+        .map(|a| {
+            let url = format!("{}/{}", base_url, a.short_code);
+            (a.short_code, url)
+        })
+        .collect();
+
+    let mut domains = db_domains::get_all_domains(&state.db)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to load domains: {:?}", e);
+            Vec::new()
+        });
+    domains.retain(|d| d.serves(summary.link.workspace_id));
+
+    let thumbnail_url = if state.screenshots.is_enabled() {
+        db_screenshots::captured_at(&state.db, id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load screenshot for link {}: {:?}", id, e);
+                None
+            })
+            .map(|at| thumbnail_path(id, at))
+    } else {
+        None
+    };
+
+    let tmpl = AnalyticsTemplate {
+        summary,
+        short_url,
+        code_pattern: short_code::input_pattern(
+            state.config.unicode_short_codes,
+            state.config.emoji_short_codes,
+        ),
+        pixel_url,
+        qr_url,
+        stats_url,
+        fallback_checks: state.config.fallback_check_minutes > 0,
+        schedule_timezone: state.config.display_timezone.name(),
+        schedule_now: chrono::Utc::now()
+            .with_timezone(&state.config.display_timezone)
+            .format("%a %H:%M")
+            .to_string(),
+        rolled_up_until: db_rollups::last_rolled_up_day(&state.db, id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load rollups for link {}: {:?}", id, e);
+                None
+            }),
+        screenshots_enabled: state.screenshots.is_enabled(),
+        thumbnail_url,
+        include_internal: query.internal,
+        hide_datacenter: query.hide_datacenter,
+        traffic_query: AnalyticsQuery::query_string(query.internal, query.hide_datacenter),
+        internal_toggle_query: AnalyticsQuery::query_string(!query.internal, query.hide_datacenter),
+        datacenter_toggle_query: AnalyticsQuery::query_string(
+            query.internal,
+            !query.hide_datacenter,
+        ),
+        top_browsers,
+        top_os,
+        top_devices,
+        top_referers,
+        top_sources,
+        top_language_rules,
+        top_variants,
+        top_countries,
+        top_continents,
+        top_networks,
+        top_screens,
+        top_languages,
+        top_timezones,
+        heatmap,
+        conversions,
+        click_alerts,
+        aliases,
+        domains,
+        flash_success,
+        flash_error,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+// ── Link details ───────────────────────────────────────────────────────────
+
+/// GET /admin/links/:id
+///
+/// A link's page: its details and notes to edit, a summary of its clicks,
+/// its QR code, and a timeline of what happened to it. Full analytics and
+/// the redirect settings are a click away.
+pub async fn link_detail(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return AppError::NotFound(msg.into()).into_response(),
+    };
+
+    let ids = [id];
+    let week_start = chrono::Utc::now().date_naive() - chrono::Duration::days(6);
+    let filter = db::TrafficFilter::default();
+    let (totals, daily, activity, milestones) = match tokio::try_join!(
+        db::comparison_totals(&state.db, &ids, filter),
+        db::daily_clicks(&state.db, &ids, week_start, filter),
+        db_link_activity::get_activity(&state.db, id),
+        db_link_activity::click_milestones(&state.db, id),
+    ) {
+        Ok(results) => results,
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load details of link {id}"))
+                .into_response();
+        }
+    };
+    let totals = totals.into_iter().next().unwrap_or_default();
+    let week_clicks = daily.iter().map(|(_, _, clicks)| clicks).sum();
+
+    let owner = match link.user_id {
+        Some(user_id) => db_users::get_user_by_id(&state.db, user_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load owner of link {}: {:?}", id, e);
+                None
+            })
+            .map(|u| u.email),
+        None => None,
+    };
+    let mut timeline: Vec<TimelineEntry> = activity
+        .into_iter()
+        .map(|a| TimelineEntry {
+            at: a.created_at,
+            label: activity_label(&a.kind).to_owned(),
+            detail: a.detail,
+            by: a.user_email,
+        })
+        .collect();
+    timeline.extend(milestones.into_iter().map(|(clicks, at)| TimelineEntry {
+        at,
+        label: if clicks == 1 {
+            "First click".to_owned()
+        } else {
+            format!("Reached {clicks} clicks")
+        },
+        detail: None,
+        by: None,
+    }));
+    timeline.push(TimelineEntry {
+        at: link.created_at,
+        label: "Created".to_owned(),
+        detail: None,
+        by: owner,
+    });
+    // Stable, so the link's creation stays last when a change shares its second
+    timeline.sort_by_key(|entry| std::cmp::Reverse(entry.at));
+
+    let tmpl = LinkDetailTemplate {
+        short_url: state.short_url(link.domain_id, &link.short_code),
+        qr_url: qr::url(&state, link.domain_id, &link.short_code),
+        resolves: state.link_resolves(&link),
+        link,
+        totals,
+        week_clicks,
+        timeline,
+        flash_success,
+        flash_error,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// How a `link_activity.kind` reads on the timeline.
+fn activity_label(kind: &str) -> &'static str {
+    match kind {
+        ACTIVITY_EDITED => "Edited",
+        ACTIVITY_ACTIVATED => "Activated",
+        ACTIVITY_DEACTIVATED => "Deactivated",
+        ACTIVITY_ARCHIVED => "Archived",
+        ACTIVITY_UNARCHIVED => "Unarchived",
+        ACTIVITY_TRASHED => "Moved to the trash",
+        ACTIVITY_RESTORED => "Restored from the trash",
+        ACTIVITY_DESTINATION_DOWN => "Destination down",
+        ACTIVITY_DESTINATION_UP => "Destination back up",
+        _ => "Settings changed",
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LinkDetailsForm {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    notes: String,
+    /// Present when the "Active" switch is on.
+    is_active: Option<String>,
+}
+
+/// POST /admin/links/:id
+pub async fn save_link_details(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<LinkDetailsForm>,
+) -> Response {
+    let detail_url = format!("/admin/links/{id}");
+
+    let before = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let mut input = LinkInput::new(&state.config);
+    let mut link = before.clone();
+    link.title = input.title("title", Some(&form.title));
+    link.description = input.description("description", Some(&form.description));
+    link.notes = input.notes("notes", Some(&form.notes));
+    if let Err(invalid) = input.finish() {
+        return set_flash_and_redirect(jar, None, Some(&invalid.to_string()), &detail_url);
+    }
+    link.is_active = form.is_active.is_some();
+
+    let saved = async {
+        db::update_link_details(
+            &state.db,
+            id,
+            link.title.as_deref(),
+            link.description.as_deref(),
+            link.is_active,
+        )
+        .await?;
+        db::set_link_notes(&state.db, id, link.notes.as_deref()).await
+    };
+    match saved.await {
+        Ok(_) => {
+            recache_link(&state, &link).await;
+            log_edits(&state, &before, &link, auth.user_id).await;
+            set_flash_and_redirect(jar, Some("Link saved."), None, &detail_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update link {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to save link."), &detail_url)
+        }
+    }
+}
+
+// ── Comparison ─────────────────────────────────────────────────────────────
+
+/// Most links on one comparison chart; beyond this the lines are hard to
+/// tell apart.
+const MAX_COMPARED_LINKS: usize = 6;
+
+/// Periods the comparison chart can cover, in days.
+const COMPARE_PERIODS: [i64; 4] = [7, 30, 90, 365];
+
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    /// Link ids, comma-separated.
+    #[serde(default)]
+    links: String,
+    /// One more link id, from the page's "Add" form.
+    add: Option<String>,
+    days: Option<i64>,
+}
+
+/// One link on the comparison page.
+struct ComparedLink {
+    id: i64,
+    label: String,
+    short_url: String,
+    totals: ComparisonTotals,
+    /// Clicks within the charted period.
+    period_clicks: i64,
+    conversion_rate: String,
+    /// The link's line on the chart, one point per day.
+    points: String,
+    /// `links` for the page without this link.
+    without: String,
+}
+
+/// GET /admin/analytics/compare?links=1,2,3
+///
+/// Daily clicks and totals of several links side by side, e.g. variants of
+/// one campaign posted on different channels.
+pub async fn compare(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CompareQuery>,
+) -> Response {
+    let mut ids: Vec<i64> = Vec::new();
+    for id in query
+        .links
+        .split(',')
+        .chain(query.add.as_deref())
+        .filter_map(|id| id.trim().parse().ok())
+    {
+        if !ids.contains(&id) && ids.len() < MAX_COMPARED_LINKS {
+            ids.push(id);
+        }
+    }
+
+    let mut links = Vec::new();
+    for id in ids {
+        match db::get_link_by_id(&state.db, id).await {
+            Ok(Some(link)) if auth.can_manage(link.workspace_id, link.user_id) => links.push(link),
+            Ok(_) => {}
+            Err(e) => {
+                return AppError::internal(e, format!("Failed to load link {id} to compare"))
+                    .into_response();
+            }
+        }
+    }
+    let ids: Vec<i64> = links.iter().map(|l| l.id).collect();
+
+    let days = query
+        .days
+        .filter(|d| COMPARE_PERIODS.contains(d))
+        .unwrap_or(30);
+    let last_day = chrono::Utc::now().date_naive();
+    let first_day = last_day - chrono::Duration::days(days - 1);
+
+    let filter = db::TrafficFilter::default();
+    let (totals, daily) = match tokio::try_join!(
+        db::comparison_totals(&state.db, &ids, filter),
+        db::daily_clicks(&state.db, &ids, first_day, filter),
+    ) {
+        Ok(results) => results,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load link comparison").into_response();
+        }
+    };
+
+    // Each link's clicks per day of the period, oldest first
+    let mut series: HashMap<i64, Vec<i64>> =
+        ids.iter().map(|&id| (id, vec![0; days as usize])).collect();
+    for (id, day, clicks) in daily {
+        let Ok(day) = NaiveDate::parse_from_str(&day, "%Y-%m-%d") else {
+            continue;
+        };
+        let index = (day - first_day).num_days();
+        if let (Some(counts), Ok(index)) = (series.get_mut(&id), usize::try_from(index)) {
+            if let Some(count) = counts.get_mut(index) {
+                *count = clicks;
+            }
+        }
+    }
+    let peak = series.values().flatten().copied().max().unwrap_or(0).max(1);
+
+    let compared = links
+        .iter()
+        .map(|link| {
+            let totals = totals
+                .iter()
+                .find(|t| t.link_id == link.id)
+                .cloned()
+                .unwrap_or_default();
+            let counts = series.remove(&link.id).unwrap_or_default();
+            let points = counts
+                .iter()
+                .enumerate()
+                .map(|(x, &n)| format!("{x},{:.1}", 100.0 - n as f64 * 100.0 / peak as f64))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let conversion_rate = if totals.clicks > 0 {
+                format!(
+                    "{:.1}%",
+                    totals.converted_clicks as f64 / totals.clicks as f64 * 100.0
+                )
+            } else {
+                "—".to_owned()
+            };
+            let without = links
+                .iter()
+                .filter(|l| l.id != link.id)
+                .map(|l| l.id.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            ComparedLink {
+                id: link.id,
+                label: link
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| link.short_code.clone()),
+                short_url: state.short_url(link.domain_id, &link.short_code),
+                period_clicks: counts.iter().sum(),
+                totals,
+                conversion_rate,
+                points,
+                without,
+            }
+        })
+        .collect();
+
+    let candidates = if links.len() < MAX_COMPARED_LINKS {
+        db::get_all_links_with_stats(&state.db, auth.scope(), db::LinkSort::default())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load links to compare: {:?}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .filter(|l| !links.iter().any(|c| c.id == l.id))
+            .map(|l| (l.id, l.title.unwrap_or(l.short_code)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    CompareTemplate {
+        links: compared,
+        candidates,
+        links_param: links
+            .iter()
+            .map(|l| l.id.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+        days,
+        periods: COMPARE_PERIODS,
+        first_day,
+        last_day,
+        peak,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+// ── Reports ────────────────────────────────────────────────────────────────
+
+/// Days a report covers unless asked otherwise, and at most.
+const REPORT_DEFAULT_DAYS: i64 = 30;
+const REPORT_MAX_DAYS: i64 = 366;
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    /// First and last day (`YYYY-MM-DD`, UTC) of the report.
+    from: Option<String>,
+    to: Option<String>,
+}
+
+/// GET /admin/links/:id/report?from=…&to=…
+///
+/// A standalone, printable summary of a link's clicks over a date range, to
+/// send to people who don't use the panel. Browsers can save it as a PDF
+/// from the print dialog.
+pub async fn link_report(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<ReportQuery>,
+) -> Response {
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(link) => link,
+        Err(msg) => return AppError::NotFound(msg.into()).into_response(),
+    };
+
+    let date = |s: &Option<String>| {
+        s.as_deref()
+            .and_then(|s| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok())
+    };
+    let to = date(&query.to).unwrap_or_else(|| chrono::Utc::now().date_naive());
+    let mut from =
+        date(&query.from).unwrap_or(to - chrono::Duration::days(REPORT_DEFAULT_DAYS - 1));
+    if from > to {
+        from = to;
+    }
+    from = from.max(to - chrono::Duration::days(REPORT_MAX_DAYS - 1));
+
+    let scope = db_graphql::ClickScope {
+        link_id: Some(link.id),
+        from: from.and_hms_opt(0, 0, 0),
+        to: (to + chrono::Duration::days(1)).and_hms_opt(0, 0, 0),
+        ..Default::default()
+    };
+    let loaded = tokio::try_join!(
+        db_graphql::count_clicks(&state.db, &scope),
+        db_graphql::count_unique_visitors(&state.db, &scope),
+        db_graphql::clicks_by_day(&state.db, &scope),
+        db_graphql::clicks_by(&state.db, &scope, db_graphql::Dimension::Country, 10),
+        db_graphql::clicks_by(&state.db, &scope, db_graphql::Dimension::Referer, 10),
+        db_graphql::clicks_by(&state.db, &scope, db_graphql::Dimension::DeviceType, 10),
+        db_graphql::clicks_by(&state.db, &scope, db_graphql::Dimension::Browser, 10),
+    );
+    let (clicks, unique_visitors, by_day, countries, referers, devices, browsers) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load report for link {id}"))
+                .into_response();
+        }
+    };
+
+    let by_day: HashMap<String, i64> = by_day.into_iter().collect();
+    let daily: Vec<(NaiveDate, i64)> = from
+        .iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| {
+            let clicks = by_day
+                .get(&day.format("%Y-%m-%d").to_string())
+                .copied()
+                .unwrap_or(0);
+            (day, clicks)
+        })
+        .collect();
+    let peak = daily.iter().map(|(_, n)| *n).max().unwrap_or(0).max(1);
+    // Unknown values aren't listed, but still count toward the percentages
+    let known = |rows: Vec<(Option<String>, i64)>| {
+        let rows = rows
+            .into_iter()
+            .filter_map(|(name, count)| Some((name.filter(|n| !n.is_empty())?, count)))
+            .collect();
+        with_pct(rows, clicks)
+    };
+
+    ReportTemplate {
+        short_url: state.short_url(link.domain_id, &link.short_code),
+        link,
+        from,
+        to,
+        clicks,
+        unique_visitors,
+        daily,
+        peak,
+        top_countries: known(countries),
+        top_referers: known(referers),
+        top_devices: known(devices),
+        top_browsers: known(browsers),
+        generated_at: chrono::Utc::now().naive_utc(),
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+/// Default and allowed sizes, in degrees, of the click map's grid cells.
+const CLICK_MAP_CELL: f64 = 1.0;
+const CLICK_MAP_CELL_RANGE: std::ops::RangeInclusive<f64> = 0.1..=10.0;
+
+#[derive(Deserialize)]
+pub struct ClickMapQuery {
+    #[serde(default)]
+    internal: bool,
+    #[serde(default)]
+    hide_datacenter: bool,
+    /// Grid cell size in degrees; smaller cells give more, finer clusters.
+    cell: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ClickMap {
+    cell: f64,
+    clusters: Vec<MapCluster>,
+}
+
+#[derive(Serialize)]
+struct MapCluster {
+    lat: f64,
+    lon: f64,
+    clicks: i64,
+}
+
+/// GET /admin/links/:id/map
+///
+/// The link's located clicks clustered on a latitude/longitude grid, as JSON
+/// for the world map on the analytics page.
+pub async fn click_map(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<ClickMapQuery>,
+) -> Response {
+    if let Err(msg) = owned_link(&auth, &state, id).await {
+        return AppError::NotFound(msg.into()).into_response();
+    }
+    let cell = query
+        .cell
+        .filter(|c| CLICK_MAP_CELL_RANGE.contains(c))
+        .unwrap_or(CLICK_MAP_CELL);
+    let filter = db::TrafficFilter {
+        include_internal: query.internal,
+        exclude_datacenter: query.hide_datacenter,
+    };
+
+    match db::click_locations(&state.db, id, filter, cell).await {
+        Ok(rows) => Json(ClickMap {
+            cell,
+            clusters: rows
+                .into_iter()
+                .map(|(lat, lon, clicks)| MapCluster { lat, lon, clicks })
+                .collect(),
+        })
+        .into_response(),
+        Err(e) => {
+            AppError::internal(e, format!("Failed to load click map for link {id}")).into_response()
+        }
+    }
+}
+
+/// GET /admin/links/:id/qr.svg
+///
+/// A QR code for the link's short URL with `?src=qr`, so visits from print
+/// show up as their own source on the analytics page.
+pub async fn qr_code(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Response {
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(link) => link,
+        Err(msg) => return AppError::NotFound(msg.into()).into_response(),
+    };
+    let url = qr::url(&state, link.domain_id, &link.short_code);
+    match qr::svg(&url) {
+        Ok(svg) => ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response(),
+        Err(e) => {
+            AppError::internal(e, format!("Failed to draw QR code for link {id}")).into_response()
+        }
+    }
+}
+
+// ── Click log ──────────────────────────────────────────────────────────────
+
+/// Clicks shown per page of the click log.
+const CLICK_LOG_PAGE_SIZE: i64 = 50;
+
+#[derive(Deserialize, Serialize, Default)]
+pub struct ClickLogQuery {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    from: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    to: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    country: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    browser: String,
+    /// `only` or `hide`; anything else shows bots and people alike.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    bots: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    referer: String,
+    #[serde(skip_serializing)]
+    before: Option<i64>,
+    #[serde(skip_serializing)]
+    after: Option<i64>,
+}
+
+/// GET /admin/links/:id/clicks
+///
+/// Individual clicks on a link, newest first, with filters.
+pub async fn click_log(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Query(query): Query<ClickLogQuery>,
+) -> Response {
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let text = |s: &str| Some(s.trim().to_owned()).filter(|s| !s.is_empty());
+    let date = |s: &str| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok();
+    let filter = ClickLogFilter {
+        from: date(&query.from),
+        to: date(&query.to),
+        country: text(&query.country),
+        browser: text(&query.browser),
+        bots: match query.bots.as_str() {
+            "only" => Some(true),
+            "hide" => Some(false),
+            _ => None,
+        },
+        referer: text(&query.referer),
+    };
+    let page = match (query.before, query.after) {
+        (Some(id), _) => Page::Before(id),
+        (None, Some(id)) => Page::After(id),
+        (None, None) => Page::Newest,
+    };
+
+    // One extra row tells whether there is another page in that direction.
+    let loaded = tokio::try_join!(
+        db_click_log::list_clicks(&state.db, id, &filter, page, CLICK_LOG_PAGE_SIZE + 1),
+        db_click_log::count_clicks(&state.db, id, &filter),
+        db_click_log::facet_values(&state.db, id, Facet::Country),
+        db_click_log::facet_values(&state.db, id, Facet::Browser),
+    );
+    let (mut clicks, matching, countries, browsers) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load click log for link {id}"))
+                .into_response();
+        }
+    };
+
+    let more = clicks.len() as i64 > CLICK_LOG_PAGE_SIZE;
+    if more {
+        match page {
+            Page::After(_) => {
+                clicks.remove(0);
+            }
+            _ => {
+                clicks.pop();
+            }
+        }
+    }
+    let (has_newer, has_older) = match page {
+        Page::Newest => (false, more),
+        Page::Before(_) => (true, more),
+        Page::After(_) => (more, true),
+    };
+    let filters = serde_urlencoded::to_string(&query).unwrap_or_default();
+    let page_url = |cursor: &str, click_id: i64| {
+        let sep = if filters.is_empty() { "" } else { "&" };
+        format!("/admin/links/{id}/clicks?{filters}{sep}{cursor}={click_id}")
+    };
+    let newer_url = clicks
+        .first()
+        .filter(|_| has_newer)
+        .map(|c| page_url("after", c.id));
+    let older_url = clicks
+        .last()
+        .filter(|_| has_older)
+        .map(|c| page_url("before", c.id));
+
+    let options = |values: Vec<String>, selected: &Option<String>| {
+        values
+            .into_iter()
+            .map(|v| {
+                let is_selected = selected.as_deref() == Some(v.as_str());
+                (v, is_selected)
+            })
+            .collect()
+    };
+
+    ClickLogTemplate {
+        short_url: state.short_url(link.domain_id, &link.short_code),
+        link,
+        clicks,
+        matching,
+        from: filter.from.map(|d| d.to_string()).unwrap_or_default(),
+        to: filter.to.map(|d| d.to_string()).unwrap_or_default(),
+        referer: filter.referer.clone().unwrap_or_default(),
+        bots: query.bots.clone(),
+        countries: options(countries, &filter.country),
+        browsers: options(browsers, &filter.browser),
+        newer_url,
+        older_url,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+// ── Public stats sharing ───────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ShareStatsForm {
+    /// `enable` issues a fresh token (revoking any old one); `disable` clears it.
+    action: String,
+}
+
+/// POST /admin/links/:id/share
+pub async fn share_stats(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<ShareStatsForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let link = match db::get_link_by_id(&state.db, id).await {
+        Ok(Some(l)) => l,
+        Ok(None) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Link not found."),
+                "/admin/short-links",
+            );
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch link {}: {:?}", id, e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Database error while looking up link."),
+                "/admin/short-links",
+            );
+        }
+    };
+
+    if !auth.can_manage(link.workspace_id, link.user_id) {
+        return set_flash_and_redirect(jar, None, Some("Access denied."), "/admin/short-links");
+    }
+
+    let (token, message) = match form.action.as_str() {
+        "enable" => (
+            Some(random_code(32)),
+            "Public stats link created. Any previous link no longer works.",
+        ),
+        "disable" => (None, "Public stats link disabled."),
+        _ => {
+            return set_flash_and_redirect(jar, None, Some("Unknown action."), &analytics_url);
+        }
+    };
+
+    match db::set_stats_token(&state.db, id, token.as_deref()).await {
+        Ok(_) => set_flash_and_redirect(jar, Some(message), None, &analytics_url),
+        Err(e) => {
+            tracing::error!("Failed to update stats token for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update public stats link."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Archive ────────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ArchiveForm {
+    /// `false` restores the link to the default list.
+    archived: bool,
+}
+
+/// POST /admin/links/:id/archive
+pub async fn set_archived(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<ArchiveForm>,
+) -> Response {
+    // Back to the list the link was in
+    let list_url = if form.archived {
+        "/admin/short-links"
+    } else {
+        "/admin/short-links?archived=true"
+    };
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), list_url),
+    };
+
+    match db::set_archived(&state.db, id, form.archived).await {
+        Ok(_) => {
+            link.archived_at = form.archived.then(|| chrono::Utc::now().naive_utc());
+            recache_link(&state, &link).await;
+            let kind = if form.archived {
+                ACTIVITY_ARCHIVED
+            } else {
+                ACTIVITY_UNARCHIVED
+            };
+            log_activity(&state, id, auth.user_id, kind, None).await;
+            let msg = if form.archived {
+                format!("Link '{}' archived.", link.short_code)
+            } else {
+                format!("Link '{}' unarchived.", link.short_code)
+            };
+            set_flash_and_redirect(jar, Some(&msg), None, list_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to archive link {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to update link."), list_url)
+        }
+    }
+}
+
+// ── Redirect mode ──────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct RedirectModeForm {
+    /// `client` serves a page that reports client details before
+    /// redirecting; `server` restores the plain 302.
+    mode: String,
+}
+
+/// POST /admin/links/:id/redirect-mode
+pub async fn set_redirect_mode(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<RedirectModeForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let (enabled, message) = match form.mode.as_str() {
+        "client" => (
+            true,
+            "Client-side redirect enabled. New clicks record screen size, language, and time zone.",
+        ),
+        "server" => (false, "Switched back to a standard redirect."),
+        _ => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Unknown redirect mode."),
+                &analytics_url,
+            );
+        }
+    };
+
+    match db::set_client_redirect(&state.db, id, enabled).await {
+        Ok(_) => {
+            link.client_redirect = enabled;
+            recache_link(&state, &link).await;
+            let detail = if enabled {
+                "Switched to a client-side redirect"
+            } else {
+                "Switched to a standard redirect"
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(detail)).await;
+            set_flash_and_redirect(jar, Some(message), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update redirect mode for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update redirect mode."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Link domain ────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct LinkDomainForm {
+    /// Domain ID; empty for the primary domain.
+    domain_id: String,
+}
+
+/// POST /admin/links/:id/domain
+/// Move a link and its aliases to another short domain. The old short URL
+/// stops resolving, since codes only resolve on their own domain.
+pub async fn set_link_domain(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<LinkDomainForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let domain_id = match form.domain_id.trim() {
+        "" => None,
+        raw => match raw.parse::<i64>().ok().and_then(|d| state.domains.get(d)) {
+            Some(domain) if domain.serves(link.workspace_id) => Some(domain.id),
+            _ => {
+                return set_flash_and_redirect(jar, None, Some("Unknown domain."), &analytics_url);
+            }
+        },
+    };
+    if domain_id == link.domain_id {
+        return set_flash_and_redirect(jar, None, None, &analytics_url);
+    }
+
+    match db::set_link_domain(&state.db, id, domain_id).await {
+        Ok(_) => {
+            // Drop the entries under the old domain before caching the new ones
+            let aliases = db_aliases::get_aliases_for_link(&state.db, id)
+                .await
+                .unwrap_or_default();
+            state.cache.remove(link.domain_id, &link.short_code);
+            for alias in &aliases {
+                state.cache.remove(link.domain_id, &alias.short_code);
+            }
+            link.domain_id = domain_id;
+            recache_link(&state, &link).await;
+            let short_url = state.short_url(domain_id, &link.short_code);
+            let detail = format!("Moved to {short_url}");
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            let message = format!("Link moved. It is now served at {short_url}.");
+            set_flash_and_redirect(jar, Some(&message), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update domain for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update the link's domain."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Country restrictions ───────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct CountryRulesForm {
+    /// Comma-separated country codes, e.g. `US, CA`. Empty clears the list.
+    allow: String,
+    deny: String,
+}
+
+/// POST /admin/links/:id/countries
+pub async fn set_country_rules(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<CountryRulesForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let rules = match CountryRules::parse(&form.allow, &form.deny) {
+        Ok(rules) => rules,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+    };
+    let allow = Some(rules.allow.join(",")).filter(|s| !s.is_empty());
+    let deny = Some(rules.deny.join(",")).filter(|s| !s.is_empty());
+
+    match db::set_country_rules(&state.db, id, allow.as_deref(), deny.as_deref()).await {
+        Ok(_) => {
+            link.allow_countries = allow;
+            link.deny_countries = deny;
+            recache_link(&state, &link).await;
+            let (msg, detail) = if rules.is_empty() {
+                (
+                    "Country restrictions removed.",
+                    "Removed country restrictions".to_owned(),
+                )
+            } else {
+                let mut lists = Vec::new();
+                if !rules.allow.is_empty() {
+                    lists.push(format!("only {}", rules.allow.join(", ")));
+                }
+                if !rules.deny.is_empty() {
+                    lists.push(format!("not {}", rules.deny.join(", ")));
+                }
+                (
+                    "Country restrictions saved.",
+                    format!("Restricted to countries: {}", lists.join("; ")),
+                )
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update country rules for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update country restrictions."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Language rules ─────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct LanguageRulesForm {
+    /// One `<language> <url>` per line, e.g. `de https://example.com/de/`.
+    /// Empty removes every rule.
+    rules: String,
+}
+
+/// POST /admin/links/:id/languages
+pub async fn set_language_rules(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<LanguageRulesForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let mut rules = match LanguageRules::parse(&form.rules) {
+        Ok(rules) => rules,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+    };
+    for rule in &mut rules.rules {
+        match rule_destination(&state, &rule.language, &rule.url).await {
+            Ok(url) => rule.url = url,
+            Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+        }
+    }
+    let text = Some(rules.to_text()).filter(|s| !s.is_empty());
+
+    match db::set_language_rules(&state.db, id, text.as_deref()).await {
+        Ok(_) => {
+            link.language_rules = text;
+            recache_link(&state, &link).await;
+            let (msg, detail) = if rules.is_empty() {
+                (
+                    "Language rules removed.",
+                    "Removed language rules".to_owned(),
+                )
+            } else {
+                let languages: Vec<_> = rules.rules.iter().map(|r| r.language.as_str()).collect();
+                (
+                    "Language rules saved.",
+                    format!("Language rules: {}", languages.join(", ")),
+                )
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update language rules for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update language rules."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+/// A language or schedule rule's URL, a fallback or a variant, checked and
+/// normalized
+/// like a link's destination, or the message to show; `label` names the
+/// rule in it.
+async fn rule_destination(state: &AppState, label: &str, url: &str) -> Result<String, String> {
+    let mut input = LinkInput::new(&state.config);
+    let normalized = input.url("rules", url);
+    if let Err(invalid) = input.finish() {
+        return Err(format!("{label}: {invalid}"));
+    }
+    let url = normalized.expect("validated URL");
+    match state.check_destination(&url).await {
+        Ok(()) => Ok(url),
+        Err(DestinationError::Db(e)) => {
+            tracing::error!("Failed to check destination for loops: {:?}", e);
+            Err("Internal error. Please try again.".into())
+        }
+        Err(e) => Err(format!("{label}: {e}")),
+    }
+}
+
+// ── Schedule rules ─────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ScheduleRulesForm {
+    /// One `<days> <start>-<end> <url>` per line, e.g.
+    /// `mon-fri 9-17 https://example.com/book`. Empty removes every rule.
+    rules: String,
+}
+
+/// POST /admin/links/:id/schedule
+pub async fn set_schedule_rules(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<ScheduleRulesForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let mut rules = match ScheduleRules::parse(&form.rules) {
+        Ok(rules) => rules,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+    };
+    for rule in &mut rules.rules {
+        match rule_destination(&state, &rule.when, &rule.url).await {
+            Ok(url) => rule.url = url,
+            Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+        }
+    }
+    let text = Some(rules.to_text()).filter(|s| !s.is_empty());
+
+    match db::set_schedule_rules(&state.db, id, text.as_deref()).await {
+        Ok(_) => {
+            link.schedule_rules = text;
+            recache_link(&state, &link).await;
+            let (msg, detail) = if rules.is_empty() {
+                ("Schedule removed.", "Removed schedule rules".to_owned())
+            } else {
+                let windows: Vec<_> = rules.rules.iter().map(|r| r.when.as_str()).collect();
+                (
+                    "Schedule saved.",
+                    format!("Schedule rules: {}", windows.join("; ")),
+                )
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update schedule rules for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update the schedule."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Fallback destination ───────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct FallbackForm {
+    /// Empty removes the fallback.
+    url: String,
+}
+
+/// POST /admin/links/:id/fallback
+pub async fn set_fallback_url(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<FallbackForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let url = match form.url.trim() {
+        "" => None,
+        url => match rule_destination(&state, "Fallback", url).await {
+            Ok(url) => Some(url),
+            Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+        },
+    };
+
+    match db::set_fallback_url(&state.db, id, url.as_deref()).await {
+        Ok(_) => {
+            if url.is_none() {
+                link.destination_down_since = None;
+            }
+            let (msg, detail) = match &url {
+                Some(url) => ("Fallback saved.", format!("Fallback: {url}")),
+                None => ("Fallback removed.", "Removed the fallback".to_owned()),
+            };
+            link.fallback_url = url;
+            recache_link(&state, &link).await;
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update fallback for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update the fallback."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Variants ───────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct VariantsForm {
+    /// One `<weight> <url>` per line, e.g. `70 https://example.com/a`.
+    /// Empty removes the variants.
+    variants: String,
+    /// Present when the checkbox is ticked.
+    sticky: Option<String>,
+}
+
+/// POST /admin/links/:id/variants
+pub async fn set_variants(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<VariantsForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let mut variants = match Variants::parse(&form.variants) {
+        Ok(variants) => variants,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+    };
+    for (i, variant) in variants.variants.iter_mut().enumerate() {
+        let label = format!("Variant {}", i + 1);
+        match rule_destination(&state, &label, &variant.url).await {
+            Ok(url) => variant.url = url,
+            Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url),
+        }
+    }
+    let text = Some(variants.to_text()).filter(|s| !s.is_empty());
+    let sticky = form.sticky.is_some();
+
+    match db::set_variants(&state.db, id, text.as_deref(), sticky).await {
+        Ok(_) => {
+            link.variants = text;
+            link.sticky_variants = sticky;
+            recache_link(&state, &link).await;
+            let (msg, detail) = if variants.is_empty() {
+                ("Variants removed.", "Removed variants".to_owned())
+            } else {
+                let weights: Vec<_> = variants
+                    .variants
+                    .iter()
+                    .map(|v| v.weight.to_string())
+                    .collect();
+                (
+                    "Variants saved.",
+                    format!(
+                        "{} variants weighted {}{}",
+                        variants.variants.len(),
+                        weights.join("/"),
+                        if sticky { ", sticky" } else { "" }
+                    ),
+                )
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update variants for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update variants."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Click limit ────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ClickLimitForm {
+    /// Empty or 0 removes the limit.
+    per_minute: String,
+    /// `"page"` to serve the waiting page, anything else for a 429.
+    over_limit: String,
+}
+
+/// POST /admin/links/:id/click-limit
+pub async fn set_click_limit(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<ClickLimitForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let per_minute = match form.per_minute.trim() {
+        "" => None,
+        value => match value.parse::<i64>() {
+            Ok(0) => None,
+            Ok(n) if n > 0 => Some(n),
+            _ => {
+                return set_flash_and_redirect(
+                    jar,
+                    None,
+                    Some("Click limit must be a whole number of clicks per minute."),
+                    &analytics_url,
+                );
+            }
+        },
+    };
+    let page = form.over_limit == "page";
+
+    match db::set_click_limit(&state.db, id, per_minute, page).await {
+        Ok(_) => {
+            link.click_limit_per_minute = per_minute;
+            link.click_limit_page = page;
+            recache_link(&state, &link).await;
+            let (msg, detail) = match per_minute {
+                Some(n) => (
+                    "Click limit saved.",
+                    format!("Limited to {n} clicks per minute"),
+                ),
+                None => ("Click limit removed.", "Removed the click limit".to_owned()),
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update click limit for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update the click limit."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Social preview ─────────────────────────────────────────────────────────
+
+/// Longest social title accepted; unfurlers cut them much shorter anyway.
+const MAX_SOCIAL_TITLE: usize = 200;
+const MAX_SOCIAL_DESCRIPTION: usize = 500;
+
+#[derive(Deserialize)]
+pub struct SocialPreviewForm {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    image_url: String,
+}
+
+/// POST /admin/links/:id/social
+pub async fn set_social_preview(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<SocialPreviewForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let mut link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let field = |value: &str| Some(value.trim().to_owned()).filter(|v| !v.is_empty());
+    let title = field(&form.title);
+    let description = field(&form.description);
+    let image_url = field(&form.image_url);
+
+    let too_long = |value: &Option<String>, max: usize| {
+        value.as_ref().is_some_and(|v| v.chars().count() > max)
+    };
+    let error = if too_long(&title, MAX_SOCIAL_TITLE) {
+        Some(format!(
+            "Title must be at most {MAX_SOCIAL_TITLE} characters."
+        ))
+    } else if too_long(&description, MAX_SOCIAL_DESCRIPTION) {
+        Some(format!(
+            "Description must be at most {MAX_SOCIAL_DESCRIPTION} characters."
+        ))
+    } else if image_url
+        .as_deref()
+        .is_some_and(|u| destination::normalize(u, false).is_none())
+    {
+        Some("Image URL must be a full http:// or https:// URL.".to_owned())
+    } else {
+        None
+    };
+    if let Some(msg) = error {
+        return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url);
+    }
+
+    match db::set_social_preview(
+        &state.db,
+        id,
+        title.as_deref(),
+        description.as_deref(),
+        image_url.as_deref(),
+    )
+    .await
+    {
+        Ok(_) => {
+            link.social_title = title;
+            link.social_description = description;
+            link.social_image_url = image_url;
+            recache_link(&state, &link).await;
+            let (msg, detail) = if link.has_social_preview() {
+                ("Social preview saved.", "Changed the social preview")
+            } else {
+                ("Social preview removed.", "Removed the social preview")
+            };
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(detail)).await;
+            set_flash_and_redirect(jar, Some(msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to update social preview for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to update social preview."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Screenshots ────────────────────────────────────────────────────────────
+
+/// When each link's thumbnail was captured, for the image URLs in link
+/// listings. Empty when screenshots are off.
+#[derive(Default)]
+struct Thumbnails(HashMap<i64, NaiveDateTime>);
+
+impl Thumbnails {
+    async fn load(state: &AppState) -> Self {
+        if !state.screenshots.is_enabled() {
+            return Self::default();
+        }
+        match db_screenshots::captured_times(&state.db).await {
+            Ok(times) => Self(times),
+            Err(e) => {
+                tracing::error!("Failed to load screenshot times: {:?}", e);
+                Self::default()
+            }
+        }
+    }
+
+    fn url(&self, link_id: &i64) -> Option<String> {
+        self.0.get(link_id).map(|at| thumbnail_path(*link_id, *at))
+    }
+}
+
+/// A thumbnail's path, versioned by its capture time so browsers can keep
+/// it cached until it is replaced.
+fn thumbnail_path(link_id: i64, captured_at: NaiveDateTime) -> String {
+    format!(
+        "/admin/links/{link_id}/screenshot?v={}",
+        captured_at.and_utc().timestamp()
+    )
+}
+
+/// GET /admin/links/:id/screenshot
+pub async fn screenshot(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Response {
+    if let Err(msg) = owned_link(&auth, &state, id).await {
+        return AppError::NotFound(msg.into()).into_response();
+    }
+    match db_screenshots::get_screenshot(&state.db, id).await {
+        Ok(Some(shot)) => (
+            [
+                (header::CONTENT_TYPE, shot.content_type),
+                (
+                    header::CACHE_CONTROL,
+                    "private, max-age=31536000".to_owned(),
+                ),
+                (
+                    header::LAST_MODIFIED,
+                    http_cache::last_modified(shot.captured_at),
+                ),
+            ],
+            shot.image,
+        )
+            .into_response(),
+        Ok(None) => AppError::NotFound("No screenshot".into()).into_response(),
+        Err(e) => AppError::internal(e, format!("Failed to load screenshot for link {id}"))
+            .into_response(),
+    }
+}
+
+/// POST /admin/links/:id/screenshot
+///
+/// Capture the link's thumbnail again, e.g. after its destination changed.
+pub async fn refresh_screenshot(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    match state.screenshots.capture_now(&link).await {
+        Ok(()) => set_flash_and_redirect(jar, Some("Screenshot updated."), None, &analytics_url),
+        Err(CaptureError::Db(e)) => {
+            tracing::error!("Failed to save screenshot for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to save the screenshot."),
+                &analytics_url,
+            )
+        }
+        Err(e) => {
+            tracing::warn!("Screenshot of link {} failed: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some(&e.to_string()), &analytics_url)
+        }
+    }
+}
+
+// ── Favicons ───────────────────────────────────────────────────────────────
+
+/// Shown for hosts without a favicon of their own: a plain globe.
+const FALLBACK_FAVICON: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 16 16" fill="none" stroke="#64748b" stroke-width="1.2"><circle cx="8" cy="8" r="6.5"/><path d="M1.5 8h13M8 1.5c2 2 2 11 0 13M8 1.5c-2 2-2 11 0 13"/></svg>"##;
+
+/// GET /admin/favicons/:host
+///
+/// The favicon of a destination host, fetched from the site the first time
+/// it's asked for.
+pub async fn favicon(
+    _auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(host): Path<String>,
+) -> Response {
+    let valid_host = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '[' | ']' | ':'));
+    let Some(favicons) = state.favicons.as_ref().filter(|_| valid_host) else {
+        return AppError::NotFound("Not found".into()).into_response();
+    };
+
+    let (content_type, body) = match favicons.get(&host.to_ascii_lowercase()).await {
+        Ok(Some((content_type, image))) => (content_type, image),
+        Ok(None) => ("image/svg+xml".to_owned(), FALLBACK_FAVICON.into()),
+        Err(e) => {
+            tracing::error!("Failed to load favicon for {}: {:?}", host, e);
+            ("image/svg+xml".to_owned(), FALLBACK_FAVICON.into())
+        }
+    };
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "private, max-age=86400".to_owned()),
+            // Icons come from other sites; an SVG must not run scripts here
+            (
+                header::CONTENT_SECURITY_POLICY,
+                "default-src 'none'; style-src 'unsafe-inline'; sandbox".to_owned(),
+            ),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// ── Click alerts ───────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ClickAlertForm {
+    threshold: String,
+}
+
+/// POST /admin/links/:id/alerts
+pub async fn add_click_alert(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<ClickAlertForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let threshold = match form.threshold.trim().parse::<i64>() {
+        Ok(n) if n > 0 => n,
+        _ => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Enter a whole number of clicks."),
+                &analytics_url,
+            );
+        }
+    };
+
+    match db::count_link_clicks(&state.db, link.id).await {
+        Ok(clicks) if threshold <= clicks => {
+            let msg = format!("This link already has {clicks} clicks.");
+            return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url);
+        }
+        Ok(_) => {}
+        Err(e) => {
+            tracing::error!("Failed to count clicks for link {}: {:?}", id, e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to add click alert."),
+                &analytics_url,
+            );
+        }
+    }
+
+    match db_alerts::create_alert(&state.db, link.id, threshold).await {
+        Ok(true) => {
+            state.click_alerts.add(link.id, threshold);
+            let msg = format!("You'll be notified at {threshold} clicks.");
+            set_flash_and_redirect(jar, Some(&msg), None, &analytics_url)
+        }
+        Ok(false) => set_flash_and_redirect(
             jar,
             None,
-            Some("URL must start with http:// or https://"),
-            "/admin/short-links",
-        );
+            Some("That alert already exists."),
+            &analytics_url,
+        ),
+        Err(e) => {
+            tracing::error!("Failed to create click alert for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to add click alert."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+/// POST /admin/links/:id/alerts/:threshold/delete
+pub async fn delete_click_alert(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((id, threshold)): Path<(i64, i64)>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    state.click_alerts.remove(link.id, threshold);
+    match db_alerts::delete_alert(&state.db, link.id, threshold).await {
+        Ok(_) => set_flash_and_redirect(jar, Some("Click alert removed."), None, &analytics_url),
+        Err(e) => {
+            tracing::error!("Failed to delete click alert for link {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to remove click alert."),
+                &analytics_url,
+            )
+        }
+    }
+}
+
+// ── Aliases ────────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct AliasForm {
+    code: String,
+}
+
+/// POST /admin/links/:id/aliases
+pub async fn add_alias(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<AliasForm>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    let mut input = LinkInput::new(&state.config);
+    let code = input.code("code", Some(&form.code));
+    let code = match (code, input.finish()) {
+        (Some(code), Ok(())) => code,
+        (_, Err(invalid)) => {
+            let msg = invalid.to_string();
+            return set_flash_and_redirect(jar, None, Some(&msg), &analytics_url);
+        }
+        (None, Ok(())) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Alias must not be empty."),
+                &analytics_url,
+            );
+        }
+    };
+    match db_bio::bio_slug_exists(&state.db, &code).await {
+        Ok(true) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("That alias conflicts with an existing links page slug."),
+                &analytics_url,
+            );
+        }
+        Ok(false) => {}
+        Err(e) => {
+            tracing::error!(
+                "DB error checking bio slug collision for '{}': {:?}",
+                code,
+                e
+            );
+        }
+    }
+
+    match db_aliases::create_alias(&state.db, link.id, &code).await {
+        Ok(()) => {
+            if state.link_resolves(&link) {
+                state
+                    .cache
+                    .set(link.domain_id, &code, CachedLink::from(&link));
+            }
+            let detail = format!("Added the alias {code}");
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            let msg = format!("Alias added: {}", state.short_url(link.domain_id, &code));
+            set_flash_and_redirect(jar, Some(&msg), None, &analytics_url)
+        }
+        Err(e) if db::is_unique_violation(&e) => set_flash_and_redirect(
+            jar,
+            None,
+            Some("That code is already taken. Try another."),
+            &analytics_url,
+        ),
+        Err(e) => {
+            tracing::error!("Failed to add alias for link {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to add alias."), &analytics_url)
+        }
+    }
+}
+
+/// POST /admin/links/:id/aliases/:code/delete
+pub async fn delete_alias(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path((id, code)): Path<(i64, String)>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
+
+    match db_aliases::delete_alias(&state.db, link.id, &code).await {
+        Ok(true) => {
+            state.cache.remove(link.domain_id, &code);
+            let detail = format!("Removed the alias {code}");
+            log_activity(&state, id, auth.user_id, ACTIVITY_SETTINGS, Some(&detail)).await;
+            let msg = format!("Alias '{code}' removed.");
+            set_flash_and_redirect(jar, Some(&msg), None, &analytics_url)
+        }
+        Ok(false) => set_flash_and_redirect(jar, None, Some("Alias not found."), &analytics_url),
+        Err(e) => {
+            tracing::error!("Failed to delete alias '{}': {:?}", code, e);
+            set_flash_and_redirect(jar, None, Some("Failed to remove alias."), &analytics_url)
+        }
     }
+}
 
-    // Determine the short code to use
-    let short_code = match form
-        .custom_code
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-    {
-        Some(code) => {
-            // Validate custom code: alphanumeric + hyphens only
-            if !code.chars().all(|c| c.is_alphanumeric() || c == '-') {
-                return set_flash_and_redirect(
-                    jar,
-                    None,
-                    Some("Custom code may only contain letters, numbers, and hyphens."),
-                    "/admin/short-links",
-                );
-            }
-            // Ensure custom code doesn't collide with a bio page slug
-            match db_bio::bio_slug_exists(&state.db, code).await {
-                Ok(true) => {
-                    return set_flash_and_redirect(
-                        jar,
-                        None,
-                        Some("That code conflicts with an existing links page slug."),
-                        "/admin/short-links",
-                    );
-                }
-                Ok(false) => {}
-                Err(e) => {
-                    tracing::error!(
-                        "DB error checking bio slug collision for '{}': {:?}",
-                        code,
-                        e
-                    );
-                }
-            }
-            code.to_owned()
-        }
-        None => generate_unique_code(&state.db).await,
-    };
+// ── Duplicate ──────────────────────────────────────────────────────────────
 
-    let title = form
-        .title
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .map(str::to_owned);
+/// POST /admin/links/:id/duplicate
+///
+/// Copy a link's destination and settings (see `db::duplicate_link`) to a new
+/// link with a generated code, and open the copy.
+pub async fn duplicate_link(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    let analytics_url = format!("/admin/links/{id}/analytics");
 
-    let description = form
-        .description
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-        .map(str::to_owned);
+    let link = match owned_link(&auth, &state, id).await {
+        Ok(l) => l,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/short-links"),
+    };
 
-    match db::create_link(
-        &state.db,
-        &short_code,
-        &url,
-        title.as_deref(),
-        description.as_deref(),
-        auth.user_id,
-    )
-    .await
-    {
-        Ok(link) => {
-            // Update the cache immediately
-            state.cache.set(&link.short_code, &link.original_url);
+    match quota::links_exhausted(&state, auth.user_id).await {
+        Ok(Some(limit)) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some(&format!(
+                    "Link limit reached: your account may have at most {limit} links."
+                )),
+                &analytics_url,
+            );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to check link quota: {:?}", e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to duplicate link."),
+                &analytics_url,
+            );
+        }
+    }
+
+    let short_code = generate_unique_code(&state.db, &state.config).await;
+    match db::duplicate_link(&state.db, link.id, &short_code, auth.user_id).await {
+        Ok(copy) => {
+            if state.link_resolves(&copy) {
+                state
+                    .cache
+                    .set(copy.domain_id, &copy.short_code, CachedLink::from(&copy));
+            }
+            let short_url = state.link_created(&copy);
             set_flash_and_redirect(
                 jar,
                 Some(&format!(
-                    "Link created: {}/{}",
-                    state.config.base_url, link.short_code
+                    "Copy of '{}' created: {short_url}",
+                    link.short_code
                 )),
                 None,
-                "/admin/short-links",
+                &format!("/admin/links/{}/analytics", copy.id),
             )
         }
         Err(e) => {
-            tracing::error!("Failed to create link: {:?}", e);
-            let msg = if e.to_string().contains("UNIQUE") {
-                "That short code is already taken. Try another.".to_owned()
-            } else {
-                format!("Database error: {e}")
-            };
-            set_flash_and_redirect(jar, None, Some(&msg), "/admin/short-links")
+            tracing::error!("Failed to duplicate link {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to duplicate link."), &analytics_url)
         }
     }
 }
 
-// ── Delete link ────────────────────────────────────────────────────────────
+// ── Merge ──────────────────────────────────────────────────────────────────
 
-/// POST /admin/links/:id/delete
-pub async fn delete_link(
-    auth: AuthUser,
+#[derive(Deserialize)]
+pub struct MergeForm {
+    /// Short code of the duplicate link to merge into this one.
+    code: String,
+    /// Checkbox: keep the duplicate's code working as an alias.
+    keep_code: Option<String>,
+}
+
+/// POST /admin/links/:id/merge
+///
+/// Admin only. Moves the clicks and aliases of the link with `code` onto
+/// link `id`, then deletes it.
+pub async fn merge_link(
+    admin: AdminUser,
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
     Path(id): Path<i64>,
+    Form(form): Form<MergeForm>,
 ) -> Response {
-    // Fetch the link first so we can check ownership and evict from cache
-    let link = match db::get_link_by_id(&state.db, id).await {
-        Ok(Some(l)) => l,
-        Ok(None) => {
+    let analytics_url = format!("/admin/links/{id}/analytics");
+
+    let into = match db::get_link_by_id(&state.db, id).await {
+        Ok(Some(l)) if l.workspace_id == admin.workspace_id => l,
+        Ok(_) => {
             return set_flash_and_redirect(
                 jar,
                 None,
@@ -1017,100 +4084,181 @@ pub async fn delete_link(
         }
     };
 
-    // Ownership check: non-admins can only delete their own links
-    if !auth.is_admin() && link.user_id != Some(auth.user_id) {
-        return set_flash_and_redirect(jar, None, Some("Access denied."), "/admin/short-links");
-    }
-
-    match db::delete_link(&state.db, id).await {
-        Ok(true) => {
-            state.cache.remove(&link.short_code);
-            set_flash_and_redirect(
+    let from = match db::find_link_by_code(&state.db, form.code.trim()).await {
+        Ok(Some(l)) if l.id == into.id => {
+            return set_flash_and_redirect(
                 jar,
-                Some(&format!("Link '{}' deleted.", link.short_code)),
                 None,
-                "/admin/short-links",
-            )
+                Some("A link can't be merged into itself."),
+                &analytics_url,
+            );
         }
-        Ok(false) => {
-            set_flash_and_redirect(jar, None, Some("Link not found."), "/admin/short-links")
+        Ok(Some(l)) if l.workspace_id == admin.workspace_id => l,
+        Ok(_) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("No link has that short code."),
+                &analytics_url,
+            );
         }
         Err(e) => {
-            tracing::error!("Failed to delete link {}: {:?}", id, e);
-            set_flash_and_redirect(
+            tracing::error!("Failed to fetch link '{}': {:?}", form.code, e);
+            return set_flash_and_redirect(
                 jar,
                 None,
-                Some("Failed to delete link."),
-                "/admin/short-links",
+                Some("Database error while looking up link."),
+                &analytics_url,
+            );
+        }
+    };
+
+    // The merged link's aliases move; look them up before they do
+    let old_aliases = db_aliases::get_aliases_for_link(&state.db, from.id)
+        .await
+        .unwrap_or_default();
+
+    let keep_code = form.keep_code.is_some();
+    match db::merge_links(&state.db, into.id, &from, keep_code).await {
+        Ok(codes) => {
+            state.cache.remove(from.domain_id, &from.short_code);
+            for alias in &old_aliases {
+                state.cache.remove(from.domain_id, &alias.short_code);
+            }
+            if state.link_resolves(&into) {
+                for code in &codes {
+                    state
+                        .cache
+                        .set(into.domain_id, code, CachedLink::from(&into));
+                }
+            }
+            state.click_alerts.forget(from.id);
+            let detail = format!("Merged in {}", from.short_code);
+            log_activity(
+                &state,
+                into.id,
+                admin.user_id,
+                ACTIVITY_SETTINGS,
+                Some(&detail),
             )
+            .await;
+
+            let msg = if keep_code {
+                format!(
+                    "Merged '{}' into this link; its code is now an alias.",
+                    from.short_code
+                )
+            } else {
+                format!("Merged '{}' into this link.", from.short_code)
+            };
+            set_flash_and_redirect(jar, Some(&msg), None, &analytics_url)
+        }
+        Err(e) => {
+            tracing::error!("Failed to merge link {} into {}: {:?}", from.id, into.id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to merge links."), &analytics_url)
         }
     }
 }
 
-// ── Analytics ──────────────────────────────────────────────────────────────
+/// The login page, with an optional error or notice.
+fn login_form(state: &AppState, error: Option<&str>) -> Response {
+    LoginTemplate {
+        error: error.map(str::to_owned),
+        app_title: state.config.app_title.clone(),
+        sso_name: state.oidc.as_ref().map(|o| o.provider_name.clone()),
+    }
+    .into_response()
+}
 
-/// GET /admin/links/:id/analytics
-pub async fn analytics(
-    auth: AuthUser,
-    State(state): State<Arc<AppState>>,
-    Path(id): Path<i64>,
-) -> Response {
-    let summary = match db::get_analytics(&state.db, id).await {
-        Ok(Some(s)) => s,
-        Ok(None) => {
-            return (axum::http::StatusCode::NOT_FOUND, "Link not found.").into_response();
-        }
+/// Fetch link `id` if `auth` may manage it.
+async fn owned_link(auth: &AuthUser, state: &AppState, id: i64) -> Result<Link, &'static str> {
+    match db::get_link_by_id(&state.db, id).await {
+        Ok(Some(l)) if auth.can_manage(l.workspace_id, l.user_id) => Ok(l),
+        Ok(Some(_)) => Err("Access denied."),
+        Ok(None) => Err("Link not found."),
         Err(e) => {
-            tracing::error!("Failed to load analytics for link {}: {:?}", id, e);
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to load analytics.",
-            )
-                .into_response();
+            tracing::error!("Failed to fetch link {}: {:?}", id, e);
+            Err("Database error while looking up link.")
         }
-    };
+    }
+}
 
-    // Ownership check
-    if !auth.is_admin() && summary.link.user_id != Some(auth.user_id) {
-        return (axum::http::StatusCode::FORBIDDEN, "Access denied.").into_response();
+/// Add a change to link `link_id`'s timeline. A failure is only logged,
+/// since the change itself went through.
+pub(crate) async fn log_activity(
+    state: &AppState,
+    link_id: i64,
+    user_id: i64,
+    kind: &str,
+    detail: Option<&str>,
+) {
+    if let Err(e) = db_link_activity::record(&state.db, link_id, Some(user_id), kind, detail).await
+    {
+        tracing::error!("Failed to record activity on link {}: {:?}", link_id, e);
     }
+}
 
-    let short_url = format!("{}/{}", state.config.base_url, summary.link.short_code);
+/// Add the changes between `before` and `after` (the same link) to its
+/// timeline: which details were edited, and whether it was switched on or
+/// off.
+pub(crate) async fn log_edits(state: &AppState, before: &Link, after: &Link, user_id: i64) {
+    let mut fields = Vec::new();
+    if before.original_url != after.original_url {
+        fields.push("destination");
+    }
+    if before.title != after.title {
+        fields.push("title");
+    }
+    if before.description != after.description {
+        fields.push("description");
+    }
+    if before.notes != after.notes {
+        fields.push("notes");
+    }
+    if !fields.is_empty() {
+        let detail = format!("Changed the {}", join_words(&fields));
+        log_activity(state, after.id, user_id, ACTIVITY_EDITED, Some(&detail)).await;
+    }
+    if before.is_active != after.is_active {
+        let kind = if after.is_active {
+            ACTIVITY_ACTIVATED
+        } else {
+            ACTIVITY_DEACTIVATED
+        };
+        log_activity(state, after.id, user_id, kind, None).await;
+    }
+}
 
-    let total = summary.total_clicks;
-    let top_browsers = with_pct(
-        count_field(summary.clicks.iter().map(|c| c.browser.as_deref())),
-        total,
-    );
-    let top_os = with_pct(
-        count_field(summary.clicks.iter().map(|c| c.os.as_deref())),
-        total,
-    );
-    let top_devices = with_pct(
-        count_field(summary.clicks.iter().map(|c| c.device_type.as_deref())),
-        total,
-    );
-    let top_referers = with_pct(
-        count_field(summary.clicks.iter().map(|c| c.referer.as_deref())),
-        total,
-    );
-    let top_countries = with_pct(
-        count_field(summary.clicks.iter().map(|c| c.country.as_deref())),
-        total,
-    );
+/// `["a", "b", "c"]` as "a, b and c".
+fn join_words(words: &[&str]) -> String {
+    match words {
+        [] => String::new(),
+        [one] => (*one).to_owned(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
 
-    AnalyticsTemplate {
-        summary,
-        short_url,
-        top_browsers,
-        top_os,
-        top_devices,
-        top_referers,
-        top_countries,
-        is_admin: auth.is_admin(),
-        app_title: state.config.app_title.clone(),
+/// Refresh the cached entries for a link's code and aliases after a setting
+/// that affects redirects changes, dropping them if it no longer resolves.
+pub(crate) async fn recache_link(state: &AppState, link: &Link) {
+    let aliases = db_aliases::get_aliases_for_link(&state.db, link.id)
+        .await
+        .unwrap_or_default();
+    if !state.link_resolves(link) {
+        state.cache.remove(link.domain_id, &link.short_code);
+        for alias in aliases {
+            state.cache.remove(link.domain_id, &alias.short_code);
+        }
+        return;
+    }
+    state
+        .cache
+        .set(link.domain_id, &link.short_code, CachedLink::from(link));
+    for alias in aliases {
+        state
+            .cache
+            .set(link.domain_id, alias.short_code, CachedLink::from(link));
     }
-    .into_response()
 }
 
 // ── Datastar validation endpoints ──────────────────────────────────────────
@@ -1138,16 +4286,22 @@ pub async fn validate_code(
     let code = code.trim();
     tracing::info!("validate_code called with: {:?}", code);
 
+    let availability = match code_availability(&state, code).await {
+        Ok((_, availability)) => availability,
+        Err(e) => {
+            tracing::error!("Failed to check custom code '{}': {:?}", code, e);
+            CodeAvailability::Invalid
+        }
+    };
+
     let fragment = if code.is_empty() {
         r#"<span id="code-validation" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none;"></span>"#.to_string()
-    } else if !code.chars().all(|c| c.is_alphanumeric() || c == '-') {
-        r#"<span id="code-validation" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none; color:#dc2626;">&#10007;</span>"#.to_string()
-    } else if let Ok(Some(_)) = db::get_link_by_code(&state.db, code).await {
-        r#"<span id="code-validation" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none; color:#dc2626;">&#10007;</span>"#.to_string()
-    } else if let Ok(true) = db_bio::bio_slug_exists(&state.db, code).await {
-        r#"<span id="code-validation" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none; color:#dc2626;">&#10007;</span>"#.to_string()
+    } else if let Some(problem) = availability.problem() {
+        format!(
+            r#"<span id="code-validation" title="{problem}" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none; color:#dc2626;">&#10007;</span>"#
+        )
     } else {
-        r#"<span id="code-validation" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none; color:#16a34a;">&#10003;</span>"#.to_string()
+        r#"<span id="code-validation" title="Available" style="position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none; color:#16a34a;">&#10003;</span>"#.to_string()
     };
 
     tracing::info!(
@@ -1157,6 +4311,67 @@ pub async fn validate_code(
     datastar_patch(fragment)
 }
 
+// ── API tokens ───────────────────────────────────────────────────────────
+
+/// POST /admin/api-tokens
+///
+/// Renders the profile page directly rather than redirecting so the token
+/// is shown once and never stored in a cookie.
+pub async fn create_api_token(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<ApiTokenForm>,
+) -> Response {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Token name is required."),
+            "/admin/profile",
+        );
+    }
+
+    let token = auth::generate_api_token();
+    if let Err(e) =
+        db_users::create_api_token(&state.db, auth.user_id, name, &auth::hash_api_token(&token))
+            .await
+    {
+        tracing::error!("Failed to create API token: {:?}", e);
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Failed to create API token."),
+            "/admin/profile",
+        );
+    }
+
+    render_profile(&auth, &state, jar, Some(token)).await
+}
+
+/// POST /admin/api-tokens/:id/delete
+pub async fn delete_api_token(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    match db_users::delete_api_token(&state.db, id, auth.user_id).await {
+        Ok(true) => set_flash_and_redirect(jar, Some("API token revoked."), None, "/admin/profile"),
+        Ok(false) => set_flash_and_redirect(jar, None, Some("Token not found."), "/admin/profile"),
+        Err(e) => {
+            tracing::error!("Failed to delete API token {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to revoke API token."),
+                "/admin/profile",
+            )
+        }
+    }
+}
+
 // ── Private helpers ────────────────────────────────────────────────────────
 
 /// Build a Datastar SSE `datastar-patch-elements` response from an HTML fragment.
@@ -1201,17 +4416,101 @@ fn set_flash_and_redirect(
     (jar, Redirect::to(destination)).into_response()
 }
 
-/// Generate a random 7-character alphanumeric short code that doesn't already
-/// exist in the database.
-async fn generate_unique_code(pool: &sqlx::SqlitePool) -> String {
+/// Generate a short code that doesn't already exist in the database: the
+/// next one from the counter with `SHORT_CODE_STRATEGY=sequential`, else a
+/// random one (see [`short_code::random`]), two characters longer than
+/// `SHORT_CODE_LENGTH` if the first ten tries are all taken.
+pub(crate) async fn generate_unique_code(pool: &sqlx::SqlitePool, config: &AppConfig) -> String {
+    if config.short_code_strategy == CodeStrategy::Sequential {
+        // Custom codes and blocked words can take a few numbers' codes
+        for _ in 0..100 {
+            let n = match db::next_code_number(pool).await {
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::error!("Failed to advance the short code counter: {e}");
+                    break;
+                }
+            };
+            let code = short_code::sequential(config, n as u64);
+            if short_code::is_blocked(config, &code) {
+                continue;
+            }
+            if let Ok(None) = db::get_link_by_code(pool, &code).await {
+                return code;
+            }
+        }
+        tracing::warn!("No free sequential short code; generating a random one");
+    }
     for _ in 0..10 {
-        let code = random_code(7);
+        let code = short_code::random(config, config.short_code_length);
         match db::get_link_by_code(pool, &code).await {
             Ok(None) => return code,
             _ => continue,
         }
     }
-    random_code(9)
+    short_code::random(config, config.short_code_length + 2)
+}
+
+/// Whether a custom code could be given to a new link or alias.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CodeAvailability {
+    Free,
+    /// Used by a link (even one in the trash), alias, or links page.
+    Taken,
+    /// One of Linkly's own pages (see [`short_code::is_reserved`]).
+    Reserved,
+    /// Not something [`short_code::parse`] accepts, or too long.
+    Invalid,
+}
+
+impl CodeAvailability {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Free => "free",
+            Self::Taken => "taken",
+            Self::Reserved => "reserved",
+            Self::Invalid => "invalid",
+        }
+    }
+
+    /// Why the code can't be used, for people choosing one.
+    pub(crate) fn problem(self) -> Option<String> {
+        match self {
+            Self::Free => None,
+            Self::Taken => Some("That code is already taken.".into()),
+            Self::Reserved => Some("That code is reserved for Linkly's own pages.".into()),
+            Self::Invalid => Some(format!(
+                "Codes may only contain letters, numbers, and hyphens, at most {MAX_CODE_LEN} characters."
+            )),
+        }
+    }
+}
+
+/// Check a custom code the way creating a link would, returning it as it
+/// would be stored (or trimmed, if invalid) and whether it's available.
+pub(crate) async fn code_availability(
+    state: &AppState,
+    code: &str,
+) -> Result<(String, CodeAvailability), sqlx::Error> {
+    let code = code.trim();
+    let Some(code) = short_code::parse(
+        code,
+        state.config.unicode_short_codes,
+        state.config.emoji_short_codes,
+    )
+    .filter(|code| code.chars().count() <= MAX_CODE_LEN) else {
+        return Ok((code.to_owned(), CodeAvailability::Invalid));
+    };
+    let availability = if short_code::is_reserved(&code, state.config.public_shorten) {
+        CodeAvailability::Reserved
+    } else if db::short_code_taken(&state.db, &code).await?
+        || db_bio::bio_slug_exists(&state.db, &code).await?
+    {
+        CodeAvailability::Taken
+    } else {
+        CodeAvailability::Free
+    };
+    Ok((code, availability))
 }
 
 /// Generate a random alphanumeric string of the given length.
@@ -1226,7 +4525,7 @@ fn random_code(len: usize) -> String {
 
 /// Tally occurrences of each non-None value, sort descending by count, and
 /// return the top 10.
-fn count_field<'a>(iter: impl Iterator<Item = Option<&'a str>>) -> Vec<(String, i64)> {
+pub(crate) fn count_field<'a>(iter: impl Iterator<Item = Option<&'a str>>) -> Vec<(String, i64)> {
     let mut counts: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
     for val in iter.flatten() {
         if !val.is_empty() {
@@ -1234,13 +4533,85 @@ fn count_field<'a>(iter: impl Iterator<Item = Option<&'a str>>) -> Vec<(String,
         }
     }
     let mut sorted: Vec<(String, i64)> = counts.into_iter().collect();
-    sorted.sort_by(|a, b| b.1.cmp(&a.1));
+    sorted.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    sorted.truncate(10);
+    sorted
+}
+
+/// Sites whose visitors count as coming from social media, matched against
+/// the referrer's host and its parent domains.
+const SOCIAL_SITES: &[&str] = &[
+    "bsky.app",
+    "facebook.com",
+    "instagram.com",
+    "linkedin.com",
+    "lnkd.in",
+    "pinterest.com",
+    "reddit.com",
+    "t.co",
+    "threads.net",
+    "tiktok.com",
+    "twitter.com",
+    "x.com",
+    "youtube.com",
+];
+
+/// How `clicks` reached the link: its QR code, social media, other websites,
+/// or directly (no referrer), most common first.
+pub(crate) fn count_sources(clicks: &[Click]) -> Vec<(String, i64)> {
+    let sources = clicks.iter().map(|click| {
+        if click.source.as_deref() == Some(SOURCE_QR) {
+            return "QR code";
+        }
+        let Some(referer) = click.referer.as_deref().filter(|r| !r.is_empty()) else {
+            return "Direct";
+        };
+        let host = reqwest::Url::parse(referer)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase));
+        let social = host.is_some_and(|host| {
+            SOCIAL_SITES.iter().any(|site| {
+                host == *site
+                    || host
+                        .strip_suffix(site)
+                        .is_some_and(|sub| sub.ends_with('.'))
+            })
+        });
+        if social {
+            "Social"
+        } else {
+            "Other websites"
+        }
+    });
+    count_field(sources.map(Some))
+}
+
+/// Top 10 countries among `clicks`, grouped by ISO code and labelled with
+/// the flag and name, e.g. "🇩🇪 Germany". Clicks logged before codes were
+/// stored are grouped by name.
+pub(crate) fn count_countries(clicks: &[Click]) -> Vec<(String, i64)> {
+    let mut counts: std::collections::HashMap<&str, (String, i64)> =
+        std::collections::HashMap::new();
+    for click in clicks {
+        let name = click.country.as_deref().filter(|n| !n.is_empty());
+        let (key, label) = match (click.country_code.as_deref(), name) {
+            (Some(code), name) => (
+                code,
+                format!("{} {}", geo::flag(code), name.unwrap_or(code)),
+            ),
+            (None, Some(name)) => (name, name.to_owned()),
+            (None, None) => continue,
+        };
+        counts.entry(key).or_insert((label, 0)).1 += 1;
+    }
+    let mut sorted: Vec<(String, i64)> = counts.into_values().collect();
+    sorted.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
     sorted.truncate(10);
     sorted
 }
 
 /// Attach a percentage-of-total column to each breakdown row.
-fn with_pct(items: Vec<(String, i64)>, total: i64) -> Vec<(String, i64, i64)> {
+pub(crate) fn with_pct(items: Vec<(String, i64)>, total: i64) -> Vec<(String, i64, i64)> {
     items
         .into_iter()
         .map(|(name, count)| {
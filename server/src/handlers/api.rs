@@ -0,0 +1,1435 @@
+use crate::{
+    auth::{ApiUser, SessionOrApiUser},
+    cache::CachedLink,
+    db, db_conversions, db_hooks,
+    destination::{self, DestinationError},
+    error::{AppError, Problem},
+    handlers::{
+        admin::{code_availability, log_edits, recache_link, CodeAvailability},
+        redirect,
+    },
+    hooks,
+    links::{self, CreateError, Created, DomainChoice, NewLink, Owner, Prepared},
+    models::{Conversion, Link, WebhookSubscription},
+    qr, quota, safe_browsing, short_code,
+    validate::{FieldError, LinkInput},
+    AppState,
+};
+use anyhow::Context;
+use askama::Template;
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use std::{net::SocketAddr, sync::Arc};
+use utoipa::{
+    openapi::{
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+        server::Server,
+    },
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+
+// ── OpenAPI document ───────────────────────────────────────────────────────
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Linkly API",
+        description = "Create short links, report conversions, and subscribe to events. \
+                       Authenticate with a personal API token from your profile page."
+    ),
+    paths(
+        me,
+        shorten,
+        quick_shorten,
+        bulk_shorten,
+        recent_links,
+        expand,
+        check_link,
+        check_code,
+        update_link,
+        heatmap,
+        top_links,
+        record_conversion,
+        list_hooks,
+        subscribe,
+        unsubscribe
+    ),
+    components(schemas(
+        Me,
+        ShortLink,
+        QuickShortenRequest,
+        QuickShortLink,
+        BulkShortenRequest,
+        BulkShortened,
+        BulkResult,
+        RecentLinks,
+        Expansion,
+        LinkCheck,
+        CheckedHop,
+        CodeCheck,
+        LinkUpdate,
+        LinkDetails,
+        Heatmap,
+        TopLinks,
+        TopLink,
+        ConversionRequest,
+        ConversionResponse,
+        Subscription,
+        SubscribeRequest,
+        Problem,
+        FieldError
+    )),
+    modifiers(&SecuritySchemes),
+    security(("token" = []), ("key" = [])),
+)]
+struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "key",
+            SecurityScheme::ApiKey(ApiKey::Query(ApiKeyValue::new("key"))),
+        );
+    }
+}
+
+#[derive(Template)]
+#[template(path = "api_docs.html")]
+struct ApiDocsTemplate {
+    app_title: String,
+}
+
+// ── Request / response types ───────────────────────────────────────────────
+
+#[derive(Deserialize, ToSchema)]
+pub struct SubscribeRequest {
+    /// One of `link.created`, `click.recorded`, or `click.threshold`.
+    #[schema(example = "link.created")]
+    event: String,
+    /// Receives a POST for each event.
+    #[schema(example = "https://hooks.zapier.com/hooks/catch/123/abc/")]
+    target_url: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ConversionRequest {
+    /// The click ID the destination received via `{click_id}`.
+    #[schema(example = "3f2a9c0e5b7d41e8a6c1d0f9b2e4a7c3")]
+    click_id: String,
+    /// What happened; defaults to `conversion`.
+    #[schema(example = "purchase")]
+    name: Option<String>,
+    /// Optional amount, e.g. order value.
+    #[schema(example = 49.99)]
+    value: Option<f64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ShortenQuery {
+    /// The destination URL, percent-encoded.
+    url: String,
+    /// Custom short code; a random one is generated when omitted.
+    code: Option<String>,
+    title: Option<String>,
+    /// Hostname of a custom domain to serve the link from; the workspace's
+    /// domain (or the primary domain) when omitted.
+    domain: Option<String>,
+    /// `json` for a JSON body; plain text otherwise.
+    format: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct QuickShortenRequest {
+    /// The destination URL.
+    #[schema(example = "https://example.com/some/long/page")]
+    url: String,
+    /// Custom short code; a random one is generated when omitted.
+    code: Option<String>,
+    title: Option<String>,
+    /// Hostname of a custom domain to serve the link from; the workspace's
+    /// domain (or the primary domain) when omitted.
+    domain: Option<String>,
+}
+
+/// Links `/api/v1/links/bulk` accepts in one request.
+const BULK_MAX_LINKS: usize = 100;
+
+#[derive(Deserialize, ToSchema)]
+pub struct BulkShortenRequest {
+    /// Up to 100 links, each as for `/api/v1/quick-shorten`.
+    links: Vec<QuickShortenRequest>,
+}
+
+/// Links `/api/v1/links/recent` returns when no `limit` is given, and at most.
+const RECENT_LINKS_DEFAULT: i64 = 10;
+const RECENT_LINKS_MAX: i64 = 50;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CodeCheckQuery {
+    /// The custom code to check.
+    code: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecentLinksQuery {
+    /// How many links to return: 10 by default, at most 50.
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TopLinksQuery {
+    /// How far back to count clicks: a number of hours or days, e.g. `24h`
+    /// or `30d`. Defaults to `7d`; at most a year.
+    #[param(example = "7d")]
+    period: Option<String>,
+    /// How many links to return, 1 to 100. Defaults to 20.
+    limit: Option<i64>,
+}
+
+/// Fields of a link to change; the others are left as they are.
+#[derive(Deserialize, ToSchema)]
+pub struct LinkUpdate {
+    /// An empty string removes the title.
+    #[schema(example = "Spring sale")]
+    title: Option<String>,
+    /// An empty string removes the description.
+    description: Option<String>,
+    /// Inactive links stop redirecting.
+    is_active: Option<bool>,
+}
+
+/// The authenticated user.
+#[derive(Serialize, ToSchema)]
+struct Me {
+    id: i64,
+    email: String,
+    /// `admin` or `user`.
+    role: String,
+}
+
+/// A newly created short link.
+#[derive(Serialize, ToSchema)]
+struct ShortLink {
+    id: i64,
+    short_code: String,
+    #[schema(example = "https://go.example.com/abc123")]
+    short_url: String,
+    url: String,
+}
+
+/// A short link for the browser extension, with its QR code.
+#[derive(Serialize, ToSchema)]
+struct QuickShortLink {
+    id: i64,
+    short_code: String,
+    #[schema(example = "https://go.example.com/abc123")]
+    short_url: String,
+    url: String,
+    title: Option<String>,
+    /// SVG QR code as a `data:` URI, ready for an `<img src>`. It opens the
+    /// short URL with `?src=qr`, so scans count as their own source.
+    #[schema(example = "data:image/svg+xml;base64,PD94bWwg...")]
+    qr_code: String,
+}
+
+/// The outcome of a bulk shorten request, one result per link sent.
+#[derive(Serialize, ToSchema)]
+struct BulkShortened {
+    /// Links created by this request.
+    created: usize,
+    /// Links that could not be created.
+    failed: usize,
+    results: Vec<BulkResult>,
+}
+
+/// One link of a bulk shorten request.
+#[derive(Serialize, ToSchema)]
+struct BulkResult {
+    /// Position of the link in the request's `links`.
+    index: usize,
+    /// `201` for a new link, `200` for an existing link to the same URL, or
+    /// the status the error would have had on its own.
+    #[schema(example = 201)]
+    status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    link: Option<ShortLink>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "That short code is already taken")]
+    error: Option<String>,
+}
+
+/// Your latest links, newest first.
+#[derive(Serialize, ToSchema)]
+struct RecentLinks {
+    links: Vec<LinkDetails>,
+}
+
+/// Where a short code leads, without following it.
+#[derive(Serialize, ToSchema)]
+struct Expansion {
+    /// The link's own code, also when an alias was asked for.
+    short_code: String,
+    #[schema(example = "https://go.example.com/abc123")]
+    short_url: String,
+    /// The destination.
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    /// `active`, `inactive` (switched off), or `archived`.
+    #[schema(example = "active")]
+    status: String,
+    /// Whether following the short URL redirects right now.
+    redirects: bool,
+    #[schema(example = "2024-01-01T00:00:00Z")]
+    created_at: String,
+}
+
+/// What re-checking a link's destination found.
+#[derive(Serialize, ToSchema)]
+struct LinkCheck {
+    short_code: String,
+    #[schema(example = "https://go.example.com/abc123")]
+    short_url: String,
+    url: String,
+    /// `ok`; `broken` when the link doesn't redirect, the destination can't
+    /// be reached, answers with an error, or its redirects loop; or `unsafe`
+    /// when Safe Browsing lists it or a page it redirects through.
+    #[schema(example = "ok")]
+    verdict: String,
+    /// Why the verdict isn't `ok`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "The destination answers 404 Not Found.")]
+    problem: Option<String>,
+    /// The status of the last page reached; `null` if it couldn't be.
+    #[schema(example = 200)]
+    status: Option<u16>,
+    /// Each request made, starting with the destination.
+    redirects: Vec<CheckedHop>,
+    /// What Safe Browsing lists the destination or its redirects for, such
+    /// as `SOCIAL_ENGINEERING`; empty when nothing is listed, and `null`
+    /// when Safe Browsing isn't configured or couldn't be reached.
+    #[schema(example = json!([]))]
+    threats: Option<Vec<String>>,
+    #[schema(example = "2026-03-14T09:26:53Z")]
+    checked_at: String,
+}
+
+/// Whether a custom code can be used.
+#[derive(Serialize, ToSchema)]
+struct CodeCheck {
+    /// The code as it would be stored.
+    #[schema(example = "spring-sale")]
+    code: String,
+    /// `free`; `taken` by a link (even one in the trash), alias, or links
+    /// page; `reserved` for one of Linkly's own pages, such as `admin`; or
+    /// `invalid`.
+    #[schema(example = "free")]
+    status: String,
+    /// Whether a link can be created with the code right now.
+    available: bool,
+    /// Why the code can't be used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(example = "That code is already taken.")]
+    problem: Option<String>,
+}
+
+/// One request made while following a destination's redirects.
+#[derive(Serialize, ToSchema)]
+struct CheckedHop {
+    url: String,
+    /// `null` when the site couldn't be reached.
+    #[schema(example = 301)]
+    status: Option<u16>,
+}
+
+/// A link's editable details.
+#[derive(Serialize, ToSchema)]
+struct LinkDetails {
+    id: i64,
+    short_code: String,
+    #[schema(example = "https://go.example.com/abc123")]
+    short_url: String,
+    url: String,
+    title: Option<String>,
+    description: Option<String>,
+    is_active: bool,
+}
+
+/// A link's clicks by day of the week and hour of the day.
+#[derive(Serialize, ToSchema)]
+struct Heatmap {
+    link_id: i64,
+    /// Time zone of `days` and hours; always `UTC`.
+    #[schema(example = "UTC")]
+    timezone: String,
+    /// Row labels for `clicks`, Monday first.
+    days: Vec<String>,
+    /// `clicks[day][hour]`: one row per day, each with 24 hourly counts.
+    clicks: Vec<Vec<i64>>,
+}
+
+/// The most-clicked links over a period.
+#[derive(Serialize, ToSchema)]
+struct TopLinks {
+    #[schema(example = "7d")]
+    period: String,
+    /// Start of the period; clicks from then until now are counted.
+    #[schema(example = "2024-01-01T00:00:00Z")]
+    since: String,
+    /// Most clicks first. Links without clicks in the period are left out.
+    links: Vec<TopLink>,
+}
+
+/// A link and its clicks in the period.
+#[derive(Serialize, ToSchema)]
+struct TopLink {
+    id: i64,
+    short_code: String,
+    #[schema(example = "https://go.example.com/abc123")]
+    short_url: String,
+    url: String,
+    title: Option<String>,
+    clicks: i64,
+}
+
+/// A recorded conversion.
+#[derive(Serialize, ToSchema)]
+struct ConversionResponse {
+    id: i64,
+    click_id: String,
+    /// The link that was clicked.
+    link_id: i64,
+    name: String,
+    value: Option<f64>,
+    #[schema(example = "2024-01-01T00:00:00Z")]
+    created_at: String,
+}
+
+/// A REST hook subscription.
+#[derive(Serialize, ToSchema)]
+struct Subscription {
+    id: i64,
+    event: String,
+    target_url: String,
+    #[schema(example = "2024-01-01T00:00:00Z")]
+    created_at: String,
+}
+
+/// GET /api/v1/openapi.json
+pub async fn openapi_json(State(state): State<Arc<AppState>>) -> Response {
+    let mut doc = ApiDoc::openapi();
+    doc.servers = Some(vec![Server::new(&state.config.base_url)]);
+    Json(doc).into_response()
+}
+
+/// GET /api/v1/docs
+///
+/// Swagger UI for the document above. The UI's assets load from a CDN.
+pub async fn docs(State(state): State<Arc<AppState>>) -> Response {
+    ApiDocsTemplate {
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+// ── Account ────────────────────────────────────────────────────────────────
+
+/// The token's owner.
+///
+/// Lets integrations (e.g. Zapier's connection test) verify a token.
+#[utoipa::path(
+    get,
+    path = "/api/v1/me",
+    tag = "account",
+    responses(
+        (status = 200, description = "The token's owner", body = Me),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn me(user: ApiUser) -> Response {
+    Json(Me {
+        id: user.user_id,
+        email: user.email,
+        role: user.role,
+    })
+    .into_response()
+}
+
+// ── Shorten ────────────────────────────────────────────────────────────────
+
+/// Create a short link.
+///
+/// Creates a link from a single GET, for bookmarklets and curl one-liners.
+/// Responds with the bare short URL as plain text, or with JSON when asked
+/// via `format=json` or `Accept: application/json`. Without `code`, an
+/// existing link of yours to the same URL is returned instead of a new one.
+#[utoipa::path(
+    get,
+    path = "/api/v1/shorten",
+    tag = "links",
+    params(ShortenQuery),
+    responses(
+        (status = 200, description = "An existing link to the same URL (no `code` given)", content(
+            ("application/json" = ShortLink),
+            ("text/plain" = String, example = json!("https://go.example.com/abc123")),
+        )),
+        (status = 201, description = "The short URL", content(
+            ("application/json" = ShortLink),
+            ("text/plain" = String, example = json!("https://go.example.com/abc123")),
+        )),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 409, description = "The code is taken", body = Problem),
+        (status = 422, description = "Invalid URL, code, title, or domain, or a destination that loops", body = Problem),
+        (status = 429, description = "Hourly request limit or link limit reached", body = Problem),
+    ),
+)]
+pub async fn shorten(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<ShortenQuery>,
+) -> Response {
+    let json = query.format.as_deref() == Some("json")
+        || headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("application/json"));
+    let respond = |status: StatusCode, link: Link, short_url: String| {
+        if json {
+            let body = ShortLink {
+                id: link.id,
+                short_code: link.short_code,
+                short_url,
+                url: link.original_url,
+            };
+            (status, Json(body)).into_response()
+        } else {
+            (status, format!("{short_url}\n")).into_response()
+        }
+    };
+
+    let request = NewLink {
+        url: &query.url,
+        code: query.code.as_deref(),
+        title: query.title.as_deref(),
+        description: None,
+        domain: DomainChoice::Host(query.domain.as_deref().unwrap_or_default()),
+    };
+    match create_link(&state, &user, request).await {
+        Ok((status, link)) => {
+            let short_url = state.short_url(link.domain_id, &link.short_code);
+            respond(status, link, short_url)
+        }
+        Err(e) if json => e.into_response(),
+        Err(e) => e.into_text_response(),
+    }
+}
+
+/// Shorten a URL from a browser extension.
+///
+/// Like `/api/v1/shorten` with a JSON body, and also returns a QR code for
+/// the link. Without `code`, an existing link of yours to the same URL is
+/// returned instead of a new one. Any origin may call it, since it only
+/// accepts a token.
+#[utoipa::path(
+    post,
+    path = "/api/v1/quick-shorten",
+    tag = "extension",
+    request_body = QuickShortenRequest,
+    responses(
+        (status = 200, description = "An existing link to the same URL (no `code` given)", body = QuickShortLink),
+        (status = 201, description = "The new link", body = QuickShortLink),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 409, description = "The code is taken", body = Problem),
+        (status = 422, description = "Invalid URL, code, title, or domain, or a destination that loops", body = Problem),
+        (status = 429, description = "Hourly request limit or link limit reached", body = Problem),
+    ),
+)]
+pub async fn quick_shorten(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<QuickShortenRequest>,
+) -> Result<Response, AppError> {
+    let request = NewLink {
+        url: &body.url,
+        code: body.code.as_deref(),
+        title: body.title.as_deref(),
+        description: None,
+        domain: DomainChoice::Host(body.domain.as_deref().unwrap_or_default()),
+    };
+    let (status, link) = create_link(&state, &user, request).await?;
+    let qr_code = qr::data_uri(&qr::url(&state, link.domain_id, &link.short_code))
+        .context("Failed to draw QR code")?;
+    Ok((
+        status,
+        Json(QuickShortLink {
+            id: link.id,
+            short_url: state.short_url(link.domain_id, &link.short_code),
+            short_code: link.short_code,
+            url: link.original_url,
+            title: link.title,
+            qr_code,
+        }),
+    )
+        .into_response())
+}
+
+/// Shorten many URLs at once.
+///
+/// Takes up to 100 links, each as for `/api/v1/quick-shorten`, and creates
+/// them in one transaction. Every link gets its own result, in the order
+/// sent: one that fails (a taken code, an invalid URL, the link limit) does
+/// not stop the others. Links to the same URL without a `code`, whether
+/// already yours or repeated in the request, share one short link.
+#[utoipa::path(
+    post,
+    path = "/api/v1/links/bulk",
+    tag = "links",
+    request_body = BulkShortenRequest,
+    responses(
+        (status = 200, description = "A result for each link", body = BulkShortened),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 422, description = "No links, or more than 100", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn bulk_shorten(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<BulkShortenRequest>,
+) -> Result<Response, AppError> {
+    let count = body.links.len();
+    if count == 0 || count > BULK_MAX_LINKS {
+        return Err(AppError::Unprocessable(format!(
+            "links must hold 1 to {BULK_MAX_LINKS} links"
+        )));
+    }
+
+    // Repeats of a URL without a code take the first one's result
+    let mut first_of = std::collections::HashMap::new();
+    let mut repeat_of = vec![None; count];
+    for (index, link) in body.links.iter().enumerate() {
+        if link.code.is_none() {
+            let key = (link.url.trim(), link.domain.as_deref().map(str::trim));
+            match first_of.get(&key) {
+                Some(&first) => repeat_of[index] = Some(first),
+                None => {
+                    first_of.insert(key, index);
+                }
+            }
+        }
+    }
+
+    // Checking destinations can mean requests to them, so prepare all at once
+    let mut preparing = tokio::task::JoinSet::new();
+    for (index, link) in body.links.into_iter().enumerate() {
+        if repeat_of[index].is_some() {
+            continue;
+        }
+        let state = state.clone();
+        let user = user.clone();
+        preparing.spawn(async move {
+            let request = NewLink {
+                url: &link.url,
+                code: link.code.as_deref(),
+                title: link.title.as_deref(),
+                description: None,
+                domain: DomainChoice::Host(link.domain.as_deref().unwrap_or_default()),
+            };
+            let prepared = links::prepare(
+                &state.db,
+                &state.config,
+                &state.probe_http,
+                &state.domains,
+                owner(&user),
+                request,
+            )
+            .await;
+            (index, prepared.map_err(AppError::from))
+        });
+    }
+    let mut prepared: Vec<Option<Result<Prepared, AppError>>> =
+        std::iter::repeat_with(|| None).take(count).collect();
+    while let Some(joined) = preparing.join_next().await {
+        let (index, result) = joined.context("Failed to prepare a bulk link")?;
+        prepared[index] = Some(result);
+    }
+
+    let mut left = quota::links_left(&state, user.user_id)
+        .await
+        .context("Failed to check link quota")?;
+    let mut outcomes: Vec<Option<Result<(StatusCode, Link), AppError>>> =
+        std::iter::repeat_with(|| None).take(count).collect();
+    let mut rows = Vec::new();
+    let mut row_index = Vec::new();
+    for (index, result) in prepared.into_iter().enumerate() {
+        match result {
+            None => {}
+            Some(Ok(Prepared::Existing(link))) => {
+                outcomes[index] = Some(Ok((StatusCode::OK, *link)))
+            }
+            Some(Ok(Prepared::New(row))) => match &mut left {
+                Some((0, limit)) => {
+                    outcomes[index] = Some(Err(CreateError::LinkLimit(*limit).into()));
+                }
+                _ => {
+                    if let Some((n, _)) = &mut left {
+                        *n -= 1;
+                    }
+                    rows.push(row);
+                    row_index.push(index);
+                }
+            },
+            Some(Err(e)) => outcomes[index] = Some(Err(e)),
+        }
+    }
+
+    let created = db::create_links(&state.db, user.user_id, &rows)
+        .await
+        .context("Failed to create links via API")?;
+    for (index, result) in row_index.into_iter().zip(created) {
+        outcomes[index] = Some(match result {
+            Ok(link) => {
+                state
+                    .cache
+                    .set(link.domain_id, &link.short_code, CachedLink::from(&link));
+                state.link_created(&link);
+                Ok((StatusCode::CREATED, link))
+            }
+            Err(_) => Err(CreateError::CodeTaken.into()),
+        });
+    }
+
+    let mut results = Vec::with_capacity(count);
+    for index in 0..count {
+        let outcome = match repeat_of[index] {
+            Some(first) => match &outcomes[first] {
+                Some(Ok((_, link))) => Ok((StatusCode::OK, link)),
+                Some(Err(e)) => Err((e.status(), e.to_string())),
+                None => unreachable!("the first of a URL always has an outcome"),
+            },
+            None => match outcomes[index].as_ref().expect("every link has an outcome") {
+                Ok((status, link)) => Ok((*status, link)),
+                Err(e) => Err((e.status(), e.to_string())),
+            },
+        };
+        results.push(match outcome {
+            Ok((status, link)) => BulkResult {
+                index,
+                status: status.as_u16(),
+                link: Some(ShortLink {
+                    id: link.id,
+                    short_code: link.short_code.clone(),
+                    short_url: state.short_url(link.domain_id, &link.short_code),
+                    url: link.original_url.clone(),
+                }),
+                error: None,
+            },
+            Err((status, error)) => BulkResult {
+                index,
+                status: status.as_u16(),
+                link: None,
+                error: Some(error),
+            },
+        });
+    }
+    for outcome in outcomes.iter().flatten() {
+        if let Err(AppError::Internal(e)) = outcome {
+            tracing::error!("{:#}", e);
+        }
+    }
+
+    Ok(Json(BulkShortened {
+        created: results.iter().filter(|r| r.status == 201).count(),
+        failed: results.iter().filter(|r| r.link.is_none()).count(),
+        results,
+    })
+    .into_response())
+}
+
+/// Your most recent links.
+///
+/// The links you created most recently, newest first, for a browser
+/// extension's history list. Any origin may call it, since it only accepts a
+/// token.
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/recent",
+    tag = "extension",
+    params(RecentLinksQuery),
+    responses(
+        (status = 200, description = "Your latest links", body = RecentLinks),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn recent_links(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RecentLinksQuery>,
+) -> Result<Response, AppError> {
+    let limit = query
+        .limit
+        .unwrap_or(RECENT_LINKS_DEFAULT)
+        .clamp(1, RECENT_LINKS_MAX);
+    let scope = db::Scope {
+        workspace_id: Some(user.workspace_id),
+        user_id: Some(user.user_id),
+    };
+    let links = db::recent_links(&state.db, scope, limit)
+        .await
+        .context("Failed to load recent links")?;
+    Ok(Json(RecentLinks {
+        links: links
+            .into_iter()
+            .map(|link| LinkDetails {
+                id: link.id,
+                short_url: state.short_url(link.domain_id, &link.short_code),
+                short_code: link.short_code,
+                url: link.original_url,
+                title: link.title,
+                description: link.description,
+                is_active: link.is_active,
+            })
+            .collect(),
+    })
+    .into_response())
+}
+
+/// The owner of links created with `user`'s token.
+fn owner(user: &ApiUser) -> Owner {
+    Owner {
+        user_id: user.user_id,
+        workspace_id: user.workspace_id,
+    }
+}
+
+/// Create the link `request` describes for `user`, or without a custom code
+/// return their existing link to the same URL (with `200` instead of `201`).
+async fn create_link(
+    state: &AppState,
+    user: &ApiUser,
+    request: NewLink<'_>,
+) -> Result<(StatusCode, Link), AppError> {
+    Ok(match links::create(state, owner(user), request).await? {
+        Created::New(link) => (StatusCode::CREATED, link),
+        Created::Existing(link) => (StatusCode::OK, link),
+    })
+}
+
+/// Let scripts on any site read `/api/v1/shorten` responses, so the
+/// bookmarklet works from whatever page it is clicked on, and likewise the
+/// browser extension endpoints. Safe because they authenticate with a
+/// token, never with cookies.
+pub async fn allow_any_origin(mut resp: Response) -> Response {
+    resp.headers_mut().insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    resp
+}
+
+/// OPTIONS for the browser extension endpoints: the CORS preflight a page
+/// makes before sending a token or a JSON body.
+pub async fn preflight() -> Response {
+    (
+        StatusCode::NO_CONTENT,
+        [
+            (header::ACCESS_CONTROL_ALLOW_METHODS, "GET, POST, OPTIONS"),
+            (
+                header::ACCESS_CONTROL_ALLOW_HEADERS,
+                "Authorization, Content-Type",
+            ),
+            (header::ACCESS_CONTROL_MAX_AGE, "86400"),
+        ],
+    )
+        .into_response()
+}
+
+// ── Links ──────────────────────────────────────────────────────────────────
+
+/// Update a link.
+///
+/// Changes a link's title, description, or whether it is active, leaving
+/// fields that aren't sent as they are. Only the link's owner (or an admin)
+/// can change it. The admin panel also calls this from the short links list
+/// with the signed-in session instead of a token.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/links/{id}",
+    tag = "links",
+    params(("id" = i64, Path, description = "Link id")),
+    request_body = LinkUpdate,
+    responses(
+        (status = 200, description = "The updated link", body = LinkDetails),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 404, description = "No such link", body = Problem),
+        (status = 422, description = "Title or description too long, listed in `errors`", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn update_link(
+    SessionOrApiUser(user): SessionOrApiUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(req): Json<LinkUpdate>,
+) -> Result<Response, AppError> {
+    let mut link = db::get_link_by_id(&state.db, id)
+        .await
+        .with_context(|| format!("Failed to load link {id}"))?
+        .filter(|link| user.can_manage(link.workspace_id, link.user_id))
+        .ok_or_else(|| AppError::NotFound("Link not found".into()))?;
+    let before = link.clone();
+
+    let mut input = LinkInput::new(&state.config);
+    if let Some(title) = req.title {
+        link.title = input.title("title", Some(&title));
+    }
+    if let Some(description) = req.description {
+        link.description = input.description("description", Some(&description));
+    }
+    input.finish()?;
+    if let Some(is_active) = req.is_active {
+        link.is_active = is_active;
+    }
+
+    let updated = db::update_link_details(
+        &state.db,
+        id,
+        link.title.as_deref(),
+        link.description.as_deref(),
+        link.is_active,
+    )
+    .await
+    .with_context(|| format!("Failed to update link {id}"))?;
+    if !updated {
+        return Err(AppError::NotFound("Link not found".into()));
+    }
+    recache_link(&state, &link).await;
+    log_edits(&state, &before, &link, user.user_id).await;
+    Ok(Json(LinkDetails {
+        id: link.id,
+        short_url: state.short_url(link.domain_id, &link.short_code),
+        short_code: link.short_code,
+        url: link.original_url,
+        title: link.title,
+        description: link.description,
+        is_active: link.is_active,
+    })
+    .into_response())
+}
+
+/// Expand a short code.
+///
+/// Returns where a short code (or alias) leads and whether it redirects,
+/// without redirecting or recording a click; the lookup is counted as an
+/// `api_expand` event instead. Links that don't redirect are
+/// only shown to their owner (or an admin); to anyone else they are not
+/// found, as on their short URL. Links don't expire, so a link that stops
+/// redirecting was switched off or archived.
+#[utoipa::path(
+    get,
+    path = "/api/v1/expand/{code}",
+    tag = "links",
+    params(("code" = String, Path, description = "Short code or alias")),
+    responses(
+        (status = 200, description = "The link", body = Expansion),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 404, description = "No such link", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn expand(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+) -> Result<Response, AppError> {
+    let code = short_code::normalize(&code);
+    let link = db::find_link_by_code_or_alias(&state.db, &code)
+        .await
+        .with_context(|| format!("Failed to look up short code '{code}'"))?
+        .ok_or_else(|| AppError::NotFound("Link not found".into()))?;
+
+    let redirects = state.link_resolves(&link);
+    if !redirects && !user.can_manage(link.workspace_id, link.user_id) {
+        return Err(AppError::NotFound("Link not found".into()));
+    }
+    redirect::log_api_expand(&state, &link, &headers, addr);
+    let status = if !link.is_active {
+        "inactive"
+    } else if link.archived_at.is_some() {
+        "archived"
+    } else {
+        "active"
+    };
+    Ok(Json(Expansion {
+        short_url: state.short_url(link.domain_id, &link.short_code),
+        short_code: link.short_code,
+        url: link.original_url,
+        title: link.title,
+        description: link.description,
+        status: status.to_owned(),
+        redirects,
+        created_at: link.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    })
+    .into_response())
+}
+
+/// Check a custom code.
+///
+/// Says whether a link could be created with `code` before trying, for
+/// forms that check codes as they are typed. Codes are shared by every
+/// workspace, so one is taken whoever's link has it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/codes/check",
+    tag = "links",
+    params(CodeCheckQuery),
+    responses(
+        (status = 200, description = "Whether the code is available", body = CodeCheck),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn check_code(
+    _user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CodeCheckQuery>,
+) -> Result<Response, AppError> {
+    let (code, availability) = code_availability(&state, &query.code)
+        .await
+        .with_context(|| format!("Failed to check custom code '{}'", query.code))?;
+    Ok(Json(CodeCheck {
+        code,
+        status: availability.as_str().to_owned(),
+        available: availability == CodeAvailability::Free,
+        problem: availability.problem(),
+    })
+    .into_response())
+}
+
+/// Check a link's destination.
+///
+/// Follows the destination's redirects now, noting each status, and looks
+/// them up in Safe Browsing when it is configured, for a last check before
+/// a link goes out in a campaign. Nothing is cached: every call makes the
+/// requests again. Only the link's owner (or an admin) can check it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/{code}/check",
+    tag = "links",
+    params(("code" = String, Path, description = "Short code or alias")),
+    responses(
+        (status = 200, description = "The verdict", body = LinkCheck),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 404, description = "No such link", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn check_link(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+) -> Result<Response, AppError> {
+    let code = short_code::normalize(&code);
+    let link = db::find_link_by_code_or_alias(&state.db, &code)
+        .await
+        .with_context(|| format!("Failed to look up short code '{code}'"))?
+        .filter(|link| user.can_manage(link.workspace_id, link.user_id))
+        .ok_or_else(|| AppError::NotFound("Link not found".into()))?;
+
+    let trace = destination::trace(
+        &state.db,
+        &state.config,
+        &state.probe_http,
+        &link.original_url,
+    )
+    .await
+    .context("Failed to check destination")?;
+    let urls: Vec<String> = trace.hops.iter().map(|hop| hop.url.clone()).collect();
+    let threats = match &state.config.safe_browsing_api_key {
+        Some(key) => match safe_browsing::lookup(&state.http, key, &urls).await {
+            Ok(found) => Some(found),
+            Err(e) => {
+                tracing::warn!("Safe Browsing lookup for '{}' failed: {:#}", code, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let status = trace.hops.last().and_then(|hop| hop.status);
+
+    let problem = if let Some(threat) = threats.as_ref().and_then(|t| t.first()) {
+        Some(format!(
+            "Safe Browsing lists {} for {}.",
+            threat.url, threat.threat_type
+        ))
+    } else if !state.link_resolves(&link) {
+        Some("The link is switched off or archived, so it doesn't redirect.".to_owned())
+    } else if let Some(problem) = &trace.problem {
+        Some(problem.to_string())
+    } else if trace.hops.is_empty() {
+        Some("The destination isn't a valid URL.".to_owned())
+    } else {
+        match status.map(|s| StatusCode::from_u16(s).unwrap_or(StatusCode::OK)) {
+            None => Some("The destination can't be reached.".to_owned()),
+            Some(s) if s.is_client_error() || s.is_server_error() => {
+                Some(format!("The destination answers {s}."))
+            }
+            Some(_) => None,
+        }
+    };
+    let verdict = match (&problem, &threats) {
+        (None, _) => "ok",
+        (Some(_), Some(found)) if !found.is_empty() => "unsafe",
+        (Some(_), _) => "broken",
+    };
+
+    Ok(Json(LinkCheck {
+        short_url: state.short_url(link.domain_id, &link.short_code),
+        short_code: link.short_code,
+        url: link.original_url,
+        verdict: verdict.to_owned(),
+        problem,
+        status,
+        redirects: trace
+            .hops
+            .into_iter()
+            .map(|hop| CheckedHop {
+                url: hop.url,
+                status: hop.status,
+            })
+            .collect(),
+        threats: threats.map(|found| {
+            let mut types: Vec<String> = found.into_iter().map(|t| t.threat_type).collect();
+            types.sort();
+            types.dedup();
+            types
+        }),
+        checked_at: chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    })
+    .into_response())
+}
+
+// ── Analytics ──────────────────────────────────────────────────────────────
+
+/// Clicks by day and hour.
+///
+/// Counts a link's clicks by UTC day of the week and hour of the day, to show
+/// when its audience is active. Clicks from internal IPs are left out. Only
+/// the link's owner (or an admin) can read it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/links/{id}/heatmap",
+    tag = "links",
+    params(("id" = i64, Path, description = "Link id")),
+    responses(
+        (status = 200, description = "Click counts", body = Heatmap),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 404, description = "No such link", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn heatmap(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    db::get_link_by_id(&state.db, id)
+        .await
+        .with_context(|| format!("Failed to load link {id}"))?
+        .filter(|link| user.can_manage(link.workspace_id, link.user_id))
+        .ok_or_else(|| AppError::NotFound("Link not found".into()))?;
+
+    let matrix = db::clicks_by_weekday_hour(&state.db, id, db::TrafficFilter::default())
+        .await
+        .with_context(|| format!("Failed to load click heatmap for link {id}"))?;
+    Ok(Json(Heatmap {
+        link_id: id,
+        timezone: "UTC".into(),
+        days: db::WEEKDAYS.iter().map(|d| (*d).into()).collect(),
+        clicks: matrix.iter().map(|hours| hours.to_vec()).collect(),
+    })
+    .into_response())
+}
+
+/// Longest period `top_links` counts over.
+const MAX_TOP_PERIOD_HOURS: i64 = 366 * 24;
+
+/// Most-clicked links.
+///
+/// Ranks your links (or, for admins, the workspace's) by clicks over a recent
+/// period, e.g. for a leaderboard on an office screen. Clicks from internal
+/// IPs are left out.
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/top",
+    tag = "links",
+    params(TopLinksQuery),
+    responses(
+        (status = 200, description = "The leaderboard", body = TopLinks),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 422, description = "Invalid period or limit", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn top_links(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TopLinksQuery>,
+) -> Result<Response, AppError> {
+    let period = query.period.as_deref().map(str::trim).unwrap_or("7d");
+    let Some(hours) = parse_period(period).filter(|h| (1..=MAX_TOP_PERIOD_HOURS).contains(h))
+    else {
+        return Err(AppError::Unprocessable(
+            "period must be a number of hours or days up to a year, e.g. 24h or 7d".into(),
+        ));
+    };
+    let limit = query.limit.unwrap_or(20);
+    if !(1..=100).contains(&limit) {
+        return Err(AppError::Unprocessable(
+            "limit must be between 1 and 100".into(),
+        ));
+    }
+
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::hours(hours);
+    let links = db::top_links_by_clicks(&state.db, limit, Some(since), user.scope())
+        .await
+        .context("Failed to load top links")?;
+    Ok(Json(TopLinks {
+        period: period.to_owned(),
+        since: since.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        links: links
+            .into_iter()
+            .filter(|l| l.click_count > 0)
+            .map(|l| TopLink {
+                short_url: state.short_url(l.domain_id, &l.short_code),
+                id: l.id,
+                short_code: l.short_code,
+                url: l.original_url,
+                title: l.title,
+                clicks: l.click_count,
+            })
+            .collect(),
+    })
+    .into_response())
+}
+
+// ── Conversions ────────────────────────────────────────────────────────────
+
+/// Longest conversion name accepted.
+const MAX_CONVERSION_NAME_LEN: usize = 64;
+
+/// Report a conversion.
+///
+/// Ties a conversion (e.g. a signup or purchase) on the destination site back
+/// to the click that brought the visitor. Put `{click_id}` in the link's
+/// destination URL to receive the ID. Only the link's owner (or an admin) can
+/// report conversions for its clicks.
+#[utoipa::path(
+    post,
+    path = "/api/v1/conversions",
+    tag = "conversions",
+    request_body = ConversionRequest,
+    responses(
+        (status = 201, description = "Recorded", body = ConversionResponse),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 404, description = "No such click", body = Problem),
+        (status = 422, description = "Invalid name or value", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn record_conversion(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<ConversionRequest>,
+) -> Result<Response, AppError> {
+    let name = req
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or("conversion");
+    if name.chars().count() > MAX_CONVERSION_NAME_LEN {
+        return Err(AppError::Unprocessable(format!(
+            "name must be at most {MAX_CONVERSION_NAME_LEN} characters"
+        )));
+    }
+    if req.value.is_some_and(|v| !v.is_finite()) {
+        return Err(AppError::Unprocessable("value must be a number".into()));
+    }
+
+    let click_id = req.click_id.trim();
+    let link_id = match db_conversions::find_click(&state.db, click_id)
+        .await
+        .with_context(|| format!("Failed to look up click '{click_id}'"))?
+    {
+        Some((link_id, workspace_id, owner)) if user.can_manage(workspace_id, owner) => link_id,
+        _ => return Err(AppError::NotFound("Click not found".into())),
+    };
+
+    let conversion = db_conversions::create_conversion(&state.db, click_id, name, req.value)
+        .await
+        .with_context(|| format!("Failed to record conversion for '{click_id}'"))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(ConversionResponse::new(conversion, link_id)),
+    )
+        .into_response())
+}
+
+// ── REST hooks ─────────────────────────────────────────────────────────────
+
+/// Your REST hook subscriptions.
+#[utoipa::path(
+    get,
+    path = "/api/v1/hooks",
+    tag = "hooks",
+    responses(
+        (status = 200, description = "Your subscriptions", body = [Subscription]),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn list_hooks(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Response, AppError> {
+    let subs = db_hooks::get_subscriptions_for_user(&state.db, user.user_id)
+        .await
+        .context("Failed to list webhook subscriptions")?;
+    Ok(Json(subs.iter().map(Subscription::from).collect::<Vec<_>>()).into_response())
+}
+
+/// Subscribe to an event.
+///
+/// Subscribe `target_url` to an event. Admins receive events for every link;
+/// other users only for links they own.
+#[utoipa::path(
+    post,
+    path = "/api/v1/hooks",
+    tag = "hooks",
+    request_body = SubscribeRequest,
+    responses(
+        (status = 201, description = "Subscribed", body = Subscription),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 422, description = "Unknown event, or an invalid or private URL", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn subscribe(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SubscribeRequest>,
+) -> Result<Response, AppError> {
+    if !hooks::EVENTS.contains(&req.event.as_str()) {
+        return Err(AppError::Unprocessable(format!(
+            "Unknown event. Expected one of: {}",
+            hooks::EVENTS.join(", ")
+        )));
+    }
+
+    let target_url = req.target_url.trim();
+    if !target_url.starts_with("http://") && !target_url.starts_with("https://") {
+        return Err(AppError::Unprocessable(
+            "target_url must start with http:// or https://".into(),
+        ));
+    }
+    match destination::check_callback(&state.db, &state.config, target_url).await {
+        Ok(()) => {}
+        Err(DestinationError::Db(e)) => {
+            return Err(anyhow::Error::new(e)
+                .context("Failed to check webhook target")
+                .into())
+        }
+        Err(e) => return Err(AppError::Unprocessable(format!("target_url: {e}"))),
+    }
+
+    let sub = db_hooks::create_subscription(&state.db, user.user_id, &req.event, target_url)
+        .await
+        .context("Failed to create webhook subscription")?;
+    state.hooks.reload().await;
+    Ok((StatusCode::CREATED, Json(Subscription::from(&sub))).into_response())
+}
+
+/// Remove a subscription.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/hooks/{id}",
+    tag = "hooks",
+    params(("id" = i64, Path, description = "Subscription id")),
+    responses(
+        (status = 204, description = "Removed"),
+        (status = 401, description = "Missing or invalid token", body = Problem),
+        (status = 404, description = "No such subscription", body = Problem),
+        (status = 429, description = "Hourly API request limit reached", body = Problem),
+    ),
+)]
+pub async fn unsubscribe(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+) -> Result<Response, AppError> {
+    let deleted = db_hooks::delete_subscription(&state.db, id, Some(user.user_id))
+        .await
+        .with_context(|| format!("Failed to delete webhook subscription {id}"))?;
+    if !deleted {
+        return Err(AppError::NotFound("Subscription not found".into()));
+    }
+    state.hooks.reload().await;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+impl From<&WebhookSubscription> for Subscription {
+    fn from(sub: &WebhookSubscription) -> Self {
+        Self {
+            id: sub.id,
+            event: sub.event.clone(),
+            target_url: sub.target_url.clone(),
+            created_at: sub.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        }
+    }
+}
+
+impl ConversionResponse {
+    fn new(c: Conversion, link_id: i64) -> Self {
+        Self {
+            id: c.id,
+            click_id: c.click_id,
+            link_id,
+            name: c.name,
+            value: c.value,
+            created_at: c.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        }
+    }
+}
+
+/// Hours in a period like `24h` or `7d`.
+fn parse_period(period: &str) -> Option<i64> {
+    let (n, hours_per) = if let Some(n) = period.strip_suffix('h') {
+        (n, 1)
+    } else {
+        (period.strip_suffix('d')?, 24)
+    };
+    n.parse::<i64>().ok()?.checked_mul(hours_per)
+}
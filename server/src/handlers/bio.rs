@@ -2,8 +2,9 @@ use crate::{
     auth::AuthUser,
     config::AppConfig,
     db, db_bio,
+    error::AppError,
     models::{BioPage, BioPageAnalytics, BioPageFull},
-    s3 as s3_util, AppState,
+    s3 as s3_util, short_code, AppState,
 };
 use askama::Template;
 use axum::{
@@ -40,6 +41,8 @@ struct BioListTemplate {
 struct BioFormTemplate {
     page: Option<BioPageFull>,
     base_url: String,
+    /// `pattern` for the slug input.
+    slug_pattern: &'static str,
     s3_enabled: bool,
     image_search_enabled: bool,
     flash_error: Option<String>,
@@ -178,21 +181,10 @@ pub async fn list_bio_pages(
         .max_age(time::Duration::seconds(0))
         .build();
 
-    let user_filter = if auth.is_admin() {
-        None
-    } else {
-        Some(auth.user_id)
-    };
-
-    let pages = match db_bio::get_all_bio_pages(&state.db, user_filter).await {
+    let pages = match db_bio::get_all_bio_pages(&state.db, auth.scope()).await {
         Ok(p) => p,
         Err(e) => {
-            tracing::error!("Failed to load bio pages: {:?}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to load bio pages",
-            )
-                .into_response();
+            return AppError::internal(e, "Failed to load bio pages").into_response();
         }
     };
 
@@ -213,6 +205,10 @@ pub async fn new_bio_page(auth: AuthUser, State(state): State<Arc<AppState>>) ->
     BioFormTemplate {
         page: None,
         base_url: state.config.base_url.clone(),
+        slug_pattern: short_code::input_pattern(
+            state.config.unicode_short_codes,
+            state.config.emoji_short_codes,
+        ),
         s3_enabled: state.config.s3_configured(),
         image_search_enabled: state.config.image_search_configured(),
         flash_error: None,
@@ -231,14 +227,25 @@ pub async fn create_bio_page(
     jar: CookieJar,
     axum::extract::Form(form): axum::extract::Form<BioPageForm>,
 ) -> Response {
-    let slug = form.slug.trim().to_lowercase();
-    if slug.is_empty() || !slug.chars().all(|c| c.is_alphanumeric() || c == '-') {
+    let Some(slug) = short_code::parse(
+        &form.slug.to_lowercase(),
+        state.config.unicode_short_codes,
+        state.config.emoji_short_codes,
+    ) else {
         return set_flash_and_redirect(
             jar,
             None,
             Some("Slug must contain only letters, numbers, and hyphens."),
             "/admin/bio/new",
         );
+    };
+    if short_code::is_reserved(&slug, state.config.public_shorten) {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("That slug is reserved for Linkly's own pages."),
+            "/admin/bio/new",
+        );
     }
 
     // Ensure slug doesn't collide with an existing short link code
@@ -276,7 +283,7 @@ pub async fn create_bio_page(
         Ok(page) => page,
         Err(e) => {
             tracing::error!("Failed to create links page: {:?}", e);
-            let msg = if e.to_string().contains("UNIQUE") {
+            let msg = if db::is_unique_violation(&e) {
                 "That slug is already taken."
             } else {
                 "Database error creating links page."
@@ -377,13 +384,17 @@ pub async fn edit_bio_page(
     match db_bio::get_bio_page_full(&state.db, id).await {
         Ok(Some(page_full)) => {
             // Ownership check
-            if !auth.is_admin() && page_full.page.user_id != Some(auth.user_id) {
-                return (StatusCode::FORBIDDEN, "Access denied").into_response();
+            if !auth.can_manage(page_full.page.workspace_id, page_full.page.user_id) {
+                return AppError::Forbidden("Access denied".into()).into_response();
             }
 
             let tmpl = BioFormTemplate {
                 page: Some(page_full),
                 base_url: state.config.base_url.clone(),
+                slug_pattern: short_code::input_pattern(
+                    state.config.unicode_short_codes,
+                    state.config.emoji_short_codes,
+                ),
                 s3_enabled: state.config.s3_configured(),
                 image_search_enabled: state.config.image_search_configured(),
                 flash_error,
@@ -394,11 +405,8 @@ pub async fn edit_bio_page(
             };
             (jar.remove(clear_error), tmpl).into_response()
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "Links page not found").into_response(),
-        Err(e) => {
-            tracing::error!("Failed to load bio page {}: {:?}", id, e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response()
-        }
+        Ok(None) => AppError::NotFound("Links page not found".into()).into_response(),
+        Err(e) => AppError::internal(e, format!("Failed to load bio page {id}")).into_response(),
     }
 }
 
@@ -412,19 +420,30 @@ pub async fn update_bio_page(
 ) -> Response {
     // Ownership check
     if let Ok(Some(page)) = db_bio::get_bio_page_by_id(&state.db, id).await {
-        if !auth.is_admin() && page.user_id != Some(auth.user_id) {
-            return (StatusCode::FORBIDDEN, "Access denied").into_response();
+        if !auth.can_manage(page.workspace_id, page.user_id) {
+            return AppError::Forbidden("Access denied".into()).into_response();
         }
     }
 
-    let slug = form.slug.trim().to_lowercase();
-    if slug.is_empty() || !slug.chars().all(|c| c.is_alphanumeric() || c == '-') {
+    let Some(slug) = short_code::parse(
+        &form.slug.to_lowercase(),
+        state.config.unicode_short_codes,
+        state.config.emoji_short_codes,
+    ) else {
         return set_flash_and_redirect(
             jar,
             None,
             Some("Slug must contain only letters, numbers, and hyphens."),
             &format!("/admin/bio/{}/edit", id),
         );
+    };
+    if short_code::is_reserved(&slug, state.config.public_shorten) {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("That slug is reserved for Linkly's own pages."),
+            &format!("/admin/bio/{}/edit", id),
+        );
     }
 
     // Ensure slug doesn't collide with an existing short link code
@@ -488,7 +507,7 @@ pub async fn update_bio_page(
         Ok(()) => {}
         Err(e) => {
             tracing::error!("Failed to update bio page {}: {:?}", id, e);
-            let msg = if e.to_string().contains("UNIQUE") {
+            let msg = if db::is_unique_violation(&e) {
                 "That slug is already taken."
             } else {
                 "Database error updating links page."
@@ -545,7 +564,7 @@ pub async fn delete_bio_page(
 ) -> Response {
     // Ownership check
     if let Ok(Some(page)) = db_bio::get_bio_page_by_id(&state.db, id).await {
-        if !auth.is_admin() && page.user_id != Some(auth.user_id) {
+        if !auth.can_manage(page.workspace_id, page.user_id) {
             return set_flash_and_redirect(jar, None, Some("Access denied."), "/admin/bio");
         }
     }
@@ -574,21 +593,17 @@ pub async fn bio_analytics(
     let analytics = match db_bio::get_bio_page_analytics(&state.db, id).await {
         Ok(Some(a)) => a,
         Ok(None) => {
-            return (StatusCode::NOT_FOUND, "Links page not found").into_response();
+            return AppError::NotFound("Links page not found".into()).into_response();
         }
         Err(e) => {
-            tracing::error!("Failed to load analytics for bio page {}: {:?}", id, e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to load analytics",
-            )
+            return AppError::internal(e, format!("Failed to load analytics for bio page {id}"))
                 .into_response();
         }
     };
 
     // Ownership check
-    if !auth.is_admin() && analytics.page.user_id != Some(auth.user_id) {
-        return (StatusCode::FORBIDDEN, "Access denied").into_response();
+    if !auth.can_manage(analytics.page.workspace_id, analytics.page.user_id) {
+        return AppError::Forbidden("Access denied".into()).into_response();
     }
 
     let total_views = analytics.total_views;
@@ -638,17 +653,13 @@ pub async fn upload_image(
     mut multipart: Multipart,
 ) -> Response {
     if !state.config.s3_configured() {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": "S3 not configured"})),
-        )
-            .into_response();
+        return AppError::BadRequest("S3 not configured".into()).into_response();
     }
 
     let bucket = match s3_util::get_bucket(&state.config) {
         Some(b) => b,
         None => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to initialize S3").into_response()
+            return AppError::Internal(anyhow::anyhow!("Failed to initialize S3")).into_response()
         }
     };
 
@@ -667,21 +678,24 @@ pub async fn upload_image(
             "image/webp" => "webp",
             "image/gif" => "gif",
             _ => {
-                return (StatusCode::BAD_REQUEST, "Unsupported image type").into_response();
+                return AppError::BadRequest("Unsupported image type".into()).into_response();
             }
         };
 
         let data = match field.bytes().await {
             Ok(d) => d,
+            Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                return AppError::PayloadTooLarge("File too large".into()).into_response();
+            }
             Err(e) => {
                 tracing::error!("Failed to read upload: {:?}", e);
-                return (StatusCode::BAD_REQUEST, "Failed to read file").into_response();
+                return AppError::BadRequest("Failed to read file".into()).into_response();
             }
         };
 
         // Limit to 5 MB
         if data.len() > 5 * 1024 * 1024 {
-            return (StatusCode::BAD_REQUEST, "File too large (max 5 MB)").into_response();
+            return AppError::BadRequest("File too large (max 5 MB)".into()).into_response();
         }
 
         tracing::info!("Uploading {} bytes ({}) to S3...", data.len(), content_type);
@@ -691,13 +705,12 @@ pub async fn upload_image(
                 return Json(UploadResponse { url }).into_response();
             }
             Err(e) => {
-                tracing::error!("S3 upload failed: {:?}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Upload failed").into_response();
+                return AppError::internal(e, "S3 upload failed").into_response();
             }
         }
     }
 
-    (StatusCode::BAD_REQUEST, "No file field found").into_response()
+    AppError::BadRequest("No file field found".into()).into_response()
 }
 
 /// GET /admin/bio/unsplash?q=nature&page=1
@@ -719,23 +732,17 @@ pub async fn search_images(
     Query(query): Query<ImageSearchQuery>,
 ) -> Response {
     if !state.config.image_search_configured() {
-        return (
-            StatusCode::BAD_REQUEST,
-            "No image search provider configured",
-        )
-            .into_response();
+        return AppError::BadRequest("No image search provider configured".into()).into_response();
     }
 
     let page = query.page.unwrap_or(1);
     let has_both =
         state.config.pexels_api_key.is_some() && state.config.unsplash_access_key.is_some();
     let per_page: u32 = if has_both { 8 } else { 15 };
-    let client = reqwest::Client::new();
-
     // Query both providers concurrently
     let (pexels, unsplash) = tokio::join!(
-        fetch_pexels(&client, &state.config, &query.q, page, per_page),
-        fetch_unsplash(&client, &state.config, &query.q, page, per_page),
+        fetch_pexels(&state.http, &state.config, &query.q, page, per_page),
+        fetch_unsplash(&state.http, &state.config, &query.q, page, per_page),
     );
 
     let results = if has_both && !pexels.is_empty() && !unsplash.is_empty() {
@@ -910,7 +917,7 @@ pub async fn validate_slug(
         .and_then(|v| v.get("slug")?.as_str().map(String::from))
         .unwrap_or_default();
     let current_id = signals.as_ref().and_then(|v| v.get("currentid")?.as_i64());
-    let slug = slug.trim().to_lowercase();
+    let slug = short_code::normalize(slug.trim()).to_lowercase();
     tracing::info!("validate_slug called with: {:?}", slug);
 
     let icon_style = r#"position:absolute; right:0.6rem; top:50%; transform:translateY(-50%); font-size:1.1rem; pointer-events:none;"#;
@@ -920,7 +927,14 @@ pub async fn validate_slug(
             r#"<span id="slug-validation" style="{}"></span>"#,
             icon_style
         )
-    } else if !slug.chars().all(|c| c.is_alphanumeric() || c == '-') {
+    } else if short_code::parse(
+        &slug,
+        state.config.unicode_short_codes,
+        state.config.emoji_short_codes,
+    )
+    .is_none()
+        || short_code::is_reserved(&slug, state.config.public_shorten)
+    {
         format!(
             r#"<span id="slug-validation" style="{} color:#dc2626;">&#10007;</span>"#,
             icon_style
@@ -1014,7 +1028,7 @@ fn count_field<'a>(iter: impl Iterator<Item = Option<&'a str>>) -> Vec<(String,
         }
     }
     let mut sorted: Vec<(String, i64)> = counts.into_iter().collect();
-    sorted.sort_by(|a, b| b.1.cmp(&a.1));
+    sorted.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
     sorted.truncate(10);
     sorted
 }
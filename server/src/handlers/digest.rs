@@ -0,0 +1,61 @@
+use crate::{db_digest, digest, error::AppError, AppState};
+use askama::Template;
+use axum::{
+    extract::{Form, Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Template)]
+#[template(path = "digest_unsubscribe.html")]
+struct UnsubscribeTemplate {
+    email: String,
+    token: String,
+    done: bool,
+    app_title: String,
+}
+
+#[derive(Deserialize)]
+pub struct UnsubscribeParams {
+    email: String,
+    token: String,
+}
+
+/// GET /digest/unsubscribe?email=…&token=…
+///
+/// Confirmation page. Unsubscribing takes a POST so that mail scanners
+/// following the link don't opt people out.
+pub async fn unsubscribe_page(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<UnsubscribeParams>,
+) -> Response {
+    if !digest::verify_unsubscribe_token(&state.config.jwt_secret, &params.email, &params.token) {
+        return AppError::NotFound("Not found".into()).into_response();
+    }
+    render(&state, params, false)
+}
+
+/// POST /digest/unsubscribe
+pub async fn unsubscribe(
+    State(state): State<Arc<AppState>>,
+    Form(params): Form<UnsubscribeParams>,
+) -> Response {
+    if !digest::verify_unsubscribe_token(&state.config.jwt_secret, &params.email, &params.token) {
+        return AppError::NotFound("Not found".into()).into_response();
+    }
+    if let Err(e) = db_digest::opt_out(&state.db, &params.email).await {
+        return AppError::internal(e, "Failed to record digest opt-out").into_response();
+    }
+    render(&state, params, true)
+}
+
+fn render(state: &AppState, params: UnsubscribeParams, done: bool) -> Response {
+    UnsubscribeTemplate {
+        email: params.email,
+        token: params.token,
+        done,
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
@@ -0,0 +1,453 @@
+use crate::{
+    auth::InstanceAdmin,
+    db, db_domains, db_workspaces,
+    error::AppError,
+    models::{Domain, Workspace},
+    AppState,
+};
+use askama::Template;
+use axum::{
+    extract::{Form, Path, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// ── Template ──────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "domains.html")]
+struct DomainsTemplate {
+    /// (domain, name of the workspace it is reserved for)
+    domains: Vec<(Domain, Option<String>)>,
+    /// Whether there is more than one workspace to reserve domains for.
+    show_workspaces: bool,
+    primary_base_url: String,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+#[derive(Template)]
+#[template(path = "edit_domain.html")]
+struct EditDomainTemplate {
+    domain: Domain,
+    /// Workspaces the domain can be reserved for; empty when there is only
+    /// the default one.
+    workspaces: Vec<Workspace>,
+    default_root_redirect_url: String,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+// ── Form types ────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct CreateDomainForm {
+    hostname: String,
+    base_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EditDomainForm {
+    base_url: String,
+    root_redirect_url: Option<String>,
+    not_found_url: Option<String>,
+    logo_url: Option<String>,
+    brand_color: Option<String>,
+    /// Workspace ID to reserve the domain for; empty to share it.
+    workspace_id: Option<String>,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────
+
+/// GET /admin/domains
+pub async fn list_domains(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let (domains, workspaces) = match tokio::try_join!(
+        db_domains::get_all_domains(&state.db),
+        db_workspaces::get_all_workspaces(&state.db),
+    ) {
+        Ok(rows) => rows,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load domains").into_response();
+        }
+    };
+    let domains = domains
+        .into_iter()
+        .map(|d| {
+            let workspace = workspaces
+                .iter()
+                .find(|(w, _, _)| Some(w.id) == d.workspace_id)
+                .map(|(w, _, _)| w.name.clone());
+            (d, workspace)
+        })
+        .collect();
+
+    let tmpl = DomainsTemplate {
+        domains,
+        show_workspaces: workspaces.len() > 1,
+        primary_base_url: state.config.base_url.clone(),
+        flash_success,
+        flash_error,
+        is_admin: true,
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/domains — Add a short domain
+pub async fn create_domain(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<CreateDomainForm>,
+) -> Response {
+    let hostname = form.hostname.trim().trim_end_matches('.').to_lowercase();
+
+    if hostname.is_empty()
+        || !hostname
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Host name may only contain letters, numbers, hyphens, and dots (no scheme or port)."),
+            "/admin/domains",
+        );
+    }
+
+    if host_of(&state.config.base_url) == Some(hostname.as_str()) {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("That host name is the primary domain (BASE_URL) and is always served."),
+            "/admin/domains",
+        );
+    }
+
+    let base_url = match form
+        .base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        Some(url) => url.trim_end_matches('/').to_owned(),
+        None => format!("https://{hostname}"),
+    };
+    if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Base URL must start with http:// or https://"),
+            "/admin/domains",
+        );
+    }
+
+    match db_domains::create_domain(&state.db, &hostname, &base_url).await {
+        Ok(domain) => {
+            reload_domains(&state).await;
+            set_flash_and_redirect(
+                jar,
+                Some(&format!("Domain '{}' added.", domain.hostname)),
+                None,
+                "/admin/domains",
+            )
+        }
+        Err(e) => {
+            tracing::error!("Failed to create domain: {:?}", e);
+            let msg = if db::is_unique_violation(&e) {
+                "That host name is already configured.".to_owned()
+            } else {
+                format!("Database error: {e}")
+            };
+            set_flash_and_redirect(jar, None, Some(&msg), "/admin/domains")
+        }
+    }
+}
+
+// ── Edit domain ───────────────────────────────────────────────────────────
+
+/// GET /admin/domains/:id/edit
+pub async fn edit_domain_page(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let (domain, workspaces) = match tokio::try_join!(
+        db_domains::get_domain_by_id(&state.db, id),
+        db_workspaces::get_all_workspaces(&state.db),
+    ) {
+        Ok((Some(d), w)) => (d, w),
+        Ok((None, _)) => {
+            return set_flash_and_redirect(jar, None, Some("Domain not found."), "/admin/domains")
+        }
+        Err(e) => {
+            tracing::error!("Failed to load domain {}: {:?}", id, e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to load domain."),
+                "/admin/domains",
+            );
+        }
+    };
+    let workspaces = if workspaces.len() > 1 {
+        workspaces.into_iter().map(|(w, _, _)| w).collect()
+    } else {
+        Vec::new()
+    };
+
+    let tmpl = EditDomainTemplate {
+        domain,
+        workspaces,
+        default_root_redirect_url: state.tunables().root_redirect_url.clone(),
+        flash_success,
+        flash_error,
+        is_admin: true,
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/domains/:id/edit
+pub async fn edit_domain(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+    Form(form): Form<EditDomainForm>,
+) -> Response {
+    let edit_url = format!("/admin/domains/{id}/edit");
+
+    let base_url = form.base_url.trim().trim_end_matches('/').to_owned();
+    let root_redirect_url = optional_field(form.root_redirect_url);
+    let not_found_url = optional_field(form.not_found_url);
+    let logo_url = optional_field(form.logo_url);
+
+    for url in [
+        Some(&base_url),
+        root_redirect_url.as_ref(),
+        not_found_url.as_ref(),
+        logo_url.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("URLs must start with http:// or https://"),
+                &edit_url,
+            );
+        }
+    }
+
+    let brand_color = optional_field(form.brand_color).map(|c| c.to_lowercase());
+    if let Some(color) = &brand_color {
+        let valid = color.len() == 7
+            && color.starts_with('#')
+            && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+        if !valid {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Brand colour must be a hex colour such as #3b82f6."),
+                &edit_url,
+            );
+        }
+    }
+
+    let workspace_id = match optional_field(form.workspace_id).map(|s| s.parse::<i64>()) {
+        None => None,
+        Some(Ok(ws)) => Some(ws),
+        Some(Err(_)) => {
+            return set_flash_and_redirect(jar, None, Some("Unknown workspace."), &edit_url);
+        }
+    };
+    match db_domains::set_domain_workspace(&state.db, id, workspace_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Links from other workspaces still use this domain; move them first."),
+                &edit_url,
+            );
+        }
+        Err(e) => {
+            tracing::error!("Failed to set workspace of domain {}: {:?}", id, e);
+            let msg = if e.to_string().contains("FOREIGN KEY") {
+                "Unknown workspace."
+            } else {
+                "Failed to update domain."
+            };
+            return set_flash_and_redirect(jar, None, Some(msg), &edit_url);
+        }
+    }
+
+    match db_domains::update_domain(
+        &state.db,
+        id,
+        &base_url,
+        root_redirect_url.as_deref(),
+        not_found_url.as_deref(),
+        logo_url.as_deref(),
+        brand_color.as_deref(),
+    )
+    .await
+    {
+        Ok(true) => {
+            reload_domains(&state).await;
+            set_flash_and_redirect(jar, Some("Domain updated."), None, &edit_url)
+        }
+        Ok(false) => set_flash_and_redirect(jar, None, Some("Domain not found."), "/admin/domains"),
+        Err(e) => {
+            tracing::error!("Failed to update domain {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to update domain."), &edit_url)
+        }
+    }
+}
+
+// ── Delete domain ─────────────────────────────────────────────────────────
+
+/// POST /admin/domains/:id/delete
+pub async fn delete_domain(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    let domain = match db_domains::get_domain_by_id(&state.db, id).await {
+        Ok(Some(d)) => d,
+        Ok(None) => {
+            return set_flash_and_redirect(jar, None, Some("Domain not found."), "/admin/domains");
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch domain {}: {:?}", id, e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Database error while looking up domain."),
+                "/admin/domains",
+            );
+        }
+    };
+
+    match db_domains::delete_domain(&state.db, id).await {
+        Ok(true) => {
+            reload_domains(&state).await;
+            state.cache.reassign_to_primary(id);
+            set_flash_and_redirect(
+                jar,
+                Some(&format!(
+                    "Domain '{}' removed. Its links now use the primary domain.",
+                    domain.hostname
+                )),
+                None,
+                "/admin/domains",
+            )
+        }
+        Ok(false) => set_flash_and_redirect(jar, None, Some("Domain not found."), "/admin/domains"),
+        Err(e) => {
+            tracing::error!("Failed to delete domain {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to delete domain."),
+                "/admin/domains",
+            )
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// Refresh the in-memory host lookup table from the database.
+async fn reload_domains(state: &AppState) {
+    match db_domains::get_all_domains(&state.db).await {
+        Ok(domains) => state.domains.reload(domains),
+        Err(e) => tracing::error!("Failed to reload domains: {:?}", e),
+    }
+}
+
+/// Trim a form field, treating blank input as unset.
+fn optional_field(value: Option<String>) -> Option<String> {
+    value.map(|s| s.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+/// Host part of a base URL, e.g. `https://go.example.com:8080` → `go.example.com`.
+fn host_of(url: &str) -> Option<&str> {
+    let rest = url.split_once("://")?.1;
+    rest.split(['/', ':']).next()
+}
+
+fn set_flash_and_redirect(
+    jar: CookieJar,
+    success: Option<&str>,
+    error: Option<&str>,
+    destination: &str,
+) -> Response {
+    let mut jar = jar;
+
+    if let Some(msg) = success {
+        let c = Cookie::build(("flash_success", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    if let Some(msg) = error {
+        let c = Cookie::build(("flash_error", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    (jar, Redirect::to(destination)).into_response()
+}
@@ -0,0 +1,69 @@
+use crate::{auth::ApiUser, db, error::AppError, models::Link, AppState};
+use askama::Template;
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+/// Entries in the feed.
+const FEED_LENGTH: i64 = 50;
+
+#[derive(Template)]
+#[template(path = "feed.xml")]
+struct FeedTemplate {
+    /// Feed and entry ids are built from the instance's base URL.
+    base_url: String,
+    /// When the newest link was created; the epoch for an empty feed.
+    updated: String,
+    entries: Vec<FeedEntry>,
+    app_title: String,
+}
+
+struct FeedEntry {
+    link: Link,
+    short_url: String,
+    created_at: String,
+}
+
+/// GET /admin/feed.xml?key=…
+///
+/// Atom feed of the newest links the token's owner can see, for feed readers
+/// and internal tools. Takes an API token like `/api/v1`, in the `key` query
+/// parameter since feed readers can't send headers.
+pub async fn new_links(user: ApiUser, State(state): State<Arc<AppState>>) -> Response {
+    let links = match db::recent_links(&state.db, user.scope(), FEED_LENGTH).await {
+        Ok(links) => links,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load links for the feed").into_response();
+        }
+    };
+
+    let entries: Vec<FeedEntry> = links
+        .into_iter()
+        .map(|link| FeedEntry {
+            short_url: state.short_url(link.domain_id, &link.short_code),
+            created_at: link.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            link,
+        })
+        .collect();
+    let feed = FeedTemplate {
+        base_url: state.config.base_url.clone(),
+        updated: entries.first().map_or_else(
+            || "1970-01-01T00:00:00Z".to_owned(),
+            |e| e.created_at.clone(),
+        ),
+        entries,
+        app_title: state.config.app_title.clone(),
+    };
+
+    match feed.render() {
+        Ok(xml) => (
+            [(header::CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+            xml,
+        )
+            .into_response(),
+        Err(e) => AppError::internal(e, "Failed to render the feed").into_response(),
+    }
+}
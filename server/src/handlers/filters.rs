@@ -0,0 +1,168 @@
+use crate::{
+    auth::InstanceAdmin, click_filters, db, db_click_filters, error::AppError, models::ClickFilter,
+    AppState,
+};
+use askama::Template;
+use axum::{
+    extract::{Form, Path, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+const FILTERS_PATH: &str = "/admin/settings/filters";
+
+// ── Template ──────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "click_filters.html")]
+struct ClickFiltersTemplate {
+    filters: Vec<ClickFilter>,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+// ── Form types ────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct CreateFilterForm {
+    pattern: String,
+    /// `regex` for a regular expression, anything else for a substring.
+    kind: String,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────
+
+/// GET /admin/settings/filters
+pub async fn list_filters(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let filters = match db_click_filters::get_click_filters(&state.db).await {
+        Ok(f) => f,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load click filters").into_response();
+        }
+    };
+
+    let tmpl = ClickFiltersTemplate {
+        filters,
+        flash_success,
+        flash_error,
+        is_admin: true,
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/settings/filters — Add a filter
+pub async fn create_filter(
+    admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<CreateFilterForm>,
+) -> Response {
+    let pattern = form.pattern.trim();
+    if pattern.is_empty() {
+        return set_flash_and_redirect(jar, None, Some("A pattern is required."));
+    }
+    let is_regex = form.kind == "regex";
+    if is_regex {
+        if let Err(msg) = click_filters::validate_regex(pattern) {
+            return set_flash_and_redirect(jar, None, Some(&msg));
+        }
+    }
+
+    match db_click_filters::create_click_filter(&state.db, pattern, is_regex, admin.0.user_id).await
+    {
+        Ok(()) => {
+            tracing::info!("Click filter '{}' added by {}", pattern, admin.0.email);
+            reload(&state).await;
+            set_flash_and_redirect(jar, Some("Filter added."), None)
+        }
+        Err(e) => {
+            tracing::error!("Failed to create click filter: {:?}", e);
+            let msg = if db::is_unique_violation(&e) {
+                "That filter already exists."
+            } else {
+                "Failed to add filter."
+            };
+            set_flash_and_redirect(jar, None, Some(msg))
+        }
+    }
+}
+
+/// POST /admin/settings/filters/:id/delete
+pub async fn delete_filter(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    match db_click_filters::delete_click_filter(&state.db, id).await {
+        Ok(true) => {
+            reload(&state).await;
+            set_flash_and_redirect(jar, Some("Filter deleted."), None)
+        }
+        Ok(false) => set_flash_and_redirect(jar, None, Some("Filter not found.")),
+        Err(e) => {
+            tracing::error!("Failed to delete click filter {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to delete filter."))
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// Apply a change to the filters to the redirects that follow.
+async fn reload(state: &AppState) {
+    if let Err(e) = state.click_filters.reload(&state.db).await {
+        tracing::error!("Failed to reload click filters: {:?}", e);
+    }
+}
+
+fn set_flash_and_redirect(jar: CookieJar, success: Option<&str>, error: Option<&str>) -> Response {
+    let mut jar = jar;
+
+    if let Some(msg) = success {
+        let c = Cookie::build(("flash_success", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    if let Some(msg) = error {
+        let c = Cookie::build(("flash_error", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    (jar, Redirect::to(FILTERS_PATH)).into_response()
+}
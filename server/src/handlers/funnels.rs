@@ -0,0 +1,332 @@
+use crate::{
+    auth::AuthUser,
+    db, db_funnels,
+    error::AppError,
+    funnel::{self, MAX_STEPS, MAX_WINDOW_HOURS},
+    models::{Funnel, FunnelStep},
+    AppState,
+};
+use askama::Template;
+use axum::{
+    extract::{Form, Path, Query, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+const FUNNELS_PATH: &str = "/admin/funnels";
+
+/// Days a funnel report can cover.
+const FUNNEL_PERIODS: [i64; 3] = [7, 30, 90];
+
+/// Longest funnel name.
+const MAX_NAME_LEN: usize = 100;
+
+// ── Templates ─────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "funnels.html")]
+struct FunnelsTemplate {
+    /// Each funnel with its steps' labels.
+    funnels: Vec<(Funnel, Vec<String>)>,
+    max_steps: usize,
+    max_window_hours: i64,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+#[derive(Template)]
+#[template(path = "funnel.html")]
+struct FunnelTemplate {
+    funnel: Funnel,
+    steps: Vec<ReportStep>,
+    days: i64,
+    periods: [i64; 3],
+    /// Visitors who reached the last step, as a share of the first.
+    overall_rate: String,
+    is_admin: bool,
+    app_title: String,
+}
+
+/// One step of a funnel report.
+struct ReportStep {
+    link: FunnelStep,
+    label: String,
+    visitors: i64,
+    /// Share of the previous step's visitors who went on to this one.
+    from_previous: String,
+    /// Share of the first step's visitors, for the bar.
+    pct_of_first: i64,
+}
+
+// ── Form types ────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct CreateFunnelForm {
+    name: String,
+    window_hours: String,
+    /// Short codes, one per line, in the order visitors should click them.
+    steps: String,
+}
+
+#[derive(Deserialize)]
+pub struct FunnelQuery {
+    days: Option<i64>,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────
+
+/// GET /admin/funnels
+pub async fn list_funnels(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let funnels = match db_funnels::get_funnels(&state.db, auth.scope()).await {
+        Ok(f) => f,
+        Err(e) => return AppError::internal(e, "Failed to load funnels").into_response(),
+    };
+    let mut listed = Vec::with_capacity(funnels.len());
+    for funnel in funnels {
+        let steps = match db_funnels::get_funnel_steps(&state.db, funnel.id).await {
+            Ok(steps) => steps,
+            Err(e) => {
+                return AppError::internal(e, format!("Failed to load funnel {}", funnel.id))
+                    .into_response();
+            }
+        };
+        listed.push((funnel, steps.into_iter().map(|s| s.short_code).collect()));
+    }
+
+    let tmpl = FunnelsTemplate {
+        funnels: listed,
+        max_steps: MAX_STEPS,
+        max_window_hours: MAX_WINDOW_HOURS,
+        flash_success,
+        flash_error,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/funnels — Create a funnel
+pub async fn create_funnel(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<CreateFunnelForm>,
+) -> Response {
+    let name = form.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        let msg = format!("A name of 1 to {MAX_NAME_LEN} characters is required.");
+        return set_flash_and_redirect(jar, None, Some(&msg), FUNNELS_PATH);
+    }
+    let Some(window_hours) = form
+        .window_hours
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|h| (1..=MAX_WINDOW_HOURS).contains(h))
+    else {
+        let msg = format!("The time between steps must be 1 to {MAX_WINDOW_HOURS} hours.");
+        return set_flash_and_redirect(jar, None, Some(&msg), FUNNELS_PATH);
+    };
+
+    let codes: Vec<&str> = form
+        .steps
+        .lines()
+        .map(|l| l.trim().trim_start_matches('/'))
+        .filter(|l| !l.is_empty())
+        .collect();
+    if !(2..=MAX_STEPS).contains(&codes.len()) {
+        let msg = format!("A funnel needs 2 to {MAX_STEPS} steps, one short code per line.");
+        return set_flash_and_redirect(jar, None, Some(&msg), FUNNELS_PATH);
+    }
+    let mut link_ids = Vec::with_capacity(codes.len());
+    for code in codes {
+        let link = match db::find_link_by_code_or_alias(&state.db, code).await {
+            Ok(Some(link)) if auth.can_manage(link.workspace_id, link.user_id) => link,
+            Ok(_) => {
+                let msg = format!("No link has the short code '{code}'.");
+                return set_flash_and_redirect(jar, None, Some(&msg), FUNNELS_PATH);
+            }
+            Err(e) => {
+                tracing::error!("Failed to look up funnel step '{}': {:?}", code, e);
+                return set_flash_and_redirect(
+                    jar,
+                    None,
+                    Some("Failed to create funnel."),
+                    FUNNELS_PATH,
+                );
+            }
+        };
+        if link_ids.contains(&link.id) {
+            let msg = format!("'{code}' is in the funnel more than once.");
+            return set_flash_and_redirect(jar, None, Some(&msg), FUNNELS_PATH);
+        }
+        link_ids.push(link.id);
+    }
+
+    match db_funnels::create_funnel(&state.db, auth.user_id, name, window_hours, &link_ids).await {
+        Ok(_) => {
+            tracing::info!("Funnel '{}' created by {}", name, auth.email);
+            set_flash_and_redirect(jar, Some("Funnel created."), None, FUNNELS_PATH)
+        }
+        Err(e) => {
+            tracing::error!("Failed to create funnel: {:?}", e);
+            set_flash_and_redirect(jar, None, Some("Failed to create funnel."), FUNNELS_PATH)
+        }
+    }
+}
+
+/// GET /admin/funnels/:id — Conversion between the funnel's steps
+pub async fn funnel_report(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Query(query): Query<FunnelQuery>,
+) -> Response {
+    let funnel = match db_funnels::get_funnel(&state.db, id).await {
+        Ok(Some(f)) if auth.can_manage(f.workspace_id, f.user_id) => f,
+        Ok(Some(_)) => return AppError::Forbidden("Access denied".into()).into_response(),
+        Ok(None) => return AppError::NotFound("Funnel not found".into()).into_response(),
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load funnel {id}")).into_response();
+        }
+    };
+
+    let days = query
+        .days
+        .filter(|d| FUNNEL_PERIODS.contains(d))
+        .unwrap_or(30);
+    let since = (chrono::Utc::now() - chrono::Duration::days(days)).naive_utc();
+
+    let steps = match db_funnels::get_funnel_steps(&state.db, id).await {
+        Ok(steps) => steps,
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load funnel {id}")).into_response();
+        }
+    };
+    let link_ids: Vec<i64> = steps.iter().map(|s| s.link_id).collect();
+    let clicks = match db_funnels::get_funnel_clicks(&state.db, &link_ids, since).await {
+        Ok(clicks) => clicks,
+        Err(e) => {
+            return AppError::internal(e, format!("Failed to load clicks for funnel {id}"))
+                .into_response();
+        }
+    };
+    let counts = funnel::count_steps(
+        &link_ids,
+        chrono::Duration::hours(funnel.window_hours),
+        &clicks,
+    );
+
+    let first = counts.first().copied().unwrap_or(0);
+    let last = counts.last().copied().unwrap_or(0);
+    let report = steps
+        .into_iter()
+        .zip(&counts)
+        .enumerate()
+        .map(|(i, (link, &visitors))| ReportStep {
+            label: link
+                .title
+                .clone()
+                .unwrap_or_else(|| link.short_code.clone()),
+            link,
+            visitors,
+            from_previous: match i {
+                0 => String::new(),
+                _ => funnel::rate(visitors, counts[i - 1]),
+            },
+            pct_of_first: if first > 0 { visitors * 100 / first } else { 0 },
+        })
+        .collect();
+
+    FunnelTemplate {
+        funnel,
+        steps: report,
+        days,
+        periods: FUNNEL_PERIODS,
+        overall_rate: funnel::rate(last, first),
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+/// POST /admin/funnels/:id/delete
+pub async fn delete_funnel(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    // Ownership check
+    if let Ok(Some(funnel)) = db_funnels::get_funnel(&state.db, id).await {
+        if !auth.can_manage(funnel.workspace_id, funnel.user_id) {
+            return set_flash_and_redirect(jar, None, Some("Access denied."), FUNNELS_PATH);
+        }
+    }
+
+    match db_funnels::delete_funnel(&state.db, id).await {
+        Ok(true) => set_flash_and_redirect(jar, Some("Funnel deleted."), None, FUNNELS_PATH),
+        Ok(false) => set_flash_and_redirect(jar, None, Some("Funnel not found."), FUNNELS_PATH),
+        Err(e) => {
+            tracing::error!("Failed to delete funnel {}: {:?}", id, e);
+            set_flash_and_redirect(jar, None, Some("Failed to delete funnel."), FUNNELS_PATH)
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+fn set_flash_and_redirect(
+    jar: CookieJar,
+    success: Option<&str>,
+    error: Option<&str>,
+    destination: &str,
+) -> Response {
+    let mut jar = jar;
+
+    if let Some(msg) = success {
+        let c = Cookie::build(("flash_success", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    if let Some(msg) = error {
+        let c = Cookie::build(("flash_error", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    (jar, Redirect::to(destination)).into_response()
+}
@@ -0,0 +1,45 @@
+use crate::{auth::ApiUser, error::AppError, graphql::Viewer, AppState};
+use async_graphql::http::GraphiQLSource;
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse, Json, Response},
+};
+use std::sync::Arc;
+
+/// POST /api/graphql
+///
+/// Runs a query against the reporting schema as the token's owner. Returns
+/// 404 unless `GRAPHQL_ENABLED` is set.
+pub async fn execute(
+    user: ApiUser,
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<async_graphql::Request>,
+) -> Response {
+    let Some(schema) = &state.graphql else {
+        return AppError::NotFound("Not found".into()).into_response();
+    };
+
+    let request = request.data(state.clone()).data(Viewer {
+        user_id: user.user_id,
+        is_admin: user.is_admin(),
+        workspace_id: user.workspace_id,
+    });
+    Json(schema.execute(request).await).into_response()
+}
+
+/// GET /api/graphql
+///
+/// GraphiQL explorer. Add `Authorization: Bearer <token>` under Headers to
+/// run queries.
+pub async fn graphiql(State(state): State<Arc<AppState>>) -> Response {
+    if state.graphql.is_none() {
+        return AppError::NotFound("Not found".into()).into_response();
+    }
+    Html(
+        GraphiQLSource::build()
+            .endpoint("/api/graphql")
+            .title(&format!("{} GraphQL", state.config.app_title))
+            .finish(),
+    )
+    .into_response()
+}
@@ -0,0 +1,62 @@
+use crate::{db, AppState};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use std::sync::{atomic::Ordering, Arc};
+
+/// Above this many in-flight click writes the instance reports itself as
+/// degraded — the database is not keeping up with redirect traffic.
+const MAX_PENDING_CLICKS: usize = 1000;
+
+/// GET /health/ready
+///
+/// Readiness probe for orchestrators (Fly.io, Kubernetes). Unlike `/health`,
+/// which only proves the process is up, this verifies that:
+/// 1. the database answers a trivial query,
+/// 2. the link cache finished its startup warm-up,
+/// 3. the background click-logging backlog is below `MAX_PENDING_CLICKS`.
+///
+/// It also reports how many redirect lookups have overrun their budget and
+/// how many redirects needed a fuzzy match since startup, and how many
+/// client IPs are banned as scanners, none of which affects readiness.
+///
+/// Returns 200 when everything passes and 503 otherwise, with a JSON body
+/// describing each check either way.
+pub async fn ready(State(state): State<Arc<AppState>>) -> Response {
+    let db_ok = match db::ping(&state.db).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("Readiness check: database ping failed: {:?}", e);
+            false
+        }
+    };
+    let cache_warm = state.cache.is_warm();
+    let pending = state.pending_clicks.load(Ordering::Relaxed);
+    let queue_ok = pending < MAX_PENDING_CLICKS;
+
+    let healthy = db_ok && cache_warm && queue_ok;
+    let status = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let body = serde_json::json!({
+        "status": if healthy { "ok" } else { "degraded" },
+        "checks": {
+            "database": { "ok": db_ok },
+            "cache": { "ok": cache_warm, "warm": cache_warm, "entries": state.cache.len() },
+            "click_queue": { "ok": queue_ok, "pending": pending, "max": MAX_PENDING_CLICKS },
+        },
+        "redirect": {
+            "slow_lookups": state.slow_lookups.load(Ordering::Relaxed),
+            "fuzzy_matches": state.fuzzy_matches.load(Ordering::Relaxed),
+            "budget_ms": state.config.redirect_lookup_budget_ms,
+            "banned_scanners": state.scanners.banned_count(),
+        },
+    });
+
+    (status, Json(body)).into_response()
+}
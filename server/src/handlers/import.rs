@@ -0,0 +1,503 @@
+use crate::{
+    auth::AuthUser,
+    cache::CachedLink,
+    db, db_bio, db_domains,
+    destination::DestinationError,
+    handlers::admin::generate_unique_code,
+    import::{self, Format, ImportedLink},
+    models::Domain,
+    quota,
+    validate::LinkInput,
+    AppState,
+};
+use askama::Template;
+use axum::{
+    extract::{Form, Multipart, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use std::{collections::HashSet, sync::Arc};
+
+/// Links one import may hold.
+const MAX_IMPORT_LINKS: usize = 10_000;
+
+/// Rows listed in the preview; the rest are only counted.
+const PREVIEW_ROWS: usize = 200;
+
+/// Destinations checked at once when importing.
+const CHECK_CHUNK: usize = 20;
+
+// ── Templates ──────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "import.html")]
+struct ImportTemplate {
+    domains: Vec<Domain>,
+    default_domain_id: Option<i64>,
+    base_url: String,
+    preview: Option<Preview>,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+/// What an uploaded export would import, shown before anything is created.
+struct Preview {
+    file_name: String,
+    format: &'static str,
+    /// Where the links will be created: the domain's ID and base URL.
+    domain_id: Option<i64>,
+    domain_url: String,
+    rows: Vec<PreviewRow>,
+    total: usize,
+    ready: usize,
+    imported_clicks: i64,
+    /// The links to import, as JSON for the confirmation form.
+    payload: String,
+}
+
+struct PreviewRow {
+    code: Option<String>,
+    url: String,
+    title: Option<String>,
+    created: Option<String>,
+    clicks: i64,
+    /// Why the link would be skipped.
+    problem: Option<String>,
+}
+
+// ── Form types ─────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ImportForm {
+    /// JSON list of the links from the preview.
+    links: String,
+    domain_id: Option<String>,
+}
+
+// ── Handlers ───────────────────────────────────────────────────────────────
+
+/// GET /admin/import — upload form.
+pub async fn import_page(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let tmpl = ImportTemplate {
+        domains: usable_domains(&auth, &state).await,
+        default_domain_id: state.domains.default_for(auth.workspace_id).map(|d| d.id),
+        base_url: state.config.base_url.clone(),
+        preview: None,
+        flash_success,
+        flash_error,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/import/preview — read an uploaded export and list what
+/// importing it would do. Nothing is created yet.
+pub async fn preview_import(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    mut multipart: Multipart,
+) -> Response {
+    let mut file: Option<(String, String)> = None;
+    let mut format = String::new();
+    let mut domain_field = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        match field.name() {
+            Some("file") => {
+                let name = field.file_name().unwrap_or("upload").to_owned();
+                match field.bytes().await {
+                    Ok(data) => file = Some((name, String::from_utf8_lossy(&data).into_owned())),
+                    Err(e) if e.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+                        let msg = format!(
+                            "The file is larger than the {} MB upload limit.",
+                            state.config.upload_limit_mb
+                        );
+                        return set_flash_and_redirect(jar, None, Some(&msg), "/admin/import");
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to read import upload: {:?}", e);
+                        return set_flash_and_redirect(
+                            jar,
+                            None,
+                            Some("Failed to read the file."),
+                            "/admin/import",
+                        );
+                    }
+                }
+            }
+            Some("format") => format = field.text().await.unwrap_or_default(),
+            Some("domain_id") => domain_field = field.text().await.ok(),
+            _ => {}
+        }
+    }
+
+    let Some((file_name, text)) = file.filter(|(_, text)| !text.trim().is_empty()) else {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Choose a file to import."),
+            "/admin/import",
+        );
+    };
+    let domain = match resolve_domain(&auth, &state, domain_field.as_deref()).await {
+        Ok(domain) => domain,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/import"),
+    };
+
+    let format = Format::from_param(&format, &text);
+    let links = match import::parse(format, &text) {
+        Ok(links) if links.is_empty() => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("No links found in the file."),
+                "/admin/import",
+            );
+        }
+        Ok(links) if links.len() > MAX_IMPORT_LINKS => {
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some(&format!(
+                    "The file has {} links; import at most {MAX_IMPORT_LINKS} at a time.",
+                    links.len()
+                )),
+                "/admin/import",
+            );
+        }
+        Ok(links) => links,
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(&msg), "/admin/import"),
+    };
+
+    let checked = async {
+        let mut reviewed = review(&state, links).await?;
+        check_destinations(&state, &mut reviewed).await?;
+        Ok::<_, sqlx::Error>(reviewed)
+    };
+    let reviewed = match checked.await {
+        Ok(reviewed) => reviewed,
+        Err(e) => {
+            tracing::error!("Failed to check imported links: {:?}", e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Database error while checking the links."),
+                "/admin/import",
+            );
+        }
+    };
+
+    let ready: Vec<&ImportedLink> = reviewed
+        .iter()
+        .filter(|(_, problem)| problem.is_none())
+        .map(|(link, _)| link)
+        .collect();
+    let preview = Preview {
+        file_name,
+        format: format.label(),
+        domain_id: domain.as_ref().map(|d| d.id),
+        domain_url: domain
+            .as_ref()
+            .map_or_else(|| state.config.base_url.clone(), |d| d.base_url.clone()),
+        total: reviewed.len(),
+        ready: ready.len(),
+        imported_clicks: ready.iter().map(|l| l.clicks).sum(),
+        payload: serde_json::to_string(&ready).unwrap_or_default(),
+        rows: reviewed
+            .iter()
+            .take(PREVIEW_ROWS)
+            .map(|(link, problem)| PreviewRow {
+                code: link.code.clone(),
+                url: link.url.clone(),
+                title: link.title.clone(),
+                created: link.created_at.map(|t| t.format("%Y-%m-%d").to_string()),
+                clicks: link.clicks,
+                problem: problem.clone(),
+            })
+            .collect(),
+    };
+
+    ImportTemplate {
+        domains: Vec::new(),
+        default_domain_id: None,
+        base_url: state.config.base_url.clone(),
+        preview: Some(preview),
+        flash_success: None,
+        flash_error: None,
+        is_admin: auth.is_admin(),
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+/// POST /admin/import — create the links confirmed in the preview. They are
+/// checked again, so codes taken in the meantime are skipped, and so are
+/// destinations refused for links created any other way.
+pub async fn import_links(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<ImportForm>,
+) -> Response {
+    let Ok(links) = serde_json::from_str::<Vec<ImportedLink>>(&form.links) else {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("The import couldn't be read; upload the file again."),
+            "/admin/import",
+        );
+    };
+    if links.len() > MAX_IMPORT_LINKS {
+        return set_flash_and_redirect(jar, None, Some("Too many links."), "/admin/import");
+    }
+    let domain_id = match resolve_domain(&auth, &state, form.domain_id.as_deref()).await {
+        Ok(domain) => domain.map(|d| d.id),
+        Err(msg) => return set_flash_and_redirect(jar, None, Some(msg), "/admin/import"),
+    };
+
+    let checked = async {
+        let mut reviewed = review(&state, links).await?;
+        check_destinations(&state, &mut reviewed).await?;
+        Ok::<_, sqlx::Error>(reviewed)
+    };
+    let reviewed = match checked.await {
+        Ok(reviewed) => reviewed,
+        Err(e) => {
+            tracing::error!("Failed to check imported links: {:?}", e);
+            return set_flash_and_redirect(
+                jar,
+                None,
+                Some("Database error while checking the links."),
+                "/admin/import",
+            );
+        }
+    };
+
+    let mut imported = 0;
+    let mut skipped = 0;
+    let mut stopped = None;
+    for (link, problem) in reviewed {
+        if problem.is_some() {
+            skipped += 1;
+            continue;
+        }
+        match quota::links_exhausted(&state, auth.user_id).await {
+            Ok(None) => {}
+            Ok(Some(limit)) => {
+                stopped = Some(format!(
+                    "Link limit reached: your account may have at most {limit} links."
+                ));
+                break;
+            }
+            Err(e) => {
+                tracing::error!("Failed to check link quota: {:?}", e);
+                stopped = Some("Database error; the import stopped early.".to_owned());
+                break;
+            }
+        }
+
+        let code = match &link.code {
+            Some(code) => code.clone(),
+            None => generate_unique_code(&state.db, &state.config).await,
+        };
+        match db::create_imported_link(&state.db, &code, &link, auth.user_id, domain_id).await {
+            Ok(created) => {
+                state.cache.set(
+                    created.domain_id,
+                    &created.short_code,
+                    CachedLink::from(&created),
+                );
+                state.link_created(&created);
+                imported += 1;
+            }
+            // Taken between the check and the insert
+            Err(e) if db::is_unique_violation(&e) => skipped += 1,
+            Err(e) => {
+                tracing::error!("Failed to import link '{}': {:?}", code, e);
+                stopped = Some("Database error; the import stopped early.".to_owned());
+                break;
+            }
+        }
+    }
+
+    let summary = format!("Imported {imported} link(s), skipped {skipped}.");
+    match stopped {
+        Some(reason) => set_flash_and_redirect(
+            jar,
+            None,
+            Some(&format!("{summary} {reason}")),
+            "/admin/short-links",
+        ),
+        None => set_flash_and_redirect(jar, Some(&summary), None, "/admin/short-links"),
+    }
+}
+
+// ── Helpers ────────────────────────────────────────────────────────────────
+
+/// Check `links` against this instance, normalizing their destinations and
+/// codes, and give the reason each one that can't be imported is skipped.
+/// Destinations aren't probed for redirect loops until the import is
+/// confirmed (see [`check_destinations`]); that takes a request per link.
+async fn review(
+    state: &AppState,
+    links: Vec<ImportedLink>,
+) -> Result<Vec<(ImportedLink, Option<String>)>, sqlx::Error> {
+    let mut seen = HashSet::new();
+    let mut reviewed = Vec::with_capacity(links.len());
+    for mut link in links {
+        let problem = check(state, &mut link, &mut seen).await?;
+        reviewed.push((link, problem));
+    }
+    Ok(reviewed)
+}
+
+async fn check(
+    state: &AppState,
+    link: &mut ImportedLink,
+    seen: &mut HashSet<String>,
+) -> Result<Option<String>, sqlx::Error> {
+    let mut input = LinkInput::new(&state.config);
+    let url = input.url("url", &link.url);
+    let code = input.code("code", link.code.as_deref());
+    let title = input.title("title", link.title.as_deref());
+    if let Err(invalid) = input.finish() {
+        return Ok(Some(invalid.to_string()));
+    }
+    link.url = url.expect("validated URL");
+    link.title = title;
+    link.clicks = link.clicks.max(0);
+    link.code = code;
+
+    let Some(code) = &link.code else {
+        return Ok(None);
+    };
+    let problem = if !seen.insert(code.clone()) {
+        "Code appears earlier in the file."
+    } else if db::short_code_taken(&state.db, code).await? {
+        "Code is already taken."
+    } else if db_bio::bio_slug_exists(&state.db, code).await? {
+        "Code is a links page slug."
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(problem.to_owned()))
+}
+
+/// Refuse the destinations of reviewed links as `destination::check` does
+/// for links created any other way, a chunk of links at a time.
+async fn check_destinations(
+    state: &Arc<AppState>,
+    reviewed: &mut [(ImportedLink, Option<String>)],
+) -> Result<(), sqlx::Error> {
+    let ready: Vec<usize> = (0..reviewed.len())
+        .filter(|&i| reviewed[i].1.is_none())
+        .collect();
+    for chunk in ready.chunks(CHECK_CHUNK) {
+        let mut checks = tokio::task::JoinSet::new();
+        for &index in chunk {
+            let state = state.clone();
+            let url = reviewed[index].0.url.clone();
+            checks.spawn(async move { (index, state.check_destination(&url).await) });
+        }
+        while let Some(joined) = checks.join_next().await {
+            let Ok((index, checked)) = joined else {
+                continue;
+            };
+            match checked {
+                Ok(()) => {}
+                Err(DestinationError::Db(e)) => return Err(e),
+                Err(e) => reviewed[index].1 = Some(e.to_string()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Domains `auth` may create links on.
+async fn usable_domains(auth: &AuthUser, state: &AppState) -> Vec<Domain> {
+    let mut domains = match db_domains::get_all_domains(&state.db).await {
+        Ok(d) => d,
+        Err(e) => {
+            tracing::error!("Failed to load domains: {:?}", e);
+            Vec::new()
+        }
+    };
+    domains.retain(|d| auth.can_use(d));
+    domains
+}
+
+/// The domain picked in a form ("" or absent means the primary domain).
+async fn resolve_domain(
+    auth: &AuthUser,
+    state: &AppState,
+    field: Option<&str>,
+) -> Result<Option<Domain>, &'static str> {
+    let Some(field) = field.map(str::trim).filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    let id = field.parse::<i64>().map_err(|_| "Unknown domain.")?;
+    match db_domains::get_domain_by_id(&state.db, id).await {
+        Ok(Some(d)) if auth.can_use(&d) => Ok(Some(d)),
+        Ok(_) => Err("Unknown domain."),
+        Err(e) => {
+            tracing::error!("Failed to fetch domain {}: {:?}", id, e);
+            Err("Database error while looking up domain.")
+        }
+    }
+}
+
+fn set_flash_and_redirect(
+    jar: CookieJar,
+    success: Option<&str>,
+    error: Option<&str>,
+    destination: &str,
+) -> Response {
+    let mut jar = jar;
+
+    if let Some(msg) = success {
+        let c = Cookie::build(("flash_success", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    if let Some(msg) = error {
+        let c = Cookie::build(("flash_error", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    (jar, Redirect::to(destination)).into_response()
+}
@@ -1,4 +1,17 @@
 pub mod admin;
+pub mod api;
 pub mod bio;
+pub mod digest;
+pub mod domains;
+pub mod feed;
+pub mod filters;
+pub mod funnels;
+pub mod graphql;
+pub mod health;
+pub mod import;
 pub mod redirect;
+pub mod shorten;
+pub mod slack;
+pub mod stats;
 pub mod users;
+pub mod workspaces;
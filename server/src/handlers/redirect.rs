@@ -1,11 +1,36 @@
-use crate::{db, db_bio, geo, models::BioLink, models::BioPageFull, AppState};
+use crate::{
+    analytics,
+    beacon::ClientInfo,
+    cache::{CachedLink, SocialPreview},
+    config::AppConfig,
+    db, db_bio,
+    db_click_log::BOT_DEVICE_TYPE,
+    destination,
+    error::AppError,
+    error_report, ga,
+    geo::{self, CountryRules, GeoCache},
+    hooks,
+    models::{
+        BioLink, BioPageFull, Domain, Link, Maintenance, EVENT_API_EXPAND, EVENT_CLICK,
+        EVENT_IMPRESSION, EVENT_PREVIEW, SOURCE_QR,
+    },
+    notify, short_code, unique_visitors, url_macros, AppState,
+};
 use askama::Template;
 use axum::{
-    extract::{ConnectInfo, Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{ConnectInfo, Form, Path, RawQuery, State},
+    http::{header, HeaderMap, StatusCode, Uri},
     response::{IntoResponse, Redirect, Response},
 };
-use std::{net::SocketAddr, sync::Arc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tokio::sync::oneshot;
 use woothee::parser::Parser;
 
 #[derive(Template)]
@@ -15,22 +40,159 @@ struct BioPageTemplate {
     active_links: Vec<BioLink>,
 }
 
+#[derive(Template)]
+#[template(path = "link_preview.html")]
+struct LinkPreviewTemplate {
+    app_title: String,
+    logo_url: Option<String>,
+    brand_color: String,
+    short_url: String,
+    /// Path that follows the short link, so continuing still counts a click.
+    continue_path: String,
+    destination: String,
+    /// Destination host, shown prominently since it is what matters most.
+    destination_host: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "client_redirect.html")]
+struct ClientRedirectTemplate {
+    destination: String,
+    beacon_path: String,
+    token: String,
+}
+
+/// Served to link unfurlers instead of a redirect when the link overrides
+/// its social preview.
+#[derive(Template)]
+#[template(path = "social_preview.html")]
+struct SocialPreviewTemplate {
+    preview: Arc<SocialPreview>,
+    short_url: String,
+    destination: String,
+}
+
+#[derive(Template)]
+#[template(path = "not_found.html")]
+struct NotFoundTemplate {
+    app_title: String,
+    home_url: String,
+    logo_url: Option<String>,
+    brand_color: String,
+}
+
+#[derive(Template)]
+#[template(path = "maintenance.html")]
+struct MaintenanceTemplate {
+    app_title: String,
+    message: String,
+    logo_url: Option<String>,
+    brand_color: String,
+}
+
+/// Served when a cache-miss lookup overruns `REDIRECT_LOOKUP_BUDGET_MS` or
+/// a link is over its click limit; reloads itself after `retry_after`
+/// seconds.
+#[derive(Template)]
+#[template(path = "retry.html")]
+struct RetryTemplate {
+    app_title: String,
+    message: &'static str,
+    retry_after: u64,
+    logo_url: Option<String>,
+    brand_color: String,
+}
+
+#[derive(Template)]
+#[template(path = "error.html")]
+struct ErrorTemplate {
+    app_title: String,
+    status: u16,
+    title: &'static str,
+    message: String,
+    logo_url: Option<String>,
+    brand_color: String,
+}
+
+#[derive(Template)]
+#[template(path = "region_blocked.html")]
+struct RegionBlockedTemplate {
+    app_title: String,
+    message: String,
+    logo_url: Option<String>,
+    brand_color: String,
+}
+
 /// GET /:code
 ///
+/// A trailing `+` (e.g. `/abc+`) shows the link preview instead; see
+/// [`preview`]. A trailing `/` is ignored, and a code that matches no link
+/// gets a second chance through [`fuzzy_resolve`].
+///
 /// 1. Check if the code matches a published bio page slug — if so, render it.
-/// 2. Otherwise check the in-memory cache for a short code (fast path).
+/// 2. Otherwise check the in-memory cache for a short code on the requested
+///    host's domain (fast path).
 /// 3. On a cache miss, fall back to the database for short links.
-/// 4. Spawn a background task to record the click.
-/// 5. Return a 302 redirect to the original URL.
+/// 4. Turn away visitors from countries the global or per-link rules block,
+///    and visitors beyond the link's click limit.
+/// 5. Spawn a background task to record the click, unless the visitor's user
+///    agent matches a click filter.
+/// 6. Return a 302 redirect to the original URL, or for client-redirect links
+///    a page that reports client details to [`beacon`] and then redirects.
+///
+/// Each database lookup may take `REDIRECT_LOOKUP_BUDGET_MS`. A bio page
+/// check that overruns it is skipped, and a short link lookup that does gets
+/// a page that retries.
+///
+/// A client that requests `SCANNER_MISS_LIMIT` unknown codes within a minute
+/// is banned for `SCANNER_BAN_MINUTES`: it skips the database lookups, so
+/// only cached links redirect, and its clicks aren't logged.
+///
+/// While maintenance mode serves its page, every code gets that page instead.
 pub async fn redirect(
     State(state): State<Arc<AppState>>,
     Path(code): Path<String>,
+    RawQuery(query): RawQuery,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    uri: Uri,
     headers: HeaderMap,
 ) -> Response {
+    let mut code = short_code::normalize(&code).into_owned();
+    if let Some(maintenance) = state.maintenance.get().filter(|m| m.serves_page()) {
+        let domain = request_host(&state.config, &headers, addr)
+            .and_then(|host| state.domains.resolve(host));
+        return maintenance_page(&state, &maintenance, domain.as_ref());
+    }
+    if let Some(code) = code.strip_suffix('+') {
+        return render_preview(&state, code, &headers, addr).await;
+    }
+
+    // Clients banned for probing unknown codes, by an IP they can't spoof,
+    // get cached links only, and nothing they do is recorded.
+    let scanner_ip = Some(client_ip(&state.config, &headers, addr))
+        .filter(|ip| !state.config.is_internal_ip(ip));
+    let scanner = scanner_ip
+        .as_deref()
+        .is_some_and(|ip| state.scanners.is_banned(ip));
+
     // ── 1. Check for a published bio page ────────────────────────────────
-    match db_bio::get_published_bio_page_full(&state.db, &code).await {
-        Ok(Some(page_full)) => {
+    let bio_page = async {
+        if scanner {
+            return Some(Ok(None));
+        }
+        within_budget(
+            &state,
+            &code,
+            db_bio::get_published_bio_page_full(&state.db, &code),
+        )
+        .await
+    };
+    match bio_page.await {
+        // Too slow: try it as a short link, which is likely cached
+        None => {}
+        Some(Ok(Some(page_full))) => {
             let active_links: Vec<BioLink> = page_full
                 .links
                 .iter()
@@ -39,44 +201,7 @@ pub async fn redirect(
                 .collect();
 
             // Log the page view in the background
-            let page_id = page_full.page.id;
-            let ip = extract_ip(&headers, addr);
-            let user_agent = headers
-                .get("user-agent")
-                .and_then(|v| v.to_str().ok())
-                .map(str::to_owned);
-            let referer = headers
-                .get("referer")
-                .and_then(|v| v.to_str().ok())
-                .map(str::to_owned);
-            let (browser, os, device_type) = parse_user_agent(user_agent.as_deref());
-
-            let state_bg = state.clone();
-            tokio::spawn(async move {
-                let (country, region, city) = if let Some(ref ip_str) = ip {
-                    match geo::lookup(ip_str, &state_bg.geo_cache).await {
-                        Some(info) => (Some(info.country), Some(info.region), Some(info.city)),
-                        None => (None, None, None),
-                    }
-                } else {
-                    (None, None, None)
-                };
-
-                let _ = db_bio::log_bio_page_view(
-                    &state_bg.db,
-                    page_id,
-                    ip.as_deref(),
-                    user_agent.as_deref(),
-                    referer.as_deref(),
-                    browser.as_deref(),
-                    os.as_deref(),
-                    device_type.as_deref(),
-                    country.as_deref(),
-                    region.as_deref(),
-                    city.as_deref(),
-                )
-                .await;
-            });
+            log_bio_page_view(&state, page_full.page.id, &headers, addr);
 
             return BioPageTemplate {
                 page: page_full,
@@ -84,109 +209,271 @@ pub async fn redirect(
             }
             .into_response();
         }
-        Ok(None) => {} // Not a bio page — continue to short link lookup
-        Err(e) => {
+        Some(Ok(None)) => {} // Not a bio page — continue to short link lookup
+        Some(Err(e)) => {
             tracing::error!("DB error checking bio page '{}': {:?}", code, e);
             // Don't fail hard — fall through to short link lookup
         }
     }
 
     // ── 2. Resolve short link URL ────────────────────────────────────────
-    let original_url = match state.cache.get(&code) {
-        Some(url) => url,
-        None => {
-            // Cache miss — check the database
-            match db::get_link_by_code(&state.db, &code).await {
-                Ok(Some(link)) => {
-                    // Backfill the cache for next time
-                    state.cache.set(&link.short_code, &link.original_url);
-                    link.original_url
-                }
-                Ok(None) => {
-                    return (StatusCode::NOT_FOUND, "Not found").into_response();
-                }
-                Err(e) => {
-                    tracing::error!("DB error looking up short code '{}': {:?}", code, e);
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
-                }
+    // Unknown hosts (and the primary host) serve links without a domain.
+    let domain =
+        request_host(&state.config, &headers, addr).and_then(|host| state.domains.resolve(host));
+    let domain_id = domain.as_ref().map(|d| d.id);
+
+    let found = match resolve(&state, domain_id, &code, scanner).await {
+        Ok(None) => fuzzy_resolve(&state, domain_id, &code, scanner).await,
+        other => other.map(|entry| entry.map(|entry| (code.clone(), entry))),
+    };
+    let cached = match found {
+        Ok(Some((matched, entry))) => {
+            if matched != code || uri.path().ends_with('/') {
+                state.fuzzy_matches.fetch_add(1, Ordering::Relaxed);
+                tracing::info!("Fuzzy match: {} resolved to '{}'", uri.path(), matched);
+                code = matched;
+            }
+            entry
+        }
+        Ok(None) => {
+            if let Some(ip) = scanner_ip.as_ref().filter(|_| !scanner) {
+                state.scanners.record_miss(ip);
             }
+            return not_found(&state, domain.as_ref(), &code);
+        }
+        // By the time the page reloads, the lookup has usually
+        // finished and cached the link.
+        Err(LookupError::Overran) => {
+            return retry_page(
+                &state,
+                domain.as_ref(),
+                StatusCode::SERVICE_UNAVAILABLE,
+                "This link is taking longer than usual to load.",
+                2,
+            );
+        }
+        Err(LookupError::Failed(e)) => {
+            return AppError::internal(e, format!("DB error looking up short code '{code}'"))
+                .into_response();
         }
     };
 
-    // ── 3. Extract request metadata ────────────────────────────────────────
-    let ip = extract_ip(&headers, addr);
+    // ── 3. Country restrictions ───────────────────────────────────────────
+    let link_rules = cached.country_rules.as_deref();
+    if !country_permitted(&state.config, &state.geo_cache, link_rules, &headers, addr).await {
+        return region_blocked(&state, domain.as_ref());
+    }
 
-    let user_agent = headers
-        .get("user-agent")
-        .and_then(|v| v.to_str().ok())
-        .map(str::to_owned);
+    // Visitors beyond the link's click limit wait, and aren't counted.
+    // Unfurlers shown a social preview never reach the destination.
+    let previewed = cached.social.is_some() && is_unfurler(&headers);
+    if let Some(limit) = cached.click_limit.filter(|_| !previewed) {
+        if let Err(retry_after) = state.click_limiter.record(cached.id, &limit) {
+            return if limit.page {
+                retry_page(
+                    &state,
+                    domain.as_ref(),
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "This link is very popular right now.",
+                    retry_after,
+                )
+            } else {
+                AppError::RateLimited(
+                    "This link is getting too many visits right now. Try again in a minute.".into(),
+                    Some(retry_after),
+                )
+                .into_response()
+            };
+        }
+    }
 
-    let referer = headers
-        .get("referer")
-        .and_then(|v| v.to_str().ok())
-        .map(str::to_owned);
+    // ── 4. Log the click in the background ─────────────────────────────────
+    // The geo lookup and DB write happen in a background task — never on the
+    // hot path.
+    let click_id = new_click_id();
+    // A schedule rule covering the local time comes first, then a rule for
+    // a language the visitor's browser prefers, then one of the link's
+    // variants, then its own URL (or its fallback while that is down).
+    let scheduled = cached.schedule_rules.as_deref().and_then(|rules| {
+        rules.pick(&chrono::Utc::now().with_timezone(&state.config.display_timezone))
+    });
+    let language = cached
+        .language_rules
+        .as_deref()
+        .filter(|_| scheduled.is_none())
+        .and_then(|rules| {
+            let accept = headers.get(header::ACCEPT_LANGUAGE)?.to_str().ok()?;
+            rules.pick(accept)
+        });
+    let variant = cached
+        .variants
+        .as_deref()
+        .filter(|_| scheduled.is_none() && language.is_none())
+        .and_then(|variants| {
+            let user_agent = headers
+                .get(header::USER_AGENT)
+                .and_then(|v| v.to_str().ok());
+            variants.pick(
+                cached.sticky_variants,
+                cached.id,
+                Some(client_ip(&state.config, &headers, addr).as_str()),
+                user_agent,
+            )
+        });
+    let target = match (scheduled, language, variant) {
+        (Some(rule), _, _) => &rule.url,
+        (None, Some(rule), _) => &rule.url,
+        (None, None, Some(variant)) => &variant.url,
+        (None, None, None) => cached.fallback_url.as_ref().unwrap_or(&cached.original_url),
+    };
+    let destination = if target.contains('{') {
+        let request = Request {
+            headers: &headers,
+            addr,
+            query: query.as_deref(),
+        };
+        expand_destination(&state, target, &code, &click_id, request).await
+    } else {
+        target.clone()
+    };
+    // Link unfurlers get the link's own social preview, if it has one
+    let social = cached.social.clone().filter(|_| is_unfurler(&headers));
+    // Client details only matter for a click that will be recorded with them
+    let beacon = (cached.client_redirect
+        && !scanner
+        && social.is_none()
+        && state.records_visits()
+        && !is_anonymous(&state, &headers)
+        && !is_filtered(&state, &headers))
+    .then(|| state.beacons.expect());
+    let beacon_token = beacon.as_ref().map(|(token, _)| token.clone());
+    if !scanner {
+        log_event(
+            &state,
+            &cached,
+            &headers,
+            addr,
+            EVENT_CLICK,
+            Some(click_id),
+            beacon,
+            visit_source(query.as_deref()),
+            language.map(|rule| rule.language.clone()),
+            variant.map(|variant| variant.url.clone()),
+        );
+    }
 
-    // Parse the User-Agent string for browser / OS / device info
-    let (browser, os, device_type) = parse_user_agent(user_agent.as_deref());
+    // ── 5. Redirect ────────────────────────────────────────────────────────
+    if let Some(preview) = social {
+        return SocialPreviewTemplate {
+            preview,
+            short_url: state.short_url(domain_id, &code),
+            destination,
+        }
+        .into_response();
+    }
+    match beacon_token {
+        Some(token) => {
+            let page = ClientRedirectTemplate {
+                destination,
+                beacon_path: format!("/{code}/beacon"),
+                token,
+            };
+            // The token is single-use, so the page must never be cached.
+            ([(header::CACHE_CONTROL, "no-store")], page).into_response()
+        }
+        None => Redirect::to(&destination::location(&destination)).into_response(),
+    }
+}
 
-    // ── 4. Log the click in the background ─────────────────────────────────
-    // Clone everything needed so the background task owns its data.
-    // The geo lookup and DB write both happen here — never on the hot path.
-    let state_bg = state.clone();
-    let code_bg = code.clone();
-    let ip_bg = ip.clone();
-    let ua_bg = user_agent.clone();
-    let ref_bg = referer.clone();
-    let browser_bg = browser.clone();
-    let os_bg = os.clone();
-    let device_bg = device_type.clone();
-
-    tokio::spawn(async move {
-        // Resolve the link_id (needed for the INSERT into clicks).
-        let link = match db::get_link_by_code(&state_bg.db, &code_bg).await {
-            Ok(Some(l)) => l,
-            Ok(None) => {
-                tracing::warn!(
-                    "Click logging: link '{}' disappeared between redirect and log",
-                    code_bg
-                );
-                return;
+#[derive(Deserialize)]
+pub struct BeaconForm {
+    token: String,
+    screen_width: Option<i64>,
+    screen_height: Option<i64>,
+    language: Option<String>,
+    timezone: Option<String>,
+}
+
+/// POST /:code/beacon
+///
+/// Client details sent by a client-redirect page. They are attached to the
+/// click the page's request started logging; stale tokens are ignored.
+pub async fn beacon(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<BeaconForm>,
+) -> StatusCode {
+    state.beacons.deliver(
+        &form.token,
+        ClientInfo {
+            screen_width: form.screen_width,
+            screen_height: form.screen_height,
+            language: form.language,
+            timezone: form.timezone,
+        },
+    );
+    StatusCode::NO_CONTENT
+}
+
+/// A 1×1 transparent GIF.
+const PIXEL_GIF: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// GET /p/:code.gif
+///
+/// Tracking pixel for emails and pages. Each load is logged as an
+/// `impression` of the link, separately from its clicks.
+pub async fn pixel(
+    State(state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    let Some(code) = file.strip_suffix(".gif") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let domain_id = request_host(&state.config, &headers, addr)
+        .and_then(|host| state.domains.resolve(host))
+        .map(|d| d.id);
+    let link = match state.cache.get(domain_id, code) {
+        Some(entry) => entry,
+        None => match db::get_link_by_code(&state.db, code).await {
+            Ok(Some(link)) if link.domain_id == domain_id && state.link_resolves(&link) => {
+                CachedLink::from(&link)
             }
+            Ok(_) => return StatusCode::NOT_FOUND.into_response(),
             Err(e) => {
-                tracing::error!("Click logging DB error for '{}': {:?}", code_bg, e);
-                return;
-            }
-        };
-
-        // Geo-lookup: consults the in-memory cache first so that repeated
-        // clicks from the same IP never trigger more than one network request.
-        let (country, region, city) = if let Some(ref ip_str) = ip_bg {
-            match geo::lookup(ip_str, &state_bg.geo_cache).await {
-                Some(info) => (Some(info.country), Some(info.region), Some(info.city)),
-                None => (None, None, None),
+                tracing::error!("DB error looking up short code '{}': {:?}", code, e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
             }
-        } else {
-            (None, None, None)
-        };
+        },
+    };
 
-        let _ = db::log_click(
-            &state_bg.db,
-            link.id,
-            ip_bg.as_deref(),
-            ua_bg.as_deref(),
-            ref_bg.as_deref(),
-            browser_bg.as_deref(),
-            os_bg.as_deref(),
-            device_bg.as_deref(),
-            country.as_deref(),
-            region.as_deref(),
-            city.as_deref(),
-        )
-        .await;
-    });
+    log_event(
+        &state,
+        &link,
+        &headers,
+        addr,
+        EVENT_IMPRESSION,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
 
-    // ── 5. Redirect ────────────────────────────────────────────────────────
-    Redirect::to(&original_url).into_response()
+    (
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            // Every open should reach the server.
+            (header::CACHE_CONTROL, "no-store, no-cache, must-revalidate"),
+        ],
+        PIXEL_GIF,
+    )
+        .into_response()
 }
 
 /// GET /c/:id
@@ -198,24 +485,36 @@ pub async fn bio_link_click(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     headers: HeaderMap,
 ) -> Response {
+    if let Some(maintenance) = state.maintenance.get().filter(|m| m.serves_page()) {
+        let domain = request_host(&state.config, &headers, addr)
+            .and_then(|host| state.domains.resolve(host));
+        return maintenance_page(&state, &maintenance, domain.as_ref());
+    }
+
     let bio_link = match db_bio::get_bio_link_by_id(&state.db, id).await {
         Ok(Some(link)) => link,
         Ok(None) => {
-            return (StatusCode::NOT_FOUND, "Link not found").into_response();
+            return AppError::NotFound("Link not found".into()).into_response();
         }
         Err(e) => {
-            tracing::error!("DB error looking up bio link {}: {:?}", id, e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal error").into_response();
+            return AppError::internal(e, format!("DB error looking up bio link {id}"))
+                .into_response();
         }
     };
 
     let destination = bio_link.url.clone();
     let page_id = bio_link.page_id;
+    if !state.records_visits() || is_filtered(&state, &headers) {
+        return Redirect::to(&destination::location(&destination)).into_response();
+    }
 
-    let ip = extract_ip(&headers, addr);
+    // Visitors asking not to be tracked are counted without these
+    let anonymous = is_anonymous(&state, &headers);
+    let ip = extract_ip(&headers, addr).filter(|_| !anonymous);
     let user_agent = headers
         .get("user-agent")
         .and_then(|v| v.to_str().ok())
+        .filter(|_| !anonymous)
         .map(str::to_owned);
     let referer = headers
         .get("referer")
@@ -230,8 +529,10 @@ pub async fn bio_link_click(
     let browser_bg = browser;
     let os_bg = os;
     let device_bg = device_type;
+    let pending = PendingClick::new(&state);
 
-    tokio::spawn(async move {
+    tokio::spawn(error_report::in_current_request(async move {
+        let _pending = pending;
         let (country, region, city) = if let Some(ref ip_str) = ip_bg {
             match geo::lookup(ip_str, &state_bg.geo_cache).await {
                 Some(info) => (Some(info.country), Some(info.region), Some(info.city)),
@@ -256,15 +557,736 @@ pub async fn bio_link_click(
             city.as_deref(),
         )
         .await;
-    });
+    }));
 
-    Redirect::to(&destination).into_response()
+    Redirect::to(&destination::location(&destination)).into_response()
 }
 
 // ── Helpers ────────────────────────────────────────────────────────────────
 
+/// Record a view of bio page `page_id` in the background, like [`log_event`]
+/// does for short links.
+fn log_bio_page_view(state: &Arc<AppState>, page_id: i64, headers: &HeaderMap, addr: SocketAddr) {
+    if !state.records_visits() || is_filtered(state, headers) {
+        return;
+    }
+
+    // Visitors asking not to be tracked are counted without these
+    let anonymous = is_anonymous(state, headers);
+    let ip = extract_ip(headers, addr).filter(|_| !anonymous);
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| !anonymous)
+        .map(str::to_owned);
+    let referer = headers
+        .get("referer")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let (browser, os, device_type) = parse_user_agent(user_agent.as_deref());
+
+    let state_bg = state.clone();
+    let pending = PendingClick::new(state);
+    tokio::spawn(error_report::in_current_request(async move {
+        let _pending = pending;
+        let (country, region, city) = if let Some(ref ip_str) = ip {
+            match geo::lookup(ip_str, &state_bg.geo_cache).await {
+                Some(info) => (Some(info.country), Some(info.region), Some(info.city)),
+                None => (None, None, None),
+            }
+        } else {
+            (None, None, None)
+        };
+
+        let _ = db_bio::log_bio_page_view(
+            &state_bg.db,
+            page_id,
+            ip.as_deref(),
+            user_agent.as_deref(),
+            referer.as_deref(),
+            browser.as_deref(),
+            os.as_deref(),
+            device_type.as_deref(),
+            country.as_deref(),
+            region.as_deref(),
+            city.as_deref(),
+        )
+        .await;
+    }));
+}
+
+/// Record an API client expanding `link`'s code (`/api/v1/expand/:code`).
+pub(crate) fn log_api_expand(
+    state: &Arc<AppState>,
+    link: &Link,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) {
+    log_event(
+        state,
+        &CachedLink::from(link),
+        headers,
+        addr,
+        EVENT_API_EXPAND,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+}
+
+/// Record a visit to `link` as `event_type` (one of `models::EVENT_*`).
+/// Request metadata is captured here; the geo lookup, DB write, and
+/// forwarding all happen in a background task. The full link
+/// is only loaded for hooks, alerts, and notifications that need it.
+///
+/// `beacon` makes the task wait for a client-redirect page's details first,
+/// and `source` is the visit's `models::SOURCE_*`, if its URL gave one.
+/// Nothing is recorded with `ANALYTICS_ENABLED` off or during maintenance.
+#[allow(clippy::too_many_arguments)]
+fn log_event(
+    state: &Arc<AppState>,
+    link: &CachedLink,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+    event_type: &'static str,
+    click_id: Option<String>,
+    beacon: Option<(String, oneshot::Receiver<ClientInfo>)>,
+    source: Option<&'static str>,
+    matched_language: Option<String>,
+    variant: Option<String>,
+) {
+    if !state.records_visits() || is_filtered(state, headers) {
+        return;
+    }
+
+    // Only an address visitors can't spoof decides whether they're internal
+    let internal = state
+        .config
+        .is_internal_ip(&client_ip(&state.config, headers, addr));
+    let ip = extract_ip(headers, addr);
+
+    // Visitors asking not to be tracked are counted without these
+    let anonymous = is_anonymous(state, headers);
+    let ip = ip.filter(|_| !anonymous);
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .filter(|_| !anonymous)
+        .map(str::to_owned);
+    let visitor = ip.as_deref().map(|ip| {
+        unique_visitors::visitor_hash(&state.config.jwt_secret, ip, user_agent.as_deref())
+    });
+
+    let referer = headers
+        .get("referer")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+
+    // Parse the User-Agent string for browser / OS / device info
+    let (browser, os, device_type) = parse_user_agent(user_agent.as_deref());
+
+    let link = link.clone();
+    let state_bg = state.clone();
+    let pending = PendingClick::new(state);
+
+    tokio::spawn(error_report::in_current_request(async move {
+        let _pending = pending;
+
+        // Geo-lookup: consults the in-memory cache first so that repeated
+        // clicks from the same IP never trigger more than one network request.
+        let geo = match ip {
+            Some(ref ip_str) => geo::lookup(ip_str, &state_bg.geo_cache).await,
+            None => None,
+        };
+        let (country, region, city) = match &geo {
+            Some(info) => (
+                Some(info.country.clone()),
+                Some(info.region.clone()),
+                Some(info.city.clone()),
+            ),
+            None => (None, None, None),
+        };
+
+        let client = match beacon {
+            Some((token, rx)) => state_bg.beacons.wait(&token, rx).await,
+            None => ClientInfo::default(),
+        };
+
+        let logged = db::log_event(
+            &state_bg.db,
+            link.id,
+            ip.as_deref(),
+            user_agent.as_deref(),
+            referer.as_deref(),
+            browser.as_deref(),
+            os.as_deref(),
+            device_type.as_deref(),
+            geo.as_ref(),
+            &client,
+            event_type,
+            click_id.as_deref(),
+            internal,
+            source,
+            matched_language.as_deref(),
+            variant.as_deref(),
+            visitor.as_deref(),
+        )
+        .await;
+
+        // Other events and internal clicks are only recorded; forwarding,
+        // hooks, and alerts are for real visitors' clicks.
+        if event_type != EVENT_CLICK || internal {
+            return;
+        }
+
+        if logged.is_ok() {
+            state_bg.live_clicks.publish(&link);
+            state_bg.analytics.send(analytics::Pageview {
+                url: state_bg.short_url(link.domain_id, &link.short_code),
+                referrer: referer.clone(),
+                user_agent: user_agent.clone(),
+                ip: ip.clone(),
+            });
+            state_bg.ga.send(ga::Click {
+                short_url: state_bg.short_url(link.domain_id, &link.short_code),
+                short_code: link.short_code.clone(),
+                referrer: referer.clone(),
+                browser: browser.clone(),
+                os: os.clone(),
+                device_type: device_type.clone(),
+                country: country.clone(),
+                region: region.clone(),
+                city: city.clone(),
+                ip: ip.clone(),
+                user_agent: user_agent.clone(),
+            });
+        }
+
+        let wants_hook = state_bg.hooks.has_subscribers(hooks::CLICK_RECORDED);
+        let wants_count =
+            state_bg.notifier.is_enabled() || state_bg.click_alerts.has_pending(link.id);
+        if logged.is_err() || !(wants_hook || wants_count) {
+            return;
+        }
+        // Hooks, alerts, and notifications describe the whole link
+        let link = match db::get_link_by_id(&state_bg.db, link.id).await {
+            Ok(Some(link)) => link,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::error!("Click logging DB error for '{}': {:?}", link.short_code, e);
+                return;
+            }
+        };
+
+        if wants_hook {
+            // The visitor's IP and raw user agent stay in Linkly.
+            let short_url = state_bg.short_url(link.domain_id, &link.short_code);
+            state_bg.hooks.emit(
+                hooks::CLICK_RECORDED,
+                &link,
+                serde_json::json!({
+                    "link": hooks::link_data(&link, &short_url),
+                    "click": {
+                        "clicked_at": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                        "click_id": click_id,
+                        "referer": referer,
+                        "browser": browser,
+                        "os": os,
+                        "device_type": device_type,
+                        "country": country,
+                        "region": region,
+                        "city": city,
+                    },
+                }),
+            );
+        }
+
+        if wants_count {
+            let counted = db::count_link_clicks(&state_bg.db, link.id).await;
+            if let Ok(clicks) = counted {
+                state_bg.click_alerts.check(&state_bg, &link, clicks).await;
+            }
+            match counted {
+                Ok(clicks) if state_bg.notifier.is_milestone(clicks) => {
+                    state_bg.notifier.send(
+                        notify::Event::ClickMilestone,
+                        notify::LinkVars {
+                            short_url: state_bg.short_url(link.domain_id, &link.short_code),
+                            code: link.short_code,
+                            url: link.original_url,
+                            title: link.title,
+                            clicks,
+                        },
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!("Failed to count clicks for '{}': {:?}", link.short_code, e)
+                }
+            }
+        }
+    }));
+}
+
+/// Counts one in-flight background logging task in `AppState::pending_clicks`
+/// for as long as it is alive, so early returns still decrement the gauge.
+struct PendingClick(Arc<AppState>);
+
+impl PendingClick {
+    fn new(state: &Arc<AppState>) -> Self {
+        state.pending_clicks.fetch_add(1, Ordering::Relaxed);
+        Self(state.clone())
+    }
+}
+
+impl Drop for PendingClick {
+    fn drop(&mut self) {
+        self.0.pending_clicks.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Why [`lookup_uncached`] has no answer.
+enum LookupError {
+    /// The lookup took longer than `REDIRECT_LOOKUP_BUDGET_MS`.
+    Overran,
+    Failed(anyhow::Error),
+}
+
+/// Characters chat apps and mail clients take for part of a link that ends
+/// a sentence or sits in brackets or quotes. No code ends with one.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ')', ']', '!', ';', ':', '\'', '"', '>'];
+
+/// The link `code` leads to on `domain_id`: the cached one, or unless the
+/// client is a banned `scanner`, the one in the database.
+async fn resolve(
+    state: &Arc<AppState>,
+    domain_id: Option<i64>,
+    code: &str,
+    scanner: bool,
+) -> Result<Option<CachedLink>, LookupError> {
+    if let Some(entry) = state.cache.get(domain_id, code) {
+        return Ok(Some(entry));
+    }
+    if scanner {
+        return Ok(None);
+    }
+    Ok(lookup_uncached(state, code)
+        .await?
+        .filter(|entry| entry.domain_id == domain_id))
+}
+
+/// The link `code` was probably meant for when it matches none, and the
+/// code that found it: `code` without trailing punctuation, then with
+/// `CASE_INSENSITIVE_CODES` the one code or alias that matches it ignoring
+/// case.
+async fn fuzzy_resolve(
+    state: &Arc<AppState>,
+    domain_id: Option<i64>,
+    code: &str,
+    scanner: bool,
+) -> Result<Option<(String, CachedLink)>, LookupError> {
+    let trimmed = code.trim_end_matches(TRAILING_PUNCTUATION);
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    if trimmed != code {
+        if let Some(entry) = resolve(state, domain_id, trimmed, scanner).await? {
+            return Ok(Some((trimmed.to_owned(), entry)));
+        }
+    }
+    if !state.config.case_insensitive_codes || scanner {
+        return Ok(None);
+    }
+    let matched = match within_budget(
+        state,
+        trimmed,
+        db::find_code_ignoring_case(&state.db, trimmed),
+    )
+    .await
+    {
+        None => return Err(LookupError::Overran),
+        Some(result) => result.map_err(|e| LookupError::Failed(e.into()))?,
+    };
+    let Some(matched) = matched.filter(|matched| matched != trimmed) else {
+        return Ok(None);
+    };
+    Ok(resolve(state, domain_id, &matched, scanner)
+        .await?
+        .map(|entry| (matched, entry)))
+}
+
+/// Look up `code` in the database after a cache miss and cache the link if
+/// it resolves. Returns `None` for unknown codes and links that don't
+/// resolve.
+///
+/// The lookup runs in its own task, so a redirect that stops waiting for it
+/// after `REDIRECT_LOOKUP_BUDGET_MS` doesn't cancel it: the link still lands
+/// in the cache, ready for the visitor's retry.
+async fn lookup_uncached(
+    state: &Arc<AppState>,
+    code: &str,
+) -> Result<Option<CachedLink>, LookupError> {
+    let task = tokio::spawn({
+        let state = state.clone();
+        let code = code.to_owned();
+        async move {
+            let link = db::get_link_by_code(&state.db, &code).await?;
+            Ok::<_, sqlx::Error>(link.filter(|l| state.link_resolves(l)).map(|link| {
+                let entry = CachedLink::from(&link);
+                // Cached under the code asked for, which may be an alias
+                state.cache.fill(link.domain_id, code, entry.clone());
+                entry
+            }))
+        }
+    });
+
+    match within_budget(state, code, task).await {
+        None => Err(LookupError::Overran),
+        Some(Ok(result)) => result.map_err(|e| LookupError::Failed(e.into())),
+        Some(Err(e)) => Err(LookupError::Failed(e.into())),
+    }
+}
+
+/// The output of `lookup`, a query for `code`, or `None` if it takes longer
+/// than `REDIRECT_LOOKUP_BUDGET_MS`. Overruns are logged and counted for
+/// `/health/ready`.
+async fn within_budget<F: Future>(state: &AppState, code: &str, lookup: F) -> Option<F::Output> {
+    let budget = state.config.redirect_lookup_budget_ms;
+    if budget == 0 {
+        return Some(lookup.await);
+    }
+    let result = tokio::time::timeout(Duration::from_millis(budget), lookup).await;
+    if result.is_err() {
+        state.slow_lookups.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("Looking up '{}' took over {} ms", code, budget);
+    }
+    result.ok()
+}
+
+/// GET /:code/preview (also `/:code+`)
+///
+/// Show where a short link goes, with a button to continue, instead of
+/// redirecting. Viewing the preview is recorded as a `preview` event, not a
+/// click.
+pub async fn preview(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Response {
+    render_preview(&state, &short_code::normalize(&code), &headers, addr).await
+}
+
+async fn render_preview(
+    state: &Arc<AppState>,
+    code: &str,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> Response {
+    let domain =
+        request_host(&state.config, headers, addr).and_then(|host| state.domains.resolve(host));
+    let domain_id = domain.as_ref().map(|d| d.id);
+
+    let link = match db::get_link_by_code(&state.db, code).await {
+        Ok(Some(link)) if link.domain_id == domain_id && state.link_resolves(&link) => link,
+        Ok(_) => return not_found(state, domain.as_ref(), code),
+        Err(e) => {
+            return AppError::internal(e, format!("DB error looking up short code '{code}'"))
+                .into_response();
+        }
+    };
+
+    let entry = CachedLink::from(&link);
+    log_event(
+        state,
+        &entry,
+        headers,
+        addr,
+        EVENT_PREVIEW,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+
+    LinkPreviewTemplate {
+        app_title: state.config.app_title.clone(),
+        logo_url: domain.as_ref().and_then(|d| d.logo_url.clone()),
+        brand_color: domain
+            .as_ref()
+            .and_then(|d| d.brand_color.clone())
+            .unwrap_or_else(|| DEFAULT_BRAND_COLOR.to_owned()),
+        short_url: state.short_url(link.domain_id, code),
+        continue_path: format!("/{code}"),
+        destination_host: reqwest::Url::parse(&link.original_url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_owned)),
+        destination: link.original_url,
+        title: link.title,
+        description: link.description,
+    }
+    .into_response()
+}
+
+/// Response for an unknown short code: the domain's `not_found_url` if it has
+/// one, else `NOT_FOUND_SEARCH_URL` with the code filled in if set, otherwise
+/// a 404 page in the domain's branding.
+fn not_found(state: &AppState, domain: Option<&Domain>, code: &str) -> Response {
+    if let Some(url) = domain.and_then(|d| d.not_found_url.as_deref()) {
+        return Redirect::to(&destination::location(url)).into_response();
+    }
+    if let Some(url) = &state.config.not_found_search_url {
+        let url = url.replace(
+            "{code}",
+            &utf8_percent_encode(code, NON_ALPHANUMERIC).to_string(),
+        );
+        return Redirect::to(&destination::location(&url)).into_response();
+    }
+
+    let tmpl = NotFoundTemplate {
+        app_title: state.config.app_title.clone(),
+        home_url: domain
+            .and_then(|d| d.root_redirect_url.clone())
+            .unwrap_or_else(|| state.tunables().root_redirect_url.clone()),
+        logo_url: domain.and_then(|d| d.logo_url.clone()),
+        brand_color: domain
+            .and_then(|d| d.brand_color.clone())
+            .unwrap_or_else(|| DEFAULT_BRAND_COLOR.to_owned()),
+    };
+    (StatusCode::NOT_FOUND, tmpl).into_response()
+}
+
+/// Page that tells the visitor to hold on and reloads itself after
+/// `retry_after` seconds, also sent as `Retry-After`.
+fn retry_page(
+    state: &AppState,
+    domain: Option<&Domain>,
+    status: StatusCode,
+    message: &'static str,
+    retry_after: u64,
+) -> Response {
+    let tmpl = RetryTemplate {
+        app_title: state.config.app_title.clone(),
+        message,
+        retry_after,
+        logo_url: domain.and_then(|d| d.logo_url.clone()),
+        brand_color: domain
+            .and_then(|d| d.brand_color.clone())
+            .unwrap_or_else(|| DEFAULT_BRAND_COLOR.to_owned()),
+    };
+    (
+        status,
+        [
+            (header::RETRY_AFTER, retry_after.to_string()),
+            (header::CACHE_CONTROL, "no-store".to_owned()),
+        ],
+        tmpl,
+    )
+        .into_response()
+}
+
+/// Page for visitors a country rule turns away. Served as 451 (Unavailable
+/// For Legal Reasons), since restrictions are usually down to licensing.
+fn region_blocked(state: &AppState, domain: Option<&Domain>) -> Response {
+    let tmpl = RegionBlockedTemplate {
+        app_title: state.config.app_title.clone(),
+        message: state.config.access_blocked_message.clone(),
+        logo_url: domain.and_then(|d| d.logo_url.clone()),
+        brand_color: domain
+            .and_then(|d| d.brand_color.clone())
+            .unwrap_or_else(|| DEFAULT_BRAND_COLOR.to_owned()),
+    };
+    (StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS, tmpl).into_response()
+}
+
+/// Page served while maintenance mode is on, in place of redirects and of
+/// refused writes. Served as 503 so crawlers and monitors come back later.
+pub(crate) fn maintenance_page(
+    state: &AppState,
+    maintenance: &Maintenance,
+    domain: Option<&Domain>,
+) -> Response {
+    let tmpl = MaintenanceTemplate {
+        app_title: state.config.app_title.clone(),
+        message: maintenance.message().to_owned(),
+        logo_url: domain.and_then(|d| d.logo_url.clone()),
+        brand_color: domain
+            .and_then(|d| d.brand_color.clone())
+            .unwrap_or_else(|| DEFAULT_BRAND_COLOR.to_owned()),
+    };
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, "300")],
+        tmpl,
+    )
+        .into_response()
+}
+
+/// Page for an [`AppError`](crate::error::AppError) answering a browser.
+pub(crate) fn error_page(
+    state: &AppState,
+    status: StatusCode,
+    message: &str,
+    domain: Option<&Domain>,
+) -> Response {
+    let tmpl = ErrorTemplate {
+        app_title: state.config.app_title.clone(),
+        status: status.as_u16(),
+        title: status.canonical_reason().unwrap_or("Error"),
+        message: message.to_owned(),
+        logo_url: domain.and_then(|d| d.logo_url.clone()),
+        brand_color: domain
+            .and_then(|d| d.brand_color.clone())
+            .unwrap_or_else(|| DEFAULT_BRAND_COLOR.to_owned()),
+    };
+    (status, tmpl).into_response()
+}
+
+/// Whether the visitor may follow a link with `link_rules`, under the
+/// global country rules too. Only restricted links wait on the geo lookup;
+/// it is usually cached.
+async fn country_permitted(
+    config: &AppConfig,
+    geo_cache: &GeoCache,
+    link_rules: Option<&CountryRules>,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> bool {
+    if config.country_rules.is_empty() && link_rules.is_none() {
+        return true;
+    }
+    let country = visitor_country_code(config, geo_cache, headers, addr).await;
+    config.country_rules.permits(country.as_deref())
+        && link_rules.is_none_or(|rules| rules.permits(country.as_deref()))
+}
+
+/// The visitor's ISO country code, or `None` when it can't be determined.
+/// Forwarding headers only count from `TRUSTED_PROXIES` (see [`client_ip`]),
+/// or anyone could claim to be somewhere else.
+async fn visitor_country_code(
+    config: &AppConfig,
+    geo_cache: &GeoCache,
+    headers: &HeaderMap,
+    addr: SocketAddr,
+) -> Option<String> {
+    let ip = client_ip(config, headers, addr);
+    geo::lookup(&ip, geo_cache)
+        .await
+        .map(|info| info.country_code)
+        .filter(|c| !c.is_empty())
+}
+
+/// The parts of a redirect request that destination placeholders draw on.
+struct Request<'a> {
+    headers: &'a HeaderMap,
+    addr: SocketAddr,
+    query: Option<&'a str>,
+}
+
+/// Fill in the placeholders of a destination URL for this request. The
+/// visitor's country is only looked up when `{country}` is used, since that
+/// may wait on the geolocation service.
+async fn expand_destination(
+    state: &AppState,
+    url: &str,
+    code: &str,
+    click_id: &str,
+    req: Request<'_>,
+) -> String {
+    let country = if url_macros::uses(url, "country") {
+        let ip = client_ip(&state.config, req.headers, req.addr);
+        geo::lookup(&ip, &state.geo_cache)
+            .await
+            .map(|info| info.country)
+            .filter(|c| !c.is_empty())
+    } else {
+        None
+    };
+    let device = if url_macros::uses(url, "device") {
+        let user_agent = req.headers.get("user-agent").and_then(|v| v.to_str().ok());
+        parse_user_agent(user_agent).2
+    } else {
+        None
+    };
+    let utm_source = req
+        .query
+        .and_then(|q| serde_urlencoded::from_str::<Vec<(String, String)>>(q).ok())
+        .and_then(|params| params.into_iter().find(|(k, _)| k == "utm_source"))
+        .map(|(_, v)| v);
+
+    url_macros::expand(
+        url,
+        &url_macros::Vars {
+            code,
+            click_id,
+            country: country.as_deref(),
+            device: device.as_deref(),
+            utm_source: utm_source.as_deref(),
+        },
+    )
+}
+
+/// Whether the visitor's browser asks not to be tracked (`DNT: 1` or
+/// `Sec-GPC: 1`) and `HONOR_DNT` is on, so their visit is counted without
+/// anything that identifies them.
+fn is_anonymous(state: &AppState, headers: &HeaderMap) -> bool {
+    state.config.honor_dnt
+        && ["dnt", "sec-gpc"].iter().any(|name| {
+            headers
+                .get(*name)
+                .is_some_and(|v| v.as_bytes().trim_ascii() == b"1")
+        })
+}
+
+/// Whether the visitor's user agent matches a click filter, so the visit
+/// isn't recorded.
+fn is_filtered(state: &AppState, headers: &HeaderMap) -> bool {
+    headers
+        .get("user-agent")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ua| state.click_filters.matches(ua))
+}
+
+/// The source a short URL's query names, e.g. `SOURCE_QR` for the `src=qr`
+/// in the link's QR code.
+fn visit_source(query: Option<&str>) -> Option<&'static str> {
+    query?
+        .split('&')
+        .any(|pair| pair == "src=qr")
+        .then_some(SOURCE_QR)
+}
+
+/// A random ID for one redirect, stored on its click.
+fn new_click_id() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+/// Accent colour used on public pages of the primary domain.
+const DEFAULT_BRAND_COLOR: &str = "#3b82f6";
+
+/// Host the request was addressed to. `X-Forwarded-Host` is only believed
+/// from `TRUSTED_PROXIES`, since anyone can send it; otherwise `Host`.
+pub(crate) fn request_host<'a>(
+    config: &AppConfig,
+    headers: &'a HeaderMap,
+    addr: SocketAddr,
+) -> Option<&'a str> {
+    let forwarded = headers
+        .get("x-forwarded-host")
+        .filter(|_| config.is_trusted_proxy(addr.ip()));
+    forwarded
+        .or_else(|| headers.get("host"))
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
 /// Determine the real client IP, preferring common proxy headers.
-fn extract_ip(headers: &HeaderMap, addr: SocketAddr) -> Option<String> {
+pub(crate) fn extract_ip(headers: &HeaderMap, addr: SocketAddr) -> Option<String> {
     // X-Forwarded-For can be a comma-separated list; take the first entry.
     if let Some(xff) = headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
         if let Some(ip) = xff.split(',').next().map(str::trim) {
@@ -283,6 +1305,75 @@ fn extract_ip(headers: &HeaderMap, addr: SocketAddr) -> Option<String> {
     Some(addr.ip().to_string())
 }
 
+/// The client IP to limit or ban by. Unlike [`extract_ip`], forwarding
+/// headers are only believed from `TRUSTED_PROXIES`, since anyone can send
+/// them; the client is the last `X-Forwarded-For` entry that isn't itself a
+/// trusted proxy.
+pub(crate) fn client_ip(config: &AppConfig, headers: &HeaderMap, addr: SocketAddr) -> String {
+    let peer = addr.ip().to_canonical();
+    if !config.is_trusted_proxy(peer) {
+        return peer.to_string();
+    }
+
+    let forwarded = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|entry| entry.trim().parse::<IpAddr>().ok())
+        .map(|ip| ip.to_canonical())
+        .collect::<Vec<_>>();
+    if let Some(ip) = forwarded
+        .iter()
+        .rev()
+        .find(|ip| !config.is_trusted_proxy(**ip))
+    {
+        return ip.to_string();
+    }
+    if let Some(ip) = forwarded.first() {
+        return ip.to_string();
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<IpAddr>().ok())
+        .map_or(peer, |ip| ip.to_canonical())
+        .to_string()
+}
+
+/// User-Agent fragments of chat and social apps fetching a link to show a
+/// preview card, besides what woothee already counts as a crawler.
+const UNFURLERS: &[&str] = &[
+    "facebookexternalhit",
+    "facebookcatalog",
+    "twitterbot",
+    "slackbot",
+    "linkedinbot",
+    "discordbot",
+    "telegrambot",
+    "whatsapp",
+    "skypeuripreview",
+    "pinterest",
+    "redditbot",
+    "embedly",
+    "mastodon",
+    "bluesky",
+    "iframely",
+    "vkshare",
+    "applebot",
+];
+
+/// Whether the request comes from a bot fetching the link for a preview.
+fn is_unfurler(headers: &HeaderMap) -> bool {
+    let Some(user_agent) = headers.get("user-agent").and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    let lower = user_agent.to_ascii_lowercase();
+    UNFURLERS.iter().any(|bot| lower.contains(bot))
+        || parse_user_agent(Some(user_agent)).2.as_deref() == Some(BOT_DEVICE_TYPE)
+}
+
 /// Parse a User-Agent string using woothee and return
 /// `(browser_name, os_name, device_category)`.
 fn parse_user_agent(ua: Option<&str>) -> (Option<String>, Option<String>, Option<String>) {
@@ -317,3 +1408,72 @@ fn parse_user_agent(ua: Option<&str>) -> (Option<String>, Option<String>, Option
         None => (None, None, None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A request from `peer` whose `X-Forwarded-For` says `forwarded_for`.
+    fn request(peer: &str, forwarded_for: &str) -> (HeaderMap, SocketAddr) {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", forwarded_for.parse().unwrap());
+        (headers, SocketAddr::new(peer.parse().unwrap(), 40000))
+    }
+
+    #[tokio::test]
+    async fn spoofed_forwarded_for_is_still_blocked_by_country() {
+        let config =
+            AppConfig::from_settings(&[("JWT_SECRET", "test"), ("ACCESS_DENY_COUNTRIES", "RU")]);
+        let geo = GeoCache::with_countries(&[("203.0.113.7", "RU"), ("8.8.8.8", "US")]);
+        let (headers, addr) = request("203.0.113.7", "8.8.8.8");
+
+        assert!(!country_permitted(&config, &geo, None, &headers, addr).await);
+    }
+
+    #[tokio::test]
+    async fn forwarded_for_from_trusted_proxy_decides_country() {
+        let config = AppConfig::from_settings(&[
+            ("JWT_SECRET", "test"),
+            ("ACCESS_DENY_COUNTRIES", "RU"),
+            ("TRUSTED_PROXIES", "10.0.0.1"),
+        ]);
+        let geo = GeoCache::with_countries(&[("203.0.113.7", "RU"), ("8.8.8.8", "US")]);
+
+        let (headers, addr) = request("10.0.0.1", "203.0.113.7");
+        assert!(!country_permitted(&config, &geo, None, &headers, addr).await);
+        let (headers, addr) = request("10.0.0.1", "8.8.8.8");
+        assert!(country_permitted(&config, &geo, None, &headers, addr).await);
+    }
+
+    /// A request to `host` from `peer` whose `X-Forwarded-Host` says
+    /// `forwarded_host`.
+    fn host_request(peer: &str, host: &str, forwarded_host: &str) -> (HeaderMap, SocketAddr) {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", host.parse().unwrap());
+        headers.insert("x-forwarded-host", forwarded_host.parse().unwrap());
+        (headers, SocketAddr::new(peer.parse().unwrap(), 40000))
+    }
+
+    #[test]
+    fn spoofed_forwarded_host_is_ignored() {
+        let config = AppConfig::from_settings(&[("JWT_SECRET", "test")]);
+        let (headers, addr) = host_request("203.0.113.7", "go.brand-b.com", "go.brand-a.com");
+
+        assert_eq!(
+            request_host(&config, &headers, addr),
+            Some("go.brand-b.com")
+        );
+    }
+
+    #[test]
+    fn forwarded_host_from_trusted_proxy_is_believed() {
+        let config =
+            AppConfig::from_settings(&[("JWT_SECRET", "test"), ("TRUSTED_PROXIES", "10.0.0.1")]);
+        let (headers, addr) = host_request("10.0.0.1", "linkly:3000", "go.brand-a.com");
+
+        assert_eq!(
+            request_host(&config, &headers, addr),
+            Some("go.brand-a.com")
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::{
+    db_users,
+    handlers::redirect::client_ip,
+    links::{self, CreateError, Created, DomainChoice, NewLink, Owner},
+    AppState,
+};
+use askama::Template;
+use axum::{
+    extract::{ConnectInfo, Form, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::{net::SocketAddr, sync::Arc};
+
+/// A form submitted sooner than this after the page was rendered is assumed
+/// to come from a script.
+const MIN_FORM_AGE_SECS: i64 = 2;
+
+/// Form tokens expire after this long.
+const MAX_FORM_AGE_SECS: i64 = 3600;
+
+const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+#[derive(Template)]
+#[template(path = "public_shorten.html")]
+struct ShortenTemplate {
+    /// Signed issue time the form posts back (see `form_token`).
+    issued: i64,
+    token: String,
+    turnstile_site_key: Option<String>,
+    url: String,
+    short_url: Option<String>,
+    error: Option<String>,
+    app_title: String,
+}
+
+#[derive(Deserialize)]
+pub struct ShortenForm {
+    url: String,
+    issued: i64,
+    token: String,
+    /// Hidden from people; bots that fill in every field give themselves away.
+    #[serde(default)]
+    website: String,
+    #[serde(default, rename = "cf-turnstile-response")]
+    turnstile_response: String,
+}
+
+/// GET /shorten (only routed with `PUBLIC_SHORTEN`)
+pub async fn shorten_page(State(state): State<Arc<AppState>>) -> Response {
+    render(&state, String::new(), None, None)
+}
+
+/// POST /shorten
+///
+/// Creates a link with a generated code for an anonymous visitor, owned by
+/// `PUBLIC_SHORTEN_OWNER` (or the first admin).
+pub async fn shorten(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Form(form): Form<ShortenForm>,
+) -> Response {
+    let url = form.url.trim().to_owned();
+    let fail = |status: StatusCode, msg: &str| {
+        (
+            status,
+            render(&state, url.clone(), None, Some(msg.to_owned())),
+        )
+            .into_response()
+    };
+
+    if !form.website.is_empty() || !verify_form_token(&state.config.jwt_secret, &form) {
+        return fail(
+            StatusCode::BAD_REQUEST,
+            "This form has expired. Please try again.",
+        );
+    }
+
+    let ip = client_ip(&state.config, &headers, addr);
+    if let Some(secret) = &state.config.turnstile_secret_key {
+        if !verify_turnstile(&state, secret, &form.turnstile_response, &ip).await {
+            return fail(
+                StatusCode::BAD_REQUEST,
+                "Please complete the captcha and try again.",
+            );
+        }
+    }
+
+    if let Err(retry_after) = state
+        .public_usage
+        .record(&ip, state.tunables().public_shorten_per_hour)
+    {
+        return fail(
+            StatusCode::TOO_MANY_REQUESTS,
+            &format!(
+                "You've shortened a lot of links. Please try again in {} minutes.",
+                retry_after.div_ceil(60)
+            ),
+        );
+    }
+
+    let owner = match &state.config.public_shorten_owner {
+        Some(email) => db_users::get_user_by_email(&state.db, &email.to_lowercase()).await,
+        None => db_users::get_first_admin(&state.db).await,
+    };
+    let owner = match owner {
+        Ok(Some(u)) => u,
+        Ok(None) => {
+            tracing::error!("Public shorten: configured link owner does not exist");
+            return fail(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Shortening is unavailable right now.",
+            );
+        }
+        Err(e) => {
+            tracing::error!("Public shorten: failed to load link owner: {:?}", e);
+            return fail(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Something went wrong. Please try again.",
+            );
+        }
+    };
+
+    let owner = Owner {
+        user_id: owner.id,
+        workspace_id: owner.workspace_id,
+    };
+    let request = NewLink {
+        url: &url,
+        code: None,
+        title: None,
+        description: None,
+        domain: DomainChoice::Default,
+    };
+    match links::create(&state, owner, request).await {
+        Ok(Created::New(link)) => {
+            tracing::info!("Public shorten from {} created '{}'", ip, link.short_code);
+            let short_url = state.short_url(link.domain_id, &link.short_code);
+            (
+                StatusCode::CREATED,
+                render(&state, String::new(), Some(short_url), None),
+            )
+                .into_response()
+        }
+        Ok(Created::Existing(link)) => {
+            let short_url = state.short_url(link.domain_id, &link.short_code);
+            render(&state, String::new(), Some(short_url), None)
+        }
+        Err(e @ (CreateError::Invalid(_) | CreateError::Destination(_))) => {
+            fail(StatusCode::UNPROCESSABLE_ENTITY, &e.to_string())
+        }
+        Err(CreateError::LinkLimit(_)) => fail(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Shortening is unavailable right now.",
+        ),
+        Err(e) => {
+            tracing::error!("Public shorten: failed to create link: {:?}", e);
+            fail(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Something went wrong. Please try again.",
+            )
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+fn render(
+    state: &AppState,
+    url: String,
+    short_url: Option<String>,
+    error: Option<String>,
+) -> Response {
+    let issued = Utc::now().timestamp();
+    ShortenTemplate {
+        issued,
+        token: form_token(&state.config.jwt_secret, issued),
+        turnstile_site_key: state.config.turnstile_site_key.clone(),
+        url,
+        short_url,
+        error,
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+/// The form carries its signed render time, so only forms this server
+/// handed out are accepted, and not instantly or long after.
+fn form_mac(secret: &str, issued: i64) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(b"public-shorten:");
+    mac.update(issued.to_string().as_bytes());
+    mac
+}
+
+fn form_token(secret: &str, issued: i64) -> String {
+    hex::encode(form_mac(secret, issued).finalize().into_bytes())
+}
+
+fn verify_form_token(secret: &str, form: &ShortenForm) -> bool {
+    let age = Utc::now().timestamp() - form.issued;
+    if !(MIN_FORM_AGE_SECS..=MAX_FORM_AGE_SECS).contains(&age) {
+        return false;
+    }
+    let Ok(expected) = hex::decode(&form.token) else {
+        return false;
+    };
+    form_mac(secret, form.issued)
+        .verify_slice(&expected)
+        .is_ok()
+}
+
+#[derive(Deserialize)]
+struct TurnstileResult {
+    success: bool,
+}
+
+/// Check a Turnstile response with Cloudflare. Fails closed.
+async fn verify_turnstile(state: &AppState, secret: &str, response: &str, ip: &str) -> bool {
+    if response.is_empty() {
+        return false;
+    }
+    let result = state
+        .http
+        .post(TURNSTILE_VERIFY_URL)
+        .form(&[("secret", secret), ("response", response), ("remoteip", ip)])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status());
+    match result {
+        Ok(resp) => match resp.json::<TurnstileResult>().await {
+            Ok(r) => r.success,
+            Err(e) => {
+                tracing::warn!("Turnstile: unreadable verification response: {:?}", e);
+                false
+            }
+        },
+        Err(e) => {
+            tracing::warn!("Turnstile: verification request failed: {:?}", e);
+            false
+        }
+    }
+}
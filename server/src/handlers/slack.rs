@@ -0,0 +1,179 @@
+use crate::{
+    db_users,
+    error::AppError,
+    links::{self, CreateError, Created, DomainChoice, NewLink, Owner},
+    AppState,
+};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
+};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// Requests whose timestamp is further than this from now are rejected, as
+/// recommended by Slack, so captured requests cannot be replayed later.
+const MAX_REQUEST_AGE_SECS: i64 = 60 * 5;
+
+/// The subset of Slack's slash-command payload we use.
+#[derive(Deserialize)]
+pub struct SlashCommand {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    user_name: String,
+}
+
+/// POST /integrations/slack
+///
+/// Slash-command endpoint: `/shorten <url> [code]` creates a link and posts
+/// the short URL in the channel. Only requests signed with
+/// `SLACK_SIGNING_SECRET` are accepted; without it the endpoint is disabled.
+pub async fn slash_command(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(secret) = state.config.slack_signing_secret.as_deref() else {
+        return AppError::NotFound("Not found".into()).into_response();
+    };
+
+    if !verify_signature(secret, &headers, &body) {
+        return AppError::Unauthorized("Invalid signature".into()).into_response();
+    }
+
+    let cmd: SlashCommand = match serde_urlencoded::from_bytes(&body) {
+        Ok(c) => c,
+        Err(e) => {
+            tracing::warn!("Malformed Slack payload: {:?}", e);
+            return AppError::BadRequest("Malformed payload".into()).into_response();
+        }
+    };
+
+    let mut args = cmd.text.split_whitespace();
+    let Some(url) = args.next().map(unwrap_slack_link) else {
+        return ephemeral("Usage: `/shorten <url> [code]`");
+    };
+    let custom_code = args.next();
+
+    let owner = match &state.config.slack_link_owner {
+        Some(email) => db_users::get_user_by_email(&state.db, &email.to_lowercase()).await,
+        None => db_users::get_first_admin(&state.db).await,
+    };
+    let owner = match owner {
+        Ok(Some(u)) => u,
+        Ok(None) => {
+            tracing::error!("Slack: configured link owner does not exist");
+            return ephemeral(
+                "Linkly has no user to own this link. Ask an admin to check SLACK_LINK_OWNER.",
+            );
+        }
+        Err(e) => {
+            tracing::error!("Slack: failed to load link owner: {:?}", e);
+            return ephemeral("Something went wrong. Please try again.");
+        }
+    };
+
+    let owner = Owner {
+        user_id: owner.id,
+        workspace_id: owner.workspace_id,
+    };
+    let request = NewLink {
+        url,
+        code: custom_code,
+        title: None,
+        description: None,
+        domain: DomainChoice::Default,
+    };
+    match links::create(&state, owner, request).await {
+        Ok(Created::New(link)) => {
+            tracing::info!(
+                "Slack user '{}' created link '{}'",
+                cmd.user_name,
+                link.short_code
+            );
+            let short_url = state.short_url(link.domain_id, &link.short_code);
+            in_channel(&format!("{} → {}", short_url, link.original_url))
+        }
+        Ok(Created::Existing(link)) => {
+            let short_url = state.short_url(link.domain_id, &link.short_code);
+            in_channel(&format!("{} → {}", short_url, link.original_url))
+        }
+        Err(CreateError::LinkLimit(limit)) => ephemeral(&format!(
+            "The Slack link owner has reached their limit of {limit} links."
+        )),
+        Err(CreateError::Db(e)) => {
+            tracing::error!("Slack: failed to create link: {:?}", e);
+            ephemeral("Something went wrong. Please try again.")
+        }
+        Err(e) => ephemeral(&e.to_string()),
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// Check `X-Slack-Signature` against an HMAC-SHA256 of `v0:{timestamp}:{body}`.
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let (Some(timestamp), Some(signature)) = (
+        header("x-slack-request-timestamp"),
+        header("x-slack-signature"),
+    ) else {
+        return false;
+    };
+
+    let Ok(ts) = timestamp.parse::<i64>() else {
+        return false;
+    };
+    if (chrono::Utc::now().timestamp() - ts).abs() > MAX_REQUEST_AGE_SECS {
+        return false;
+    }
+
+    let Some(expected) = signature
+        .strip_prefix("v0=")
+        .and_then(|hex_sig| hex::decode(hex_sig).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(b"v0:");
+    mac.update(timestamp.as_bytes());
+    mac.update(b":");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Slack wraps URLs in message text as `<https://…>` or `<https://…|label>`.
+fn unwrap_slack_link(arg: &str) -> &str {
+    let inner = arg
+        .strip_prefix('<')
+        .and_then(|s| s.strip_suffix('>'))
+        .unwrap_or(arg);
+    inner.split('|').next().unwrap_or(inner)
+}
+
+/// A reply everyone in the channel sees.
+fn in_channel(text: &str) -> Response {
+    Json(serde_json::json!({
+        "response_type": "in_channel",
+        "text": text,
+    }))
+    .into_response()
+}
+
+/// A reply only the invoking user sees.
+fn ephemeral(text: &str) -> Response {
+    Json(serde_json::json!({
+        "response_type": "ephemeral",
+        "text": text,
+    }))
+    .into_response()
+}
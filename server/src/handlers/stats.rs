@@ -0,0 +1,118 @@
+use crate::{
+    db,
+    error::AppError,
+    handlers::admin::{count_countries, count_field, with_pct},
+    models::AnalyticsSummary,
+    AppState,
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// ── Template ──────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "public_stats.html")]
+struct PublicStatsTemplate {
+    summary: AnalyticsSummary,
+    short_url: String,
+    // Pre-computed breakdowns: (name, count, pct_of_total)
+    top_countries: Vec<(String, i64, i64)>,
+    top_referers: Vec<(String, i64, i64)>,
+    top_devices: Vec<(String, i64, i64)>,
+    top_browsers: Vec<(String, i64, i64)>,
+    app_title: String,
+}
+
+#[derive(Deserialize)]
+pub struct StatsQuery {
+    token: Option<String>,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────
+
+/// GET /stats/:code?token=…
+///
+/// Read-only stats for one link, for sharing with people without an account.
+/// Any missing or wrong token gets the same 404 as an unknown code, so the
+/// page never confirms which codes exist.
+pub async fn public_stats(
+    State(state): State<Arc<AppState>>,
+    Path(code): Path<String>,
+    Query(q): Query<StatsQuery>,
+) -> Response {
+    let not_found = || AppError::NotFound("Not found".into()).into_response();
+
+    let link = match db::find_link_by_code(&state.db, &code).await {
+        Ok(Some(l)) => l,
+        Ok(None) => return not_found(),
+        Err(e) => {
+            return AppError::internal(e, format!("DB error looking up stats for '{code}'"))
+                .into_response();
+        }
+    };
+
+    let authorised = match (&link.stats_token, &q.token) {
+        (Some(expected), Some(given)) => tokens_match(expected, given),
+        _ => false,
+    };
+    if !authorised {
+        return not_found();
+    }
+
+    let summary = match db::get_analytics(&state.db, link.id, db::TrafficFilter::default()).await {
+        Ok(Some(s)) => s,
+        Ok(None) => return not_found(),
+        Err(e) => {
+            return AppError::internal(
+                e,
+                format!("Failed to load public stats for link {}", link.id),
+            )
+            .into_response();
+        }
+    };
+
+    let short_url = state.short_url(link.domain_id, &link.short_code);
+
+    let total = summary.total_clicks;
+    let top_countries = with_pct(count_countries(&summary.clicks), total);
+    let top_referers = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.referer.as_deref())),
+        total,
+    );
+    let top_devices = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.device_type.as_deref())),
+        total,
+    );
+    let top_browsers = with_pct(
+        count_field(summary.clicks.iter().map(|c| c.browser.as_deref())),
+        total,
+    );
+
+    PublicStatsTemplate {
+        summary,
+        short_url,
+        top_countries,
+        top_referers,
+        top_devices,
+        top_browsers,
+        app_title: state.config.app_title.clone(),
+    }
+    .into_response()
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+/// Compare tokens without short-circuiting on the first differing byte.
+fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
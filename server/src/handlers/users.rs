@@ -1,4 +1,10 @@
-use crate::{auth::AdminUser, db_users, models::User, password, AppState};
+use crate::{
+    auth::AdminUser,
+    db, db_users, db_workspaces,
+    error::AppError,
+    models::{User, Workspace},
+    password, quota, AppState,
+};
 use askama::Template;
 use axum::{
     extract::{Form, Path, State},
@@ -9,25 +15,42 @@ use axum_extra::extract::{
     CookieJar,
 };
 use serde::Deserialize;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 // ── Template ──────────────────────────────────────────────────────────────
 
 #[derive(Template)]
 #[template(path = "users.html")]
 struct UsersTemplate {
-    users: Vec<User>,
+    users: Vec<UserRow>,
+    /// Workspaces a new user can join; only offered to instance admins once
+    /// there is more than one.
+    workspaces: Vec<Workspace>,
     flash_success: Option<String>,
     flash_error: Option<String>,
     is_admin: bool,
     app_title: String,
 }
 
+/// A user with their workspace name and quota usage.
+struct UserRow {
+    user: User,
+    workspace: String,
+    links: i64,
+    /// Effective limits (0 = unlimited).
+    link_limit: u64,
+    api_requests: u64,
+    api_limit: u64,
+}
+
 #[derive(Template)]
 #[template(path = "edit_user.html")]
 struct EditUserTemplate {
     user: User,
     is_self: bool,
+    /// Instance defaults shown next to the quota overrides (0 = unlimited).
+    default_max_links: u64,
+    default_api_requests_per_hour: u64,
     flash_success: Option<String>,
     flash_error: Option<String>,
     is_admin: bool,
@@ -49,6 +72,8 @@ pub struct CreateUserForm {
     role: Option<String>,
     is_approved: Option<String>,
     force_password_change: Option<String>,
+    /// Only honoured for instance admins; others add to their own workspace.
+    workspace_id: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -60,13 +85,16 @@ pub struct EditUserForm {
     force_password_change: Option<String>,
     new_password: Option<String>,
     new_password_confirm: Option<String>,
+    /// Quota overrides; blank uses the instance default.
+    max_links: Option<String>,
+    api_requests_per_hour: Option<String>,
 }
 
 // ── Handlers ──────────────────────────────────────────────────────────────
 
 /// GET /admin/users
 pub async fn list_users(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
 ) -> Response {
@@ -82,20 +110,42 @@ pub async fn list_users(
         .max_age(time::Duration::seconds(0))
         .build();
 
-    let users = match db_users::get_all_users(&state.db).await {
-        Ok(u) => u,
+    let (users, workspaces, link_counts) = match tokio::try_join!(
+        db_users::get_all_users(&state.db, admin.user_scope()),
+        db_workspaces::get_all_workspaces(&state.db),
+        db_users::link_counts(&state.db),
+    ) {
+        Ok(rows) => rows,
         Err(e) => {
-            tracing::error!("Failed to load users: {:?}", e);
-            return (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to load users",
-            )
-                .into_response();
+            return AppError::internal(e, "Failed to load users").into_response();
         }
     };
+    let link_counts: HashMap<i64, i64> = link_counts.into_iter().collect();
+    let workspaces: Vec<Workspace> = workspaces.into_iter().map(|(w, _, _)| w).collect();
+    let users = users
+        .into_iter()
+        .map(|user| UserRow {
+            workspace: workspaces
+                .iter()
+                .find(|w| w.id == user.workspace_id)
+                .map(|w| w.name.clone())
+                .unwrap_or_default(),
+            links: link_counts.get(&user.id).copied().unwrap_or(0),
+            link_limit: quota::link_limit(&state, user.max_links),
+            api_requests: state.api_usage.user_requests(user.id),
+            api_limit: quota::api_limit(&state, user.api_requests_per_hour),
+            user,
+        })
+        .collect();
+    let workspaces = if admin.is_instance_admin() && workspaces.len() > 1 {
+        workspaces
+    } else {
+        Vec::new()
+    };
 
     let tmpl = UsersTemplate {
         users,
+        workspaces,
         flash_success,
         flash_error,
         is_admin: true,
@@ -107,7 +157,7 @@ pub async fn list_users(
 
 /// POST /admin/users — Admin creates a new user
 pub async fn create_user(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
     Form(form): Form<CreateUserForm>,
@@ -184,6 +234,32 @@ pub async fn create_user(
     let is_approved = form.is_approved.as_deref() == Some("on");
     let force_password_change = form.force_password_change.as_deref() == Some("on");
 
+    let workspace_id = match form.workspace_id {
+        Some(id) if admin.is_instance_admin() && id != admin.workspace_id => {
+            match db_workspaces::get_workspace_by_id(&state.db, id).await {
+                Ok(Some(w)) => w.id,
+                Ok(None) => {
+                    return set_flash_and_redirect(
+                        jar,
+                        None,
+                        Some("Unknown workspace."),
+                        "/admin/users",
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("Failed to fetch workspace {}: {:?}", id, e);
+                    return set_flash_and_redirect(
+                        jar,
+                        None,
+                        Some("Internal error. Please try again."),
+                        "/admin/users",
+                    );
+                }
+            }
+        }
+        _ => admin.workspace_id,
+    };
+
     match db_users::create_user(
         &state.db,
         &email,
@@ -192,6 +268,7 @@ pub async fn create_user(
         role,
         is_approved,
         force_password_change,
+        workspace_id,
     )
     .await
     {
@@ -203,7 +280,7 @@ pub async fn create_user(
         ),
         Err(e) => {
             tracing::error!("Failed to create user: {:?}", e);
-            let msg = if e.to_string().contains("UNIQUE") {
+            let msg = if db::is_unique_violation(&e) {
                 "An account with that email already exists."
             } else {
                 "Failed to create user."
@@ -215,12 +292,12 @@ pub async fn create_user(
 
 /// POST /admin/users/:id/approve
 pub async fn approve_user(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
     Path(id): Path<i64>,
 ) -> Response {
-    match db_users::approve_user(&state.db, id).await {
+    match db_users::approve_user(&state.db, admin.user_scope(), id).await {
         Ok(true) => set_flash_and_redirect(jar, Some("User approved."), None, "/admin/users"),
         Ok(false) => set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users"),
         Err(e) => {
@@ -255,13 +332,17 @@ pub async fn change_role(
         }
     };
 
-    match db_users::set_user_role(&state.db, id, role).await {
-        Ok(true) => set_flash_and_redirect(
-            jar,
-            Some(&format!("User role changed to '{}'.", role)),
-            None,
-            "/admin/users",
-        ),
+    match db_users::set_user_role(&state.db, admin.user_scope(), id, role).await {
+        Ok(true) => {
+            // Admin subscribers receive events for every link.
+            state.hooks.reload().await;
+            set_flash_and_redirect(
+                jar,
+                Some(&format!("User role changed to '{}'.", role)),
+                None,
+                "/admin/users",
+            )
+        }
         Ok(false) => set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users"),
         Err(e) => {
             tracing::error!("Failed to change role for user {}: {:?}", id, e);
@@ -287,8 +368,11 @@ pub async fn delete_user(
         );
     }
 
-    match db_users::delete_user(&state.db, id).await {
-        Ok(true) => set_flash_and_redirect(jar, Some("User deleted."), None, "/admin/users"),
+    match db_users::delete_user(&state.db, admin.user_scope(), id).await {
+        Ok(true) => {
+            state.hooks.reload().await;
+            set_flash_and_redirect(jar, Some("User deleted."), None, "/admin/users")
+        }
         Ok(false) => set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users"),
         Err(e) => {
             tracing::error!("Failed to delete user {}: {:?}", id, e);
@@ -319,10 +403,8 @@ pub async fn edit_user_page(
         .build();
 
     let user = match db_users::get_user_by_id(&state.db, id).await {
-        Ok(Some(u)) => u,
-        Ok(None) => {
-            return set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users")
-        }
+        Ok(Some(u)) if admin.manages(&u) => u,
+        Ok(_) => return set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users"),
         Err(e) => {
             tracing::error!("Failed to load user {}: {:?}", id, e);
             return set_flash_and_redirect(jar, None, Some("Failed to load user."), "/admin/users");
@@ -332,6 +414,8 @@ pub async fn edit_user_page(
     let tmpl = EditUserTemplate {
         is_self: user.id == admin.user_id,
         user,
+        default_max_links: state.tunables().quota_max_links,
+        default_api_requests_per_hour: state.tunables().quota_api_requests_per_hour,
         flash_success,
         flash_error,
         is_admin: true,
@@ -368,10 +452,8 @@ pub async fn edit_user(
 
     // Load current user to check for email change
     let current_user = match db_users::get_user_by_id(&state.db, id).await {
-        Ok(Some(u)) => u,
-        Ok(None) => {
-            return set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users")
-        }
+        Ok(Some(u)) if admin.manages(&u) => u,
+        Ok(_) => return set_flash_and_redirect(jar, None, Some("User not found."), "/admin/users"),
         Err(e) => {
             tracing::error!("Failed to load user {}: {:?}", id, e);
             return set_flash_and_redirect(jar, None, Some("Internal error."), &redirect_to);
@@ -410,9 +492,22 @@ pub async fn edit_user(
     let is_approved = form.is_approved.as_deref() == Some("on");
     let force_password_change = form.force_password_change.as_deref() == Some("on");
 
+    let (Ok(max_links), Ok(api_requests_per_hour)) = (
+        parse_quota(form.max_links.as_deref()),
+        parse_quota(form.api_requests_per_hour.as_deref()),
+    ) else {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Quotas must be whole numbers (0 = unlimited), or blank for the default."),
+            &redirect_to,
+        );
+    };
+
     // Update all fields
     if let Err(e) = db_users::update_user_full(
         &state.db,
+        admin.user_scope(),
         id,
         &email,
         &display_name,
@@ -423,13 +518,20 @@ pub async fn edit_user(
     .await
     {
         tracing::error!("Failed to update user {}: {:?}", id, e);
-        let msg = if e.to_string().contains("UNIQUE") {
+        let msg = if db::is_unique_violation(&e) {
             "That email is already in use by another account."
         } else {
             "Failed to update user."
         };
         return set_flash_and_redirect(jar, None, Some(msg), &redirect_to);
     }
+    state.hooks.reload().await;
+
+    if let Err(e) = db_users::set_user_quotas(&state.db, id, max_links, api_requests_per_hour).await
+    {
+        tracing::error!("Failed to update quotas for user {}: {:?}", id, e);
+        return set_flash_and_redirect(jar, None, Some("Failed to update quotas."), &redirect_to);
+    }
 
     // Handle optional password reset
     let new_password = form
@@ -484,6 +586,14 @@ pub async fn edit_user(
 
 // ── Helpers ───────────────────────────────────────────────────────────────
 
+/// A quota override from the edit form: blank is `None` (instance default).
+fn parse_quota(value: Option<&str>) -> Result<Option<i64>, std::num::ParseIntError> {
+    match value.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(n) => n.parse::<u32>().map(|n| Some(n.into())),
+        None => Ok(None),
+    }
+}
+
 fn set_flash_and_redirect(
     jar: CookieJar,
     success: Option<&str>,
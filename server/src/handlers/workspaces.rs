@@ -0,0 +1,178 @@
+use crate::{auth::InstanceAdmin, db, db_workspaces, error::AppError, models::Workspace, AppState};
+use askama::Template;
+use axum::{
+    extract::{Form, Path, State},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+// ── Template ──────────────────────────────────────────────────────────────
+
+#[derive(Template)]
+#[template(path = "workspaces.html")]
+struct WorkspacesTemplate {
+    /// (workspace, users, links)
+    workspaces: Vec<(Workspace, i64, i64)>,
+    default_workspace: i64,
+    flash_success: Option<String>,
+    flash_error: Option<String>,
+    is_admin: bool,
+    app_title: String,
+}
+
+// ── Form types ────────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct CreateWorkspaceForm {
+    name: String,
+}
+
+// ── Handlers ──────────────────────────────────────────────────────────────
+
+/// GET /admin/workspaces
+pub async fn list_workspaces(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+) -> Response {
+    let flash_success = jar.get("flash_success").map(|c| c.value().to_owned());
+    let flash_error = jar.get("flash_error").map(|c| c.value().to_owned());
+
+    let clear_success = Cookie::build(("flash_success", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+    let clear_error = Cookie::build(("flash_error", ""))
+        .path("/")
+        .max_age(time::Duration::seconds(0))
+        .build();
+
+    let workspaces = match db_workspaces::get_all_workspaces(&state.db).await {
+        Ok(w) => w,
+        Err(e) => {
+            return AppError::internal(e, "Failed to load workspaces").into_response();
+        }
+    };
+
+    let tmpl = WorkspacesTemplate {
+        workspaces,
+        default_workspace: crate::models::DEFAULT_WORKSPACE,
+        flash_success,
+        flash_error,
+        is_admin: true,
+        app_title: state.config.app_title.clone(),
+    };
+
+    (jar.remove(clear_success).remove(clear_error), tmpl).into_response()
+}
+
+/// POST /admin/workspaces — Add a workspace
+pub async fn create_workspace(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Form(form): Form<CreateWorkspaceForm>,
+) -> Response {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return set_flash_and_redirect(
+            jar,
+            None,
+            Some("Workspace name is required."),
+            "/admin/workspaces",
+        );
+    }
+
+    match db_workspaces::create_workspace(&state.db, name).await {
+        Ok(workspace) => set_flash_and_redirect(
+            jar,
+            Some(&format!(
+                "Workspace '{}' added. Create its first admin from the Users page.",
+                workspace.name
+            )),
+            None,
+            "/admin/workspaces",
+        ),
+        Err(e) => {
+            tracing::error!("Failed to create workspace: {:?}", e);
+            let msg = if db::is_unique_violation(&e) {
+                "A workspace with that name already exists."
+            } else {
+                "Failed to create workspace."
+            };
+            set_flash_and_redirect(jar, None, Some(msg), "/admin/workspaces")
+        }
+    }
+}
+
+/// POST /admin/workspaces/:id/delete
+pub async fn delete_workspace(
+    _admin: InstanceAdmin,
+    State(state): State<Arc<AppState>>,
+    jar: CookieJar,
+    Path(id): Path<i64>,
+) -> Response {
+    match db_workspaces::delete_workspace(&state.db, id).await {
+        Ok(true) => {
+            // Domains reserved for it are shared again
+            match crate::db_domains::get_all_domains(&state.db).await {
+                Ok(domains) => state.domains.reload(domains),
+                Err(e) => tracing::error!("Failed to reload domains: {:?}", e),
+            }
+            set_flash_and_redirect(jar, Some("Workspace deleted."), None, "/admin/workspaces")
+        }
+        Ok(false) => set_flash_and_redirect(
+            jar,
+            None,
+            Some("Only an empty workspace other than the default one can be deleted."),
+            "/admin/workspaces",
+        ),
+        Err(e) => {
+            tracing::error!("Failed to delete workspace {}: {:?}", id, e);
+            set_flash_and_redirect(
+                jar,
+                None,
+                Some("Failed to delete workspace."),
+                "/admin/workspaces",
+            )
+        }
+    }
+}
+
+// ── Helpers ───────────────────────────────────────────────────────────────
+
+fn set_flash_and_redirect(
+    jar: CookieJar,
+    success: Option<&str>,
+    error: Option<&str>,
+    destination: &str,
+) -> Response {
+    let mut jar = jar;
+
+    if let Some(msg) = success {
+        let c = Cookie::build(("flash_success", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    if let Some(msg) = error {
+        let c = Cookie::build(("flash_error", msg.to_owned()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(30))
+            .build();
+        jar = jar.add(c);
+    }
+
+    (jar, Redirect::to(destination)).into_response()
+}
@@ -0,0 +1,212 @@
+use crate::{
+    db_hooks,
+    models::{Link, WebhookSubscription},
+};
+use sqlx::SqlitePool;
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// Events a REST hook can subscribe to.
+pub const LINK_CREATED: &str = "link.created";
+pub const CLICK_RECORDED: &str = "click.recorded";
+/// A per-link click alert threshold was reached.
+pub const CLICK_THRESHOLD: &str = "click.threshold";
+
+pub const EVENTS: &[&str] = &[LINK_CREATED, CLICK_RECORDED, CLICK_THRESHOLD];
+
+/// Attempts per delivery before it is dropped.
+const MAX_ATTEMPTS: u32 = 3;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// REST hook dispatcher.
+///
+/// Subscriptions are mirrored in memory so the per-click check for
+/// `click.recorded` subscribers never touches the database. Call
+/// [`Hooks::reload`] after any change to subscriptions or user roles.
+#[derive(Clone)]
+pub struct Hooks {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    db: SqlitePool,
+    client: reqwest::Client,
+    /// (subscription, workspace its owner administers)
+    subscriptions: RwLock<Vec<(WebhookSubscription, Option<i64>)>>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Hooks {
+    pub async fn load(db: SqlitePool, client: reqwest::Client) -> Result<Self, sqlx::Error> {
+        let subscriptions = db_hooks::get_all_subscriptions(&db).await?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                db,
+                client,
+                subscriptions: RwLock::new(subscriptions),
+            }),
+        })
+    }
+
+    /// Refresh the in-memory subscription list from the database.
+    pub async fn reload(&self) {
+        match db_hooks::get_all_subscriptions(&self.inner.db).await {
+            Ok(subs) => *self.inner.subscriptions.write().unwrap() = subs,
+            Err(e) => tracing::error!("Failed to reload webhook subscriptions: {:?}", e),
+        }
+    }
+
+    /// Whether anyone is subscribed to `event`.
+    pub fn has_subscribers(&self, event: &str) -> bool {
+        self.inner
+            .subscriptions
+            .read()
+            .unwrap()
+            .iter()
+            .any(|(s, _)| s.event == event)
+    }
+
+    /// Deliver `event` in the background to every subscriber allowed to see
+    /// `link`.
+    pub fn emit(&self, event: &'static str, link: &Link, data: serde_json::Value) {
+        if !self.has_subscribers(event) {
+            return;
+        }
+        let hooks = self.clone();
+        let (workspace_id, owner_id) = (link.workspace_id, link.user_id);
+        tokio::spawn(async move { hooks.deliver(event, workspace_id, owner_id, data).await });
+    }
+
+    /// Deliver `event` for a link in `workspace_id` owned by `owner_id` to
+    /// every matching subscriber and wait for completion.
+    pub async fn deliver(
+        &self,
+        event: &str,
+        workspace_id: i64,
+        owner_id: Option<i64>,
+        data: serde_json::Value,
+    ) {
+        let targets: Vec<WebhookSubscription> = self
+            .inner
+            .subscriptions
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(s, admin_of)| {
+                s.event == event && (*admin_of == Some(workspace_id) || Some(s.user_id) == owner_id)
+            })
+            .map(|(s, _)| s.clone())
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        let body = serde_json::json!({
+            "event": event,
+            "occurred_at": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "data": data,
+        });
+
+        let mut removed_any = false;
+        for sub in &targets {
+            if self.post_with_retry(sub, &body).await == Delivery::Gone {
+                // REST hook convention: 410 Gone means the subscriber is gone for good.
+                match db_hooks::delete_subscription(&self.inner.db, sub.id, None).await {
+                    Ok(_) => {
+                        tracing::info!("Removed webhook subscription {} after 410 Gone", sub.id);
+                        removed_any = true;
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to remove webhook subscription {}: {:?}", sub.id, e)
+                    }
+                }
+            }
+        }
+        if removed_any {
+            self.reload().await;
+        }
+    }
+}
+
+/// Event payload describing a link.
+pub fn link_data(link: &Link, short_url: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": link.id,
+        "short_code": link.short_code,
+        "short_url": short_url,
+        "original_url": link.original_url,
+        "title": link.title,
+        "description": link.description,
+        "created_at": link.created_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "is_active": link.is_active,
+    })
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+#[derive(PartialEq)]
+enum Delivery {
+    Done,
+    Gone,
+}
+
+impl Hooks {
+    async fn post_with_retry(
+        &self,
+        sub: &WebhookSubscription,
+        body: &serde_json::Value,
+    ) -> Delivery {
+        let mut delay = Duration::from_secs(1);
+        for attempt in 1..=MAX_ATTEMPTS {
+            match self
+                .inner
+                .client
+                .post(&sub.target_url)
+                .json(body)
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => return Delivery::Done,
+                Ok(resp) if resp.status() == reqwest::StatusCode::GONE => return Delivery::Gone,
+                Ok(resp)
+                    if resp.status().is_client_error()
+                        && resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+                {
+                    tracing::warn!(
+                        "Webhook subscription {} rejected delivery with {}; not retrying",
+                        sub.id,
+                        resp.status()
+                    );
+                    return Delivery::Done;
+                }
+                Ok(resp) => tracing::warn!(
+                    "Webhook subscription {} returned {} (attempt {}/{})",
+                    sub.id,
+                    resp.status(),
+                    attempt,
+                    MAX_ATTEMPTS
+                ),
+                Err(e) => tracing::warn!(
+                    "Webhook subscription {} failed: {} (attempt {}/{})",
+                    sub.id,
+                    e.without_url(),
+                    attempt,
+                    MAX_ATTEMPTS
+                ),
+            }
+
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        tracing::error!("Giving up on webhook subscription {} delivery", sub.id);
+        Delivery::Done
+    }
+}
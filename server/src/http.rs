@@ -0,0 +1,32 @@
+use crate::config::AppConfig;
+use anyhow::Context;
+use std::time::Duration;
+
+/// Build the outbound HTTP client shared through `AppState`, so every
+/// integration reuses one connection pool and honours the same timeout,
+/// user agent, and proxy (`OUTBOUND_*`). Requests needing a different
+/// timeout set it per request.
+pub fn client(config: &AppConfig) -> anyhow::Result<reqwest::Client> {
+    builder(config)?
+        .build()
+        .context("Failed to build the outbound HTTP client")
+}
+
+/// Like [`client`], but redirects are returned instead of followed, for
+/// walking a destination's redirect chain one hop at a time.
+pub fn probe_client(config: &AppConfig) -> anyhow::Result<reqwest::Client> {
+    builder(config)?
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .context("Failed to build the redirect-probing HTTP client")
+}
+
+fn builder(config: &AppConfig) -> anyhow::Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.outbound_timeout_secs))
+        .user_agent(&config.outbound_user_agent);
+    if let Some(proxy) = &config.outbound_proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy).context("Invalid OUTBOUND_PROXY")?);
+    }
+    Ok(builder)
+}
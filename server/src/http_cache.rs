@@ -0,0 +1,102 @@
+use axum::{
+    body::{self, Body, HttpBody},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, NaiveDateTime};
+use sha2::{Digest, Sha256};
+
+/// Format of `Last-Modified` and `If-Modified-Since` (RFC 9110 IMF-fixdate).
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+// ── Middleware ─────────────────────────────────────────────────────────────
+
+/// Tag successful `GET` responses with an `ETag` and answer a matching
+/// `If-None-Match` (or `If-Modified-Since`, for responses that set
+/// `Last-Modified`) with `304 Not Modified`. Pages are still rendered, but a
+/// browser revalidating an unchanged dashboard or report doesn't download it
+/// again. Streamed responses (downloads, live updates) pass through as-is.
+///
+/// Responses without a `Cache-Control` get `private, no-cache`, so browsers
+/// keep them but check back every time and shared caches don't keep them.
+pub async fn revalidate(request: Request, next: Next) -> Response {
+    if !matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+    let if_modified_since = request.headers().get(header::IF_MODIFIED_SINCE).cloned();
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    if body.size_hint().exact().is_none() {
+        return Response::from_parts(parts, body);
+    }
+    let bytes = match body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to buffer a response for its ETag: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let headers = &mut parts.headers;
+    if !headers.contains_key(header::ETAG) {
+        // Weak, since compression changes the bytes actually sent
+        let digest = Sha256::digest(&bytes);
+        let etag = format!("W/\"{}\"", hex::encode(&digest[..16]));
+        headers.insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    }
+    headers
+        .entry(header::CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static("private, no-cache"));
+
+    if not_modified(if_none_match, if_modified_since, headers) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_TYPE);
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// A `Last-Modified` header value for `at` (UTC).
+pub fn last_modified(at: NaiveDateTime) -> String {
+    at.format(HTTP_DATE).to_string()
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Whether the client's copy is current. `If-None-Match` takes precedence;
+/// ETags are compared weakly, as they are for `GET`.
+fn not_modified(
+    if_none_match: Option<HeaderValue>,
+    if_modified_since: Option<HeaderValue>,
+    response: &HeaderMap,
+) -> bool {
+    if let Some(if_none_match) = if_none_match {
+        let Some(etag) = response.get(header::ETAG).and_then(|v| v.to_str().ok()) else {
+            return false;
+        };
+        let etag = etag.trim_start_matches("W/");
+        return if_none_match.to_str().is_ok_and(|list| {
+            list.split(',')
+                .map(|tag| tag.trim())
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+        });
+    }
+
+    let date =
+        |value: Option<&HeaderValue>| DateTime::parse_from_rfc2822(value?.to_str().ok()?).ok();
+    match (
+        date(if_modified_since.as_ref()),
+        date(response.get(header::LAST_MODIFIED)),
+    ) {
+        (Some(since), Some(modified)) => modified <= since,
+        _ => false,
+    }
+}
@@ -0,0 +1,443 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// Columns of a YOURLS `yourls_url` table, for dumps whose `INSERT`s don't
+/// name them.
+const YOURLS_COLUMNS: [&str; 6] = ["keyword", "url", "title", "timestamp", "ip", "clicks"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Where an export came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A CSV file with a header row, as exported by Bitly or TinyURL.
+    Csv,
+    /// A MySQL dump of a YOURLS database.
+    Yourls,
+}
+
+/// A link read from another shortener's export, before it is checked
+/// against this instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedLink {
+    /// The link's back-half in the old service; `None` to generate one.
+    pub code: Option<String>,
+    pub url: String,
+    pub title: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    /// Clicks the old service counted.
+    pub clicks: i64,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Format {
+    /// The format named by a form field; `auto` (or anything else) guesses
+    /// from `text`.
+    pub fn from_param(value: &str, text: &str) -> Self {
+        match value {
+            "csv" => Self::Csv,
+            "yourls" => Self::Yourls,
+            _ if text.to_ascii_lowercase().contains("insert into") => Self::Yourls,
+            _ => Self::Csv,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Csv => "CSV",
+            Self::Yourls => "YOURLS SQL dump",
+        }
+    }
+}
+
+/// Read the links in an export.
+pub fn parse(format: Format, text: &str) -> Result<Vec<ImportedLink>, String> {
+    match format {
+        Format::Csv => parse_csv(text),
+        Format::Yourls => parse_yourls(text),
+    }
+}
+
+// ── CSV ────────────────────────────────────────────────────────────────────
+
+/// Links from a CSV export, finding the columns by their header. Bitly
+/// names them e.g. `long_url`, `link` and `total_clicks`, TinyURL `url`,
+/// `tinyurl` and `hits`.
+fn parse_csv(text: &str) -> Result<Vec<ImportedLink>, String> {
+    let mut rows = csv_rows(text).into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .ok_or("The file is empty.")?
+        .iter()
+        .map(|name| {
+            name.chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_lowercase()
+        })
+        .collect();
+    let column = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|h| h == name))
+    };
+
+    let url = column(&[
+        "longurl",
+        "destinationurl",
+        "destination",
+        "originalurl",
+        "url",
+    ])
+    .ok_or("No destination column found; expected one named e.g. long_url or url.")?;
+    let custom = column(&[
+        "custombitlinks",
+        "custombacklinks",
+        "alias",
+        "keyword",
+        "code",
+    ]);
+    let short = column(&[
+        "bitlink",
+        "link",
+        "shortlink",
+        "shorturl",
+        "tinyurl",
+        "short",
+    ]);
+    let title = column(&["title", "name"]);
+    let created = column(&["createdat", "created", "datecreated", "createdutc", "date"]);
+    let clicks = column(&["totalclicks", "clicks", "engagements", "hits", "visits"]);
+
+    let mut links = Vec::new();
+    for row in rows {
+        let field = |i: Option<usize>| {
+            i.and_then(|i| row.get(i))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
+        let Some(destination) = field(Some(url)) else {
+            continue;
+        };
+        // Custom back-halves are listed comma-separated; the first one wins
+        let code = field(custom)
+            .and_then(|v| v.split(',').map(str::trim).find(|v| !v.is_empty()))
+            .or_else(|| field(short))
+            .and_then(back_half);
+        links.push(ImportedLink {
+            code,
+            url: destination.to_owned(),
+            title: field(title).map(str::to_owned),
+            created_at: field(created).and_then(parse_time),
+            clicks: field(clicks).map_or(0, parse_count),
+        });
+    }
+    Ok(links)
+}
+
+/// The records of a CSV document. Quoted fields may hold commas, doubled
+/// quotes and line breaks; blank lines are skipped.
+fn csv_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    row.push(field);
+    rows.push(row);
+    rows.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    rows
+}
+
+// ── YOURLS ─────────────────────────────────────────────────────────────────
+
+/// Links from the `INSERT` statements for a YOURLS `…_url` table (any table
+/// prefix) in a `mysqldump` file. Other statements are skipped.
+fn parse_yourls(sql: &str) -> Result<Vec<ImportedLink>, String> {
+    // Lower-casing ASCII keeps byte offsets, so statements are found in
+    // `lower` and read from `sql`
+    let lower = sql.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut found_table = false;
+    let mut pos = 0;
+    while let Some(at) = lower[pos..].find("insert into") {
+        let mut p = SqlReader {
+            sql,
+            pos: pos + at + "insert into".len(),
+        };
+        let table = p.identifier().ok_or_else(|| p.error())?;
+        let mut columns: Vec<String> = YOURLS_COLUMNS.iter().map(|c| c.to_string()).collect();
+        if p.eat('(') {
+            columns.clear();
+            loop {
+                columns.push(
+                    p.identifier()
+                        .ok_or_else(|| p.error())?
+                        .to_ascii_lowercase(),
+                );
+                if !p.eat(',') {
+                    break;
+                }
+            }
+            if !p.eat(')') {
+                return Err(p.error());
+            }
+        }
+        if !p.keyword("values") {
+            return Err(p.error());
+        }
+
+        let is_url_table = table.to_ascii_lowercase().ends_with("url");
+        found_table |= is_url_table;
+        let column = |name: &str| columns.iter().position(|c| c == name);
+        let (keyword, url, title, timestamp, clicks) = (
+            column("keyword"),
+            column("url"),
+            column("title"),
+            column("timestamp"),
+            column("clicks"),
+        );
+        loop {
+            let values = p.tuple().ok_or_else(|| p.error())?;
+            if is_url_table {
+                let value = |i: Option<usize>| {
+                    i.and_then(|i| values.get(i))
+                        .and_then(Option::as_deref)
+                        .map(str::trim)
+                        .filter(|v| !v.is_empty())
+                };
+                if let Some(destination) = value(url) {
+                    links.push(ImportedLink {
+                        code: value(keyword).map(str::to_owned),
+                        url: destination.to_owned(),
+                        title: value(title).map(str::to_owned),
+                        created_at: value(timestamp).and_then(parse_time),
+                        clicks: value(clicks).map_or(0, parse_count),
+                    });
+                }
+            }
+            if !p.eat(',') {
+                break;
+            }
+        }
+        pos = p.pos;
+    }
+
+    if !found_table {
+        return Err("No INSERT INTO statements for a YOURLS url table were found.".into());
+    }
+    Ok(links)
+}
+
+/// A cursor over the SQL of one `INSERT` statement.
+struct SqlReader<'a> {
+    sql: &'a str,
+    pos: usize,
+}
+
+impl<'a> SqlReader<'a> {
+    fn error(&self) -> String {
+        let line = self.sql[..self.pos.min(self.sql.len())].lines().count();
+        format!("Couldn't read the SQL dump near line {line}.")
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.sql[self.pos..]
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume `word` (lower case) if it comes next, in any case.
+    fn keyword(&mut self, word: &str) -> bool {
+        self.skip_space();
+        let rest = self.rest();
+        if rest.len() >= word.len() && rest[..word.len()].eq_ignore_ascii_case(word) {
+            self.pos += word.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A table or column name, bare or in backticks.
+    fn identifier(&mut self) -> Option<String> {
+        self.skip_space();
+        let rest = self.rest();
+        if let Some(quoted) = rest.strip_prefix('`') {
+            let end = quoted.find('`')?;
+            self.pos += end + 2;
+            return Some(quoted[..end].to_owned());
+        }
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        self.pos += end;
+        // `db`.`table` keeps only the table
+        Some(
+            rest[..end]
+                .rsplit('.')
+                .next()
+                .unwrap_or_default()
+                .to_owned(),
+        )
+    }
+
+    /// A parenthesised list of values; `NULL`s are `None`.
+    fn tuple(&mut self) -> Option<Vec<Option<String>>> {
+        if !self.eat('(') {
+            return None;
+        }
+        let mut values = Vec::new();
+        loop {
+            values.push(self.value()?);
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.eat(')').then_some(values)
+    }
+
+    fn value(&mut self) -> Option<Option<String>> {
+        self.skip_space();
+        if self.rest().starts_with('\'') {
+            return self.string().map(Some);
+        }
+        if self.keyword("null") {
+            return Some(None);
+        }
+        let rest = self.rest();
+        let end = rest.find([',', ')']).unwrap_or(rest.len());
+        if end == 0 {
+            return None;
+        }
+        self.pos += end;
+        Some(Some(rest[..end].trim().to_owned()))
+    }
+
+    /// A single-quoted string with MySQL's backslash escapes.
+    fn string(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let mut out = String::new();
+        let mut chars = rest.char_indices().skip(1);
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let (_, escaped) = chars.next()?;
+                    out.push(match escaped {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        '0' => '\0',
+                        'Z' => '\u{1a}',
+                        other => other,
+                    });
+                }
+                '\'' if rest[i + 1..].starts_with('\'') => {
+                    chars.next();
+                    out.push('\'');
+                }
+                '\'' => {
+                    self.pos += i + 1;
+                    return Some(out);
+                }
+                c => out.push(c),
+            }
+        }
+        None
+    }
+}
+
+// ── Fields ─────────────────────────────────────────────────────────────────
+
+/// The code from a short URL or bare back-half, e.g. `abc` from
+/// `https://bit.ly/abc`.
+fn back_half(value: &str) -> Option<String> {
+    let path = value
+        .split_once("://")
+        .map_or(value, |(_, rest)| rest)
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    let code = match path.rsplit_once('/') {
+        Some((_, code)) => code,
+        // A bare host, e.g. "bit.ly", has no code
+        None if path.contains('.') && value.contains("://") => "",
+        None => path,
+    };
+    (!code.is_empty()).then(|| code.to_owned())
+}
+
+/// Timestamps as the exports write them, read as UTC.
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Some(t.naive_utc());
+    }
+    // Bitly writes offsets without a colon, e.g. +0000
+    for format in ["%Y-%m-%dT%H:%M:%S%z", "%Y-%m-%d %H:%M:%S %z"] {
+        if let Ok(t) = DateTime::parse_from_str(value, format) {
+            return Some(t.naive_utc());
+        }
+    }
+    let value = value.trim_end_matches(" UTC").trim_end_matches('Z');
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%m/%d/%Y %H:%M:%S",
+        "%m/%d/%Y %H:%M",
+    ] {
+        if let Ok(t) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(t);
+        }
+    }
+    ["%Y-%m-%d", "%m/%d/%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+}
+
+/// A click count, tolerating thousands separators.
+fn parse_count(value: &str) -> i64 {
+    value
+        .chars()
+        .filter(|c| !matches!(c, ',' | '_' | ' '))
+        .collect::<String>()
+        .parse::<i64>()
+        .unwrap_or(0)
+        .max(0)
+}
@@ -0,0 +1,125 @@
+/// Most rules a link may have.
+pub const MAX_RULES: usize = 50;
+
+/// Most `Accept-Language` entries looked at; browsers send a handful.
+const MAX_PREFERENCES: usize = 20;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A link's language rules, in the order they were written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageRules {
+    pub rules: Vec<LanguageRule>,
+}
+
+/// Visitors preferring `language` are sent to `url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageRule {
+    /// Lower-cased language tag, e.g. "de" or "pt-br".
+    pub language: String,
+    pub url: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl LanguageRules {
+    /// Parse one rule per line, a language tag and a URL, e.g.
+    /// `de https://example.com/de/`. Blank lines are skipped. URLs are
+    /// taken as written; the caller validates them.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rules: Vec<LanguageRule> = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let Some((tag, url)) = line.split_once(char::is_whitespace) else {
+                return Err(format!(
+                    "'{line}' needs a language and a URL, e.g. 'de https://example.com/de/'"
+                ));
+            };
+            if !is_language_tag(tag) {
+                return Err(format!(
+                    "'{tag}' is not a language tag (e.g. de, fr, pt-BR)"
+                ));
+            }
+            let language = tag.to_ascii_lowercase();
+            if rules.iter().any(|r| r.language == language) {
+                return Err(format!("'{tag}' has more than one rule"));
+            }
+            rules.push(LanguageRule {
+                language,
+                url: url.trim().to_owned(),
+            });
+        }
+        if rules.len() > MAX_RULES {
+            return Err(format!(
+                "A link may have at most {MAX_RULES} language rules"
+            ));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rules as stored in `links.language_rules`.
+    pub fn to_text(&self) -> String {
+        let lines: Vec<_> = self
+            .rules
+            .iter()
+            .map(|r| format!("{} {}", r.language, r.url))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// The rule for the most preferred language in `accept_language` that
+    /// has one. A rule for "pt" also covers "pt-BR", but a rule for "pt-BR"
+    /// wins for visitors asking for it.
+    pub fn pick(&self, accept_language: &str) -> Option<&LanguageRule> {
+        preferences(accept_language).iter().find_map(|tag| {
+            self.rules
+                .iter()
+                .filter(|r| {
+                    tag == &r.language
+                        || tag
+                            .strip_prefix(&r.language)
+                            .is_some_and(|rest| rest.starts_with('-'))
+                })
+                .max_by_key(|r| r.language.len())
+        })
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// A BCP 47 tag in the common form: a 2–3 letter language, then subtags of
+/// 1–8 letters or digits, e.g. "zh-Hant-TW".
+fn is_language_tag(tag: &str) -> bool {
+    let mut parts = tag.split('-');
+    let primary = parts.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|p| (1..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// The languages `Accept-Language` asks for, lower-cased and most preferred
+/// first. Those with `q=0` are refused, so they're left out, as is `*`.
+fn preferences(accept_language: &str) -> Vec<String> {
+    let mut weighted: Vec<(String, f32)> = accept_language
+        .split(',')
+        .take(MAX_PREFERENCES)
+        .filter_map(|entry| {
+            let mut params = entry.split(';');
+            let tag = params.next()?.trim();
+            if tag == "*" || !is_language_tag(tag) {
+                return None;
+            }
+            let q = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            (q > 0.0).then(|| (tag.to_ascii_lowercase(), q))
+        })
+        .collect();
+    // Stable, so equal weights keep the browser's order
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    weighted.into_iter().map(|(tag, _)| tag).collect()
+}
@@ -0,0 +1,236 @@
+use crate::{
+    cache::{CachedLink, DomainCache},
+    config::AppConfig,
+    db::{self, NewLinkRow},
+    db_bio,
+    destination::{self, DestinationError},
+    error::AppError,
+    handlers::admin::generate_unique_code,
+    models::Link,
+    quota,
+    validate::{Invalid, LinkInput},
+    AppState,
+};
+use sqlx::SqlitePool;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// The user a new link belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct Owner {
+    pub user_id: i64,
+    pub workspace_id: i64,
+}
+
+/// A link someone asked for, before it is checked.
+pub struct NewLink<'a> {
+    pub url: &'a str,
+    pub code: Option<&'a str>,
+    pub title: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub domain: DomainChoice<'a>,
+}
+
+/// Which short domain a new link is on.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum DomainChoice<'a> {
+    /// The owner's workspace domain, or the primary domain if it has none.
+    #[default]
+    Default,
+    Id(i64),
+    /// A host name; blank means the default.
+    Host(&'a str),
+}
+
+/// What [`prepare`] makes of a request.
+pub enum Prepared {
+    /// The owner's existing link to the same URL.
+    Existing(Box<Link>),
+    New(NewLinkRow),
+}
+
+/// What [`create`] did.
+pub enum Created {
+    New(Link),
+    /// Without a custom code, the owner's existing link to the same URL is
+    /// given back instead of a new one.
+    Existing(Link),
+}
+
+/// Why a link wasn't created.
+#[derive(Debug, thiserror::Error)]
+pub enum CreateError {
+    #[error(transparent)]
+    Invalid(#[from] Invalid),
+    #[error("domain is not a configured custom domain")]
+    UnknownDomain,
+    #[error(transparent)]
+    Destination(DestinationError),
+    #[error("Link limit reached ({0} links per account)")]
+    LinkLimit(u64),
+    #[error("That code conflicts with an existing links page slug.")]
+    SlugTaken,
+    #[error("That short code is already taken. Try another.")]
+    CodeTaken,
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// Create the link `request` describes for `owner`, the same way wherever
+/// it comes from: [`prepare`] it, count it against the owner's link limit,
+/// insert it, cache it, and announce it (see [`AppState::link_created`]).
+pub async fn create(
+    state: &AppState,
+    owner: Owner,
+    request: NewLink<'_>,
+) -> Result<Created, CreateError> {
+    let row = match prepare(
+        &state.db,
+        &state.config,
+        &state.probe_http,
+        &state.domains,
+        owner,
+        request,
+    )
+    .await?
+    {
+        Prepared::Existing(link) => return Ok(Created::Existing(*link)),
+        Prepared::New(row) => row,
+    };
+
+    if let Some(limit) = quota::links_exhausted(state, owner.user_id).await? {
+        return Err(CreateError::LinkLimit(limit));
+    }
+
+    let link = insert(&state.db, owner, &row).await?;
+    state
+        .cache
+        .set(link.domain_id, &link.short_code, CachedLink::from(&link));
+    state.link_created(&link);
+    Ok(Created::New(link))
+}
+
+/// Validate `request` and settle its domain and short code, short of
+/// creating the link or counting it against the owner's link limit. Takes
+/// the parts of [`AppState`] it needs, so the CLI can use it without one.
+pub async fn prepare(
+    pool: &SqlitePool,
+    config: &AppConfig,
+    probe_http: &reqwest::Client,
+    domains: &DomainCache,
+    owner: Owner,
+    request: NewLink<'_>,
+) -> Result<Prepared, CreateError> {
+    let mut input = LinkInput::new(config);
+    let url = input.url("url", request.url);
+    let custom_code = input.code("code", request.code);
+    let title = input.title("title", request.title);
+    let description = input.description("description", request.description);
+    input.finish()?;
+    let url = url.expect("validated URL");
+
+    let domain = match request.domain {
+        DomainChoice::Id(id) => Some(domains.get(id).ok_or(CreateError::UnknownDomain)?),
+        DomainChoice::Host(host) if !host.trim().is_empty() => Some(
+            domains
+                .resolve(host.trim())
+                .ok_or(CreateError::UnknownDomain)?,
+        ),
+        _ => domains.default_for(owner.workspace_id),
+    };
+    if domain
+        .as_ref()
+        .is_some_and(|d| !d.serves(owner.workspace_id))
+    {
+        return Err(CreateError::UnknownDomain);
+    }
+    let domain_id = domain.map(|d| d.id);
+
+    // Without a custom code, the same destination gets the same link back
+    if custom_code.is_none() {
+        match db::find_duplicate_link(pool, owner.user_id, domain_id, &url).await {
+            Ok(Some(link)) => return Ok(Prepared::Existing(Box::new(link))),
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to check for a duplicate link: {:?}", e),
+        }
+    }
+
+    destination::check(pool, config, probe_http, &url).await?;
+
+    let short_code = match custom_code {
+        Some(code) => {
+            match db_bio::bio_slug_exists(pool, &code).await {
+                Ok(true) => return Err(CreateError::SlugTaken),
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::error!(
+                        "DB error checking bio slug collision for '{}': {:?}",
+                        code,
+                        e
+                    );
+                }
+            }
+            code
+        }
+        None => generate_unique_code(pool, config).await,
+    };
+    Ok(Prepared::New(NewLinkRow {
+        short_code,
+        original_url: url,
+        title,
+        description,
+        domain_id,
+    }))
+}
+
+/// Insert a link [`prepare`]d for `owner`.
+pub async fn insert(
+    pool: &SqlitePool,
+    owner: Owner,
+    row: &NewLinkRow,
+) -> Result<Link, CreateError> {
+    db::create_link(
+        pool,
+        &row.short_code,
+        &row.original_url,
+        row.title.as_deref(),
+        row.description.as_deref(),
+        owner.user_id,
+        row.domain_id,
+    )
+    .await
+    .map_err(|e| {
+        if db::is_unique_violation(&e) {
+            CreateError::CodeTaken
+        } else {
+            CreateError::Db(e)
+        }
+    })
+}
+
+/// A failed destination check is the caller's problem, unless the database
+/// failed.
+impl From<DestinationError> for CreateError {
+    fn from(e: DestinationError) -> Self {
+        match e {
+            DestinationError::Db(e) => Self::Db(e),
+            e => Self::Destination(e),
+        }
+    }
+}
+
+impl From<CreateError> for AppError {
+    fn from(e: CreateError) -> Self {
+        match e {
+            CreateError::Invalid(invalid) => Self::Invalid(invalid),
+            CreateError::LinkLimit(_) => Self::RateLimited(e.to_string(), None),
+            CreateError::SlugTaken | CreateError::CodeTaken => Self::Conflict(e.to_string()),
+            CreateError::Db(e) => Self::internal(e, "Failed to create link"),
+            CreateError::UnknownDomain | CreateError::Destination(_) => {
+                Self::Unprocessable(e.to_string())
+            }
+        }
+    }
+}
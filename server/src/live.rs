@@ -0,0 +1,55 @@
+use crate::{cache::CachedLink, db::Scope};
+use tokio::sync::broadcast;
+
+/// Clicks buffered for each listener. One that falls further behind skips
+/// ahead, which is fine for counters that are recounted anyway.
+const CAPACITY: usize = 256;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A click just recorded, as announced to live dashboards.
+#[derive(Debug, Clone, Copy)]
+pub struct LiveClick {
+    pub link_id: i64,
+    pub workspace_id: i64,
+    pub user_id: Option<i64>,
+}
+
+/// Fans recorded clicks out to open dashboards, so their counters tick up
+/// without a refresh. Cheap to clone; publishing with nobody listening does
+/// nothing.
+#[derive(Clone)]
+pub struct LiveClicks {
+    tx: broadcast::Sender<LiveClick>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl LiveClicks {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        Self { tx }
+    }
+
+    /// Announce a recorded click on `link`.
+    pub fn publish(&self, link: &CachedLink) {
+        // Only fails when no dashboard is open
+        let _ = self.tx.send(LiveClick {
+            link_id: link.id,
+            workspace_id: link.workspace_id,
+            user_id: link.user_id,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveClick> {
+        self.tx.subscribe()
+    }
+}
+
+impl LiveClick {
+    /// Whether the click counts toward a listing over `scope`.
+    pub fn in_scope(&self, scope: Scope) -> bool {
+        scope.workspace_id.is_none_or(|id| id == self.workspace_id)
+            && scope.user_id.is_none_or(|id| Some(id) == self.user_id)
+    }
+}
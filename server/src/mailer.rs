@@ -0,0 +1,164 @@
+use crate::config::{AppConfig, SmtpSecurity};
+use askama::Template;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Attempts per email before it is dropped.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Emails queued beyond this are dropped rather than growing memory without
+/// bound while the SMTP server is unreachable.
+const QUEUE_CAPACITY: usize = 1_000;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Sends plain-text alert emails through the configured SMTP server.
+///
+/// Messages go onto a queue drained by a background task started in
+/// [`Mailer::start`], which retries transient failures. Without `SMTP_HOST`
+/// this does nothing.
+#[derive(Clone)]
+pub struct Mailer {
+    tx: Option<mpsc::Sender<Email>>,
+}
+
+struct Email {
+    to: Mailbox,
+    subject: String,
+    body: String,
+}
+
+type Transport = AsyncSmtpTransport<Tokio1Executor>;
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Mailer {
+    /// Spawn the send queue when SMTP is configured.
+    pub fn start(config: &AppConfig) -> Self {
+        let (Some(host), Some(from)) = (&config.smtp_host, &config.smtp_from) else {
+            return Self { tx: None };
+        };
+        // Validated by `AppConfig`.
+        let Ok(from) = from.parse::<Mailbox>() else {
+            return Self { tx: None };
+        };
+
+        let builder = match config.smtp_security {
+            SmtpSecurity::StartTls => Transport::starttls_relay(host),
+            SmtpSecurity::Tls => Transport::relay(host),
+            SmtpSecurity::None => Ok(Transport::builder_dangerous(host)),
+        };
+        let mut builder = match builder {
+            Ok(b) => b.port(config.smtp_port),
+            Err(e) => {
+                tracing::error!("Email disabled: invalid SMTP_HOST '{}': {}", host, e);
+                return Self { tx: None };
+            }
+        };
+        if let (Some(user), Some(pass)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+        }
+
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(run(rx, builder.build(), from));
+        Self { tx: Some(tx) }
+    }
+
+    /// Whether SMTP is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// Render `body` and queue it for delivery to `to`. Never blocks the
+    /// caller; failures are logged, not returned.
+    pub fn send(&self, to: &str, subject: &str, body: &impl Template) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+
+        let to = match to.parse::<Mailbox>() {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::warn!("Not emailing invalid address '{}': {}", to, e);
+                return;
+            }
+        };
+        let body = match body.render() {
+            Ok(b) => b,
+            Err(e) => {
+                tracing::error!("Failed to render email '{}': {:?}", subject, e);
+                return;
+            }
+        };
+
+        let email = Email {
+            to,
+            subject: subject.to_owned(),
+            body,
+        };
+        if let Err(mpsc::error::TrySendError::Full(_)) = tx.try_send(email) {
+            tracing::warn!("Email queue is full; dropping '{}'", subject);
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// Deliver queued emails one at a time until the channel closes.
+async fn run(mut rx: mpsc::Receiver<Email>, transport: Transport, from: Mailbox) {
+    while let Some(email) = rx.recv().await {
+        let message = match Message::builder()
+            .from(from.clone())
+            .to(email.to.clone())
+            .subject(&email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body)
+        {
+            Ok(m) => m,
+            Err(e) => {
+                tracing::error!("Failed to build email '{}': {}", email.subject, e);
+                continue;
+            }
+        };
+
+        let mut delay = Duration::from_secs(5);
+        for attempt in 1..=MAX_ATTEMPTS {
+            match transport.send(message.clone()).await {
+                Ok(_) => break,
+                Err(e) if e.is_permanent() => {
+                    tracing::warn!(
+                        "SMTP server rejected '{}' to {}: {}; not retrying",
+                        email.subject,
+                        email.to.email,
+                        e
+                    );
+                    break;
+                }
+                Err(e) if attempt == MAX_ATTEMPTS => {
+                    tracing::error!(
+                        "Giving up on '{}' to {}: {}",
+                        email.subject,
+                        email.to.email,
+                        e
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Sending '{}' failed: {} (attempt {}/{})",
+                        email.subject,
+                        e,
+                        attempt,
+                        MAX_ATTEMPTS
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 3;
+                }
+            }
+        }
+    }
+}
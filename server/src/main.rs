@@ -1,28 +1,118 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize},
+        Arc,
+    },
+    time::Duration,
+};
 
 use axum::{
     extract::DefaultBodyLimit,
-    routing::{get, post},
+    routing::{delete, get, patch, post},
     Router,
 };
+use clap::Parser;
 use sqlx::sqlite::SqlitePoolOptions;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tower_http::{compression::CompressionLayer, timeout::TimeoutLayer, trace::TraceLayer};
 
+mod alerts;
+mod analytics;
 mod auth;
+mod backup;
+mod beacon;
 mod cache;
+mod cache_bus;
+mod cli;
+mod click_filters;
+mod click_limit;
 mod config;
 mod db;
+mod db_alerts;
+mod db_aliases;
 mod db_bio;
+mod db_click_filters;
+mod db_click_log;
+mod db_conversions;
+mod db_digest;
+mod db_domains;
+mod db_favicons;
+mod db_funnels;
+mod db_geo_backfill;
+mod db_geo_cache;
+mod db_graphql;
+mod db_hooks;
+mod db_link_activity;
+mod db_maintenance;
+mod db_rollups;
+mod db_screenshots;
 mod db_users;
+mod db_workspaces;
+mod destination;
+mod digest;
+mod error;
+mod error_report;
+mod fallback;
+mod favicon;
+mod funnel;
+mod ga;
 mod geo;
+mod geo_backfill;
+mod geo_provider;
+mod graphql;
 mod handlers;
+mod hooks;
+mod http;
+mod http_cache;
+mod import;
+mod language;
+mod links;
+mod live;
+mod mailer;
+mod maintenance;
 mod models;
+mod notify;
+mod oidc;
 mod password;
+mod qr;
+mod quota;
+mod reload;
+mod retention;
 mod s3;
+mod safe_browsing;
+mod scanners;
+mod schedule;
+mod screenshot;
+mod short_code;
+mod sketch;
+mod telegram;
+mod trash;
+mod unique_visitors;
+mod url_macros;
+mod validate;
+mod variants;
+mod wal;
 
-use cache::LinkCache;
+use alerts::ClickAlerts;
+use analytics::Forwarder;
+use beacon::Beacons;
+use cache::{DomainCache, LinkCache};
+use click_filters::ClickFilters;
+use click_limit::ClickLimiter;
+use favicon::Favicons;
+use ga::GaExporter;
 use geo::GeoCache;
+use geo_backfill::GeoBackfill;
+use hooks::Hooks;
+use live::LiveClicks;
+use mailer::Mailer;
+use maintenance::MaintenanceMode;
+use notify::Notifier;
+use quota::{ApiUsage, ClientUsage};
+use reload::Reloader;
+use scanners::Scanners;
+use screenshot::Screenshots;
 
 // ── Shared application state ───────────────────────────────────────────────
 
@@ -30,9 +120,124 @@ pub struct AppState {
     pub db: sqlx::SqlitePool,
     pub config: config::AppConfig,
     pub cache: LinkCache,
-    /// In-memory cache for IP → GeoInfo lookups so the same IP is never
-    /// looked up more than once per server lifetime.
+    /// Extra short domains, keyed by host name for the redirect handler.
+    pub domains: DomainCache,
+    /// Cache for IP → GeoInfo lookups, persisted to the `geo_cache` table so
+    /// the same IP isn't looked up again until its entry expires.
     pub geo_cache: GeoCache,
+    /// Admin-triggered re-resolution of clicks recorded without a location.
+    pub geo_backfill: GeoBackfill,
+    /// Number of click/view logging tasks spawned but not yet written to the
+    /// database. Reported by `/health/ready`.
+    pub pending_clicks: AtomicUsize,
+    /// Redirect lookups that gave up after `REDIRECT_LOOKUP_BUDGET_MS`.
+    /// Reported by `/health/ready`.
+    pub slow_lookups: AtomicU64,
+    /// Redirects that only found their link after tidying up the code (see
+    /// `handlers::redirect::fuzzy_resolve`). Reported by `/health/ready`.
+    pub fuzzy_matches: AtomicU64,
+    /// Client IPs banned for probing unknown short codes.
+    pub scanners: Scanners,
+    /// Outbound chat-webhook notifications (no-op when none are configured).
+    pub notifier: Notifier,
+    /// REST hook subscriptions (`/api/v1/hooks`).
+    pub hooks: Hooks,
+    /// Click forwarding to Plausible / Umami (no-op when not configured).
+    pub analytics: Forwarder,
+    /// Batched click export to Google Analytics 4 (no-op when not configured).
+    pub ga: GaExporter,
+    /// Outbound alert email (no-op when SMTP is not configured).
+    pub mailer: Mailer,
+    /// Per-link click thresholds not yet reached.
+    pub click_alerts: ClickAlerts,
+    /// Clicks counted this minute on links with a click limit.
+    pub click_limiter: ClickLimiter,
+    /// Clicks on client-redirect links waiting for their page's beacon.
+    pub beacons: Beacons,
+    /// Recorded clicks for the dashboard's live counter.
+    pub live_clicks: LiveClicks,
+    /// Reporting schema for `/api/graphql`; `None` unless `GRAPHQL_ENABLED`.
+    pub graphql: Option<graphql::LinklySchema>,
+    /// Shared outbound HTTP client (see `http::client`).
+    pub http: reqwest::Client,
+    /// Client that doesn't follow redirects, for checking new destinations
+    /// for loops (see `destination::check`).
+    pub probe_http: reqwest::Client,
+    /// Single sign-on for the admin panel; `None` unless `OIDC_ISSUER` is set.
+    pub oidc: Option<oidc::Oidc>,
+    /// API requests this hour, for the `QUOTA_API_*` limits.
+    pub api_usage: ApiUsage,
+    /// Links created on the public `/shorten` page this hour, per client IP.
+    pub public_usage: ClientUsage,
+    /// Link thumbnails (no-op unless `SCREENSHOT_URL` is set).
+    pub screenshots: Screenshots,
+    /// Destination favicons for the admin panel; `None` with `FAVICONS=false`.
+    pub favicons: Option<Favicons>,
+    /// Maintenance mode, switched from the dashboard by instance admins.
+    pub maintenance: MaintenanceMode,
+    /// User agents whose visits aren't recorded (`/admin/settings/filters`).
+    pub click_filters: ClickFilters,
+    /// Config reloads and the settings they change (see [`AppState::tunables`]).
+    pub reloader: Reloader,
+}
+
+impl AppState {
+    /// Public short URL for `short_code` on the given domain (`None` = primary).
+    pub fn short_url(&self, domain_id: Option<i64>, short_code: &str) -> String {
+        let domain = domain_id.and_then(|id| self.domains.get(id));
+        let base_url = domain
+            .as_ref()
+            .map_or(self.config.base_url.as_str(), |d| d.base_url.as_str());
+        format!("{}/{}", base_url, short_code)
+    }
+
+    /// Whether `link` answers on its short URL: active, not in the trash, and
+    /// not archived unless archived links keep redirecting.
+    pub fn link_resolves(&self, link: &models::Link) -> bool {
+        link.is_active
+            && link.deleted_at.is_none()
+            && (link.archived_at.is_none() || self.config.archived_links_redirect)
+    }
+
+    /// The settings a config reload can change, as currently in effect. Read
+    /// these rather than the same fields on `config`.
+    pub fn tunables(&self) -> Arc<config::Tunables> {
+        self.reloader.tunables()
+    }
+
+    /// Whether visits are recorded: `ANALYTICS_ENABLED` is on and maintenance
+    /// mode isn't.
+    pub fn records_visits(&self) -> bool {
+        self.config.analytics_enabled && self.maintenance.get().is_none()
+    }
+
+    /// Announce a newly created link to chat webhooks and REST hook
+    /// subscribers, and capture its thumbnail. Returns the link's short URL.
+    pub fn link_created(&self, link: &models::Link) -> String {
+        let short_url = self.short_url(link.domain_id, &link.short_code);
+        self.notifier.send(
+            notify::Event::LinkCreated,
+            notify::LinkVars {
+                code: link.short_code.clone(),
+                short_url: short_url.clone(),
+                url: link.original_url.clone(),
+                title: link.title.clone(),
+                clicks: 0,
+            },
+        );
+        self.hooks.emit(
+            hooks::LINK_CREATED,
+            link,
+            serde_json::json!({ "link": hooks::link_data(link, &short_url) }),
+        );
+        self.screenshots.capture(link);
+        short_url
+    }
+
+    /// Check a new link's destination for loops (see `destination::check`).
+    pub async fn check_destination(&self, url: &str) -> Result<(), destination::DestinationError> {
+        destination::check(&self.db, &self.config, &self.probe_http, url).await
+    }
 }
 
 // ── Entry point ────────────────────────────────────────────────────────────
@@ -40,22 +245,41 @@ pub struct AppState {
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Load .env (ignore error if file is absent — env vars may already be set)
-    dotenvy::dotenv().ok();
+    config::load_dotenv();
 
     // Initialise structured logging
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "linkly=info,tower_http=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    reload::init_logging();
 
-    // Load configuration from environment
-    let config = config::AppConfig::from_env()?;
-    tracing::info!("Starting Linkly on {}:{}", config.host, config.port);
-    tracing::info!("Base URL: {}", config.base_url);
+    let cli = cli::Cli::parse();
+
+    let config_path = cli.config.clone().or_else(|| {
+        Path::new(config::DEFAULT_CONFIG_FILE)
+            .exists()
+            .then(|| PathBuf::from(config::DEFAULT_CONFIG_FILE))
+    });
+
+    // Load configuration from environment, falling back to the config file
+    let config = config::AppConfig::load(config_path.as_deref())?;
+    if cli.check_config {
+        match &config_path {
+            Some(p) => println!("# Config file: {}", p.display()),
+            None => println!("# Config file: (none, environment only)"),
+        }
+        print!("{}", config.describe());
+        return Ok(());
+    }
+    reload::set_log_filter(&config.log_filter)?;
+
+    let db = open_database(&config).await?;
 
+    match cli.command.unwrap_or(cli::Command::Serve) {
+        cli::Command::Serve => serve(config, config_path, db).await,
+        command => cli::run(command, &config, &db).await,
+    }
+}
+
+/// Open the SQLite pool, apply migrations, and make sure the seed admin exists.
+async fn open_database(config: &config::AppConfig) -> anyhow::Result<sqlx::SqlitePool> {
     // Open SQLite connection pool
     let db = SqlitePoolOptions::new()
         .max_connections(10)
@@ -82,8 +306,17 @@ async fn main() -> anyhow::Result<()> {
             None => {
                 let hash = password::hash_password(pass)
                     .map_err(|e| anyhow::anyhow!("Failed to hash seed password: {}", e))?;
-                let admin =
-                    db_users::create_user(&db, email, "Admin", &hash, "admin", true, false).await?;
+                let admin = db_users::create_user(
+                    &db,
+                    email,
+                    "Admin",
+                    &hash,
+                    "admin",
+                    true,
+                    false,
+                    models::DEFAULT_WORKSPACE,
+                )
+                .await?;
                 tracing::info!("Seeded admin user: {}", email);
 
                 // Attribute existing unowned links/pages to the seed admin
@@ -107,19 +340,159 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    Ok(db)
+}
+
+/// Run the HTTP server until it is shut down.
+async fn serve(
+    config: config::AppConfig,
+    config_path: Option<PathBuf>,
+    db: sqlx::SqlitePool,
+) -> anyhow::Result<()> {
+    tracing::info!("Starting Linkly on {}:{}", config.host, config.port);
+    tracing::info!("Base URL: {}", config.base_url);
+    if !config.analytics_enabled {
+        tracing::info!("Click analytics are off (ANALYTICS_ENABLED=false); visits aren't recorded");
+    }
+    if !config.geo_enabled && !config.country_rules.allow.is_empty() {
+        tracing::warn!(
+            "ACCESS_ALLOW_COUNTRIES is set but GEO_ENABLED is off; \
+             every visitor will be turned away"
+        );
+    }
+
     // Build shared state
-    let cache = LinkCache::new();
-    db::warm_cache(&db, &cache).await?;
+    let cache = LinkCache::new(
+        config.link_cache_max_entries,
+        (config.link_cache_ttl_minutes > 0)
+            .then(|| Duration::from_secs(config.link_cache_ttl_minutes * 60)),
+    );
+    db::warm_cache(
+        &db,
+        &cache,
+        config.archived_links_redirect,
+        config.link_cache_max_entries,
+    )
+    .await?;
+    cache_bus::start(&config, &cache)?;
 
-    let geo_cache = GeoCache::new();
+    let domains = DomainCache::new();
+    domains.reload(db_domains::get_all_domains(&db).await?);
+
+    let http = http::client(&config)?;
+    error_report::start(&config, http.clone());
+    let probe_http = http::probe_client(&config)?;
+    let geo_cache = GeoCache::load(&config, db.clone(), http.clone()).await?;
+    let notifier = Notifier::new(&config, http.clone());
+    let hooks = Hooks::load(db.clone(), http.clone()).await?;
+    let analytics = Forwarder::new(&config, http.clone());
+    let ga = GaExporter::start(&config, http.clone());
+    let mailer = Mailer::start(&config);
+    let click_alerts = ClickAlerts::load(&db).await?;
+    let graphql = config.graphql_enabled.then(graphql::schema);
+    let oidc = oidc::Oidc::new(&config, http.clone());
+    let screenshots = Screenshots::new(&config, http.clone(), db.clone());
+    let favicons = config
+        .favicons
+        .then(|| Favicons::new(http.clone(), db.clone()));
+    let maintenance = MaintenanceMode::load(&db).await?;
+    let click_filters = ClickFilters::load(&db).await?;
+    let reloader = Reloader::new(&config, config_path);
+    let scanners = Scanners::new(
+        config.scanner_miss_limit,
+        Duration::from_secs(config.scanner_ban_minutes * 60),
+    );
 
     let state = Arc::new(AppState {
         db,
         config,
         cache,
+        domains,
         geo_cache,
+        geo_backfill: GeoBackfill::new(),
+        pending_clicks: AtomicUsize::new(0),
+        slow_lookups: AtomicU64::new(0),
+        fuzzy_matches: AtomicU64::new(0),
+        scanners,
+        notifier,
+        hooks,
+        analytics,
+        ga,
+        mailer,
+        click_alerts,
+        click_limiter: ClickLimiter::new(),
+        beacons: Beacons::new(),
+        live_clicks: LiveClicks::new(),
+        graphql,
+        http,
+        probe_http,
+        oidc,
+        api_usage: ApiUsage::new(),
+        public_usage: ClientUsage::new(),
+        screenshots,
+        favicons,
+        maintenance,
+        click_filters,
+        reloader,
     });
 
+    // ── Background tasks ───────────────────────────────────────────────────
+    tokio::spawn(trash::run(state.db.clone()));
+    if state.config.click_retention_days > 0 {
+        tokio::spawn(retention::run(
+            state.db.clone(),
+            state.config.click_retention_days,
+            state.config.jwt_secret.clone(),
+        ));
+    } else {
+        tokio::spawn(unique_visitors::run(
+            state.db.clone(),
+            state.config.jwt_secret.clone(),
+        ));
+    }
+    #[cfg(unix)]
+    tokio::spawn(reload::run(state.clone()));
+    if let Some(token) = state.config.telegram_bot_token.clone() {
+        tokio::spawn(telegram::run(state.clone(), token));
+    }
+    if !state.config.digest_recipients.is_empty() {
+        if state.mailer.is_enabled() {
+            tokio::spawn(digest::run(state.clone()));
+        } else {
+            tracing::warn!(
+                "DIGEST_RECIPIENTS is set but SMTP is not configured; no digest will be sent"
+            );
+        }
+    }
+    if state.config.backup_s3 && !state.config.s3_configured() {
+        tracing::warn!("BACKUP_S3 is set but S3 is not configured; backups will fail to upload");
+    }
+    if state.config.backup_dir.is_some() || state.config.backup_s3 {
+        tokio::spawn(backup::run(state.clone()));
+    }
+    if state.config.wal_checkpoint_minutes > 0 {
+        let interval = Duration::from_secs(state.config.wal_checkpoint_minutes * 60);
+        tokio::spawn(wal::run(state.db.clone(), interval));
+    }
+    if state.config.fallback_check_minutes > 0 {
+        let interval = Duration::from_secs(state.config.fallback_check_minutes * 60);
+        tokio::spawn(fallback::run(state.clone(), interval));
+    }
+    if state.config.link_cache_ttl_minutes > 0 {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        state.cache.revalidate_with(tx);
+        tokio::spawn(cache::revalidate(state.clone(), rx));
+    }
+    if state.config.link_cache_resync_minutes > 0 {
+        let interval = Duration::from_secs(state.config.link_cache_resync_minutes * 60);
+        tokio::spawn(cache::resync(
+            state.db.clone(),
+            state.cache.clone(),
+            state.config.archived_links_redirect,
+            interval,
+        ));
+    }
+
     // ── Router ─────────────────────────────────────────────────────────────
     let admin_router = Router::new()
         .route("/", get(handlers::admin::admin_index))
@@ -127,6 +500,8 @@ async fn main() -> anyhow::Result<()> {
             "/login",
             get(handlers::admin::login_page).post(handlers::admin::login),
         )
+        .route("/login/sso", get(handlers::admin::sso_login))
+        .route("/login/sso/callback", get(handlers::admin::sso_callback))
         .route(
             "/register",
             get(handlers::admin::register_page).post(handlers::admin::register),
@@ -141,11 +516,86 @@ async fn main() -> anyhow::Result<()> {
             get(handlers::admin::profile_page).post(handlers::admin::update_profile),
         )
         .route("/dashboard", get(handlers::admin::dashboard))
+        .route("/dashboard/live", get(handlers::admin::dashboard_live))
+        .route("/feed.xml", get(handlers::feed::new_links))
+        .route("/reload-config", post(handlers::admin::reload_config))
+        .route("/analytics/compare", get(handlers::admin::compare))
+        .route(
+            "/funnels",
+            get(handlers::funnels::list_funnels).post(handlers::funnels::create_funnel),
+        )
+        .route("/funnels/:id", get(handlers::funnels::funnel_report))
+        .route(
+            "/funnels/:id/delete",
+            post(handlers::funnels::delete_funnel),
+        )
+        .route("/links/:id/report", get(handlers::admin::link_report))
         .route("/short-links", get(handlers::admin::short_links))
+        .route("/short-links/trash", get(handlers::admin::trash))
         .route("/validate-code", get(handlers::admin::validate_code))
         .route("/links", post(handlers::admin::create_link))
+        .route(
+            "/links/:id",
+            get(handlers::admin::link_detail).post(handlers::admin::save_link_details),
+        )
         .route("/links/:id/delete", post(handlers::admin::delete_link))
+        .route("/links/:id/restore", post(handlers::admin::restore_link))
+        .route("/links/:id/archive", post(handlers::admin::set_archived))
         .route("/links/:id/analytics", get(handlers::admin::analytics))
+        .route("/links/:id/clicks", get(handlers::admin::click_log))
+        .route("/links/:id/map", get(handlers::admin::click_map))
+        .route("/links/:id/qr.svg", get(handlers::admin::qr_code))
+        .route("/links/:id/share", post(handlers::admin::share_stats))
+        .route(
+            "/links/:id/redirect-mode",
+            post(handlers::admin::set_redirect_mode),
+        )
+        .route("/links/:id/domain", post(handlers::admin::set_link_domain))
+        .route(
+            "/links/:id/countries",
+            post(handlers::admin::set_country_rules),
+        )
+        .route(
+            "/links/:id/languages",
+            post(handlers::admin::set_language_rules),
+        )
+        .route(
+            "/links/:id/schedule",
+            post(handlers::admin::set_schedule_rules),
+        )
+        .route(
+            "/links/:id/fallback",
+            post(handlers::admin::set_fallback_url),
+        )
+        .route("/links/:id/variants", post(handlers::admin::set_variants))
+        .route(
+            "/links/:id/click-limit",
+            post(handlers::admin::set_click_limit),
+        )
+        .route(
+            "/links/:id/social",
+            post(handlers::admin::set_social_preview),
+        )
+        .route("/favicons/:host", get(handlers::admin::favicon))
+        .route(
+            "/links/:id/screenshot",
+            get(handlers::admin::screenshot).post(handlers::admin::refresh_screenshot),
+        )
+        .route("/links/:id/merge", post(handlers::admin::merge_link))
+        .route(
+            "/links/:id/duplicate",
+            post(handlers::admin::duplicate_link),
+        )
+        .route("/links/:id/aliases", post(handlers::admin::add_alias))
+        .route(
+            "/links/:id/aliases/:code/delete",
+            post(handlers::admin::delete_alias),
+        )
+        .route("/links/:id/alerts", post(handlers::admin::add_click_alert))
+        .route(
+            "/links/:id/alerts/:threshold/delete",
+            post(handlers::admin::delete_click_alert),
+        )
         // Bio pages
         .route(
             "/bio",
@@ -153,7 +603,6 @@ async fn main() -> anyhow::Result<()> {
         )
         .route("/bio/new", get(handlers::bio::new_bio_page))
         .route("/bio/validate-slug", get(handlers::bio::validate_slug))
-        .route("/bio/upload", post(handlers::bio::upload_image))
         .route("/bio/unsplash", get(handlers::bio::search_unsplash))
         .route("/bio/search-images", get(handlers::bio::search_images))
         .route("/bio/:id/edit", get(handlers::bio::edit_bio_page))
@@ -172,23 +621,172 @@ async fn main() -> anyhow::Result<()> {
             "/users/:id/edit",
             get(handlers::users::edit_user_page).post(handlers::users::edit_user),
         )
-        .layer(DefaultBodyLimit::max(10 * 1024 * 1024));
+        // Short domains (instance admins only)
+        .route(
+            "/domains",
+            get(handlers::domains::list_domains).post(handlers::domains::create_domain),
+        )
+        .route(
+            "/domains/:id/edit",
+            get(handlers::domains::edit_domain_page).post(handlers::domains::edit_domain),
+        )
+        .route(
+            "/domains/:id/delete",
+            post(handlers::domains::delete_domain),
+        )
+        // Workspaces (instance admins only)
+        .route(
+            "/workspaces",
+            get(handlers::workspaces::list_workspaces).post(handlers::workspaces::create_workspace),
+        )
+        .route(
+            "/workspaces/:id/delete",
+            post(handlers::workspaces::delete_workspace),
+        )
+        .route("/geo-backfill", post(handlers::admin::start_geo_backfill))
+        .route("/maintenance", post(handlers::admin::set_maintenance))
+        .route(
+            "/settings/filters",
+            get(handlers::filters::list_filters).post(handlers::filters::create_filter),
+        )
+        .route(
+            "/settings/filters/:id/delete",
+            post(handlers::filters::delete_filter),
+        )
+        .route("/api-tokens", post(handlers::admin::create_api_token))
+        .route(
+            "/api-tokens/:id/delete",
+            post(handlers::admin::delete_api_token),
+        )
+        .layer(axum::middleware::from_fn(http_cache::revalidate))
+        .layer(DefaultBodyLimit::max(
+            state.config.form_body_limit_kb * 1024,
+        ))
+        // Uploads get their own, larger limit. The import confirmation is a
+        // form, but it carries every link from the uploaded file.
+        .merge(
+            Router::new()
+                .route(
+                    "/import",
+                    get(handlers::import::import_page).post(handlers::import::import_links),
+                )
+                .route("/import/preview", post(handlers::import::preview_import))
+                .route("/bio/upload", post(handlers::bio::upload_image))
+                .layer(DefaultBodyLimit::max(
+                    state.config.upload_limit_mb * 1024 * 1024,
+                )),
+        );
+
+    // Database downloads can take longer than REQUEST_TIMEOUT_SECS to
+    // prepare, so they're added after the timeout.
+    let download_router = Router::new()
+        .route("/admin/backup", get(handlers::admin::download_backup))
+        .route("/admin/db-snapshot", get(handlers::admin::db_snapshot));
+
+    // Anonymous shortening; not routed at all unless enabled, so `/shorten`
+    // stays free for a short code otherwise.
+    let public_router = if state.config.public_shorten {
+        Router::new().route(
+            "/shorten",
+            get(handlers::shorten::shorten_page).post(handlers::shorten::shorten),
+        )
+    } else {
+        Router::new()
+    };
 
     let app = Router::new()
         .route("/", get(handlers::admin::index))
         .route("/health", get(|| async { axum::http::StatusCode::OK }))
+        .route("/health/ready", get(handlers::health::ready))
         .nest("/admin", admin_router)
+        .route("/integrations/slack", post(handlers::slack::slash_command))
+        .route("/api/v1/openapi.json", get(handlers::api::openapi_json))
+        .route("/api/v1/docs", get(handlers::api::docs))
+        .route("/api/v1/me", get(handlers::api::me))
+        .route(
+            "/api/v1/shorten",
+            get(handlers::api::shorten).layer(axum::middleware::map_response(
+                handlers::api::allow_any_origin,
+            )),
+        )
+        .route(
+            "/api/v1/quick-shorten",
+            post(handlers::api::quick_shorten)
+                .options(handlers::api::preflight)
+                .layer(axum::middleware::map_response(
+                    handlers::api::allow_any_origin,
+                )),
+        )
+        .route("/api/v1/links/bulk", post(handlers::api::bulk_shorten))
+        .route("/api/v1/codes/check", get(handlers::api::check_code))
+        .route(
+            "/api/v1/links/recent",
+            get(handlers::api::recent_links)
+                .options(handlers::api::preflight)
+                .layer(axum::middleware::map_response(
+                    handlers::api::allow_any_origin,
+                )),
+        )
+        .route(
+            "/api/v1/hooks",
+            get(handlers::api::list_hooks).post(handlers::api::subscribe),
+        )
+        .route("/api/v1/expand/:code", get(handlers::api::expand))
+        .route("/api/v1/links/:id", patch(handlers::api::update_link))
+        .route("/api/v1/links/:code/check", get(handlers::api::check_link))
+        .route(
+            "/api/v1/links/:id/heatmap",
+            get(handlers::api::heatmap).layer(axum::middleware::from_fn(http_cache::revalidate)),
+        )
+        .route(
+            "/api/v1/stats/top",
+            get(handlers::api::top_links).layer(axum::middleware::from_fn(http_cache::revalidate)),
+        )
+        .route("/api/v1/hooks/:id", delete(handlers::api::unsubscribe))
+        .route(
+            "/api/v1/conversions",
+            post(handlers::api::record_conversion),
+        )
+        .route(
+            "/api/graphql",
+            get(handlers::graphql::graphiql).post(handlers::graphql::execute),
+        )
         .route("/c/:id", get(handlers::redirect::bio_link_click))
+        .route("/p/:file", get(handlers::redirect::pixel))
+        .route("/stats/:code", get(handlers::stats::public_stats))
+        .route(
+            "/digest/unsubscribe",
+            get(handlers::digest::unsubscribe_page).post(handlers::digest::unsubscribe),
+        )
+        .merge(public_router)
         .route("/:code", get(handlers::redirect::redirect))
-        .with_state(state)
-        .layer(TraceLayer::new_for_http());
+        .route("/:code/", get(handlers::redirect::redirect))
+        .route("/:code/preview", get(handlers::redirect::preview))
+        .route("/:code/beacon", post(handlers::redirect::beacon));
+    let app = if state.config.request_timeout_secs > 0 {
+        app.layer(TimeoutLayer::new(Duration::from_secs(
+            state.config.request_timeout_secs,
+        )))
+    } else {
+        app
+    };
+    let app = app
+        .merge(download_router)
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            maintenance::guard,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            error::render,
+        ))
+        .with_state(state.clone())
+        .layer(CompressionLayer::new())
+        .layer(TraceLayer::new_for_http())
+        .layer(axum::middleware::from_fn(error_report::capture_request));
 
     // ── Serve ──────────────────────────────────────────────────────────────
-    let bind_addr = format!(
-        "{}:{}",
-        std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into()),
-        std::env::var("PORT").unwrap_or_else(|_| "3000".into()),
-    );
+    let bind_addr = format!("{}:{}", state.config.host, state.config.port);
 
     let listener = tokio::net::TcpListener::bind(&bind_addr).await?;
     tracing::info!("Listening on http://{}", listener.local_addr()?);
@@ -0,0 +1,88 @@
+use crate::{
+    auth, db_maintenance, error::AppError, handlers::redirect, models::Maintenance, AppState,
+};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::SqlitePool;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
+
+/// Writes still accepted from anyone during maintenance: signing in, so an
+/// admin can end it, and requests that only read or concern the visitor.
+const OPEN_PATHS: &[&str] = &["/admin/login", "/api/graphql", "/digest/unsubscribe"];
+
+/// Reads that create links, refused like any other write.
+const WRITING_GETS: &[&str] = &["/api/v1/shorten"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Whether maintenance mode is on, mirrored from the `maintenance` table so
+/// redirects don't query it.
+#[derive(Default)]
+pub struct MaintenanceMode {
+    current: RwLock<Option<Arc<Maintenance>>>,
+}
+
+impl MaintenanceMode {
+    pub async fn load(db: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let current = db_maintenance::get_maintenance(db).await?;
+        if current.is_some() {
+            tracing::warn!("Maintenance mode is on");
+        }
+        Ok(Self {
+            current: RwLock::new(current.map(Arc::new)),
+        })
+    }
+
+    /// The current maintenance, or `None` when it's off.
+    pub fn get(&self) -> Option<Arc<Maintenance>> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Re-read the state after it changed in the database.
+    pub async fn reload(&self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let current = db_maintenance::get_maintenance(db).await?;
+        *self.current.write().unwrap() = current.map(Arc::new);
+        Ok(())
+    }
+}
+
+// ── Middleware ─────────────────────────────────────────────────────────────
+
+/// Refuse writes from anyone but instance admins while maintenance mode is
+/// on: API requests with a JSON 503, everything else with the maintenance
+/// page.
+pub async fn guard(State(state): State<Arc<AppState>>, request: Request, next: Next) -> Response {
+    let Some(maintenance) = state.maintenance.get() else {
+        return next.run(request).await;
+    };
+
+    let path = request.uri().path();
+    let writes = !request.method().is_safe() || WRITING_GETS.contains(&path);
+    if !writes || OPEN_PATHS.contains(&path) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    if auth::is_instance_admin(&state, &parts).await {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    if parts.uri.path().starts_with("/api/") {
+        AppError::Unavailable(maintenance.message().to_owned()).into_response()
+    } else {
+        let domain = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .and_then(|ConnectInfo(addr)| {
+                redirect::request_host(&state.config, &parts.headers, *addr)
+            })
+            .and_then(|h| state.domains.resolve(h));
+        redirect::maintenance_page(&state, &maintenance, domain.as_ref())
+    }
+}
@@ -1,5 +1,19 @@
 use chrono::NaiveDateTime;
 
+// ── Workspaces ────────────────────────────────────────────────────────────
+
+/// A tenant with its own users, links, and links pages.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// The workspace that existed before workspaces did. Its admins manage the
+/// instance: workspaces, domains, and every workspace's users.
+pub const DEFAULT_WORKSPACE: i64 = 1;
+
 // ── Users ─────────────────────────────────────────────────────────────────
 
 /// A user account.
@@ -15,6 +29,62 @@ pub struct User {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub force_password_change: bool,
+    pub workspace_id: i64,
+    /// Quota overrides; `None` uses the instance default, 0 is unlimited.
+    pub max_links: Option<i64>,
+    pub api_requests_per_hour: Option<i64>,
+}
+
+/// A personal API token (the secret itself is never stored).
+#[allow(dead_code)]
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+/// A REST hook: `target_url` receives a POST whenever `event` happens.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct WebhookSubscription {
+    pub id: i64,
+    pub user_id: i64,
+    pub event: String,
+    pub target_url: String,
+    pub created_at: NaiveDateTime,
+}
+
+// ── Domains ───────────────────────────────────────────────────────────────
+
+/// An additional short domain served by this instance (the primary domain is
+/// `BASE_URL` and has no row here).
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Domain {
+    pub id: i64,
+    /// Lower-cased host name matched against the request `Host` header.
+    pub hostname: String,
+    /// Public base URL for links on this domain, without a trailing slash.
+    pub base_url: String,
+    pub created_at: NaiveDateTime,
+    /// Where `/` redirects on this domain (defaults to `ROOT_REDIRECT_URL`).
+    pub root_redirect_url: Option<String>,
+    /// Where unknown codes redirect; when unset a branded 404 page is shown.
+    pub not_found_url: Option<String>,
+    /// Logo shown on the domain's public pages.
+    pub logo_url: Option<String>,
+    /// Accent colour (`#rrggbb`) for the domain's public pages.
+    pub brand_color: Option<String>,
+    /// Workspace the domain is reserved for; `None` when shared.
+    pub workspace_id: Option<i64>,
+}
+
+impl Domain {
+    /// Whether links in `workspace_id` may be served from this domain.
+    pub fn serves(&self, workspace_id: i64) -> bool {
+        self.workspace_id.is_none() || self.workspace_id == Some(workspace_id)
+    }
 }
 
 // ── Short Links ───────────────────────────────────────────────────────────
@@ -30,9 +100,88 @@ pub struct Link {
     pub created_at: NaiveDateTime,
     pub is_active: bool,
     pub user_id: Option<i64>,
+    /// `None` means the primary domain.
+    pub domain_id: Option<i64>,
+    /// Token for the public stats page; `None` when sharing is disabled.
+    pub stats_token: Option<String>,
+    /// Redirect from a small page that reports client details first, instead
+    /// of with a 302.
+    pub client_redirect: bool,
+    /// Comma-separated country codes allowed to follow the link, e.g. "US,CA".
+    pub allow_countries: Option<String>,
+    /// Comma-separated country codes that may not follow the link.
+    pub deny_countries: Option<String>,
+    pub workspace_id: i64,
+    /// Title shown when the link is unfurled in chat and social apps.
+    pub social_title: Option<String>,
+    pub social_description: Option<String>,
+    pub social_image_url: Option<String>,
+    /// When the link was archived: hidden from the default list, still
+    /// resolving unless `ARCHIVED_LINKS_REDIRECT` is off.
+    pub archived_at: Option<NaiveDateTime>,
+    /// When the link was moved to the trash; purged for good after
+    /// `trash::RETENTION_DAYS`.
+    pub deleted_at: Option<NaiveDateTime>,
+    /// Clicks counted by the service the link was imported from.
+    pub imported_clicks: i64,
+    /// Most clicks let through per minute; `None` for no cap.
+    pub click_limit_per_minute: Option<i64>,
+    /// Over the cap, serve a page that retries by itself instead of a 429.
+    pub click_limit_page: bool,
+    /// Free-form notes for whoever manages the link; never shown to visitors.
+    pub notes: Option<String>,
+    /// Destinations by visitor language, one `<language> <url>` per line;
+    /// see [`crate::language::LanguageRules`].
+    pub language_rules: Option<String>,
+    /// Destinations by local time, one `<days> <start>-<end> <url>` per
+    /// line; see [`crate::schedule::ScheduleRules`].
+    pub schedule_rules: Option<String>,
+    /// Where visitors go while the destination is down.
+    pub fallback_url: Option<String>,
+    /// When the health checker found the destination down; `None` while it
+    /// is up. Only links with a fallback URL are checked.
+    pub destination_down_since: Option<NaiveDateTime>,
+    /// Destinations visitors are split between, one `<weight> <url>` per
+    /// line; see [`crate::variants::Variants`].
+    pub variants: Option<String>,
+    /// Give each visitor the same variant every time.
+    pub sticky_variants: bool,
+}
+
+impl Link {
+    /// Whether any social preview field is set.
+    pub fn has_social_preview(&self) -> bool {
+        self.social_title.is_some()
+            || self.social_description.is_some()
+            || self.social_image_url.is_some()
+    }
+}
+
+/// A link's thumbnail from the screenshot service.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Screenshot {
+    pub content_type: String,
+    pub image: Vec<u8>,
+    pub captured_at: NaiveDateTime,
 }
 
-/// A single click event from the `clicks` table.
+/// `events.event_type` for a short-link redirect.
+pub const EVENT_CLICK: &str = "click";
+/// `events.event_type` for a tracking-pixel load, e.g. an email open.
+pub const EVENT_IMPRESSION: &str = "impression";
+/// `events.event_type` for a view of a link's preview page (`/:code+`).
+pub const EVENT_PREVIEW: &str = "preview";
+/// `events.event_type` for an API client looking the link up with
+/// `/api/v1/expand/:code` rather than following it.
+pub const EVENT_API_EXPAND: &str = "api_expand";
+
+/// `events.source` for a visit through the link's QR code, which adds
+/// `?src=qr` to the short URL. A scan is a source rather than an event type
+/// of its own because it is a click like any other: it redirects, and
+/// belongs in the click totals, breakdowns, and hooks.
+pub const SOURCE_QR: &str = "qr";
+
+/// A single click from the `events` table.
 #[derive(Debug, Clone, sqlx::FromRow)]
 #[allow(dead_code)]
 pub struct Click {
@@ -46,12 +195,56 @@ pub struct Click {
     pub os: Option<String>,
     pub device_type: Option<String>,
     pub country: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. "US".
+    pub country_code: Option<String>,
+    /// Two-letter continent code, e.g. "EU"; see `geo::continent_name`.
+    pub continent: Option<String>,
     pub region: Option<String>,
     pub city: Option<String>,
+    /// Autonomous system number of the visitor's network.
+    pub asn: Option<i64>,
+    pub isp: Option<String>,
+    /// Reported by the browser; only set for client-side redirects.
+    pub screen_width: Option<i64>,
+    pub screen_height: Option<i64>,
+    pub language: Option<String>,
+    pub timezone: Option<String>,
+    /// From one of `INTERNAL_IPS`; left out of analytics by default.
+    pub internal: bool,
+    /// From a hosting or datacenter network, so probably automated.
+    pub datacenter: bool,
+    /// [`SOURCE_QR`] when the visitor scanned the link's QR code.
+    pub source: Option<String>,
+    /// Language of the link's language rule that sent the click, e.g. "de".
+    pub matched_language: Option<String>,
+    /// The link's variant the click was sent to, by URL.
+    pub variant: Option<String>,
+}
+
+/// A conversion reported for a click via `POST /api/v1/conversions`.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Conversion {
+    pub id: i64,
+    pub click_id: String,
+    /// What happened, e.g. `signup` or `purchase`.
+    pub name: String,
+    /// Optional amount, e.g. order value.
+    pub value: Option<f64>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A link's conversions with one name.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ConversionTotal {
+    pub name: String,
+    pub conversions: i64,
+    /// Clicks with at least one conversion of this name.
+    pub clicks: i64,
+    pub value: Option<f64>,
 }
 
 /// A link row joined with its aggregated click count, used on the dashboard.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, sqlx::FromRow)]
 #[allow(dead_code)]
 pub struct LinkWithStats {
     pub id: i64,
@@ -63,6 +256,89 @@ pub struct LinkWithStats {
     pub is_active: bool,
     pub click_count: i64,
     pub user_id: Option<i64>,
+    pub domain_id: Option<i64>,
+    /// Base URL of the link's domain, or `None` for the primary domain.
+    pub domain_base_url: Option<String>,
+    pub archived_at: Option<NaiveDateTime>,
+    /// First and latest click, not counting internal ones; `None` if never
+    /// clicked.
+    pub first_clicked_at: Option<NaiveDateTime>,
+    pub last_clicked_at: Option<NaiveDateTime>,
+}
+
+impl LinkWithStats {
+    /// Full public short URL, using the link's own domain when it has one.
+    pub fn short_url(&self, default_base_url: &str) -> String {
+        let base = self.domain_base_url.as_deref().unwrap_or(default_base_url);
+        format!("{}/{}", base, self.short_code)
+    }
+
+    /// Host (and non-default port) of the destination, for its favicon.
+    pub fn destination_host(&self) -> Option<String> {
+        let url = reqwest::Url::parse(&self.original_url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+        Some(match url.port() {
+            Some(port) => format!("{host}:{port}"),
+            None => host,
+        })
+    }
+}
+
+/// A click-count threshold on a link that alerts once when reached.
+#[derive(Debug, Clone, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct ClickAlert {
+    pub link_id: i64,
+    pub threshold: i64,
+    pub created_at: NaiveDateTime,
+    pub triggered_at: Option<NaiveDateTime>,
+}
+
+/// An extra short code that redirects like its link.
+#[derive(Debug, Clone, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct LinkAlias {
+    pub short_code: String,
+    pub link_id: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// A change made to a link, for the timeline on its page.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LinkActivity {
+    /// One of the `ACTIVITY_*` kinds.
+    pub kind: String,
+    /// Specifics, e.g. which fields were edited.
+    pub detail: Option<String>,
+    /// Who made the change, while their account exists.
+    pub user_email: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// `link_activity.kind` values.
+pub const ACTIVITY_EDITED: &str = "edited";
+pub const ACTIVITY_ACTIVATED: &str = "activated";
+pub const ACTIVITY_DEACTIVATED: &str = "deactivated";
+pub const ACTIVITY_ARCHIVED: &str = "archived";
+pub const ACTIVITY_UNARCHIVED: &str = "unarchived";
+pub const ACTIVITY_TRASHED: &str = "trashed";
+pub const ACTIVITY_RESTORED: &str = "restored";
+/// A redirect setting changed: mode, domain, countries, languages, schedule,
+/// fallback, variants, click limit, social preview, aliases or a merge.
+pub const ACTIVITY_SETTINGS: &str = "settings";
+/// The health checker found the destination down, so visitors now go to the
+/// fallback URL, or found it back up.
+pub const ACTIVITY_DESTINATION_DOWN: &str = "destination_down";
+pub const ACTIVITY_DESTINATION_UP: &str = "destination_up";
+
+/// A link's clicks in the weekly digest period and the week before it.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DigestLink {
+    pub short_code: String,
+    pub title: Option<String>,
+    pub domain_id: Option<i64>,
+    pub clicks: i64,
+    pub prev_clicks: i64,
 }
 
 /// Summary statistics for the analytics page of a single link.
@@ -70,10 +346,54 @@ pub struct LinkWithStats {
 pub struct AnalyticsSummary {
     pub link: Link,
     pub total_clicks: i64,
-    pub unique_ips: i64,
+    pub unique_visitors: i64,
+    /// Whether `unique_visitors` is estimated from visitor sketches.
+    pub unique_visitors_estimated: bool,
+    /// Tracking-pixel loads; not included in `total_clicks` or `clicks`.
+    pub impressions: i64,
+    /// Views of the link's preview page, likewise not counted as clicks.
+    pub previews: i64,
+    /// Lookups through `/api/v1/expand/:code`, likewise not clicks.
+    pub api_expansions: i64,
+    /// Clicks from `INTERNAL_IPS`, whether or not the other figures include them.
+    pub internal_clicks: i64,
+    /// Clicks from datacenter networks, whether or not the other figures
+    /// include them.
+    pub datacenter_clicks: i64,
     pub clicks: Vec<Click>,
 }
 
+/// One link's totals on the comparison page.
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct ComparisonTotals {
+    pub link_id: i64,
+    pub clicks: i64,
+    pub unique_visitors: i64,
+    pub impressions: i64,
+    /// Clicks that led to at least one conversion.
+    pub converted_clicks: i64,
+}
+
+/// Links visitors are expected to click in order, each within
+/// `window_hours` of the one before.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Funnel {
+    pub id: i64,
+    pub workspace_id: i64,
+    pub user_id: Option<i64>,
+    pub name: String,
+    pub window_hours: i64,
+    pub created_at: NaiveDateTime,
+}
+
+/// One step of a funnel, with enough of its link to show it.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct FunnelStep {
+    pub link_id: i64,
+    pub short_code: String,
+    pub title: Option<String>,
+}
+
 // ── Bio Pages ─────────────────────────────────────────────────────────────
 
 /// A bio page record from the `bio_pages` table.
@@ -94,6 +414,7 @@ pub struct BioPage {
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub user_id: Option<i64>,
+    pub workspace_id: i64,
 }
 
 /// A link on a bio page.
@@ -203,3 +524,47 @@ pub struct BioPageAnalytics {
     pub views: Vec<BioPageView>,
     pub link_clicks: Vec<BioLinkClick>,
 }
+
+// ── Maintenance ───────────────────────────────────────────────────────────
+
+/// Maintenance mode, while it is on (see `maintenance`).
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Maintenance {
+    /// [`MAINTENANCE_PAGE`] or [`MAINTENANCE_REDIRECT`].
+    pub mode: String,
+    /// Shown to visitors and refused writers; a default when `None`.
+    pub message: Option<String>,
+    /// Email of the admin who switched it on.
+    pub started_by: Option<String>,
+    pub started_at: NaiveDateTime,
+}
+
+/// Short links serve the maintenance page instead of redirecting.
+pub const MAINTENANCE_PAGE: &str = "page";
+/// Short links keep redirecting, but no clicks are recorded.
+pub const MAINTENANCE_REDIRECT: &str = "redirect";
+
+impl Maintenance {
+    pub fn serves_page(&self) -> bool {
+        self.mode == MAINTENANCE_PAGE
+    }
+
+    pub fn message(&self) -> &str {
+        self.message
+            .as_deref()
+            .unwrap_or("We're doing some maintenance. Please try again shortly.")
+    }
+}
+
+/// A user-agent pattern whose requests are never recorded as visits.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ClickFilter {
+    pub id: i64,
+    /// Matched case-insensitively anywhere in the user agent, or as a regular
+    /// expression when `is_regex`.
+    pub pattern: String,
+    pub is_regex: bool,
+    /// Email of the admin who added it.
+    pub created_by: Option<String>,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,202 @@
+use crate::config::AppConfig;
+use std::{sync::Arc, time::Duration};
+
+/// Attempts per webhook before a notification is dropped.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Upper bound on a server-requested `Retry-After` delay.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+const DEFAULT_LINK_CREATED: &str = "🔗 New short link {short_url} → {url}";
+const DEFAULT_LINK_DELETED: &str = "🗑️ Short link {short_url} ({title}) was deleted";
+const DEFAULT_CLICK_MILESTONE: &str = "🎉 {short_url} ({title}) just reached {clicks} clicks";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Something worth telling the team about.
+#[derive(Debug, Clone, Copy)]
+pub enum Event {
+    LinkCreated,
+    LinkDeleted,
+    ClickMilestone,
+}
+
+/// Values substituted into message templates.
+#[derive(Debug, Clone)]
+pub struct LinkVars {
+    pub code: String,
+    pub short_url: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub clicks: i64,
+}
+
+/// Posts templated messages to Discord / Mattermost / Slack incoming webhooks.
+///
+/// Cheap to clone; a notifier with no webhook URLs does nothing.
+#[derive(Clone)]
+pub struct Notifier {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    urls: Vec<String>,
+    milestones: Vec<i64>,
+    link_created: String,
+    link_deleted: String,
+    click_milestone: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Notifier {
+    pub fn new(config: &AppConfig, client: reqwest::Client) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                urls: config.notify_webhook_urls.clone(),
+                milestones: config.notify_click_milestones.clone(),
+                link_created: config
+                    .notify_template_link_created
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_LINK_CREATED.into()),
+                link_deleted: config
+                    .notify_template_link_deleted
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_LINK_DELETED.into()),
+                click_milestone: config
+                    .notify_template_click_milestone
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_CLICK_MILESTONE.into()),
+            }),
+        }
+    }
+
+    /// Whether any webhook URLs are configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.inner.urls.is_empty()
+    }
+
+    /// Whether `clicks` is one of the configured milestones.
+    pub fn is_milestone(&self, clicks: i64) -> bool {
+        self.is_enabled() && self.inner.milestones.binary_search(&clicks).is_ok()
+    }
+
+    /// Deliver a notification in the background. Never blocks the caller.
+    pub fn send(&self, event: Event, vars: LinkVars) {
+        if !self.is_enabled() {
+            return;
+        }
+        let notifier = self.clone();
+        tokio::spawn(async move { notifier.deliver(event, vars).await });
+    }
+
+    /// Deliver a notification to every webhook, retrying transient failures.
+    /// Failures are logged, not returned.
+    pub async fn deliver(&self, event: Event, vars: LinkVars) {
+        let template = match event {
+            Event::LinkCreated => &self.inner.link_created,
+            Event::LinkDeleted => &self.inner.link_deleted,
+            Event::ClickMilestone => &self.inner.click_milestone,
+        };
+        let message = render(template, &vars);
+
+        for url in &self.inner.urls {
+            self.post_with_retry(url, &message).await;
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl Notifier {
+    async fn post_with_retry(&self, url: &str, message: &str) {
+        // Discord reads `content`; Slack and Mattermost read `text`.
+        let body = if is_discord(url) {
+            serde_json::json!({ "content": message })
+        } else {
+            serde_json::json!({ "text": message })
+        };
+
+        let mut delay = Duration::from_secs(1);
+        for attempt in 1..=MAX_ATTEMPTS {
+            match self.inner.client.post(url).json(&body).send().await {
+                Ok(resp) if resp.status().is_success() => return,
+                Ok(resp) => {
+                    let status = resp.status();
+                    if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                    {
+                        tracing::warn!(
+                            "Webhook {} rejected notification with {}; not retrying",
+                            redact(url),
+                            status
+                        );
+                        return;
+                    }
+                    if let Some(retry_after) = retry_after(&resp) {
+                        delay = retry_after.min(MAX_RETRY_AFTER);
+                    }
+                    tracing::warn!(
+                        "Webhook {} returned {} (attempt {}/{})",
+                        redact(url),
+                        status,
+                        attempt,
+                        MAX_ATTEMPTS
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Webhook {} failed: {} (attempt {}/{})",
+                        redact(url),
+                        e,
+                        attempt,
+                        MAX_ATTEMPTS
+                    );
+                }
+            }
+
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        tracing::error!("Giving up on webhook {} notification", redact(url));
+    }
+}
+
+/// Substitute `{code}`, `{short_url}`, `{url}`, `{title}`, and `{clicks}`.
+/// `{title}` falls back to the short code for untitled links.
+fn render(template: &str, vars: &LinkVars) -> String {
+    template
+        .replace("{code}", &vars.code)
+        .replace("{short_url}", &vars.short_url)
+        .replace("{url}", &vars.url)
+        .replace("{title}", vars.title.as_deref().unwrap_or(&vars.code))
+        .replace("{clicks}", &vars.clicks.to_string())
+}
+
+fn is_discord(url: &str) -> bool {
+    url.contains("://discord.com/") || url.contains("://discordapp.com/")
+}
+
+/// Parse a `Retry-After` header given in seconds.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// Webhook URLs carry their secret in the path; log only the host.
+fn redact(url: &str) -> &str {
+    url.split_once("://")
+        .and_then(|(_, rest)| rest.split('/').next())
+        .unwrap_or("(invalid url)")
+}
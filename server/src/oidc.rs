@@ -0,0 +1,205 @@
+use crate::config::AppConfig;
+use anyhow::{Context, Result};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Path the provider redirects back to after sign-in.
+pub const CALLBACK_PATH: &str = "/admin/login/sso/callback";
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Single sign-on through an OpenID Connect provider, e.g. Google Workspace,
+/// using the authorization code flow.
+///
+/// The provider's endpoints are discovered on first use rather than at
+/// startup, so an unreachable provider never stops the server from booting.
+#[derive(Clone)]
+pub struct Oidc {
+    pub provider_name: String,
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    admin_emails: Vec<String>,
+    admin_domains: Vec<String>,
+    user_emails: Vec<String>,
+    user_domains: Vec<String>,
+    http: reqwest::Client,
+    discovery: Arc<OnceCell<Discovery>>,
+}
+
+/// The parts of `/.well-known/openid-configuration` used here.
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdClaims {
+    email: Option<String>,
+    /// Usually a boolean, but some providers send `"true"` / `"false"`.
+    email_verified: Option<serde_json::Value>,
+    nonce: Option<String>,
+    name: Option<String>,
+}
+
+/// A user the provider has signed in.
+#[derive(Debug)]
+pub struct Identity {
+    /// Lower-cased email address.
+    pub email: String,
+    pub name: Option<String>,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Oidc {
+    /// `None` unless `OIDC_ISSUER`, `OIDC_CLIENT_ID`, and `OIDC_CLIENT_SECRET`
+    /// are all set.
+    pub fn new(config: &AppConfig, http: reqwest::Client) -> Option<Self> {
+        Some(Self {
+            provider_name: config.oidc_provider_name.clone(),
+            issuer: config.oidc_issuer.clone()?,
+            client_id: config.oidc_client_id.clone()?,
+            client_secret: config.oidc_client_secret.clone()?,
+            redirect_uri: format!("{}{CALLBACK_PATH}", config.base_url),
+            admin_emails: config.oidc_admin_emails.clone(),
+            admin_domains: config.oidc_admin_domains.clone(),
+            user_emails: config.oidc_user_emails.clone(),
+            user_domains: config.oidc_user_domains.clone(),
+            http,
+            discovery: Arc::new(OnceCell::new()),
+        })
+    }
+
+    /// URL of the provider's sign-in page. `state` and `nonce` must be
+    /// remembered (in a cookie) and passed back to [`Oidc::exchange`].
+    pub async fn authorize_url(&self, state: &str, nonce: &str) -> Result<String> {
+        let discovery = self.discovery().await?;
+        let url = reqwest::Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.client_id.as_str()),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("scope", "openid email profile"),
+                ("state", state),
+                ("nonce", nonce),
+            ],
+        )
+        .context("Invalid authorization_endpoint in provider discovery document")?;
+        Ok(url.into())
+    }
+
+    /// Trade the authorization `code` from the callback for the signed-in
+    /// user's identity.
+    ///
+    /// The ID token comes straight from the token endpoint over TLS, so its
+    /// issuer, audience, expiry, and nonce are checked but not its signature
+    /// (OpenID Connect Core §3.1.3.7).
+    pub async fn exchange(&self, code: &str, nonce: &str) -> Result<Identity> {
+        let discovery = self.discovery().await?;
+        let tokens: TokenResponse = self
+            .http
+            .post(&discovery.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .context("Token request failed")?
+            .error_for_status()
+            .context("Token endpoint rejected the authorization code")?
+            .json()
+            .await
+            .context("Token response has no id_token")?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.insecure_disable_signature_validation();
+        validation.set_issuer(&[&discovery.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+        let claims = jsonwebtoken::decode::<IdClaims>(
+            &tokens.id_token,
+            &DecodingKey::from_secret(&[]),
+            &validation,
+        )
+        .context("Invalid ID token")?
+        .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            anyhow::bail!("ID token nonce does not match the sign-in request");
+        }
+        let verified = match claims.email_verified {
+            Some(serde_json::Value::Bool(b)) => b,
+            Some(serde_json::Value::String(s)) => s != "false",
+            _ => true,
+        };
+        if !verified {
+            anyhow::bail!("Provider reports the email address as unverified");
+        }
+        let email = claims
+            .email
+            .map(|e| e.trim().to_lowercase())
+            .filter(|e| e.contains('@'))
+            .context("ID token has no email address; is the `email` scope allowed?")?;
+
+        Ok(Identity {
+            email,
+            name: claims.name.filter(|n| !n.trim().is_empty()),
+        })
+    }
+
+    /// The role an email address signs in with, or `None` if it may not
+    /// sign in. Admin entries win over user entries.
+    pub fn role_for(&self, email: &str) -> Option<&'static str> {
+        let domain = email.rsplit_once('@').map_or("", |(_, d)| d);
+        let matches = |emails: &[String], domains: &[String]| {
+            emails.iter().any(|e| e == email) || domains.iter().any(|d| d == domain)
+        };
+        if matches(&self.admin_emails, &self.admin_domains) {
+            Some("admin")
+        } else if matches(&self.user_emails, &self.user_domains) {
+            Some("user")
+        } else {
+            None
+        }
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl Oidc {
+    /// Fetch the discovery document once. Failures are not cached, so the
+    /// next sign-in attempt retries.
+    async fn discovery(&self) -> Result<&Discovery> {
+        self.discovery
+            .get_or_try_init(|| async {
+                let url = format!("{}/.well-known/openid-configuration", self.issuer);
+                self.http
+                    .get(&url)
+                    .send()
+                    .await
+                    .with_context(|| format!("Failed to fetch {url}"))?
+                    .error_for_status()
+                    .with_context(|| format!("Failed to fetch {url}"))?
+                    .json::<Discovery>()
+                    .await
+                    .with_context(|| format!("Invalid discovery document at {url}"))
+            })
+            .await
+    }
+}
@@ -0,0 +1,24 @@
+use crate::{models::SOURCE_QR, AppState};
+use base64::Engine;
+use qrcode::{render::svg, types::QrError, QrCode};
+
+/// What a link's QR code opens: its short URL marked with `?src=qr`, so
+/// scans count as their own source in analytics.
+pub fn url(state: &AppState, domain_id: Option<i64>, short_code: &str) -> String {
+    format!("{}?src={SOURCE_QR}", state.short_url(domain_id, short_code))
+}
+
+/// `url` as a QR code in SVG.
+pub fn svg(url: &str) -> Result<String, QrError> {
+    let code = QrCode::new(url.as_bytes())?;
+    Ok(code.render::<svg::Color>().min_dimensions(240, 240).build())
+}
+
+/// [`svg`] as a `data:` URI, for clients that show it directly.
+pub fn data_uri(url: &str) -> Result<String, QrError> {
+    let svg = svg(url)?;
+    Ok(format!(
+        "data:image/svg+xml;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(svg)
+    ))
+}
@@ -0,0 +1,176 @@
+use crate::{db_users, AppState};
+use chrono::Utc;
+use dashmap::DashMap;
+use std::sync::Arc;
+
+/// Client IPs remembered before counters from earlier hours are dropped.
+const MAX_TRACKED_IPS: usize = 10_000;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// API requests counted per user and per API token over the current clock
+/// hour, for `QUOTA_API_REQUESTS_PER_HOUR` and
+/// `QUOTA_API_KEY_REQUESTS_PER_HOUR`.
+///
+/// Kept in memory: a restart resets the counters, which at worst lets an
+/// integration through early for the rest of the hour.
+#[derive(Clone, Debug, Default)]
+pub struct ApiUsage {
+    users: Arc<DashMap<i64, Window>>,
+    keys: Arc<DashMap<i64, Window>>,
+}
+
+/// Links created on the public `/shorten` page this hour, per client IP
+/// (`PUBLIC_SHORTEN_PER_HOUR`). In memory, like [`ApiUsage`].
+#[derive(Clone, Debug, Default)]
+pub struct ClientUsage {
+    ips: Arc<DashMap<String, Window>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Window {
+    /// Hours since the Unix epoch.
+    hour: i64,
+    requests: u64,
+}
+
+/// Why an API request was turned away.
+#[derive(Debug, Clone, Copy)]
+pub struct LimitReached {
+    /// `true` when the token's own limit was hit rather than its owner's.
+    pub per_key: bool,
+    pub limit: u64,
+    /// Seconds until the counters reset.
+    pub retry_after: u64,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl ApiUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one request by `user_id` with token `key_id`, unless either is
+    /// already at its limit (0 = unlimited). Refused requests aren't counted.
+    pub fn record(
+        &self,
+        user_id: i64,
+        user_limit: u64,
+        key_id: i64,
+        key_limit: u64,
+    ) -> Result<(), LimitReached> {
+        let now = Utc::now().timestamp();
+        let hour = now.div_euclid(3600);
+        let retry_after = (3600 - now.rem_euclid(3600)) as u64;
+
+        let mut user = self.users.entry(user_id).or_default();
+        let mut key = self.keys.entry(key_id).or_default();
+        for window in [&mut *user, &mut *key] {
+            if window.hour != hour {
+                *window = Window { hour, requests: 0 };
+            }
+        }
+        if user_limit > 0 && user.requests >= user_limit {
+            return Err(LimitReached {
+                per_key: false,
+                limit: user_limit,
+                retry_after,
+            });
+        }
+        if key_limit > 0 && key.requests >= key_limit {
+            return Err(LimitReached {
+                per_key: true,
+                limit: key_limit,
+                retry_after,
+            });
+        }
+        user.requests += 1;
+        key.requests += 1;
+        Ok(())
+    }
+
+    /// API requests made by `user_id` this hour, across all their tokens.
+    pub fn user_requests(&self, user_id: i64) -> u64 {
+        Self::current(&self.users, user_id)
+    }
+
+    /// API requests made with token `key_id` this hour.
+    pub fn key_requests(&self, key_id: i64) -> u64 {
+        Self::current(&self.keys, key_id)
+    }
+}
+
+impl ClientUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one request from `ip` unless it is already at `limit`
+    /// (0 = unlimited). Returns the seconds until the counter resets when
+    /// refused.
+    pub fn record(&self, ip: &str, limit: u64) -> Result<(), u64> {
+        let now = Utc::now().timestamp();
+        let hour = now.div_euclid(3600);
+        if self.ips.len() > MAX_TRACKED_IPS {
+            self.ips.retain(|_, w| w.hour == hour);
+        }
+
+        let mut window = self.ips.entry(ip.to_owned()).or_default();
+        if window.hour != hour {
+            *window = Window { hour, requests: 0 };
+        }
+        if limit > 0 && window.requests >= limit {
+            return Err((3600 - now.rem_euclid(3600)) as u64);
+        }
+        window.requests += 1;
+        Ok(())
+    }
+}
+
+/// The effective API request limit for a user: their own override, else the
+/// instance default. 0 = unlimited.
+pub fn api_limit(state: &AppState, user_override: Option<i64>) -> u64 {
+    match user_override {
+        Some(n) => n.max(0) as u64,
+        None => state.tunables().quota_api_requests_per_hour,
+    }
+}
+
+/// The effective link limit for a user, as for [`api_limit`].
+pub fn link_limit(state: &AppState, user_override: Option<i64>) -> u64 {
+    match user_override {
+        Some(n) => n.max(0) as u64,
+        None => state.tunables().quota_max_links,
+    }
+}
+
+/// `Some(limit)` when `user_id` already owns as many links as they may.
+pub async fn links_exhausted(state: &AppState, user_id: i64) -> Result<Option<u64>, sqlx::Error> {
+    let Some((links, user_override)) = db_users::link_quota(&state.db, user_id).await? else {
+        return Ok(None);
+    };
+    let limit = link_limit(state, user_override);
+    Ok((limit > 0 && links as u64 >= limit).then_some(limit))
+}
+
+/// How many more links `user_id` may create and their limit, or `None` when
+/// they have no limit.
+pub async fn links_left(state: &AppState, user_id: i64) -> Result<Option<(u64, u64)>, sqlx::Error> {
+    let Some((links, user_override)) = db_users::link_quota(&state.db, user_id).await? else {
+        return Ok(None);
+    };
+    let limit = link_limit(state, user_override);
+    Ok((limit > 0).then(|| (limit.saturating_sub(links.max(0) as u64), limit)))
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl ApiUsage {
+    fn current(map: &DashMap<i64, Window>, id: i64) -> u64 {
+        let hour = Utc::now().timestamp().div_euclid(3600);
+        map.get(&id)
+            .filter(|w| w.hour == hour)
+            .map_or(0, |w| w.requests)
+    }
+}
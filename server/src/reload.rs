@@ -0,0 +1,155 @@
+use crate::{
+    config::{AppConfig, Tunables, DEFAULT_LOG_FILTER},
+    error_report, AppState,
+};
+use serde::Serialize;
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+use tracing_subscriber::{
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+/// Swaps the log filter when `RUST_LOG` is reloaded.
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Reloads the configuration on SIGHUP or `POST /admin/reload-config`, and
+/// holds the [`Tunables`] that takes effect for.
+pub struct Reloader {
+    /// Config file the server was started with, if any.
+    path: Option<PathBuf>,
+    /// The configuration at startup and as last loaded, to tell which
+    /// changes need a restart and which were just applied.
+    startup: AppConfig,
+    latest: Mutex<AppConfig>,
+    tunables: RwLock<Arc<Tunables>>,
+}
+
+/// What a reload changed, by environment variable name.
+#[derive(Debug, Serialize)]
+pub struct Reloaded {
+    /// Settings that changed and are now in effect.
+    pub applied: Vec<&'static str>,
+    /// Settings that differ from startup but only take effect on a restart.
+    pub restart_required: Vec<&'static str>,
+}
+
+impl Reloader {
+    pub fn new(config: &AppConfig, path: Option<PathBuf>) -> Self {
+        Self {
+            path,
+            startup: config.clone(),
+            latest: Mutex::new(config.clone()),
+            tunables: RwLock::new(Arc::new(config.tunables())),
+        }
+    }
+
+    /// The tunable settings currently in effect.
+    pub fn tunables(&self) -> Arc<Tunables> {
+        self.tunables.read().unwrap().clone()
+    }
+
+    /// Read the environment, `.env`, and the config file again and apply
+    /// the tunable settings. Nothing changes if the new configuration is
+    /// invalid.
+    pub fn reload(&self) -> anyhow::Result<Reloaded> {
+        let config = AppConfig::reload(self.path.as_deref())?;
+        let tunables = config.tunables();
+        if tunables.log_filter != self.tunables().log_filter {
+            set_log_filter(&tunables.log_filter)?;
+        }
+
+        let mut latest = self.latest.lock().unwrap();
+        let reloaded = Reloaded {
+            applied: latest
+                .changes(&config)
+                .into_iter()
+                .filter(|name| Tunables::NAMES.contains(name))
+                .collect(),
+            restart_required: self
+                .startup
+                .changes(&config)
+                .into_iter()
+                .filter(|name| !Tunables::NAMES.contains(name))
+                .collect(),
+        };
+        *self.tunables.write().unwrap() = Arc::new(tunables);
+        *latest = config;
+        Ok(reloaded)
+    }
+}
+
+// ── Logging ────────────────────────────────────────────────────────────────
+
+/// Install the global subscriber, logging to stderr through a filter that a
+/// reload can replace. Starts from `RUST_LOG` in the environment; `main`
+/// applies the configured filter once the config is loaded.
+pub fn init_logging() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .with(error_report::ReportLayer)
+        .init();
+    let _ = LOG_FILTER.set(handle);
+}
+
+/// Replace the log filter with `directives` (already validated by the
+/// config).
+pub fn set_log_filter(directives: &str) -> anyhow::Result<()> {
+    if let Some(handle) = LOG_FILTER.get() {
+        handle.reload(EnvFilter::try_new(directives)?)?;
+    }
+    Ok(())
+}
+
+// ── Signal ─────────────────────────────────────────────────────────────────
+
+/// Reload the configuration whenever the process receives SIGHUP.
+#[cfg(unix)]
+pub async fn run(state: Arc<AppState>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::error!("Failed to listen for SIGHUP: {:?}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        log(&state.reloader.reload());
+    }
+}
+
+/// Log the outcome of a reload.
+pub fn log(result: &anyhow::Result<Reloaded>) {
+    match result {
+        Ok(reloaded) => {
+            tracing::info!(
+                "Configuration reloaded; changed: {}",
+                list(&reloaded.applied)
+            );
+            if !reloaded.restart_required.is_empty() {
+                tracing::warn!(
+                    "Restart Linkly to apply: {}",
+                    reloaded.restart_required.join(", ")
+                );
+            }
+        }
+        Err(e) => tracing::error!("Configuration not reloaded: {:#}", e),
+    }
+}
+
+fn list(names: &[&str]) -> String {
+    if names.is_empty() {
+        "nothing".into()
+    } else {
+        names.join(", ")
+    }
+}
@@ -0,0 +1,38 @@
+use crate::{db_rollups, unique_visitors};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// How often events past their retention are looked for.
+const PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Fold events older than `days` into daily rollups and delete them, a
+/// whole UTC day at a time, once at startup and then every hour. Clicks
+/// with conversions are kept. Finished days are sketched first, so the
+/// sketches cover every day up to the last one pruned. Spawned from `main`
+/// unless `CLICK_RETENTION_DAYS` is 0.
+pub async fn run(pool: SqlitePool, days: u64, secret: String) {
+    loop {
+        unique_visitors::sketch_finished_days(&pool, &secret).await;
+        let before = Utc::now().date_naive() - Duration::days(days as i64);
+        loop {
+            let day = match db_rollups::oldest_prunable_day(&pool, before).await {
+                Ok(Some(day)) => day,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Failed to find events to prune: {:?}", e);
+                    break;
+                }
+            };
+            match db_rollups::roll_up_day(&pool, day).await {
+                Ok(n) => tracing::info!("Rolled up and pruned {} event(s) from {}", n, day),
+                Err(e) => {
+                    tracing::error!("Failed to roll up events from {}: {:?}", day, e);
+                    break;
+                }
+            }
+        }
+        tokio::time::sleep(PRUNE_INTERVAL).await;
+    }
+}
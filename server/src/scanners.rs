@@ -0,0 +1,99 @@
+use dashmap::DashMap;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Window over which a client's unknown codes are counted.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Clients remembered before stale counters and expired bans are dropped.
+const MAX_TRACKED_IPS: usize = 10_000;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Clients that asked for many unknown short codes in a short time, which
+/// only scanners do. Banned clients are answered from the link cache alone:
+/// anything not cached is a 404 without touching the database, and their
+/// clicks aren't recorded. See `SCANNER_MISS_LIMIT`.
+///
+/// Kept in memory: a restart lifts every ban.
+#[derive(Clone, Debug)]
+pub struct Scanners {
+    misses: Arc<DashMap<String, Misses>>,
+    banned: Arc<DashMap<String, Instant>>,
+    limit: u64,
+    ban: Duration,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Misses {
+    since: Instant,
+    count: u64,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Scanners {
+    /// Ban a client for `ban` once it misses `limit` codes within a minute
+    /// (0 = never).
+    pub fn new(limit: u64, ban: Duration) -> Self {
+        Self {
+            misses: Arc::new(DashMap::new()),
+            banned: Arc::new(DashMap::new()),
+            limit,
+            ban,
+        }
+    }
+
+    /// Whether `ip` is banned right now.
+    pub fn is_banned(&self, ip: &str) -> bool {
+        self.banned
+            .get(ip)
+            .is_some_and(|until| *until > Instant::now())
+    }
+
+    /// Count a request from `ip` for a code that doesn't exist, banning it
+    /// if that makes too many.
+    pub fn record_miss(&self, ip: &str) {
+        if self.limit == 0 {
+            return;
+        }
+        let now = Instant::now();
+        if self.misses.len() > MAX_TRACKED_IPS {
+            self.misses.retain(|_, m| now - m.since < WINDOW);
+        }
+        if self.banned.len() > MAX_TRACKED_IPS {
+            self.banned.retain(|_, until| *until > now);
+        }
+
+        let mut misses = self.misses.entry(ip.to_owned()).or_insert(Misses {
+            since: now,
+            count: 0,
+        });
+        if now - misses.since >= WINDOW {
+            *misses = Misses {
+                since: now,
+                count: 0,
+            };
+        }
+        misses.count += 1;
+        if misses.count >= self.limit {
+            drop(misses);
+            self.misses.remove(ip);
+            self.banned.insert(ip.to_owned(), now + self.ban);
+            tracing::warn!(
+                "Banning {} for {} minute(s): {} unknown short codes within a minute",
+                ip,
+                self.ban.as_secs() / 60,
+                self.limit
+            );
+        }
+    }
+
+    /// Clients banned right now.
+    pub fn banned_count(&self) -> usize {
+        let now = Instant::now();
+        self.banned.iter().filter(|e| *e.value() > now).count()
+    }
+}
@@ -0,0 +1,166 @@
+use chrono::{Datelike, Timelike};
+
+/// Most rules a link may have.
+pub const MAX_RULES: usize = 50;
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Minutes in a day; a window may end at 24:00.
+const DAY_MINUTES: u16 = 24 * 60;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// A link's destinations by local time, in the order they were written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScheduleRules {
+    pub rules: Vec<ScheduleRule>,
+}
+
+/// Visitors arriving on `days` between `start` and `end` are sent to `url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleRule {
+    /// The days and times as written, lower-cased, e.g. "mon-fri 9-17".
+    pub when: String,
+    /// Indexed from Monday.
+    days: [bool; 7],
+    /// Minutes after midnight. A window whose end is at or before its start
+    /// runs past midnight into the next day.
+    start: u16,
+    end: u16,
+    pub url: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl ScheduleRules {
+    /// Parse one rule per line: days, a time window and a URL, e.g.
+    /// `mon-fri 09:00-17:00 https://example.com/book`. Days are a
+    /// comma-separated list of days or ranges (`sat,sun`, `fri-mon`), or
+    /// `daily`. Blank lines are skipped. URLs are taken as written; the
+    /// caller validates them.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let parts: Vec<_> = line.split_whitespace().collect();
+            let [days, window, url] = parts[..] else {
+                return Err(format!(
+                    "'{line}' needs days, hours and a URL, e.g. 'mon-fri 9-17 https://example.com/'"
+                ));
+            };
+            let days = days.to_ascii_lowercase();
+            let day_set = parse_days(&days)?;
+            let (start, end) = parse_window(window)?;
+            rules.push(ScheduleRule {
+                when: format!("{days} {window}"),
+                days: day_set,
+                start,
+                end,
+                url: url.to_owned(),
+            });
+        }
+        if rules.len() > MAX_RULES {
+            return Err(format!(
+                "A link may have at most {MAX_RULES} schedule rules"
+            ));
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The rules as stored in `links.schedule_rules`.
+    pub fn to_text(&self) -> String {
+        let lines: Vec<_> = self
+            .rules
+            .iter()
+            .map(|r| format!("{} {}", r.when, r.url))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// The first rule whose window `now` falls in.
+    pub fn pick<Tz: chrono::TimeZone>(&self, now: &chrono::DateTime<Tz>) -> Option<&ScheduleRule> {
+        let day = now.weekday().num_days_from_monday() as usize;
+        // Whole minutes, so 17:00:30 is past a window ending at 17:00
+        let minute = (now.hour() * 60 + now.minute()) as u16;
+        self.rules.iter().find(|r| r.covers(day, minute))
+    }
+}
+
+impl ScheduleRule {
+    fn covers(&self, day: usize, minute: u16) -> bool {
+        if self.start < self.end {
+            return self.days[day] && (self.start..self.end).contains(&minute);
+        }
+        // Past midnight: the evening belongs to the listed day, the small
+        // hours to the day after it.
+        let yesterday = (day + 6) % 7;
+        (self.days[day] && minute >= self.start) || (self.days[yesterday] && minute < self.end)
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    if matches!(days, "daily" | "*") {
+        return Ok([true; 7]);
+    }
+    let mut set = [false; 7];
+    for part in days.split(',').filter(|p| !p.is_empty()) {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (day_index(first)?, day_index(last)?),
+            None => (day_index(part)?, day_index(part)?),
+        };
+        // A range may wrap around the weekend, e.g. fri-mon
+        let mut day = first;
+        loop {
+            set[day] = true;
+            if day == last {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    if !set.contains(&true) {
+        return Err(format!("'{days}' names no days"));
+    }
+    Ok(set)
+}
+
+/// Monday-first index of a day name, e.g. "tue" or "tuesday".
+fn day_index(name: &str) -> Result<usize, String> {
+    name.parse::<chrono::Weekday>()
+        .map(|day| day.num_days_from_monday() as usize)
+        .map_err(|_| {
+            format!(
+                "'{name}' is not a day of the week (use {})",
+                DAY_NAMES.join(", ")
+            )
+        })
+}
+
+/// A window like `9-17` or `09:00-17:30`, as minutes after midnight.
+fn parse_window(window: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("'{window}' is not a time window, e.g. 9-17 or 08:30-12:00");
+    let (start, end) = window.split_once('-').ok_or_else(invalid)?;
+    let start = parse_time(start).ok_or_else(invalid)?;
+    let end = parse_time(end).ok_or_else(invalid)?;
+    if start == end || start == DAY_MINUTES {
+        return Err(invalid());
+    }
+    Ok((start, end % DAY_MINUTES))
+}
+
+/// `9`, `09`, `9:30` or `09:30`, up to `24:00`, as minutes after midnight.
+fn parse_time(time: &str) -> Option<u16> {
+    let (hour, minute) = time.split_once(':').unwrap_or((time, "0"));
+    let valid = |s: &str| (1..=2).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_digit());
+    if !valid(hour) || !valid(minute) {
+        return None;
+    }
+    let (hour, minute): (u16, u16) = (hour.parse().ok()?, minute.parse().ok()?);
+    let total = hour * 60 + minute;
+    (minute < 60 && total <= DAY_MINUTES).then_some(total)
+}
@@ -0,0 +1,139 @@
+use crate::{config::AppConfig, db_screenshots, models::Link, url_macros};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::header;
+use sqlx::SqlitePool;
+use std::{sync::Arc, time::Duration};
+
+/// Rendering a page takes a while, so captures get longer than the shared
+/// client's default timeout.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Largest thumbnail stored; anything bigger is refused.
+const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Captures link thumbnails through the `SCREENSHOT_URL` service.
+///
+/// Cheap to clone; without a service configured it does nothing.
+#[derive(Clone)]
+pub struct Screenshots {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    endpoint: String,
+    client: reqwest::Client,
+    db: SqlitePool,
+}
+
+/// Why a capture failed.
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("Screenshots are not configured.")]
+    Disabled,
+    #[error("The screenshot service could not be reached.")]
+    Request(#[from] reqwest::Error),
+    #[error("The screenshot service didn't return an image.")]
+    NotAnImage,
+    #[error("The screenshot is larger than {} MB.", MAX_IMAGE_BYTES / 1024 / 1024)]
+    TooLarge,
+    #[error(transparent)]
+    Db(#[from] sqlx::Error),
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Screenshots {
+    pub fn new(config: &AppConfig, client: reqwest::Client, db: SqlitePool) -> Self {
+        Self {
+            inner: config.screenshot_url.clone().map(|endpoint| {
+                Arc::new(Inner {
+                    endpoint,
+                    client,
+                    db,
+                })
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Capture a link's thumbnail in the background. Failures are logged.
+    pub fn capture(&self, link: &Link) {
+        if !self.is_enabled() {
+            return;
+        }
+        let screenshots = self.clone();
+        let (id, url) = (link.id, destination(link));
+        tokio::spawn(async move {
+            if let Err(e) = screenshots.capture_url(id, &url).await {
+                tracing::warn!("Screenshot of link {} failed: {:?}", id, e);
+            }
+        });
+    }
+
+    /// Capture a link's thumbnail now, replacing any earlier one.
+    pub async fn capture_now(&self, link: &Link) -> Result<(), CaptureError> {
+        self.capture_url(link.id, &destination(link)).await
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl Screenshots {
+    async fn capture_url(&self, link_id: i64, url: &str) -> Result<(), CaptureError> {
+        let inner = self.inner.as_ref().ok_or(CaptureError::Disabled)?;
+        let request_url = inner.endpoint.replace(
+            "{url}",
+            &utf8_percent_encode(url, NON_ALPHANUMERIC).to_string(),
+        );
+
+        let resp = inner
+            .client
+            .get(request_url)
+            .timeout(CAPTURE_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?;
+        let content_type = resp
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| v.starts_with("image/"))
+            .ok_or(CaptureError::NotAnImage)?;
+        if resp
+            .content_length()
+            .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+        {
+            return Err(CaptureError::TooLarge);
+        }
+        let image = resp.bytes().await?;
+        if image.len() > MAX_IMAGE_BYTES {
+            return Err(CaptureError::TooLarge);
+        }
+
+        db_screenshots::save_screenshot(&inner.db, link_id, &content_type, &image).await?;
+        tracing::info!("Captured a screenshot of link {}", link_id);
+        Ok(())
+    }
+}
+
+/// The page to capture: the destination with its placeholders filled in as
+/// for a visitor nothing is known about.
+fn destination(link: &Link) -> String {
+    url_macros::expand(
+        &link.original_url,
+        &url_macros::Vars {
+            code: &link.short_code,
+            click_id: "",
+            country: None,
+            device: None,
+            utm_source: None,
+        },
+    )
+}
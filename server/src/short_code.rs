@@ -0,0 +1,210 @@
+use crate::config::AppConfig;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+use unicode_properties::UnicodeEmoji;
+
+/// Top-level paths routed ahead of short codes (see [`is_reserved`]).
+const RESERVED: &[&str] = &["admin", "health"];
+
+/// Words generated codes never contain while `SHORT_CODE_WORD_FILTER` is on.
+const OFFENSIVE_WORDS: &[&str] = &[
+    "anal", "anus", "arse", "ass", "bitch", "bollock", "boner", "boob", "bugger", "butt", "chink",
+    "clit", "cock", "coon", "crap", "cum", "cunt", "damn", "dick", "dildo", "dyke", "fag", "fuck",
+    "fuk", "gook", "hitler", "jizz", "kike", "kkk", "nazi", "negro", "nigga", "nigger", "orgasm",
+    "penis", "piss", "poop", "porn", "prick", "pube", "pussy", "rape", "retard", "scrotum", "sex",
+    "shit", "slut", "spic", "tit", "twat", "vagina", "wank", "whore", "xxx",
+];
+
+/// Generated codes tried before settling for one with a blocked word, so a
+/// tiny alphabet with a long block list can't spin forever.
+const MAX_ATTEMPTS: usize = 100;
+
+/// A random code of `len` characters from `SHORT_CODE_ALPHABET`, redrawn
+/// while it contains a blocked word (see [`is_blocked`]).
+pub fn random(config: &AppConfig, len: usize) -> String {
+    let alphabet = &config.short_code_alphabet;
+    let mut rng = rand::thread_rng();
+    let mut draw = || -> String {
+        (0..len)
+            .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+            .collect()
+    };
+    for _ in 1..MAX_ATTEMPTS {
+        let code = draw();
+        if !is_blocked(config, &code) {
+            return code;
+        }
+    }
+    tracing::warn!(
+        "No code without a blocked word after {MAX_ATTEMPTS} tries; check SHORT_CODE_ALPHABET and SHORT_CODE_BLOCKED_WORDS"
+    );
+    draw()
+}
+
+/// Most numbers scrambled together as codes of one length; past 2^64 the
+/// counter would overflow anyway.
+const SEQUENCE_SPACE_MAX: u128 = 1 << 64;
+
+/// The code for number `n` of the sequential code counter.
+///
+/// Codes are `SHORT_CODE_LENGTH` long until every code of that length has
+/// been handed out, then one character longer, and so on. Within a length,
+/// `n` goes through an affine map and each character through a permutation
+/// of `SHORT_CODE_ALPHABET`, all keyed by `JWT_SECRET`, so different numbers
+/// always give different codes but consecutive ones look unrelated.
+pub fn sequential(config: &AppConfig, mut n: u64) -> String {
+    let alphabet = &config.short_code_alphabet;
+    let base = alphabet.len() as u128;
+    let mut len = config.short_code_length;
+    let space = loop {
+        let space = base
+            .checked_pow(len as u32)
+            .map_or(SEQUENCE_SPACE_MAX, |space| space.min(SEQUENCE_SPACE_MAX));
+        if u128::from(n) < space {
+            break space;
+        }
+        n -= space as u64;
+        len += 1;
+    };
+
+    let mut value = affine(n.into(), sequence_key(config, len, 0), space);
+    (1..=len)
+        .map(|position| {
+            let digit = affine(value % base, sequence_key(config, len, position), base);
+            value /= base;
+            alphabet[digit as usize]
+        })
+        .collect()
+}
+
+/// Secret multiplier and offset for one step of writing `len`-character
+/// sequential codes: the number itself (`part` 0) or one of its characters.
+fn sequence_key(config: &AppConfig, len: usize, part: usize) -> (u128, u128) {
+    let mut mac = Hmac::<Sha256>::new_from_slice(config.jwt_secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(format!("short-code-sequence:{len}:{part}").as_bytes());
+    let bytes = mac.finalize().into_bytes();
+    let word = |at: usize| {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(&bytes[at..at + 8]);
+        u128::from(u64::from_le_bytes(buf))
+    };
+    (word(0), word(8))
+}
+
+/// `value * a + c` modulo `modulus` (at most 2^64), with `a` moved up to the
+/// next number coprime to `modulus` so that no two values map to the same one.
+fn affine(value: u128, (a, c): (u128, u128), modulus: u128) -> u128 {
+    let mut a = a % modulus;
+    while gcd(a, modulus) != 1 {
+        a = (a + 1) % modulus;
+    }
+    (value * a % modulus + c % modulus) % modulus
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Whether `code` contains a word from the built-in list (if
+/// `SHORT_CODE_WORD_FILTER`) or `SHORT_CODE_BLOCKED_WORDS`, ignoring case
+/// and hyphens and reading digits as the letters they look like.
+pub fn is_blocked(config: &AppConfig, code: &str) -> bool {
+    let plain: String = code
+        .chars()
+        .filter(|c| *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect();
+    // `1` passes for both `i` and `l`, so read the code both ways
+    let readings = [plain.clone(), unleet(&plain, 'i'), unleet(&plain, 'l')];
+    let built_in: &[&str] = if config.short_code_word_filter {
+        OFFENSIVE_WORDS
+    } else {
+        &[]
+    };
+    let mut words = built_in
+        .iter()
+        .copied()
+        .chain(config.short_code_blocked_words.iter().map(String::as_str));
+    words.any(|word| readings.iter().any(|reading| reading.contains(word)))
+}
+
+/// `code` with digits swapped for the letters they stand in for, `1` as `one`.
+fn unleet(code: &str, one: char) -> String {
+    code.chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' => one,
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            '9' => 'g',
+            c => c,
+        })
+        .collect()
+}
+
+/// A custom short code, alias, or links page slug as it should be stored,
+/// or `None` if it may not be used: letters, digits, and hyphens, where
+/// letters and digits are ASCII unless `unicode` (`UNICODE_SHORT_CODES`),
+/// or if `emoji` (`EMOJI_SHORT_CODES`) nothing but emoji (see [`is_emoji`]).
+///
+/// Unicode codes are stored in the form [`normalize`] looks them up in.
+pub fn parse(code: &str, unicode: bool, emoji: bool) -> Option<String> {
+    let code = normalize(code.trim());
+    let allowed =
+        |c: char| c == '-' || c.is_ascii_alphanumeric() || (unicode && c.is_alphanumeric());
+    let valid = !code.is_empty() && (code.chars().all(allowed) || (emoji && is_emoji(&code)));
+    valid.then(|| code.into_owned())
+}
+
+/// Whether `code` is made of emoji alone, including the joiners, skin tones,
+/// and flag parts that build them up. Emoji can't be mixed with letters,
+/// digits, or hyphens, and keycaps (`1️⃣`) aren't allowed since they start
+/// with a plain digit.
+pub fn is_emoji(code: &str) -> bool {
+    code.chars()
+        .all(|c| !c.is_ascii() && c.is_emoji_char_or_emoji_component())
+        && code.chars().any(|c| c.is_emoji_char())
+}
+
+/// Whether `code` names a page Linkly serves at the top level (`/admin`,
+/// `/health`, and `/shorten` when `public_shorten`), where a link or links
+/// page could never be reached.
+pub fn is_reserved(code: &str, public_shorten: bool) -> bool {
+    RESERVED.contains(&code) || (public_shorten && code == "shorten")
+}
+
+/// HTML `pattern` for code and slug inputs, matching what [`parse`] accepts
+/// (give or take which emoji).
+pub fn input_pattern(unicode: bool, emoji: bool) -> &'static str {
+    match (unicode, emoji) {
+        (false, false) => r"[A-Za-z0-9\-]+",
+        (true, false) => r"[\p{L}\p{N}\-]+",
+        (false, true) => r"[A-Za-z0-9\-]+|[\p{Emoji}\p{Emoji_Component}]+",
+        (true, true) => r"[\p{L}\p{N}\-]+|[\p{Emoji}\p{Emoji_Component}]+",
+    }
+}
+
+/// Canonical form of a requested code, so `é` typed as one character or as
+/// `e` plus a combining accent finds the same link. Emoji presentation
+/// selectors are dropped, so `❤️` and `❤` do too; no other code has them.
+pub fn normalize(code: &str) -> Cow<'_, str> {
+    if code.is_ascii() {
+        Cow::Borrowed(code)
+    } else {
+        Cow::Owned(
+            code.nfc()
+                .filter(|c| !matches!(c, '\u{FE0E}' | '\u{FE0F}'))
+                .collect(),
+        )
+    }
+}
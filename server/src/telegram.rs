@@ -0,0 +1,250 @@
+use crate::{
+    db, db_users,
+    links::{self, CreateError, Created, DomainChoice, NewLink, Owner},
+    AppState,
+};
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+
+/// Seconds Telegram holds a `getUpdates` request open waiting for messages.
+const POLL_TIMEOUT_SECS: u64 = 30;
+
+/// Pause after a failed poll before trying again.
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+const HELP: &str = "Send me a URL (optionally followed by a custom code) and I'll shorten it.\n\
+                    /stats <code> — click counts for a short link";
+
+// ── Telegram Bot API shapes ────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    result: Option<T>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+/// Long-poll the Telegram Bot API forever, answering messages from the
+/// allowed chats. Spawned from `main` when `TELEGRAM_BOT_TOKEN` is set.
+pub async fn run(state: Arc<AppState>, token: String) {
+    let client = &state.http;
+    let api = format!("https://api.telegram.org/bot{token}");
+
+    if state.config.telegram_allowed_chat_ids.is_empty() {
+        tracing::warn!(
+            "Telegram bot started with no TELEGRAM_ALLOWED_CHAT_IDS; every message will be ignored"
+        );
+    }
+    tracing::info!("Telegram bot polling for messages");
+
+    let mut offset = 0_i64;
+    loop {
+        let updates = match get_updates(client, &api, offset).await {
+            Ok(u) => u,
+            Err(e) => {
+                tracing::warn!("Telegram poll failed: {}", e);
+                tokio::time::sleep(ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+
+        for update in updates {
+            offset = offset.max(update.update_id + 1);
+
+            let Some(Message {
+                chat,
+                text: Some(text),
+            }) = update.message
+            else {
+                continue;
+            };
+
+            if !state.config.telegram_allowed_chat_ids.contains(&chat.id) {
+                tracing::warn!(
+                    "Ignoring Telegram message from unauthorised chat {}",
+                    chat.id
+                );
+                continue;
+            }
+
+            let reply = handle_message(&state, text.trim()).await;
+            if let Err(e) = send_message(client, &api, chat.id, &reply).await {
+                tracing::warn!("Telegram reply to chat {} failed: {}", chat.id, e);
+            }
+        }
+    }
+}
+
+// ── Commands ───────────────────────────────────────────────────────────────
+
+async fn handle_message(state: &AppState, text: &str) -> String {
+    let mut args = text.split_whitespace();
+    let Some(first) = args.next() else {
+        return HELP.into();
+    };
+
+    // Commands may carry a bot mention in groups, e.g. `/stats@linkly_bot`.
+    match first.split('@').next().unwrap_or(first) {
+        "/start" | "/help" => HELP.into(),
+        "/stats" => match args.next() {
+            Some(code) => stats(state, code).await,
+            None => "Usage: /stats <code>".into(),
+        },
+        _ => shorten(state, first, args.next()).await,
+    }
+}
+
+/// Create a short link and return the message to send back.
+async fn shorten(state: &AppState, url: &str, custom_code: Option<&str>) -> String {
+    if let Some(maintenance) = state.maintenance.get() {
+        return maintenance.message().to_owned();
+    }
+    let owner = match &state.config.telegram_link_owner {
+        Some(email) => db_users::get_user_by_email(&state.db, &email.to_lowercase()).await,
+        None => db_users::get_first_admin(&state.db).await,
+    };
+    let owner = match owner {
+        Ok(Some(u)) => u,
+        Ok(None) => {
+            tracing::error!("Telegram: configured link owner does not exist");
+            return "Linkly has no user to own this link. Ask an admin to check TELEGRAM_LINK_OWNER."
+                .into();
+        }
+        Err(e) => {
+            tracing::error!("Telegram: failed to load link owner: {:?}", e);
+            return "Something went wrong. Please try again.".into();
+        }
+    };
+
+    let owner = Owner {
+        user_id: owner.id,
+        workspace_id: owner.workspace_id,
+    };
+    let request = NewLink {
+        url,
+        code: custom_code,
+        title: None,
+        description: None,
+        domain: DomainChoice::Default,
+    };
+    match links::create(state, owner, request).await {
+        Ok(Created::New(link) | Created::Existing(link)) => {
+            state.short_url(link.domain_id, &link.short_code)
+        }
+        // Anything that isn't a command is taken for a URL, so a message
+        // that isn't one gets the help too
+        Err(CreateError::Invalid(invalid)) if invalid.0.iter().any(|e| e.field == "url") => {
+            format!("{invalid}\n\n{HELP}")
+        }
+        Err(CreateError::LinkLimit(limit)) => {
+            format!("The Telegram link owner has reached their limit of {limit} links.")
+        }
+        Err(CreateError::Db(e)) => {
+            tracing::error!("Telegram: failed to create link: {:?}", e);
+            "Something went wrong. Please try again.".into()
+        }
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Click totals for one link.
+async fn stats(state: &AppState, code: &str) -> String {
+    let link = match db::find_link_by_code(&state.db, code).await {
+        Ok(Some(l)) => l,
+        Ok(None) => return format!("No link with code '{code}'."),
+        Err(e) => {
+            tracing::error!("Telegram: failed to look up '{}': {:?}", code, e);
+            return "Something went wrong. Please try again.".into();
+        }
+    };
+
+    match db::get_analytics(&state.db, link.id, db::TrafficFilter::default()).await {
+        Ok(Some(summary)) => format!(
+            "{}\n→ {}\nClicks: {}\nUnique visitors: {}",
+            state.short_url(link.domain_id, &link.short_code),
+            link.original_url,
+            summary.total_clicks,
+            summary.unique_visitors,
+        ),
+        Ok(None) => format!("No link with code '{code}'."),
+        Err(e) => {
+            tracing::error!("Telegram: failed to load stats for '{}': {:?}", code, e);
+            "Something went wrong. Please try again.".into()
+        }
+    }
+}
+
+// ── Bot API calls ──────────────────────────────────────────────────────────
+
+/// Errors are stringified without the request URL, which contains the token.
+async fn get_updates(
+    client: &reqwest::Client,
+    api: &str,
+    offset: i64,
+) -> Result<Vec<Update>, String> {
+    let resp: ApiResponse<Vec<Update>> = client
+        .get(format!("{api}/getUpdates"))
+        // Telegram holds the request open for up to POLL_TIMEOUT_SECS
+        .timeout(Duration::from_secs(POLL_TIMEOUT_SECS + 10))
+        .query(&[
+            ("offset", offset.to_string()),
+            ("timeout", POLL_TIMEOUT_SECS.to_string()),
+            ("allowed_updates", r#"["message"]"#.to_owned()),
+        ])
+        .send()
+        .await
+        .map_err(|e| e.without_url().to_string())?
+        .json()
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+
+    if !resp.ok {
+        return Err(resp.description.unwrap_or_else(|| "unknown error".into()));
+    }
+    Ok(resp.result.unwrap_or_default())
+}
+
+async fn send_message(
+    client: &reqwest::Client,
+    api: &str,
+    chat_id: i64,
+    text: &str,
+) -> Result<(), String> {
+    let resp: ApiResponse<serde_json::Value> = client
+        .post(format!("{api}/sendMessage"))
+        .json(&serde_json::json!({
+            "chat_id": chat_id,
+            "text": text,
+            "disable_web_page_preview": true,
+        }))
+        .send()
+        .await
+        .map_err(|e| e.without_url().to_string())?
+        .json()
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+
+    if !resp.ok {
+        return Err(resp.description.unwrap_or_else(|| "unknown error".into()));
+    }
+    Ok(())
+}
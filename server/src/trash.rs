@@ -0,0 +1,26 @@
+use crate::db;
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// Days a deleted link stays in the trash before it is purged for good.
+pub const RETENTION_DAYS: i64 = 30;
+
+/// How often the trash is checked for links past their retention.
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Permanently delete links that have been in the trash for longer than
+/// [`RETENTION_DAYS`], once at startup and then every hour. Spawned from
+/// `main`.
+pub async fn run(pool: SqlitePool) {
+    loop {
+        let cutoff = Utc::now().naive_utc() - Duration::days(RETENTION_DAYS);
+        match db::purge_deleted_links(&pool, cutoff).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Purged {} link(s) from the trash", n),
+            Err(e) => tracing::error!("Failed to purge the trash: {:?}", e),
+        }
+        tokio::time::sleep(PURGE_INTERVAL).await;
+    }
+}
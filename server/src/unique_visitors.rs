@@ -0,0 +1,86 @@
+use crate::db_rollups;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sqlx::SqlitePool;
+
+/// How often finished days are looked for.
+const SKETCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Clicks from before visitor hashes that are hashed per transaction.
+const HASH_BATCH: i64 = 1000;
+
+/// A keyed hash of the visitor's IP and User-Agent that is the same on
+/// every link, so funnels can follow a visitor from link to link and unique
+/// visitors be counted. Keyed with `JWT_SECRET` so it can't be reversed by
+/// hashing likely IPs.
+pub fn visitor_hash(secret: &str, ip: &str, user_agent: Option<&str>) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(ip.as_bytes());
+    mac.update(&[0]);
+    mac.update(user_agent.unwrap_or_default().as_bytes());
+    hex::encode(&mac.finalize().into_bytes()[..16])
+}
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Sketch finished days once at startup and then every hour. Spawned from
+/// `main` when `CLICK_RETENTION_DAYS` is 0; otherwise the retention job
+/// sketches days before pruning them.
+pub async fn run(pool: SqlitePool, secret: String) {
+    loop {
+        sketch_finished_days(&pool, &secret).await;
+        tokio::time::sleep(SKETCH_INTERVAL).await;
+    }
+}
+
+/// Hash clicks from before visitor hashes, then sketch the visitors of each
+/// finished UTC day's clicks, oldest first, so unique visitors of large
+/// links are estimated from the sketches.
+pub async fn sketch_finished_days(pool: &SqlitePool, secret: &str) {
+    hash_old_clicks(pool, secret).await;
+
+    let today = Utc::now().date_naive();
+    loop {
+        let day = match db_rollups::next_unsketched_day(pool, today).await {
+            Ok(Some(day)) => day,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::error!("Failed to find clicks to sketch: {:?}", e);
+                break;
+            }
+        };
+        match db_rollups::sketch_day(pool, day).await {
+            Ok(n) => tracing::debug!("Sketched visitors of {} link(s) on {}", n, day),
+            Err(e) => {
+                tracing::error!("Failed to sketch visitors on {}: {:?}", day, e);
+                break;
+            }
+        }
+    }
+}
+
+async fn hash_old_clicks(pool: &SqlitePool, secret: &str) {
+    loop {
+        let events = match db_rollups::unhashed_events(pool, HASH_BATCH).await {
+            Ok(events) if events.is_empty() => break,
+            Ok(events) => events,
+            Err(e) => {
+                tracing::error!("Failed to find clicks without visitor hashes: {:?}", e);
+                break;
+            }
+        };
+        let hashes: Vec<(i64, String)> = events
+            .iter()
+            .map(|(id, ip, user_agent)| (*id, visitor_hash(secret, ip, user_agent.as_deref())))
+            .collect();
+        match db_rollups::set_visitor_hashes(pool, &hashes).await {
+            Ok(()) => tracing::debug!("Hashed visitors of {} earlier click(s)", hashes.len()),
+            Err(e) => {
+                tracing::error!("Failed to store visitor hashes: {:?}", e);
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Characters left as-is in substituted values (RFC 3986 "unreserved").
+const VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Per-request values for the `{name}` placeholders a destination URL may use.
+pub struct Vars<'a> {
+    pub code: &'a str,
+    pub click_id: &'a str,
+    pub country: Option<&'a str>,
+    pub device: Option<&'a str>,
+    pub utm_source: Option<&'a str>,
+}
+
+impl<'a> Vars<'a> {
+    /// `None` for an unknown placeholder; `Some(None)` for a known one whose
+    /// value isn't available for this request.
+    fn get(&self, name: &str) -> Option<Option<&'a str>> {
+        match name {
+            "code" => Some(Some(self.code)),
+            "click_id" => Some(Some(self.click_id)),
+            "country" => Some(self.country),
+            "device" => Some(self.device),
+            "utm_source" => Some(self.utm_source),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `url` contains the placeholder `{name}`.
+pub fn uses(url: &str, name: &str) -> bool {
+    url.contains(&format!("{{{name}}}"))
+}
+
+/// Replace each placeholder in `url` with its percent-encoded value, or with
+/// nothing when the value is unknown. Braces that don't form a known
+/// placeholder are left alone.
+pub fn expand(url: &str, vars: &Vars) -> String {
+    let mut out = String::with_capacity(url.len());
+    let mut rest = url;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let placeholder = after
+            .find('}')
+            .and_then(|end| Some((end, vars.get(&after[..end])?)));
+        match placeholder {
+            Some((end, value)) => {
+                out.extend(utf8_percent_encode(value.unwrap_or_default(), VALUE));
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -0,0 +1,199 @@
+use crate::{config::AppConfig, destination, short_code};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Longest destination accepted, after normalizing. Browsers and proxies
+/// start refusing URLs not much longer than this.
+const MAX_URL_LEN: usize = 2048;
+pub const MAX_CODE_LEN: usize = 64;
+const MAX_TITLE_LEN: usize = 200;
+const MAX_DESCRIPTION_LEN: usize = 500;
+const MAX_NOTES_LEN: usize = 5000;
+
+/// Schemes refused by name rather than as "not http(s)", since a link using
+/// one is more likely an attack than a typo.
+const BLOCKED_SCHEMES: &[&str] = &["javascript", "vbscript", "data", "file", "blob"];
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Checks the fields of a link being created or edited, collecting every
+/// problem rather than stopping at the first. Each method returns the
+/// field's value as it should be stored, or `None` if it is empty or
+/// invalid; [`LinkInput::finish`] then says whether all were valid.
+pub struct LinkInput {
+    strip_tracking: bool,
+    unicode_codes: bool,
+    emoji_codes: bool,
+    public_shorten: bool,
+    errors: Vec<FieldError>,
+}
+
+/// What is wrong with one field.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FieldError {
+    /// The field as the request named it.
+    #[schema(example = "title")]
+    pub field: &'static str,
+    #[schema(example = "Title must be at most 200 characters.")]
+    pub message: String,
+}
+
+/// The fields of a link that failed validation.
+#[derive(Debug, Clone)]
+pub struct Invalid(pub Vec<FieldError>);
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl LinkInput {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            strip_tracking: config.strip_tracking_params,
+            unicode_codes: config.unicode_short_codes,
+            emoji_codes: config.emoji_short_codes,
+            public_shorten: config.public_shorten,
+            errors: Vec::new(),
+        }
+    }
+
+    /// A destination: an absolute http(s) URL, normalized (see
+    /// [`destination::normalize`]). Required.
+    pub fn url(&mut self, field: &'static str, value: &str) -> Option<String> {
+        let value = strip_controls(value, false);
+        let value = value.trim();
+        if value.is_empty() {
+            return self.fail(field, "URL must not be empty.".into());
+        }
+        if let Some(scheme) = scheme(value).filter(|s| BLOCKED_SCHEMES.contains(&s.as_str())) {
+            return self.fail(
+                field,
+                format!("{scheme}: URLs aren't allowed; only http:// and https:// links are."),
+            );
+        }
+        let Some(url) = destination::normalize(value, self.strip_tracking) else {
+            return self.fail(field, "URL must be a full http:// or https:// URL.".into());
+        };
+        if url.len() > MAX_URL_LEN {
+            return self.fail(
+                field,
+                format!("URL must be at most {MAX_URL_LEN} characters."),
+            );
+        }
+        Some(url)
+    }
+
+    /// A custom short code (see [`short_code::parse`]), other than a
+    /// reserved one. Optional.
+    pub fn code(&mut self, field: &'static str, value: Option<&str>) -> Option<String> {
+        let value = strip_controls(value?, false);
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        let Some(code) = short_code::parse(value, self.unicode_codes, self.emoji_codes) else {
+            let message = if self.emoji_codes {
+                "Code may only contain letters, numbers, and hyphens, or only emoji."
+            } else {
+                "Code may only contain letters, numbers, and hyphens."
+            };
+            return self.fail(field, message.into());
+        };
+        if code.chars().count() > MAX_CODE_LEN {
+            return self.fail(
+                field,
+                format!("Code must be at most {MAX_CODE_LEN} characters."),
+            );
+        }
+        if short_code::is_reserved(&code, self.public_shorten) {
+            return self.fail(
+                field,
+                format!("'{code}' is reserved for Linkly's own pages."),
+            );
+        }
+        Some(code)
+    }
+
+    /// A link's title, on one line. Optional.
+    pub fn title(&mut self, field: &'static str, value: Option<&str>) -> Option<String> {
+        self.text(field, value, "Title", MAX_TITLE_LEN, false)
+    }
+
+    /// A link's description, which may span lines. Optional.
+    pub fn description(&mut self, field: &'static str, value: Option<&str>) -> Option<String> {
+        self.text(field, value, "Description", MAX_DESCRIPTION_LEN, true)
+    }
+
+    /// A link's notes, which may span lines. Optional.
+    pub fn notes(&mut self, field: &'static str, value: Option<&str>) -> Option<String> {
+        self.text(field, value, "Notes", MAX_NOTES_LEN, true)
+    }
+
+    pub fn finish(self) -> Result<(), Invalid> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Invalid(self.errors))
+        }
+    }
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<_> = self.0.iter().map(|e| e.message.as_str()).collect();
+        f.write_str(&messages.join(" "))
+    }
+}
+
+impl std::error::Error for Invalid {}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+impl LinkInput {
+    fn text(
+        &mut self,
+        field: &'static str,
+        value: Option<&str>,
+        label: &str,
+        max: usize,
+        multiline: bool,
+    ) -> Option<String> {
+        let value = strip_controls(value?, multiline);
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        if value.chars().count() > max {
+            return self.fail(field, format!("{label} must be at most {max} characters."));
+        }
+        Some(value.to_owned())
+    }
+
+    fn fail(&mut self, field: &'static str, message: String) -> Option<String> {
+        self.errors.push(FieldError { field, message });
+        None
+    }
+}
+
+/// `value` without control characters or the invisible marks that reorder
+/// text (bidi overrides), which can make a title or URL display as
+/// something it isn't. Line breaks are kept if `multiline`, as `\n`.
+fn strip_controls(value: &str, multiline: bool) -> String {
+    value
+        .replace("\r\n", "\n")
+        .chars()
+        .filter(|&c| {
+            let bidi = matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}');
+            (multiline && c == '\n') || !(c.is_control() || bidi)
+        })
+        .collect()
+}
+
+/// The lower-cased scheme `value` starts with, if it has one.
+fn scheme(value: &str) -> Option<String> {
+    let (scheme, _) = value.split_once(':')?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme.to_ascii_lowercase())
+}
@@ -0,0 +1,114 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+/// Most variants a link may have.
+pub const MAX_VARIANTS: usize = 20;
+
+/// Largest weight a variant may have.
+const MAX_WEIGHT: u32 = 1000;
+
+// ── Types ──────────────────────────────────────────────────────────────────
+
+/// Destinations a link's visitors are split between, in proportion to their
+/// weights.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Variants {
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub weight: u32,
+    pub url: String,
+}
+
+// ── Public API ─────────────────────────────────────────────────────────────
+
+impl Variants {
+    /// Parse one variant per line, a weight and a URL, e.g.
+    /// `70 https://example.com/a`. Blank lines are skipped. URLs are taken
+    /// as written; the caller validates them.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut variants: Vec<Variant> = Vec::new();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let Some((weight, url)) = line.split_once(char::is_whitespace) else {
+                return Err(format!(
+                    "'{line}' needs a weight and a URL, e.g. '50 https://example.com/a'"
+                ));
+            };
+            let weight = weight
+                .parse::<u32>()
+                .ok()
+                .filter(|w| (1..=MAX_WEIGHT).contains(w))
+                .ok_or_else(|| format!("'{weight}' is not a weight from 1 to {MAX_WEIGHT}"))?;
+            let url = url.trim().to_owned();
+            if variants.iter().any(|v| v.url == url) {
+                return Err(format!("{url} is listed more than once"));
+            }
+            variants.push(Variant { weight, url });
+        }
+        if variants.len() == 1 {
+            return Err("List at least two variants to split visitors between".into());
+        }
+        if variants.len() > MAX_VARIANTS {
+            return Err(format!("A link may have at most {MAX_VARIANTS} variants"));
+        }
+        Ok(Self { variants })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// The variants as stored in `links.variants`.
+    pub fn to_text(&self) -> String {
+        let lines: Vec<_> = self
+            .variants
+            .iter()
+            .map(|v| format!("{} {}", v.weight, v.url))
+            .collect();
+        lines.join("\n")
+    }
+
+    /// A variant chosen by weight: at random, or with `sticky` from a hash
+    /// of the link and the visitor's IP and User-Agent, so the same visitor
+    /// always gets the same one.
+    pub fn pick(
+        &self,
+        sticky: bool,
+        link_id: i64,
+        ip: Option<&str>,
+        user_agent: Option<&str>,
+    ) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = if sticky {
+            visitor_hash(link_id, ip, user_agent) % total
+        } else {
+            rand::thread_rng().gen_range(0..total)
+        };
+        self.variants.iter().find(|v| {
+            let hit = roll < u64::from(v.weight);
+            roll = roll.saturating_sub(u64::from(v.weight));
+            hit
+        })
+    }
+}
+
+// ── Internals ──────────────────────────────────────────────────────────────
+
+/// A stable number for a visitor of `link_id`. The link is part of it, so a
+/// visitor's variants on different links don't line up.
+fn visitor_hash(link_id: i64, ip: Option<&str>, user_agent: Option<&str>) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(link_id.to_be_bytes());
+    hasher.update(ip.unwrap_or_default());
+    hasher.update([0]);
+    hasher.update(user_agent.unwrap_or_default());
+    let digest = hasher.finalize();
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
@@ -0,0 +1,25 @@
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+// ── Scheduler ──────────────────────────────────────────────────────────────
+
+/// Checkpoint the write-ahead log and truncate it to zero bytes every
+/// `interval`. SQLite's own checkpoints never shrink the `-wal` file, so
+/// without this it stays as large as the biggest burst of writes. Spawned
+/// from `main` unless `WAL_CHECKPOINT_MINUTES` is 0.
+///
+/// A checkpoint can't finish while another connection is reading, such as
+/// Litestream holding its lock; it is simply tried again next time.
+pub async fn run(pool: SqlitePool, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        match sqlx::query_as::<_, (i64, i64, i64)>("PRAGMA wal_checkpoint(TRUNCATE)")
+            .fetch_one(&pool)
+            .await
+        {
+            Ok((0, pages, _)) => tracing::debug!("Checkpointed {} WAL page(s)", pages),
+            Ok(_) => tracing::debug!("WAL checkpoint deferred; the database is busy"),
+            Err(e) => tracing::error!("Failed to checkpoint the WAL: {:?}", e),
+        }
+    }
+}